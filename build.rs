@@ -26,9 +26,20 @@ fn try_gcc(lib: &str, msg: &str) {
 }
 
 fn main() {
-    try_gcc("-lblas", "BLAS not found. On Ubuntu try 'sudo apt-get install libblas3' before continuing.");
-    try_gcc("-lopencv_highgui", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-highgui-dev' before continuing.");
-    try_gcc("-lopencv_core", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-core2.4' before continuing.");
-    try_gcc("-lopencv_imgproc", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-imgproc2.4' before continuing.");
+    // BLAS and OpenCV are not required if everything is implemented in Rust
+    // or if only the no_std inference is compiled
+    if env::var("CARGO_FEATURE_STD").is_ok() && env::var("CARGO_FEATURE_PURE_RUST").is_err() {
+        try_gcc("-lblas", "BLAS not found. On Ubuntu try 'sudo apt-get install libblas3' before continuing.");
+        try_gcc("-lopencv_highgui", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-highgui-dev' before continuing.");
+        try_gcc("-lopencv_core", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-core2.4' before continuing.");
+        try_gcc("-lopencv_imgproc", "OpenCV not found. On Ubuntu try 'sudo apt-get install libopencv-imgproc2.4' before continuing.");
+    }
+    if env::var("CARGO_FEATURE_HDF5").is_ok() {
+        try_gcc("-lhdf5", "HDF5 not found. On Ubuntu try 'sudo apt-get install libhdf5-dev' before continuing.");
+    }
+    if env::var("CARGO_FEATURE_CUDA").is_ok() {
+        try_gcc("-lcudart", "CUDA runtime not found. Please install the CUDA toolkit before continuing.");
+        try_gcc("-lcublas", "cuBLAS not found. Please install the CUDA toolkit before continuing.");
+    }
 }
 
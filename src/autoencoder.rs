@@ -0,0 +1,274 @@
+//! Autoencoders with optionally tied weights.
+//!
+//! An [Autoencoder](struct.Autoencoder.html) is a
+//! [NeuralNetwork](../nn/struct.NeuralNetwork.html) with one hidden layer
+//! which is trained to reconstruct its input. The hidden layer (the code)
+//! is usually smaller than the input so that the network learns a
+//! compressed representation of the data.
+//!
+//! If the weights are tied, the weights of the decoder (hidden to output
+//! layer) are the transpose of the weights of the encoder (input to hidden
+//! layer) and only the bias of the decoder has its own parameters. This
+//! halves the number of parameters for the same number of hidden units.
+//! The gradient of the shared weights is the sum of the gradients of both
+//! uses.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::autoencoder::Autoencoder;
+//! use rustml::opt::empty_opts;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     0.9, 0.1, 0.9, 0.1;
+//!     0.1, 0.9, 0.1, 0.9;
+//!     0.9, 0.1, 0.9, 0.1;
+//!     0.1, 0.9, 0.1, 0.9
+//! ];
+//!
+//! let a = Autoencoder::new(4, 2).tied(true);
+//! assert_eq!(a.n_params(), 12);
+//! let b = a.gd(&x, empty_opts().alpha(10.0).iter(500));
+//! assert!(b.is_tied());
+//! assert!(b.reconstruction_error(&x) < a.reconstruction_error(&x));
+//! assert_eq!(b.encode(&x).cols(), 2);
+//! # }
+//! ```
+extern crate rand;
+
+use self::rand::Rng;
+
+use matrix::Matrix;
+use ops::Functions;
+use ops_inplace::{MatrixMatrixOpsInPlace, MatrixScalarOpsInPlace};
+use nn::{NeuralNetwork, MeanSquareError};
+use opt::OptParams;
+use progress::{Progress, ProgressInfo, NoProgress, report, wants_loss};
+use rng;
+
+/// Autoencoder with one hidden layer and sigmoid units.
+#[derive(Clone, Debug)]
+pub struct Autoencoder {
+    network: NeuralNetwork,
+    tied: bool
+}
+
+impl Autoencoder {
+
+    /// Creates an autoencoder with `inputs` input and output units and
+    /// `hidden` hidden units with untied random weights created with
+    /// [rng::rng](../rng/fn.rng.html).
+    ///
+    /// Panics if `inputs` or `hidden` is zero.
+    pub fn new(inputs: usize, hidden: usize) -> Autoencoder {
+        Autoencoder::with_rng(inputs, hidden, &mut rng::rng())
+    }
+
+    /// Creates an autoencoder like [new](#method.new) but creates the random
+    /// weights with the given random number generator.
+    ///
+    /// Panics if `inputs` or `hidden` is zero.
+    pub fn with_rng<R: Rng>(inputs: usize, hidden: usize, rng: &mut R) -> Autoencoder {
+        Autoencoder {
+            network: NeuralNetwork::new()
+                .add_layer_with_rng(inputs, rng)
+                .add_layer_with_rng(hidden, rng)
+                .add_layer_with_rng(inputs, rng),
+            tied: false
+        }
+    }
+
+    /// Ties (or unties) the weights of the decoder to the transpose of the
+    /// weights of the encoder. When the weights are tied the weights of the
+    /// decoder are replaced by the transpose of the weights of the encoder.
+    pub fn tied(&self, tied: bool) -> Autoencoder {
+
+        let mut a = Autoencoder { tied: tied, .. self.clone() };
+        if tied {
+            let p = a.network.params();
+            let dec = with_weights(&p[1], &transpose(&p[0]));
+            a.network = a.network.set_params(1, dec);
+        }
+        a
+    }
+
+    /// Returns true if the weights are tied.
+    pub fn is_tied(&self) -> bool {
+        self.tied
+    }
+
+    /// Returns the number of free parameters, i.e. the weights of the encoder
+    /// and the bias of the decoder plus the weights of the decoder if they
+    /// are not tied.
+    pub fn n_params(&self) -> usize {
+
+        let p = self.network.params();
+        let (h, d) = (p[0].rows(), p[0].cols());
+        if self.tied { h * d + d } else { h * d + d * (h + 1) }
+    }
+
+    /// Returns the underlying network, e.g. to export it.
+    pub fn network(&self) -> &NeuralNetwork {
+        &self.network
+    }
+
+    /// Returns the activations of the hidden units (the codes) for each row
+    /// of `x`.
+    pub fn encode(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        let p = self.network.params();
+        NeuralNetwork::new()
+            .add_layer(p[0].cols())
+            .add_layer(p[0].rows())
+            .set_params(0, p[0].clone())
+            .predict(x)
+    }
+
+    /// Returns the reconstructions of the codes in the rows of `h`.
+    ///
+    /// Panics if the number of columns of `h` does not match the number of
+    /// hidden units.
+    pub fn decode(&self, h: &Matrix<f64>) -> Matrix<f64> {
+
+        let p = self.network.params();
+        let dec = &p[1];
+        assert!(h.cols() + 1 == dec.cols(), "Number of hidden units does not match.");
+        let v = h.row_iter()
+            .flat_map(|r| dec.row_iter().map(move |w| {
+                r.iter().zip(w[1..].iter()).fold(w[0], |acc, (a, b)| acc + a * b).sigmoid()
+            }))
+            .collect();
+        Matrix::from_vec(v, h.rows(), dec.rows())
+    }
+
+    /// Returns the reconstruction of each row of `x`.
+    pub fn reconstruct(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.network.predict(x)
+    }
+
+    /// Returns the reconstruction error, i.e. the sum of the squared
+    /// differences between the inputs and their reconstructions divided by
+    /// two times the number of rows.
+    pub fn reconstruction_error(&self, x: &Matrix<f64>) -> f64 {
+        self.network.mse(x, x)
+    }
+
+    /// Computes the derivatives of the
+    /// [reconstruction error](#method.reconstruction_error) with respect to
+    /// the parameters of the network (see
+    /// [derivatives](../nn/struct.NeuralNetwork.html#method.derivatives)).
+    ///
+    /// If the weights are tied the derivatives of the shared weights are
+    /// accumulated from the encoder and the decoder and returned for both
+    /// so that an update keeps the weights tied.
+    pub fn derivatives(&self, x: &Matrix<f64>) -> Vec<Matrix<f64>> {
+
+        let mut d = self.network.derivatives(x, x);
+        if self.tied {
+            let mut shared = transpose(&strip_bias(&d[1]));
+            shared.iadd(&d[0]);
+            d[1] = with_weights(&d[1], &transpose(&shared));
+            d[0] = shared;
+        }
+        d
+    }
+
+    /// Trains the autoencoder on the rows of `x` via gradient descent with
+    /// the learning rate and the number of iterations of `p`.
+    pub fn gd(&self, x: &Matrix<f64>, p: OptParams<f64>) -> Autoencoder {
+        self.gd_with_progress(x, p, &mut NoProgress)
+    }
+
+    /// Like [gd](#method.gd) but reports the reconstruction error after each
+    /// iteration (see the module [progress](../progress/index.html)).
+    pub fn gd_with_progress<P: Progress>(&self, x: &Matrix<f64>, p: OptParams<f64>, progress: &mut P) -> Autoencoder {
+
+        let alpha = p.alpha.unwrap();
+        let iter = p.iter.unwrap();
+        let mut a = self.clone();
+        for k in 0..iter {
+            let mut d = a.derivatives(x);
+            for m in &mut d {
+                m.imul_scalar(-alpha);
+            }
+            a.network.update_params(&d);
+            let loss = if wants_loss(progress) { Some(a.reconstruction_error(x)) } else { None };
+            report(progress, &ProgressInfo { algorithm: "autoencoder", iteration: k + 1, max_iter: iter, loss: loss });
+        }
+        a
+    }
+}
+
+fn transpose(m: &Matrix<f64>) -> Matrix<f64> {
+    Matrix::from_vec((0..m.cols()).flat_map(|c| m.col(c).unwrap()).collect(), m.cols(), m.rows())
+}
+
+// removes the first column which contains the weights of the bias unit
+fn strip_bias(m: &Matrix<f64>) -> Matrix<f64> {
+    Matrix::from_vec(m.row_iter().flat_map(|r| r[1..].to_vec()).collect(), m.rows(), m.cols() - 1)
+}
+
+// replaces all columns except the bias column of `m` by `w`
+fn with_weights(m: &Matrix<f64>, w: &Matrix<f64>) -> Matrix<f64> {
+    Matrix::from_vec(m.row_iter().zip(w.row_iter()).flat_map(|(a, b)| Some(a[0]).into_iter().chain(b.iter().cloned())).collect(), m.rows(), m.cols())
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use opt::empty_opts;
+
+    #[test]
+    fn test_tied_derivatives() {
+
+        let a = Autoencoder::with_rng(3, 2, &mut rng::seeded_rng([1, 2, 3, 4])).tied(true);
+        assert_eq!(a.n_params(), 9);
+        assert_eq!(a.tied(false).n_params(), 15);
+        let x = Matrix::from_vec(vec![0.2, 0.7, 0.4, 0.9, 0.1, 0.5], 2, 3);
+        let p = a.network().params();
+        assert_eq!(strip_bias(&p[1]), transpose(&p[0]));
+
+        // numerical derivatives of the shared weights change both layers
+        let d = a.derivatives(&x);
+        let h = 1e-6;
+        for r in 0..2 {
+            for c in 0..3 {
+                let error = |delta: f64| {
+                    let mut w = p[0].clone();
+                    *w.get_mut(r, c).unwrap() += delta;
+                    Autoencoder { network: a.network.set_params(0, w), tied: false }.tied(true).reconstruction_error(&x)
+                };
+                let n = (error(h) - error(-h)) / (2.0 * h);
+                assert!((n - d[0].get(r, c).unwrap()).abs() < 1e-8);
+                assert_eq!(d[0].get(r, c), d[1].get(c, r + 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_gd() {
+
+        let mut r = rng::seeded_rng([5, 6, 7, 8]);
+        let x = Matrix::from_it((0..20).flat_map(|i| {
+            let v = if i % 2 == 0 { 0.9 } else { 0.1 };
+            vec![v, 1.0 - v, v, 1.0 - v, v].into_iter().map(|e| e + 0.05 * r.gen::<f64>()).collect::<Vec<f64>>()
+        }), 5);
+
+        for &tied in &[false, true] {
+            let a = Autoencoder::with_rng(5, 2, &mut rng::seeded_rng([1, 2, 3, 4])).tied(tied);
+            let mut errors = vec![];
+            let b = a.gd_with_progress(&x, empty_opts().alpha(10.0).iter(300), &mut |i: &ProgressInfo| errors.push(i.loss.unwrap()));
+            assert_eq!(errors.len(), 300);
+            assert!(errors[299] < 0.2 * a.reconstruction_error(&x));
+            assert!(b.decode(&b.encode(&x)).similar(&b.reconstruct(&x), 1e-12));
+            let p = b.network().params();
+            assert_eq!(strip_bias(&p[1]) == transpose(&p[0]), tied);
+        }
+    }
+}
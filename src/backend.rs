@@ -0,0 +1,405 @@
+//! Selection of the implementations of matrix multiplications and of vector
+//! kernels.
+//!
+//! By default the matrix multiplications of [d_gemm](../ops_inplace/fn.d_gemm.html)
+//! and [s_gemm](../ops_inplace/fn.s_gemm.html) (and hence of all operations
+//! which are based on them, like `Matrix::mul`) are computed with the
+//! [BLAS](../blas/index.html) library which is installed on the system. If
+//! rustml is compiled with the feature `matrixmultiply` the crate
+//! [matrixmultiply](https://crates.io/crates/matrixmultiply) can be used
+//! instead. It is implemented in pure Rust and much faster than the fallback
+//! of the feature `pure-rust`, i.e. it is a good choice if no BLAS library
+//! can be installed.
+//!
+//! The backend is selected at runtime with [set_backend](fn.set_backend.html).
+//! If both features `pure-rust` and `matrixmultiply` are enabled
+//! `matrixmultiply` is the default.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::backend::*;
+//!
+//! assert!(is_available(Backend::Blas));
+//! if is_available(Backend::MatrixMultiply) {
+//!     set_backend(Backend::MatrixMultiply).unwrap();
+//!     assert_eq!(backend(), Backend::MatrixMultiply);
+//!     set_backend(Backend::Blas).unwrap();
+//! }
+//! ```
+//!
+//! # Vector kernels
+//!
+//! The reductions `sub_square_sum` and `mul_sum` of
+//! [FusedOps](../ops_inplace/trait.FusedOps.html) for `f64` are computed with
+//! a [Kernel](enum.Kernel.html) which is selected at runtime from the
+//! [features](fn.cpu_features.html) of the CPU, i.e. with AVX instructions
+//! if they are supported and with plain loops otherwise. Large vectors are
+//! processed by several threads.
+//!
+//! The results are deterministic: the vectors are split into blocks of
+//! fixed size whose partial results are summed up in the same order
+//! independent of the number of threads. Different kernels may round
+//! differently, so the kernel can be overridden with
+//! [set_kernel](fn.set_kernel.html) and the current configuration can be
+//! queried with [current_backend](fn.current_backend.html), e.g. to include
+//! it into a report of an experiment.
+//!
+//! ```
+//! use rustml::backend::*;
+//!
+//! let info = current_backend();
+//! assert_eq!(info.kernel, kernel());
+//! assert!(is_kernel_available(Kernel::Scalar));
+//! assert!(info.threads >= 1);
+//! println!("{}", info);
+//! ```
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use error::RustmlError;
+
+/// Implementations of matrix multiplications.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The functions of the module [blas](../blas/index.html).
+    Blas,
+    /// The crate `matrixmultiply` (requires the feature `matrixmultiply`).
+    MatrixMultiply
+}
+
+const UNSET: usize = 0;
+const BLAS: usize = 1;
+const MATRIXMULTIPLY: usize = 2;
+
+static BACKEND: AtomicUsize = AtomicUsize::new(UNSET);
+
+fn default_backend() -> Backend {
+    if cfg!(all(feature = "pure-rust", feature = "matrixmultiply")) {
+        Backend::MatrixMultiply
+    } else {
+        Backend::Blas
+    }
+}
+
+/// Returns `true` if the backend has been compiled into rustml.
+pub fn is_available(b: Backend) -> bool {
+    match b {
+        Backend::Blas => true,
+        Backend::MatrixMultiply => cfg!(feature = "matrixmultiply")
+    }
+}
+
+/// Returns the backend which is currently used for matrix multiplications.
+pub fn backend() -> Backend {
+    match BACKEND.load(Ordering::Relaxed) {
+        BLAS => Backend::Blas,
+        MATRIXMULTIPLY => Backend::MatrixMultiply,
+        _ => default_backend()
+    }
+}
+
+/// Selects the backend for all subsequent matrix multiplications of all
+/// threads.
+///
+/// Returns an error if the backend is not [available](fn.is_available.html).
+pub fn set_backend(b: Backend) -> Result<(), RustmlError> {
+
+    if !is_available(b) {
+        return Err(RustmlError::InvalidArgument(
+            format!("Backend {:?} is not available. Enable the corresponding feature.", b)));
+    }
+    BACKEND.store(match b { Backend::Blas => BLAS, Backend::MatrixMultiply => MATRIXMULTIPLY }, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Implementations of the vector kernels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Plain loops which are available on all CPUs.
+    Scalar,
+    /// AVX instructions with four lanes (requires a x86_64 CPU with AVX).
+    Avx
+}
+
+/// Features of the CPU which are relevant for the selection of the kernels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuFeatures {
+    /// The CPU supports AVX instructions.
+    pub avx: bool,
+    /// Number of threads which can run in parallel.
+    pub cores: usize
+}
+
+/// Configuration of the numeric computations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackendInfo {
+    /// Implementation of matrix multiplications.
+    pub backend: Backend,
+    /// Implementation of the vector kernels.
+    pub kernel: Kernel,
+    /// Maximum number of threads of the vector kernels.
+    pub threads: usize,
+    /// Detected features of the CPU.
+    pub features: CpuFeatures
+}
+
+impl fmt::Display for BackendInfo {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "backend {:?}, kernel {:?}, threads {}, avx {}, cores {}",
+            self.backend, self.kernel, self.threads, self.features.avx, self.features.cores)
+    }
+}
+
+const SCALAR: usize = 1;
+const AVX: usize = 2;
+
+static KERNEL: AtomicUsize = AtomicUsize::new(UNSET);
+static THREADS: AtomicUsize = AtomicUsize::new(UNSET);
+static CORES: AtomicUsize = AtomicUsize::new(UNSET);
+
+// number of elements whose partial result is computed at once
+const BLOCK: usize = 4096;
+
+// minimum number of elements which are processed by several threads
+const PARALLEL_MIN: usize = 1 << 18;
+
+/// Detects the features of the CPU.
+pub fn cpu_features() -> CpuFeatures {
+    CpuFeatures {
+        avx: has_avx(),
+        cores: cores()
+    }
+}
+
+// the number of cores is determined only once because this requires a
+// system call (on Linux it even reads the cgroup files)
+fn cores() -> usize {
+    match CORES.load(Ordering::Relaxed) {
+        UNSET => {
+            let n = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+            CORES.store(n, Ordering::Relaxed);
+            n
+        },
+        n => n
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx() -> bool {
+    is_x86_feature_detected!("avx")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn has_avx() -> bool {
+    false
+}
+
+/// Returns `true` if the kernel is supported by the CPU.
+pub fn is_kernel_available(k: Kernel) -> bool {
+    match k {
+        Kernel::Scalar => true,
+        Kernel::Avx => has_avx()
+    }
+}
+
+/// Returns the kernel which is currently used, i.e. the kernel which has
+/// been selected with [set_kernel](fn.set_kernel.html) or the fastest
+/// kernel which is supported by the CPU.
+pub fn kernel() -> Kernel {
+    match KERNEL.load(Ordering::Relaxed) {
+        SCALAR => Kernel::Scalar,
+        AVX => Kernel::Avx,
+        _ => if has_avx() { Kernel::Avx } else { Kernel::Scalar }
+    }
+}
+
+/// Selects the vector kernel for all subsequent computations of all
+/// threads.
+///
+/// Returns an error if the kernel is not
+/// [supported](fn.is_kernel_available.html) by the CPU.
+pub fn set_kernel(k: Kernel) -> Result<(), RustmlError> {
+
+    if !is_kernel_available(k) {
+        return Err(RustmlError::InvalidArgument(format!("Kernel {:?} is not supported by the CPU.", k)));
+    }
+    KERNEL.store(match k { Kernel::Scalar => SCALAR, Kernel::Avx => AVX }, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the maximum number of threads of the vector kernels, i.e. the
+/// number set with [set_threads](fn.set_threads.html) or the number of
+/// cores.
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        UNSET => cores(),
+        n => n
+    }
+}
+
+/// Sets the maximum number of threads of the vector kernels. The results
+/// do not depend on the number of threads.
+///
+/// Returns an error if `n` is zero.
+pub fn set_threads(n: usize) -> Result<(), RustmlError> {
+
+    if n == 0 {
+        return Err(RustmlError::InvalidArgument("Number of threads must not be zero.".to_string()));
+    }
+    THREADS.store(n, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the current configuration of the matrix multiplications and the
+/// vector kernels.
+pub fn current_backend() -> BackendInfo {
+    BackendInfo {
+        backend: backend(),
+        kernel: kernel(),
+        threads: threads(),
+        features: cpu_features()
+    }
+}
+
+/// Computes the dot product of `x` and `y` with the current kernel.
+///
+/// Panics if the lengths of the vectors differ.
+pub fn dot(x: &[f64], y: &[f64]) -> f64 {
+
+    assert!(x.len() == y.len(), "Dimensions do not match.");
+    reduce(x, y, kernel(), threads(), false)
+}
+
+/// Computes the squared euclidean distance of `x` and `y` with the current
+/// kernel.
+///
+/// Panics if the lengths of the vectors differ.
+pub fn squared_distance(x: &[f64], y: &[f64]) -> f64 {
+
+    assert!(x.len() == y.len(), "Dimensions do not match.");
+    reduce(x, y, kernel(), threads(), true)
+}
+
+// Sums up the partial results of the blocks in order. If `diff` is true the
+// squared differences are summed up, otherwise the products.
+fn reduce(x: &[f64], y: &[f64], k: Kernel, threads: usize, diff: bool) -> f64 {
+
+    let block = |(a, b): (&[f64], &[f64])| match k {
+        Kernel::Scalar => block_scalar(a, b, diff),
+        Kernel::Avx => block_avx(a, b, diff)
+    };
+
+    let n = (x.len() + BLOCK - 1) / BLOCK;
+    if threads < 2 || x.len() < PARALLEL_MIN {
+        return x.chunks(BLOCK).zip(y.chunks(BLOCK)).map(block).fold(0.0, |acc, v| acc + v);
+    }
+
+    // each thread computes the partial results of consecutive blocks
+    let per_thread = (n + threads - 1) / threads * BLOCK;
+    let partials: Vec<Vec<f64>> = thread::scope(|s| {
+        let handles: Vec<_> = x.chunks(per_thread).zip(y.chunks(per_thread))
+            .map(|(a, b)| s.spawn(move || a.chunks(BLOCK).zip(b.chunks(BLOCK)).map(block).collect::<Vec<f64>>()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    partials.iter().flat_map(|p| p.iter()).fold(0.0, |acc, v| acc + v)
+}
+
+fn block_scalar(x: &[f64], y: &[f64], diff: bool) -> f64 {
+    if diff {
+        x.iter().zip(y.iter()).fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b))
+    } else {
+        x.iter().zip(y.iter()).fold(0.0, |acc, (a, b)| acc + a * b)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn block_avx(x: &[f64], y: &[f64], diff: bool) -> f64 {
+    assert!(has_avx(), "AVX is not supported by the CPU.");
+    unsafe { block_avx_impl(x, y, diff) }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn block_avx(_x: &[f64], _y: &[f64], _diff: bool) -> f64 {
+    unreachable!("AVX is not supported by the CPU.")
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn block_avx_impl(x: &[f64], y: &[f64], diff: bool) -> f64 {
+
+    use std::arch::x86_64::*;
+
+    let n = x.len() / 4 * 4;
+    let mut acc = _mm256_setzero_pd();
+    for i in (0..n).step_by(4) {
+        let a = _mm256_loadu_pd(x.as_ptr().add(i));
+        let b = _mm256_loadu_pd(y.as_ptr().add(i));
+        let v = if diff { _mm256_sub_pd(a, b) } else { a };
+        let w = if diff { v } else { b };
+        acc = _mm256_add_pd(acc, _mm256_mul_pd(v, w));
+    }
+    let mut lanes = [0.0; 4];
+    _mm256_storeu_pd(lanes.as_mut_ptr(), acc);
+    let r = (lanes[0] + lanes[1]) + (lanes[2] + lanes[3]);
+    r + block_scalar(&x[n..], &y[n..], diff)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend() {
+
+        assert!(is_available(Backend::Blas));
+        assert_eq!(is_available(Backend::MatrixMultiply), cfg!(feature = "matrixmultiply"));
+        assert!(set_backend(Backend::Blas).is_ok());
+        assert_eq!(backend(), Backend::Blas);
+        if !cfg!(feature = "matrixmultiply") {
+            assert!(set_backend(Backend::MatrixMultiply).is_err());
+            assert_eq!(backend(), Backend::Blas);
+        }
+    }
+
+    #[test]
+    fn test_kernels() {
+
+        let x: Vec<f64> = (0..PARALLEL_MIN + 1001).map(|i| ((i * 7919) % 1000) as f64 / 997.0).collect();
+        let y: Vec<f64> = (0..x.len()).map(|i| ((i * 104729) % 1000) as f64 / 991.0 - 0.5).collect();
+
+        let mut kernels = vec![Kernel::Scalar];
+        if is_kernel_available(Kernel::Avx) {
+            kernels.push(Kernel::Avx);
+        }
+        for &k in &kernels {
+            for &diff in &[false, true] {
+                // independent of the number of threads
+                let r = reduce(&x, &y, k, 1, diff);
+                assert_eq!(reduce(&x, &y, k, 3, diff), r);
+                assert_eq!(reduce(&x, &y, k, 8, diff), r);
+                assert!((r - reduce(&x, &y, Kernel::Scalar, 1, diff)).abs() < 1e-9 * r.abs());
+                // short vectors and remainders
+                assert!((reduce(&x[..7], &y[..7], k, 4, diff) - block_scalar(&x[..7], &y[..7], diff)).abs() < 1e-12);
+            }
+        }
+        assert_eq!(reduce(&[], &[], kernel(), 2, false), 0.0);
+        assert_eq!(dot(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), 32.0);
+        assert_eq!(squared_distance(&[1.0, 2.0], &[3.0, 5.0]), 13.0);
+
+        assert_eq!(is_kernel_available(Kernel::Avx), cpu_features().avx);
+        if !is_kernel_available(Kernel::Avx) {
+            assert!(set_kernel(Kernel::Avx).is_err());
+        }
+        assert!(set_kernel(kernel()).is_ok());
+        assert!(set_threads(0).is_err());
+        let info = current_backend();
+        assert_eq!(info.threads, threads());
+        assert!(format!("{}", info).contains("kernel"));
+    }
+}
@@ -0,0 +1,249 @@
+//! Timing utilities and reference micro-benchmarks.
+//!
+//! [time_it](fn.time_it.html) measures a single execution of a closure and
+//! [bench](fn.bench.html) measures repeated executions (after some warm-up
+//! runs) and returns statistics about the run times. The functions with the
+//! prefix `bench_` are reference benchmarks of the most performance critical
+//! operations of this library (matrix multiplication via BLAS, prediction and
+//! backpropagation of neural networks and the search for nearest
+//! neighbours). They can be used to compare different BLAS libraries or to
+//! detect performance regressions, e.g. by running
+//! [reference_suite](fn.reference_suite.html) with a release build.
+//!
+//! The inputs of the reference benchmarks are created with a fixed seed so
+//! that the measurements of different runs are comparable.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::bench::*;
+//!
+//! let r = bench("sum", 1, 5, || (0..1000).sum::<u64>());
+//! assert_eq!(r.runs(), 5);
+//! assert!(r.min() <= r.median() && r.median() <= r.max());
+//! println!("{}", r);
+//! ```
+use std::fmt;
+use std::hint::black_box;
+use std::time::Instant;
+
+use matrix::Matrix;
+use ops::MatrixMatrixOps;
+use nn::NeuralNetwork;
+use knn::scan;
+use distance::Metric;
+use rng::seeded_rng;
+
+const SEED: [u32; 4] = [0x5eed, 0xbe4c, 0x4a11, 0x0001];
+
+/// Executes `f` once and returns its result together with the elapsed time
+/// in seconds.
+pub fn time_it<T, F: FnOnce() -> T>(f: F) -> (T, f64) {
+
+    let start = Instant::now();
+    let r = f();
+    (r, start.elapsed().as_secs_f64())
+}
+
+/// Run times of repeated executions of a benchmark.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchResult {
+    name: String,
+    times: Vec<f64>
+}
+
+impl BenchResult {
+
+    /// Creates a result from the run times in seconds.
+    ///
+    /// Panics if `times` is empty.
+    pub fn new(name: &str, times: Vec<f64>) -> BenchResult {
+
+        assert!(!times.is_empty(), "At least one run is required.");
+        BenchResult {
+            name: name.to_string(),
+            times: times
+        }
+    }
+
+    /// Returns the name of the benchmark.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the run times in seconds in the order of their execution.
+    pub fn times(&self) -> &[f64] {
+        &self.times
+    }
+
+    /// Returns the number of runs.
+    pub fn runs(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Returns the shortest run time in seconds.
+    pub fn min(&self) -> f64 {
+        self.times.iter().cloned().fold(::std::f64::INFINITY, f64::min)
+    }
+
+    /// Returns the longest run time in seconds.
+    pub fn max(&self) -> f64 {
+        self.times.iter().cloned().fold(0.0, f64::max)
+    }
+
+    /// Returns the mean run time in seconds.
+    pub fn mean(&self) -> f64 {
+        self.times.iter().sum::<f64>() / self.runs() as f64
+    }
+
+    /// Returns the median run time in seconds.
+    pub fn median(&self) -> f64 {
+
+        let mut v = self.times.clone();
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = v.len();
+        if n % 2 == 1 { v[n / 2] } else { (v[n / 2 - 1] + v[n / 2]) / 2.0 }
+    }
+
+    /// Returns the sample standard deviation of the run times in seconds. If
+    /// there is only one run the standard deviation is zero.
+    pub fn std_dev(&self) -> f64 {
+
+        let n = self.runs();
+        if n < 2 {
+            return 0.0;
+        }
+        let m = self.mean();
+        (self.times.iter().map(|t| (t - m) * (t - m)).sum::<f64>() / (n - 1) as f64).sqrt()
+    }
+}
+
+impl fmt::Display for BenchResult {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} runs, median {:.6}s, mean {:.6}s +- {:.6}s, min {:.6}s, max {:.6}s",
+            self.name, self.runs(), self.median(), self.mean(), self.std_dev(), self.min(), self.max())
+    }
+}
+
+/// Executes `f` `warmup` times without measuring and then `runs` times with
+/// measuring the run time of each execution.
+///
+/// The results of `f` are passed to `std::hint::black_box` so that the
+/// compiler cannot remove the computation.
+///
+/// Panics if `runs` is zero.
+pub fn bench<T, F: FnMut() -> T>(name: &str, warmup: usize, runs: usize, mut f: F) -> BenchResult {
+
+    assert!(runs > 0, "At least one run is required.");
+    for _ in 0..warmup {
+        black_box(f());
+    }
+    let times = (0..runs).map(|_| time_it(|| black_box(f())).1).collect();
+    BenchResult::new(name, times)
+}
+
+fn random_matrix(rows: usize, cols: usize, seed: u32) -> Matrix<f64> {
+    let mut r = seeded_rng([SEED[0], SEED[1], SEED[2], seed]);
+    Matrix::<f64>::random_with_rng::<f64, _>(rows, cols, &mut r)
+}
+
+/// Benchmarks the multiplication of two random `n`x`n` matrices.
+pub fn bench_gemm(n: usize, runs: usize) -> BenchResult {
+
+    let a = random_matrix(n, n, 1);
+    let b = random_matrix(n, n, 2);
+    bench(&format!("gemm {}x{}", n, n), 1, runs, || a.mul(&b, false, false))
+}
+
+fn network(layers: &[usize]) -> NeuralNetwork {
+
+    let mut r = seeded_rng(SEED);
+    layers.iter().fold(NeuralNetwork::new(), |n, &l| n.add_layer_with_rng(l, &mut r))
+}
+
+/// Benchmarks the prediction of a neural network with the given layer sizes
+/// for `n` random examples.
+///
+/// Panics if fewer than two layers are given.
+pub fn bench_predict(layers: &[usize], n: usize, runs: usize) -> BenchResult {
+
+    assert!(layers.len() >= 2, "At least two layers are required.");
+    let net = network(layers);
+    let x = random_matrix(n, layers[0], 3);
+    bench(&format!("predict {:?} n={}", layers, n), 1, runs, || net.predict(&x))
+}
+
+/// Benchmarks the computation of the derivatives (backpropagation) of a
+/// neural network with the given layer sizes for `n` random examples.
+///
+/// Panics if fewer than two layers are given.
+pub fn bench_derivatives(layers: &[usize], n: usize, runs: usize) -> BenchResult {
+
+    assert!(layers.len() >= 2, "At least two layers are required.");
+    let net = network(layers);
+    let x = random_matrix(n, layers[0], 4);
+    let t = random_matrix(n, *layers.last().unwrap(), 5);
+    bench(&format!("derivatives {:?} n={}", layers, n), 1, runs, || net.derivatives(&x, &t))
+}
+
+/// Benchmarks the search of the `k` nearest neighbours of `queries` random
+/// examples in `n` random examples with `dim` features.
+pub fn bench_knn(n: usize, dim: usize, queries: usize, k: usize, runs: usize) -> BenchResult {
+
+    let m = random_matrix(n, dim, 6);
+    let q = random_matrix(queries, dim, 7);
+    bench(&format!("knn n={} dim={} k={}", n, dim, k), 1, runs, || {
+        q.row_iter()
+            .map(|e| scan(&m, e, k, |a, b| Metric::Euclidean.distance(a, b)).unwrap())
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Runs all reference benchmarks with medium sized inputs.
+pub fn reference_suite(runs: usize) -> Vec<BenchResult> {
+    vec![
+        bench_gemm(256, runs),
+        bench_predict(&[784, 100, 10], 1000, runs),
+        bench_derivatives(&[784, 100, 10], 100, runs),
+        bench_knn(5000, 20, 100, 5, runs)
+    ]
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_result() {
+
+        let r = BenchResult::new("x", vec![3.0, 1.0, 2.0, 6.0]);
+        assert_eq!(r.runs(), 4);
+        assert_eq!(r.min(), 1.0);
+        assert_eq!(r.max(), 6.0);
+        assert_eq!(r.mean(), 3.0);
+        assert_eq!(r.median(), 2.5);
+        assert!((r.std_dev() - (14.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(BenchResult::new("y", vec![2.0]).std_dev(), 0.0);
+        assert!(format!("{}", r).starts_with("x: 4 runs, median 2.500000s"));
+    }
+
+    #[test]
+    fn test_reference_benchmarks() {
+
+        let (v, t) = time_it(|| 42);
+        assert_eq!(v, 42);
+        assert!(t >= 0.0);
+
+        let mut calls = 0;
+        assert_eq!(bench("c", 2, 3, || calls += 1).runs(), 3);
+        assert_eq!(calls, 5);
+
+        assert_eq!(bench_gemm(8, 2).name(), "gemm 8x8");
+        assert_eq!(bench_predict(&[4, 3, 2], 5, 2).runs(), 2);
+        assert_eq!(bench_derivatives(&[4, 3, 2], 5, 2).runs(), 2);
+        assert_eq!(bench_knn(20, 3, 2, 3, 2).runs(), 2);
+    }
+}
@@ -37,9 +37,18 @@
 //! This module provides low level functions to access the BLAS functions. It is highly recommended
 //! to use the wrappers in the module [ops_inplace](../ops_inplace/index.html) which provide
 //! a more convenient and safer high level interface.
+//!
+//! # Pure Rust fallback
+//!
+//! If rustml is compiled with the feature `pure-rust` no BLAS library is required. Instead,
+//! the `cblas_*` functions of this module are implemented in Rust with the same signatures.
+//! They are considerably slower than an optimized BLAS library but allow to compile rustml
+//! for targets without a BLAS library. Only positive increments are
+//! supported by the fallback.
 //! 
 extern crate libc;
 
+#[cfg(not(feature = "pure-rust"))]
 use self::libc::{c_int, c_double, c_float};
 
 // documentation
@@ -68,6 +77,40 @@ pub enum Transpose {
     ConjTrans = 113
 }
 
+/// Enum to specify whether the upper or the lower triangle of a matrix
+/// is used. Required for the `cblas_*` functions.
+#[repr(C)]
+pub enum Uplo {
+    /// upper triangular matrix
+    Upper = 121,
+    /// lower triangular matrix
+    Lower = 122
+}
+
+/// Enum to specify whether a triangular matrix has ones on its diagonal.
+/// Required for the `cblas_*` functions.
+#[repr(C)]
+pub enum Diag {
+    /// The diagonal is read from the matrix.
+    NonUnit = 131,
+    /// The diagonal is assumed to be one and is not read.
+    Unit = 132
+}
+
+/// Enum to specify on which side a matrix is multiplied. Required for the
+/// `cblas_*` functions.
+#[repr(C)]
+pub enum Side {
+    /// The matrix is on the left side.
+    Left = 141,
+    /// The matrix is on the right side.
+    Right = 142
+}
+
+#[cfg(feature = "pure-rust")]
+pub use self::fallback::*;
+
+#[cfg(not(feature = "pure-rust"))]
 #[link(name = "blas")]
 extern {
     // TODO wrapper functions
@@ -136,6 +179,43 @@ extern {
         incy: c_int
     );
 
+    /// Solves `op(A) * x = b` for a triangular matrix `A` and stores the solution in `x`
+    /// which contains `b` on entry.
+    ///
+    /// The parameter `uplo` specifies whether `A` is an upper or a lower triangular matrix,
+    /// `op(A)` is the transpose of `A` if `transA` is set to [`Trans`](enum.Transpose.html).
+    /// If `diag` is set to [`Unit`](enum.Diag.html) the diagonal elements are assumed to be
+    /// one. The parameter `n` specifies the number of rows and columns of `A`.
+    ///
+    /// For a high level interface you should use [d_trsv](../ops_inplace/fn.d_trsv.html)
+    /// in the module [ops_inplace](../ops_inplace/index.html).
+    pub fn cblas_dtrsv(
+        order: Order, uplo: Uplo, transA: Transpose, diag: Diag,
+        n: c_int,
+        a: *const c_double, lda: c_int,
+        x: *mut c_double,
+        incx: c_int
+    );
+
+    /// Solves `op(A) * X = alpha * B` or `X * op(A) = alpha * B` for a triangular
+    /// matrix `A` and stores the solution in `B`.
+    ///
+    /// The parameter `side` specifies whether `op(A)` is on the left or on the right
+    /// side of `X`. The `m x n` matrix `B` is overwritten with the solution `X`. The
+    /// parameters `uplo`, `transA` and `diag` are the same as for
+    /// [cblas_dtrsv](fn.cblas_dtrsv.html).
+    ///
+    /// For a high level interface you should use [d_trsm](../ops_inplace/fn.d_trsm.html)
+    /// in the module [ops_inplace](../ops_inplace/index.html).
+    pub fn cblas_dtrsm(
+        order: Order, side: Side, uplo: Uplo, transA: Transpose, diag: Diag,
+        m: c_int,
+        n: c_int,
+        alpha: c_double,
+        A: *const c_double, lda: c_int,
+        B: *mut c_double, ldb: c_int
+    );
+
     /// Computes the L2 norm (euclidean norm) of a vector of elements of type f64 (doubles).
     ///
     /// The parameter `n` specifies the number of elements in the vector `x`. The parameter
@@ -220,3 +300,169 @@ extern {
     );
 }
 
+
+#[cfg(feature = "pure-rust")]
+#[allow(non_snake_case)]
+mod fallback {
+    use super::{Order, Transpose, Uplo, Diag, Side};
+    use super::libc::{c_int, c_double, c_float};
+
+    // Returns the element at row `i` and column `j` of a matrix with the
+    // leading dimension `ld`.
+    unsafe fn at<T: Copy>(order: &Order, a: *const T, ld: c_int, i: usize, j: usize) -> T {
+        match *order {
+            Order::RowMajor => *a.add(i * ld as usize + j),
+            Order::ColMajor => *a.add(i + j * ld as usize)
+        }
+    }
+
+    // Returns the element at row `i` and column `j` of `op(A)`.
+    unsafe fn at_op<T: Copy>(order: &Order, trans: &Transpose, a: *const T, ld: c_int, i: usize, j: usize) -> T {
+        match *trans {
+            Transpose::NoTrans => at(order, a, ld, i, j),
+            _ => at(order, a, ld, j, i)
+        }
+    }
+
+    macro_rules! impl_fallback {
+        ($t:ty, $axpy:ident, $gemm:ident, $gemv:ident, $nrm2:ident) => (
+
+            /// Computes `alpha * x + y` and stores the result in `y`.
+            pub unsafe fn $axpy(n: c_int, alpha: $t, x: *const $t, incx: c_int, y: *mut $t, incy: c_int) {
+                for i in 0..n as usize {
+                    *y.add(i * incy as usize) += alpha * *x.add(i * incx as usize);
+                }
+            }
+
+            /// Computes `alpha * op(A) * op(B) + beta * C` and stores the result in `C`.
+            pub unsafe fn $gemm(
+                order: Order, transA: Transpose, transB: Transpose,
+                m: c_int, n: c_int, k: c_int,
+                alpha: $t,
+                A: *const $t, lda: c_int,
+                B: *const $t, ldb: c_int,
+                beta: $t,
+                C: *mut $t, ldc: c_int) {
+
+                for i in 0..m as usize {
+                    for j in 0..n as usize {
+                        let mut s = 0.0;
+                        for p in 0..k as usize {
+                            s += at_op(&order, &transA, A, lda, i, p) * at_op(&order, &transB, B, ldb, p, j);
+                        }
+                        let c = match order {
+                            Order::RowMajor => C.add(i * ldc as usize + j),
+                            Order::ColMajor => C.add(i + j * ldc as usize)
+                        };
+                        // C is not read if beta is zero, i.e. it may contain NaNs
+                        *c = if beta == 0.0 { alpha * s } else { alpha * s + beta * *c };
+                    }
+                }
+            }
+
+            /// Computes `alpha * op(A) * x + beta * y` and stores the result in `y`.
+            pub unsafe fn $gemv(
+                order: Order, transA: Transpose,
+                m: c_int, n: c_int,
+                alpha: $t,
+                a: *const $t, lda: c_int,
+                x: *const $t, incx: c_int,
+                beta: $t,
+                y: *mut $t, incy: c_int) {
+
+                let (rows, cols) = match transA {
+                    Transpose::NoTrans => (m as usize, n as usize),
+                    _ => (n as usize, m as usize)
+                };
+                for i in 0..rows {
+                    let mut s = 0.0;
+                    for j in 0..cols {
+                        s += at_op(&order, &transA, a, lda, i, j) * *x.add(j * incx as usize);
+                    }
+                    let yi = y.add(i * incy as usize);
+                    *yi = if beta == 0.0 { alpha * s } else { alpha * s + beta * *yi };
+                }
+            }
+
+            /// Computes the L2 norm (euclidean norm) of a vector.
+            pub unsafe fn $nrm2(n: c_int, x: *const $t, incx: c_int) -> $t {
+                let mut s = 0.0;
+                for i in 0..n as usize {
+                    let v = *x.add(i * incx as usize);
+                    s += v * v;
+                }
+                s.sqrt()
+            }
+        )
+    }
+
+    impl_fallback!(c_double, cblas_daxpy, cblas_dgemm, cblas_dgemv, cblas_dnrm2);
+
+    // Solves `op(A) * x = b` in place where the elements of `x` are `incx`
+    // elements apart.
+    unsafe fn trsv(order: &Order, uplo: &Uplo, trans: bool, diag: &Diag, n: usize,
+                   a: *const c_double, lda: c_int, x: *mut c_double, incx: usize) {
+
+        let t = if trans { Transpose::Trans } else { Transpose::NoTrans };
+        // op(A) is a lower triangular matrix
+        let lower = match *uplo { Uplo::Lower => !trans, Uplo::Upper => trans };
+        for k in 0..n {
+            let i = if lower { k } else { n - 1 - k };
+            let (from, to) = if lower { (0, i) } else { (i + 1, n) };
+            let mut s = *x.add(i * incx);
+            for j in from..to {
+                s -= at_op(order, &t, a, lda, i, j) * *x.add(j * incx);
+            }
+            if let Diag::NonUnit = *diag {
+                s /= at_op(order, &t, a, lda, i, i);
+            }
+            *x.add(i * incx) = s;
+        }
+    }
+
+    /// Solves `op(A) * x = b` and stores the result in `x`.
+    pub unsafe fn cblas_dtrsv(
+        order: Order, uplo: Uplo, transA: Transpose, diag: Diag,
+        n: c_int,
+        a: *const c_double, lda: c_int,
+        x: *mut c_double, incx: c_int) {
+
+        let trans = match transA { Transpose::NoTrans => false, _ => true };
+        trsv(&order, &uplo, trans, &diag, n as usize, a, lda, x, incx as usize);
+    }
+
+    /// Solves `op(A) * X = alpha * B` or `X * op(A) = alpha * B` and stores the result in `B`.
+    pub unsafe fn cblas_dtrsm(
+        order: Order, side: Side, uplo: Uplo, transA: Transpose, diag: Diag,
+        m: c_int, n: c_int,
+        alpha: c_double,
+        A: *const c_double, lda: c_int,
+        B: *mut c_double, ldb: c_int) {
+
+        let (m, n, ldb) = (m as usize, n as usize, ldb as usize);
+        for i in 0..m * n {
+            let b = match order {
+                Order::RowMajor => B.add((i / n) * ldb + i % n),
+                Order::ColMajor => B.add((i % m) + (i / m) * ldb)
+            };
+            *b *= alpha;
+        }
+        let trans = match transA { Transpose::NoTrans => false, _ => true };
+        // (element stride, vector stride) of the columns and the rows of B
+        let (cols, rows) = match order {
+            Order::RowMajor => ((ldb, 1), (1, ldb)),
+            Order::ColMajor => ((1, ldb), (ldb, 1))
+        };
+        match side {
+            // each column of X is the solution of op(A) * x = b
+            Side::Left => for j in 0..n {
+                trsv(&order, &uplo, trans, &diag, m, A, lda, B.add(j * cols.1), cols.0);
+            },
+            // each row of X is the solution of op(A)^T * x = b
+            Side::Right => for i in 0..m {
+                trsv(&order, &uplo, !trans, &diag, n, A, lda, B.add(i * rows.1), rows.0);
+            }
+        }
+    }
+    impl_fallback!(c_float, cblas_saxpy, cblas_sgemm, cblas_sgemv, cblas_snrm2);
+}
@@ -0,0 +1,266 @@
+//! Periodic checkpoints of long training runs.
+//!
+//! A [Checkpointer](struct.Checkpointer.html) saves the model together
+//! with the state of the optimizer (a [TrainState](struct.TrainState.html))
+//! every `n` epochs or every `t` seconds. The file is written with
+//! [save_model](../io/model/fn.save_model.html) into a temporary file which
+//! then replaces the previous checkpoint, i.e. a crash while a checkpoint is
+//! written does not destroy the last checkpoint. After a crash the training
+//! is continued from the checkpoint which is loaded with
+//! [resume_from](fn.resume_from.html).
+//!
+//! Checkpoints are supported by the gradient descent of
+//! [neural networks](../nn/struct.NeuralNetwork.html#method.gd_with_checkpoints)
+//! and by the [out-of-core training](../stream/struct.StreamTrainer.html#method.fit_with_checkpoints).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::nn::NeuralNetwork;
+//! use rustml::opt::empty_opts;
+//! use rustml::progress::NoProgress;
+//! use rustml::checkpoint::*;
+//!
+//! # fn main() {
+//! let x = mat![0.0, 0.0; 0.0, 1.0; 1.0, 0.0; 1.0, 1.0];
+//! let y = mat![0.1; 0.9; 0.9; 0.1];
+//!
+//! let path = std::env::temp_dir().join("rustml_doc_nn.ckpt");
+//! let fname = path.to_str().unwrap();
+//! let mut c = Checkpointer::new(fname).every_epochs(10);
+//!
+//! // the training is interrupted after 25 of 50 iterations ...
+//! let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+//! n.gd_with_checkpoints(&x, &y, empty_opts().alpha(1.0).iter(25), &mut c, &mut NoProgress).unwrap();
+//!
+//! // ... and continued from the last checkpoint
+//! let ckpt: Checkpoint<NeuralNetwork> = resume_from(fname).unwrap();
+//! assert_eq!(ckpt.state.epoch, 20);
+//! let n = ckpt.resume_gd(&x, &y, empty_opts().alpha(1.0).iter(50), &mut c, &mut NoProgress).unwrap();
+//! assert_eq!(resume_from::<NeuralNetwork>(fname).unwrap().state.epoch, 50);
+//! # }
+//! ```
+use std::fs;
+use std::time::{Duration, Instant};
+
+use io::model::{Model, Encoder, Decoder, save_model, load_model};
+
+/// State of an optimizer which is saved together with the model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrainState {
+    /// Number of completed epochs (or iterations).
+    pub epoch: usize,
+    /// Loss after each completed epoch if it is computed by the optimizer
+    /// (e.g. not if the progress callback does not want it).
+    pub losses: Vec<f64>,
+    /// Slow weights of [Lookahead](../opt/struct.Lookahead.html) or empty
+    /// if it is not used.
+    pub slow: Vec<f64>,
+    /// Average of the parameters (see
+    /// [PolyakAverage](../opt/struct.PolyakAverage.html)) or empty if no
+    /// parameters have been averaged yet.
+    pub average: Vec<f64>
+}
+
+impl TrainState {
+
+    /// Creates the state of an optimizer which has not started yet.
+    pub fn new() -> TrainState {
+        TrainState::default()
+    }
+}
+
+/// A model together with the state of its optimizer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint<M> {
+    /// The model after the last completed epoch.
+    pub model: M,
+    /// The state of the optimizer.
+    pub state: TrainState
+}
+
+impl <M> Checkpoint<M> {
+
+    /// Creates a checkpoint of an untrained model, i.e. a training which
+    /// starts with this checkpoint starts at the first epoch.
+    pub fn new(model: M) -> Checkpoint<M> {
+        Checkpoint { model: model, state: TrainState::new() }
+    }
+}
+
+impl <M: Model> Model for Checkpoint<M> {
+
+    fn tag() -> &'static str { "rustml.checkpoint.Checkpoint" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.str(M::tag());
+        self.model.encode(e);
+        e.usize(self.state.epoch);
+        e.f64s(&self.state.losses);
+        e.f64s(&self.state.slow);
+        e.f64s(&self.state.average);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Checkpoint<M>, String> {
+
+        let tag = try!(d.str());
+        if tag != M::tag() {
+            return Err(format!("Expected a checkpoint of type {} but found {}.", M::tag(), tag));
+        }
+        let model = try!(M::decode(d));
+        let epoch = try!(d.usize());
+        let losses = try!(d.f64s());
+        let slow = try!(d.f64s());
+        let average = try!(d.f64s());
+        let state = TrainState { epoch: epoch, losses: losses, slow: slow, average: average };
+        Ok(Checkpoint { model: model, state: state })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Schedule {
+    Epochs(usize),
+    Interval(Duration)
+}
+
+/// Saves checkpoints of a training run into a file.
+///
+/// By default a checkpoint is saved after each epoch.
+#[derive(Clone, Debug)]
+pub struct Checkpointer {
+    path: String,
+    schedule: Schedule,
+    last: Instant,
+    saves: usize
+}
+
+impl Checkpointer {
+
+    /// Creates a checkpointer which saves the checkpoints into the file
+    /// `path`.
+    pub fn new(path: &str) -> Checkpointer {
+        Checkpointer {
+            path: path.to_string(),
+            schedule: Schedule::Epochs(1),
+            last: Instant::now(),
+            saves: 0
+        }
+    }
+
+    /// Saves a checkpoint after every `n` epochs.
+    ///
+    /// Panics if `n` is zero.
+    pub fn every_epochs(&self, n: usize) -> Checkpointer {
+
+        assert!(n > 0, "Number of epochs must be greater than zero.");
+        Checkpointer { schedule: Schedule::Epochs(n), .. self.clone() }
+    }
+
+    /// Saves a checkpoint after the first epoch which ends at least `secs`
+    /// seconds after the last checkpoint (or the creation of the
+    /// checkpointer).
+    pub fn every_secs(&self, secs: u64) -> Checkpointer {
+        Checkpointer { schedule: Schedule::Interval(Duration::from_secs(secs)), .. self.clone() }
+    }
+
+    /// Returns the path of the checkpoint file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the number of checkpoints which have been saved.
+    pub fn saves(&self) -> usize {
+        self.saves
+    }
+
+    /// Saves a checkpoint if one is due after the epoch `state.epoch`. This
+    /// is called by the optimizers after each epoch.
+    ///
+    /// Returns whether a checkpoint has been saved.
+    pub fn update<M: Model + Clone>(&mut self, model: &M, state: &TrainState) -> Result<bool, String> {
+
+        let due = match self.schedule {
+            Schedule::Epochs(n) => state.epoch % n == 0,
+            Schedule::Interval(d) => self.last.elapsed() >= d
+        };
+        if due {
+            try!(self.save(model, state));
+        }
+        Ok(due)
+    }
+
+    /// Saves a checkpoint.
+    pub fn save<M: Model + Clone>(&mut self, model: &M, state: &TrainState) -> Result<(), String> {
+
+        let tmp = format!("{}.tmp", self.path);
+        try!(save_model(&tmp, &Checkpoint { model: model.clone(), state: state.clone() }));
+        try!(fs::rename(&tmp, &self.path).map_err(|e| format!("Could not write checkpoint {}: {}", self.path, e)));
+        self.last = Instant::now();
+        self.saves += 1;
+        Ok(())
+    }
+}
+
+/// Loads a checkpoint which has been saved by a
+/// [Checkpointer](struct.Checkpointer.html).
+pub fn resume_from<M: Model>(path: &str) -> Result<Checkpoint<M>, String> {
+    load_model(path)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use regression::Hypothesis;
+    use stream::{StreamTrainer, MatrixChunks};
+
+    #[test]
+    fn test_checkpointer() {
+
+        let path = ::std::env::temp_dir().join("rustml_test_checkpoint.ckpt");
+        let fname = path.to_str().unwrap();
+
+        let mut c = Checkpointer::new(fname).every_epochs(3);
+        let h = Hypothesis::from_params(&[1.0, 2.0]);
+        let mut s = TrainState::new();
+        for i in 1..8 {
+            s.epoch = i;
+            s.losses.push(i as f64);
+            assert_eq!(c.update(&h, &s).unwrap(), i % 3 == 0);
+        }
+        assert_eq!(c.saves(), 2);
+
+        let r: Checkpoint<Hypothesis> = resume_from(fname).unwrap();
+        assert_eq!(r.model.params(), vec![1.0, 2.0]);
+        assert_eq!(r.state, TrainState { epoch: 6, losses: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], .. TrainState::new() });
+        assert!(resume_from::<::nn::NeuralNetwork>(fname).is_err());
+
+        let mut c = Checkpointer::new(fname).every_secs(3600);
+        assert!(!c.update(&h, &s).unwrap());
+    }
+
+    #[test]
+    fn test_stream_checkpoints() {
+
+        let path = ::std::env::temp_dir().join("rustml_test_stream.ckpt");
+        let fname = path.to_str().unwrap();
+        let data = Matrix::from_vec((0..20).flat_map(|i| vec![1.0, i as f64 / 20.0, 2.0 * i as f64 / 20.0]).collect(), 20, 3);
+        let t = StreamTrainer::new().epochs(10).batch_size(5).alpha(0.5);
+        let mut c = Checkpointer::new(fname).every_epochs(4);
+
+        // uninterrupted
+        let a = t.fit_with_checkpoints(Checkpoint::new(Hypothesis::from_params(&[0.0, 0.0])),
+            &mut MatrixChunks::new(&data, 10), &mut c).unwrap();
+        assert_eq!(c.saves(), 2);
+
+        // resumed after 8 epochs
+        let r: Checkpoint<Hypothesis> = resume_from(fname).unwrap();
+        assert_eq!(r.state.epoch, 8);
+        let b = t.fit_with_checkpoints(r, &mut MatrixChunks::new(&data, 10), &mut c).unwrap();
+        assert_eq!(a.params(), b.params());
+    }
+}
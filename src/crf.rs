@@ -0,0 +1,473 @@
+//! Linear-chain conditional random fields for sequence labeling.
+//!
+//! A [Crf](struct.Crf.html) assigns a label to each token of a sequence
+//! (e.g. part-of-speech tags or named entity tags to the words of a
+//! sentence). The score of a label sequence is the sum of the weights of
+//! the features of each token combined with its label and the weights of
+//! the transitions between consecutive labels (including the transitions
+//! from the start and to the end of the sequence). The features of a token
+//! are generated by [feature templates](enum.Template.html), e.g. the word
+//! itself, its neighbours or its suffix.
+//!
+//! The weights are trained by minimizing the negative log-likelihood of the
+//! training sequences with an L2 penalty with [lbfgs](../opt/fn.lbfgs.html).
+//! The gradient is computed with the forward-backward algorithm and the
+//! most probable label sequence is found with the Viterbi algorithm.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::crf::Crf;
+//!
+//! let sentences = vec![
+//!     vec!["the", "dog", "runs"],
+//!     vec!["a", "cat", "sleeps"],
+//!     vec!["the", "cat", "runs"]
+//! ];
+//! let tags = vec![
+//!     vec!["DET", "NOUN", "VERB"],
+//!     vec!["DET", "NOUN", "VERB"],
+//!     vec!["DET", "NOUN", "VERB"]
+//! ];
+//!
+//! let mut crf = Crf::new();
+//! crf.fit(&sentences, &tags);
+//! assert_eq!(crf.predict(&["a", "dog", "sleeps"]), vec!["DET", "NOUN", "VERB"]);
+//! ```
+use std::collections::BTreeMap;
+
+use opt::{lbfgs, empty_opts};
+
+/// Templates which generate the features of a token.
+///
+/// Words are lowercased except for the shape. Positions outside of the
+/// sequence have the words `<s>` (before) and `</s>` (after).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Template {
+    /// Feature which is present for every token, i.e. a weight per label.
+    Bias,
+    /// The word at the given offset relative to the token, e.g. `-1` for
+    /// the previous word.
+    Word(isize),
+    /// The words at both offsets relative to the token.
+    WordPair(isize, isize),
+    /// The first `n` characters of the word.
+    Prefix(usize),
+    /// The last `n` characters of the word.
+    Suffix(usize),
+    /// The shape of the word in which uppercase letters are replaced by
+    /// `X`, lowercase letters by `x` and digits by `d` and repetitions are
+    /// collapsed, e.g. `Xx` for "Hello" and `d.d` for "3.14".
+    Shape,
+    /// Whether the word starts with an uppercase letter.
+    Capitalized,
+    /// Whether the word contains a digit.
+    Digit
+}
+
+impl Template {
+
+    /// Returns the features of the template for the token at position `i`
+    /// of `tokens`.
+    pub fn features<S: AsRef<str>>(&self, tokens: &[S], i: usize) -> Vec<String> {
+
+        let word = |off: isize| -> String {
+            let j = i as isize + off;
+            if j < 0 {
+                "<s>".to_string()
+            } else if j as usize >= tokens.len() {
+                "</s>".to_string()
+            } else {
+                tokens[j as usize].as_ref().to_lowercase()
+            }
+        };
+        let w = tokens[i].as_ref();
+
+        match *self {
+            Template::Bias => vec!["bias".to_string()],
+            Template::Word(o) => vec![format!("w[{}]={}", o, word(o))],
+            Template::WordPair(a, b) => vec![format!("w[{}]|w[{}]={}|{}", a, b, word(a), word(b))],
+            Template::Prefix(n) => vec![format!("prefix{}={}", n, word(0).chars().take(n).collect::<String>())],
+            Template::Suffix(n) => {
+                let c: Vec<char> = word(0).chars().collect();
+                vec![format!("suffix{}={}", n, c[c.len().saturating_sub(n)..].iter().collect::<String>())]
+            }
+            Template::Shape => {
+                let mut s = String::new();
+                for c in w.chars() {
+                    let x = if c.is_uppercase() { 'X' } else if c.is_lowercase() { 'x' } else if c.is_numeric() { 'd' } else { c };
+                    if !s.ends_with(x) {
+                        s.push(x);
+                    }
+                }
+                vec![format!("shape={}", s)]
+            }
+            Template::Capitalized => {
+                if w.chars().next().map(|c| c.is_uppercase()).unwrap_or(false) { vec!["capitalized".to_string()] } else { vec![] }
+            }
+            Template::Digit => {
+                if w.chars().any(|c| c.is_numeric()) { vec!["digit".to_string()] } else { vec![] }
+            }
+        }
+    }
+}
+
+/// Linear-chain conditional random field.
+#[derive(Clone, Debug)]
+pub struct Crf<L> {
+    templates: Vec<Template>,
+    c2: f64,
+    max_iter: usize,
+    labels: Vec<L>,
+    features: BTreeMap<String, usize>,
+    weights: Vec<f64>
+}
+
+impl <L: Ord + Clone> Crf<L> {
+
+    /// Creates an untrained CRF with the templates `Bias`, `Word(-1)`,
+    /// `Word(0)`, `Word(1)`, `Prefix(3)`, `Suffix(3)`, `Shape`,
+    /// `Capitalized` and `Digit`, an L2 penalty of 0.1 and at most 100
+    /// iterations of L-BFGS.
+    pub fn new() -> Crf<L> {
+        Crf {
+            templates: vec![
+                Template::Bias, Template::Word(-1), Template::Word(0), Template::Word(1),
+                Template::Prefix(3), Template::Suffix(3), Template::Shape,
+                Template::Capitalized, Template::Digit
+            ],
+            c2: 0.1,
+            max_iter: 100,
+            labels: vec![],
+            features: BTreeMap::new(),
+            weights: vec![]
+        }
+    }
+
+    /// Sets the feature templates.
+    ///
+    /// Panics if no template is given.
+    pub fn templates(&self, t: &[Template]) -> Crf<L> {
+
+        assert!(!t.is_empty(), "At least one template is required.");
+        Crf { templates: t.to_vec(), .. self.clone() }
+    }
+
+    /// Sets the weight of the L2 penalty, i.e. `c2 / 2 * |w|^2` is added to
+    /// the negative log-likelihood.
+    pub fn c2(&self, c2: f64) -> Crf<L> {
+        Crf { c2: c2, .. self.clone() }
+    }
+
+    /// Sets the maximum number of iterations of L-BFGS.
+    pub fn max_iter(&self, n: usize) -> Crf<L> {
+        Crf { max_iter: n, .. self.clone() }
+    }
+
+    /// Returns the sorted labels of the training data.
+    pub fn labels(&self) -> &[L] {
+        &self.labels
+    }
+
+    /// Returns the number of distinct features of the training data.
+    pub fn n_features(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Returns the features of the token at position `i` generated by all
+    /// templates.
+    pub fn token_features<S: AsRef<str>>(&self, tokens: &[S], i: usize) -> Vec<String> {
+        self.templates.iter().flat_map(|t| t.features(tokens, i)).collect()
+    }
+
+    /// Trains the CRF on the token sequences `x` with the label sequences
+    /// `y`.
+    ///
+    /// Panics if the number of sequences differ or if a label sequence has
+    /// not the same length as its token sequence.
+    pub fn fit<S: AsRef<str>>(&mut self, x: &[Vec<S>], y: &[Vec<L>]) {
+
+        assert!(x.len() == y.len(), "Number of token and label sequences must be equal.");
+        assert!(x.iter().zip(y.iter()).all(|(a, b)| a.len() == b.len()), "Sequences of tokens and labels must have the same length.");
+
+        let mut labels: Vec<L> = y.iter().flat_map(|s| s.iter().cloned()).collect();
+        labels.sort();
+        labels.dedup();
+        self.labels = labels;
+
+        self.features = BTreeMap::new();
+        let mut data = vec![];
+        for (tokens, tags) in x.iter().zip(y.iter()) {
+            let mut feats = vec![];
+            for i in 0..tokens.len() {
+                let mut ids = vec![];
+                for f in self.token_features(tokens, i) {
+                    let n = self.features.len();
+                    ids.push(*self.features.entry(f).or_insert(n));
+                }
+                feats.push(ids);
+            }
+            let tags: Vec<usize> = tags.iter().map(|l| self.labels.binary_search(l).unwrap()).collect();
+            data.push((feats, tags));
+        }
+
+        let k = self.labels.len();
+        let n = self.features.len() * k + k * k + 2 * k;
+        let c2 = self.c2;
+        let fg = |w: &[f64]| {
+            let mut f = 0.5 * c2 * w.iter().fold(0.0, |acc, x| acc + x * x);
+            let mut g: Vec<f64> = w.iter().map(|x| c2 * x).collect();
+            for &(ref feats, ref tags) in &data {
+                f += nll(w, k, feats, tags, &mut g);
+            }
+            (f, g)
+        };
+        self.weights = lbfgs(&fg, &vec![0.0; n], empty_opts().iter(self.max_iter)).params;
+    }
+
+    /// Returns the most probable label sequence of `tokens`.
+    ///
+    /// Panics if the CRF is not trained.
+    pub fn predict<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<L> {
+
+        assert!(!self.labels.is_empty(), "CRF is not trained.");
+        if tokens.is_empty() {
+            return vec![];
+        }
+        let k = self.labels.len();
+        let feats = self.encode(tokens);
+        let emit = emissions(&self.weights, k, &feats);
+        let (trans, start, end) = transitions(&self.weights, k, self.features.len());
+
+        let n = tokens.len();
+        let mut score: Vec<f64> = (0..k).map(|y| start[y] + emit[0][y]).collect();
+        let mut back = vec![vec![0; k]; n];
+        for t in 1..n {
+            let prev = score.clone();
+            for y in 0..k {
+                let (best, s) = (0..k)
+                    .map(|p| (p, prev[p] + trans[p * k + y]))
+                    .fold((0, ::std::f64::NEG_INFINITY), |a, b| if b.1 > a.1 { b } else { a });
+                back[t][y] = best;
+                score[y] = s + emit[t][y];
+            }
+        }
+        let mut y = (0..k).fold(0, |a, b| if score[b] + end[b] > score[a] + end[a] { b } else { a });
+        let mut path = vec![y; n];
+        for t in (1..n).rev() {
+            y = back[t][y];
+            path[t - 1] = y;
+        }
+        path.iter().map(|&i| self.labels[i].clone()).collect()
+    }
+
+    /// Returns the probability of each label for each token, i.e. row `t`
+    /// contains the marginal distribution of the label of token `t` over
+    /// the [labels](#method.labels).
+    ///
+    /// Panics if the CRF is not trained.
+    pub fn marginals<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<Vec<f64>> {
+
+        assert!(!self.labels.is_empty(), "CRF is not trained.");
+        if tokens.is_empty() {
+            return vec![];
+        }
+        let k = self.labels.len();
+        let feats = self.encode(tokens);
+        let emit = emissions(&self.weights, k, &feats);
+        let (trans, start, end) = transitions(&self.weights, k, self.features.len());
+        let (alpha, beta, log_z) = forward_backward(&emit, trans, start, end);
+        alpha.iter().zip(beta.iter())
+            .map(|(a, b)| a.iter().zip(b.iter()).map(|(x, y)| (x + y - log_z).exp()).collect())
+            .collect()
+    }
+
+    // ids of the known features of each token
+    fn encode<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<Vec<usize>> {
+        (0..tokens.len())
+            .map(|i| self.token_features(tokens, i).iter().filter_map(|f| self.features.get(f).cloned()).collect())
+            .collect()
+    }
+}
+
+// The weights are stored as the state weights (one row of k weights per
+// feature), the transition weights (k x k), the start weights (k) and the
+// end weights (k).
+
+fn emissions(w: &[f64], k: usize, feats: &[Vec<usize>]) -> Vec<Vec<f64>> {
+    feats.iter()
+        .map(|ids| (0..k).map(|y| ids.iter().fold(0.0, |acc, &f| acc + w[f * k + y])).collect())
+        .collect()
+}
+
+fn transitions(w: &[f64], k: usize, n_features: usize) -> (&[f64], &[f64], &[f64]) {
+    let o = n_features * k;
+    (&w[o..o + k * k], &w[o + k * k..o + k * k + k], &w[o + k * k + k..o + k * k + 2 * k])
+}
+
+fn log_sum_exp<I: Iterator<Item = f64>>(it: I) -> f64 {
+
+    let v: Vec<f64> = it.collect();
+    let m = v.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    if m == ::std::f64::NEG_INFINITY {
+        return m;
+    }
+    m + v.iter().fold(0.0, |acc, x| acc + (x - m).exp()).ln()
+}
+
+// returns the forward and backward log scores and the log partition function
+fn forward_backward(emit: &[Vec<f64>], trans: &[f64], start: &[f64], end: &[f64]) -> (Vec<Vec<f64>>, Vec<Vec<f64>>, f64) {
+
+    let n = emit.len();
+    let k = start.len();
+    let mut alpha = vec![vec![0.0; k]; n];
+    let mut beta = vec![vec![0.0; k]; n];
+    for y in 0..k {
+        alpha[0][y] = start[y] + emit[0][y];
+        beta[n - 1][y] = end[y];
+    }
+    for t in 1..n {
+        for y in 0..k {
+            alpha[t][y] = log_sum_exp((0..k).map(|p| alpha[t - 1][p] + trans[p * k + y])) + emit[t][y];
+        }
+    }
+    for t in (0..n - 1).rev() {
+        for y in 0..k {
+            beta[t][y] = log_sum_exp((0..k).map(|q| trans[y * k + q] + emit[t + 1][q] + beta[t + 1][q]));
+        }
+    }
+    let log_z = log_sum_exp((0..k).map(|y| alpha[n - 1][y] + end[y]));
+    (alpha, beta, log_z)
+}
+
+// negative log-likelihood of a sequence, adds its gradient to g
+fn nll(w: &[f64], k: usize, feats: &[Vec<usize>], tags: &[usize], g: &mut [f64]) -> f64 {
+
+    let n = tags.len();
+    if n == 0 {
+        return 0.0;
+    }
+    let nf = (w.len() - k * k - 2 * k) / k;
+    let emit = emissions(w, k, feats);
+    let (trans, start, end) = transitions(w, k, nf);
+    let (alpha, beta, log_z) = forward_backward(&emit, trans, start, end);
+
+    let (to, so, eo) = (nf * k, nf * k + k * k, nf * k + k * k + k);
+    let mut score = start[tags[0]] + end[tags[n - 1]];
+    g[so + tags[0]] -= 1.0;
+    g[eo + tags[n - 1]] -= 1.0;
+    for t in 0..n {
+        score += emit[t][tags[t]];
+        for &f in &feats[t] {
+            g[f * k + tags[t]] -= 1.0;
+        }
+        if t > 0 {
+            score += trans[tags[t - 1] * k + tags[t]];
+            g[to + tags[t - 1] * k + tags[t]] -= 1.0;
+        }
+    }
+
+    for t in 0..n {
+        for y in 0..k {
+            let p = (alpha[t][y] + beta[t][y] - log_z).exp();
+            for &f in &feats[t] {
+                g[f * k + y] += p;
+            }
+            if t == 0 {
+                g[so + y] += p;
+            }
+            if t == n - 1 {
+                g[eo + y] += p;
+            }
+            if t > 0 {
+                for p0 in 0..k {
+                    g[to + p0 * k + y] += (alpha[t - 1][p0] + trans[p0 * k + y] + emit[t][y] + beta[t][y] - log_z).exp();
+                }
+            }
+        }
+    }
+    log_z - score
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates() {
+
+        let s = ["Hello", "world", "3.14"];
+        assert_eq!(Template::Word(-1).features(&s, 0), vec!["w[-1]=<s>"]);
+        assert_eq!(Template::Word(1).features(&s, 0), vec!["w[1]=world"]);
+        assert_eq!(Template::WordPair(0, 1).features(&s, 2), vec!["w[0]|w[1]=3.14|</s>"]);
+        assert_eq!(Template::Suffix(3).features(&s, 0), vec!["suffix3=llo"]);
+        assert_eq!(Template::Prefix(3).features(&s, 1), vec!["prefix3=wor"]);
+        assert_eq!(Template::Shape.features(&s, 0), vec!["shape=Xx"]);
+        assert_eq!(Template::Shape.features(&s, 2), vec!["shape=d.d"]);
+        assert_eq!(Template::Capitalized.features(&s, 0).len(), 1);
+        assert!(Template::Capitalized.features(&s, 1).is_empty());
+        assert_eq!(Template::Digit.features(&s, 2).len(), 1);
+    }
+
+    #[test]
+    fn test_crf() {
+
+        // names are capitalized, numbers contain digits and "bank" is
+        // labeled by its context
+        let x = vec![
+            vec!["Alice", "paid", "42", "to", "the", "bank"],
+            vec!["Bob", "went", "to", "the", "bank"],
+            vec!["we", "bank", "on", "Carol"],
+            vec!["Dave", "saw", "7", "cats"],
+            vec!["they", "bank", "17"]
+        ];
+        let y = vec![
+            vec!["NAME", "O", "NUM", "O", "O", "NOUN"],
+            vec!["NAME", "O", "O", "O", "NOUN"],
+            vec!["O", "VERB", "O", "NAME"],
+            vec!["NAME", "O", "NUM", "O"],
+            vec!["O", "VERB", "NUM"]
+        ];
+
+        let mut crf = Crf::new().c2(0.01);
+        crf.fit(&x, &y);
+        assert_eq!(crf.labels(), &["NAME", "NOUN", "NUM", "O", "VERB"]);
+        for (a, b) in x.iter().zip(y.iter()) {
+            assert_eq!(&crf.predict(a), b);
+        }
+        assert_eq!(crf.predict(&["Eve", "paid", "99", "to", "the", "bank"]), vec!["NAME", "O", "NUM", "O", "O", "NOUN"]);
+        assert_eq!(crf.predict(&["you", "bank", "8"]), vec!["O", "VERB", "NUM"]);
+        assert!(crf.predict::<&str>(&[]).is_empty());
+
+        let m = crf.marginals(&["Eve", "paid"]);
+        assert_eq!(m.len(), 2);
+        for r in &m {
+            assert!((r.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        }
+        assert!(m[0][0] > 0.5);
+    }
+
+    #[test]
+    fn test_gradient() {
+
+        // analytic gradient matches the numerical gradient
+        let k = 3;
+        let feats = vec![vec![0, 1], vec![1], vec![0, 2]];
+        let tags = vec![2, 0, 1];
+        let n = 3 * k + k * k + 2 * k;
+        let w: Vec<f64> = (0..n).map(|i| ((i * 7) % 5) as f64 * 0.1 - 0.2).collect();
+        let mut g = vec![0.0; n];
+        nll(&w, k, &feats, &tags, &mut g);
+        for i in 0..n {
+            let mut a = w.clone();
+            let mut b = w.clone();
+            a[i] += 1e-6;
+            b[i] -= 1e-6;
+            let mut tmp = vec![0.0; n];
+            let d = (nll(&a, k, &feats, &tags, &mut tmp) - nll(&b, k, &feats, &tags, &mut tmp)) / 2e-6;
+            assert!((d - g[i]).abs() < 1e-6, "{} {} {}", i, d, g[i]);
+        }
+    }
+}
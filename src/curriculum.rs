@@ -0,0 +1,188 @@
+//! Schedules of the weights of training examples.
+//!
+//! A [WeightSchedule](trait.WeightSchedule.html) changes the weights of the
+//! examples between the iterations of the training of a
+//! [neural network](../nn/struct.NeuralNetwork.html#method.weighted_gd).
+//! Before each iteration the schedule gets the index of the iteration and
+//! the error of each example under the current network, i.e. half of the
+//! squared distance between the output and the target. Any closure
+//! `FnMut(usize, &[f64], &mut [f64])` can be used as schedule.
+//!
+//! * [SelfPaced](struct.SelfPaced.html) implements curriculum learning:
+//!   the training starts with the easy examples and harder examples are
+//!   added with each iteration.
+//! * [ErrorReweighting](struct.ErrorReweighting.html) increases the weights
+//!   of the examples with a large error similar to boosting.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::nn::NeuralNetwork;
+//! use rustml::opt::empty_opts;
+//! use rustml::curriculum::ErrorReweighting;
+//!
+//! # fn main() {
+//! let x = mat![0.0, 0.0; 0.0, 1.0; 1.0, 0.0; 1.0, 1.0];
+//! let t = mat![0.1; 0.9; 0.9; 0.1];
+//! let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+//!
+//! let mut w = vec![1.0; 4];
+//! let m = n.weighted_gd(&x, &t, &mut w, empty_opts().alpha(1.0).iter(50), &mut ErrorReweighting::new(0.1));
+//! assert_eq!(m.layers(), 3);
+//! // the mean of the weights stays one
+//! assert!((w.iter().sum::<f64>() - 4.0).abs() < 1e-9);
+//! # }
+//! ```
+
+/// Changes the weights of the training examples between iterations.
+pub trait WeightSchedule {
+
+    /// Is called before the iteration `iter` (starting at 0) with the error
+    /// of each example and may change their weights.
+    fn update(&mut self, iter: usize, errors: &[f64], weights: &mut [f64]);
+}
+
+impl <F: FnMut(usize, &[f64], &mut [f64])> WeightSchedule for F {
+
+    fn update(&mut self, iter: usize, errors: &[f64], weights: &mut [f64]) {
+        self(iter, errors, weights)
+    }
+}
+
+/// Schedule which keeps the weights unchanged.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedWeights;
+
+impl WeightSchedule for FixedWeights {
+
+    fn update(&mut self, _iter: usize, _errors: &[f64], _weights: &mut [f64]) { }
+}
+
+/// Self-paced curriculum learning.
+///
+/// In iteration `i` each example with an error of at most
+/// `threshold * growth^i` gets the weight one and all other examples get
+/// the weight zero, i.e. the training starts with the easy examples and
+/// the threshold grows until all examples are used.
+#[derive(Clone, Copy, Debug)]
+pub struct SelfPaced {
+    threshold: f64,
+    growth: f64
+}
+
+impl SelfPaced {
+
+    /// Creates the schedule with the initial threshold and its growth
+    /// factor per iteration.
+    ///
+    /// Panics if `threshold` is not positive or if `growth` is smaller than
+    /// one.
+    pub fn new(threshold: f64, growth: f64) -> SelfPaced {
+
+        assert!(threshold > 0.0, "Threshold must be positive.");
+        assert!(growth >= 1.0, "Growth factor must not be smaller than one.");
+        SelfPaced { threshold: threshold, growth: growth }
+    }
+
+    /// Returns the threshold of the iteration `iter`.
+    pub fn threshold(&self, iter: usize) -> f64 {
+        self.threshold * self.growth.powi(iter as i32)
+    }
+}
+
+impl WeightSchedule for SelfPaced {
+
+    fn update(&mut self, iter: usize, errors: &[f64], weights: &mut [f64]) {
+
+        let t = self.threshold(iter);
+        for (w, &e) in weights.iter_mut().zip(errors.iter()) {
+            *w = if e <= t { 1.0 } else { 0.0 };
+        }
+    }
+}
+
+/// Boosting-style reweighting of the examples.
+///
+/// Before each iteration the weight of each example is multiplied by
+/// `exp(rate * e / m)` where `e` is the error of the example and `m` is the
+/// mean error of all examples. Afterwards the weights are normalized so
+/// that their mean is one.
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorReweighting {
+    rate: f64
+}
+
+impl ErrorReweighting {
+
+    /// Creates the schedule with the given rate.
+    ///
+    /// Panics if `rate` is negative.
+    pub fn new(rate: f64) -> ErrorReweighting {
+
+        assert!(rate >= 0.0, "Rate must not be negative.");
+        ErrorReweighting { rate: rate }
+    }
+}
+
+impl WeightSchedule for ErrorReweighting {
+
+    fn update(&mut self, _iter: usize, errors: &[f64], weights: &mut [f64]) {
+
+        let m = errors.iter().sum::<f64>() / errors.len() as f64;
+        if m <= 0.0 {
+            return;
+        }
+        for (w, &e) in weights.iter_mut().zip(errors.iter()) {
+            *w *= (self.rate * e / m).exp();
+        }
+        let s = weights.iter().sum::<f64>() / weights.len() as f64;
+        if s > 0.0 {
+            for w in weights.iter_mut() {
+                *w /= s;
+            }
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_paced() {
+
+        let mut s = SelfPaced::new(0.1, 2.0);
+        let e = [0.05, 0.15, 0.3, 0.5];
+        let mut w = vec![0.5; 4];
+        s.update(0, &e, &mut w);
+        assert_eq!(w, vec![1.0, 0.0, 0.0, 0.0]);
+        s.update(2, &e, &mut w);
+        assert_eq!(w, vec![1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(s.threshold(3), 0.8);
+    }
+
+    #[test]
+    fn test_error_reweighting() {
+
+        let mut s = ErrorReweighting::new(1.0);
+        let mut w = vec![1.0, 1.0, 2.0];
+        s.update(0, &[0.0, 0.2, 0.1], &mut w);
+        assert!((w.iter().sum::<f64>() - 3.0).abs() < 1e-12);
+        assert!(w[1] > w[0]);
+        assert!((w[1] / w[0] - 2.0f64.exp()).abs() < 1e-12);
+        assert!((w[2] / w[0] - 2.0 * 1.0f64.exp()).abs() < 1e-12);
+
+        // all errors zero
+        let mut v = w.clone();
+        s.update(1, &[0.0; 3], &mut v);
+        assert_eq!(v, w);
+
+        let mut f = FixedWeights;
+        f.update(0, &[1.0, 2.0, 3.0], &mut v);
+        assert_eq!(v, w);
+    }
+}
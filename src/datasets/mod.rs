@@ -0,0 +1,1120 @@
+//! Module to easily access popular datasets often used to measure the performance of
+//! machine learning algorithms.
+//!
+//! The image on the left shows an example of 200 handwritten digits from the 
+//! [MNIST database of handwritten digits](http://yann.lecun.com/exdb/mnist/).
+//! Rustml provides a simple interface via 
+//! [MnistDigits](struct.MnistDigits.html) to easily access those digits. The MNIST
+//! database comes with 60,000 examples in a training set and 10,000 examples in
+//! a test set. The image has been created with rustml with just a few lines of code.
+//! See the example [here](https://github.com/daniel-e/rustml/blob/master/examples/image_grid.rs).
+//! With the feature `download` the database can be downloaded and cached
+//! automatically. Files in the IDX format, which is used by the MNIST database, can be
+//! read with [Idx](struct.Idx.html).
+//! 
+//! The image in the middle shows an example of 1000 points normally distributed with the
+//! mean at (1, 2) and a standard deviation of 0.3 for the first dimension and 0.4 for
+//! the second dimension.
+//!
+//! The image on the right shows a mixture model. A dataset which consists of three sources
+//! which are normally distributed with different parameters. The components of a
+//! [Mixture](struct.Mixture.html) can also be drawn from any distribution which
+//! implements [Sample](../distributions/trait.Sample.html), e.g. a
+//! [MultivariateNormal](../distributions/struct.MultivariateNormal.html) for
+//! correlated data, with mixing weights.
+//!
+//! For examples and tests the module also contains the embedded
+//! [Iris dataset](fn.iris.html) and generators for synthetic toy problems:
+//! [make_moons](fn.make_moons.html), [make_circles](fn.make_circles.html),
+//! [make_blobs](fn.make_blobs.html) and
+//! [make_regression](fn.make_regression.html) for a linear regression problem.
+//!
+//! <div style="font-size:80%">
+//!  <div style="float:left;text-align:center;">
+//!   MNIST database of handwritten digits<br/><img style="border-top:1px solid black" src="../../digits_grid.png">
+//!  </div>
+//!  <div style="float:left;text-align:center;padding-left:10px">
+//!   Toy data: normally distributed data<br/><img src="../../plot_normal_1.png">
+//!  </div>
+//!  <div style="float:left;text-align:center;padding-left:10px">
+//!   Toy data: mixture<br/><img src="../../plot_mixture.png">
+//!  </div>
+//! </div>
+//! <div style="clear:both;"></div>
+
+extern crate num;
+extern crate time;
+extern crate rand;
+
+use std::f64;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::Read;
+use std::env::home_dir;
+use std::path::Path;
+use std::rc::Rc;
+use self::rand::distributions::{Normal, IndependentSample};
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+
+use io::GzipData;
+use matrix::*;
+use distributions::Sample;
+
+use consts::MNIST_PATH;
+
+/// This structure offers access to the MNIST database of handwritten digits.
+///
+/// The database that is used is available at http://yann.lecun.com/exdb/mnist/
+/// and contains 60,000 training examples and 10,000 test examples of handwritten
+/// digits.
+///
+/// Currently, due to upload limits at crate.io a smaller training dataset
+/// is used with 50,000 training examples.
+///
+/// To be able to use the dataset please follow the instructions mentioned
+/// [here](https://github.com/daniel-e/rustml#datasets).
+pub struct MnistDigits;
+
+impl MnistDigits {
+
+    fn from_high_endian(arr: &[u8]) -> u64 {
+
+        arr.iter().fold(0, |acc, val| acc * 256 + (*val as u64))
+    }
+
+    fn read_u32<T: Read>(src: &mut T) -> Result<u32, &'static str> {
+
+        let mut buf: [u8; 4] = [0; 4];
+
+        match src.read(&mut buf) {
+            Ok(4) => Ok(MnistDigits::from_high_endian(&buf) as u32),
+            _     => Err("Could not read data."),
+        }
+     }
+
+    fn read_labels(fname: &str) -> Result<Vec<u8>, &'static str> {
+
+        let mut data = try!(GzipData::from_file(fname));
+
+        if try!(MnistDigits::read_u32(&mut data)) != 8 * 256 + 1 {
+            return Err("Invalid magic number.");
+        }
+
+        let n = try!(MnistDigits::read_u32(&mut data));
+
+        let l: Vec<u8> = data.iter().cloned().collect();
+        if l.len() != n as usize {
+            return Err("Invalid number of items.");
+        }
+
+        if !l.iter().all(|&x| x <= 9) {
+            return Err("Found invalid values for labels.");
+        }
+        
+        Ok(l)
+    }
+
+    fn read_examples(fname: &str) -> Result<Vec<u8>, &'static str> {
+
+        let mut data = try!(GzipData::from_file(fname));
+
+        if try!(MnistDigits::read_u32(&mut data)) != 8 * 256 + 3 {
+            return Err("Invalid magic number.");
+        }
+
+        let n = try!(MnistDigits::read_u32(&mut data));
+
+        let rows = try!(MnistDigits::read_u32(&mut data));
+        let cols = try!(MnistDigits::read_u32(&mut data));
+        if rows != 28 || cols != 28 {
+            return Err("Invalid number of rows or columns.");
+        }
+
+        let v = data.buf();
+        if v.len() != (n * 28 * 28) as usize {
+            return Err("Could not read data.");
+        }
+
+        Ok(v.to_vec())
+    }
+
+    pub fn from(vectors_fname: &str, labels_fname: &str) -> Result<(Matrix<u8>, Vec<u8>), &'static str> {
+
+        let labels = try!(MnistDigits::read_labels(labels_fname));
+        let values = try!(MnistDigits::read_examples(vectors_fname));
+
+        match values.len() == labels.len() * 784 {
+            true => {
+                let m = Matrix::from_vec(values, labels.len(), 784);
+                Ok((m, labels))
+            },
+            false => Err("Invalid matrix.")
+        }
+    }
+
+    fn path(fname: &str) -> Result<String, &'static str> {
+
+        match home_dir() {
+            Some(ref mut p) => {
+                p.push(Path::new(MNIST_PATH));
+                p.push(Path::new(fname));
+                Ok(p.as_path().to_str().unwrap().to_string())
+            }
+            None => Err("Could not get home directory.")
+        }
+    }
+
+    /// Reads the default MNIST training set.
+    ///
+    /// Each row of the returned matrix represents an image of size 28x28.
+    pub fn default_training_set() -> Result<(Matrix<u8>, Vec<u8>), &'static str> {
+
+        // tested in tests directory
+        let features = try!(MnistDigits::path("train-images-idx3-ubyte.gz"));
+        let labels = try!(MnistDigits::path("train-labels-idx1-ubyte.gz"));
+        MnistDigits::from(&features, &labels)
+    }
+
+    pub fn default_test_set() -> Result<(Matrix<u8>, Vec<u8>), &'static str> {
+
+        // tested in tests directory
+        let features = try!(MnistDigits::path("t10k-images-idx3-ubyte.gz"));
+        let labels = try!(MnistDigits::path("t10k-labels-idx1-ubyte.gz"));
+        MnistDigits::from(&features, &labels)
+    }
+
+    /// Reads the MNIST training and test sets from the directory `dir` and
+    /// scales the pixel values into the interval [0, 1].
+    ///
+    /// The directory must contain the files `train-images-idx3-ubyte.gz`,
+    /// `train-labels-idx1-ubyte.gz`, `t10k-images-idx3-ubyte.gz` and
+    /// `t10k-labels-idx1-ubyte.gz`.
+    pub fn load_normalized(dir: &str) -> Result<Mnist, &'static str> {
+
+        let p = |f: &str| Path::new(dir).join(f).to_str().unwrap().to_string();
+        let (train_x, train_y) = try!(MnistDigits::from(
+            &p("train-images-idx3-ubyte.gz"), &p("train-labels-idx1-ubyte.gz")));
+        let (test_x, test_y) = try!(MnistDigits::from(
+            &p("t10k-images-idx3-ubyte.gz"), &p("t10k-labels-idx1-ubyte.gz")));
+        Ok(Mnist {
+            train_x: MnistDigits::normalize(&train_x),
+            train_y: train_y,
+            test_x: MnistDigits::normalize(&test_x),
+            test_y: test_y
+        })
+    }
+
+    fn normalize(m: &Matrix<u8>) -> Matrix<f64> {
+
+        Matrix::from_vec(m.buf().iter().map(|&x| x as f64 / 255.0).collect(), m.rows(), m.cols())
+    }
+
+    /// Downloads the MNIST database into the directory `dir` unless the
+    /// files already exist and reads it like
+    /// [load_normalized](#method.load_normalized). If `dir` is `None` the
+    /// directory of the default datasets (`~/.rustml/datasets/mnist_digits`)
+    /// is used as cache.
+    ///
+    /// Each file is checked before it is moved into the cache and files
+    /// which already exist in the cache are checked before they are used:
+    /// the file must be a valid gzip file which contains an IDX file with
+    /// the correct magic number, the expected number of images or labels
+    /// (60,000 for the training set and 10,000 for the test set) and the
+    /// corresponding size. Invalid files in the cache are downloaded again.
+    ///
+    /// This function is only available with the feature `download`. The
+    /// files are downloaded with `curl`, which must be installed.
+    #[cfg(feature = "download")]
+    pub fn download(dir: Option<&str>) -> Result<Mnist, &'static str> {
+
+        use std::fs::{create_dir_all, rename};
+        use std::process::Command;
+
+        let dir = match dir {
+            Some(d) => d.to_string(),
+            None => {
+                let p = try!(MnistDigits::path(""));
+                p.trim_end_matches('/').to_string()
+            }
+        };
+        try!(create_dir_all(&dir).map_err(|_| "Could not create directory."));
+
+        for &(f, n) in &MNIST_FILES {
+            let target = Path::new(&dir).join(f);
+            if MnistDigits::is_valid_file(&target, n) {
+                continue;
+            }
+            // download into a temporary file so that a failed download
+            // does not leave a broken file in the cache
+            let tmp = Path::new(&dir).join(format!("{}.part", f));
+            let ok = try!(Command::new("curl")
+                .arg("-fsSL")
+                .arg("-o").arg(&tmp)
+                .arg(format!("{}{}", MNIST_URL, f))
+                .status()
+                .map_err(|_| "Could not run curl.")).success();
+            if !ok {
+                return Err("Could not download the MNIST database.");
+            }
+            if !MnistDigits::is_valid_file(&tmp, n) {
+                let _ = ::std::fs::remove_file(&tmp);
+                return Err("Downloaded file is not a valid file of the MNIST database.");
+            }
+            try!(rename(&tmp, &target).map_err(|_| "Could not move downloaded file."));
+        }
+        MnistDigits::load_normalized(&dir)
+    }
+
+    /// Returns true if `path` is a gzip compressed IDX file of the MNIST
+    /// database with `n` labels (if the name contains `labels`) or `n`
+    /// images.
+    #[cfg(feature = "download")]
+    fn is_valid_file(path: &Path, n: usize) -> bool {
+
+        let (fname, name) = match (path.to_str(), path.file_name().and_then(|f| f.to_str())) {
+            (Some(p), Some(f)) => (p, f),
+            _ => return false
+        };
+        if name.contains("labels") {
+            MnistDigits::read_labels(fname).map(|l| l.len() == n).unwrap_or(false)
+        } else {
+            MnistDigits::read_examples(fname).map(|v| v.len() == n * 784).unwrap_or(false)
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+const MNIST_URL: &'static str = "https://storage.googleapis.com/cvdf-datasets/mnist/";
+
+// files of the database and the number of images or labels in each file
+#[cfg(feature = "download")]
+const MNIST_FILES: [(&'static str, usize); 4] = [
+    ("train-images-idx3-ubyte.gz", 60000),
+    ("train-labels-idx1-ubyte.gz", 60000),
+    ("t10k-images-idx3-ubyte.gz", 10000),
+    ("t10k-labels-idx1-ubyte.gz", 10000)
+];
+
+/// The MNIST training and test sets with pixel values in the interval [0, 1].
+///
+/// Each row of a matrix of images represents an image of size 28x28.
+pub struct Mnist {
+    /// Images of the training set.
+    pub train_x: Matrix<f64>,
+    /// Labels of the training set.
+    pub train_y: Vec<u8>,
+    /// Images of the test set.
+    pub test_x: Matrix<f64>,
+    /// Labels of the test set.
+    pub test_y: Vec<u8>
+}
+
+// ----------------------------------------------------------------------------
+
+/// An array read from a file in the IDX format.
+///
+/// The IDX format is a simple format for multi-dimensional arrays that is
+/// used e.g. for the MNIST database. A file consists of a magic number
+/// which encodes the data type and the number of dimensions, the size of
+/// each dimension and the values in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Idx {
+    dims: Vec<usize>,
+    data: Vec<f64>
+}
+
+impl Idx {
+
+    /// Parses an array in the IDX format. All data types of the format
+    /// (unsigned and signed bytes, 16-bit and 32-bit integers, floats and
+    /// doubles) are supported and converted into `f64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::datasets::Idx;
+    ///
+    /// // a 2x2 array of unsigned bytes
+    /// let buf = [0, 0, 8, 2, 0, 0, 0, 2, 0, 0, 0, 2, 1, 2, 3, 255];
+    /// let idx = Idx::parse(&buf).unwrap();
+    /// assert_eq!(idx.dims(), &[2, 2]);
+    /// assert_eq!(idx.data(), &[1.0, 2.0, 3.0, 255.0]);
+    /// ```
+    pub fn parse(buf: &[u8]) -> Result<Idx, &'static str> {
+
+        if buf.len() < 4 || buf[0] != 0 || buf[1] != 0 {
+            return Err("Invalid magic number.");
+        }
+        let size = match buf[2] {
+            0x08 | 0x09 => 1,
+            0x0B => 2,
+            0x0C | 0x0D => 4,
+            0x0E => 8,
+            _ => return Err("Invalid data type.")
+        };
+        let n = buf[3] as usize;
+        if buf.len() < 4 + 4 * n {
+            return Err("Could not read data.");
+        }
+
+        let dims: Vec<usize> = buf[4..4 + 4 * n].chunks(4)
+            .map(|b| MnistDigits::from_high_endian(b) as usize)
+            .collect();
+        let len = dims.iter().fold(1, |acc, &d| acc * d);
+        let values = &buf[4 + 4 * n..];
+        if values.len() != len * size {
+            return Err("Invalid number of items.");
+        }
+
+        let data = values.chunks(size).map(|b| {
+            let x = MnistDigits::from_high_endian(b);
+            match buf[2] {
+                0x08 => x as f64,
+                0x09 => x as u8 as i8 as f64,
+                0x0B => x as u16 as i16 as f64,
+                0x0C => x as u32 as i32 as f64,
+                0x0D => f32::from_bits(x as u32) as f64,
+                _ => f64::from_bits(x)
+            }
+        }).collect();
+        Ok(Idx { dims: dims, data: data })
+    }
+
+    /// Reads an array in the IDX format from a file. Files with the
+    /// extension `.gz` are decompressed.
+    pub fn from_file(fname: &str) -> Result<Idx, &'static str> {
+
+        let buf = if fname.ends_with(".gz") {
+            try!(GzipData::from_file(fname)).buf().to_vec()
+        } else {
+            let mut v = Vec::new();
+            try!(File::open(fname).and_then(|mut f| f.read_to_end(&mut v))
+                .map_err(|_| "Could not read file."));
+            v
+        };
+        Idx::parse(&buf)
+    }
+
+    /// Returns the size of each dimension.
+    pub fn dims(&self) -> &[usize] {
+        &self.dims
+    }
+
+    /// Returns the values in row-major order.
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Converts the array into a matrix with one row for each element of
+    /// the first dimension, i.e. all other dimensions are flattened (e.g.
+    /// each image of a 3-dimensional array of images becomes a row). A
+    /// 1-dimensional array becomes a matrix with one column.
+    pub fn into_matrix(self) -> Matrix<f64> {
+
+        let rows = self.dims.first().cloned().unwrap_or(1);
+        let cols = if rows == 0 { 0 } else { self.data.len() / rows };
+        Matrix::from_vec(self.data, rows, cols)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Generates multi-dimensional data where each dimension is normally distributed.
+///
+/// # Example
+///
+/// ```
+/// # extern crate rustml;
+/// use rustml::datasets::*;
+///
+/// # fn main() {
+/// let seed = [1, 2, 3, 4];
+/// // create 2-dimensional vectors where the values of the first
+/// // component are normal distributed with the parameters
+/// // mu = 1.0 and std = 0.5 and the values of the second component
+/// // are normal distributed with the parameters mu = 2.0 and
+/// // std = 0.8.
+/// let nd = 
+///     normal_builder(seed)
+///     .add(1.0, 0.5)  // parameters for the 1st dimension
+///     .add(2.0, 0.8); // parameters for the 2nd dimension
+/// for v in nd.take(100) {
+///     // do s.th.
+///     assert_eq!(v.len(), 2);
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct NormalData {
+    rng: XorShiftRng,
+    normal: Vec<Normal>
+}
+
+impl NormalData {
+    /// Adds a dimension for which the data is normally distributed with
+    /// the given parameters.
+    pub fn add(&self, mean: f64, std: f64) -> NormalData {
+
+        let mut n = self.normal.clone();
+        n.push(Normal::new(mean, std));
+
+        NormalData {
+            rng: self.rng.clone(),
+            normal: n
+        }
+    }
+
+    /// Returns the number of dimensions added via the `add` method.
+    pub fn len(&self) -> usize { self.normal.len() }
+}
+
+impl Iterator for NormalData {
+    type Item = Vec<f64>;
+    
+    fn next(&mut self) -> Option<Vec<f64>> {
+
+        let n = self.normal.len();
+        let mut v: Vec<f64> = vec![];
+
+        for i in 0..n {
+            v.push(self.normal[i].ind_sample(&mut self.rng));
+        }
+        Some(v)
+    }
+}
+
+/// Draws each dimension from its normal distribution with the given random
+/// number generator instead of the generator of the data source.
+impl Sample for NormalData {
+
+    fn dim(&self) -> usize {
+        self.normal.len()
+    }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        self.normal.iter().map(|n| n.ind_sample(&mut rng)).collect()
+    }
+}
+
+/// Creates a normally distributed data source.
+pub fn normal_builder(seed: [u32; 4]) -> NormalData {
+    NormalData { 
+        rng: XorShiftRng::from_seed(seed),
+        normal: vec![]
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Generates random multi-dimensional data points (a population) from
+/// different normally distributed sources (subpopulations).
+///
+/// # Example
+///
+/// ```
+/// # extern crate rustml;
+/// use rustml::datasets::*;
+///
+/// # fn main() {
+/// let seed = [2, 3, 5, 7];
+/// let m = 
+///     mixture_builder()
+///         .add(100, normal_builder(seed).add(1.0, 1.2).add(2.0, 1.2))
+///         .add(100, normal_builder(seed).add(5.0, 1.5).add(6.0, 1.5))
+///         .add(100, normal_builder(seed).add(6.0, 1.5).add(0.0, 1.5))
+///         .as_matrix();
+/// assert_eq!(m.rows(), 300);
+/// assert_eq!(m.cols(), 3);
+/// # }
+/// ```
+///
+/// Components from arbitrary distributions are added with mixing weights
+/// via [add_component](#method.add_component). For each sample
+/// [sample](#method.sample) first chooses a component with a probability
+/// proportional to its weight and returns the index of the component as the
+/// label of the sample:
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::datasets::*;
+/// use rustml::distributions::{MultivariateNormal, Dirichlet};
+///
+/// # fn main() {
+/// let corr = MultivariateNormal::new(&[5.0, 5.0], &mat![1.0, 0.9; 0.9, 1.0]).unwrap();
+/// let (x, y) =
+///     mixture_builder()
+///         .add_component(3.0, normal_builder([1, 1, 1, 1]).add(0.0, 1.0).add(0.0, 1.0))
+///         .add_component(1.0, corr)
+///         .add_component(1.0, Dirichlet::new(&[2.0, 2.0]))
+///         .sample(500, [2, 3, 5, 7]);
+/// assert_eq!(x.rows(), 500);
+/// assert_eq!(x.cols(), 2);
+/// assert!(y.iter().filter(|&&l| l == 0).count() > 250);
+/// # }
+/// ```
+pub struct Mixture {
+    normal: Vec<(usize, NormalData)>,
+    components: Vec<(f64, Rc<dyn Sample>)>
+}
+
+impl Mixture {
+
+    /// Adds a normally distributed data source (subpopulation) 
+    /// which generates `n` samples.
+    pub fn add(&self, n: usize, src: NormalData) -> Mixture {
+
+        if self.normal.len() > 0 && self.normal[0].1.len() != src.len() {
+            panic!("Invalid length.");
+        }
+
+        let mut v = self.normal.clone();
+        v.push((n, src));
+        Mixture {
+            normal: v,
+            components: self.components.clone()
+        }
+    }
+
+    /// Adds a component which draws its samples from `dist` and which is
+    /// chosen with a probability proportional to `weight` by
+    /// [sample](#method.sample).
+    ///
+    /// Panics if the weight is not greater than zero or if the number of
+    /// dimensions differs from the dimensions of the other components.
+    pub fn add_component<S: Sample + 'static>(&self, weight: f64, dist: S) -> Mixture {
+
+        assert!(weight > 0.0, "Weight must be greater than zero.");
+        if let Some(c) = self.components.first() {
+            assert!(c.1.dim() == dist.dim(), "Invalid length.");
+        }
+
+        let mut v = self.components.clone();
+        v.push((weight, Rc::new(dist)));
+        Mixture {
+            normal: self.normal.clone(),
+            components: v
+        }
+    }
+
+    /// Returns the mixing weights of the components added via
+    /// [add_component](#method.add_component) normalized to sum to one.
+    pub fn weights(&self) -> Vec<f64> {
+
+        let s: f64 = self.components.iter().map(|c| c.0).sum();
+        self.components.iter().map(|c| c.0 / s).collect()
+    }
+
+    /// Draws `n` samples from the components added via
+    /// [add_component](#method.add_component) with a random number
+    /// generator created from `seed`.
+    ///
+    /// Returns a matrix which contains a sample in each row and the labels
+    /// of the samples, i.e. the index of the component from which each
+    /// sample has been drawn.
+    ///
+    /// Panics if no component has been added.
+    pub fn sample(&self, n: usize, seed: [u32; 4]) -> (Matrix<f64>, Vec<usize>) {
+
+        assert!(!self.components.is_empty(), "At least one component is required.");
+
+        let mut rng = XorShiftRng::from_seed(seed);
+        let weights = self.weights();
+        let d = self.components[0].1.dim();
+        let mut v = Vec::with_capacity(n * d);
+        let mut labels = Vec::with_capacity(n);
+        for _ in 0..n {
+            let u = rng.gen::<f64>();
+            let mut acc = 0.0;
+            let mut idx = weights.len() - 1;
+            for (i, w) in weights.iter().enumerate() {
+                acc += *w;
+                if u < acc {
+                    idx = i;
+                    break;
+                }
+            }
+            v.extend(self.components[idx].1.draw(&mut rng));
+            labels.push(idx);
+        }
+        (Matrix::from_vec(v, n, d), labels)
+    }
+
+    /// Returns a matrix which contains the population consisting of one or
+    /// more subpopulations.
+    ///
+    /// Each row of the matrix represents a sample generated from one of
+    /// the subpopulations. 
+    ///
+    /// The value in the first column of
+    /// a row denotes the subpopulation from which this sample has been generated.
+    /// If the value is 0 this sample has been
+    /// created from the first subpopulation (i.e. the first data source that has
+    /// been added with the `add` method). If the value is 1 this sample has been
+    /// created from the second subpopulation and so on.
+    ///
+    /// The following columns denote the dimensions of the data sources.
+    ///
+    /// # Example
+    ///
+    /// Let's assume you create a mixture model with two data sources
+    /// each with two dimensions as follows:
+    ///
+    /// ```ignore
+    /// let m = 
+    ///     mixture_builder()
+    ///         .add(100, normal_builder(seed).add(1.0, 0.2).add(2.0, 0.2))
+    ///         .add(100, normal_builder(seed).add(5.0, 0.2).add(6.0, 0.2))
+    ///         .as_matrix();
+    /// ```
+    ///
+    /// Then, the matrix could look like:
+    /// 
+    pub fn as_matrix(&mut self) -> Matrix<f64> {
+
+        let mut m = Matrix::new();
+
+        for (idx, &mut (n, ref mut nd)) in self.normal.iter_mut().enumerate() {
+            for _ in 0..n {
+                let v = nd.next().unwrap().clone();
+                let mut x = vec![idx as f64];
+                for j in v {
+                    x.push(j);
+                }
+                m.add_row(&x);
+            }
+        }
+        m
+    }
+}
+
+/// Creates a mixture model with normally distributed data sources or
+/// components from arbitrary distributions.
+pub fn mixture_builder() -> Mixture {
+    Mixture {
+        normal: vec![],
+        components: vec![]
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Names of the features of the [Iris dataset](fn.iris.html).
+pub const IRIS_FEATURES: [&'static str; 4] = ["sepal length", "sepal width", "petal length", "petal width"];
+
+/// Names of the classes of the [Iris dataset](fn.iris.html).
+pub const IRIS_CLASSES: [&'static str; 3] = ["setosa", "versicolor", "virginica"];
+
+/// Returns the Iris flower dataset.
+///
+/// The dataset contains 150 examples of three classes (50 examples per
+/// class) of iris plants. Each example has the four features described by
+/// [IRIS_FEATURES](constant.IRIS_FEATURES.html) measured in centimeters. The
+/// labels are the indexes of the classes in
+/// [IRIS_CLASSES](constant.IRIS_CLASSES.html). The dataset is embedded into
+/// the library, i.e. no file is required.
+///
+/// # Example
+///
+/// ```
+/// use rustml::datasets::*;
+///
+/// let (x, y) = iris();
+/// assert_eq!(x.rows(), 150);
+/// assert_eq!(x.cols(), 4);
+/// assert_eq!(IRIS_CLASSES[y[0]], "setosa");
+/// ```
+pub fn iris() -> (Matrix<f64>, Vec<usize>) {
+
+    let mut m = Matrix::new();
+    let mut labels = vec![];
+    for line in IRIS.lines() {
+        let v: Vec<f64> = line.split(',').map(|x| x.parse().unwrap()).collect();
+        m.add_row(&v[..4]);
+        labels.push(v[4] as usize);
+    }
+    (m, labels)
+}
+
+const IRIS: &'static str = include_str!("iris.csv");
+
+/// Adds normally distributed noise with the standard deviation `noise` to
+/// all values of `m`.
+fn add_noise(m: &mut Vec<f64>, noise: f64, rng: &mut XorShiftRng) {
+
+    if noise > 0.0 {
+        let n = Normal::new(0.0, noise);
+        for x in m.iter_mut() {
+            *x += n.ind_sample(rng);
+        }
+    }
+}
+
+/// Generates two interleaving half circles in two dimensions.
+///
+/// The first `n / 2` examples form the upper half circle with label `0` and
+/// the remaining examples the lower half circle with label `1`. Normally
+/// distributed noise with the standard deviation `noise` is added to each
+/// coordinate. The classes are not linearly separable.
+///
+/// # Example
+///
+/// ```
+/// use rustml::datasets::*;
+///
+/// let (x, y) = make_moons(100, 0.1, [1, 2, 3, 4]);
+/// assert_eq!(x.rows(), 100);
+/// assert_eq!(y.iter().filter(|&&l| l == 1).count(), 50);
+/// ```
+pub fn make_moons(n: usize, noise: f64, seed: [u32; 4]) -> (Matrix<f64>, Vec<usize>) {
+
+    let n_out = n / 2;
+    let n_in = n - n_out;
+    let step = |i: usize, k: usize| if k > 1 { PI * i as f64 / (k - 1) as f64 } else { 0.0 };
+
+    let mut v = Vec::with_capacity(2 * n);
+    for i in 0..n_out {
+        let t = step(i, n_out);
+        v.push(t.cos());
+        v.push(t.sin());
+    }
+    for i in 0..n_in {
+        let t = step(i, n_in);
+        v.push(1.0 - t.cos());
+        v.push(0.5 - t.sin());
+    }
+    add_noise(&mut v, noise, &mut XorShiftRng::from_seed(seed));
+
+    let labels = (0..n).map(|i| if i < n_out { 0 } else { 1 }).collect();
+    (Matrix::from_vec(v, n, 2), labels)
+}
+
+/// Generates a small circle inside a large circle in two dimensions.
+///
+/// The first `n / 2` examples are on the outer circle with radius 1 and
+/// label `0` and the remaining examples on the inner circle with radius
+/// `factor` and label `1`. Normally distributed noise with the standard
+/// deviation `noise` is added to each coordinate.
+///
+/// Panics if `factor` is not in the interval [0, 1).
+pub fn make_circles(n: usize, noise: f64, factor: f64, seed: [u32; 4]) -> (Matrix<f64>, Vec<usize>) {
+
+    assert!(factor >= 0.0 && factor < 1.0, "Factor must be in [0, 1).");
+
+    let n_out = n / 2;
+    let n_in = n - n_out;
+
+    let mut v = Vec::with_capacity(2 * n);
+    for &(k, r) in &[(n_out, 1.0), (n_in, factor)] {
+        for i in 0..k {
+            let t = 2.0 * PI * i as f64 / k as f64;
+            v.push(r * t.cos());
+            v.push(r * t.sin());
+        }
+    }
+    add_noise(&mut v, noise, &mut XorShiftRng::from_seed(seed));
+
+    let labels = (0..n).map(|i| if i < n_out { 0 } else { 1 }).collect();
+    (Matrix::from_vec(v, n, 2), labels)
+}
+
+/// Generates isotropic Gaussian blobs for clustering and classification.
+///
+/// Each row of `centers` is the center of a blob. The `n` examples are
+/// distributed as evenly as possible over the blobs and each coordinate is
+/// normally distributed around the center with the standard deviation
+/// `std`. The label of an example is the index of its blob. The examples
+/// are ordered by blob.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::datasets::*;
+///
+/// # fn main() {
+/// let (x, y) = make_blobs(10, &mat![0.0, 0.0; 5.0, 5.0; -5.0, 5.0], 0.5, [1, 2, 3, 4]);
+/// assert_eq!(x.rows(), 10);
+/// assert_eq!(y, vec![0, 0, 0, 0, 1, 1, 1, 2, 2, 2]);
+/// # }
+/// ```
+pub fn make_blobs(n: usize, centers: &Matrix<f64>, std: f64, seed: [u32; 4]) -> (Matrix<f64>, Vec<usize>) {
+
+    assert!(centers.rows() > 0, "At least one center is required.");
+
+    let k = centers.rows();
+    let mut v = Vec::with_capacity(n * centers.cols());
+    let mut labels = Vec::with_capacity(n);
+    for (i, c) in centers.row_iter().enumerate() {
+        let m = n / k + if i < n % k { 1 } else { 0 };
+        for _ in 0..m {
+            v.extend_from_slice(c);
+            labels.push(i);
+        }
+    }
+    add_noise(&mut v, std, &mut XorShiftRng::from_seed(seed));
+    (Matrix::from_vec(v, n, centers.cols()), labels)
+}
+
+/// Generates a random linear regression problem.
+///
+/// The features are drawn from a standard normal distribution. The target
+/// is a linear combination of the first `informative` features with random
+/// coefficients in the interval [0, 100) plus normally distributed noise
+/// with the standard deviation `noise`. The coefficients of the remaining
+/// features are zero.
+///
+/// Returns the features, the targets and the coefficients.
+///
+/// Panics if `informative > features`.
+///
+/// # Example
+///
+/// ```
+/// use rustml::datasets::*;
+///
+/// let (x, y, coef) = make_regression(100, 5, 2, 0.0, [1, 2, 3, 4]);
+/// assert_eq!((x.rows(), x.cols()), (100, 5));
+/// assert_eq!(y.len(), 100);
+/// assert_eq!(&coef[2..], &[0.0, 0.0, 0.0]);
+/// ```
+pub fn make_regression(n: usize, features: usize, informative: usize, noise: f64, seed: [u32; 4])
+    -> (Matrix<f64>, Vec<f64>, Vec<f64>) {
+
+    assert!(informative <= features, "Number of informative features must not exceed the number of features.");
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let normal = Normal::new(0.0, 1.0);
+    let x: Vec<f64> = (0..n * features).map(|_| normal.ind_sample(&mut rng)).collect();
+    let coef: Vec<f64> = (0..features)
+        .map(|i| if i < informative { 100.0 * rng.gen::<f64>() } else { 0.0 })
+        .collect();
+
+    let mut y: Vec<f64> = x.chunks(features.max(1)).take(n)
+        .map(|row| row.iter().zip(coef.iter()).fold(0.0, |acc, (a, b)| acc + a * b))
+        .collect();
+    y.resize(n, 0.0);
+    add_noise(&mut y, noise, &mut rng);
+    (Matrix::from_vec(x, n, features), y, coef)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "download")]
+    extern crate flate2;
+
+    use super::*;
+    use io::GzipData;
+
+    #[test]
+    fn test_from_high_endian() {
+
+        let a = [1, 2, 3];
+        assert_eq!(
+            MnistDigits::from_high_endian(&a), 
+            (1 * 256 + 2) * 256 + 3
+        );
+
+        let b = [255, 254, 253, 252];
+        assert_eq!(
+            MnistDigits::from_high_endian(&b), 
+            ((255 * 256 + 254) * 256 + 253) * 256 + 252
+        );
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_is_valid_file() {
+
+        use std::io::Write;
+        use self::flate2::Compression;
+        use self::flate2::write::GzEncoder;
+
+        // file with three labels
+        let path = ::std::env::temp_dir().join("rustml_test-labels-idx1-ubyte.gz");
+        let mut e = GzEncoder::new(File::create(&path).unwrap(), Compression::Default);
+        e.write_all(&[0, 0, 8, 1, 0, 0, 0, 3, 1, 2, 3]).unwrap();
+        e.finish().unwrap();
+        assert!(MnistDigits::is_valid_file(&path, 3));
+        assert!(!MnistDigits::is_valid_file(&path, 4));
+
+        // the magic number of images
+        let path = ::std::env::temp_dir().join("rustml_test-images-idx3-ubyte.gz");
+        let mut e = GzEncoder::new(File::create(&path).unwrap(), Compression::Default);
+        e.write_all(&[0, 0, 8, 1, 0, 0, 0, 3, 1, 2, 3]).unwrap();
+        e.finish().unwrap();
+        assert!(!MnistDigits::is_valid_file(&path, 3));
+        assert!(!MnistDigits::is_valid_file(Path::new("datasets/testing/hello_world.gz"), 0));
+    }
+
+    #[test]
+    fn test_read_u32() {
+
+        let mut gz = GzipData::from_buf(vec![1, 2, 3]);
+        assert!(MnistDigits::read_u32(&mut gz).is_err());
+
+        gz = GzipData::from_buf(vec![1, 2, 3, 4]);
+        assert_eq!(
+            MnistDigits::read_u32(&mut gz).unwrap(), 
+            ((256 * 1 + 2) * 256 + 3) * 256 + 4
+        );
+    }
+
+    #[test]
+    fn test_idx() {
+
+        // 2x3 array of signed 16-bit integers
+        let buf = [0, 0, 0x0B, 2, 0, 0, 0, 2, 0, 0, 0, 3, 0, 1, 0, 2, 0, 3, 255, 255, 1, 0, 0, 0];
+        let idx = Idx::parse(&buf).unwrap();
+        assert_eq!(idx.dims(), &[2, 3]);
+        let m = idx.into_matrix();
+        assert_eq!(m.rows(), 2);
+        assert_eq!(m.row(1).unwrap(), &[-1.0, 256.0, 0.0]);
+
+        // 1-dimensional array of doubles
+        let mut buf = vec![0, 0, 0x0E, 1, 0, 0, 0, 1];
+        buf.extend_from_slice(&[0x3F, 0xF8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Idx::parse(&buf).unwrap().data(), &[1.5]);
+
+        assert!(Idx::parse(&[0, 0, 0x08, 1, 0, 0, 0, 2, 1]).is_err());
+        assert!(Idx::parse(&[0, 0, 0x0A, 1, 0, 0, 0, 0]).is_err());
+        assert!(Idx::parse(&[1, 0, 0x08, 1, 0, 0, 0, 0]).is_err());
+
+        let labels = Idx::from_file("datasets/mnist_digits/t10k-labels-idx1-ubyte.gz").unwrap();
+        assert_eq!(labels.dims(), &[10000]);
+        assert!(labels.data().iter().all(|&x| x >= 0.0 && x <= 9.0));
+    }
+
+    #[test]
+    fn test_load_normalized() {
+
+        let m = MnistDigits::load_normalized("datasets/mnist_digits").unwrap();
+        assert_eq!(m.test_x.rows(), 10000);
+        assert_eq!(m.test_x.cols(), 784);
+        assert_eq!(m.test_y.len(), 10000);
+        assert_eq!(m.train_x.rows(), m.train_y.len());
+        assert!(m.test_x.buf().iter().all(|&x| x >= 0.0 && x <= 1.0));
+        assert!(m.test_x.buf().iter().any(|&x| x == 1.0));
+        assert!(MnistDigits::load_normalized("datasets/testing").is_err());
+    }
+
+    #[test]
+    fn test_iris() {
+
+        let (x, y) = iris();
+        assert_eq!((x.rows(), x.cols()), (150, 4));
+        for c in 0..3 {
+            assert_eq!(y.iter().filter(|&&l| l == c).count(), 50);
+        }
+        assert_eq!(x.row(0).unwrap(), &[5.1, 3.5, 1.4, 0.2]);
+        assert_eq!(x.row(149).unwrap(), &[5.9, 3.0, 5.1, 1.8]);
+        let sum = x.col_iter().map(|c| c.iter().fold(0.0, |acc, v| acc + v)).collect::<Vec<f64>>();
+        for (a, b) in sum.iter().zip([876.5, 458.6, 563.7, 179.9].iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_make_moons_circles() {
+
+        let (x, y) = make_moons(7, 0.0, [1, 2, 3, 4]);
+        assert_eq!(y, vec![0, 0, 0, 1, 1, 1, 1]);
+        assert!((x.get(0, 0).unwrap() - 1.0).abs() < 1e-12);
+        assert!((x.get(3, 1).unwrap() - 0.5).abs() < 1e-12);
+        assert!((x.get(6, 0).unwrap() - 2.0).abs() < 1e-12);
+
+        let (x, y) = make_circles(8, 0.0, 0.5, [1, 2, 3, 4]);
+        for (row, &l) in x.row_iter().zip(y.iter()) {
+            let r = (row[0] * row[0] + row[1] * row[1]).sqrt();
+            assert!((r - if l == 0 { 1.0 } else { 0.5 }).abs() < 1e-12);
+        }
+
+        // same seed yields the same noise
+        let (a, _) = make_moons(20, 0.2, [1, 2, 3, 4]);
+        let (b, _) = make_moons(20, 0.2, [1, 2, 3, 4]);
+        let (c, _) = make_moons(20, 0.2, [4, 3, 2, 1]);
+        assert_eq!(a, b);
+        assert!(a != c);
+    }
+
+    #[test]
+    fn test_make_blobs() {
+
+        let centers = mat![0.0, 0.0; 100.0, 100.0];
+        let (x, y) = make_blobs(200, &centers, 1.0, [1, 2, 3, 4]);
+        assert_eq!((x.rows(), x.cols()), (200, 2));
+        for (row, &l) in x.row_iter().zip(y.iter()) {
+            assert!((row[0] - centers.get(l, 0).unwrap()).abs() < 10.0);
+        }
+    }
+
+    #[test]
+    fn test_make_regression() {
+
+        let (x, y, coef) = make_regression(50, 4, 3, 0.0, [1, 2, 3, 4]);
+        assert_eq!(coef[3], 0.0);
+        assert!(coef[..3].iter().all(|&c| c > 0.0 && c < 100.0));
+        for (row, t) in x.row_iter().zip(y.iter()) {
+            let p = row.iter().zip(coef.iter()).fold(0.0, |acc, (a, b)| acc + a * b);
+            assert!((p - t).abs() < 1e-9);
+        }
+        let (_, y2, _) = make_regression(50, 4, 3, 1.0, [1, 2, 3, 4]);
+        assert!(y.iter().zip(y2.iter()).any(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn test_normal_data() {
+        let n = normal_builder([1,2,3,4]).add(0.0, 2.0).add(1.0, 1.0);
+
+        let data = n.take(5).collect::<Vec<Vec<f64>>>();
+        assert_eq!(data.len(), 5);
+        assert_eq!(data[0].len(), 2);
+    }
+
+    #[test]
+    fn test_mixture() {
+
+        let seed = [1, 2, 3, 4];
+        let m = mixture_builder()
+            .add(3, normal_builder(seed).add(1.0, 0.5).add(2.0, 1.0))
+            .add(5, normal_builder(seed).add(3.0, 0.5).add(4.0, 1.0))
+            .add(8, normal_builder(seed).add(2.0, 0.5).add(7.0, 1.0))
+            .as_matrix();
+        assert_eq!(m.rows(), 16);
+        assert_eq!(m.cols(), 3);
+        // TODO more tests
+    }
+
+    #[test]
+    fn test_mixture_components() {
+
+        use distributions::{Gamma, Poisson};
+
+        let m = mixture_builder()
+            .add_component(1.0, normal_builder([1, 1, 1, 1]).add(-10.0, 0.1))
+            .add_component(3.0, Gamma::new(2.0, 1.0))
+            .add_component(4.0, Poisson::new(50.0));
+        assert_eq!(m.weights(), vec![0.125, 0.375, 0.5]);
+
+        let (x, y) = m.sample(4000, [1, 2, 3, 4]);
+        assert_eq!((x.rows(), x.cols()), (4000, 1));
+        for (i, &l) in y.iter().enumerate() {
+            let v = *x.get(i, 0).unwrap();
+            match l {
+                0 => assert!(v < -9.0),
+                1 => assert!(v > 0.0 && v < 30.0),
+                _ => assert!(v > 20.0 && v == v.floor())
+            }
+        }
+        let count = |k| y.iter().filter(|&&l| l == k).count() as f64 / 4000.0;
+        assert!((count(0) - 0.125).abs() < 0.02);
+        assert!((count(2) - 0.5).abs() < 0.03);
+
+        // deterministic for the same seed
+        assert_eq!(m.sample(10, [5, 6, 7, 8]), m.sample(10, [5, 6, 7, 8]));
+    }
+
+}
@@ -0,0 +1,364 @@
+//! Module to discretize continuous features into bins.
+//!
+//! The [`KBinsDiscretizer`](struct.KBinsDiscretizer.html) divides the range
+//! of each column into a number of intervals (bins). Each value is then
+//! replaced by the index of the bin it falls into (ordinal encoding) or by
+//! a one-hot encoded vector with one element per bin.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::discretization::*;
+//!
+//! # fn main() {
+//! let m = mat![
+//!     0.0, 10.0;
+//!     1.0, 20.0;
+//!     2.0, 30.0;
+//!     4.0, 40.0
+//! ];
+//! let d = KBinsDiscretizer::fit(&m, 2, BinStrategy::Uniform, BinEncoding::Ordinal);
+//! assert_eq!(d.edges(0), &[0.0, 2.0, 4.0]);
+//! assert_eq!(d.transform(&m), mat![0.0, 0.0; 0.0, 0.0; 1.0, 1.0; 1.0, 1.0]);
+//!
+//! let d = KBinsDiscretizer::fit(&m, 2, BinStrategy::Uniform, BinEncoding::OneHot);
+//! assert_eq!(d.transform(&mat![3.0, 12.0]), mat![0.0, 1.0, 1.0, 0.0]);
+//! # }
+//! ```
+extern crate num;
+
+use std::cmp::Ordering;
+use self::num::traits::{Float, NumCast};
+
+use matrix::Matrix;
+use math::Quantile;
+use io::model::{Model, Encoder, Decoder};
+
+/// Determines how the edges of the bins are computed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinStrategy {
+    /// All bins of a column have the same width.
+    Uniform,
+    /// All bins of a column contain (approximately) the same number of
+    /// values of the training set.
+    Quantile,
+    /// The values of a column are clustered with a one dimensional k-means.
+    /// The edges are in the middle between two neighbouring cluster
+    /// centers.
+    KMeans
+}
+
+/// Determines how the bin of a value is represented.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinEncoding {
+    /// The value is replaced by the index of its bin.
+    Ordinal,
+    /// The value is replaced by a vector with one element per bin. The
+    /// element of the bin the value falls into is one, all other elements
+    /// are zero.
+    OneHot
+}
+
+/// Discretizes each column of a matrix into a number of bins.
+///
+/// For each column the edges `e_0 < e_1 < ... < e_k` of the `k` bins are
+/// computed during `fit`. A value `x` belongs to bin `i` if
+/// `e_i <= x < e_(i+1)`. The last bin also contains the value `e_k`. Values
+/// outside of the range seen during `fit` are put into the first or the last
+/// bin respectively.
+///
+/// A column might get fewer bins than requested, e.g. if the column
+/// contains only a few distinct values and quantiles are used. A column in
+/// which all values are equal gets exactly one bin.
+#[derive(Clone, Debug)]
+pub struct KBinsDiscretizer<T> {
+    edges: Vec<Vec<T>>,
+    encoding: BinEncoding
+}
+
+impl <T: Float> KBinsDiscretizer<T> {
+
+    /// Computes the edges of `n_bins` bins for each column of `m`.
+    ///
+    /// Panics if `n_bins` is zero or if `m` has no rows.
+    pub fn fit(m: &Matrix<T>, n_bins: usize, strategy: BinStrategy, encoding: BinEncoding) -> KBinsDiscretizer<T>
+        where Vec<T>: Quantile<T> {
+
+        assert!(n_bins > 0, "Number of bins must be at least 1.");
+        assert!(m.rows() > 0, "Matrix must not be empty.");
+
+        let edges = m.col_iter()
+            .map(|mut c| {
+                c.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                let e = match strategy {
+                    BinStrategy::Uniform => uniform_edges(&c, n_bins),
+                    BinStrategy::Quantile => quantile_edges(&c, n_bins),
+                    BinStrategy::KMeans => kmeans_edges(&c, n_bins)
+                };
+                dedup(e)
+            })
+            .collect();
+
+        KBinsDiscretizer {
+            edges: edges,
+            encoding: encoding
+        }
+    }
+
+    /// Computes the edges and returns the discretizer together with the
+    /// transformed matrix.
+    pub fn fit_transform(m: &Matrix<T>, n_bins: usize, strategy: BinStrategy, encoding: BinEncoding)
+        -> (KBinsDiscretizer<T>, Matrix<T>)
+        where Vec<T>: Quantile<T> {
+
+        let d = KBinsDiscretizer::fit(m, n_bins, strategy, encoding);
+        let r = d.transform(m);
+        (d, r)
+    }
+
+    /// Returns the edges of the bins of the given column.
+    ///
+    /// Panics if the column does not exist.
+    pub fn edges(&self, col: usize) -> &[T] {
+        &self.edges[col]
+    }
+
+    /// Returns the number of bins of the given column.
+    ///
+    /// Panics if the column does not exist.
+    pub fn n_bins(&self, col: usize) -> usize {
+        self.edges[col].len() - 1
+    }
+
+    /// Returns the number of columns of a transformed matrix.
+    pub fn output_size(&self) -> usize {
+        match self.encoding {
+            BinEncoding::Ordinal => self.edges.len(),
+            BinEncoding::OneHot => (0..self.edges.len()).map(|i| self.n_bins(i)).sum()
+        }
+    }
+
+    /// Returns the index of the bin of the value `x` in column `col`.
+    pub fn bin(&self, col: usize, x: T) -> usize {
+
+        let e = &self.edges[col];
+        let n = e.len() - 1;
+        e[1..n].iter().take_while(|&&v| v <= x).count()
+    }
+
+    /// Replaces each value of `m` by its bin.
+    ///
+    /// Panics if the number of columns does not match the number of columns
+    /// used during `fit`.
+    pub fn transform(&self, m: &Matrix<T>) -> Matrix<T> {
+
+        assert!(m.cols() == self.edges.len(),
+            "Number of columns does not match the number of features.");
+
+        let cols = self.output_size();
+        let mut v = Vec::with_capacity(m.rows() * cols);
+        for row in m.row_iter() {
+            for (j, &x) in row.iter().enumerate() {
+                let b = self.bin(j, x);
+                match self.encoding {
+                    BinEncoding::Ordinal => v.push(cast(b)),
+                    BinEncoding::OneHot => {
+                        for i in 0..self.n_bins(j) {
+                            v.push(if i == b { T::one() } else { T::zero() });
+                        }
+                    }
+                }
+            }
+        }
+        Matrix::from_vec(v, m.rows(), cols)
+    }
+
+    /// Replaces each bin by the center of the bin.
+    ///
+    /// The matrix must have been transformed with this discretizer. For
+    /// one-hot encoded bins the bin with the largest value in each block is
+    /// used.
+    pub fn inverse_transform(&self, m: &Matrix<T>) -> Matrix<T> {
+
+        assert!(m.cols() == self.output_size(),
+            "Number of columns does not match the number of features.");
+
+        let mut v = Vec::with_capacity(m.rows() * self.edges.len());
+        for row in m.row_iter() {
+            let mut p = 0;
+            for (j, e) in self.edges.iter().enumerate() {
+                let b = match self.encoding {
+                    BinEncoding::Ordinal => {
+                        p += 1;
+                        row[j].to_usize().unwrap_or(0).min(self.n_bins(j) - 1)
+                    },
+                    BinEncoding::OneHot => {
+                        let n = self.n_bins(j);
+                        let block = &row[p..p + n];
+                        p += n;
+                        (0..n).fold(0, |best, i| if block[i] > block[best] { i } else { best })
+                    }
+                };
+                v.push((e[b] + e[b + 1]) / cast(2));
+            }
+        }
+        Matrix::from_vec(v, m.rows(), self.edges.len())
+    }
+}
+
+fn cast<T: Float, N: NumCast>(x: N) -> T {
+    T::from(x).unwrap()
+}
+
+fn uniform_edges<T: Float>(c: &[T], n_bins: usize) -> Vec<T> {
+
+    let (min, max) = (c[0], c[c.len() - 1]);
+    let w = (max - min) / cast(n_bins);
+    (0..n_bins + 1)
+        .map(|i| if i == n_bins { max } else { min + w * cast(i) })
+        .collect()
+}
+
+fn quantile_edges<T: Float>(c: &[T], n_bins: usize) -> Vec<T>
+    where Vec<T>: Quantile<T> {
+
+    let v = c.to_vec();
+    (0..n_bins + 1)
+        .map(|i| v.quantile(cast::<T, _>(i) / cast(n_bins)))
+        .collect()
+}
+
+fn kmeans_edges<T: Float>(c: &[T], n_bins: usize) -> Vec<T> {
+
+    // initialize the centers with the centers of uniform bins
+    let u = uniform_edges(c, n_bins);
+    let mut centers: Vec<T> = u.windows(2).map(|w| (w[0] + w[1]) / cast(2)).collect();
+
+    for _ in 0..100 {
+        let mut sums = vec![T::zero(); n_bins];
+        let mut counts = vec![0; n_bins];
+        for &x in c {
+            let k = (0..n_bins).fold(0, |best, i|
+                if (x - centers[i]).abs() < (x - centers[best]).abs() { i } else { best });
+            sums[k] = sums[k] + x;
+            counts[k] += 1;
+        }
+        let next: Vec<T> = (0..n_bins)
+            .map(|i| if counts[i] > 0 { sums[i] / cast(counts[i]) } else { centers[i] })
+            .collect();
+        if next == centers {
+            break;
+        }
+        centers = next;
+    }
+    centers.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let mut e = vec![c[0]];
+    e.extend(centers.windows(2).map(|w| (w[0] + w[1]) / cast(2)));
+    e.push(c[c.len() - 1]);
+    e
+}
+
+/// Removes edges that are not larger than their predecessor. At least two
+/// edges (i.e. one bin) are kept.
+fn dedup<T: Float>(e: Vec<T>) -> Vec<T> {
+
+    let last = e[e.len() - 1];
+    let mut r = vec![e[0]];
+    for x in e {
+        if x > r[r.len() - 1] {
+            r.push(x);
+        }
+    }
+    if r.len() == 1 {
+        r.push(last);
+    }
+    r
+}
+
+impl Model for KBinsDiscretizer<f64> {
+
+    fn tag() -> &'static str { "rustml.discretization.KBinsDiscretizer" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.edges.len());
+        for v in &self.edges {
+            e.f64s(v);
+        }
+        e.bool(self.encoding == BinEncoding::OneHot);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<KBinsDiscretizer<f64>, String> {
+
+        let n = try!(d.usize());
+        let edges = try!((0..n).map(|_| d.f64s()).collect());
+        let encoding = if try!(d.bool()) { BinEncoding::OneHot } else { BinEncoding::Ordinal };
+        Ok(KBinsDiscretizer { edges: edges, encoding: encoding })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_uniform() {
+
+        let m = mat![0.0; 1.0; 2.0; 3.0; 9.0];
+        let d = KBinsDiscretizer::fit(&m, 3, BinStrategy::Uniform, BinEncoding::Ordinal);
+        assert_eq!(d.edges(0), &[0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(d.transform(&m), mat![0.0; 0.0; 0.0; 1.0; 2.0]);
+
+        // values outside of the range
+        assert_eq!(d.transform(&mat![-5.0; 20.0]), mat![0.0; 2.0]);
+        assert_eq!(d.inverse_transform(&mat![0.0; 2.0]), mat![1.5; 7.5]);
+    }
+
+    #[test]
+    fn test_quantile() {
+
+        let m = mat![1.0; 2.0; 3.0; 4.0; 5.0; 100.0];
+        let d = KBinsDiscretizer::fit(&m, 2, BinStrategy::Quantile, BinEncoding::Ordinal);
+        assert_eq!(d.edges(0), &[1.0, 3.5, 100.0]);
+        assert_eq!(d.transform(&m), mat![0.0; 0.0; 0.0; 1.0; 1.0; 1.0]);
+
+        // duplicate edges are removed
+        let m = mat![1.0; 1.0; 1.0; 1.0; 2.0];
+        let d = KBinsDiscretizer::fit(&m, 4, BinStrategy::Quantile, BinEncoding::Ordinal);
+        assert_eq!(d.edges(0), &[1.0, 2.0]);
+        assert_eq!(d.n_bins(0), 1);
+    }
+
+    #[test]
+    fn test_kmeans() {
+
+        let m = mat![0.0; 1.0; 2.0; 10.0; 11.0; 12.0];
+        let d = KBinsDiscretizer::fit(&m, 2, BinStrategy::KMeans, BinEncoding::Ordinal);
+        assert_eq!(d.edges(0), &[0.0, 6.0, 12.0]);
+        assert_eq!(d.transform(&m), mat![0.0; 0.0; 0.0; 1.0; 1.0; 1.0]);
+    }
+
+    #[test]
+    fn test_one_hot() {
+
+        let m = mat![
+            0.0, 5.0;
+            1.0, 5.0;
+            2.0, 5.0
+        ];
+        let (d, r) = KBinsDiscretizer::fit_transform(&m, 2, BinStrategy::Uniform, BinEncoding::OneHot);
+        assert_eq!(d.n_bins(1), 1);
+        assert_eq!(d.output_size(), 3);
+        assert_eq!(r, mat![
+            1.0, 0.0, 1.0;
+            0.0, 1.0, 1.0;
+            0.0, 1.0, 1.0
+        ]);
+        assert_eq!(d.inverse_transform(&r), mat![0.5, 5.0; 1.5, 5.0; 1.5, 5.0]);
+    }
+}
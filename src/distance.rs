@@ -1,12 +1,29 @@
 //! Functions to compute the distance between vectors.
 
 extern crate libc;
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 
+use std::collections::BTreeSet;
 use self::libc::{c_int, c_double, c_float};
+use self::rand::{Rng, SeedableRng, XorShiftRng};
 use matrix::*;
 use norm::{L2Norm, Norm};
 use blas::{cblas_daxpy, cblas_saxpy};
 use geometry::Point2D;
+use ops::MatrixMatrixOps;
+use math::{Mean, Dimension, Normalization};
+use linalg::{covariance, cholesky};
+#[cfg(feature = "rayon")]
+use model_selection::row_batches;
+#[cfg(feature = "rayon")]
+use self::rayon::prelude::*;
+
+/// Number of rows of the blocks into which
+/// [`par_pairwise_distances`](fn.par_pairwise_distances.html) splits the
+/// first matrix.
+pub const BLOCK_ROWS: usize = 256;
 
 pub trait DistancePoint2D<T> {
     fn euclid(&self, other: &Point2D<T>) -> T;
@@ -129,6 +146,597 @@ pub fn all_pair_distances(m: &Matrix<f64>) -> Matrix<f64> {
     r
 }
 
+/// Metrics for [`pairwise_distances`](fn.pairwise_distances.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// Euclidean distance (L2 norm of the difference).
+    Euclidean,
+    /// Squared Euclidean distance.
+    SquaredEuclidean,
+    /// Sum of the absolute differences (L1 norm of the difference).
+    Manhattan,
+    /// One minus the cosine of the angle between both vectors. If one of the
+    /// vectors is zero the distance is one.
+    Cosine,
+    /// Largest absolute difference (maximum norm of the difference).
+    Chebyshev,
+    /// Minkowski distance with the given order `p >= 1`, i.e.
+    /// `(sum |a_i - b_i|^p)^(1/p)`.
+    Minkowski(f64)
+}
+
+impl Metric {
+
+    /// Computes the distance between the vectors `a` and `b`.
+    ///
+    /// Panics if the vectors have a different length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::distance::Metric;
+    ///
+    /// let a = [1.0, 2.0];
+    /// let b = [4.0, 6.0];
+    /// assert_eq!(Metric::Euclidean.distance(&a, &b), 5.0);
+    /// assert_eq!(Metric::Manhattan.distance(&a, &b), 7.0);
+    /// assert_eq!(Metric::Chebyshev.distance(&a, &b), 4.0);
+    /// ```
+    pub fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+
+        assert!(a.len() == b.len(), "Vectors must have the same length.");
+
+        let d = a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs());
+        match *self {
+            Metric::Euclidean => d.fold(0.0, |acc, x| acc + x * x).sqrt(),
+            Metric::SquaredEuclidean => d.fold(0.0, |acc, x| acc + x * x),
+            Metric::Manhattan => d.fold(0.0, |acc, x| acc + x),
+            Metric::Chebyshev => d.fold(0.0, |acc: f64, x| acc.max(x)),
+            Metric::Minkowski(p) => d.fold(0.0, |acc, x| acc + x.powf(p)).powf(1.0 / p),
+            Metric::Cosine => {
+                let dot = a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + x * y);
+                cosine(dot, L2Norm::compute(a), L2Norm::compute(b))
+            }
+        }
+    }
+}
+
+fn cosine(dot: f64, na: f64, nb: f64) -> f64 {
+    if na == 0.0 || nb == 0.0 { 1.0 } else { 1.0 - dot / (na * nb) }
+}
+
+/// Computes the distances between each row of `a` and each row of `b`.
+///
+/// The element in row `i` and column `j` of the result is the distance
+/// between row `i` of `a` and row `j` of `b`.
+///
+/// For the (squared) Euclidean distance and the cosine distance the dot
+/// products of all pairs of rows are computed with a single BLAS matrix
+/// multiplication, i.e. the squared Euclidean distance is computed as
+/// `|a|^2 + |b|^2 - 2 a b`. This is much faster for large matrices but might
+/// be slightly less accurate than computing the differences directly;
+/// negative values due to rounding errors are set to zero.
+///
+/// Panics if the number of columns of `a` and `b` differ.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::distance::*;
+///
+/// # fn main() {
+/// let a = mat![0.0, 0.0; 1.0, 1.0];
+/// let b = mat![3.0, 4.0];
+/// let d = pairwise_distances(&a, &b, Metric::Manhattan);
+/// assert_eq!(d, mat![7.0; 5.0]);
+/// # }
+/// ```
+pub fn pairwise_distances(a: &Matrix<f64>, b: &Matrix<f64>, metric: Metric) -> Matrix<f64> {
+
+    assert!(a.cols() == b.cols(), "Number of columns must be equal.");
+
+    if a.rows() == 0 || b.rows() == 0 {
+        return Matrix::from_vec(vec![], a.rows(), b.rows());
+    }
+
+    match metric {
+        Metric::Euclidean | Metric::SquaredEuclidean | Metric::Cosine => {
+            let dot = a.mul(b, false, true);
+            // squared norms of the rows
+            let sq = |r: &[f64]| r.iter().fold(0.0, |acc, x| acc + x * x);
+            let na: Vec<f64> = a.row_iter().map(&sq).collect();
+            let nb: Vec<f64> = b.row_iter().map(&sq).collect();
+            let n = b.rows();
+            Matrix::from_vec(
+                dot.iter().enumerate()
+                    .map(|(k, &d)| {
+                        let (x, y) = (na[k / n], nb[k % n]);
+                        match metric {
+                            Metric::Cosine => cosine(d, x.sqrt(), y.sqrt()),
+                            _ => {
+                                let s = (x + y - 2.0 * d).max(0.0);
+                                if metric == Metric::Euclidean { s.sqrt() } else { s }
+                            }
+                        }
+                    })
+                    .collect(),
+                a.rows(), b.rows()
+            )
+        },
+        _ => {
+            let mut v = Vec::with_capacity(a.rows() * b.rows());
+            for r in a.row_iter() {
+                v.extend(b.row_iter().map(|q| metric.distance(r, q)));
+            }
+            Matrix::from_vec(v, a.rows(), b.rows())
+        }
+    }
+}
+
+/// Computes the distances between each row of `a` and each row of `b` like
+/// [`pairwise_distances`](fn.pairwise_distances.html) but in parallel on
+/// the global thread pool of rayon (requires the feature `rayon`).
+///
+/// The rows of `a` are split into blocks of
+/// [`BLOCK_ROWS`](constant.BLOCK_ROWS.html) rows. The distances of each
+/// block to all rows of `b` are computed independently, i.e. for the
+/// (squared) Euclidean and the cosine distance each block needs one BLAS
+/// matrix multiplication. The result is the same as the result of
+/// `pairwise_distances`.
+///
+/// Panics if the number of columns of `a` and `b` differ.
+#[cfg(feature = "rayon")]
+pub fn par_pairwise_distances(a: &Matrix<f64>, b: &Matrix<f64>, metric: Metric) -> Matrix<f64> {
+
+    assert!(a.cols() == b.cols(), "Number of columns must be equal.");
+
+    if a.rows() <= BLOCK_ROWS || b.rows() == 0 {
+        return pairwise_distances(a, b, metric);
+    }
+    let blocks: Vec<Matrix<f64>> = row_batches(a, BLOCK_ROWS).par_iter()
+        .map(|x| pairwise_distances(x, b, metric))
+        .collect();
+    let mut v = Vec::with_capacity(a.rows() * b.rows());
+    for m in blocks {
+        v.extend(m.into_vec());
+    }
+    Matrix::from_vec(v, a.rows(), b.rows())
+}
+
+/// Computes the Hamming distance between two sequences, i.e. the number of
+/// positions at which the elements differ.
+///
+/// Returns `None` if the sequences have a different length.
+///
+/// # Example
+///
+/// ```
+/// use rustml::distance::hamming;
+///
+/// assert_eq!(hamming(b"karolin", b"kathrin"), Some(3));
+/// assert_eq!(hamming(&[1, 0, 1], &[1, 0]), None);
+/// ```
+pub fn hamming<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize> {
+
+    if a.len() != b.len() {
+        return None;
+    }
+    Some(a.iter().zip(b.iter()).filter(|&(x, y)| x != y).count())
+}
+
+/// Computes the Jaccard distance between the sets of elements of `a` and
+/// `b`, i.e. one minus the size of the intersection divided by the size of
+/// the union. Duplicates are ignored. The distance between two empty sets
+/// is zero.
+///
+/// # Example
+///
+/// ```
+/// use rustml::distance::jaccard;
+///
+/// assert_eq!(jaccard(&["a", "b", "c"], &["b", "c", "d"]), 0.5);
+/// ```
+pub fn jaccard<T: Ord>(a: &[T], b: &[T]) -> f64 {
+
+    let sa: BTreeSet<&T> = a.iter().collect();
+    let sb: BTreeSet<&T> = b.iter().collect();
+    let union = sa.union(&sb).count();
+    if union == 0 {
+        return 0.0;
+    }
+    1.0 - sa.intersection(&sb).count() as f64 / union as f64
+}
+
+/// Computes the Jaccard distance between two bit vectors, i.e. the number
+/// of positions where exactly one of the bits is set divided by the number
+/// of positions where at least one of the bits is set. The distance between
+/// two vectors without any set bit is zero.
+///
+/// Returns `None` if the vectors have a different length.
+///
+/// # Example
+///
+/// ```
+/// use rustml::distance::jaccard_bits;
+///
+/// let a = [true, true, false, false];
+/// let b = [true, false, true, false];
+/// assert_eq!(jaccard_bits(&a, &b), Some(2.0 / 3.0));
+/// ```
+pub fn jaccard_bits(a: &[bool], b: &[bool]) -> Option<f64> {
+
+    if a.len() != b.len() {
+        return None;
+    }
+    let (diff, union) = a.iter().zip(b.iter())
+        .fold((0, 0), |(d, u), (&x, &y)| (d + (x != y) as usize, u + (x || y) as usize));
+    if union == 0 {
+        Some(0.0)
+    } else {
+        Some(diff as f64 / union as f64)
+    }
+}
+
+/// Computes the Levenshtein (edit) distance between two strings, i.e. the
+/// minimum number of insertions, deletions and substitutions of characters
+/// which are required to transform `a` into `b`.
+///
+/// The distance is computed on Unicode scalar values with dynamic
+/// programming in O(nm) time and O(m) space where `n` and `m` are the
+/// number of characters of the strings.
+///
+/// # Example
+///
+/// ```
+/// use rustml::distance::levenshtein;
+///
+/// assert_eq!(levenshtein("kitten", "sitting"), 3);
+/// assert_eq!(levenshtein("", "abc"), 3);
+/// ```
+pub fn levenshtein(a: &str, b: &str) -> usize {
+
+    let b: Vec<char> = b.chars().collect();
+    // distances between the current prefix of a and all prefixes of b
+    let mut d: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = d[0];
+        d[0] = i + 1;
+        for j in 0..b.len() {
+            let sub = diag + (ca != b[j]) as usize;
+            diag = d[j + 1];
+            d[j + 1] = sub.min(d[j] + 1).min(diag + 1);
+        }
+    }
+    d[b.len()]
+}
+
+/// Computes the dynamic time warping (DTW) distance between two numeric
+/// sequences.
+///
+/// DTW aligns both sequences by repeating elements so that the sum of the
+/// absolute differences between the aligned elements is minimal, i.e.
+/// sequences which have the same shape but are shifted or stretched in time
+/// have a small distance.
+///
+/// With `window = Some(w)` the alignment is restricted to a Sakoe-Chiba band,
+/// i.e. element `i` of `a` can only be aligned with elements `j` of `b` with
+/// `|i - j| <= w`. This prevents pathological alignments and reduces the
+/// runtime from O(nm) to O(nw). If the lengths of the sequences differ by
+/// more than `w` the band is widened to this difference.
+///
+/// The distance between two empty sequences is zero, the distance between
+/// an empty and a non-empty sequence is infinite.
+///
+/// # Example
+///
+/// Since rows of a matrix have the same length `dtw` can be used with the
+/// k-nearest neighbour functions to classify time series.
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::distance::dtw;
+/// use rustml::knn::classify;
+///
+/// # fn main() {
+/// let a = [0.0, 1.0, 2.0, 1.0, 0.0, 0.0];
+/// let b = [0.0, 0.0, 1.0, 2.0, 1.0, 0.0];
+/// assert_eq!(dtw(&a, &b, None), 0.0);
+/// assert_eq!(dtw(&a, &b, Some(0)), 4.0);
+///
+/// let series = mat![
+///     0.0, 1.0, 2.0, 1.0, 0.0, 0.0;
+///     2.0, 2.0, 0.0, 0.0, 2.0, 2.0
+/// ];
+/// let labels = vec!["peak", "valley"];
+/// let l = classify(&series, &labels, &b, 1, |p, q| dtw(p, q, Some(2)));
+/// assert_eq!(l, "peak");
+/// # }
+/// ```
+pub fn dtw(a: &[f64], b: &[f64], window: Option<usize>) -> f64 {
+
+    let (n, m) = (a.len(), b.len());
+    if n == 0 || m == 0 {
+        return if n == m { 0.0 } else { ::std::f64::INFINITY };
+    }
+    let w = window.map(|w| w.max(if n > m { n - m } else { m - n })).unwrap_or(n.max(m));
+
+    // cost of the best alignment of the previous and the current prefix of
+    // a with all prefixes of b; index 0 is the empty prefix
+    let inf = ::std::f64::INFINITY;
+    let mut prev = vec![inf; m + 1];
+    let mut cur = vec![inf; m + 1];
+    prev[0] = 0.0;
+    for i in 1..n + 1 {
+        for x in cur.iter_mut() {
+            *x = inf;
+        }
+        let lo = if i > w { i - w } else { 1 };
+        let hi = (i + w).min(m);
+        for j in lo..hi + 1 {
+            let best = prev[j - 1].min(prev[j]).min(cur[j - 1]);
+            cur[j] = (a[i - 1] - b[j - 1]).abs() + best;
+        }
+        ::std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[m]
+}
+
+/// Mahalanobis distance for a given covariance matrix `S`, i.e. the distance
+/// between `a` and `b` is `sqrt((a - b)^T S^-1 (a - b))`.
+///
+/// The inverse of the covariance matrix is never computed explicitly. Instead
+/// the Cholesky factor `L` of `S = L L^T` is stored and the distance is
+/// computed as the length of `z` where `L z = a - b`.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::distance::Mahalanobis;
+///
+/// # fn main() {
+/// let m = Mahalanobis::new(&mat![4.0, 0.0; 0.0, 1.0]).unwrap();
+/// assert_eq!(m.distance(&[2.0, 0.0], &[0.0, 0.0]), 1.0);
+/// assert_eq!(m.distance(&[0.0, 2.0], &[0.0, 0.0]), 2.0);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mahalanobis {
+    l: Matrix<f64>
+}
+
+impl Mahalanobis {
+
+    /// Creates the distance for the covariance matrix `cov`.
+    ///
+    /// Returns `None` if the covariance matrix is not positive definite.
+    ///
+    /// Panics if `cov` is not a square matrix.
+    pub fn new(cov: &Matrix<f64>) -> Option<Mahalanobis> {
+        cholesky(cov).map(|l| Mahalanobis { l: l })
+    }
+
+    /// Creates the distance from the lower triangular Cholesky factor `l` of
+    /// the covariance matrix. Only the lower triangle of `l` is used.
+    ///
+    /// Panics if `l` is not a square matrix or if an element on its diagonal
+    /// is zero.
+    pub fn from_cholesky(l: &Matrix<f64>) -> Mahalanobis {
+
+        assert!(l.rows() == l.cols(), "Matrix must be a square matrix.");
+        assert!((0..l.rows()).all(|i| *l.get(i, i).unwrap() != 0.0),
+            "Diagonal of the Cholesky factor must not contain zeros.");
+        Mahalanobis { l: l.clone() }
+    }
+
+    /// Returns the lower triangular Cholesky factor of the covariance matrix.
+    pub fn cholesky(&self) -> &Matrix<f64> {
+        &self.l
+    }
+
+    /// Returns the logarithm of the determinant of the covariance matrix.
+    pub fn log_det(&self) -> f64 {
+        (0..self.l.rows()).fold(0.0, |acc, i| acc + 2.0 * self.l.get(i, i).unwrap().abs().ln())
+    }
+
+    /// Computes the Mahalanobis distance between `a` and `b`.
+    ///
+    /// Panics if the length of the vectors does not match the dimension of
+    /// the covariance matrix.
+    pub fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+
+        let n = self.l.rows();
+        assert!(a.len() == n && b.len() == n,
+            "Length of the vectors does not match the dimension of the covariance matrix.");
+
+        // forward substitution to solve L z = a - b
+        let l = self.l.buf();
+        let mut z = vec![0.0; n];
+        for i in 0..n {
+            let s = (0..i).fold(a[i] - b[i], |acc, k| acc - l[i * n + k] * z[k]);
+            z[i] = s / l[i * n + i];
+        }
+        L2Norm::compute(&z)
+    }
+
+    /// Computes the Mahalanobis distance between each row of `x` and
+    /// `center`.
+    pub fn distances(&self, x: &Matrix<f64>, center: &[f64]) -> Vec<f64> {
+        x.row_iter().map(|r| self.distance(r, center)).collect()
+    }
+}
+
+/// Robust estimator of the location and covariance of a dataset based on the
+/// minimum covariance determinant (MCD).
+///
+/// The MCD estimator looks for the `h` examples whose covariance matrix has
+/// the smallest determinant and uses their mean and covariance as the
+/// estimate. Because outliers inflate the covariance matrix they are usually
+/// not part of this subset, i.e. the estimate is not affected by up to
+/// `n - h` outliers. The Mahalanobis distances to the robust location can
+/// therefore be used to detect outliers.
+///
+/// The subset is searched with the FastMCD algorithm: several random initial
+/// subsets are improved by concentration steps (C-steps) which replace the
+/// subset by the `h` examples with the smallest Mahalanobis distances until
+/// the subset does not change anymore. The covariance matrix is not rescaled
+/// for consistency with a normal distribution.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::distance::MinCovDet;
+///
+/// # fn main() {
+/// let x = mat![
+///     1.0, 2.0;
+///     2.0, 1.0;
+///     2.0, 3.0;
+///     3.0, 2.0;
+///     2.0, 2.0;
+///     50.0, -40.0
+/// ];
+/// let mcd = MinCovDet::fit(&x, 0.75, [1, 2, 3, 4]);
+/// assert_eq!(mcd.location(), &[2.0, 2.0]);
+/// assert!(!mcd.support().contains(&5));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MinCovDet {
+    location: Vec<f64>,
+    covariance: Matrix<f64>,
+    support: Vec<usize>
+}
+
+/// Number of random initial subsets of the FastMCD algorithm.
+const MCD_TRIALS: usize = 20;
+
+impl MinCovDet {
+
+    /// Computes the MCD estimate of the examples in the rows of `x` with a
+    /// subset of `h = ceil(support_fraction * n)` examples where `n` is the
+    /// number of rows. A fraction of `0.5` gives the highest robustness. The
+    /// covariance matrix is normalized by `h`. The random number generator
+    /// for the initial subsets is initialized with `seed`, which must not be
+    /// `[0, 0, 0, 0]`.
+    ///
+    /// Panics if `support_fraction` is not in `(0, 1]` or if `h` is smaller
+    /// than the number of columns plus one.
+    pub fn fit(x: &Matrix<f64>, support_fraction: f64, seed: [u32; 4]) -> MinCovDet {
+
+        assert!(support_fraction > 0.0 && support_fraction <= 1.0,
+            "Support fraction must be in (0, 1].");
+
+        let n = x.rows();
+        let p = x.cols();
+        let h = ((support_fraction * n as f64).ceil() as usize).min(n);
+        assert!(h > p, "Support must contain more examples than the number of columns.");
+
+        let mut rng = XorShiftRng::from_seed(seed);
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut best: Option<(f64, MinCovDet)> = None;
+
+        for _ in 0..MCD_TRIALS {
+            rng.shuffle(&mut perm);
+            // extend the initial subset of p + 1 examples until its covariance
+            // matrix is not singular
+            let mut k = p + 1;
+            let mut init = mcd_subset(x, &perm[..k]);
+            while init.0.is_none() && k < h {
+                k += 1;
+                init = mcd_subset(x, &perm[..k]);
+            }
+            let idx = match init {
+                (Some(ref m), ref mean, _) => nearest(x, m, mean, h),
+                _ => perm[..h].to_vec()
+            };
+            let (log_det, mcd) = c_steps(x, idx, h);
+            let better = match best {
+                Some((d, _)) => log_det < d,
+                None => true
+            };
+            if better {
+                best = Some((log_det, mcd));
+            }
+            // an exact fit cannot be improved
+            if log_det == ::std::f64::NEG_INFINITY {
+                break;
+            }
+        }
+        best.unwrap().1
+    }
+
+    /// Returns the robust location, i.e. the mean of the support.
+    pub fn location(&self) -> &[f64] {
+        &self.location
+    }
+
+    /// Returns the robust covariance matrix, i.e. the covariance matrix of
+    /// the support.
+    pub fn covariance(&self) -> &Matrix<f64> {
+        &self.covariance
+    }
+
+    /// Returns the (sorted) indexes of the rows used for the estimate.
+    pub fn support(&self) -> &[usize] {
+        &self.support
+    }
+
+    /// Computes the Mahalanobis distance between each row of `x` and the
+    /// robust location with respect to the robust covariance matrix.
+    ///
+    /// Returns `None` if the robust covariance matrix is singular, i.e. the
+    /// support lies in a hyperplane.
+    pub fn mahalanobis(&self, x: &Matrix<f64>) -> Option<Vec<f64>> {
+        Mahalanobis::new(&self.covariance).map(|m| m.distances(x, &self.location))
+    }
+}
+
+/// Returns the Mahalanobis distance, the mean and the covariance matrix of
+/// the given rows.
+fn mcd_subset(x: &Matrix<f64>, idx: &[usize]) -> (Option<Mahalanobis>, Vec<f64>, Matrix<f64>) {
+
+    let s = x.select_rows(idx);
+    let cov = covariance(&s, Normalization::N);
+    (Mahalanobis::new(&cov), s.mean(Dimension::Column), cov)
+}
+
+/// Returns the (sorted) indexes of the `h` rows closest to `mean`.
+fn nearest(x: &Matrix<f64>, m: &Mahalanobis, mean: &[f64], h: usize) -> Vec<usize> {
+
+    let d = m.distances(x, mean);
+    let mut idx: Vec<usize> = (0..x.rows()).collect();
+    idx.sort_by(|&i, &j| d[i].partial_cmp(&d[j]).unwrap());
+    idx.truncate(h);
+    idx.sort();
+    idx
+}
+
+fn c_steps(x: &Matrix<f64>, mut idx: Vec<usize>, h: usize) -> (f64, MinCovDet) {
+
+    for _ in 0..100 {
+        let (m, mean, cov) = mcd_subset(x, &idx);
+        let next = match m {
+            Some(ref m) => nearest(x, m, &mean, h),
+            None => idx.clone()
+        };
+        if next == idx {
+            let log_det = m.map(|m| m.log_det()).unwrap_or(::std::f64::NEG_INFINITY);
+            return (log_det, MinCovDet { location: mean, covariance: cov, support: idx });
+        }
+        idx = next;
+    }
+    let (m, mean, cov) = mcd_subset(x, &idx);
+    let log_det = m.map(|m| m.log_det()).unwrap_or(::std::f64::NEG_INFINITY);
+    (log_det, MinCovDet { location: mean, covariance: cov, support: idx })
+}
+
 #[cfg(test)]
 mod tests {
     use matrix::*;
@@ -168,6 +776,166 @@ mod tests {
         assert!(*r.get(2, 1).unwrap() - 17.0 <= 0.001);
     }
 
+    #[test]
+    fn test_metric() {
+
+        let a = [1.0, -2.0, 3.0];
+        let b = [2.0, 0.0, 0.0];
+
+        assert_eq!(Metric::SquaredEuclidean.distance(&a, &b), 14.0);
+        assert_eq!(Metric::Manhattan.distance(&a, &b), 6.0);
+        assert_eq!(Metric::Chebyshev.distance(&a, &b), 3.0);
+        assert!((Metric::Minkowski(2.0).distance(&a, &b) - 14.0f64.sqrt()).abs() < 1e-12);
+        assert!((Metric::Minkowski(3.0).distance(&a, &b) - 36.0f64.powf(1.0 / 3.0)).abs() < 1e-12);
+        assert!((Metric::Cosine.distance(&[1.0, 0.0], &[0.0, 2.0]) - 1.0).abs() < 1e-12);
+        assert!(Metric::Cosine.distance(&[1.0, 1.0], &[2.0, 2.0]).abs() < 1e-12);
+        assert_eq!(Metric::Cosine.distance(&[0.0, 0.0], &[2.0, 2.0]), 1.0);
+    }
+
+    #[test]
+    fn test_pairwise_distances() {
+
+        let a = mat![1.0, 2.0; 5.0, 12.0; 13.0, 27.0; 0.0, 0.0];
+        let b = mat![1.0, 2.0; -1.0, 4.0];
+
+        for &m in &[Metric::Euclidean, Metric::SquaredEuclidean, Metric::Cosine,
+                    Metric::Manhattan, Metric::Chebyshev, Metric::Minkowski(1.5)] {
+            let d = pairwise_distances(&a, &b, m);
+            assert_eq!(d.rows(), 4);
+            assert_eq!(d.cols(), 2);
+            for i in 0..4 {
+                for j in 0..2 {
+                    let e = m.distance(a.row(i).unwrap(), b.row(j).unwrap());
+                    assert!((d.get(i, j).unwrap() - e).abs() < 1e-9);
+                }
+            }
+        }
+        assert_eq!(*pairwise_distances(&a, &b, Metric::Euclidean).get(0, 0).unwrap(), 0.0);
+        assert_eq!(pairwise_distances(&Matrix::from_vec(vec![], 0, 2), &b, Metric::Euclidean).rows(), 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_pairwise_distances() {
+
+        let a = Matrix::from_vec((0..3 * (BLOCK_ROWS + 17)).map(|i| (i % 31) as f64 / 7.0).collect(), BLOCK_ROWS + 17, 3);
+        let b = Matrix::from_vec((0..30).map(|i| (i % 13) as f64 / 5.0).collect(), 10, 3);
+        for &m in &[Metric::Euclidean, Metric::Manhattan] {
+            assert_eq!(par_pairwise_distances(&a, &b, m), pairwise_distances(&a, &b, m));
+        }
+    }
+
+    #[test]
+    fn test_hamming() {
+
+        assert_eq!(hamming(&[1, 0, 1, 1], &[1, 1, 1, 0]), Some(2));
+        assert_eq!(hamming::<u8>(&[], &[]), Some(0));
+        assert_eq!(hamming(&['a'], &['a', 'b']), None);
+    }
+
+    #[test]
+    fn test_jaccard() {
+
+        assert_eq!(jaccard(&[1, 2, 2, 3], &[3, 2, 1]), 0.0);
+        assert_eq!(jaccard(&[1, 2], &[3, 4]), 1.0);
+        assert_eq!(jaccard(&[1, 2, 3, 4], &[4]), 0.75);
+        assert_eq!(jaccard::<u8>(&[], &[]), 0.0);
+
+        assert_eq!(jaccard_bits(&[true, false], &[false, true]), Some(1.0));
+        assert_eq!(jaccard_bits(&[false, false], &[false, false]), Some(0.0));
+        assert_eq!(jaccard_bits(&[true, true, true], &[true, true, false]), Some(1.0 / 3.0));
+        assert_eq!(jaccard_bits(&[true], &[]), None);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+        assert_eq!(levenshtein("saturday", "sunday"), 3);
+        assert_eq!(levenshtein("sunday", "saturday"), 3);
+        assert_eq!(levenshtein("straße", "strasse"), 2);
+    }
+
+    #[test]
+    fn test_dtw() {
+
+        let a = [1.0, 2.0, 3.0];
+        assert_eq!(dtw(&a, &a, None), 0.0);
+        assert_eq!(dtw(&a, &[1.0, 1.0, 2.0, 2.0, 3.0], None), 0.0);
+        assert_eq!(dtw(&a, &[2.0, 2.0, 2.0], None), 2.0);
+        assert_eq!(dtw(&[0.0, 5.0], &[5.0], None), 5.0);
+
+        let b = [0.0, 0.0, 0.0, 5.0];
+        let c = [5.0, 0.0, 0.0, 0.0];
+        assert_eq!(dtw(&b, &c, None), 10.0);
+        assert_eq!(dtw(&b, &c, Some(0)), 10.0);
+
+        let d = [0.0, 0.0, 5.0, 5.0];
+        assert_eq!(dtw(&b, &d, Some(1)), 0.0);
+        assert_eq!(dtw(&b, &d, Some(0)), 5.0);
+        // the band is widened to the difference of the lengths
+        assert_eq!(dtw(&[1.0], &[1.0, 1.0, 1.0], Some(0)), 0.0);
+
+        assert_eq!(dtw(&[], &[], None), 0.0);
+        assert!(dtw(&[], &[1.0], None).is_infinite());
+    }
+
+    #[test]
+    fn test_mahalanobis() {
+
+        let a = [1.0, 3.0];
+        let b = [-2.0, 7.0];
+
+        let m = Mahalanobis::new(&mat![1.0, 0.0; 0.0, 1.0]).unwrap();
+        assert!((m.distance(&a, &b) - 5.0).abs() < 1e-12);
+        assert_eq!(m.log_det(), 0.0);
+
+        // S = [4 2; 2 5], S^-1 = [5 -2; -2 4] / 16
+        let s = mat![4.0, 2.0; 2.0, 5.0];
+        let m = Mahalanobis::new(&s).unwrap();
+        let e = ((5.0 * 9.0 + 2.0 * 2.0 * 3.0 * 4.0 + 4.0 * 16.0) / 16.0f64).sqrt();
+        assert!((m.distance(&a, &b) - e).abs() < 1e-12);
+        assert!((m.log_det() - 16.0f64.ln()).abs() < 1e-12);
+
+        let c = Mahalanobis::from_cholesky(m.cholesky());
+        assert!((c.distance(&a, &b) - e).abs() < 1e-12);
+
+        assert!(Mahalanobis::new(&mat![1.0, 1.0; 1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn test_min_cov_det() {
+
+        let mut v = Vec::new();
+        for i in 0..20 {
+            let t = i as f64;
+            v.push(t.sin() * 2.0);
+            v.push(t.cos() + 0.5 * t.sin());
+        }
+        v.extend_from_slice(&[30.0, 30.0, -25.0, 40.0, 35.0, -30.0]);
+        let x = Matrix::from_vec(v, 23, 2);
+
+        let mcd = MinCovDet::fit(&x, 0.75, [1, 2, 3, 4]);
+        assert_eq!(mcd.support().len(), 18);
+        assert!(mcd.support().iter().all(|&i| i < 20));
+        assert!(L2Norm::compute(mcd.location()) < 1.0);
+
+        let d = mcd.mahalanobis(&x).unwrap();
+        let max_inlier = d[..20].iter().fold(0.0, |acc: f64, &x| acc.max(x));
+        assert!(d[20..].iter().all(|&x| x > 5.0 * max_inlier));
+    }
+
+    #[test]
+    fn test_min_cov_det_exact_fit() {
+
+        let x = mat![0.0, 0.0; 1.0, 1.0; 2.0, 2.0; 3.0, 3.0; 5.0, -1.0];
+        let mcd = MinCovDet::fit(&x, 0.8, [1, 2, 3, 4]);
+        assert_eq!(mcd.support(), &[0, 1, 2, 3]);
+        assert!(mcd.mahalanobis(&x).is_none());
+    }
+
     #[test]
     fn test_euclid_point2d() {
 
@@ -0,0 +1,608 @@
+//! Probability distributions for the generation of data and for Bayesian
+//! methods.
+//!
+//! A [MultivariateNormal](struct.MultivariateNormal.html) distribution is
+//! described by its mean vector and its covariance matrix, i.e. unlike the
+//! [normal_builder](../datasets/fn.normal_builder.html), which draws each
+//! dimension independently, it can generate correlated data. The Cholesky
+//! decomposition of the covariance matrix is computed once when the
+//! distribution is created.
+//!
+//! The univariate distributions [Gamma](struct.Gamma.html),
+//! [Beta](struct.Beta.html) and [Poisson](struct.Poisson.html) and the
+//! [Dirichlet](struct.Dirichlet.html) distribution (e.g. for priors of
+//! Bayesian methods) evaluate their density (or probability mass) function
+//! and draw samples. All samplers take a random number generator, i.e. the
+//! samples are deterministic if it is created with
+//! [seeded_rng](../rng/fn.seeded_rng.html).
+//!
+//! All distributions implement the [Sample](trait.Sample.html) trait which
+//! draws a single point as a vector, so that they can be used as components
+//! of a [Mixture](../datasets/struct.Mixture.html).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::distributions::MultivariateNormal;
+//! use rustml::rng::seeded_rng;
+//!
+//! # fn main() {
+//! let d = MultivariateNormal::new(&[1.0, 2.0], &mat![1.0, 0.9; 0.9, 1.0]).unwrap();
+//! let x = d.sample(1000, &mut seeded_rng([1, 2, 3, 4]));
+//!
+//! // the parameters estimated from the sample are close to the original ones
+//! let e = MultivariateNormal::fit(&x).unwrap();
+//! assert!((e.mean()[1] - 2.0).abs() < 0.1);
+//! assert!((e.covariance().get(0, 1).unwrap() - 0.9).abs() < 0.1);
+//!
+//! // the density is higher at the mean
+//! assert!(d.log_pdf(&[1.0, 2.0]) > d.log_pdf(&[1.0, 0.0]));
+//! # }
+//! ```
+//!
+//! ```
+//! use rustml::distributions::*;
+//! use rustml::rng::seeded_rng;
+//!
+//! let mut rng = seeded_rng([1, 2, 3, 4]);
+//! let b = Beta::new(2.0, 5.0);
+//! assert!((b.pdf(0.2) - 2.4576).abs() < 1e-10);
+//! assert!(b.sample_n(100, &mut rng).iter().all(|&x| x > 0.0 && x < 1.0));
+//!
+//! let p = Poisson::new(3.0);
+//! assert!((p.pmf(2) - 4.5 * (-3.0f64).exp()).abs() < 1e-12);
+//!
+//! let d = Dirichlet::new(&[1.0, 2.0, 3.0]);
+//! let x = d.sample(&mut rng);
+//! assert!((x.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+//! ```
+extern crate rand;
+
+use std::f64::consts::PI;
+use self::rand::Rng;
+use self::rand::distributions::{Normal, Gamma as GammaSampler, IndependentSample};
+
+use matrix::Matrix;
+use math::{Mean, Dimension, Normalization};
+use linalg::{covariance, cholesky};
+use stats::special::ln_gamma;
+
+/// Multivariate normal distribution.
+#[derive(Clone, Debug)]
+pub struct MultivariateNormal {
+    mean: Vec<f64>,
+    cov: Matrix<f64>,
+    l: Matrix<f64>,
+    log_det: f64
+}
+
+impl MultivariateNormal {
+
+    /// Creates the distribution with the given mean vector and covariance
+    /// matrix.
+    ///
+    /// Returns an error if the dimensions do not match or if the covariance
+    /// matrix is not symmetric positive definite.
+    pub fn new(mean: &[f64], cov: &Matrix<f64>) -> Result<MultivariateNormal, String> {
+
+        let d = mean.len();
+        if d == 0 || cov.rows() != d || cov.cols() != d {
+            return Err("Covariance matrix must be a square matrix with one row per dimension.".to_string());
+        }
+        for i in 0..d {
+            for j in 0..i {
+                let (a, b) = (cov.get(i, j).unwrap(), cov.get(j, i).unwrap());
+                if (a - b).abs() > 1e-10 * (a.abs() + b.abs()).max(1.0) {
+                    return Err("Covariance matrix is not symmetric.".to_string());
+                }
+            }
+        }
+        let l = match cholesky(cov) {
+            Some(l) => l,
+            None => return Err("Covariance matrix is not positive definite.".to_string())
+        };
+        let log_det = 2.0 * (0..d).map(|i| l.get(i, i).unwrap().ln()).sum::<f64>();
+        Ok(MultivariateNormal {
+            mean: mean.to_vec(),
+            cov: cov.clone(),
+            l: l,
+            log_det: log_det
+        })
+    }
+
+    /// Estimates the mean and the covariance matrix (maximum likelihood,
+    /// i.e. normalized by `n`) from the examples in the rows of `x`.
+    ///
+    /// Returns an error if the estimated covariance matrix is singular,
+    /// e.g. if there are not more examples than dimensions.
+    pub fn fit(x: &Matrix<f64>) -> Result<MultivariateNormal, String> {
+
+        if x.rows() == 0 {
+            return Err("Data must not be empty.".to_string());
+        }
+        MultivariateNormal::new(&x.mean(Dimension::Column), &covariance(x, Normalization::N))
+    }
+
+    /// Returns the number of dimensions.
+    pub fn dim(&self) -> usize {
+        self.mean.len()
+    }
+
+    /// Returns the mean vector.
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// Returns the covariance matrix.
+    pub fn covariance(&self) -> &Matrix<f64> {
+        &self.cov
+    }
+
+    /// Returns the lower triangular Cholesky factor `L` of the covariance
+    /// matrix, i.e. `L L^T` is the covariance matrix.
+    pub fn cholesky_factor(&self) -> &Matrix<f64> {
+        &self.l
+    }
+
+    /// Returns the logarithm of the probability density at `x`.
+    ///
+    /// Panics if the length of `x` is not the number of dimensions.
+    pub fn log_pdf(&self, x: &[f64]) -> f64 {
+
+        assert!(x.len() == self.dim(), "Length of the vector does not match the number of dimensions.");
+
+        // solves L z = x - mean, then (x - mean)' cov^-1 (x - mean) = z'z
+        let d = self.dim();
+        let l = self.l.buf();
+        let mut z = vec![0.0; d];
+        for i in 0..d {
+            let s = (0..i).fold(x[i] - self.mean[i], |acc, k| acc - l[i * d + k] * z[k]);
+            z[i] = s / l[i * d + i];
+        }
+        let q: f64 = z.iter().map(|v| v * v).sum();
+        -0.5 * (d as f64 * (2.0 * PI).ln() + self.log_det + q)
+    }
+
+    /// Returns the probability density at `x`.
+    pub fn pdf(&self, x: &[f64]) -> f64 {
+        self.log_pdf(x).exp()
+    }
+
+    /// Returns the logarithm of the probability density at each row of `x`.
+    pub fn log_pdf_rows(&self, x: &Matrix<f64>) -> Vec<f64> {
+        x.row_iter().map(|r| self.log_pdf(r)).collect()
+    }
+
+    /// Draws `n` samples which are returned in the rows of a matrix.
+    pub fn sample<R: Rng>(&self, n: usize, rng: &mut R) -> Matrix<f64> {
+
+        let d = self.dim();
+        let normal = Normal::new(0.0, 1.0);
+        let l = self.l.buf();
+        let mut v = Vec::with_capacity(n * d);
+        let mut z = vec![0.0; d];
+        for _ in 0..n {
+            for zi in &mut z {
+                *zi = normal.ind_sample(rng);
+            }
+            for i in 0..d {
+                v.push(self.mean[i] + (0..i + 1).fold(0.0, |acc, k| acc + l[i * d + k] * z[k]));
+            }
+        }
+        Matrix::from_vec(v, n, d)
+    }
+}
+
+/// Distribution from which points with a fixed number of dimensions can be
+/// drawn.
+///
+/// Univariate distributions draw vectors of length one and the Poisson
+/// distribution returns its counts as `f64`.
+pub trait Sample {
+    /// Returns the number of dimensions of a point.
+    fn dim(&self) -> usize;
+
+    /// Draws a point.
+    fn draw(&self, rng: &mut dyn Rng) -> Vec<f64>;
+}
+
+impl Sample for MultivariateNormal {
+
+    fn dim(&self) -> usize {
+        self.mean.len()
+    }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        self.sample(1, &mut rng).buf().clone()
+    }
+}
+
+impl Sample for Gamma {
+
+    fn dim(&self) -> usize { 1 }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        vec![self.sample(&mut rng)]
+    }
+}
+
+impl Sample for Beta {
+
+    fn dim(&self) -> usize { 1 }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        vec![self.sample(&mut rng)]
+    }
+}
+
+impl Sample for Dirichlet {
+
+    fn dim(&self) -> usize {
+        self.alpha.len()
+    }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        self.sample(&mut rng)
+    }
+}
+
+impl Sample for Poisson {
+
+    fn dim(&self) -> usize { 1 }
+
+    fn draw(&self, mut rng: &mut dyn Rng) -> Vec<f64> {
+        vec![self.sample(&mut rng) as f64]
+    }
+}
+
+/// Gamma distribution with the shape `k` and the scale `theta`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gamma {
+    shape: f64,
+    scale: f64
+}
+
+impl Gamma {
+
+    /// Creates the distribution.
+    ///
+    /// Panics if the shape or the scale is not greater than zero.
+    pub fn new(shape: f64, scale: f64) -> Gamma {
+
+        assert!(shape > 0.0 && scale > 0.0, "Shape and scale must be greater than zero.");
+        Gamma { shape: shape, scale: scale }
+    }
+
+    /// Returns the mean `k * theta`.
+    pub fn mean(&self) -> f64 {
+        self.shape * self.scale
+    }
+
+    /// Returns the variance `k * theta^2`.
+    pub fn var(&self) -> f64 {
+        self.shape * self.scale * self.scale
+    }
+
+    /// Returns the logarithm of the density at `x`.
+    pub fn ln_pdf(&self, x: f64) -> f64 {
+
+        if x < 0.0 || (x == 0.0 && self.shape > 1.0) {
+            return ::std::f64::NEG_INFINITY;
+        }
+        (self.shape - 1.0) * x.ln() - x / self.scale - ln_gamma(self.shape) - self.shape * self.scale.ln()
+    }
+
+    /// Returns the density at `x`.
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.ln_pdf(x).exp()
+    }
+
+    /// Draws a sample.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        GammaSampler::new(self.shape, self.scale).ind_sample(rng)
+    }
+
+    /// Draws `n` samples.
+    pub fn sample_n<R: Rng>(&self, n: usize, rng: &mut R) -> Vec<f64> {
+
+        let g = GammaSampler::new(self.shape, self.scale);
+        (0..n).map(|_| g.ind_sample(rng)).collect()
+    }
+}
+
+/// Beta distribution with the shape parameters `a` and `b`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Beta {
+    a: f64,
+    b: f64
+}
+
+impl Beta {
+
+    /// Creates the distribution.
+    ///
+    /// Panics if `a` or `b` is not greater than zero.
+    pub fn new(a: f64, b: f64) -> Beta {
+
+        assert!(a > 0.0 && b > 0.0, "Shape parameters must be greater than zero.");
+        Beta { a: a, b: b }
+    }
+
+    /// Returns the mean `a / (a + b)`.
+    pub fn mean(&self) -> f64 {
+        self.a / (self.a + self.b)
+    }
+
+    /// Returns the variance.
+    pub fn var(&self) -> f64 {
+        let s = self.a + self.b;
+        self.a * self.b / (s * s * (s + 1.0))
+    }
+
+    /// Returns the logarithm of the density at `x`.
+    pub fn ln_pdf(&self, x: f64) -> f64 {
+
+        if x < 0.0 || x > 1.0 {
+            return ::std::f64::NEG_INFINITY;
+        }
+        (self.a - 1.0) * x.ln() + (self.b - 1.0) * (1.0 - x).ln()
+            + ln_gamma(self.a + self.b) - ln_gamma(self.a) - ln_gamma(self.b)
+    }
+
+    /// Returns the density at `x`.
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.ln_pdf(x).exp()
+    }
+
+    /// Draws a sample, i.e. `X / (X + Y)` for `X ~ Gamma(a, 1)` and
+    /// `Y ~ Gamma(b, 1)`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+
+        let x = GammaSampler::new(self.a, 1.0).ind_sample(rng);
+        let y = GammaSampler::new(self.b, 1.0).ind_sample(rng);
+        x / (x + y)
+    }
+
+    /// Draws `n` samples.
+    pub fn sample_n<R: Rng>(&self, n: usize, rng: &mut R) -> Vec<f64> {
+        (0..n).map(|_| self.sample(rng)).collect()
+    }
+}
+
+/// Dirichlet distribution over the probability vectors of length `k` with
+/// the concentration parameters `alpha`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dirichlet {
+    alpha: Vec<f64>
+}
+
+impl Dirichlet {
+
+    /// Creates the distribution.
+    ///
+    /// Panics if there are fewer than two parameters or if a parameter is
+    /// not greater than zero.
+    pub fn new(alpha: &[f64]) -> Dirichlet {
+
+        assert!(alpha.len() >= 2, "At least two concentration parameters are required.");
+        assert!(alpha.iter().all(|&a| a > 0.0), "Concentration parameters must be greater than zero.");
+        Dirichlet { alpha: alpha.to_vec() }
+    }
+
+    /// Returns the concentration parameters.
+    pub fn alpha(&self) -> &[f64] {
+        &self.alpha
+    }
+
+    /// Returns the mean, i.e. the normalized concentration parameters.
+    pub fn mean(&self) -> Vec<f64> {
+        let s: f64 = self.alpha.iter().sum();
+        self.alpha.iter().map(|a| a / s).collect()
+    }
+
+    /// Returns the logarithm of the density at `x`, which must be a
+    /// probability vector.
+    ///
+    /// Panics if the length of `x` does not match the number of parameters.
+    pub fn ln_pdf(&self, x: &[f64]) -> f64 {
+
+        assert!(x.len() == self.alpha.len(), "Length of the vector does not match the number of parameters.");
+        if x.iter().any(|&v| v < 0.0) || (x.iter().sum::<f64>() - 1.0).abs() > 1e-9 {
+            return ::std::f64::NEG_INFINITY;
+        }
+        let s: f64 = self.alpha.iter().sum();
+        ln_gamma(s) + self.alpha.iter().zip(x.iter()).map(|(&a, &v)| (a - 1.0) * v.ln() - ln_gamma(a)).sum::<f64>()
+    }
+
+    /// Returns the density at `x`.
+    pub fn pdf(&self, x: &[f64]) -> f64 {
+        self.ln_pdf(x).exp()
+    }
+
+    /// Draws a sample, i.e. the normalized vector of independent samples of
+    /// `Gamma(alpha_i, 1)`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Vec<f64> {
+
+        let g: Vec<f64> = self.alpha.iter().map(|&a| GammaSampler::new(a, 1.0).ind_sample(rng)).collect();
+        let s: f64 = g.iter().sum();
+        g.iter().map(|x| x / s).collect()
+    }
+
+    /// Draws `n` samples which are returned in the rows of a matrix.
+    pub fn sample_n<R: Rng>(&self, n: usize, rng: &mut R) -> Matrix<f64> {
+
+        let v = (0..n).flat_map(|_| self.sample(rng)).collect();
+        Matrix::from_vec(v, n, self.alpha.len())
+    }
+}
+
+/// Poisson distribution with the rate `lambda`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Poisson {
+    lambda: f64
+}
+
+impl Poisson {
+
+    /// Creates the distribution.
+    ///
+    /// Panics if `lambda` is not greater than zero.
+    pub fn new(lambda: f64) -> Poisson {
+
+        assert!(lambda > 0.0, "Rate must be greater than zero.");
+        Poisson { lambda: lambda }
+    }
+
+    /// Returns the mean (and the variance) `lambda`.
+    pub fn mean(&self) -> f64 {
+        self.lambda
+    }
+
+    /// Returns the logarithm of the probability of `k`.
+    pub fn ln_pmf(&self, k: u64) -> f64 {
+        k as f64 * self.lambda.ln() - self.lambda - ln_gamma(k as f64 + 1.0)
+    }
+
+    /// Returns the probability of `k`.
+    pub fn pmf(&self, k: u64) -> f64 {
+        self.ln_pmf(k).exp()
+    }
+
+    /// Draws a sample. For `lambda < 10` the sample is computed by
+    /// multiplying uniform numbers, for larger rates with the transformed
+    /// rejection method of Hörmann (PTRS).
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u64 {
+
+        let lambda = self.lambda;
+        if lambda < 10.0 {
+            let l = (-lambda).exp();
+            let mut k = 0;
+            let mut p = rng.gen::<f64>();
+            while p > l {
+                k += 1;
+                p *= rng.gen::<f64>();
+            }
+            return k;
+        }
+
+        let slam = lambda.sqrt();
+        let loglam = lambda.ln();
+        let b = 0.931 + 2.53 * slam;
+        let a = -0.059 + 0.02483 * b;
+        let invalpha = 1.1239 + 1.1328 / (b - 3.4);
+        let vr = 0.9277 - 3.6224 / (b - 2.0);
+        loop {
+            let u = rng.gen::<f64>() - 0.5;
+            let v = rng.gen::<f64>();
+            let us = 0.5 - u.abs();
+            let k = ((2.0 * a / us + b) * u + lambda + 0.43).floor();
+            if us >= 0.07 && v <= vr {
+                return k as u64;
+            }
+            if k < 0.0 || (us < 0.013 && v > us) {
+                continue;
+            }
+            if v.ln() + invalpha.ln() - (a / (us * us) + b).ln() <= -lambda + k * loglam - ln_gamma(k + 1.0) {
+                return k as u64;
+            }
+        }
+    }
+
+    /// Draws `n` samples.
+    pub fn sample_n<R: Rng>(&self, n: usize, rng: &mut R) -> Vec<u64> {
+        (0..n).map(|_| self.sample(rng)).collect()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use gaussian::{Gaussian, GaussianFunctions};
+    use rng::seeded_rng;
+    use ops::MatrixMatrixOps;
+
+    #[test]
+    fn test_multivariate_normal() {
+
+        // one dimension
+        let d = MultivariateNormal::new(&[1.0], &Matrix::from_vec(vec![4.0], 1, 1)).unwrap();
+        assert!((d.pdf(&[2.0]) - Gaussian::new(1.0, 4.0).pr(2.0)).abs() < 1e-6);
+
+        // independent dimensions: product of the densities
+        let d = MultivariateNormal::new(&[0.0, 1.0], &Matrix::from_vec(vec![1.0, 0.0, 0.0, 2.0], 2, 2)).unwrap();
+        let p = Gaussian::new(0.0, 1.0).pr(0.5) * Gaussian::new(1.0, 2.0).pr(-1.0);
+        assert!((d.pdf(&[0.5, -1.0]) - p).abs() < 1e-6);
+
+        // correlated dimensions
+        let c = Matrix::from_vec(vec![2.0, -1.2, 0.3, -1.2, 1.0, 0.0, 0.3, 0.0, 0.5], 3, 3);
+        let d = MultivariateNormal::new(&[1.0, 2.0, 3.0], &c).unwrap();
+        let l = d.cholesky_factor();
+        assert!(l.mul(l, false, true).similar(&c, 1e-12));
+        let x = d.sample(20000, &mut seeded_rng([1, 2, 3, 4]));
+        let e = MultivariateNormal::fit(&x).unwrap();
+        assert!(e.mean().similar(&[1.0, 2.0, 3.0], 0.05));
+        assert!(e.covariance().similar(&c, 0.05));
+        assert_eq!(d.log_pdf_rows(&x).len(), 20000);
+
+        assert!(MultivariateNormal::new(&[0.0, 0.0], &Matrix::from_vec(vec![1.0, 2.0, 2.0, 1.0], 2, 2)).is_err());
+        assert!(MultivariateNormal::new(&[0.0, 0.0], &Matrix::from_vec(vec![1.0, 0.5, 0.0, 1.0], 2, 2)).is_err());
+        assert!(MultivariateNormal::new(&[0.0], &c).is_err());
+        assert!(MultivariateNormal::fit(&Matrix::from_vec(vec![1.0, 2.0], 1, 2)).is_err());
+    }
+
+    fn mean_var(v: &[f64]) -> (f64, f64) {
+        let n = v.len() as f64;
+        let m = v.iter().sum::<f64>() / n;
+        (m, v.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / n)
+    }
+
+    #[test]
+    fn test_gamma_beta() {
+
+        let mut rng = seeded_rng([1, 2, 3, 4]);
+
+        let g = Gamma::new(2.0, 3.0);
+        assert!((g.pdf(1.0) - (-1.0f64 / 3.0).exp() / 9.0).abs() < 1e-10);
+        assert_eq!(g.pdf(-1.0), 0.0);
+        let (m, v) = mean_var(&g.sample_n(20000, &mut rng));
+        assert!((m - g.mean()).abs() < 0.1 && (v - g.var()).abs() < 1.0);
+
+        let b = Beta::new(2.0, 3.0);
+        // 12 x (1 - x)^2
+        assert!((b.pdf(0.5) - 1.5).abs() < 1e-10);
+        let (m, v) = mean_var(&b.sample_n(20000, &mut rng));
+        assert!((m - b.mean()).abs() < 0.01 && (v - b.var()).abs() < 0.005);
+
+        // same seed, same samples
+        assert_eq!(b.sample_n(5, &mut seeded_rng([5, 6, 7, 8])), b.sample_n(5, &mut seeded_rng([5, 6, 7, 8])));
+    }
+
+    #[test]
+    fn test_dirichlet_poisson() {
+
+        let mut rng = seeded_rng([1, 2, 3, 4]);
+
+        let d = Dirichlet::new(&[1.0, 1.0, 1.0]);
+        // uniform on the simplex
+        assert!((d.pdf(&[0.2, 0.3, 0.5]) - 2.0).abs() < 1e-10);
+        assert_eq!(d.pdf(&[0.2, 0.3, 0.6]), 0.0);
+        let d = Dirichlet::new(&[2.0, 3.0, 5.0]);
+        let x = d.sample_n(10000, &mut rng);
+        assert!(x.mean(Dimension::Column).similar(&d.mean(), 0.01));
+
+        for &lambda in &[0.5, 4.0, 25.0, 1000.0] {
+            let p = Poisson::new(lambda);
+            let s: Vec<f64> = p.sample_n(20000, &mut rng).iter().map(|&k| k as f64).collect();
+            let (m, v) = mean_var(&s);
+            assert!((m - lambda).abs() < 0.05 * lambda.max(1.0), "{} {}", lambda, m);
+            assert!((v - lambda).abs() < 0.1 * lambda.max(1.0), "{} {}", lambda, v);
+        }
+        let p = Poisson::new(2.0);
+        assert!(((0..50).map(|k| p.pmf(k)).sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,393 @@
+//! Expectation-maximization (EM).
+//!
+//! Latent variable models (e.g. mixture models or hidden Markov models)
+//! implement the trait [EmModel](trait.EmModel.html), i.e. how to
+//! initialize the parameters, how to compute the posterior of the latent
+//! variables given the parameters (E-step) and how to update the parameters
+//! given the posterior (M-step). The driver [Em](struct.Em.html) runs the
+//! loop: it monitors the log-likelihood, detects convergence and restarts
+//! the algorithm from several random initializations keeping the best
+//! result, because EM only finds a local maximum.
+//!
+//! The module contains a [GaussianMixture](struct.GaussianMixture.html)
+//! with full covariance matrices as an implementation of the trait.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::em::*;
+//!
+//! # fn main() {
+//! let x = mixture_builder()
+//!     .add(100, normal_builder([1, 2, 3, 4]).add(0.0, 0.5).add(0.0, 0.5))
+//!     .add(100, normal_builder([5, 6, 7, 8]).add(5.0, 0.5).add(5.0, 0.5))
+//!     .as_matrix()
+//!     .rm_column(0);
+//!
+//! let r = Em::new().n_init(3).seed([1, 2, 3, 4]).fit(&GaussianMixture::new(2), &x);
+//! assert!(r.converged);
+//!
+//! let labels = r.model.predict(&x);
+//! assert!(labels[..100].iter().all(|&l| l == labels[0]));
+//! assert!(labels[100..].iter().all(|&l| l != labels[0]));
+//! # }
+//! ```
+extern crate rand;
+
+use self::rand::{Rng, XorShiftRng};
+
+use matrix::Matrix;
+use math::Normalization;
+use linalg::covariance;
+use distributions::MultivariateNormal;
+use progress::{Progress, ProgressInfo, NoProgress, report};
+use rng;
+
+/// Model whose parameters are estimated with expectation-maximization on
+/// data of type `D`.
+pub trait EmModel<D: ?Sized>: Clone {
+    /// Posterior of the latent variables computed by the E-step, e.g. the
+    /// responsibilities of the components of a mixture for each example.
+    type Posterior;
+
+    /// Initializes the parameters (e.g. randomly from the data) before the
+    /// first E-step of each restart.
+    fn initialize(&mut self, data: &D, rng: &mut dyn Rng);
+
+    /// Computes the posterior of the latent variables and the
+    /// log-likelihood of the data for the current parameters.
+    fn e_step(&self, data: &D) -> (Self::Posterior, f64);
+
+    /// Updates the parameters to maximize the expected complete data
+    /// log-likelihood under the posterior.
+    fn m_step(&mut self, data: &D, posterior: &Self::Posterior);
+}
+
+/// Result of [Em::fit](struct.Em.html#method.fit), i.e. the best run of
+/// all restarts.
+#[derive(Clone, Debug)]
+pub struct EmResult<M> {
+    /// The fitted model.
+    pub model: M,
+    /// Log-likelihood of the data for the fitted parameters.
+    pub log_likelihood: f64,
+    /// Log-likelihood after each iteration.
+    pub history: Vec<f64>,
+    /// True if the run converged before the maximum number of iterations.
+    pub converged: bool
+}
+
+/// Driver of the EM loop.
+#[derive(Clone, Debug)]
+pub struct Em {
+    max_iter: usize,
+    tol: f64,
+    n_init: usize,
+    rng: XorShiftRng
+}
+
+impl Em {
+
+    /// Creates a driver with at most 100 iterations, a tolerance of 1e-6
+    /// and one run. The random number generator is created with
+    /// [rng](../rng/fn.rng.html).
+    pub fn new() -> Em {
+        Em {
+            max_iter: 100,
+            tol: 1e-6,
+            n_init: 1,
+            rng: rng::rng()
+        }
+    }
+
+    /// Sets the maximum number of iterations (E-step followed by M-step) of
+    /// each run.
+    pub fn max_iter(&self, n: usize) -> Em {
+        Em { max_iter: n, .. self.clone() }
+    }
+
+    /// A run has converged if the log-likelihood improves by not more than
+    /// `tol` times its absolute value (at least one).
+    pub fn tol(&self, tol: f64) -> Em {
+        Em { tol: tol, .. self.clone() }
+    }
+
+    /// Sets the number of runs from different random initializations.
+    ///
+    /// Panics if `n` is zero.
+    pub fn n_init(&self, n: usize) -> Em {
+
+        assert!(n > 0, "Number of runs must be greater than zero.");
+        Em { n_init: n, .. self.clone() }
+    }
+
+    /// Seeds the random number generator of the initializations.
+    pub fn seed(&self, seed: [u32; 4]) -> Em {
+        Em { rng: rng::seeded_rng(seed), .. self.clone() }
+    }
+
+    /// Fits copies of `model` on `data` and returns the run with the
+    /// largest final log-likelihood.
+    pub fn fit<D: ?Sized, M: EmModel<D>>(&self, model: &M, data: &D) -> EmResult<M> {
+        self.fit_with_progress(model, data, &mut NoProgress)
+    }
+
+    /// Like [fit](#method.fit) but reports the negative log-likelihood
+    /// after each iteration of each run to `progress`.
+    pub fn fit_with_progress<D: ?Sized, M: EmModel<D>, P: Progress + ?Sized>(&self, model: &M, data: &D, progress: &mut P) -> EmResult<M> {
+
+        let mut rng = self.rng.clone();
+        let mut best: Option<EmResult<M>> = None;
+        for _ in 0..self.n_init {
+            let mut m = model.clone();
+            m.initialize(data, &mut rng);
+            let mut history = vec![];
+            let mut converged = false;
+            let (mut post, mut ll) = m.e_step(data);
+            for i in 0..self.max_iter {
+                m.m_step(data, &post);
+                let (p, l) = m.e_step(data);
+                history.push(l);
+                report(progress, &ProgressInfo { algorithm: "em", iteration: i + 1, max_iter: self.max_iter, loss: Some(-l) });
+                let change = l - ll;
+                post = p;
+                ll = l;
+                if change.abs() <= self.tol * ll.abs().max(1.0) {
+                    converged = true;
+                    break;
+                }
+            }
+            if best.as_ref().map(|b| ll > b.log_likelihood).unwrap_or(true) {
+                best = Some(EmResult { model: m, log_likelihood: ll, history: history, converged: converged });
+            }
+        }
+        best.unwrap()
+    }
+}
+
+/// Gaussian mixture model with full covariance matrices.
+#[derive(Clone, Debug)]
+pub struct GaussianMixture {
+    k: usize,
+    reg_covar: f64,
+    weights: Vec<f64>,
+    components: Vec<MultivariateNormal>
+}
+
+impl GaussianMixture {
+
+    /// Creates an unfitted mixture of `k` components which adds 1e-6 to the
+    /// diagonal of the covariance matrices.
+    ///
+    /// Panics if `k` is zero.
+    pub fn new(k: usize) -> GaussianMixture {
+
+        assert!(k > 0, "Number of components must be greater than zero.");
+        GaussianMixture {
+            k: k,
+            reg_covar: 1e-6,
+            weights: vec![],
+            components: vec![]
+        }
+    }
+
+    /// Sets the value which is added to the diagonal of the covariance
+    /// matrices to keep them positive definite.
+    pub fn reg_covar(&self, r: f64) -> GaussianMixture {
+        GaussianMixture { reg_covar: r, .. self.clone() }
+    }
+
+    /// Returns the mixing weights.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// Returns the components.
+    pub fn components(&self) -> &[MultivariateNormal] {
+        &self.components
+    }
+
+    /// Returns the logarithm of the density of the mixture at each row of
+    /// `x`.
+    ///
+    /// Panics if the mixture is not fitted.
+    pub fn score_samples(&self, x: &Matrix<f64>) -> Vec<f64> {
+        self.log_joint(x).row_iter().map(log_sum_exp).collect()
+    }
+
+    /// Returns the posterior probability of each component (columns) for
+    /// each row of `x`.
+    ///
+    /// Panics if the mixture is not fitted.
+    pub fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.e_step(x).0
+    }
+
+    /// Returns the most probable component of each row of `x`.
+    ///
+    /// Panics if the mixture is not fitted.
+    pub fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+        self.log_joint(x).row_iter()
+            .map(|r| (0..r.len()).fold(0, |a, b| if r[b] > r[a] { b } else { a }))
+            .collect()
+    }
+
+    // ln(w_k) + ln N(x_i | mu_k, Sigma_k)
+    fn log_joint(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(!self.components.is_empty(), "Mixture is not fitted.");
+        let v = x.row_iter()
+            .flat_map(|r| self.components.iter().zip(self.weights.iter()).map(move |(c, w)| w.ln() + c.log_pdf(r)))
+            .collect();
+        Matrix::from_vec(v, x.rows(), self.k)
+    }
+
+    fn component(&self, mean: &[f64], cov: Matrix<f64>) -> MultivariateNormal {
+
+        let d = mean.len();
+        let mut reg = self.reg_covar.max(1e-12);
+        loop {
+            let mut c = cov.clone();
+            for i in 0..d {
+                *c.get_mut(i, i).unwrap() += reg;
+            }
+            if let Ok(n) = MultivariateNormal::new(mean, &c) {
+                return n;
+            }
+            reg *= 10.0;
+        }
+    }
+}
+
+impl EmModel<Matrix<f64>> for GaussianMixture {
+
+    /// Matrix with the responsibilities of the components (columns) for
+    /// each example (rows).
+    type Posterior = Matrix<f64>;
+
+    /// Chooses `k` distinct random examples as means, the covariance matrix
+    /// of the data for all components and equal weights.
+    ///
+    /// Panics if there are fewer examples than components.
+    fn initialize(&mut self, x: &Matrix<f64>, rng: &mut dyn Rng) {
+
+        assert!(x.rows() >= self.k, "Number of examples must not be smaller than the number of components.");
+        let mut idx: Vec<usize> = (0..x.rows()).collect();
+        for i in 0..self.k {
+            let j = i + (rng.next_u32() as usize) % (idx.len() - i);
+            idx.swap(i, j);
+        }
+        let cov = covariance(x, Normalization::N);
+        self.weights = vec![1.0 / self.k as f64; self.k];
+        self.components = idx[..self.k].iter().map(|&i| self.component(x.row(i).unwrap(), cov.clone())).collect();
+    }
+
+    fn e_step(&self, x: &Matrix<f64>) -> (Matrix<f64>, f64) {
+
+        let lj = self.log_joint(x);
+        let mut ll = 0.0;
+        let mut v = Vec::with_capacity(x.rows() * self.k);
+        for r in lj.row_iter() {
+            let z = log_sum_exp(r);
+            ll += z;
+            v.extend(r.iter().map(|l| (l - z).exp()));
+        }
+        (Matrix::from_vec(v, x.rows(), self.k), ll)
+    }
+
+    fn m_step(&mut self, x: &Matrix<f64>, resp: &Matrix<f64>) {
+
+        let (n, d) = (x.rows(), x.cols());
+        let mut weights = Vec::with_capacity(self.k);
+        let mut components = Vec::with_capacity(self.k);
+        for c in 0..self.k {
+            let r = resp.col(c).unwrap();
+            let nk = r.iter().sum::<f64>() + 10.0 * ::std::f64::EPSILON;
+            let mut mean = vec![0.0; d];
+            for (row, w) in x.row_iter().zip(r.iter()) {
+                for j in 0..d {
+                    mean[j] += w * row[j] / nk;
+                }
+            }
+            let mut cov = vec![0.0; d * d];
+            for (row, w) in x.row_iter().zip(r.iter()) {
+                for i in 0..d {
+                    for j in 0..d {
+                        cov[i * d + j] += w * (row[i] - mean[i]) * (row[j] - mean[j]) / nk;
+                    }
+                }
+            }
+            weights.push(nk / n as f64);
+            components.push(self.component(&mean, Matrix::from_vec(cov, d, d)));
+        }
+        self.weights = weights;
+        self.components = components;
+    }
+}
+
+fn log_sum_exp(v: &[f64]) -> f64 {
+
+    let m = v.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    if m == ::std::f64::NEG_INFINITY {
+        return m;
+    }
+    m + v.iter().fold(0.0, |acc, x| acc + (x - m).exp()).ln()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use datasets::mixture_builder;
+
+    #[test]
+    fn test_gaussian_mixture() {
+
+        let a = MultivariateNormal::new(&[0.0, 0.0], &Matrix::from_vec(vec![1.0, 0.8, 0.8, 1.0], 2, 2)).unwrap();
+        let b = MultivariateNormal::new(&[6.0, 0.0], &Matrix::from_vec(vec![0.5, 0.0, 0.0, 2.0], 2, 2)).unwrap();
+        assert_eq!(a.dim(), 2);
+        let (x, y) = mixture_builder().add_component(0.3, a).add_component(0.7, b).sample(1000, [1, 2, 3, 4]);
+
+        let mut steps = 0;
+        let r = Em::new().n_init(2).seed([1, 2, 3, 4])
+            .fit_with_progress(&GaussianMixture::new(2), &x, &mut |_: &ProgressInfo| steps += 1);
+        assert!(r.converged);
+        assert!(steps >= r.history.len());
+
+        // EM never decreases the log-likelihood
+        for w in r.history.windows(2) {
+            assert!(w[1] >= w[0] - 1e-9);
+        }
+        assert_eq!(*r.history.last().unwrap(), r.log_likelihood);
+
+        let m = &r.model;
+        let first = if m.components()[0].mean()[0] < 3.0 { 0 } else { 1 };
+        assert!((m.weights()[first] - 0.3).abs() < 0.05);
+        assert!(m.components()[first].mean().similar(&vec![0.0, 0.0], 0.2));
+        assert!(m.components()[first].covariance().similar(&Matrix::from_vec(vec![1.0, 0.8, 0.8, 1.0], 2, 2), 0.2));
+
+        let labels = m.predict(&x);
+        let agree = labels.iter().zip(y.iter()).filter(|&(&l, &t)| (l == first) == (t == 0)).count();
+        assert!(agree > 990);
+        let p = m.predict_proba(&x);
+        assert!(p.row_iter().all(|r| (r[0] + r[1] - 1.0).abs() < 1e-10));
+        let s: f64 = m.score_samples(&x).iter().sum();
+        assert!((s - r.log_likelihood).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_restarts() {
+
+        // two identical runs with one restart each and the best of both
+        let x = Matrix::from_vec(vec![0.0, 0.1, 0.2, 5.0, 5.1, 5.2, 10.0, 10.1, 10.2], 9, 1);
+        let one = Em::new().seed([3, 3, 3, 3]).fit(&GaussianMixture::new(3).reg_covar(1e-3), &x);
+        let again = Em::new().seed([3, 3, 3, 3]).fit(&GaussianMixture::new(3).reg_covar(1e-3), &x);
+        assert_eq!(one.history, again.history);
+        let best = Em::new().n_init(5).seed([3, 3, 3, 3]).fit(&GaussianMixture::new(3).reg_covar(1e-3), &x);
+        assert!(best.log_likelihood >= one.log_likelihood);
+    }
+}
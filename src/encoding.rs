@@ -0,0 +1,639 @@
+//! Module to encode categorical features as numeric features.
+//!
+//! Many algorithms (e.g. neural networks or linear regression) can only
+//! handle numeric features. The encoders in this module learn the set of
+//! categories (the vocabulary) of each column of a matrix and map the
+//! categories to numbers.
+//!
+//! * [`OrdinalEncoder`](struct.OrdinalEncoder.html) maps each category to its
+//!   index in the vocabulary of the column.
+//! * [`OneHotEncoder`](struct.OneHotEncoder.html) maps each category to a
+//!   vector which contains a one at the index of the category and zeros
+//!   everywhere else.
+//!
+//! For the target values of a classification task there are
+//!
+//! * [`LabelEncoder`](struct.LabelEncoder.html) which maps class labels to
+//!   contiguous indexes and
+//! * [`LabelBinarizer`](struct.LabelBinarizer.html) which maps class labels to
+//!   a matrix with one row per label that can be used e.g. as the target
+//!   matrix of a [neural network](../nn/index.html).
+//!
+//! Categories can be of any type which implements `Ord` and `Clone`, e.g.
+//! strings or integers. The vocabulary of each column is sorted so that the
+//! encoding does not depend on the order of the examples. Encoders with
+//! `String` categories implement [Model](../io/model/trait.Model.html) and
+//! can be saved with [save_model](../io/model/fn.save_model.html).
+
+use std::f64;
+
+use matrix::Matrix;
+use io::model::{Model, Encoder, Decoder};
+
+// ----------------------------------------------------------------------------
+
+/// The sorted set of categories of each column of a matrix.
+#[derive(Clone, Debug)]
+struct Vocabulary<T> {
+    categories: Vec<Vec<T>>
+}
+
+impl <T: Ord + Clone> Vocabulary<T> {
+
+    fn fit(m: &Matrix<T>) -> Vocabulary<T> {
+
+        let mut categories: Vec<Vec<T>> = (0..m.cols()).map(|_| vec![]).collect();
+        for row in m.row_iter() {
+            for (j, val) in row.iter().enumerate() {
+                if let Err(pos) = categories[j].binary_search(val) {
+                    categories[j].insert(pos, val.clone());
+                }
+            }
+        }
+        Vocabulary {
+            categories: categories
+        }
+    }
+
+    fn index(&self, col: usize, val: &T) -> Option<usize> {
+        self.categories[col].binary_search(val).ok()
+    }
+
+    fn check(&self, cols: usize) {
+        assert!(self.categories.len() == cols,
+            "Number of columns does not match the number of features.");
+    }
+}
+
+impl Vocabulary<String> {
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.categories.len());
+        for c in &self.categories {
+            encode_strs(e, c);
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Vocabulary<String>, String> {
+
+        let mut categories = vec![];
+        for _ in 0..try!(d.usize()) {
+            categories.push(try!(decode_strs(d)));
+        }
+        Ok(Vocabulary { categories: categories })
+    }
+}
+
+fn encode_strs(e: &mut Encoder, v: &[String]) {
+
+    e.usize(v.len());
+    for s in v {
+        e.str(s);
+    }
+}
+
+fn decode_strs(d: &mut Decoder) -> Result<Vec<String>, String> {
+
+    let mut v = vec![];
+    for _ in 0..try!(d.usize()) {
+        v.push(try!(d.str()));
+    }
+    Ok(v)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Encodes each category of a column by its index in the sorted vocabulary of
+/// that column.
+///
+/// Categories which have not been seen during `fit` are encoded as NaN so that
+/// they can be handled later, e.g. by an imputer.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::encoding::OrdinalEncoder;
+///
+/// # fn main() {
+/// let m = mat![
+///     "red",   "small";
+///     "green", "large";
+///     "blue",  "small"
+/// ];
+/// let e = OrdinalEncoder::fit(&m);
+/// assert_eq!(e.categories(0), &["blue", "green", "red"]);
+///
+/// let x = e.transform(&m);
+/// assert_eq!(x, mat![2.0, 1.0; 1.0, 0.0; 0.0, 1.0]);
+///
+/// let y = e.inverse_transform(&x);
+/// assert_eq!(y.get(0, 0).unwrap(), &Some("red"));
+///
+/// // unknown categories are mapped to NaN
+/// assert!(e.transform(&mat!["pink", "large"]).get(0, 0).unwrap().is_nan());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct OrdinalEncoder<T> {
+    vocab: Vocabulary<T>
+}
+
+impl <T: Ord + Clone> OrdinalEncoder<T> {
+
+    /// Learns the categories of each column of `m`.
+    pub fn fit(m: &Matrix<T>) -> OrdinalEncoder<T> {
+        OrdinalEncoder {
+            vocab: Vocabulary::fit(m)
+        }
+    }
+
+    /// Returns the sorted categories of the column at index `col`.
+    ///
+    /// Panics if the column does not exist.
+    pub fn categories(&self, col: usize) -> &[T] {
+        &self.vocab.categories[col]
+    }
+
+    /// Replaces each category by its index in the vocabulary of the column.
+    ///
+    /// Unknown categories are replaced by NaN. Panics if the number of columns
+    /// does not match the number of columns of the matrix used for fitting.
+    pub fn transform(&self, m: &Matrix<T>) -> Matrix<f64> {
+
+        self.vocab.check(m.cols());
+        let c = m.cols();
+        Matrix::from_vec(
+            m.iter().enumerate()
+                .map(|(i, val)| match self.vocab.index(i % c, val) {
+                    Some(idx) => idx as f64,
+                    None      => f64::NAN
+                })
+                .collect(),
+            m.rows(), m.cols()
+        )
+    }
+
+    /// Maps the indexes back to the categories.
+    ///
+    /// Values which are not a valid index into the vocabulary of the column
+    /// (e.g. NaN for unknown categories) are mapped to `None`.
+    pub fn inverse_transform(&self, m: &Matrix<f64>) -> Matrix<Option<T>> {
+
+        self.vocab.check(m.cols());
+        let c = m.cols();
+        Matrix::from_vec(
+            m.iter().enumerate()
+                .map(|(i, &val)| self.category(i % c, val))
+                .collect(),
+            m.rows(), m.cols()
+        )
+    }
+
+    fn category(&self, col: usize, val: f64) -> Option<T> {
+
+        if val.is_nan() || val < 0.0 || val.fract() != 0.0 {
+            return None;
+        }
+        self.vocab.categories[col].get(val as usize).cloned()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Encodes each categorical column by a block of binary columns, one column
+/// for each category of the vocabulary.
+///
+/// The column of the block that corresponds to the category of an example is
+/// set to one, all other columns of the block are set to zero. The blocks
+/// are arranged in the same order as the columns of the input matrix.
+/// Categories which have not been seen during `fit` are encoded by a block
+/// where all elements are zero.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::encoding::OneHotEncoder;
+///
+/// # fn main() {
+/// let m = mat![
+///     3, 10;
+///     1, 20;
+///     3, 20
+/// ];
+/// let e = OneHotEncoder::fit(&m);
+/// assert_eq!(e.output_size(), 4);
+///
+/// let x = e.transform(&m);
+/// assert_eq!(x, mat![
+///     0.0, 1.0, 1.0, 0.0;
+///     1.0, 0.0, 0.0, 1.0;
+///     0.0, 1.0, 0.0, 1.0
+/// ]);
+///
+/// // unknown categories are mapped to zeros
+/// assert_eq!(e.transform(&mat![5, 10]), mat![0.0, 0.0, 1.0, 0.0]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct OneHotEncoder<T> {
+    vocab: Vocabulary<T>
+}
+
+impl <T: Ord + Clone> OneHotEncoder<T> {
+
+    /// Learns the categories of each column of `m`.
+    pub fn fit(m: &Matrix<T>) -> OneHotEncoder<T> {
+        OneHotEncoder {
+            vocab: Vocabulary::fit(m)
+        }
+    }
+
+    /// Returns the sorted categories of the column at index `col`.
+    ///
+    /// Panics if the column does not exist.
+    pub fn categories(&self, col: usize) -> &[T] {
+        &self.vocab.categories[col]
+    }
+
+    /// Returns the number of columns of a transformed matrix, i.e. the
+    /// total number of categories over all columns.
+    pub fn output_size(&self) -> usize {
+        self.vocab.categories.iter().map(|c| c.len()).fold(0, |acc, n| acc + n)
+    }
+
+    /// Replaces each categorical column by its one-hot encoding.
+    ///
+    /// Panics if the number of columns does not match the number of columns
+    /// of the matrix used for fitting.
+    pub fn transform(&self, m: &Matrix<T>) -> Matrix<f64> {
+
+        self.vocab.check(m.cols());
+
+        let n = self.output_size();
+        let mut r = Matrix::fill(0.0, m.rows(), n);
+        for (i, row) in m.row_iter().enumerate() {
+            let mut offset = 0;
+            for (j, val) in row.iter().enumerate() {
+                if let Some(idx) = self.vocab.index(j, val) {
+                    r.set(i, offset + idx, 1.0);
+                }
+                offset += self.vocab.categories[j].len();
+            }
+        }
+        r
+    }
+
+    /// Maps the one-hot encoded blocks back to the categories.
+    ///
+    /// For each block the category with the largest value is chosen so that
+    /// also scores or probabilities (e.g. the output of a neural network) can
+    /// be mapped to categories. If all values of a block are zero (i.e. the
+    /// encoding of an unknown category) `None` is returned for that column.
+    ///
+    /// Panics if the number of columns is not equal to `output_size()`.
+    pub fn inverse_transform(&self, m: &Matrix<f64>) -> Matrix<Option<T>> {
+
+        assert!(m.cols() == self.output_size(),
+            "Number of columns does not match the number of encoded features.");
+
+        let mut r = Matrix::new();
+        for row in m.row_iter() {
+            let mut v = vec![];
+            let mut offset = 0;
+            for c in &self.vocab.categories {
+                let block = &row[offset..offset + c.len()];
+                let best = block.iter().enumerate()
+                    .filter(|&(_, &x)| x != 0.0)
+                    .fold(None, |acc: Option<(usize, f64)>, (i, &x)| match acc {
+                        Some((_, y)) if y >= x => acc,
+                        _ => Some((i, x))
+                    });
+                v.push(best.map(|(i, _)| c[i].clone()));
+                offset += c.len();
+            }
+            r.add_row(&v);
+        }
+        r
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Maps class labels to contiguous indexes `0, 1, ..., n - 1` where `n` is
+/// the number of distinct labels.
+///
+/// The indexes are assigned in the sorted order of the labels.
+///
+/// # Example
+///
+/// ```
+/// use rustml::encoding::LabelEncoder;
+///
+/// let labels = vec!["dog", "cat", "bird", "cat"];
+/// let e = LabelEncoder::fit(&labels);
+/// assert_eq!(e.classes(), &["bird", "cat", "dog"]);
+/// assert_eq!(e.transform(&labels).unwrap(), vec![2, 1, 0, 1]);
+/// assert_eq!(e.inverse_transform(&[0, 2]).unwrap(), vec!["bird", "dog"]);
+///
+/// // unknown labels cannot be encoded
+/// assert!(e.transform(&["fish"]).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct LabelEncoder<T> {
+    classes: Vec<T>
+}
+
+impl <T: Ord + Clone> LabelEncoder<T> {
+
+    /// Learns the distinct labels in `labels`.
+    pub fn fit(labels: &[T]) -> LabelEncoder<T> {
+
+        let mut classes = labels.to_vec();
+        classes.sort();
+        classes.dedup();
+        LabelEncoder {
+            classes: classes
+        }
+    }
+
+    /// Returns the sorted distinct labels.
+    pub fn classes(&self) -> &[T] {
+        &self.classes
+    }
+
+    /// Returns the number of distinct labels.
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// Returns the index of the label `label` or `None` if the label is unknown.
+    pub fn index(&self, label: &T) -> Option<usize> {
+        self.classes.binary_search(label).ok()
+    }
+
+    /// Maps each label to its index.
+    ///
+    /// Returns `None` if at least one label has not been seen during `fit`.
+    pub fn transform(&self, labels: &[T]) -> Option<Vec<usize>> {
+        labels.iter().map(|l| self.index(l)).collect()
+    }
+
+    /// Maps each index back to its label.
+    ///
+    /// Returns `None` if at least one index is out of range.
+    pub fn inverse_transform(&self, idx: &[usize]) -> Option<Vec<T>> {
+        idx.iter().map(|&i| self.classes.get(i).cloned()).collect()
+    }
+}
+
+/// Maps class labels to a target matrix with one row per label and one column
+/// per class.
+///
+/// The column that corresponds to the class of a label is set to one and
+/// all other columns are set to zero. The columns are arranged in the same
+/// order as the classes of a [`LabelEncoder`](struct.LabelEncoder.html), i.e.
+/// in the sorted order of the labels. Also for two classes two columns are
+/// created so that the matrix can be directly used as the target matrix
+/// of a neural network with one output unit per class.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::encoding::LabelBinarizer;
+///
+/// # fn main() {
+/// let labels = vec![7, 3, 5, 3];
+/// let b = LabelBinarizer::fit(&labels);
+/// let t = b.transform(&labels);
+/// assert_eq!(t, mat![
+///     0.0, 0.0, 1.0;
+///     1.0, 0.0, 0.0;
+///     0.0, 1.0, 0.0;
+///     1.0, 0.0, 0.0
+/// ]);
+///
+/// // map the output of a classifier (e.g. probabilities) back to labels
+/// let p = mat![0.1, 0.7, 0.2; 0.3, 0.3, 0.4];
+/// assert_eq!(b.inverse_transform(&p), vec![5, 7]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LabelBinarizer<T> {
+    encoder: LabelEncoder<T>
+}
+
+impl <T: Ord + Clone> LabelBinarizer<T> {
+
+    /// Learns the distinct labels in `labels`.
+    pub fn fit(labels: &[T]) -> LabelBinarizer<T> {
+        LabelBinarizer {
+            encoder: LabelEncoder::fit(labels)
+        }
+    }
+
+    /// Returns the sorted distinct labels, i.e. the label of each column.
+    pub fn classes(&self) -> &[T] {
+        self.encoder.classes()
+    }
+
+    /// Creates a matrix with one row for each label in `labels`.
+    ///
+    /// A row for a label that has not been seen during `fit` contains only
+    /// zeros.
+    pub fn transform(&self, labels: &[T]) -> Matrix<f64> {
+
+        let mut m = Matrix::fill(0.0, labels.len(), self.encoder.len());
+        for (i, l) in labels.iter().enumerate() {
+            if let Some(idx) = self.encoder.index(l) {
+                m.set(i, idx, 1.0);
+            }
+        }
+        m
+    }
+
+    /// Returns for each row of `m` the label of the column with the largest
+    /// value.
+    ///
+    /// If two or more columns have the largest value the first one is
+    /// chosen. Panics if the number of columns is not equal to the number of
+    /// classes.
+    pub fn inverse_transform(&self, m: &Matrix<f64>) -> Vec<T> {
+
+        assert!(m.cols() == self.encoder.len() && m.cols() > 0,
+            "Number of columns does not match the number of classes.");
+
+        m.row_iter()
+            .map(|row| {
+                let idx = row.iter().enumerate()
+                    .fold(0, |best, (i, &x)| if x > row[best] { i } else { best });
+                self.encoder.classes[idx].clone()
+            })
+            .collect()
+    }
+}
+
+impl Model for OrdinalEncoder<String> {
+
+    fn tag() -> &'static str { "rustml.encoding.OrdinalEncoder" }
+
+    fn encode(&self, e: &mut Encoder) {
+        self.vocab.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<OrdinalEncoder<String>, String> {
+        Ok(OrdinalEncoder { vocab: try!(Vocabulary::decode(d)) })
+    }
+}
+
+impl Model for OneHotEncoder<String> {
+
+    fn tag() -> &'static str { "rustml.encoding.OneHotEncoder" }
+
+    fn encode(&self, e: &mut Encoder) {
+        self.vocab.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<OneHotEncoder<String>, String> {
+        Ok(OneHotEncoder { vocab: try!(Vocabulary::decode(d)) })
+    }
+}
+
+impl Model for LabelEncoder<String> {
+
+    fn tag() -> &'static str { "rustml.encoding.LabelEncoder" }
+
+    fn encode(&self, e: &mut Encoder) {
+        encode_strs(e, &self.classes);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<LabelEncoder<String>, String> {
+        Ok(LabelEncoder { classes: try!(decode_strs(d)) })
+    }
+}
+
+impl Model for LabelBinarizer<String> {
+
+    fn tag() -> &'static str { "rustml.encoding.LabelBinarizer" }
+
+    fn encode(&self, e: &mut Encoder) {
+        self.encoder.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<LabelBinarizer<String>, String> {
+        Ok(LabelBinarizer { encoder: try!(LabelEncoder::decode(d)) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_ordinal_encoder() {
+
+        let m = mat![
+            "b".to_string(), "x".to_string();
+            "a".to_string(), "y".to_string();
+            "c".to_string(), "x".to_string()
+        ];
+
+        let e = OrdinalEncoder::fit(&m);
+        assert_eq!(e.categories(0), &["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(e.categories(1), &["x".to_string(), "y".to_string()]);
+
+        let x = e.transform(&m);
+        assert_eq!(x, mat![1.0, 0.0; 0.0, 1.0; 2.0, 0.0]);
+
+        let y = e.inverse_transform(&mat![2.0, 1.0; 3.0, 0.5]);
+        assert_eq!(y.row(0).unwrap(), &[Some("c".to_string()), Some("y".to_string())]);
+        assert_eq!(y.row(1).unwrap(), &[None, None]);
+    }
+
+    #[test]
+    fn test_ordinal_encoder_unknown() {
+
+        let e = OrdinalEncoder::fit(&mat![1, 2; 3, 4]);
+        let x = e.transform(&mat![3, 5]);
+        assert_eq!(*x.get(0, 0).unwrap(), 1.0);
+        assert!(x.get(0, 1).unwrap().is_nan());
+        assert_eq!(e.inverse_transform(&x).row(0).unwrap(), &[Some(3), None]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ordinal_encoder_panic() {
+
+        let e = OrdinalEncoder::fit(&mat![1, 2; 3, 4]);
+        e.transform(&mat![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_one_hot_encoder() {
+
+        let m = mat![
+            "b", "x";
+            "a", "y";
+            "c", "x"
+        ];
+
+        let e = OneHotEncoder::fit(&m);
+        assert_eq!(e.output_size(), 5);
+
+        let x = e.transform(&m);
+        assert_eq!(x, mat![
+            0.0, 1.0, 0.0, 1.0, 0.0;
+            1.0, 0.0, 0.0, 0.0, 1.0;
+            0.0, 0.0, 1.0, 1.0, 0.0
+        ]);
+
+        let y = e.inverse_transform(&x);
+        assert_eq!(y.row(0).unwrap(), &[Some("b"), Some("x")]);
+        assert_eq!(y.row(1).unwrap(), &[Some("a"), Some("y")]);
+        assert_eq!(y.row(2).unwrap(), &[Some("c"), Some("x")]);
+
+        let z = e.inverse_transform(&mat![0.1, 0.7, 0.2, 0.0, 0.0]);
+        assert_eq!(z.row(0).unwrap(), &[Some("b"), None]);
+    }
+
+    #[test]
+    fn test_one_hot_encoder_unknown() {
+
+        let e = OneHotEncoder::fit(&mat![1; 2]);
+        assert_eq!(e.transform(&mat![2; 7]), mat![0.0, 1.0; 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_label_encoder() {
+
+        let e = LabelEncoder::fit(&[4, 1, 4, 9, 1]);
+        assert_eq!(e.classes(), &[1, 4, 9]);
+        assert_eq!(e.len(), 3);
+        assert_eq!(e.transform(&[9, 1, 4]).unwrap(), vec![2, 0, 1]);
+        assert_eq!(e.index(&4), Some(1));
+        assert!(e.transform(&[1, 5]).is_none());
+        assert_eq!(e.inverse_transform(&[1, 1, 2]).unwrap(), vec![4, 4, 9]);
+        assert!(e.inverse_transform(&[3]).is_none());
+    }
+
+    #[test]
+    fn test_label_binarizer() {
+
+        let b = LabelBinarizer::fit(&["yes", "no", "yes"]);
+        assert_eq!(b.classes(), &["no", "yes"]);
+        assert_eq!(b.transform(&["yes", "no", "maybe"]), mat![
+            0.0, 1.0;
+            1.0, 0.0;
+            0.0, 0.0
+        ]);
+        assert_eq!(b.inverse_transform(&mat![0.2, 0.8; 0.5, 0.5; 0.9, 0.1]), vec!["yes", "no", "no"]);
+    }
+}
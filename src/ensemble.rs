@@ -0,0 +1,551 @@
+//! Ensembles which combine the predictions of several estimators.
+//!
+//! [Stacking](struct.Stacking.html) trains a meta-learner on the
+//! predictions of base estimators. To avoid that the meta-learner only sees
+//! predictions of examples which the base estimators have been trained on,
+//! the predictions are computed out-of-fold with a k-fold cross-validation
+//! (stacking) or on a holdout set (blending). All estimators implement the
+//! trait [Estimator](../model_selection/trait.Estimator.html), and so does
+//! the ensemble, i.e. it can be evaluated with
+//! [cross_val_score](../model_selection/fn.cross_val_score.html) or be
+//! nested into another ensemble.
+//!
+//! A [VotingClassifier](struct.VotingClassifier.html) combines the labels
+//! (hard voting) or the class probabilities (soft voting) of classifiers
+//! which can be of different types and which are usually already fitted.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::model_selection::Estimator;
+//! use rustml::ensemble::Stacking;
+//!
+//! // predicts the value of a column
+//! struct Column(usize);
+//!
+//! impl Estimator<f64, f64> for Column {
+//!     fn fit(&mut self, _x: &Matrix<f64>, _y: &[f64]) { }
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+//!         x.row_iter().map(|r| r[self.0]).collect()
+//!     }
+//! }
+//!
+//! // predicts the sum of the features
+//! struct Sum;
+//!
+//! impl Estimator<f64, f64> for Sum {
+//!     fn fit(&mut self, _x: &Matrix<f64>, _y: &[f64]) { }
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+//!         x.row_iter().map(|r| r.iter().sum()).collect()
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let x = mat![1.0, 2.0; 3.0, 4.0; 5.0, 6.0; 7.0, 8.0];
+//! let y = vec![3.0, 7.0, 11.0, 15.0];
+//!
+//! let mut s = Stacking::new(Sum).add(Column(0)).add(Column(1)).folds(2);
+//! s.fit(&x, &y);
+//! assert_eq!(s.predict(&x), y);
+//! # }
+//! ```
+extern crate num;
+
+use self::num::traits::ToPrimitive;
+
+use matrix::Matrix;
+use model_selection::{Estimator, ProbabilisticClassifier, KFold, shuffled_indexes};
+
+/// Stacking (or blending) ensemble of estimators with a meta-learner.
+///
+/// The labels predicted by the base estimators are converted into `f64`
+/// features for the meta-learner, e.g. class labels `0` and `1` become the
+/// features `0.0` and `1.0`.
+pub struct Stacking<L> {
+    estimators: Vec<Box<dyn Estimator<f64, L>>>,
+    meta: Box<dyn Estimator<f64, L>>,
+    folds: usize,
+    seed: Option<[u32; 4]>,
+    holdout: Option<f64>,
+    passthrough: bool
+}
+
+impl <L: Clone + ToPrimitive> Stacking<L> {
+
+    /// Creates an ensemble without base estimators which uses `meta` as the
+    /// meta-learner and a 5-fold cross-validation without shuffling.
+    pub fn new<M: Estimator<f64, L> + 'static>(meta: M) -> Stacking<L> {
+        Stacking {
+            estimators: vec![],
+            meta: Box::new(meta),
+            folds: 5,
+            seed: None,
+            holdout: None,
+            passthrough: false
+        }
+    }
+
+    /// Adds a base estimator.
+    pub fn add<E: Estimator<f64, L> + 'static>(mut self, estimator: E) -> Stacking<L> {
+        self.estimators.push(Box::new(estimator));
+        self
+    }
+
+    /// Sets the number of folds of the cross-validation which computes the
+    /// out-of-fold predictions.
+    ///
+    /// Panics if `k < 2`.
+    pub fn folds(mut self, k: usize) -> Stacking<L> {
+
+        assert!(k >= 2, "Number of folds must be at least 2.");
+        self.folds = k;
+        self
+    }
+
+    /// Shuffles the examples before they are divided into folds (or into
+    /// the training set and the holdout set). The seed must not be
+    /// `[0, 0, 0, 0]`.
+    pub fn shuffle(mut self, seed: [u32; 4]) -> Stacking<L> {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Uses blending instead of stacking: the base estimators are trained
+    /// once on the examples which are not in the holdout set and the
+    /// meta-learner is trained on their predictions of the holdout set,
+    /// which contains the given fraction of the examples (the last examples
+    /// if the examples are not shuffled).
+    ///
+    /// Panics if `fraction` is not within (0, 1).
+    pub fn blend(mut self, fraction: f64) -> Stacking<L> {
+
+        assert!(fraction > 0.0 && fraction < 1.0, "Fraction of the holdout set must be within (0, 1).");
+        self.holdout = Some(fraction);
+        self
+    }
+
+    /// If `true` the meta-learner gets the original features in addition to
+    /// the predictions of the base estimators.
+    pub fn passthrough(mut self, passthrough: bool) -> Stacking<L> {
+        self.passthrough = passthrough;
+        self
+    }
+
+    /// Returns the number of base estimators.
+    pub fn len(&self) -> usize {
+        self.estimators.len()
+    }
+
+    /// Returns `true` if the ensemble has no base estimators.
+    pub fn is_empty(&self) -> bool {
+        self.estimators.is_empty()
+    }
+
+    /// Returns the features of the meta-learner for the rows of `x`, i.e.
+    /// one column with the predictions of each base estimator (followed by
+    /// the columns of `x` if `passthrough` is set).
+    pub fn meta_features(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        let preds: Vec<Vec<L>> = self.estimators.iter().map(|e| e.predict(x)).collect();
+        self.combine(x, &preds)
+    }
+
+    fn combine(&self, x: &Matrix<f64>, preds: &[Vec<L>]) -> Matrix<f64> {
+
+        let cols = preds.len() + if self.passthrough { x.cols() } else { 0 };
+        let mut v = Vec::with_capacity(x.rows() * cols);
+        for i in 0..x.rows() {
+            v.extend(preds.iter().map(|p| p[i].to_f64().expect("Label cannot be converted into f64.")));
+            if self.passthrough {
+                v.extend_from_slice(x.row(i).unwrap());
+            }
+        }
+        Matrix::from_vec(v, x.rows(), cols)
+    }
+
+    fn indexes(&self, n: usize) -> Vec<usize> {
+        match self.seed {
+            Some(s) => shuffled_indexes(n, s),
+            None => (0..n).collect()
+        }
+    }
+}
+
+impl <L: Clone + ToPrimitive> Estimator<f64, L> for Stacking<L> {
+
+    /// Trains the base estimators and the meta-learner.
+    ///
+    /// Panics if the ensemble has no base estimators, if the number of rows
+    /// of `x` does not match the number of labels or if there are fewer
+    /// examples than folds.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[L]) {
+
+        assert!(!self.estimators.is_empty(), "Ensemble has no base estimators.");
+        assert!(x.rows() == y.len(), "Number of rows and number of labels must be equal.");
+
+        let idx = self.indexes(x.rows());
+        if let Some(f) = self.holdout {
+            let n = ((x.rows() as f64) * f).round() as usize;
+            assert!(n > 0 && n < x.rows(), "Holdout set and training set must not be empty.");
+            let (train, test) = idx.split_at(x.rows() - n);
+            let xt = x.select_rows(train);
+            let yt: Vec<L> = train.iter().map(|&i| y[i].clone()).collect();
+            for e in &mut self.estimators {
+                e.fit(&xt, &yt);
+            }
+            let xh = x.select_rows(test);
+            let yh: Vec<L> = test.iter().map(|&i| y[i].clone()).collect();
+            let z = self.meta_features(&xh);
+            self.meta.fit(&z, &yh);
+            return;
+        }
+
+        // out-of-fold predictions of each base estimator
+        let mut preds: Vec<Vec<Option<L>>> = vec![vec![None; x.rows()]; self.estimators.len()];
+        let folds = KFold::new(x.rows(), self.folds);
+        for (train, test) in folds {
+            let train: Vec<usize> = train.iter().map(|&i| idx[i]).collect();
+            let test: Vec<usize> = test.iter().map(|&i| idx[i]).collect();
+            let xt = x.select_rows(&train);
+            let yt: Vec<L> = train.iter().map(|&i| y[i].clone()).collect();
+            let xv = x.select_rows(&test);
+            for (e, p) in self.estimators.iter_mut().zip(preds.iter_mut()) {
+                e.fit(&xt, &yt);
+                for (&i, l) in test.iter().zip(e.predict(&xv)) {
+                    p[i] = Some(l);
+                }
+            }
+        }
+        let preds: Vec<Vec<L>> = preds.into_iter().map(|p| p.into_iter().map(|l| l.unwrap()).collect()).collect();
+        let z = self.combine(x, &preds);
+        self.meta.fit(&z, y);
+
+        for e in &mut self.estimators {
+            e.fit(x, y);
+        }
+    }
+
+    /// Predicts the labels with the meta-learner on the predictions of the
+    /// base estimators.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<L> {
+        self.meta.predict(&self.meta_features(x))
+    }
+}
+
+/// How a [VotingClassifier](struct.VotingClassifier.html) combines the
+/// predictions of its classifiers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Voting {
+    /// Each classifier votes for its predicted label.
+    Hard,
+    /// The class probabilities of the classifiers are averaged.
+    Soft
+}
+
+enum Member {
+    Labels(Box<dyn Estimator<f64, usize>>),
+    Probabilities(Box<dyn ProbabilisticClassifier>)
+}
+
+impl Member {
+
+    fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+        match *self {
+            Member::Labels(ref e) => e.predict(x),
+            Member::Probabilities(ref e) => e.predict(x)
+        }
+    }
+
+    fn fit(&mut self, x: &Matrix<f64>, y: &[usize]) {
+        match *self {
+            Member::Labels(ref mut e) => e.fit(x, y),
+            Member::Probabilities(ref mut e) => e.fit(x, y)
+        }
+    }
+}
+
+/// Ensemble of classifiers which predicts the class with the largest
+/// (weighted) number of votes or the largest (weighted) mean probability.
+///
+/// Ties are resolved in favour of the smallest label.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::model_selection::Estimator;
+/// use rustml::ensemble::{VotingClassifier, Voting};
+///
+/// // predicts class 1 if the feature is larger than a threshold
+/// struct Threshold(f64);
+///
+/// impl Estimator<f64, usize> for Threshold {
+///     fn fit(&mut self, _x: &Matrix<f64>, _y: &[usize]) { }
+///     fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+///         x.row_iter().map(|r| if r[0] > self.0 { 1 } else { 0 }).collect()
+///     }
+/// }
+///
+/// # fn main() {
+/// let v = VotingClassifier::new(Voting::Hard)
+///     .add(Threshold(1.0), 1.0)
+///     .add(Threshold(2.0), 1.0)
+///     .add(Threshold(3.0), 1.0);
+///
+/// assert_eq!(v.predict(&mat![0.0; 1.5; 2.5; 3.5]), vec![0, 0, 1, 1]);
+/// # }
+/// ```
+pub struct VotingClassifier {
+    voting: Voting,
+    members: Vec<(Member, f64)>,
+    n_classes: usize
+}
+
+impl VotingClassifier {
+
+    /// Creates an empty ensemble with the given kind of voting.
+    pub fn new(voting: Voting) -> VotingClassifier {
+        VotingClassifier {
+            voting: voting,
+            members: vec![],
+            n_classes: 0
+        }
+    }
+
+    /// Adds a classifier which can only predict labels with the weight
+    /// `weight`.
+    ///
+    /// Panics if the ensemble uses soft voting or if the weight is negative.
+    pub fn add<E: Estimator<f64, usize> + 'static>(mut self, classifier: E, weight: f64) -> VotingClassifier {
+
+        assert!(self.voting == Voting::Hard, "Soft voting needs classifiers which predict probabilities.");
+        assert!(weight >= 0.0, "Weight must not be negative.");
+        self.members.push((Member::Labels(Box::new(classifier)), weight));
+        self
+    }
+
+    /// Adds a classifier which can predict class probabilities with the
+    /// weight `weight`.
+    ///
+    /// Panics if the weight is negative.
+    pub fn add_probabilistic<E: ProbabilisticClassifier + 'static>(mut self, classifier: E, weight: f64) -> VotingClassifier {
+
+        assert!(weight >= 0.0, "Weight must not be negative.");
+        self.members.push((Member::Probabilities(Box::new(classifier)), weight));
+        self
+    }
+
+    /// Sets the number of classes. By default the number of classes is
+    /// derived from the predictions.
+    pub fn n_classes(mut self, n: usize) -> VotingClassifier {
+        self.n_classes = n;
+        self
+    }
+
+    /// Returns the number of classifiers.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Returns `true` if the ensemble has no classifiers.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns for each row in `x` the weighted fraction of the votes
+    /// (hard voting) or the weighted mean of the probabilities (soft
+    /// voting) of each class.
+    ///
+    /// Panics if the ensemble has no classifiers or if all weights are zero.
+    pub fn scores(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(!self.members.is_empty(), "Ensemble has no classifiers.");
+        let total: f64 = self.members.iter().map(|&(_, w)| w).sum();
+        assert!(total > 0.0, "Sum of the weights must be greater than zero.");
+
+        let n = x.rows();
+        let mut scores: Vec<Vec<f64>> = vec![vec![0.0; self.n_classes]; n];
+        for &(ref m, w) in &self.members {
+            match (self.voting, m) {
+                (Voting::Soft, &Member::Probabilities(ref e)) => {
+                    let p = e.predict_proba(x);
+                    for (s, r) in scores.iter_mut().zip(p.row_iter()) {
+                        add_scaled(s, r.iter().cloned().enumerate(), w / total);
+                    }
+                }
+                _ => {
+                    for (s, l) in scores.iter_mut().zip(m.predict(x)) {
+                        add_scaled(s, Some((l, 1.0)).into_iter(), w / total);
+                    }
+                }
+            }
+        }
+        let k = scores.iter().map(|s| s.len()).max().unwrap_or(0);
+        let v = scores.into_iter().flat_map(|mut s| { s.resize(k, 0.0); s }).collect();
+        Matrix::from_vec(v, n, k)
+    }
+}
+
+fn add_scaled<I: Iterator<Item = (usize, f64)>>(s: &mut Vec<f64>, it: I, w: f64) {
+
+    for (j, p) in it {
+        if j >= s.len() {
+            s.resize(j + 1, 0.0);
+        }
+        s[j] += w * p;
+    }
+}
+
+impl Estimator<f64, usize> for VotingClassifier {
+
+    /// Fits all classifiers on the examples in `x` with the labels `y`.
+    /// This is not necessary if the classifiers have been fitted before.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[usize]) {
+        for &mut (ref mut m, _) in &mut self.members {
+            m.fit(x, y);
+        }
+    }
+
+    /// Predicts the class with the highest score for each row in `x`.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+
+        self.scores(x).row_iter()
+            .map(|r| r.iter().enumerate().fold((0, ::std::f64::MIN), |b, (j, &s)| if s > b.1 { (j, s) } else { b }).0)
+            .collect()
+    }
+}
+
+impl ProbabilisticClassifier for VotingClassifier {
+
+    /// Returns the [scores](#method.scores) of the classes.
+    fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+        self.scores(x)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    // least squares fit of the label to one column (without intercept)
+    struct ColumnFit(usize, f64);
+
+    impl Estimator<f64, f64> for ColumnFit {
+        fn fit(&mut self, x: &Matrix<f64>, y: &[f64]) {
+            let c = x.col(self.0).unwrap();
+            self.1 = c.iter().zip(y.iter()).map(|(a, b)| a * b).sum::<f64>() / c.iter().map(|a| a * a).sum::<f64>();
+        }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+            x.row_iter().map(|r| r[self.0] * self.1).collect()
+        }
+    }
+
+    // least squares fit of the label to two columns (without intercept)
+    struct PlaneFit(f64, f64);
+
+    impl Estimator<f64, f64> for PlaneFit {
+        fn fit(&mut self, x: &Matrix<f64>, y: &[f64]) {
+            let (mut a, mut b, mut c, mut u, mut v) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (r, t) in x.row_iter().zip(y.iter()) {
+                a += r[0] * r[0]; b += r[0] * r[1]; c += r[1] * r[1];
+                u += r[0] * t; v += r[1] * t;
+            }
+            let det = a * c - b * b;
+            self.0 = (u * c - v * b) / det;
+            self.1 = (a * v - b * u) / det;
+        }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+            x.row_iter().map(|r| r[0] * self.0 + r[1] * self.1).collect()
+        }
+    }
+
+    fn mse(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f64>() / a.len() as f64
+    }
+
+    #[test]
+    fn test_stacking() {
+
+        let x = Matrix::from_vec((0..40).flat_map(|i| vec![(i % 5) as f64, ((i * 7) % 11) as f64]).collect(), 40, 2);
+        let y: Vec<f64> = x.row_iter().map(|r| 3.0 * r[0] - r[1]).collect();
+
+        let mut base = ColumnFit(0, 0.0);
+        base.fit(&x, &y);
+        let base_err = mse(&base.predict(&x), &y);
+
+        let mut s = Stacking::new(PlaneFit(0.0, 0.0)).add(ColumnFit(0, 0.0)).add(ColumnFit(1, 0.0)).shuffle([1, 2, 3, 4]);
+        s.fit(&x, &y);
+        assert_eq!(s.len(), 2);
+        assert_eq!(s.meta_features(&x).cols(), 2);
+        assert!(mse(&s.predict(&x), &y) < base_err / 10.0);
+
+        let mut b = Stacking::new(PlaneFit(0.0, 0.0)).add(ColumnFit(0, 0.0)).add(ColumnFit(1, 0.0)).blend(0.25);
+        b.fit(&x, &y);
+        assert!(mse(&b.predict(&x), &y) < 1e-12);
+
+        let p = Stacking::new(PlaneFit(0.0, 0.0)).add(ColumnFit(0, 0.0)).passthrough(true);
+        assert_eq!(p.meta_features(&x).cols(), 3);
+    }
+
+    // probability of class 1 is the feature clipped to [0, 1]
+    struct Clip;
+
+    impl Estimator<f64, usize> for Clip {
+        fn fit(&mut self, _x: &Matrix<f64>, _y: &[usize]) { }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+            x.row_iter().map(|r| if r[0] > 0.5 { 1 } else { 0 }).collect()
+        }
+    }
+
+    impl ProbabilisticClassifier for Clip {
+        fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+            let v = x.row_iter().flat_map(|r| { let p = r[0].max(0.0).min(1.0); vec![1.0 - p, p] }).collect();
+            Matrix::from_vec(v, x.rows(), 2)
+        }
+    }
+
+    struct Constant(usize);
+
+    impl Estimator<f64, usize> for Constant {
+        fn fit(&mut self, _x: &Matrix<f64>, _y: &[usize]) { }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+            vec![self.0; x.rows()]
+        }
+    }
+
+    #[test]
+    fn test_voting() {
+
+        let x = Matrix::from_vec(vec![0.0, 0.4, 0.6, 1.0], 4, 1);
+
+        // the constant classifier outvotes the other one
+        let h = VotingClassifier::new(Voting::Hard).add_probabilistic(Clip, 1.0).add(Constant(2), 2.0);
+        assert_eq!(h.predict(&x), vec![2; 4]);
+        let p = h.predict_proba(&x);
+        assert_eq!(p.cols(), 3);
+        assert!(p.row(0).unwrap().similar(&[1.0 / 3.0, 0.0, 2.0 / 3.0], 1e-12));
+
+        // ties are resolved in favour of the smallest label
+        let t = VotingClassifier::new(Voting::Hard).add(Constant(1), 1.0).add(Constant(0), 1.0);
+        assert_eq!(t.predict(&x), vec![0; 4]);
+
+        // soft voting of a confident and an unconfident classifier
+        let s = VotingClassifier::new(Voting::Soft).add_probabilistic(Clip, 1.0).n_classes(3);
+        let p = s.predict_proba(&x);
+        assert_eq!(p.cols(), 3);
+        assert!(p.row(1).unwrap().similar(&[0.6, 0.4, 0.0], 1e-12));
+        assert_eq!(s.predict(&x), vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_soft_voting_needs_probabilities() {
+        VotingClassifier::new(Voting::Soft).add(Constant(0), 1.0);
+    }
+}
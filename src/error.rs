@@ -0,0 +1,111 @@
+//! Errors of the fallible functions of this library.
+//!
+//! Many functions panic if they are called with invalid arguments, e.g. if
+//! the dimensions of two matrices do not match. For these functions
+//! fallible variants with the prefix `try_` exist (e.g.
+//! [Matrix::try_from_vec](../matrix/struct.Matrix.html#method.try_from_vec),
+//! [NeuralNetwork::try_predict](../nn/struct.NeuralNetwork.html#method.try_predict))
+//! which return a [RustmlError](enum.RustmlError.html) instead. The
+//! panicking functions are thin wrappers around them which panic with the
+//! message of the error.
+//!
+//! `RustmlError` is only used by these `try_` variants of matrices and
+//! neural networks and by the configuration of the
+//! [backend](../backend/index.html). Functions which are fallible because
+//! of their input data, e.g. the reading and parsing of files in the module
+//! [io](../io/index.html), the loading of models or the fitting of
+//! estimators which fails for degenerate data (e.g.
+//! [MultivariateNormal::fit](../distributions/struct.MultivariateNormal.html#method.fit)),
+//! return `Result<_, String>` with a message which describes the problem. A `RustmlError` can be converted into such a message with
+//! `into()`, so that both kinds of errors can be combined with `try!`:
+//!
+//! ```
+//! use rustml::matrix::Matrix;
+//!
+//! fn parse(s: &str) -> Result<Matrix<f64>, String> {
+//!     let v = try!(s.split(',').map(|x| x.trim().parse::<f64>().map_err(|e| e.to_string())).collect());
+//!     Ok(try!(Matrix::try_from_vec(v, 2, 2)))
+//! }
+//!
+//! assert!(parse("1, 2, 3, 4").is_ok());
+//! assert!(parse("1, 2, 3").unwrap_err().starts_with("Number of elements"));
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::matrix::Matrix;
+//! use rustml::error::RustmlError;
+//!
+//! match Matrix::try_from_vec(vec![1.0, 2.0, 3.0], 2, 2) {
+//!     Err(RustmlError::DimensionMismatch(_)) => { },
+//!     _ => panic!("expected an error")
+//! }
+//! ```
+use std::error::Error;
+use std::fmt;
+
+/// Errors of the fallible functions of this library.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RustmlError {
+    /// The dimensions of the arguments do not match.
+    DimensionMismatch(String),
+    /// The layer with the given index does not exist.
+    NoSuchLayer(usize),
+    /// A neural network has fewer layers than required for the operation.
+    MissingLayers(String),
+    /// An argument has an invalid value.
+    InvalidArgument(String)
+}
+
+impl fmt::Display for RustmlError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RustmlError::DimensionMismatch(ref s) |
+            RustmlError::MissingLayers(ref s) |
+            RustmlError::InvalidArgument(ref s) => write!(f, "{}", s),
+            RustmlError::NoSuchLayer(_) => write!(f, "Layer does not exist.")
+        }
+    }
+}
+
+impl Error for RustmlError { }
+
+impl From<RustmlError> for String {
+
+    fn from(e: RustmlError) -> String {
+        e.to_string()
+    }
+}
+
+/// Returns the value of `r` or panics with the message of the error. This
+/// function is used by the panicking wrappers of the fallible functions.
+pub fn unwrap_or_panic<T>(r: Result<T, RustmlError>) -> T {
+    match r {
+        Ok(v) => v,
+        Err(e) => panic!("{}", e)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+
+        assert_eq!(RustmlError::NoSuchLayer(3).to_string(), "Layer does not exist.");
+        let s: String = RustmlError::DimensionMismatch("abc".to_string()).into();
+        assert_eq!(s, "abc");
+        assert_eq!(unwrap_or_panic::<usize>(Ok(1)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "bad value")]
+    fn test_unwrap_or_panic() {
+        unwrap_or_panic::<usize>(Err(RustmlError::InvalidArgument("bad value".to_string())));
+    }
+}
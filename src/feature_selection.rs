@@ -0,0 +1,534 @@
+//! Module to select the most informative features of a dataset.
+//!
+//! Each feature (i.e. column) is scored individually by a statistical test
+//! of its dependency with the class labels. [`SelectKBest`](struct.SelectKBest.html)
+//! keeps the `k` features with the highest scores, so that high-dimensional
+//! datasets can be reduced before a model is trained. The recursive feature
+//! elimination [`Rfe`](struct.Rfe.html) instead uses the importances of the
+//! features that are learned by a model.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::feature_selection::*;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     1.0, 5.0, 0.0;
+//!     1.1, 3.0, 0.0;
+//!     3.0, 4.0, 1.0;
+//!     3.1, 5.0, 1.0
+//! ];
+//! let y = vec![0, 0, 1, 1];
+//!
+//! let s = SelectKBest::fit(&x, &y, 2, ScoreFunction::FClassif);
+//! assert_eq!(s.selected(), &[0, 2]);
+//! assert_eq!(s.transform(&x), mat![1.0, 0.0; 1.1, 0.0; 3.0, 1.0; 3.1, 1.0]);
+//! # }
+//! ```
+use std::cmp::Ordering;
+use std::f64;
+
+use matrix::Matrix;
+use model_selection::Estimator;
+use discretization::{KBinsDiscretizer, BinStrategy, BinEncoding};
+use io::model::{Model, Encoder, Decoder};
+use regression::Hypothesis;
+use groupby::label_indexes;
+
+/// The statistical test that is used to score the features.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreFunction {
+    /// Chi-squared statistic, see [`chi2`](fn.chi2.html).
+    Chi2,
+    /// ANOVA F-value, see [`f_classif`](fn.f_classif.html).
+    FClassif,
+    /// Mutual information, see [`mutual_info`](fn.mutual_info.html).
+    MutualInfo
+}
+
+/// Computes the chi-squared statistic between each feature and the class
+/// labels.
+///
+/// The features must not be negative, e.g. counts or frequencies of words.
+/// For each feature the observed sum of the feature per class is compared
+/// with the sum that is expected if the feature were independent of the
+/// class. Features with a sum of zero get a score of zero.
+///
+/// Panics if `x` contains negative values or if the number of rows of `x`
+/// does not match the number of labels.
+pub fn chi2<L: Ord>(x: &Matrix<f64>, y: &[L]) -> Vec<f64> {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+    assert!(x.iter().all(|&v| v >= 0.0), "Features must not be negative.");
+
+    let (c, k) = label_indexes(y);
+    let n = y.len() as f64;
+    let mut prior = vec![0.0; k];
+    for &i in &c {
+        prior[i] += 1.0 / n;
+    }
+
+    x.col_iter()
+        .map(|col| {
+            let mut observed = vec![0.0; k];
+            for (v, &i) in col.iter().zip(c.iter()) {
+                observed[i] += *v;
+            }
+            let total = col.iter().fold(0.0, |acc, v| acc + v);
+            observed.iter().zip(prior.iter())
+                .map(|(o, p)| (o, p * total))
+                .filter(|&(_, e)| e > 0.0)
+                .fold(0.0, |acc, (o, e)| acc + (o - e) * (o - e) / e)
+        })
+        .collect()
+}
+
+/// Computes the ANOVA F-value between each feature and the class labels.
+///
+/// The F-value is the ratio of the variance between the class means and the
+/// variance within the classes. If the variance within the classes is zero
+/// the score is infinity (or zero if the variance between the classes is
+/// also zero).
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+pub fn f_classif<L: Ord>(x: &Matrix<f64>, y: &[L]) -> Vec<f64> {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let (c, k) = label_indexes(y);
+    let n = y.len();
+
+    x.col_iter()
+        .map(|col| {
+            let mut sums = vec![0.0; k];
+            let mut counts = vec![0.0; k];
+            for (v, &i) in col.iter().zip(c.iter()) {
+                sums[i] += *v;
+                counts[i] += 1.0;
+            }
+            let mean = col.iter().fold(0.0, |acc, v| acc + v) / n as f64;
+            let means: Vec<f64> = sums.iter().zip(counts.iter()).map(|(s, c)| s / c).collect();
+
+            let ssb = (0..k).fold(0.0, |acc, i| acc + counts[i] * (means[i] - mean).powi(2));
+            let ssw = col.iter().zip(c.iter()).fold(0.0, |acc, (v, &i)| acc + (v - means[i]).powi(2));
+
+            if k < 2 || ssb == 0.0 {
+                0.0
+            } else if ssw == 0.0 || n <= k {
+                f64::INFINITY
+            } else {
+                (ssb / (k - 1) as f64) / (ssw / (n - k) as f64)
+            }
+        })
+        .collect()
+}
+
+/// Estimates the mutual information (in nats) between each feature and the
+/// class labels.
+///
+/// Each feature is discretized into `n_bins` bins of equal width and the
+/// mutual information between the bins and the classes is computed from
+/// their joint frequencies.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if `x` has no rows.
+pub fn mutual_info<L: Ord>(x: &Matrix<f64>, y: &[L], n_bins: usize) -> Vec<f64> {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let (c, k) = label_indexes(y);
+    let n = y.len() as f64;
+    let d = KBinsDiscretizer::fit(x, n_bins, BinStrategy::Uniform, BinEncoding::Ordinal);
+    let bins = d.transform(x);
+
+    (0..x.cols())
+        .map(|j| {
+            let b = d.n_bins(j);
+            let mut joint = vec![0.0; b * k];
+            for (row, &i) in bins.row_iter().zip(c.iter()) {
+                joint[row[j] as usize * k + i] += 1.0 / n;
+            }
+            let px: Vec<f64> = (0..b).map(|u| joint[u * k..(u + 1) * k].iter().fold(0.0, |a, p| a + p)).collect();
+            let py: Vec<f64> = (0..k).map(|i| (0..b).fold(0.0, |a, u| a + joint[u * k + i])).collect();
+
+            let mut mi = 0.0;
+            for u in 0..b {
+                for i in 0..k {
+                    let p = joint[u * k + i];
+                    if p > 0.0 {
+                        mi += p * (p / (px[u] * py[i])).ln();
+                    }
+                }
+            }
+            mi.max(0.0)
+        })
+        .collect()
+}
+
+/// Selects the `k` features with the highest scores.
+#[derive(Clone, Debug)]
+pub struct SelectKBest {
+    scores: Vec<f64>,
+    selected: Vec<usize>
+}
+
+impl SelectKBest {
+
+    /// Scores the features of `x` with the given score function and selects
+    /// the `k` best ones. If several features have the same score the
+    /// feature with the smaller index is preferred.
+    ///
+    /// Panics if `k` is larger than the number of columns of `x` or if the
+    /// number of rows of `x` does not match the number of labels.
+    pub fn fit<L: Ord>(x: &Matrix<f64>, y: &[L], k: usize, score: ScoreFunction) -> SelectKBest {
+
+        assert!(k <= x.cols(), "Cannot select more features than available.");
+
+        let scores = match score {
+            ScoreFunction::Chi2 => chi2(x, y),
+            ScoreFunction::FClassif => f_classif(x, y),
+            ScoreFunction::MutualInfo => mutual_info(x, y, 10)
+        };
+        SelectKBest::from_scores(scores, k)
+    }
+
+    /// Selects the `k` features with the highest of the given scores.
+    ///
+    /// Panics if `k` is larger than the number of scores.
+    pub fn from_scores(scores: Vec<f64>, k: usize) -> SelectKBest {
+
+        assert!(k <= scores.len(), "Cannot select more features than available.");
+
+        let mut idx: Vec<usize> = (0..scores.len()).collect();
+        idx.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(Ordering::Equal));
+        let mut selected = idx[..k].to_vec();
+        selected.sort();
+
+        SelectKBest {
+            scores: scores,
+            selected: selected
+        }
+    }
+
+    /// Returns the score of each feature.
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+
+    /// Returns the (sorted) indexes of the selected features.
+    pub fn selected(&self) -> &[usize] {
+        &self.selected
+    }
+
+    /// Returns a mask in which the element of each selected feature is
+    /// `true`.
+    pub fn support(&self) -> Vec<bool> {
+        (0..self.scores.len()).map(|i| self.selected.contains(&i)).collect()
+    }
+
+    /// Returns a matrix which contains only the selected columns of `x`.
+    ///
+    /// Panics if the number of columns of `x` does not match the number of
+    /// features used during `fit`.
+    pub fn transform<T: Clone>(&self, x: &Matrix<T>) -> Matrix<T> {
+
+        assert!(x.cols() == self.scores.len(),
+            "Number of columns does not match the number of features.");
+
+        select_columns(x, &self.selected)
+    }
+}
+
+/// Returns a matrix which contains only the given columns of `x`.
+fn select_columns<T: Clone>(x: &Matrix<T>, cols: &[usize]) -> Matrix<T> {
+
+    let mut v = Vec::with_capacity(x.rows() * cols.len());
+    for row in x.row_iter() {
+        v.extend(cols.iter().map(|&j| row[j].clone()));
+    }
+    Matrix::from_vec(v, x.rows(), cols.len())
+}
+
+/// Trait for models which can report how important each feature is, e.g.
+/// the coefficients of a linear model.
+pub trait FeatureImportance {
+
+    /// Returns one value per feature (i.e. per column of the examples the
+    /// model has been trained on). The larger the absolute value the more
+    /// important is the feature.
+    fn feature_importances(&self) -> Vec<f64>;
+}
+
+/// The importance of a feature is the magnitude of its parameter. If the
+/// hypothesis has been trained on a design matrix the first importance is
+/// the one of the bias column. The features should have the same scale,
+/// e.g. after a [StandardScaler](../scaling/struct.StandardScaler.html).
+impl FeatureImportance for Hypothesis {
+
+    fn feature_importances(&self) -> Vec<f64> {
+        self.params().iter().map(|p| p.abs()).collect()
+    }
+}
+
+/// Recursive feature elimination.
+///
+/// Starting with all features an estimator is trained repeatedly. After
+/// each training the `step` features with the smallest absolute importance
+/// are removed until only the requested number of features remains.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::regression::Hypothesis;
+/// use rustml::feature_selection::*;
+///
+/// # fn main() {
+/// // the importances of a linear model are the magnitudes of its parameters
+/// let x = mat![
+///     1.0, 0.0, -1.0;
+///     0.0, 1.0,  1.0;
+///     1.0, 1.0,  0.0;
+///     0.5, 2.0,  1.0
+/// ];
+/// let y: Vec<f64> = x.row_iter().map(|r| 0.1 * r[0] - 2.0 * r[1] + 5.0 * r[2]).collect();
+/// let r = Rfe::fit(&Hypothesis::from_params(&[]), &x, &y, 1, 1);
+/// assert_eq!(r.selected(), vec![2]);
+/// assert_eq!(r.ranking(), &[3, 2, 1]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rfe {
+    ranking: Vec<usize>
+}
+
+impl Rfe {
+
+    /// Eliminates features until `n_features` features remain. In each
+    /// iteration a copy of `estimator` is trained on the remaining features
+    /// and the `step` least important features are removed (fewer in the
+    /// last iteration if necessary).
+    ///
+    /// Panics if `n_features` is zero or larger than the number of columns
+    /// of `x`, if `step` is zero or if the estimator does not report one
+    /// importance per feature.
+    pub fn fit<T, L, E>(estimator: &E, x: &Matrix<T>, y: &[L], n_features: usize, step: usize) -> Rfe
+        where T: Clone, E: Estimator<T, L> + FeatureImportance + Clone {
+
+        assert!(n_features > 0 && n_features <= x.cols(), "Invalid number of features.");
+        assert!(step > 0, "Step must be at least 1.");
+
+        let mut remaining: Vec<usize> = (0..x.cols()).collect();
+        let mut eliminated: Vec<Vec<usize>> = Vec::new();
+
+        while remaining.len() > n_features {
+            let mut e = estimator.clone();
+            e.fit(&select_columns(x, &remaining), y);
+            let imp = e.feature_importances();
+            assert!(imp.len() == remaining.len(), "Invalid number of feature importances.");
+
+            let mut order: Vec<usize> = (0..remaining.len()).collect();
+            order.sort_by(|&a, &b| imp[a].abs().partial_cmp(&imp[b].abs()).unwrap_or(Ordering::Equal));
+
+            let n = step.min(remaining.len() - n_features);
+            let drop: Vec<usize> = order[..n].iter().map(|&i| remaining[i]).collect();
+            remaining.retain(|i| !drop.contains(i));
+            eliminated.push(drop);
+        }
+
+        let mut ranking = vec![1; x.cols()];
+        for (r, d) in eliminated.iter().rev().enumerate() {
+            for &i in d {
+                ranking[i] = r + 2;
+            }
+        }
+
+        Rfe {
+            ranking: ranking
+        }
+    }
+
+    /// Returns the rank of each feature. Selected features have rank 1,
+    /// the features removed in the last iteration have rank 2 and so on.
+    pub fn ranking(&self) -> &[usize] {
+        &self.ranking
+    }
+
+    /// Returns the (sorted) indexes of the selected features.
+    pub fn selected(&self) -> Vec<usize> {
+        (0..self.ranking.len()).filter(|&i| self.ranking[i] == 1).collect()
+    }
+
+    /// Returns a mask in which the element of each selected feature is
+    /// `true`.
+    pub fn support(&self) -> Vec<bool> {
+        self.ranking.iter().map(|&r| r == 1).collect()
+    }
+
+    /// Returns a matrix which contains only the selected columns of `x`.
+    ///
+    /// Panics if the number of columns of `x` does not match the number of
+    /// features used during `fit`.
+    pub fn transform<T: Clone>(&self, x: &Matrix<T>) -> Matrix<T> {
+
+        assert!(x.cols() == self.ranking.len(),
+            "Number of columns does not match the number of features.");
+
+        select_columns(x, &self.selected())
+    }
+}
+
+impl Model for SelectKBest {
+
+    fn tag() -> &'static str { "rustml.feature_selection.SelectKBest" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.scores);
+        e.usizes(&self.selected);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<SelectKBest, String> {
+        Ok(SelectKBest { scores: try!(d.f64s()), selected: try!(d.usizes()) })
+    }
+}
+
+impl Model for Rfe {
+
+    fn tag() -> &'static str { "rustml.feature_selection.Rfe" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usizes(&self.ranking);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Rfe, String> {
+        Ok(Rfe { ranking: try!(d.usizes()) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use io::model::{write_model, read_model};
+
+    #[test]
+    fn test_chi2() {
+
+        let x = mat![
+            1.0, 1.0, 0.0;
+            1.0, 1.0, 0.0;
+            0.0, 1.0, 0.0;
+            0.0, 1.0, 0.0
+        ];
+        let y = vec!['a', 'a', 'b', 'b'];
+
+        // column 0: observed [2, 0], expected [1, 1]
+        assert_eq!(chi2(&x, &y), vec![2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_chi2_negative() {
+        chi2(&mat![-1.0; 1.0], &[0, 1]);
+    }
+
+    #[test]
+    fn test_f_classif() {
+
+        let x = mat![
+            1.0, 1.0, 2.0;
+            3.0, 1.0, 2.0;
+            5.0, 3.0, 2.0;
+            7.0, 3.0, 2.0
+        ];
+        let y = vec![0, 0, 1, 1];
+
+        // column 0: ssb = 16, ssw = 4, f = (16 / 1) / (4 / 2) = 8
+        let f = f_classif(&x, &y);
+        assert_eq!(f, vec![8.0, f64::INFINITY, 0.0]);
+    }
+
+    #[test]
+    fn test_mutual_info() {
+
+        let x = mat![
+            0.0, 0.0;
+            0.0, 1.0;
+            1.0, 0.0;
+            1.0, 1.0
+        ];
+        let y = vec![0, 0, 1, 1];
+
+        let mi = mutual_info(&x, &y, 2);
+        assert!((mi[0] - 2.0f64.ln()).abs() < 1e-10);
+        assert!(mi[1].abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_select_k_best() {
+
+        let s = SelectKBest::from_scores(vec![1.0, 3.0, 2.0, 3.0], 2);
+        assert_eq!(s.selected(), &[1, 3]);
+        assert_eq!(s.support(), vec![false, true, false, true]);
+        assert_eq!(s.transform(&mat![1, 2, 3, 4]), mat![2, 4]);
+
+        let x = mat![
+            1.0, 0.0, 0.0;
+            1.0, 0.0, 1.0;
+            0.0, 1.0, 0.0;
+            0.0, 1.0, 1.0
+        ];
+        let y = vec![0, 0, 1, 1];
+        for &f in &[ScoreFunction::Chi2, ScoreFunction::FClassif, ScoreFunction::MutualInfo] {
+            let s = SelectKBest::fit(&x, &y, 2, f);
+            assert_eq!(s.selected(), &[0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_hypothesis_importances() {
+
+        let h = Hypothesis::from_params(&[0.5, -2.0, 0.0]);
+        assert_eq!(h.feature_importances(), vec![0.5, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_rfe() {
+
+        // the importance of the features increases with their index and
+        // the signs of the parameters alternate
+        let x = mat![
+            0.3, 1.0, 0.2, 0.7, 0.1;
+            0.9, 0.4, 0.5, 0.1, 0.6;
+            0.2, 0.8, 0.9, 0.4, 0.3;
+            0.6, 0.1, 0.3, 0.9, 0.8;
+            0.5, 0.6, 0.7, 0.2, 0.9;
+            0.1, 0.3, 0.4, 0.5, 0.2;
+            0.8, 0.7, 0.1, 0.3, 0.4
+        ];
+        let y: Vec<f64> = x.row_iter().map(|r| 0.01 * r[0] - 0.1 * r[1] + r[2] - 10.0 * r[3] + 100.0 * r[4]).collect();
+        let h = Hypothesis::from_params(&[]);
+
+        let r = Rfe::fit(&h, &x, &y, 2, 1);
+        assert_eq!(r.ranking(), &[4, 3, 2, 1, 1]);
+        assert_eq!(r.selected(), vec![3, 4]);
+        assert_eq!(r.support(), vec![false, false, false, true, true]);
+        assert_eq!(r.transform(&x), Matrix::from_vec(x.row_iter().flat_map(|r| vec![r[3], r[4]]).collect(), 7, 2));
+
+        let mut buf = vec![];
+        write_model(&mut buf, &r).unwrap();
+        assert_eq!(read_model::<_, Rfe>(&buf[..]).unwrap().ranking(), r.ranking());
+
+        let r = Rfe::fit(&h, &x, &y, 2, 2);
+        assert_eq!(r.ranking(), &[3, 3, 2, 1, 1]);
+
+        let r = Rfe::fit(&h, &x, &y, 5, 1);
+        assert_eq!(r.ranking(), &[1, 1, 1, 1, 1]);
+    }
+}
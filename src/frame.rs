@@ -0,0 +1,319 @@
+//! Matrices with named and typed columns.
+//!
+//! A [Frame](struct.Frame.html) wraps a matrix of `f64` together with the
+//! names of its columns and the type of each column (see
+//! [ColumnType](../io/csv/enum.ColumnType.html)). Columns can be selected,
+//! dropped and appended by their names, e.g. to separate the features from
+//! the label before training a model. A frame can be created from a matrix
+//! or from a table loaded from a CSV file and converted back into a matrix
+//! at any time.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::frame::Frame;
+//!
+//! # fn main() {
+//! let f = Frame::new(mat![1.0, 2.0, 0.0; 3.0, 4.0, 1.0], &["a", "b", "label"]);
+//!
+//! let (x, y) = f.split_label("label").unwrap();
+//! assert_eq!(x.names(), &["a", "b"]);
+//! assert_eq!(y, vec![0.0, 1.0]);
+//!
+//! let g = x.select(&["b"]).unwrap().with_column("y", &y);
+//! assert!(g.matrix().eq(&mat![2.0, 0.0; 4.0, 1.0]));
+//! # }
+//! ```
+use std::collections::HashSet;
+
+use matrix::Matrix;
+use io::csv::{ColumnType, CsvTable};
+
+/// A matrix with named and typed columns.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    names: Vec<String>,
+    types: Vec<ColumnType>,
+    data: Matrix<f64>
+}
+
+fn check_names(names: &[String]) {
+
+    let s: HashSet<&String> = names.iter().collect();
+    assert!(s.len() == names.len(), "Column names must be unique.");
+}
+
+impl Frame {
+
+    /// Creates a frame from a matrix and the names of its columns. All
+    /// columns are numeric.
+    ///
+    /// Panics if the number of names is not the number of columns or if the
+    /// names are not unique.
+    pub fn new(m: Matrix<f64>, names: &[&str]) -> Frame {
+
+        assert!(names.len() == m.cols(), "Number of names must be the number of columns.");
+        let names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        check_names(&names);
+        Frame {
+            types: vec![ColumnType::Numeric; names.len()],
+            names: names,
+            data: m
+        }
+    }
+
+    /// Creates a frame from a matrix. The names of the columns are their
+    /// (zero-based) indexes and all columns are numeric.
+    pub fn from_matrix(m: Matrix<f64>) -> Frame {
+
+        let names: Vec<String> = (0..m.cols()).map(|i| i.to_string()).collect();
+        let n: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        Frame::new(m, &n)
+    }
+
+    /// Returns the names of the columns.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the types of the columns.
+    pub fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+
+    /// Returns the values.
+    pub fn matrix(&self) -> &Matrix<f64> {
+        &self.data
+    }
+
+    /// Returns the values and discards the names and the types of the
+    /// columns.
+    pub fn into_matrix(self) -> Matrix<f64> {
+        self.data
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.data.rows()
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.data.cols()
+    }
+
+    /// Returns the index of the column with the given name.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    fn indexes(&self, names: &[&str]) -> Result<Vec<usize>, String> {
+
+        names.iter()
+            .map(|n| self.index(n).ok_or(format!("Unknown column {}.", n)))
+            .collect()
+    }
+
+    /// Returns the values of the column with the given name.
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        self.index(name).and_then(|i| self.data.col(i))
+    }
+
+    /// Returns the type of the column with the given name.
+    pub fn column_type(&self, name: &str) -> Option<&ColumnType> {
+        self.index(name).map(|i| &self.types[i])
+    }
+
+    /// Sets the type of a column, e.g. to mark a column of integers as
+    /// categorical. Returns an error if there is no column with the given
+    /// name.
+    pub fn set_type(&self, name: &str, t: ColumnType) -> Result<Frame, String> {
+
+        let i = try!(self.indexes(&[name]))[0];
+        let mut f = self.clone();
+        f.types[i] = t;
+        Ok(f)
+    }
+
+    /// Returns the names of the categorical columns.
+    pub fn categorical(&self) -> Vec<&str> {
+
+        self.names.iter().zip(self.types.iter())
+            .filter(|&(_, t)| match *t { ColumnType::Categorical(_) => true, _ => false })
+            .map(|(n, _)| n.as_str())
+            .collect()
+    }
+
+    /// Returns a frame which contains the given columns in the given order.
+    /// Returns an error if a column does not exist.
+    ///
+    /// Panics if a name occurs more than once.
+    pub fn select(&self, names: &[&str]) -> Result<Frame, String> {
+
+        let idx = try!(self.indexes(names));
+        let names: Vec<String> = idx.iter().map(|&i| self.names[i].clone()).collect();
+        check_names(&names);
+        Ok(Frame {
+            names: names,
+            types: idx.iter().map(|&i| self.types[i].clone()).collect(),
+            data: self.data.sub_matrix(&(0..self.rows()).collect::<Vec<usize>>(), &idx)
+        })
+    }
+
+    /// Returns a frame without the given columns. Returns an error if a
+    /// column does not exist.
+    pub fn drop(&self, names: &[&str]) -> Result<Frame, String> {
+
+        let idx = try!(self.indexes(names));
+        let keep: Vec<&str> = (0..self.cols())
+            .filter(|i| !idx.contains(i))
+            .map(|i| self.names[i].as_str())
+            .collect();
+        self.select(&keep)
+    }
+
+    /// Returns a frame with an additional numeric column appended on the
+    /// right, e.g. to join the labels with the features.
+    ///
+    /// Panics if the number of values is not the number of rows or if a
+    /// column with the same name already exists.
+    pub fn with_column(&self, name: &str, values: &[f64]) -> Frame {
+        self.with_typed_column(name, values, ColumnType::Numeric)
+    }
+
+    /// Returns a frame with an additional column of the given type appended
+    /// on the right.
+    ///
+    /// Panics if the number of values is not the number of rows or if a
+    /// column with the same name already exists.
+    pub fn with_typed_column(&self, name: &str, values: &[f64], t: ColumnType) -> Frame {
+
+        assert!(self.index(name).is_none(), "Column names must be unique.");
+        // an empty frame gets the number of rows of the first column
+        let data = if self.cols() == 0 {
+            Matrix::from_vec(values.to_vec(), values.len(), 1)
+        } else {
+            assert!(values.len() == self.rows(), "Number of values must be the number of rows.");
+            self.data.insert_column(self.cols(), values)
+        };
+        let mut f = self.clone();
+        f.names.push(name.to_string());
+        f.types.push(t);
+        f.data = data;
+        f
+    }
+
+    /// Splits the frame into the frame without the column `name` and the
+    /// values of this column, e.g. to separate the features from the labels.
+    /// Returns an error if the column does not exist.
+    pub fn split_label(&self, name: &str) -> Result<(Frame, Vec<f64>), String> {
+
+        let y = try!(self.column(name).ok_or(format!("Unknown column {}.", name)));
+        Ok((try!(self.drop(&[name])), y))
+    }
+}
+
+impl From<CsvTable> for Frame {
+
+    fn from(t: CsvTable) -> Frame {
+
+        let types = t.types().to_vec();
+        let (data, names) = t.into_parts();
+        Frame {
+            names: names,
+            types: types,
+            data: data
+        }
+    }
+}
+
+impl From<Matrix<f64>> for Frame {
+
+    fn from(m: Matrix<f64>) -> Frame {
+        Frame::from_matrix(m)
+    }
+}
+
+impl From<Frame> for Matrix<f64> {
+
+    fn from(f: Frame) -> Matrix<f64> {
+        f.into_matrix()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use io::csv::{ColumnType, CsvLoader};
+
+    #[test]
+    fn test_frame_select_drop() {
+
+        let f = Frame::new(mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0], &["a", "b", "c"]);
+        assert_eq!((f.rows(), f.cols()), (2, 3));
+        assert_eq!(f.index("c"), Some(2));
+        assert_eq!(f.column("b"), Some(vec![2.0, 5.0]));
+        assert_eq!(f.column("d"), None);
+
+        let g = f.select(&["c", "a"]).unwrap();
+        assert_eq!(g.names(), &["c", "a"]);
+        assert!(g.matrix().eq(&mat![3.0, 1.0; 6.0, 4.0]));
+        assert!(f.select(&["x"]).is_err());
+
+        let g = f.drop(&["b"]).unwrap();
+        assert_eq!(g.names(), &["a", "c"]);
+        assert!(g.into_matrix().eq(&mat![1.0, 3.0; 4.0, 6.0]));
+        assert!(f.drop(&["x"]).is_err());
+    }
+
+    #[test]
+    fn test_frame_columns_types() {
+
+        let f = Frame::from_matrix(mat![1.0, 2.0; 3.0, 4.0]);
+        assert_eq!(f.names(), &["0", "1"]);
+
+        let c = ColumnType::Categorical(vec!["x".to_string(), "y".to_string()]);
+        let g = f.with_typed_column("cat", &[1.0, 0.0], c.clone()).set_type("0", ColumnType::Boolean).unwrap();
+        assert_eq!(g.types(), &[ColumnType::Boolean, ColumnType::Numeric, c.clone()]);
+        assert_eq!(g.column_type("cat"), Some(&c));
+        assert_eq!(g.categorical(), vec!["cat"]);
+        assert!(g.set_type("x", ColumnType::Numeric).is_err());
+
+        let (x, y) = g.split_label("cat").unwrap();
+        assert_eq!(y, vec![1.0, 0.0]);
+        assert_eq!(x.types(), &[ColumnType::Boolean, ColumnType::Numeric]);
+
+        let e = Frame::from_matrix(Matrix::new()).with_column("a", &[1.0, 2.0, 3.0]);
+        assert_eq!((e.rows(), e.cols()), (3, 1));
+    }
+
+    #[test]
+    fn test_frame_conversions() {
+
+        let t = CsvLoader::new().parse("x,city\n1,Berlin\n2,Paris\n").unwrap();
+        let f = Frame::from(t);
+        assert_eq!(f.names(), &["x", "city"]);
+        assert_eq!(f.categorical(), vec!["city"]);
+
+        let m: Matrix<f64> = f.into();
+        assert!(m.eq(&mat![1.0, 0.0; 2.0, 1.0]));
+        assert_eq!(Frame::from(m).cols(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_frame_duplicate_names() {
+        Frame::new(mat![1.0, 2.0], &["a", "a"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_frame_with_column_invalid_length() {
+        Frame::new(mat![1.0, 2.0], &["a", "b"]).with_column("c", &[1.0, 2.0]);
+    }
+}
@@ -17,13 +17,41 @@
 //! assert!(g.std() - 0.31623 <= 0.0001);
 //! # }
 //! ```
-//! 
+//!
+//! # Kernel density estimation
+//!
+//! A [KernelDensity](struct.KernelDensity.html) estimates the density of
+//! one- or multi-dimensional data nonparametrically as the mean of Gaussian
+//! kernels centered at the examples. The bandwidth of the kernels is either
+//! given or selected with Scott's rule, Silverman's rule or a leave-one-out
+//! cross-validation (see [Bandwidth](enum.Bandwidth.html)).
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::gaussian::*;
+//!
+//! # fn main() {
+//! let k = KernelDensity::fit_1d(&[1.0, 1.2, 0.8, 5.0, 5.1], Bandwidth::Fixed(0.5));
+//! let s = k.score_samples(&mat![1.0; 3.0; 5.0]);
+//! // the log density is higher close to the examples
+//! assert!(s[0] > s[1] && s[2] > s[1]);
+//!
+//! let x = k.sample(100, [1, 2, 3, 4]);
+//! assert_eq!((x.rows(), x.cols()), (100, 1));
+//! # }
+//! ```
 
 extern crate num;
+extern crate rand;
 
+use std::f64::consts::PI;
 use self::num::traits::Float;
-use math::{Mean, MeanVec, Var};
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+use self::rand::distributions::{Normal, IndependentSample};
+use math::{Mean, MeanVec, Var, Quantile};
 use math::{Dimension, Normalization};
+use matrix::Matrix;
 
 
 /// Trait to estimate the mean and the variance of a set of samples.
@@ -164,6 +192,167 @@ gaussian_impl!{ f32 f64 }
 
 // ----------------------------------------------------------------------------
 
+/// Selection of the bandwidth of a [KernelDensity](struct.KernelDensity.html).
+///
+/// For Scott's and Silverman's rule the bandwidth of each dimension is the
+/// standard deviation of the dimension (or `IQR / 1.34` if this is smaller
+/// and not zero) times a factor which decreases with the number of examples
+/// `n` and the number of dimensions `d`: `n^(-1 / (d + 4))` for Scott's rule
+/// and `(n * (d + 2) / 4)^(-1 / (d + 4))` for Silverman's rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Bandwidth {
+    /// Scott's rule of thumb.
+    Scott,
+    /// Silverman's rule of thumb.
+    Silverman,
+    /// The same given bandwidth for all dimensions.
+    Fixed(f64),
+    /// The bandwidth (the same for all dimensions) of the candidates which
+    /// maximizes the leave-one-out log-likelihood of the examples.
+    CrossValidation(Vec<f64>)
+}
+
+/// Kernel density estimate with Gaussian kernels.
+#[derive(Clone, Debug)]
+pub struct KernelDensity {
+    data: Matrix<f64>,
+    h: Vec<f64>
+}
+
+impl KernelDensity {
+
+    /// Estimates the density of the examples in the rows of `x`.
+    ///
+    /// Panics if `x` has no rows or no columns, if a fixed bandwidth or a
+    /// candidate is not positive or if there are no candidates.
+    pub fn fit(x: &Matrix<f64>, bandwidth: Bandwidth) -> KernelDensity {
+
+        assert!(x.rows() > 0 && x.cols() > 0, "Data must not be empty.");
+        let n = x.rows() as f64;
+        let d = x.cols() as f64;
+        let h = match bandwidth {
+            Bandwidth::Scott => KernelDensity::scales(x, n.powf(-1.0 / (d + 4.0))),
+            Bandwidth::Silverman => KernelDensity::scales(x, (n * (d + 2.0) / 4.0).powf(-1.0 / (d + 4.0))),
+            Bandwidth::Fixed(h) => {
+                assert!(h > 0.0, "Bandwidth must be greater than zero.");
+                vec![h; x.cols()]
+            }
+            Bandwidth::CrossValidation(candidates) => {
+                assert!(!candidates.is_empty(), "No candidates for the bandwidth.");
+                let mut best = (::std::f64::NEG_INFINITY, candidates[0]);
+                for &h in &candidates {
+                    assert!(h > 0.0, "Bandwidth must be greater than zero.");
+                    let k = KernelDensity { data: x.clone(), h: vec![h; x.cols()] };
+                    let l = k.loo_log_likelihood();
+                    if l > best.0 {
+                        best = (l, h);
+                    }
+                }
+                vec![best.1; x.cols()]
+            }
+        };
+        KernelDensity { data: x.clone(), h: h }
+    }
+
+    /// Estimates the density of one-dimensional values.
+    pub fn fit_1d(x: &[f64], bandwidth: Bandwidth) -> KernelDensity {
+        KernelDensity::fit(&Matrix::from_vec(x.to_vec(), x.len(), 1), bandwidth)
+    }
+
+    // robust scale of each column times the factor; constant columns get
+    // the factor as the bandwidth
+    fn scales(x: &Matrix<f64>, factor: f64) -> Vec<f64> {
+
+        x.col_iter()
+            .map(|c| {
+                let s = c.var(Dimension::Row, Normalization::MinusOne).sqrt();
+                let r = c.iqr() / 1.34;
+                let s = if r > 0.0 && r < s { r } else { s };
+                if s > 0.0 && s.is_finite() { s * factor } else { factor }
+            })
+            .collect()
+    }
+
+    /// Returns the bandwidth of each dimension.
+    pub fn bandwidth(&self) -> &[f64] {
+        &self.h
+    }
+
+    // log of the kernel of example i at x without the normalization
+    fn log_kernel(&self, i: usize, x: &[f64]) -> f64 {
+        let r = self.data.row(i).unwrap();
+        -0.5 * r.iter().zip(x.iter()).zip(self.h.iter()).map(|((a, b), h)| ((a - b) / h).powi(2)).sum::<f64>()
+    }
+
+    fn log_norm(&self) -> f64 {
+        -self.h.iter().map(|h| h.ln()).sum::<f64>() - 0.5 * self.h.len() as f64 * (2.0 * PI).ln()
+    }
+
+    /// Returns the logarithm of the estimated density at each row of `x`.
+    ///
+    /// Panics if the number of columns of `x` does not match the number of
+    /// dimensions.
+    pub fn score_samples(&self, x: &Matrix<f64>) -> Vec<f64> {
+
+        assert!(x.cols() == self.data.cols(), "Number of columns does not match the number of dimensions.");
+        let n = self.data.rows();
+        let c = self.log_norm() - (n as f64).ln();
+        x.row_iter()
+            .map(|r| log_sum_exp((0..n).map(|i| self.log_kernel(i, r))) + c)
+            .collect()
+    }
+
+    // sum of the log densities of each example estimated without it
+    fn loo_log_likelihood(&self) -> f64 {
+
+        let n = self.data.rows();
+        if n < 2 {
+            return 0.0;
+        }
+        let c = self.log_norm() - ((n - 1) as f64).ln();
+        (0..n)
+            .map(|j| {
+                let r = self.data.row(j).unwrap();
+                log_sum_exp((0..n).filter(|&i| i != j).map(|i| self.log_kernel(i, r))) + c
+            })
+            .sum()
+    }
+
+    /// Draws `n` samples from the estimated density, i.e. each sample is a
+    /// random example plus Gaussian noise with the bandwidth as the
+    /// standard deviation. The seed must not be `[0, 0, 0, 0]`.
+    pub fn sample(&self, n: usize, seed: [u32; 4]) -> Matrix<f64> {
+        self.sample_with_rng(n, &mut XorShiftRng::from_seed(seed))
+    }
+
+    /// Like [sample](#method.sample) with the given random number generator.
+    pub fn sample_with_rng<R: Rng>(&self, n: usize, rng: &mut R) -> Matrix<f64> {
+
+        let normal = Normal::new(0.0, 1.0);
+        let d = self.data.cols();
+        let mut v = Vec::with_capacity(n * d);
+        for _ in 0..n {
+            let r = self.data.row(rng.gen_range(0, self.data.rows())).unwrap();
+            for j in 0..d {
+                v.push(r[j] + self.h[j] * normal.ind_sample(rng));
+            }
+        }
+        Matrix::from_vec(v, n, d)
+    }
+}
+
+fn log_sum_exp<I: Iterator<Item = f64>>(it: I) -> f64 {
+
+    let v: Vec<f64> = it.collect();
+    let m = v.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    if m == ::std::f64::NEG_INFINITY {
+        return m;
+    }
+    m + v.iter().map(|x| (x - m).exp()).sum::<f64>().ln()
+}
+
+// ----------------------------------------------------------------------------
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,4 +375,39 @@ mod tests {
         assert!(p.pr(2.0) - 0.51713 <= 0.00001);
         assert!(p.pr(1.5) - 0.48048 <= 0.00001);
     }
+
+    #[test]
+    fn test_kernel_density() {
+
+        // with one example the estimate is a normal distribution
+        let k = KernelDensity::fit_1d(&[2.0], Bandwidth::Fixed(0.5));
+        let g = Gaussian::new(2.0, 0.25);
+        let s = k.score_samples(&Matrix::from_vec(vec![1.0, 2.5], 2, 1));
+        assert!((s[0] - g.pr(1.0).ln()).abs() < 1e-6);
+        assert!((s[1] - g.pr(2.5).ln()).abs() < 1e-6);
+
+        // the density integrates to one
+        let x: Vec<f64> = (0..50).map(|i| ((i * 37) % 50) as f64 / 10.0).collect();
+        let k = KernelDensity::fit_1d(&x, Bandwidth::Silverman);
+        let grid = Matrix::from_vec((0..2000).map(|i| -5.0 + i as f64 * 0.0075).collect(), 2000, 1);
+        let area: f64 = k.score_samples(&grid).iter().map(|l| l.exp() * 0.0075).sum();
+        assert!((area - 1.0).abs() < 1e-3);
+
+        // rules of thumb in two dimensions
+        let m = Matrix::from_vec((0..100).map(|i| (i % 7) as f64 * if i % 2 == 0 { 1.0 } else { 10.0 }).collect(), 50, 2);
+        let scott = KernelDensity::fit(&m, Bandwidth::Scott);
+        let silverman = KernelDensity::fit(&m, Bandwidth::Silverman);
+        assert!(scott.bandwidth()[1] > 5.0 * scott.bandwidth()[0]);
+        // both rules are the same in two dimensions
+        assert_eq!(silverman.bandwidth(), scott.bandwidth());
+        assert!(k.bandwidth()[0] > KernelDensity::fit_1d(&x, Bandwidth::Scott).bandwidth()[0]);
+
+        // cross-validation prefers a reasonable bandwidth
+        let cv = KernelDensity::fit_1d(&x, Bandwidth::CrossValidation(vec![0.001, 0.3, 100.0]));
+        assert_eq!(cv.bandwidth(), &[0.3]);
+
+        let s = k.sample(1000, [1, 2, 3, 4]);
+        let mean = s.iter().sum::<f64>() / 1000.0;
+        assert!((mean - x.iter().sum::<f64>() / 50.0).abs() < 0.2);
+    }
 }
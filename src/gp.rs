@@ -0,0 +1,365 @@
+//! Gaussian processes.
+//!
+//! A Gaussian process is a prior over functions which is described by a
+//! [Kernel](enum.Kernel.html), i.e. the covariance of the function values
+//! at two points. The
+//! [GaussianProcessClassifier](struct.GaussianProcessClassifier.html)
+//! performs binary classification with a latent function with a Gaussian
+//! process prior which is squashed by the logistic function. Because the
+//! posterior of the latent function is not Gaussian, it is approximated by
+//! a Gaussian around its mode (Laplace approximation, see Rasmussen and
+//! Williams, Gaussian Processes for Machine Learning, Algorithms 3.1 and
+//! 3.2).
+//!
+//! Unlike the probabilities of many other classifiers, the predictive
+//! probabilities account for the uncertainty of the latent function, i.e.
+//! they approach 0.5 far away from the training data. The mean and the
+//! variance of the latent function are returned by
+//! [predict_latent](struct.GaussianProcessClassifier.html#method.predict_latent).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::gp::*;
+//! use rustml::model_selection::{Estimator, ProbabilisticClassifier};
+//!
+//! # fn main() {
+//! let x = mat![-2.0; -1.5; -1.0; 1.0; 1.5; 2.0];
+//! let y = [0, 0, 0, 1, 1, 1];
+//!
+//! let mut gp = GaussianProcessClassifier::new(Kernel::Rbf(1.0)).amplitude(4.0);
+//! gp.fit(&x, &y);
+//! assert_eq!(gp.predict(&mat![-1.2; 1.7]), vec![0, 1]);
+//!
+//! // uncertain far away from the data
+//! let p = gp.predict_proba(&mat![1.5; 30.0]);
+//! assert!(*p.get(0, 1).unwrap() > 0.7);
+//! assert!((p.get(1, 1).unwrap() - 0.5).abs() < 1e-3);
+//! # }
+//! ```
+use std::f64::consts::PI;
+
+use matrix::Matrix;
+use linalg::{cholesky, is_positive_definite};
+use model_selection::{Estimator, ProbabilisticClassifier};
+
+/// Covariance functions of a Gaussian process.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kernel {
+    /// Squared exponential kernel `exp(-|a - b|^2 / (2 l^2))` with the
+    /// length scale `l`.
+    Rbf(f64),
+    /// Laplacian (Matérn 1/2) kernel `exp(-|a - b| / l)` with the length
+    /// scale `l`.
+    Exponential(f64),
+    /// Linear kernel `a^T b`.
+    Linear
+}
+
+impl Kernel {
+
+    /// Computes the kernel between the vectors `a` and `b`.
+    pub fn compute(&self, a: &[f64], b: &[f64]) -> f64 {
+
+        let sq = || a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + (x - y) * (x - y));
+        match *self {
+            Kernel::Rbf(l) => (-sq() / (2.0 * l * l)).exp(),
+            Kernel::Exponential(l) => (-sq().sqrt() / l).exp(),
+            Kernel::Linear => a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + x * y)
+        }
+    }
+
+    /// Computes the matrix of the kernel between the rows of `a` (rows of
+    /// the result) and the rows of `b` (columns of the result).
+    ///
+    /// Panics if the number of columns differ.
+    pub fn matrix(&self, a: &Matrix<f64>, b: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(a.cols() == b.cols(), "Number of columns must be equal.");
+        let v = a.row_iter().flat_map(|r| b.row_iter().map(move |s| self.compute(r, s))).collect();
+        Matrix::from_vec(v, a.rows(), b.rows())
+    }
+}
+
+/// Binary Gaussian process classifier with the Laplace approximation.
+///
+/// The labels are 0 and 1.
+#[derive(Clone, Debug)]
+pub struct GaussianProcessClassifier {
+    kernel: Kernel,
+    amplitude: f64,
+    max_iter: usize,
+    tol: f64,
+    x: Matrix<f64>,
+    // gradient of the log-likelihood at the mode, i.e. t - pi
+    grad: Vec<f64>,
+    sqrt_w: Vec<f64>,
+    // Cholesky factor of I + W^1/2 K W^1/2
+    l: Matrix<f64>,
+    log_marginal_likelihood: f64
+}
+
+impl GaussianProcessClassifier {
+
+    /// Creates an unfitted classifier with the given kernel, an amplitude
+    /// (signal variance) of 1, at most 100 Newton iterations and a
+    /// tolerance of 1e-8.
+    pub fn new(kernel: Kernel) -> GaussianProcessClassifier {
+        GaussianProcessClassifier {
+            kernel: kernel,
+            amplitude: 1.0,
+            max_iter: 100,
+            tol: 1e-8,
+            x: Matrix::new(),
+            grad: vec![],
+            sqrt_w: vec![],
+            l: Matrix::new(),
+            log_marginal_likelihood: 0.0
+        }
+    }
+
+    /// Multiplies the kernel with `s2`, i.e. the prior variance of the
+    /// latent function.
+    ///
+    /// Panics if `s2` is not greater than zero.
+    pub fn amplitude(&self, s2: f64) -> GaussianProcessClassifier {
+
+        assert!(s2 > 0.0, "Amplitude must be greater than zero.");
+        GaussianProcessClassifier { amplitude: s2, .. self.clone() }
+    }
+
+    /// Sets the maximum number of Newton iterations to find the mode.
+    pub fn max_iter(&self, n: usize) -> GaussianProcessClassifier {
+        GaussianProcessClassifier { max_iter: n, .. self.clone() }
+    }
+
+    /// The Newton iterations stop if the objective improves by less than
+    /// `tol`.
+    pub fn tol(&self, tol: f64) -> GaussianProcessClassifier {
+        GaussianProcessClassifier { tol: tol, .. self.clone() }
+    }
+
+    /// Returns the approximate log marginal likelihood of the training
+    /// labels, e.g. to compare kernels.
+    pub fn log_marginal_likelihood(&self) -> f64 {
+        self.log_marginal_likelihood
+    }
+
+    /// Returns the mean and the variance of the latent function at each row
+    /// of `x`.
+    ///
+    /// Panics if the classifier is not fitted.
+    pub fn predict_latent(&self, x: &Matrix<f64>) -> (Vec<f64>, Vec<f64>) {
+
+        assert!(self.x.rows() > 0, "Classifier is not fitted.");
+        let ks = self.kernel_matrix(&self.x, x);
+        let n = self.x.rows();
+        let mut mean = Vec::with_capacity(x.rows());
+        let mut var = Vec::with_capacity(x.rows());
+        for (j, r) in x.row_iter().enumerate() {
+            let k: Vec<f64> = (0..n).map(|i| *ks.get(i, j).unwrap()).collect();
+            mean.push(k.iter().zip(self.grad.iter()).fold(0.0, |acc, (a, b)| acc + a * b));
+            let wk: Vec<f64> = k.iter().zip(self.sqrt_w.iter()).map(|(a, b)| a * b).collect();
+            let v = solve_lower(&self.l, &wk);
+            let kss = self.amplitude * self.kernel.compute(r, r);
+            var.push((kss - v.iter().fold(0.0, |acc, x| acc + x * x)).max(0.0));
+        }
+        (mean, var)
+    }
+
+    fn kernel_matrix(&self, a: &Matrix<f64>, b: &Matrix<f64>) -> Matrix<f64> {
+
+        let k = self.kernel.matrix(a, b);
+        let v = k.buf().iter().map(|x| x * self.amplitude).collect();
+        Matrix::from_vec(v, k.rows(), k.cols())
+    }
+}
+
+impl Estimator<f64, usize> for GaussianProcessClassifier {
+
+    /// Finds the mode of the posterior of the latent function with Newton's
+    /// method.
+    ///
+    /// Panics if a label is not 0 or 1 or if the number of rows and labels
+    /// differ.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[usize]) {
+
+        assert!(x.rows() == y.len(), "Number of examples and labels must be equal.");
+        assert!(y.iter().all(|&l| l < 2), "Labels must be 0 or 1.");
+
+        let n = x.rows();
+        let k = self.kernel_matrix(x, x);
+        if !is_positive_definite(&k) {
+            warn!(target: "rustml", "gp: kernel matrix is not positive definite, e.g. due to duplicate examples");
+        }
+        let kb = k.buf();
+        let t: Vec<f64> = y.iter().map(|&l| l as f64).collect();
+        let s: Vec<f64> = t.iter().map(|x| 2.0 * x - 1.0).collect();
+
+        let mut f = vec![0.0; n];
+        let mut obj = ::std::f64::NEG_INFINITY;
+        let mut l = Matrix::new();
+        let mut sw = vec![0.0; n];
+        for _ in 0..self.max_iter.max(1) {
+            let pi: Vec<f64> = f.iter().map(|&x| sigmoid(x)).collect();
+            sw = pi.iter().map(|p| (p * (1.0 - p)).sqrt()).collect();
+
+            l = factor(kb, &sw);
+
+            // b = W f + grad, a = b - W^1/2 L^T \ (L \ (W^1/2 K b))
+            let bv: Vec<f64> = (0..n).map(|i| sw[i] * sw[i] * f[i] + t[i] - pi[i]).collect();
+            let kbv: Vec<f64> = (0..n).map(|i| sw[i] * (0..n).fold(0.0, |acc, j| acc + kb[i * n + j] * bv[j])).collect();
+            let c = solve_upper_transposed(&l, &solve_lower(&l, &kbv));
+            let a: Vec<f64> = (0..n).map(|i| bv[i] - sw[i] * c[i]).collect();
+            f = (0..n).map(|i| (0..n).fold(0.0, |acc, j| acc + kb[i * n + j] * a[j])).collect();
+
+            let new_obj = -0.5 * a.iter().zip(f.iter()).fold(0.0, |acc, (x, y)| acc + x * y)
+                + s.iter().zip(f.iter()).fold(0.0, |acc, (y, x)| acc + log_sigmoid(y * x));
+            let done = (new_obj - obj).abs() < self.tol;
+            obj = new_obj;
+            if done {
+                break;
+            }
+        }
+
+        // W, the Cholesky factor and the gradient at the mode
+        let pi: Vec<f64> = f.iter().map(|&x| sigmoid(x)).collect();
+        let w: Vec<f64> = pi.iter().map(|p| (p * (1.0 - p)).sqrt()).collect();
+        if w != sw {
+            l = factor(kb, &w);
+        }
+        let log_det: f64 = (0..n).map(|i| l.get(i, i).unwrap().ln()).sum();
+
+        self.x = x.clone();
+        self.grad = t.iter().zip(pi.iter()).map(|(a, b)| a - b).collect();
+        self.sqrt_w = w;
+        self.l = l;
+        self.log_marginal_likelihood = obj - log_det;
+    }
+
+    /// Returns the class with the larger predictive probability.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+
+        let (mean, _) = self.predict_latent(x);
+        mean.iter().map(|&m| if m > 0.0 { 1 } else { 0 }).collect()
+    }
+}
+
+impl ProbabilisticClassifier for GaussianProcessClassifier {
+
+    /// Returns the predictive probabilities of the classes 0 and 1 in the
+    /// columns. The logistic function of the Gaussian latent function is
+    /// averaged with the probit approximation.
+    fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        let (mean, var) = self.predict_latent(x);
+        let v = mean.iter().zip(var.iter())
+            .flat_map(|(m, v)| {
+                let p = sigmoid(m / (1.0 + PI * v / 8.0).sqrt());
+                vec![1.0 - p, p]
+            })
+            .collect();
+        Matrix::from_vec(v, x.rows(), 2)
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// numerically stable ln(sigmoid(x))
+fn log_sigmoid(x: f64) -> f64 {
+    if x > 0.0 { -(-x).exp().ln_1p() } else { x - x.exp().ln_1p() }
+}
+
+// Cholesky factor of B = I + W^1/2 K W^1/2 which is positive definite
+fn factor(k: &[f64], sw: &[f64]) -> Matrix<f64> {
+
+    let n = sw.len();
+    let mut b = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..n {
+            b[i * n + j] = sw[i] * k[i * n + j] * sw[j] + if i == j { 1.0 } else { 0.0 };
+        }
+    }
+    cholesky(&Matrix::from_vec(b, n, n)).unwrap()
+}
+
+// solves L x = b for a lower triangular L
+fn solve_lower(l: &Matrix<f64>, b: &[f64]) -> Vec<f64> {
+
+    let n = b.len();
+    let lb = l.buf();
+    let mut x = vec![0.0; n];
+    for i in 0..n {
+        x[i] = (0..i).fold(b[i], |acc, k| acc - lb[i * n + k] * x[k]) / lb[i * n + i];
+    }
+    x
+}
+
+// solves L^T x = b for a lower triangular L
+fn solve_upper_transposed(l: &Matrix<f64>, b: &[f64]) -> Vec<f64> {
+
+    let n = b.len();
+    let lb = l.buf();
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        x[i] = (i + 1..n).fold(b[i], |acc, k| acc - lb[k * n + i] * x[k]) / lb[i * n + i];
+    }
+    x
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel() {
+
+        let a = Matrix::from_vec(vec![0.0, 0.0, 1.0, 1.0], 2, 2);
+        let k = Kernel::Rbf(1.0).matrix(&a, &a);
+        assert_eq!(*k.get(0, 0).unwrap(), 1.0);
+        assert!((k.get(0, 1).unwrap() - (-1.0f64).exp()).abs() < 1e-12);
+        assert!((Kernel::Exponential(2.0).compute(&[0.0, 0.0], &[3.0, 4.0]) - (-2.5f64).exp()).abs() < 1e-12);
+        assert_eq!(Kernel::Linear.compute(&[1.0, 2.0], &[3.0, 4.0]), 11.0);
+    }
+
+    #[test]
+    fn test_gp_classifier() {
+
+        // class 1 inside the interval [-1, 1]
+        let xs: Vec<f64> = (0..21).map(|i| -2.5 + i as f64 * 0.25).collect();
+        let y: Vec<usize> = xs.iter().map(|&x| if x.abs() < 1.0 { 1 } else { 0 }).collect();
+        let x = Matrix::from_vec(xs, 21, 1);
+
+        let mut gp = GaussianProcessClassifier::new(Kernel::Rbf(0.5)).amplitude(10.0);
+        gp.fit(&x, &y);
+        assert_eq!(gp.predict(&x), y);
+
+        let q = Matrix::from_vec(vec![0.0, 2.0, 100.0], 3, 1);
+        let p = gp.predict_proba(&q);
+        assert!(*p.get(0, 1).unwrap() > 0.8);
+        assert!(*p.get(1, 0).unwrap() > 0.8);
+        for r in p.row_iter() {
+            assert!((r[0] + r[1] - 1.0).abs() < 1e-12);
+        }
+
+        // the variance of the latent function grows away from the data and
+        // approaches the prior variance
+        let (mean, var) = gp.predict_latent(&q);
+        assert!(var[0] < var[2]);
+        assert!((var[2] - 10.0).abs() < 1e-6);
+        assert!(mean[2].abs() < 1e-6);
+
+        // a kernel with a more plausible length scale has a larger marginal
+        // likelihood than a very short one
+        let mut short = GaussianProcessClassifier::new(Kernel::Rbf(0.01)).amplitude(10.0);
+        short.fit(&x, &y);
+        assert!(gp.log_marginal_likelihood() > short.log_marginal_likelihood());
+        assert!(gp.log_marginal_likelihood() < 0.0);
+    }
+}
@@ -0,0 +1,404 @@
+//! Offloading of large matrix multiplications to the GPU.
+//!
+//! If rustml is compiled with the feature `cuda` the multiplications of this
+//! module are computed with cuBLAS on the first CUDA device. This requires
+//! the CUDA toolkit (the libraries `cudart` and `cublas`). Without the
+//! feature, or if no CUDA device is found at runtime, all computations fall
+//! back to BLAS on the CPU (see [ops_inplace](../ops_inplace/index.html)),
+//! i.e. code which uses this module runs on every machine.
+//!
+//! Copying the matrices to the device is only worth it for large matrices.
+//! Therefore, [mul](fn.mul.html) only uses the GPU if the number of
+//! multiply-add operations is at least [threshold](fn.threshold.html). The
+//! functions [predict](fn.predict.html) and
+//! [derivatives](fn.derivatives.html) compute the output and the gradients
+//! of a [neural network](../nn/index.html) for a whole batch of examples
+//! with matrix multiplications, i.e. large batches are processed on the GPU.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::gpu;
+//!
+//! # fn main() {
+//! let a = mat![1.0, 2.0; 3.0, 4.0];
+//! let b = mat![5.0, 6.0; 7.0, 8.0];
+//! // small matrices are always multiplied on the CPU
+//! assert!(gpu::mul(&a, &b, false, false).eq(&mat![19.0, 22.0; 43.0, 50.0]));
+//! # }
+//! ```
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use matrix::Matrix;
+use nn::{NeuralNetwork, Cost};
+use ops_inplace::{d_gemm, MatrixScalarOpsInPlace};
+
+static THRESHOLD: AtomicUsize = AtomicUsize::new(1 << 24);
+
+/// Returns the minimum number of multiply-add operations (i.e. `m * n * k`
+/// for a `m`x`k` times `k`x`n` multiplication) for which a multiplication is
+/// offloaded to the GPU. The default is 2^24.
+pub fn threshold() -> usize {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Sets the minimum number of multiply-add operations for which a
+/// multiplication is offloaded to the GPU.
+pub fn set_threshold(n: usize) {
+    THRESHOLD.store(n, Ordering::Relaxed)
+}
+
+/// Returns `true` if rustml has been compiled with the feature `cuda` and a
+/// CUDA device could be initialized.
+pub fn available() -> bool {
+    cuda::available()
+}
+
+/// Computes `op(a) * op(b)` where `op(x)` is the transpose of `x` if the
+/// corresponding flag is `true`.
+///
+/// The product is computed on the GPU if it is [available](fn.available.html)
+/// and the number of operations is at least the [threshold](fn.threshold.html).
+/// Otherwise, or if the computation on the GPU fails, it is computed with
+/// BLAS on the CPU.
+///
+/// Panics if the dimensions do not match.
+pub fn mul(a: &Matrix<f64>, b: &Matrix<f64>, lhs_t: bool, rhs_t: bool) -> Matrix<f64> {
+
+    let r = if lhs_t { a.cols() } else { a.rows() };
+    let k = if lhs_t { a.rows() } else { a.cols() };
+    let kb = if rhs_t { b.cols() } else { b.rows() };
+    let c = if rhs_t { b.rows() } else { b.cols() };
+
+    assert!(k == kb, "Dimensions for mul do not match: {}x{} * {}x{}", r, k, kb, c);
+
+    if r * c * k >= threshold() && available() {
+        if let Some(m) = cuda::gemm(a, b, lhs_t, rhs_t, r, c, k) {
+            return m;
+        }
+    }
+    let mut m = Matrix::fill(0.0, r, c);
+    d_gemm(1.0, a, b, 0.0, &mut m, lhs_t, rhs_t);
+    m
+}
+
+fn with_bias(m: &Matrix<f64>) -> Matrix<f64> {
+    m.insert_column(0, &vec![1.0; m.rows()])
+}
+
+// applies `f` to each element of `m`
+fn map<F: Fn(f64) -> f64>(m: &Matrix<f64>, f: F) -> Matrix<f64> {
+    Matrix::from_vec(m.iter().map(|&x| f(x)).collect(), m.rows(), m.cols())
+}
+
+fn hadamard(a: &Matrix<f64>, b: &Matrix<f64>) -> Matrix<f64> {
+    Matrix::from_vec(a.iter().zip(b.iter()).map(|(x, y)| x * y).collect(), a.rows(), a.cols())
+}
+
+/// Computes the outputs of the network for all examples in `input` (one
+/// example per row) with [mul](fn.mul.html).
+///
+/// The result is the same as the result of
+/// [NeuralNetwork::predict](../nn/struct.NeuralNetwork.html#method.predict).
+pub fn predict(net: &NeuralNetwork, input: &Matrix<f64>) -> Matrix<f64> {
+
+    assert!(net.layers() >= 2, "At least two layers are required.");
+    assert!(input.cols() == net.input_size(), "Dimension of input vector does not match.");
+
+    let params = net.params();
+    let mut o = input.clone();
+    for (i, p) in params.iter().enumerate() {
+        let x = activate(net, i, &mul(&o, p, false, true));
+        o = if i + 1 < params.len() { with_bias(&x) } else { x };
+    }
+    o
+}
+
+// activations of the units of the layer `i + 1` for the net inputs `z`
+fn activate(net: &NeuralNetwork, i: usize, z: &Matrix<f64>) -> Matrix<f64> {
+
+    if i + 2 == net.layers() && net.is_softmax() {
+        let mut m = z.clone();
+        for r in 0..m.rows() {
+            let v = m.row_mut(r).unwrap();
+            let mx = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let mut s = 0.0;
+            for x in v.iter_mut() {
+                *x = (*x - mx).exp();
+                s += *x;
+            }
+            for x in v.iter_mut() {
+                *x /= s;
+            }
+        }
+        m
+    } else {
+        let act = net.activation(i + 1);
+        map(z, |v| act.apply(v))
+    }
+}
+
+// derivatives of the cost with respect to the net inputs of the output units
+fn output_delta(net: &NeuralNetwork, out: &Matrix<f64>, z: &Matrix<f64>, targets: &Matrix<f64>) -> Matrix<f64> {
+
+    let act = net.activation(net.layers() - 1);
+    let mut d = Matrix::fill(0.0, out.rows(), out.cols());
+    for r in 0..out.rows() {
+        let (o, t, z) = (out.row(r).unwrap(), targets.row(r).unwrap(), z.row(r).unwrap());
+        let s = (0..o.len()).fold(0.0, |acc, i| acc + (o[i] - t[i]) * o[i]);
+        let v = d.row_mut(r).unwrap();
+        for i in 0..v.len() {
+            v[i] = match (net.cost(), net.is_softmax()) {
+                (Cost::CrossEntropy, true) => o[i] - t[i],
+                (Cost::CrossEntropy, false) if act.is_logistic() => o[i] - t[i],
+                (Cost::CrossEntropy, false) => {
+                    // keeps the output away from zero and one
+                    let y = o[i].max(1e-15).min(1.0 - 1e-15);
+                    (y - t[i]) / (y * (1.0 - y)) * act.derivative(z[i])
+                },
+                // product with the Jacobian of the softmax
+                (Cost::MeanSquare, true) => o[i] * (o[i] - t[i] - s),
+                (Cost::MeanSquare, false) => (o[i] - t[i]) * act.derivative(z[i])
+            };
+        }
+    }
+    d
+}
+
+/// Computes the derivatives of the error of the network for a batch of
+/// examples with [mul](fn.mul.html).
+///
+/// In contrast to
+/// [NeuralNetwork::derivatives](../nn/struct.NeuralNetwork.html#method.derivatives),
+/// which computes the derivatives example by example, the backpropagation
+/// is done for all examples at once. The results are the same.
+pub fn derivatives(net: &NeuralNetwork, examples: &Matrix<f64>, targets: &Matrix<f64>) -> Vec<Matrix<f64>> {
+
+    assert!(net.layers() >= 2, "At least two layers are required.");
+    assert!(examples.rows() == targets.rows(), "Number of examples and labels mismatch.");
+    assert!(examples.cols() == net.input_size(), "Dimension of input vector does not match.");
+    assert!(net.output_size() == targets.cols(), "Dimension of target values mismatch.");
+
+    let params = net.params();
+    let n = params.len();
+
+    // activations (inputs of the next layer incl. bias unit) and net inputs
+    let mut av = vec![examples.clone()];
+    let mut zv = vec![];
+    for (i, p) in params.iter().enumerate() {
+        let z = mul(av.last().unwrap(), p, false, true);
+        let a = activate(net, i, &z);
+        av.push(if i + 1 < n { with_bias(&a) } else { a });
+        zv.push(z);
+    }
+
+    // error of the output layer
+    let mut delta = output_delta(net, av.last().unwrap(), &zv[n - 1], targets);
+
+    let mut grads = vec![];
+    for i in (0..n).rev() {
+        let mut g = mul(&delta, &av[i], true, false);
+        g.idiv_scalar(examples.rows() as f64);
+        grads.push(g);
+        if i > 0 {
+            // error of the hidden layer without the bias unit
+            let v = mul(&delta, &params[i], false, false).rm_column(0);
+            let act = net.activation(i);
+            delta = hadamard(&v, &map(&zv[i - 1], |z| act.derivative(z)));
+        }
+    }
+    grads.reverse();
+    grads
+}
+
+#[cfg(not(feature = "cuda"))]
+mod cuda {
+    use matrix::Matrix;
+
+    pub fn available() -> bool {
+        false
+    }
+
+    pub fn gemm(_a: &Matrix<f64>, _b: &Matrix<f64>, _lhs_t: bool, _rhs_t: bool,
+                _r: usize, _c: usize, _k: usize) -> Option<Matrix<f64>> {
+        None
+    }
+}
+
+#[cfg(feature = "cuda")]
+mod cuda {
+    extern crate libc;
+
+    use std::ptr;
+    use std::mem::size_of;
+    use self::libc::{c_int, c_void};
+
+    use matrix::Matrix;
+
+    #[allow(non_camel_case_types)]
+    type cublasHandle_t = *mut c_void;
+
+    const CUBLAS_OP_N: c_int = 0;
+    const CUBLAS_OP_T: c_int = 1;
+    const CUDA_MEMCPY_HOST_TO_DEVICE: c_int = 1;
+    const CUDA_MEMCPY_DEVICE_TO_HOST: c_int = 2;
+
+    #[link(name = "cudart")]
+    extern "C" {
+        fn cudaMalloc(p: *mut *mut c_void, size: usize) -> c_int;
+        fn cudaFree(p: *mut c_void) -> c_int;
+        fn cudaMemcpy(dst: *mut c_void, src: *const c_void, count: usize, kind: c_int) -> c_int;
+    }
+
+    #[link(name = "cublas")]
+    extern "C" {
+        fn cublasCreate_v2(handle: *mut cublasHandle_t) -> c_int;
+        fn cublasDestroy_v2(handle: cublasHandle_t) -> c_int;
+        fn cublasDgemm_v2(handle: cublasHandle_t, transa: c_int, transb: c_int,
+                          m: c_int, n: c_int, k: c_int,
+                          alpha: *const f64, a: *const f64, lda: c_int,
+                          b: *const f64, ldb: c_int,
+                          beta: *const f64, c: *mut f64, ldc: c_int) -> c_int;
+    }
+
+    /// cuBLAS context which is destroyed when dropped.
+    struct Handle(cublasHandle_t);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe { cublasDestroy_v2(self.0); }
+        }
+    }
+
+    thread_local! {
+        // the handle is created on first use and destroyed when the thread exits
+        static HANDLE: Option<Handle> = unsafe {
+            let mut h = ptr::null_mut();
+            if cublasCreate_v2(&mut h) == 0 { Some(Handle(h)) } else { None }
+        };
+    }
+
+    /// Memory on the device which is freed when dropped.
+    struct DeviceBuffer {
+        ptr: *mut c_void,
+        len: usize
+    }
+
+    impl DeviceBuffer {
+
+        fn new(len: usize) -> Option<DeviceBuffer> {
+            let mut p = ptr::null_mut();
+            match unsafe { cudaMalloc(&mut p, len * size_of::<f64>()) } {
+                0 => Some(DeviceBuffer { ptr: p, len: len }),
+                _ => None
+            }
+        }
+
+        fn from_slice(v: &[f64]) -> Option<DeviceBuffer> {
+            let b = match DeviceBuffer::new(v.len()) { Some(b) => b, None => return None };
+            let r = unsafe {
+                cudaMemcpy(b.ptr, v.as_ptr() as *const c_void, v.len() * size_of::<f64>(), CUDA_MEMCPY_HOST_TO_DEVICE)
+            };
+            if r == 0 { Some(b) } else { None }
+        }
+
+        fn to_vec(&self) -> Option<Vec<f64>> {
+            let mut v = vec![0.0; self.len];
+            let r = unsafe {
+                cudaMemcpy(v.as_mut_ptr() as *mut c_void, self.ptr, self.len * size_of::<f64>(), CUDA_MEMCPY_DEVICE_TO_HOST)
+            };
+            if r == 0 { Some(v) } else { None }
+        }
+    }
+
+    impl Drop for DeviceBuffer {
+        fn drop(&mut self) {
+            unsafe { cudaFree(self.ptr); }
+        }
+    }
+
+    pub fn available() -> bool {
+        HANDLE.with(|h| h.is_some())
+    }
+
+    pub fn gemm(a: &Matrix<f64>, b: &Matrix<f64>, lhs_t: bool, rhs_t: bool,
+                r: usize, c: usize, k: usize) -> Option<Matrix<f64>> {
+
+        let da = match DeviceBuffer::from_slice(a.buf()) { Some(b) => b, None => return None };
+        let db = match DeviceBuffer::from_slice(b.buf()) { Some(b) => b, None => return None };
+        let dc = match DeviceBuffer::new(r * c) { Some(b) => b, None => return None };
+        let (alpha, beta) = (1.0, 0.0);
+
+        // cuBLAS expects column-major matrices, i.e. a row-major matrix is
+        // seen as its transpose. Hence, C^T = op(B)^T * op(A)^T is computed.
+        let status = HANDLE.with(|h| match *h {
+            Some(ref h) => unsafe {
+                cublasDgemm_v2(h.0,
+                    if rhs_t { CUBLAS_OP_T } else { CUBLAS_OP_N },
+                    if lhs_t { CUBLAS_OP_T } else { CUBLAS_OP_N },
+                    c as c_int, r as c_int, k as c_int,
+                    &alpha, db.ptr as *const f64, b.cols() as c_int,
+                    da.ptr as *const f64, a.cols() as c_int,
+                    &beta, dc.ptr as *mut f64, c as c_int)
+            },
+            None => -1
+        });
+        if status != 0 {
+            return None;
+        }
+        dc.to_vec().map(|v| Matrix::from_vec(v, r, c))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use nn::{NeuralNetwork, Cost, Tanh, LeakyRelu};
+
+    #[test]
+    fn test_mul() {
+
+        let a = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        let b = mat![1.0, 2.0; 3.0, 4.0; 5.0, 6.0];
+        assert!(mul(&a, &b, false, false).eq(&mat![22.0, 28.0; 49.0, 64.0]));
+        assert!(mul(&a, &a, true, false).eq(&mat![17.0, 22.0, 27.0; 22.0, 29.0, 36.0; 27.0, 36.0, 45.0]));
+        assert!(mul(&a, &b, true, true).eq(&mat![9.0, 19.0, 29.0; 12.0, 26.0, 40.0; 15.0, 33.0, 51.0]));
+    }
+
+    #[test]
+    fn test_predict_and_derivatives() {
+
+        let n = NeuralNetwork::new()
+            .add_layer(3)
+            .add_layer(2)
+            .add_layer(3)
+            .set_params(0, mat![0.1, 0.2, 0.4; 0.2, 0.1, 2.0])
+            .set_params(1, mat![0.8, 1.2, 0.6; 0.4, 0.5, 0.8; 1.4, 1.5, 2.0]);
+        let x = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0; 0.7, 0.9, 1.8];
+        let t = mat![1.0, 0.0, 0.0; 0.0, 1.0, 0.0; 0.0, 0.0, 1.0];
+
+        let nets = [
+            n.clone(),
+            n.set_activation(1, Tanh).set_activation(2, LeakyRelu(0.1)),
+            n.set_softmax(true),
+            n.set_softmax(true).set_cost(Cost::CrossEntropy),
+            n.set_cost(Cost::CrossEntropy),
+            n.set_activation(2, Tanh).set_cost(Cost::CrossEntropy)
+        ];
+        for m in nets.iter() {
+            assert!(predict(m, &x).similar(&m.predict(&x), 1e-12));
+
+            let a = derivatives(m, &x, &t);
+            let b = m.derivatives(&x, &t);
+            assert_eq!(a.len(), b.len());
+            for (p, q) in a.iter().zip(b.iter()) {
+                assert!(p.similar(q, 1e-12));
+            }
+        }
+    }
+}
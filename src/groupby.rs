@@ -0,0 +1,203 @@
+//! Module to group the rows of a matrix by their labels.
+//!
+//! Many algorithms need statistics of the examples of each class, e.g. the
+//! mean vector and the covariance matrix of each class for Gaussian
+//! discriminant analysis or the number of examples of each class to check
+//! whether a dataset is balanced. [`GroupBy`](struct.GroupBy.html) groups the
+//! rows of a matrix by a vector of labels and computes such aggregations for
+//! each group.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::groupby::GroupBy;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     1.0, 2.0;
+//!     5.0, 5.0;
+//!     3.0, 4.0;
+//!     7.0, 9.0
+//! ];
+//! let y = vec!["a", "b", "a", "b"];
+//!
+//! let g = GroupBy::new(&x, &y);
+//! assert_eq!(g.labels(), vec![&"a", &"b"]);
+//! assert_eq!(g.counts(), vec![2, 2]);
+//! assert_eq!(g.means(), vec![vec![2.0, 3.0], vec![6.0, 7.0]]);
+//! assert_eq!(g.aggregate(|m| m.get(0, 0).cloned().unwrap()), vec![1.0, 5.0]);
+//! # }
+//! ```
+use std::collections::BTreeMap;
+
+use math::{Mean, Dimension, Normalization};
+use matrix::Matrix;
+use linalg::covariance;
+
+/// Groups the indexes of the labels `y` by their values. Returns each label
+/// together with the indexes at which it occurs, ordered by the labels.
+pub fn group_indexes<L: Ord + Clone>(y: &[L]) -> Vec<(L, Vec<usize>)> {
+
+    let mut g: BTreeMap<L, Vec<usize>> = BTreeMap::new();
+    for (i, l) in y.iter().enumerate() {
+        g.entry(l.clone()).or_insert(Vec::new()).push(i);
+    }
+    g.into_iter().collect()
+}
+
+/// Maps each label of `y` to the index of its group, i.e. to the position of
+/// the label within the sorted distinct labels. Returns the indexes and the
+/// number of groups.
+pub fn label_indexes<L: Ord>(y: &[L]) -> (Vec<usize>, usize) {
+
+    let mut g: BTreeMap<&L, usize> = BTreeMap::new();
+    for l in y {
+        g.insert(l, 0);
+    }
+    let n = g.len();
+    for (i, (_, v)) in g.iter_mut().enumerate() {
+        *v = i;
+    }
+    (y.iter().map(|l| g[l]).collect(), n)
+}
+
+/// The rows of a matrix grouped by their labels.
+///
+/// The groups are ordered by their labels. All methods which return one
+/// value per group return the values in the same order as
+/// [`labels`](#method.labels).
+pub struct GroupBy<'a, T: 'a, L> {
+    x: &'a Matrix<T>,
+    groups: Vec<(L, Vec<usize>)>
+}
+
+impl <'a, T: Clone, L: Ord + Clone> GroupBy<'a, T, L> {
+
+    /// Groups the rows of `x` by the labels `y`, i.e. row `i` belongs to the
+    /// group with the label `y[i]`.
+    ///
+    /// Panics if the number of rows of `x` does not match the number of
+    /// labels.
+    pub fn new(x: &'a Matrix<T>, y: &[L]) -> GroupBy<'a, T, L> {
+
+        assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+        GroupBy {
+            x: x,
+            groups: group_indexes(y)
+        }
+    }
+
+    /// Returns the (sorted) labels of the groups.
+    pub fn labels(&self) -> Vec<&L> {
+        self.groups.iter().map(|&(ref l, _)| l).collect()
+    }
+
+    /// Returns the number of groups.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns `true` if there are no groups, i.e. the matrix has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the indexes of the rows of the group with the given label.
+    pub fn indexes(&self, label: &L) -> Option<&[usize]> {
+        self.groups.iter().find(|&&(ref l, _)| l == label).map(|&(_, ref v)| &v[..])
+    }
+
+    /// Returns the label and the indexes of the rows of each group.
+    pub fn groups(&self) -> &[(L, Vec<usize>)] {
+        &self.groups
+    }
+
+    /// Returns the number of rows of each group.
+    pub fn counts(&self) -> Vec<usize> {
+        self.groups.iter().map(|&(_, ref v)| v.len()).collect()
+    }
+
+    /// Returns the rows of each group as a matrix.
+    pub fn matrices(&self) -> Vec<Matrix<T>> {
+        self.aggregate(|m| m)
+    }
+
+    /// Applies `f` to the matrix of each group and returns the results.
+    pub fn aggregate<R, F>(&self, f: F) -> Vec<R>
+        where F: Fn(Matrix<T>) -> R {
+
+        self.groups.iter()
+            .map(|&(_, ref idx)| f(self.x.select_rows(idx)))
+            .collect()
+    }
+}
+
+impl <'a, L: Ord + Clone> GroupBy<'a, f64, L> {
+
+    /// Returns the mean vector of each group.
+    pub fn means(&self) -> Vec<Vec<f64>> {
+        self.aggregate(|m| m.mean(Dimension::Column))
+    }
+
+    /// Returns the covariance matrix of each group (see
+    /// [`linalg::covariance`](../linalg/fn.covariance.html)).
+    pub fn covariances(&self, nrm: Normalization) -> Vec<Matrix<f64>> {
+        self.aggregate(|m| covariance(&m, nrm))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use math::Normalization;
+
+    #[test]
+    fn test_group_by() {
+
+        let x = mat![
+            1.0, 2.0;
+            5.0, 5.0;
+            3.0, 6.0;
+            7.0, 9.0;
+            0.0, 0.0
+        ];
+        let y = vec![2, 1, 2, 1, 3];
+
+        let g = GroupBy::new(&x, &y);
+        assert_eq!(g.len(), 3);
+        assert_eq!(g.labels(), vec![&1, &2, &3]);
+        assert_eq!(g.counts(), vec![2, 2, 1]);
+        assert_eq!(g.indexes(&2), Some(&[0, 2][..]));
+        assert_eq!(g.indexes(&4), None);
+        assert_eq!(g.groups()[2], (3, vec![4]));
+        assert_eq!(g.matrices()[0], mat![5.0, 5.0; 7.0, 9.0]);
+        assert_eq!(g.means(), vec![vec![6.0, 7.0], vec![2.0, 4.0], vec![0.0, 0.0]]);
+
+        let c = g.covariances(Normalization::MinusOne);
+        assert_eq!(c[1], mat![2.0, 4.0; 4.0, 8.0]);
+        assert_eq!(c[2], mat![0.0, 0.0; 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_group_indexes() {
+
+        let y = vec!["b", "a", "c", "a"];
+        assert_eq!(group_indexes(&y), vec![("a", vec![1, 3]), ("b", vec![0]), ("c", vec![2])]);
+        assert_eq!(label_indexes(&y), (vec![1, 0, 2, 0], 3));
+        assert_eq!(label_indexes::<usize>(&[]), (vec![], 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_group_by_panic() {
+
+        let x = mat![1.0; 2.0];
+        GroupBy::new(&x, &[1]);
+    }
+}
@@ -0,0 +1,589 @@
+//! Images as feature vectors without OpenCV.
+//!
+//! An [Image](struct.Image.html) stores the intensities of its pixels as
+//! values in the range `[0, 1]`, either with one channel (grayscale) or with
+//! three channels (RGB). Images can be converted to grayscale, resized and
+//! split into patches. Each image (or patch) can be converted into a feature
+//! row so that a set of images can be fed into any of the algorithms of this
+//! library (see [to_matrix](fn.to_matrix.html)).
+//!
+//! Images can also be described by classical feature extractors which
+//! implement the trait [FeatureExtractor](trait.FeatureExtractor.html), e.g.
+//! the histogram of oriented gradients [Hog](struct.Hog.html) or the
+//! [ColorHistogram](struct.ColorHistogram.html), so that they can be
+//! classified with SVMs or k-nearest neighbours (see
+//! [extract_features](fn.extract_features.html)).
+//!
+//! PNG and JPEG files can be loaded with `Image::open` if the crate is
+//! compiled with the feature `image`, which uses the
+//! [image](https://crates.io/crates/image) crate for decoding.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::imaging::Image;
+//!
+//! // a 2x2 RGB image
+//! let img = Image::from_vec(vec![
+//!     1.0, 0.0, 0.0,  0.0, 1.0, 0.0,
+//!     0.0, 0.0, 1.0,  1.0, 1.0, 1.0
+//! ], 2, 2, 3);
+//!
+//! let g = img.to_gray();
+//! assert_eq!(g.channels(), 1);
+//! assert!((g.pixel(1, 1)[0] - 1.0).abs() < 1e-9);
+//! assert_eq!(img.resize(4, 4).width(), 4);
+//! ```
+#[cfg(feature = "image")]
+extern crate image;
+
+use matrix::Matrix;
+
+/// An image with one (grayscale) or three (RGB) channels and intensities in
+/// the range `[0, 1]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    channels: usize,
+    data: Vec<f64>
+}
+
+impl Image {
+
+    /// Creates a black image.
+    ///
+    /// Panics if the number of channels is not 1 or 3.
+    pub fn new(width: usize, height: usize, channels: usize) -> Image {
+        Image::from_vec(vec![0.0; width * height * channels], width, height, channels)
+    }
+
+    /// Creates an image from the intensities of its pixels. The pixels are
+    /// stored row by row and the channels of a pixel are stored
+    /// consecutively, i.e. the value of channel `c` of the pixel at `(x, y)`
+    /// is at index `(y * width + x) * channels + c`.
+    ///
+    /// Panics if the number of channels is not 1 or 3 or if the length of
+    /// `data` does not match the dimensions.
+    pub fn from_vec(data: Vec<f64>, width: usize, height: usize, channels: usize) -> Image {
+
+        assert!(channels == 1 || channels == 3, "Number of channels must be 1 or 3.");
+        assert!(data.len() == width * height * channels, "Dimensions do not match the number of values.");
+        Image {
+            width: width,
+            height: height,
+            channels: channels,
+            data: data
+        }
+    }
+
+    /// Loads a PNG or JPEG image as RGB image.
+    #[cfg(feature = "image")]
+    pub fn open(path: &str) -> Result<Image, String> {
+
+        let img = try!(image::open(path).map_err(|e| format!("Could not load image {}: {}", path, e)));
+        Ok(Image::from_dynamic(img))
+    }
+
+    /// Decodes a PNG or JPEG image from memory as RGB image.
+    #[cfg(feature = "image")]
+    pub fn from_memory(buf: &[u8]) -> Result<Image, String> {
+
+        let img = try!(image::load_from_memory(buf).map_err(|e| format!("Could not decode image: {}", e)));
+        Ok(Image::from_dynamic(img))
+    }
+
+    #[cfg(feature = "image")]
+    fn from_dynamic(img: image::DynamicImage) -> Image {
+
+        let rgb = img.to_rgb8();
+        let (w, h) = (rgb.width() as usize, rgb.height() as usize);
+        let data = rgb.into_raw().into_iter().map(|v| v as f64 / 255.0).collect();
+        Image::from_vec(data, w, h, 3)
+    }
+
+    /// Saves the image. The format is determined by the extension of the
+    /// file name (`.png` or `.jpg`).
+    #[cfg(feature = "image")]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+
+        let buf: Vec<u8> = self.data.iter().map(|v| (v.max(0.0).min(1.0) * 255.0).round() as u8).collect();
+        let (w, h) = (self.width as u32, self.height as u32);
+        let r = if self.channels == 1 {
+            image::GrayImage::from_raw(w, h, buf).unwrap().save(path)
+        } else {
+            image::RgbImage::from_raw(w, h, buf).unwrap().save(path)
+        };
+        r.map_err(|e| format!("Could not save image {}: {}", path, e))
+    }
+
+    /// Returns the width of the image.
+    pub fn width(&self) -> usize { self.width }
+
+    /// Returns the height of the image.
+    pub fn height(&self) -> usize { self.height }
+
+    /// Returns the number of channels of the image.
+    pub fn channels(&self) -> usize { self.channels }
+
+    /// Returns the intensities of all pixels (see
+    /// [from_vec](#method.from_vec) for the layout).
+    pub fn buf(&self) -> &Vec<f64> { &self.data }
+
+    /// Returns the values of the channels of the pixel at `(x, y)`.
+    ///
+    /// Panics if the position is outside of the image.
+    pub fn pixel(&self, x: usize, y: usize) -> &[f64] {
+
+        assert!(x < self.width && y < self.height, "Position is outside of the image.");
+        let i = (y * self.width + x) * self.channels;
+        &self.data[i..i + self.channels]
+    }
+
+    /// Returns the grayscale version of the image. The intensity of a pixel
+    /// is `0.299 * r + 0.587 * g + 0.114 * b`.
+    pub fn to_gray(&self) -> Image {
+
+        if self.channels == 1 {
+            return self.clone();
+        }
+        let data = self.data.chunks(3).map(|p| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2]).collect();
+        Image::from_vec(data, self.width, self.height, 1)
+    }
+
+    /// Returns the RGB version of the image.
+    pub fn to_rgb(&self) -> Image {
+
+        if self.channels == 3 {
+            return self.clone();
+        }
+        let data = self.data.iter().flat_map(|&v| vec![v, v, v]).collect();
+        Image::from_vec(data, self.width, self.height, 3)
+    }
+
+    /// Returns the image scaled to the given size with bilinear
+    /// interpolation.
+    ///
+    /// Panics if the image or the new size is empty.
+    pub fn resize(&self, width: usize, height: usize) -> Image {
+
+        assert!(self.width > 0 && self.height > 0 && width > 0 && height > 0, "Empty images cannot be resized.");
+
+        // maps a target coordinate to the two nearest source coordinates and
+        // the weight of the second one
+        let map = |i: usize, src: usize, dst: usize| -> (usize, usize, f64) {
+            let s = ((i as f64 + 0.5) * src as f64 / dst as f64 - 0.5).max(0.0).min((src - 1) as f64);
+            let a = s.floor() as usize;
+            (a, (a + 1).min(src - 1), s - a as f64)
+        };
+
+        let mut data = Vec::with_capacity(width * height * self.channels);
+        for y in 0..height {
+            let (y0, y1, wy) = map(y, self.height, height);
+            for x in 0..width {
+                let (x0, x1, wx) = map(x, self.width, width);
+                for c in 0..self.channels {
+                    let p = |px: usize, py: usize| self.data[(py * self.width + px) * self.channels + c];
+                    let top = p(x0, y0) * (1.0 - wx) + p(x1, y0) * wx;
+                    let bottom = p(x0, y1) * (1.0 - wx) + p(x1, y1) * wx;
+                    data.push(top * (1.0 - wy) + bottom * wy);
+                }
+            }
+        }
+        Image::from_vec(data, width, height, self.channels)
+    }
+
+    /// Returns the region of the image with the upper left corner `(x, y)`.
+    ///
+    /// Panics if the region is not completely inside of the image.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+
+        assert!(x + width <= self.width && y + height <= self.height, "Region is outside of the image.");
+        let mut data = Vec::with_capacity(width * height * self.channels);
+        for r in y..y + height {
+            let i = (r * self.width + x) * self.channels;
+            data.extend_from_slice(&self.data[i..i + width * self.channels]);
+        }
+        Image::from_vec(data, width, height, self.channels)
+    }
+
+    /// Extracts all patches of the given size whose upper left corners are
+    /// `stride` pixels apart. Each row of the returned matrix contains the
+    /// values of one patch (see [to_row](#method.to_row)). The patches are
+    /// ordered row by row.
+    ///
+    /// Panics if `stride` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::imaging::Image;
+    ///
+    /// let img = Image::from_vec((0..16).map(|v| v as f64 / 15.0).collect(), 4, 4, 1);
+    /// let p = img.patches(2, 2, 2);
+    /// assert_eq!(p.rows(), 4);
+    /// assert_eq!(p.cols(), 4);
+    /// ```
+    pub fn patches(&self, width: usize, height: usize, stride: usize) -> Matrix<f64> {
+
+        assert!(stride > 0, "Stride must be greater than zero.");
+
+        let mut m = Matrix::new();
+        if width > self.width || height > self.height {
+            return m;
+        }
+        for y in (0..self.height - height + 1).filter(|y| y % stride == 0) {
+            for x in (0..self.width - width + 1).filter(|x| x % stride == 0) {
+                m.add_row(&self.crop(x, y, width, height).data);
+            }
+        }
+        m
+    }
+
+    /// Returns the intensities of the image as a single feature vector (see
+    /// [from_vec](#method.from_vec) for the layout).
+    pub fn to_row(&self) -> Vec<f64> {
+        self.data.clone()
+    }
+}
+
+/// Converts images into a matrix where each row contains the features of
+/// one image (see [Image::to_row](struct.Image.html#method.to_row)).
+///
+/// Panics if the images have different dimensions or numbers of channels.
+pub fn to_matrix(images: &[Image]) -> Matrix<f64> {
+
+    let mut m = Matrix::new();
+    for img in images {
+        assert!(img.width == images[0].width && img.height == images[0].height && img.channels == images[0].channels,
+            "All images must have the same dimensions.");
+        m.add_row(&img.data);
+    }
+    m
+}
+
+/// Trait for algorithms which compute a feature vector of fixed length from
+/// an image.
+pub trait FeatureExtractor {
+
+    /// Returns the features of the image.
+    fn extract(&self, img: &Image) -> Vec<f64>;
+}
+
+/// Computes the features of each image with the extractor `e` and returns a
+/// matrix where each row contains the features of one image.
+///
+/// Panics if the extractor returns feature vectors of different lengths,
+/// e.g. if [Hog](struct.Hog.html) is used for images of different sizes.
+pub fn extract_features<E: FeatureExtractor>(e: &E, images: &[Image]) -> Matrix<f64> {
+
+    let mut m = Matrix::new();
+    for img in images {
+        let v = e.extract(img);
+        assert!(m.rows() == 0 || v.len() == m.cols(), "All feature vectors must have the same length.");
+        m.add_row(&v);
+    }
+    m
+}
+
+/// Histogram of oriented gradients (HOG).
+///
+/// The gradients of the grayscale version of an image are computed with the
+/// kernel `[-1, 0, 1]`. The image is divided into cells of `cell_size x
+/// cell_size` pixels and for each cell a histogram of the unsigned gradient
+/// orientations (0 to 180 degrees) weighted by the gradient magnitudes is
+/// computed. The histograms of overlapping blocks of `block_size x
+/// block_size` cells are L2-normalized and concatenated. Pixels at the
+/// right and bottom border which do not fill a complete cell are ignored.
+///
+/// The length of the feature vector depends on the size of the image (see
+/// [len](#method.len)), i.e. all images should have the same size (see
+/// [Image::resize](struct.Image.html#method.resize)).
+///
+/// # Example
+///
+/// ```
+/// use rustml::imaging::*;
+///
+/// let img = Image::new(64, 128, 3);
+/// let hog = Hog::new();
+/// assert_eq!(hog.extract(&img).len(), hog.len(64, 128));
+/// assert_eq!(hog.len(64, 128), 3780);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Hog {
+    cell_size: usize,
+    block_size: usize,
+    bins: usize
+}
+
+impl Hog {
+
+    /// Creates a HOG extractor with cells of 8x8 pixels, blocks of 2x2
+    /// cells and 9 orientation bins.
+    pub fn new() -> Hog {
+        Hog {
+            cell_size: 8,
+            block_size: 2,
+            bins: 9
+        }
+    }
+
+    /// Sets the width and height of a cell in pixels.
+    ///
+    /// Panics if `n` is zero.
+    pub fn cell_size(&self, n: usize) -> Hog {
+        assert!(n > 0, "Cell size must be greater than zero.");
+        Hog { cell_size: n, .. *self }
+    }
+
+    /// Sets the width and height of a block in cells.
+    ///
+    /// Panics if `n` is zero.
+    pub fn block_size(&self, n: usize) -> Hog {
+        assert!(n > 0, "Block size must be greater than zero.");
+        Hog { block_size: n, .. *self }
+    }
+
+    /// Sets the number of orientation bins.
+    ///
+    /// Panics if `n` is zero.
+    pub fn bins(&self, n: usize) -> Hog {
+        assert!(n > 0, "Number of bins must be greater than zero.");
+        Hog { bins: n, .. *self }
+    }
+
+    fn blocks(&self, width: usize, height: usize) -> (usize, usize) {
+
+        let f = |n: usize| (n / self.cell_size + 1).saturating_sub(self.block_size);
+        (f(width), f(height))
+    }
+
+    /// Returns the length of the feature vector for images of the given
+    /// size.
+    pub fn len(&self, width: usize, height: usize) -> usize {
+
+        let (bx, by) = self.blocks(width, height);
+        bx * by * self.block_size * self.block_size * self.bins
+    }
+}
+
+impl FeatureExtractor for Hog {
+
+    fn extract(&self, img: &Image) -> Vec<f64> {
+
+        let g = img.to_gray();
+        let (w, h) = (g.width, g.height);
+        let (cx, cy) = (w / self.cell_size, h / self.cell_size);
+        let p = |x: usize, y: usize| g.data[y * w + x];
+        let width = 180.0 / self.bins as f64;
+
+        let mut hist = vec![0.0; cx * cy * self.bins];
+        for y in 0..cy * self.cell_size {
+            for x in 0..cx * self.cell_size {
+                let gx = p((x + 1).min(w - 1), y) - p(x.saturating_sub(1), y);
+                let gy = p(x, (y + 1).min(h - 1)) - p(x, y.saturating_sub(1));
+                let mag = (gx * gx + gy * gy).sqrt();
+                if mag == 0.0 {
+                    continue;
+                }
+                let angle = gy.atan2(gx).to_degrees().rem_euclid(180.0);
+                // linear interpolation between the two nearest bin centers
+                let pos = angle / width - 0.5;
+                let wt = pos - pos.floor();
+                let b0 = (pos.floor() as isize).rem_euclid(self.bins as isize) as usize;
+                let b1 = (b0 + 1) % self.bins;
+                let cell = ((y / self.cell_size) * cx + x / self.cell_size) * self.bins;
+                hist[cell + b0] += mag * (1.0 - wt);
+                hist[cell + b1] += mag * wt;
+            }
+        }
+
+        let (bx, by) = self.blocks(w, h);
+        let mut features = Vec::with_capacity(self.len(w, h));
+        for y in 0..by {
+            for x in 0..bx {
+                let start = features.len();
+                for j in y..y + self.block_size {
+                    for i in x..x + self.block_size {
+                        let c = (j * cx + i) * self.bins;
+                        features.extend_from_slice(&hist[c..c + self.bins]);
+                    }
+                }
+                let norm = (features[start..].iter().map(|v| v * v).sum::<f64>() + 1e-12).sqrt();
+                for v in features[start..].iter_mut() {
+                    *v /= norm;
+                }
+            }
+        }
+        features
+    }
+}
+
+/// Color histogram.
+///
+/// The range `[0, 1]` of each channel of the RGB version of an image is
+/// divided into `bins` intervals of equal width. The feature vector contains
+/// the fraction of pixels whose value falls into each interval, first for
+/// the red channel, then for the green and the blue channel, i.e. the
+/// length of the feature vector is `3 * bins` for all images.
+///
+/// # Example
+///
+/// ```
+/// use rustml::imaging::*;
+///
+/// let img = Image::from_vec(vec![1.0, 0.0, 0.2, 0.9, 0.1, 0.6], 2, 1, 3);
+/// let h = ColorHistogram::new(2).extract(&img);
+/// assert_eq!(h, vec![0.0, 1.0, 1.0, 0.0, 0.5, 0.5]);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ColorHistogram {
+    bins: usize
+}
+
+impl ColorHistogram {
+
+    /// Creates a color histogram with `bins` bins per channel.
+    ///
+    /// Panics if `bins` is zero.
+    pub fn new(bins: usize) -> ColorHistogram {
+        assert!(bins > 0, "Number of bins must be greater than zero.");
+        ColorHistogram { bins: bins }
+    }
+}
+
+impl FeatureExtractor for ColorHistogram {
+
+    fn extract(&self, img: &Image) -> Vec<f64> {
+
+        let rgb = img.to_rgb();
+        let n = (rgb.width * rgb.height) as f64;
+        let mut h = vec![0.0; 3 * self.bins];
+        for p in rgb.data.chunks(3) {
+            for (c, v) in p.iter().enumerate() {
+                let b = ((v.max(0.0) * self.bins as f64) as usize).min(self.bins - 1);
+                h[c * self.bins + b] += 1.0 / n;
+            }
+        }
+        h
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_gray_rgb() {
+
+        let img = Image::from_vec(vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0], 2, 1, 3);
+        let g = img.to_gray();
+        assert_eq!((g.width(), g.height(), g.channels()), (2, 1, 1));
+        assert!((g.buf()[0] - 0.299).abs() < 1e-9);
+        assert!((g.buf()[1] - 0.114).abs() < 1e-9);
+        assert_eq!(g.to_rgb().pixel(1, 0), &[g.buf()[1]; 3]);
+        assert_eq!(img.to_rgb(), img);
+    }
+
+    #[test]
+    fn test_resize() {
+
+        let img = Image::from_vec(vec![0.0, 1.0, 1.0, 0.0], 2, 2, 1);
+        assert_eq!(img.resize(2, 2), img);
+        assert_eq!(img.resize(1, 1).buf(), &vec![0.5]);
+
+        let r = img.resize(4, 4);
+        assert_eq!(r.pixel(0, 0), &[0.0]);
+        assert_eq!(r.pixel(3, 0), &[1.0]);
+        assert!((r.pixel(1, 0)[0] - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_crop_patches() {
+
+        let img = Image::from_vec((0..12).map(|v| v as f64).collect(), 4, 3, 1);
+        assert_eq!(img.crop(1, 1, 2, 2).buf(), &vec![5.0, 6.0, 9.0, 10.0]);
+
+        let p = img.patches(2, 2, 2);
+        assert!(p.eq(&mat![0.0, 1.0, 4.0, 5.0; 2.0, 3.0, 6.0, 7.0]));
+        assert_eq!(img.patches(3, 3, 1).rows(), 2);
+        assert_eq!(img.patches(5, 1, 1).rows(), 0);
+
+        let m = to_matrix(&[img.clone(), img.clone()]);
+        assert_eq!((m.rows(), m.cols()), (2, 12));
+        assert_eq!(m.row(1).unwrap(), &img.to_row()[..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_to_matrix_different_dimensions() {
+        to_matrix(&[Image::new(2, 2, 1), Image::new(2, 2, 3)]);
+    }
+
+    #[test]
+    fn test_hog() {
+
+        let hog = Hog::new().cell_size(4).block_size(2).bins(9);
+        assert_eq!(hog.len(16, 8), 3 * 1 * 4 * 9);
+        assert_eq!(hog.len(7, 7), 0);
+        assert_eq!(hog.extract(&Image::new(7, 7, 1)).len(), 0);
+
+        // uniform images have no gradients
+        assert!(hog.extract(&Image::new(16, 8, 3)).iter().all(|&v| v == 0.0));
+
+        // vertical edge: horizontal gradients, i.e. an orientation of 0
+        // degrees which is split between the first and the last bin
+        let img = Image::from_vec((0..128).map(|i| if i % 16 >= 8 { 1.0 } else { 0.0 }).collect(), 16, 8, 1);
+        let f = hog.extract(&img);
+        assert_eq!(f.len(), hog.len(16, 8));
+        for (i, v) in f.iter().enumerate() {
+            if i % 9 != 0 && i % 9 != 8 {
+                assert_eq!(*v, 0.0);
+            }
+        }
+        let block: f64 = f[..36].iter().map(|v| v * v).sum();
+        assert!((block - 1.0).abs() < 1e-6);
+
+        // horizontal edge: orientation of 90 degrees in the center bin
+        let img = Image::from_vec((0..128).map(|i| if i >= 64 { 1.0 } else { 0.0 }).collect(), 16, 8, 1);
+        let f = hog.extract(&img);
+        assert!(f.iter().enumerate().all(|(i, &v)| i % 9 == 4 || v == 0.0));
+        assert!(f.iter().any(|&v| v > 0.0));
+    }
+
+    #[test]
+    fn test_color_histogram_extract_features() {
+
+        let h = ColorHistogram::new(4);
+        let gray = Image::from_vec(vec![0.0, 0.3, 0.6, 1.0], 2, 2, 1);
+        assert_eq!(h.extract(&gray), vec![0.25; 12]);
+
+        let m = extract_features(&h, &[gray.clone(), Image::new(3, 1, 3)]);
+        assert_eq!((m.rows(), m.cols()), (2, 12));
+        assert_eq!(m.row(1).unwrap(), &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_extract_features_different_lengths() {
+        extract_features(&Hog::new().cell_size(2), &[Image::new(4, 4, 1), Image::new(8, 8, 1)]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_save_open() {
+
+        let img = Image::from_vec(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0], 2, 2, 3);
+        let path = ::std::env::temp_dir().join("rustml_test_image.png");
+        img.save(path.to_str().unwrap()).unwrap();
+        assert_eq!(Image::open(path.to_str().unwrap()).unwrap(), img);
+
+        let buf = ::std::fs::read(&path).unwrap();
+        assert_eq!(Image::from_memory(&buf).unwrap(), img);
+        assert!(Image::from_memory(&[1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Module to replace missing values in a matrix.
+//!
+//! Missing values are represented by NaN. Because a single NaN value
+//! propagates through nearly every computation (e.g. the mean of a column, a
+//! matrix multiplication or the output of a neural network) missing values
+//! should be replaced before a model is trained.
+//!
+//! The [`Imputer`](struct.Imputer.html) computes a replacement value for each
+//! column from the values of the training set which are not missing. The same
+//! replacement values can then be used for other datasets, e.g. a test set.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use std::f64::NAN;
+//! use rustml::*;
+//! use rustml::imputation::*;
+//!
+//! # fn main() {
+//! let m = mat![
+//!     1.0, NAN;
+//!     NAN, 4.0;
+//!     3.0, 8.0
+//! ];
+//! let i = Imputer::fit(&m, Strategy::Mean);
+//! assert_eq!(i.values(), &[2.0, 6.0]);
+//!
+//! // positions of the values that are replaced
+//! let mask = missing_mask(&m);
+//! assert_eq!(mask, mat![false, true; true, false; false, false]);
+//!
+//! assert_eq!(i.transform(&m), mat![1.0, 6.0; 2.0, 4.0; 3.0, 8.0]);
+//! # }
+//! ```
+extern crate num;
+
+use std::cmp::Ordering;
+use self::num::traits::Float;
+
+use matrix::Matrix;
+use math::{MeanVec, Quantile};
+use io::model::{Model, Encoder, Decoder};
+
+/// Determines how the replacement value of a column is computed.
+#[derive(Clone, Copy, Debug)]
+pub enum Strategy<T> {
+    /// Use the mean of the values that are not missing.
+    Mean,
+    /// Use the median of the values that are not missing.
+    Median,
+    /// Use the value that occurs most often. If more than one value occurs
+    /// most often the smallest one is used.
+    MostFrequent,
+    /// Use the given constant.
+    Constant(T)
+}
+
+/// Replaces missing values (i.e. NaN) of each column by a value computed
+/// from the training set.
+///
+/// If all values of a column are missing during `fit` (and the strategy is
+/// not `Strategy::Constant`) the replacement value for that column is zero.
+#[derive(Clone, Debug)]
+pub struct Imputer<T> {
+    values: Vec<T>
+}
+
+impl <T: Float> Imputer<T> {
+
+    /// Computes the replacement value of each column of `m` with the
+    /// given strategy.
+    pub fn fit(m: &Matrix<T>, strategy: Strategy<T>) -> Imputer<T>
+        where Vec<T>: MeanVec<T> + Quantile<T> {
+
+        let values = m.col_iter()
+            .map(|c| c.into_iter().filter(|x| !x.is_nan()).collect::<Vec<T>>())
+            .map(|c| match strategy {
+                Strategy::Constant(val) => val,
+                _ if c.len() == 0 => T::zero(),
+                Strategy::Mean => c.mean(),
+                Strategy::Median => c.median(),
+                Strategy::MostFrequent => most_frequent(c)
+            })
+            .collect();
+
+        Imputer {
+            values: values
+        }
+    }
+
+    /// Computes the replacement values from `m` and returns the imputer
+    /// together with the matrix in which the missing values have been
+    /// replaced.
+    pub fn fit_transform(m: &Matrix<T>, strategy: Strategy<T>) -> (Imputer<T>, Matrix<T>)
+        where Vec<T>: MeanVec<T> + Quantile<T> {
+
+        let i = Imputer::fit(m, strategy);
+        let r = i.transform(m);
+        (i, r)
+    }
+
+    /// Creates an imputer that uses the given replacement values for the
+    /// columns.
+    pub fn from_values(values: &[T]) -> Imputer<T> {
+        Imputer {
+            values: values.to_vec()
+        }
+    }
+
+    /// Returns the replacement value of each column.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Replaces each missing value of `m` by the replacement value of its
+    /// column.
+    ///
+    /// Panics if the number of columns does not match the number of
+    /// replacement values.
+    pub fn transform(&self, m: &Matrix<T>) -> Matrix<T> {
+
+        assert!(m.cols() == self.values.len(),
+            "Number of columns does not match the number of features.");
+
+        let c = m.cols();
+        Matrix::from_vec(
+            m.iter().enumerate()
+                .map(|(i, &x)| if x.is_nan() { self.values[i % c] } else { x })
+                .collect(),
+            m.rows(), m.cols()
+        )
+    }
+}
+
+/// Returns the value that occurs most often. The vector must not be empty
+/// and must not contain NaN values.
+fn most_frequent<T: Float>(mut v: Vec<T>) -> T {
+
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let mut best = (v[0], 0);
+    let mut cur = (v[0], 0);
+    for x in v {
+        if x == cur.0 {
+            cur.1 += 1;
+        } else {
+            cur = (x, 1);
+        }
+        if cur.1 > best.1 {
+            best = cur;
+        }
+    }
+    best.0
+}
+
+/// Returns a matrix with the same dimension as `m` where an element is `true`
+/// if the corresponding element in `m` is missing (i.e. NaN).
+pub fn missing_mask<T: Float>(m: &Matrix<T>) -> Matrix<bool> {
+    m.map(|x| x.is_nan())
+}
+
+impl Model for Imputer<f64> {
+
+    fn tag() -> &'static str { "rustml.imputation.Imputer" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.values);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Imputer<f64>, String> {
+        Ok(Imputer { values: try!(d.f64s()) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::f64::NAN;
+
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_imputer_strategies() {
+
+        let m = mat![
+            1.0, NAN, 2.0;
+            NAN, 4.0, 2.0;
+            3.0, 6.0, NAN;
+            8.0, 4.0, 5.0
+        ];
+
+        assert_eq!(Imputer::fit(&m, Strategy::Mean).values(), &[4.0, 14.0 / 3.0, 3.0]);
+        assert_eq!(Imputer::fit(&m, Strategy::Median).values(), &[3.0, 4.0, 2.0]);
+        assert_eq!(Imputer::fit(&m, Strategy::MostFrequent).values(), &[1.0, 4.0, 2.0]);
+        assert_eq!(Imputer::fit(&m, Strategy::Constant(-1.0)).values(), &[-1.0, -1.0, -1.0]);
+
+        let (_, x) = Imputer::fit_transform(&m, Strategy::Median);
+        assert_eq!(x, mat![
+            1.0, 4.0, 2.0;
+            3.0, 4.0, 2.0;
+            3.0, 6.0, 2.0;
+            8.0, 4.0, 5.0
+        ]);
+    }
+
+    #[test]
+    fn test_imputer_all_missing() {
+
+        let m = mat![NAN, 1.0; NAN, 2.0];
+        assert_eq!(Imputer::fit(&m, Strategy::Mean).values(), &[0.0, 1.5]);
+        assert_eq!(Imputer::fit(&m, Strategy::Constant(7.0)).values(), &[7.0, 7.0]);
+    }
+
+    #[test]
+    fn test_imputer_from_values() {
+
+        let i = Imputer::from_values(&[5.0, 6.0]);
+        assert_eq!(i.transform(&mat![NAN, 1.0; 2.0, NAN]), mat![5.0, 1.0; 2.0, 6.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_imputer_panic() {
+
+        let i = Imputer::from_values(&[5.0, 6.0]);
+        i.transform(&mat![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_missing_mask() {
+
+        let m = mat![NAN, 1.0; 2.0, 3.0];
+        assert_eq!(missing_mask(&m), mat![true, false; false, false]);
+    }
+}
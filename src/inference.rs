@@ -0,0 +1,522 @@
+//! Inference of trained models without the standard library.
+//!
+//! This module only depends on `core` and `alloc` so that trained models can
+//! be deployed on embedded devices. If rustml is compiled without the
+//! default feature `std` (i.e. with `--no-default-features`) the crate is
+//! `no_std` and this module is the only module which is available.
+//!
+//! Models are trained with the full library, converted into the types of
+//! this module (e.g. a [NeuralNetwork](../nn/struct.NeuralNetwork.html) into
+//! a [Network](struct.Network.html) via `TryFrom`, which fails if the network
+//! uses a user-defined activation function) and serialized with `to_bytes`.
+//! On the
+//! device the bytes (e.g. embedded with `include_bytes!`) are loaded with
+//! `from_bytes`.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::nn::NeuralNetwork;
+//! use rustml::inference::Network;
+//! use std::convert::TryFrom;
+//!
+//! # fn main() {
+//! let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+//! let bytes = Network::try_from(&n).unwrap().to_bytes();
+//!
+//! // on the device
+//! let net = Network::from_bytes(&bytes).unwrap();
+//! let y = net.predict_row(&[0.5, 1.0]);
+//! assert!((y[0] - n.predict(&mat![0.5, 1.0]).get(0, 0).unwrap()).abs() < 1e-12);
+//! # }
+//! ```
+use alloc::vec::Vec;
+use libm::{exp, tanh};
+
+#[cfg(feature = "std")]
+use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use matrix::Matrix;
+#[cfg(feature = "std")]
+use nn::NeuralNetwork;
+#[cfg(feature = "std")]
+use regression::Hypothesis;
+
+/// A dense matrix of `f64` values stored in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dense {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>
+}
+
+impl Dense {
+
+    /// Creates a matrix from the values in `data` in row-major order.
+    ///
+    /// Panics if the number of values is not `rows * cols`.
+    pub fn new(data: Vec<f64>, rows: usize, cols: usize) -> Dense {
+
+        assert!(rows * cols == data.len(),
+            "Number of elements in vector must be equal to the number of elements in the matrix.");
+        Dense { rows: rows, cols: cols, data: data }
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the values in row-major order.
+    pub fn buf(&self) -> &[f64] {
+        &self.data
+    }
+
+    /// Returns the row at index `n`.
+    ///
+    /// Panics if the row does not exist.
+    pub fn row(&self, n: usize) -> &[f64] {
+        &self.data[n * self.cols..(n + 1) * self.cols]
+    }
+
+    /// Computes the product of the matrix and the vector `x` and appends the
+    /// result to `out`.
+    ///
+    /// Panics if the length of `x` is not the number of columns.
+    pub fn mul_vec_into(&self, x: &[f64], out: &mut Vec<f64>) {
+
+        assert!(x.len() == self.cols, "Dimensions do not match.");
+        for i in 0..self.rows {
+            out.push(dot(self.row(i), x));
+        }
+    }
+
+    /// Computes the product of the matrix and the vector `x`.
+    pub fn mul_vec(&self, x: &[f64]) -> Vec<f64> {
+        let mut v = Vec::with_capacity(self.rows);
+        self.mul_vec_into(x, &mut v);
+        v
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + x * y)
+}
+
+/// Activation function of the units of a layer (see the functions of the
+/// module [nn](../nn/index.html)).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation {
+    /// The logistic function.
+    Sigmoid,
+    /// The hyperbolic tangent.
+    Tanh,
+    /// The rectifier `max(0, z)`.
+    Relu,
+    /// The rectifier with the given slope for negative inputs.
+    LeakyRelu(f64)
+}
+
+impl Activation {
+
+    /// Computes the activation of a unit with the net input `z`.
+    pub fn apply(&self, z: f64) -> f64 {
+        match *self {
+            Activation::Sigmoid => 1.0 / (1.0 + exp(-z)),
+            Activation::Tanh => tanh(z),
+            Activation::Relu => if z > 0.0 { z } else { 0.0 },
+            Activation::LeakyRelu(a) => if z > 0.0 { z } else { a * z }
+        }
+    }
+
+    // code and parameter of the function used by to_bytes
+    fn code(&self) -> (u32, f64) {
+        match *self {
+            Activation::Sigmoid => (0, 0.0),
+            Activation::Tanh => (1, 0.0),
+            Activation::Relu => (2, 0.0),
+            Activation::LeakyRelu(a) => (3, a)
+        }
+    }
+
+    fn from_code(code: u32, param: f64) -> Option<Activation> {
+        match code {
+            0 => Some(Activation::Sigmoid),
+            1 => Some(Activation::Tanh),
+            2 => Some(Activation::Relu),
+            3 => Some(Activation::LeakyRelu(param)),
+            _ => None
+        }
+    }
+}
+
+// numerically stable softmax
+fn softmax(v: &mut [f64]) {
+    let m = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut s = 0.0;
+    for x in v.iter_mut() {
+        *x = exp(*x - m);
+        s += *x;
+    }
+    for x in v.iter_mut() {
+        *x /= s;
+    }
+}
+
+/// A neural network with the same architecture as
+/// [NeuralNetwork](../nn/struct.NeuralNetwork.html), i.e. with a bias unit in
+/// each hidden layer, an activation function for each layer and optionally
+/// the softmax in the output layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    params: Vec<Dense>,
+    activations: Vec<Activation>,
+    softmax: bool
+}
+
+impl Network {
+
+    /// Creates a network with sigmoid units from the parameters of each
+    /// layer. The parameters of layer `i` are a matrix where row `j`
+    /// contains the weights of unit `j` of layer `i + 1`. The first column
+    /// of the parameters of a hidden layer contains the weights of the bias
+    /// unit.
+    ///
+    /// Returns `None` if no parameters are given or if the dimensions of the
+    /// parameters do not match.
+    pub fn new(params: Vec<Dense>) -> Option<Network> {
+
+        let activations = params.iter().map(|_| Activation::Sigmoid).collect();
+        Network::with_activations(params, activations, false)
+    }
+
+    /// Creates a network like [new](#method.new) where the units of layer
+    /// `i + 1` use the activation function `activations[i]`. If `softmax` is
+    /// true the output layer computes the softmax instead.
+    ///
+    /// Returns `None` if no parameters are given or if the dimensions of the
+    /// parameters or the number of activation functions do not match.
+    pub fn with_activations(params: Vec<Dense>, activations: Vec<Activation>, softmax: bool) -> Option<Network> {
+
+        if params.is_empty() || activations.len() != params.len() {
+            return None;
+        }
+        for i in 1..params.len() {
+            if params[i].cols() != params[i - 1].rows() + 1 {
+                return None;
+            }
+        }
+        Some(Network { params: params, activations: activations, softmax: softmax })
+    }
+
+    /// Returns the parameters of each layer.
+    pub fn params(&self) -> &[Dense] {
+        &self.params
+    }
+
+    /// Returns the activation function of each layer except the input
+    /// layer.
+    pub fn activations(&self) -> &[Activation] {
+        &self.activations
+    }
+
+    /// Returns true if the output layer computes the softmax.
+    pub fn is_softmax(&self) -> bool {
+        self.softmax
+    }
+
+    /// Returns the number of units in the input layer.
+    pub fn input_size(&self) -> usize {
+        self.params[0].cols()
+    }
+
+    /// Returns the number of units in the output layer.
+    pub fn output_size(&self) -> usize {
+        self.params[self.params.len() - 1].rows()
+    }
+
+    /// Computes the output of the network for the input `x`.
+    ///
+    /// Panics if the length of `x` is not the number of input units.
+    pub fn predict_row(&self, x: &[f64]) -> Vec<f64> {
+
+        let n = self.params.len();
+        let mut a = x.to_vec();
+        let mut b = Vec::new();
+        for (i, p) in self.params.iter().enumerate() {
+            b.clear();
+            // bias unit for the next layer
+            if i + 1 < n {
+                b.push(1.0);
+            }
+            let k = b.len();
+            p.mul_vec_into(&a, &mut b);
+            if self.softmax && i + 1 == n {
+                softmax(&mut b[k..]);
+            } else {
+                for v in &mut b[k..] {
+                    *v = self.activations[i].apply(*v);
+                }
+            }
+            core::mem::swap(&mut a, &mut b);
+        }
+        a
+    }
+
+    /// Computes the outputs of the network for the examples in the rows of
+    /// `x`.
+    pub fn predict(&self, x: &Dense) -> Dense {
+
+        let mut data = Vec::with_capacity(x.rows() * self.output_size());
+        for i in 0..x.rows() {
+            data.extend(self.predict_row(x.row(i)));
+        }
+        Dense::new(data, x.rows(), self.output_size())
+    }
+
+    /// Serializes the network. All values are stored as little-endian
+    /// numbers: the number of layers of parameters (`u32`), for each layer
+    /// the number of rows and columns (`u32`), the activation function
+    /// (`u32`, 0 = sigmoid, 1 = tanh, 2 = relu, 3 = leaky relu) and its
+    /// parameter (`f64`) followed by the parameters (`f64`) in row-major
+    /// order, and finally the softmax flag (`u32`).
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        let mut v = Vec::new();
+        v.extend_from_slice(&(self.params.len() as u32).to_le_bytes());
+        for (p, a) in self.params.iter().zip(self.activations.iter()) {
+            let (code, param) = a.code();
+            v.extend_from_slice(&(p.rows() as u32).to_le_bytes());
+            v.extend_from_slice(&(p.cols() as u32).to_le_bytes());
+            v.extend_from_slice(&code.to_le_bytes());
+            v.extend_from_slice(&param.to_le_bytes());
+            for x in p.buf() {
+                v.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        v.extend_from_slice(&(self.softmax as u32).to_le_bytes());
+        v
+    }
+
+    /// Deserializes a network which has been serialized with
+    /// [to_bytes](#method.to_bytes). Returns `None` if the data is invalid.
+    pub fn from_bytes(data: &[u8]) -> Option<Network> {
+
+        let mut r = Reader { data: data, pos: 0 };
+        let n = match r.u32() { Some(n) => n, None => return None };
+        let mut params = Vec::new();
+        let mut activations = Vec::new();
+        for _ in 0..n {
+            let (rows, cols) = match (r.u32(), r.u32()) {
+                (Some(a), Some(b)) => (a as usize, b as usize),
+                _ => return None
+            };
+            match (r.u32(), r.f64()) {
+                (Some(c), Some(p)) => match Activation::from_code(c, p) {
+                    Some(a) => activations.push(a),
+                    None => return None
+                },
+                _ => return None
+            }
+            let mut v = Vec::new();
+            for _ in 0..rows * cols {
+                match r.f64() {
+                    Some(x) => v.push(x),
+                    None => return None
+                }
+            }
+            params.push(Dense::new(v, rows, cols));
+        }
+        let softmax = match r.u32() {
+            Some(0) => false,
+            Some(1) => true,
+            _ => return None
+        };
+        if r.pos != data.len() {
+            return None;
+        }
+        Network::with_activations(params, activations, softmax)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl <'a> Reader<'a> {
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return None;
+        }
+        self.pos += n;
+        Some(&self.data[self.pos - n..self.pos])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.take(8).map(|b| {
+            let mut a = [0u8; 8];
+            a.copy_from_slice(b);
+            f64::from_le_bytes(a)
+        })
+    }
+}
+
+/// A linear model which computes the dot product of the parameters and
+/// the input like [Hypothesis](../regression/struct.Hypothesis.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linear {
+    thetas: Vec<f64>
+}
+
+impl Linear {
+
+    /// Creates a linear model with the given parameters.
+    pub fn new(thetas: Vec<f64>) -> Linear {
+        Linear { thetas: thetas }
+    }
+
+    /// Returns the parameters.
+    pub fn params(&self) -> &[f64] {
+        &self.thetas
+    }
+
+    /// Computes the prediction for the input `x`.
+    ///
+    /// Panics if the length of `x` is not the number of parameters.
+    pub fn predict_row(&self, x: &[f64]) -> f64 {
+
+        assert!(x.len() == self.thetas.len(), "Dimensions do not match.");
+        dot(&self.thetas, x)
+    }
+
+    /// Computes the predictions for the examples in the rows of `x`.
+    pub fn predict(&self, x: &Dense) -> Vec<f64> {
+        (0..x.rows()).map(|i| self.predict_row(x.row(i))).collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl <'a> From<&'a Matrix<f64>> for Dense {
+
+    fn from(m: &Matrix<f64>) -> Dense {
+        Dense::new(m.buf().clone(), m.rows(), m.cols())
+    }
+}
+
+#[cfg(feature = "std")]
+impl <'a> TryFrom<&'a NeuralNetwork> for Network {
+
+    type Error = String;
+
+    /// Returns an error if the network has fewer than two layers or if a
+    /// layer uses an activation function which is not one of the
+    /// functions of the module [nn](../nn/index.html).
+    fn try_from(n: &NeuralNetwork) -> Result<Network, String> {
+
+        if n.layers() < 2 {
+            return Err("At least two layers are required.".to_string());
+        }
+        let mut activations = Vec::new();
+        for i in 1..n.layers() {
+            let a = n.activation(i);
+            activations.push(match (a.name(), a.params().len()) {
+                ("sigmoid", 0) => Activation::Sigmoid,
+                ("tanh", 0) => Activation::Tanh,
+                ("relu", 0) => Activation::Relu,
+                ("leaky_relu", 1) => Activation::LeakyRelu(a.params()[0]),
+                (name, _) => return Err(format!("Activation function {} is not supported.", name))
+            });
+        }
+        Ok(Network::with_activations(n.params().iter().map(Dense::from).collect(), activations, n.is_softmax()).unwrap())
+    }
+}
+
+#[cfg(feature = "std")]
+impl <'a> From<&'a Hypothesis> for Linear {
+
+    fn from(h: &Hypothesis) -> Linear {
+        Linear::new(h.params())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use nn::{self, NeuralNetwork, Tanh, Relu, LeakyRelu};
+    use regression::Hypothesis;
+
+    #[test]
+    fn test_network() {
+
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(5).add_layer(2);
+        let x = Matrix::from_vec((0..30).map(|i| i as f64 / 10.0).collect(), 10, 3);
+        let net = Network::try_from(&n).unwrap();
+        assert_eq!(net.input_size(), 3);
+        assert_eq!(net.output_size(), 2);
+
+        let p = net.predict(&Dense::from(&x));
+        assert!(Matrix::from_vec(p.buf().to_vec(), 10, 2).similar(&n.predict(&x), 1e-12));
+
+        let b = net.to_bytes();
+        assert_eq!(Network::from_bytes(&b), Some(net));
+        assert_eq!(Network::from_bytes(&b[..b.len() - 1]), None);
+        assert!(Network::new(vec![Dense::new(vec![1.0; 6], 2, 3), Dense::new(vec![1.0; 2], 1, 2)]).is_none());
+    }
+
+    // user-defined activation function
+    #[derive(Debug)]
+    struct Square;
+
+    impl nn::Activation for Square {
+        fn name(&self) -> &'static str { "square" }
+        fn apply(&self, z: f64) -> f64 { z * z }
+        fn derivative(&self, z: f64) -> f64 { 2.0 * z }
+    }
+
+    #[test]
+    fn test_network_activations() {
+
+        let x = Matrix::from_vec((0..30).map(|i| i as f64 / 5.0 - 3.0).collect(), 10, 3);
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(5).add_layer(3)
+            .set_activation(1, Tanh)
+            .set_activation(2, LeakyRelu(0.1))
+            .set_activation(3, Relu);
+        for n in &[n.clone(), n.set_softmax(true)] {
+            let net = Network::try_from(n).unwrap();
+            assert_eq!(net.activations(), &[Activation::Tanh, Activation::LeakyRelu(0.1), Activation::Relu]);
+            assert_eq!(net.is_softmax(), n.is_softmax());
+            let p = net.predict(&Dense::from(&x));
+            assert!(Matrix::from_vec(p.buf().to_vec(), 10, 3).similar(&n.predict(&x), 1e-12));
+            assert_eq!(Network::from_bytes(&net.to_bytes()), Some(net));
+        }
+
+        assert!(Network::try_from(&n.set_activation(2, Square)).is_err());
+        assert!(Network::try_from(&NeuralNetwork::new().add_layer(2)).is_err());
+    }
+
+    #[test]
+    fn test_linear() {
+
+        let h = Hypothesis::from_params(&[1.0, 2.0, -1.0]);
+        let x = mat![1.0, 2.0, 3.0; 1.0, 0.5, 0.0];
+        let l = Linear::from(&h);
+        assert_eq!(l.predict(&Dense::from(&x)), h.eval(&x));
+        assert_eq!(l.predict_row(&[1.0, 1.0, 1.0]), 2.0);
+    }
+}
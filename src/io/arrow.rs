@@ -0,0 +1,658 @@
+//! Reading of numeric columns from Apache Arrow IPC files.
+//!
+//! This module is only available if rustml is compiled with the feature
+//! `arrow`. It reads files in the Arrow IPC file format (also known as
+//! Feather version 2, usually with the extension `.arrow` or `.feather`) and
+//! in the Arrow IPC streaming format.
+//!
+//! Columns of integers, floating point numbers (16, 32 and 64 bit) and
+//! booleans are loaded into a matrix of `f64` with one column per selected
+//! field. Booleans are converted into `0` and `1` and null values into
+//! `NaN`. Columns of other types (e.g. strings, nested or dictionary encoded
+//! columns) are skipped unless they are explicitly requested, which is an
+//! error. All record batches of a file are concatenated.
+//!
+//! Compressed record batches are not supported. Parquet files are read with
+//! the module [parquet](../parquet/index.html) (feature `parquet`).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rustml::io::arrow::*;
+//!
+//! let (names, x) = load_arrow("data.arrow", &["age", "income"]).unwrap();
+//! assert_eq!(names.len(), x.cols());
+//! ```
+use std::f64;
+use std::fs::File;
+use std::io::Read;
+
+use matrix::Matrix;
+use sparse::SparseMatrix;
+
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+
+// types of the message header union
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+// types of the type union
+const TYPE_NULL: u8 = 1;
+const TYPE_INT: u8 = 2;
+const TYPE_FLOAT: u8 = 3;
+const TYPE_BINARY: u8 = 4;
+const TYPE_UTF8: u8 = 5;
+const TYPE_BOOL: u8 = 6;
+const TYPE_STRUCT: u8 = 13;
+const TYPE_UNION: u8 = 14;
+const TYPE_FIXED_SIZE_LIST: u8 = 16;
+const TYPE_LARGE_BINARY: u8 = 19;
+const TYPE_LARGE_UTF8: u8 = 20;
+const TYPE_RUN_END_ENCODED: u8 = 22;
+
+fn u16_at(buf: &[u8], pos: usize) -> Result<u16, String> {
+    match buf.get(pos..pos + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+        None => Err("Invalid metadata.".to_string())
+    }
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> Result<u32, String> {
+    match buf.get(pos..pos + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err("Invalid metadata.".to_string())
+    }
+}
+
+fn u64_at(buf: &[u8], pos: usize) -> Result<u64, String> {
+    let lo = try!(u32_at(buf, pos)) as u64;
+    let hi = try!(u32_at(buf, pos + 4)) as u64;
+    Ok(hi << 32 | lo)
+}
+
+/// A table of the flatbuffers encoded metadata.
+#[derive(Clone, Copy)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    vtable: usize
+}
+
+impl <'a> Table<'a> {
+
+    fn new(buf: &'a [u8], pos: usize) -> Result<Table<'a>, String> {
+        let soffset = try!(u32_at(buf, pos)) as i32 as i64;
+        let vtable = pos as i64 - soffset;
+        if vtable < 0 {
+            return Err("Invalid metadata.".to_string());
+        }
+        Ok(Table { buf: buf, pos: pos, vtable: vtable as usize })
+    }
+
+    fn root(buf: &'a [u8]) -> Result<Table<'a>, String> {
+        let pos = try!(u32_at(buf, 0)) as usize;
+        Table::new(buf, pos)
+    }
+
+    /// Returns the position of a field or `None` if the field is absent.
+    fn field(&self, slot: usize) -> Result<Option<usize>, String> {
+        let size = try!(u16_at(self.buf, self.vtable)) as usize;
+        if 4 + 2 * slot >= size {
+            return Ok(None);
+        }
+        match try!(u16_at(self.buf, self.vtable + 4 + 2 * slot)) {
+            0 => Ok(None),
+            o => Ok(Some(self.pos + o as usize))
+        }
+    }
+
+    fn u8(&self, slot: usize) -> Result<u8, String> {
+        match try!(self.field(slot)) {
+            Some(p) => self.buf.get(p).cloned().ok_or("Invalid metadata.".to_string()),
+            None => Ok(0)
+        }
+    }
+
+    fn u16(&self, slot: usize) -> Result<u16, String> {
+        match try!(self.field(slot)) {
+            Some(p) => u16_at(self.buf, p),
+            None => Ok(0)
+        }
+    }
+
+    fn u32(&self, slot: usize) -> Result<u32, String> {
+        match try!(self.field(slot)) {
+            Some(p) => u32_at(self.buf, p),
+            None => Ok(0)
+        }
+    }
+
+    fn u64(&self, slot: usize) -> Result<u64, String> {
+        match try!(self.field(slot)) {
+            Some(p) => u64_at(self.buf, p),
+            None => Ok(0)
+        }
+    }
+
+    /// Follows the offset stored in a field.
+    fn indirect(&self, slot: usize) -> Result<Option<usize>, String> {
+        match try!(self.field(slot)) {
+            Some(p) => Ok(Some(p + try!(u32_at(self.buf, p)) as usize)),
+            None => Ok(None)
+        }
+    }
+
+    fn table(&self, slot: usize) -> Result<Option<Table<'a>>, String> {
+        match try!(self.indirect(slot)) {
+            Some(p) => Table::new(self.buf, p).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn string(&self, slot: usize) -> Result<String, String> {
+        match try!(self.indirect(slot)) {
+            Some(p) => {
+                let n = try!(u32_at(self.buf, p)) as usize;
+                match self.buf.get(p + 4..p + 4 + n) {
+                    Some(s) => Ok(String::from_utf8_lossy(s).into_owned()),
+                    None => Err("Invalid metadata.".to_string())
+                }
+            },
+            None => Ok(String::new())
+        }
+    }
+
+    /// Returns the position of the first element and the length of a vector.
+    fn vector(&self, slot: usize) -> Result<(usize, usize), String> {
+        match try!(self.indirect(slot)) {
+            Some(p) => Ok((p + 4, try!(u32_at(self.buf, p)) as usize)),
+            None => Ok((0, 0))
+        }
+    }
+
+    fn tables(&self, slot: usize) -> Result<Vec<Table<'a>>, String> {
+        let (start, n) = try!(self.vector(slot));
+        (0..n).map(|i| {
+            let p = start + 4 * i;
+            Table::new(self.buf, p + try!(u32_at(self.buf, p)) as usize)
+        }).collect()
+    }
+
+    /// Returns a vector of structs which consist of two 64-bit integers.
+    fn pairs(&self, slot: usize) -> Result<Vec<(u64, u64)>, String> {
+        let (start, n) = try!(self.vector(slot));
+        (0..n).map(|i| {
+            let p = start + 16 * i;
+            Ok((try!(u64_at(self.buf, p)), try!(u64_at(self.buf, p + 8))))
+        }).collect()
+    }
+}
+
+/// Type of a column which can be converted into numbers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Numeric {
+    Int(usize, bool),
+    Half,
+    Float,
+    Double,
+    Bool
+}
+
+/// A field of the schema in depth-first order.
+struct Field {
+    name: String,
+    numeric: Option<Numeric>,
+    // number of buffers and the number of nested fields
+    buffers: usize,
+    children: usize
+}
+
+/// Appends a field and all its children to `fields`.
+fn parse_field(t: &Table, fields: &mut Vec<Field>) -> Result<(), String> {
+
+    let name = try!(t.string(0));
+    let type_type = try!(t.u8(2));
+    let dictionary = try!(t.field(4)).is_some();
+    let ty = try!(t.table(3));
+
+    let numeric = match (type_type, ty) {
+        _ if dictionary => None,
+        (TYPE_INT, Some(ty)) => match try!(ty.u32(0)) {
+            b @ 8 | b @ 16 | b @ 32 | b @ 64 => Some(Numeric::Int(b as usize, try!(ty.u8(1)) != 0)),
+            _ => None
+        },
+        (TYPE_FLOAT, Some(ty)) => match try!(ty.u16(0)) {
+            0 => Some(Numeric::Half),
+            1 => Some(Numeric::Float),
+            _ => Some(Numeric::Double)
+        },
+        (TYPE_BOOL, _) => Some(Numeric::Bool),
+        _ => None
+    };
+    let buffers = match type_type {
+        TYPE_NULL | TYPE_RUN_END_ENCODED => 0,
+        TYPE_STRUCT | TYPE_FIXED_SIZE_LIST => 1,
+        TYPE_BINARY | TYPE_UTF8 | TYPE_LARGE_BINARY | TYPE_LARGE_UTF8 => 3,
+        TYPE_UNION => return Err(format!("Column {} has an unsupported union type.", name)),
+        _ => 2
+    };
+
+    let i = fields.len();
+    fields.push(Field { name: name, numeric: numeric, buffers: buffers, children: 0 });
+    for c in try!(t.tables(5)) {
+        try!(parse_field(&c, fields));
+    }
+    fields[i].children = fields.len() - i - 1;
+    Ok(())
+}
+
+/// Converts the values of a column into `f64`.
+fn decode(ty: Numeric, n: usize, validity: &[u8], data: &[u8], out: &mut Vec<f64>) -> Result<(), String> {
+
+    let bit = |b: &[u8], i: usize| b[i / 8] >> (i % 8) & 1 == 1;
+    let size = match ty {
+        Numeric::Int(bits, _) => bits / 8,
+        Numeric::Half => 2,
+        Numeric::Float => 4,
+        Numeric::Double => 8,
+        Numeric::Bool => 0
+    };
+    if (size == 0 && data.len() * 8 < n) || data.len() < size * n ||
+        (!validity.is_empty() && validity.len() * 8 < n) {
+        return Err("Buffer is too small.".to_string());
+    }
+
+    for i in 0..n {
+        if !validity.is_empty() && !bit(validity, i) {
+            out.push(f64::NAN);
+            continue;
+        }
+        let b = &data[i * size..(i + 1) * size];
+        let mut v = [0u8; 8];
+        v[..size].copy_from_slice(b);
+        let bits = u64::from_le_bytes(v);
+        out.push(match ty {
+            Numeric::Bool => if bit(data, i) { 1.0 } else { 0.0 },
+            Numeric::Int(s, true) => {
+                let shift = 64 - s as u32;
+                ((bits << shift) as i64 >> shift) as f64
+            },
+            Numeric::Int(_, false) => bits as f64,
+            Numeric::Half => half_to_f64(bits as u16),
+            Numeric::Float => f32::from_bits(bits as u32) as f64,
+            Numeric::Double => f64::from_bits(bits)
+        });
+    }
+    Ok(())
+}
+
+fn half_to_f64(h: u16) -> f64 {
+
+    let sign = if h >> 15 == 1 { -1.0 } else { 1.0 };
+    let exp = (h >> 10 & 0x1F) as i32;
+    let frac = (h & 0x3FF) as f64;
+    sign * match exp {
+        0 => frac * 2f64.powi(-24),
+        31 => if frac == 0.0 { f64::INFINITY } else { f64::NAN },
+        _ => (1.0 + frac / 1024.0) * 2f64.powi(exp - 15)
+    }
+}
+
+/// Reads numeric columns from an Arrow IPC file or stream given as a
+/// reader.
+///
+/// If `columns` is empty all top-level columns of a numeric or boolean
+/// type are loaded. Otherwise the columns with the given names are loaded
+/// in the given order. Returns the names of the loaded columns and the
+/// matrix with one row per record.
+pub fn read_arrow<R: Read>(mut reader: R, columns: &[&str]) -> Result<(Vec<String>, Matrix<f64>), String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+
+    // the file format starts with a magic string followed by a stream
+    let mut pos = if buf.starts_with(b"ARROW1") { 8 } else { 0 };
+
+    let mut fields: Option<Vec<Field>> = None;
+    let mut selected: Vec<usize> = Vec::new();
+    let mut values: Vec<Vec<f64>> = Vec::new();
+
+    while pos + 4 <= buf.len() {
+        let mut len = try!(u32_at(&buf, pos));
+        pos += 4;
+        if len == CONTINUATION {
+            len = try!(u32_at(&buf, pos));
+            pos += 4;
+        }
+        // end of stream
+        if len == 0 {
+            break;
+        }
+        let len = len as usize;
+        let meta = match buf.get(pos..pos + len) {
+            Some(m) => m,
+            None => return Err("Truncated message.".to_string())
+        };
+        let msg = try!(Table::root(meta));
+        let body_len = try!(msg.u64(3)) as usize;
+        let body = match buf.get(pos + len..pos + len + body_len) {
+            Some(b) => b,
+            None => return Err("Truncated message body.".to_string())
+        };
+        pos += len + body_len;
+
+        let header = match try!(msg.table(2)) {
+            Some(h) => h,
+            None => continue
+        };
+        match try!(msg.u8(1)) {
+            HEADER_SCHEMA if fields.is_none() => {
+                let mut f = Vec::new();
+                for t in try!(header.tables(1)) {
+                    try!(parse_field(&t, &mut f));
+                }
+                selected = try!(select(&f, columns));
+                values = vec![Vec::new(); selected.len()];
+                fields = Some(f);
+            },
+            HEADER_RECORD_BATCH => {
+                let f = match fields {
+                    Some(ref f) => f,
+                    None => return Err("Record batch before schema.".to_string())
+                };
+                if try!(header.field(3)).is_some() {
+                    return Err("Compressed record batches are not supported.".to_string());
+                }
+                let nodes = try!(header.pairs(1));
+                let buffers = try!(header.pairs(2));
+                if nodes.len() != f.len() {
+                    return Err("Number of nodes does not match the schema.".to_string());
+                }
+
+                let mut b = 0;
+                for (i, field) in f.iter().enumerate() {
+                    if let Some(k) = selected.iter().position(|&s| s == i) {
+                        let slice = |j: usize| -> Result<&[u8], String> {
+                            let (o, l) = match buffers.get(b + j) {
+                                Some(&x) => x,
+                                None => return Err("Missing buffer.".to_string())
+                            };
+                            body.get(o as usize..(o + l) as usize).ok_or("Invalid buffer.".to_string())
+                        };
+                        let n = nodes[i].0 as usize;
+                        let validity = if nodes[i].1 == 0 { &[][..] } else { try!(slice(0)) };
+                        try!(decode(field.numeric.unwrap(), n, validity, try!(slice(1)), &mut values[k]));
+                    }
+                    b += field.buffers;
+                }
+            },
+            _ => ()
+        }
+    }
+
+    let f = match fields {
+        Some(f) => f,
+        None => return Err("No schema found.".to_string())
+    };
+    let rows = values.first().map(|v| v.len()).unwrap_or(0);
+    let mut m = Vec::with_capacity(rows * values.len());
+    for r in 0..rows {
+        m.extend(values.iter().map(|v| v[r]));
+    }
+    let names = selected.iter().map(|&i| f[i].name.clone()).collect();
+    Ok((names, Matrix::from_vec(m, rows, values.len())))
+}
+
+/// Returns the indexes of the selected top-level fields.
+fn select(fields: &[Field], columns: &[&str]) -> Result<Vec<usize>, String> {
+
+    let mut top = Vec::new();
+    let mut i = 0;
+    while i < fields.len() {
+        top.push(i);
+        i += fields[i].children + 1;
+    }
+
+    if columns.is_empty() {
+        return Ok(top.into_iter().filter(|&i| fields[i].numeric.is_some()).collect());
+    }
+    columns.iter().map(|c| {
+        match top.iter().find(|&&i| fields[i].name == *c) {
+            Some(&i) if fields[i].numeric.is_some() => Ok(i),
+            Some(_) => Err(format!("Column {} is not numeric.", c)),
+            None => Err(format!("Column {} not found.", c))
+        }
+    }).collect()
+}
+
+/// Loads numeric columns from an Arrow IPC file (see
+/// [`read_arrow`](fn.read_arrow.html)).
+pub fn load_arrow(fname: &str, columns: &[&str]) -> Result<(Vec<String>, Matrix<f64>), String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_arrow(f, columns)
+}
+
+/// Loads numeric columns from an Arrow IPC file into a sparse matrix (see
+/// [`read_arrow`](fn.read_arrow.html)). Null values are stored as `NaN`.
+pub fn load_arrow_sparse(fname: &str, columns: &[&str]) -> Result<(Vec<String>, SparseMatrix<f64>), String> {
+
+    let (names, m) = try!(load_arrow(fname, columns));
+    Ok((names, SparseMatrix::from_dense(&m)))
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    /// Minimal flatbuffers objects to build test messages.
+    enum Fb {
+        Table(Vec<(usize, Fv)>),
+        Str(&'static str),
+        Tables(Vec<Fb>),
+        Pairs(Vec<(u64, u64)>)
+    }
+
+    enum Fv {
+        Bytes(Vec<u8>),
+        Ref(Fb)
+    }
+
+    fn patch(buf: &mut Vec<u8>, at: usize, target: usize) {
+        let o = ((target - at) as u32).to_le_bytes();
+        buf[at..at + 4].copy_from_slice(&o);
+    }
+
+    fn emit(buf: &mut Vec<u8>, obj: &Fb) -> usize {
+        match *obj {
+            Fb::Table(ref fields) => {
+                let slots = fields.iter().map(|f| f.0 + 1).max().unwrap_or(0);
+                let mut offsets = vec![0u16; slots];
+                let mut o = 4;
+                for &(slot, ref v) in fields {
+                    offsets[slot] = o as u16;
+                    o += match *v { Fv::Bytes(ref b) => b.len(), Fv::Ref(_) => 4 };
+                }
+                let vt = buf.len();
+                buf.extend_from_slice(&((4 + 2 * slots) as u16).to_le_bytes());
+                buf.extend_from_slice(&(o as u16).to_le_bytes());
+                for x in offsets {
+                    buf.extend_from_slice(&x.to_le_bytes());
+                }
+                let pos = buf.len();
+                buf.extend_from_slice(&((pos - vt) as u32).to_le_bytes());
+                let mut refs = Vec::new();
+                for &(_, ref v) in fields {
+                    match *v {
+                        Fv::Bytes(ref b) => buf.extend_from_slice(b),
+                        Fv::Ref(ref c) => {
+                            refs.push((buf.len(), c));
+                            buf.extend_from_slice(&[0; 4]);
+                        }
+                    }
+                }
+                for (at, c) in refs {
+                    let p = emit(buf, c);
+                    patch(buf, at, p);
+                }
+                pos
+            },
+            Fb::Str(s) => {
+                let pos = buf.len();
+                buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                buf.extend_from_slice(s.as_bytes());
+                buf.push(0);
+                pos
+            },
+            Fb::Tables(ref v) => {
+                let pos = buf.len();
+                buf.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                let start = buf.len();
+                buf.extend(vec![0; 4 * v.len()]);
+                for (i, c) in v.iter().enumerate() {
+                    let p = emit(buf, c);
+                    patch(buf, start + 4 * i, p);
+                }
+                pos
+            },
+            Fb::Pairs(ref v) => {
+                let pos = buf.len();
+                buf.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                for &(a, b) in v {
+                    buf.extend_from_slice(&a.to_le_bytes());
+                    buf.extend_from_slice(&b.to_le_bytes());
+                }
+                pos
+            }
+        }
+    }
+
+    fn message(out: &mut Vec<u8>, header_type: u8, header: Fb, body: &[u8]) {
+        let msg = Fb::Table(vec![
+            (0, Fv::Bytes(vec![4, 0])),
+            (1, Fv::Bytes(vec![header_type])),
+            (2, Fv::Ref(header)),
+            (3, Fv::Bytes((body.len() as u64).to_le_bytes().to_vec()))
+        ]);
+        let mut meta = vec![0; 4];
+        let root = emit(&mut meta, &msg);
+        patch(&mut meta, 0, root);
+        while meta.len() % 8 != 0 {
+            meta.push(0);
+        }
+        out.extend_from_slice(&CONTINUATION.to_le_bytes());
+        out.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+        out.extend_from_slice(&meta);
+        out.extend_from_slice(body);
+    }
+
+    fn field(name: &'static str, type_type: u8, ty: Fb, children: Vec<Fb>) -> Fb {
+        Fb::Table(vec![
+            (0, Fv::Ref(Fb::Str(name))),
+            (1, Fv::Bytes(vec![1])),
+            (2, Fv::Bytes(vec![type_type])),
+            (3, Fv::Ref(ty)),
+            (5, Fv::Ref(Fb::Tables(children)))
+        ])
+    }
+
+    fn stream() -> Vec<u8> {
+
+        let int32 = || Fb::Table(vec![(0, Fv::Bytes(32u32.to_le_bytes().to_vec())), (1, Fv::Bytes(vec![1]))]);
+        let schema = Fb::Table(vec![(1, Fv::Ref(Fb::Tables(vec![
+            field("a", TYPE_INT, int32(), vec![]),
+            field("s", TYPE_UTF8, Fb::Table(vec![]), vec![]),
+            field("p", TYPE_STRUCT, Fb::Table(vec![]), vec![
+                field("q", TYPE_INT, int32(), vec![])
+            ]),
+            field("b", TYPE_FLOAT, Fb::Table(vec![(0, Fv::Bytes(vec![2, 0]))]), vec![]),
+            field("c", TYPE_BOOL, Fb::Table(vec![]), vec![])
+        ])))]);
+
+        let mut out = Vec::new();
+        message(&mut out, HEADER_SCHEMA, schema, &[]);
+
+        // three rows: a = [1, -2, null], s = ["x", "", ""], p.q = [0, 0, 0],
+        // b = [0.5, 1.5, -3], c = [true, false, true]
+        let mut body = Vec::new();
+        let mut buffers = Vec::new();
+        let mut add = |b: &[u8]| {
+            buffers.push((body.len() as u64, b.len() as u64));
+            body.extend_from_slice(b);
+            while body.len() % 8 != 0 {
+                body.push(0);
+            }
+        };
+        let ints = |v: &[i32]| v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect::<Vec<u8>>();
+        add(&[0b011]);
+        add(&ints(&[1, -2, 0]));
+        add(&[]);
+        add(&ints(&[0, 1, 1, 1]));
+        add(b"x");
+        add(&[]);
+        add(&[]);
+        add(&ints(&[0, 0, 0]));
+        add(&[]);
+        add(&[0.5f64, 1.5, -3.0].iter().flat_map(|x| x.to_bits().to_le_bytes().to_vec()).collect::<Vec<u8>>());
+        add(&[]);
+        add(&[0b101]);
+
+        let batch = Fb::Table(vec![
+            (0, Fv::Bytes(3u64.to_le_bytes().to_vec())),
+            (1, Fv::Ref(Fb::Pairs(vec![(3, 1), (3, 0), (3, 0), (3, 0), (3, 0), (3, 0)]))),
+            (2, Fv::Ref(Fb::Pairs(buffers)))
+        ]);
+        message(&mut out, HEADER_RECORD_BATCH, batch, &body);
+        out.extend_from_slice(&CONTINUATION.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out
+    }
+
+    #[test]
+    fn test_read_arrow_stream() {
+
+        let s = stream();
+        assert_eq!(u32_at(&s, 0).unwrap(), CONTINUATION);
+
+        let (names, m) = read_arrow(&s[..], &[]).unwrap();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert_eq!(m.row(0).unwrap(), &[1.0, 0.5, 1.0]);
+        assert_eq!(m.row(1).unwrap(), &[-2.0, 1.5, 0.0]);
+        assert!(m.get(2, 0).unwrap().is_nan());
+        assert_eq!(m.get(2, 1), Some(&-3.0));
+
+        let (names, m) = read_arrow(&s[..], &["c", "a"]).unwrap();
+        assert_eq!(names, vec!["c", "a"]);
+        assert_eq!(m.col(1).unwrap()[..2], [1.0, -2.0]);
+
+        assert!(read_arrow(&s[..], &["s"]).is_err());
+        assert!(read_arrow(&s[..], &["q"]).is_err());
+    }
+
+    #[test]
+    fn test_read_arrow_file() {
+
+        // file format: magic, stream, footer (ignored), footer size, magic
+        let mut f = b"ARROW1\0\0".to_vec();
+        f.extend(stream());
+        f.extend_from_slice(&[0; 4]);
+        f.extend_from_slice(b"ARROW1");
+
+        let (_, m) = read_arrow(&f[..], &["b"]).unwrap();
+        assert_eq!(m, mat![0.5; 1.5; -3.0]);
+
+        assert!(read_arrow(&b"ARROW1\0\0"[..], &[]).is_err());
+        assert!(load_arrow("datasets/testing/nonexisting.arrow", &[]).is_err());
+    }
+
+    #[test]
+    fn test_half_to_f64() {
+        assert_eq!(half_to_f64(0x3C00), 1.0);
+        assert_eq!(half_to_f64(0xC000), -2.0);
+        assert_eq!(half_to_f64(0x0001), 2f64.powi(-24));
+        assert!(half_to_f64(0x7E00).is_nan());
+    }
+}
@@ -0,0 +1,621 @@
+//! Loader for CSV files of real datasets.
+//!
+//! In contrast to the [`CsvReader`](../struct.CsvReader.html), which splits
+//! plain lines of numbers, [`CsvLoader`](struct.CsvLoader.html) handles the
+//! features of CSV files found in the wild:
+//!
+//! * an optional header with the names of the columns
+//! * quoted fields which may contain the delimiter, line breaks and escaped
+//! quotes (`""`)
+//! * missing values (e.g. empty fields or `NA`), which are converted to `NaN`
+//! * selection of a subset of the columns by name or by index
+//! * an arbitrary delimiter, e.g. a tab for TSV files
+//! * optional comments
+//!
+//! The type of each column is inferred from its values (see
+//! [`ColumnType`](enum.ColumnType.html)), so that columns with booleans or
+//! categories can also be loaded into a matrix of `f64`.
+//!
+//! Matrices can be written to CSV files via the trait
+//! [`WriteCsv`](trait.WriteCsv.html) and predictions together with the IDs
+//! of the examples via [`write_predictions`](fn.write_predictions.html),
+//! e.g. for submissions to competitions.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::io::csv::*;
+//!
+//! # fn main() {
+//! let s = "name,age,smoker,city\n\
+//!          \"Doe, John\",42,true,Berlin\n\
+//!          Jane,NA,false,Paris\n\
+//!          Max,17,,Berlin\n";
+//!
+//! let t = CsvLoader::new().parse(s).unwrap();
+//! assert_eq!(t.names(), &["name", "age", "smoker", "city"]);
+//! assert_eq!(t.types()[1], ColumnType::Numeric);
+//! assert_eq!(t.types()[2], ColumnType::Boolean);
+//! assert_eq!(t.types()[3], ColumnType::Categorical(vec!["Berlin".to_string(), "Paris".to_string()]));
+//!
+//! let t = CsvLoader::new().columns(&["age", "city"]).parse(s).unwrap();
+//! let m = t.matrix();
+//! assert_eq!(m.row(0).unwrap(), &[42.0, 0.0]);
+//! assert!(m.get(1, 0).unwrap().is_nan());
+//! assert_eq!(m.row(2).unwrap(), &[17.0, 0.0]);
+//! # }
+//! ```
+use std::collections::BTreeSet;
+use std::f64;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{Read, Write, BufWriter};
+
+use matrix::Matrix;
+
+/// The type of a column which is inferred from its (non-missing) values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnType {
+    /// All values are numbers. This is also the type of columns whose values
+    /// are all missing.
+    Numeric,
+    /// All values are `true` or `false` (case insensitive). The values are
+    /// converted to 1 and 0.
+    Boolean,
+    /// Any other values. Each value is converted to the index of the value
+    /// in the contained sorted list of distinct values (categories).
+    Categorical(Vec<String>)
+}
+
+/// A table loaded from a CSV file.
+#[derive(Clone, Debug)]
+pub struct CsvTable {
+    names: Vec<String>,
+    types: Vec<ColumnType>,
+    data: Matrix<f64>
+}
+
+impl CsvTable {
+
+    /// Returns the names of the columns.
+    ///
+    /// If the file has no header the names are the (zero-based) indexes of
+    /// the columns in the file.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the inferred types of the columns.
+    pub fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+
+    /// Returns the values. Missing values are `NaN`.
+    pub fn matrix(&self) -> &Matrix<f64> {
+        &self.data
+    }
+
+    /// Returns the values and the names of the columns.
+    pub fn into_parts(self) -> (Matrix<f64>, Vec<String>) {
+        (self.data, self.names)
+    }
+
+    /// Returns the index of the column with the given name.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Columns {
+    All,
+    Names(Vec<String>),
+    Indexes(Vec<usize>)
+}
+
+/// Configurable loader for CSV files.
+///
+/// By default the delimiter is `,`, the quote character is `"`, the first
+/// line is a header and empty fields as well as the values `NA`, `N/A`,
+/// `NaN`, `null` and `?` are missing values. Comments are disabled.
+#[derive(Clone, Debug)]
+pub struct CsvLoader {
+    delim: char,
+    quote: char,
+    comment: Option<char>,
+    header: bool,
+    missing: Vec<String>,
+    columns: Columns
+}
+
+impl CsvLoader {
+
+    /// Creates a loader with the default configuration.
+    pub fn new() -> CsvLoader {
+        CsvLoader {
+            delim: ',',
+            quote: '"',
+            comment: None,
+            header: true,
+            missing: vec!["", "NA", "N/A", "NaN", "null", "?"].iter().map(|s| s.to_string()).collect(),
+            columns: Columns::All
+        }
+    }
+
+    /// Sets the delimiter, e.g. `'\t'` for TSV files.
+    pub fn delimiter(&self, delim: char) -> CsvLoader {
+        CsvLoader { delim: delim, .. self.clone() }
+    }
+
+    /// Sets the character which is used to quote fields.
+    pub fn quote(&self, quote: char) -> CsvLoader {
+        CsvLoader { quote: quote, .. self.clone() }
+    }
+
+    /// Sets the character which starts a comment. The comment character
+    /// and all following characters up to the end of the line are ignored
+    /// unless the comment character is part of a quoted field.
+    pub fn comment(&self, c: char) -> CsvLoader {
+        CsvLoader { comment: Some(c), .. self.clone() }
+    }
+
+    /// Sets whether the first line contains the names of the columns.
+    pub fn header(&self, header: bool) -> CsvLoader {
+        CsvLoader { header: header, .. self.clone() }
+    }
+
+    /// Sets the (unquoted) values which are interpreted as missing values.
+    /// Empty fields are always missing values.
+    pub fn missing_values(&self, values: &[&str]) -> CsvLoader {
+        CsvLoader { missing: values.iter().map(|s| s.to_string()).collect(), .. self.clone() }
+    }
+
+    /// Loads only the columns with the given names in the given order. This
+    /// requires a header.
+    pub fn columns(&self, names: &[&str]) -> CsvLoader {
+        CsvLoader { columns: Columns::Names(names.iter().map(|s| s.to_string()).collect()), .. self.clone() }
+    }
+
+    /// Loads only the columns with the given (zero-based) indexes in the
+    /// given order.
+    pub fn column_indexes(&self, idx: &[usize]) -> CsvLoader {
+        CsvLoader { columns: Columns::Indexes(idx.to_vec()), .. self.clone() }
+    }
+
+    /// Loads a table from a file.
+    pub fn from_file(&self, fname: &str) -> Result<CsvTable, String> {
+
+        let mut s = String::new();
+        try!(
+            try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)))
+            .read_to_string(&mut s)
+            .map_err(|e| format!("Could not read file {}: {}", fname, e))
+        );
+        self.parse(&s)
+    }
+
+    /// Loads a table from a reader.
+    pub fn read<R: Read>(&self, mut reader: R) -> Result<CsvTable, String> {
+
+        let mut s = String::new();
+        try!(reader.read_to_string(&mut s).map_err(|e| format!("Could not read data: {}", e)));
+        self.parse(&s)
+    }
+
+    /// Loads a table from a string.
+    ///
+    /// Returns an error if a quoted field is not terminated, if the rows
+    /// have a different number of fields or if a selected column does not
+    /// exist.
+    pub fn parse(&self, s: &str) -> Result<CsvTable, String> {
+
+        let mut records = try!(parse_records(s, self.delim, self.quote, self.comment));
+        let header = if self.header && !records.is_empty() {
+            Some(records.remove(0))
+        } else {
+            None
+        };
+
+        let n = header.as_ref().or(records.first()).map(|&(_, ref r)| r.len()).unwrap_or(0);
+        for &(line, ref r) in &records {
+            if r.len() != n {
+                return Err(format!("Line {}: expected {} fields but found {}.", line, n, r.len()));
+            }
+        }
+
+        let names: Vec<String> = match header {
+            Some((_, h)) => h.into_iter().map(|(f, _)| f).collect(),
+            None => (0..n).map(|i| i.to_string()).collect()
+        };
+
+        let idx: Vec<usize> = match self.columns {
+            Columns::All => (0..n).collect(),
+            Columns::Indexes(ref v) => {
+                if let Some(i) = v.iter().find(|&&i| i >= n) {
+                    return Err(format!("Column {} does not exist.", i));
+                }
+                v.clone()
+            },
+            Columns::Names(ref v) => {
+                if !self.header {
+                    return Err("Columns can only be selected by name if the file has a header.".to_string());
+                }
+                let mut idx = Vec::with_capacity(v.len());
+                for name in v {
+                    match names.iter().position(|x| x == name) {
+                        Some(i) => idx.push(i),
+                        None => return Err(format!("Column {} does not exist.", name))
+                    }
+                }
+                idx
+            }
+        };
+
+        let rows = records.len();
+        let mut types = Vec::with_capacity(idx.len());
+        let mut cols = Vec::with_capacity(idx.len());
+        for &c in &idx {
+            let values: Vec<Option<&str>> = records.iter()
+                .map(|&(_, ref r)| {
+                    let (ref f, quoted) = r[c];
+                    if !quoted && (f.is_empty() || self.missing.contains(f)) { None } else { Some(&f[..]) }
+                })
+                .collect();
+            let (t, v) = convert(&values);
+            types.push(t);
+            cols.push(v);
+        }
+
+        let mut data = Vec::with_capacity(rows * idx.len());
+        for r in 0..rows {
+            data.extend(cols.iter().map(|c| c[r]));
+        }
+
+        Ok(CsvTable {
+            names: idx.iter().map(|&i| names[i].clone()).collect(),
+            types: types,
+            data: Matrix::from_vec(data, rows, idx.len())
+        })
+    }
+}
+
+/// Quotes a field if it contains the delimiter, a quote or a line break.
+fn escape(field: &str, delim: char) -> String {
+
+    if field.contains(delim) || field.contains('"') || field.contains('\n') || field.contains('\r') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_record<W: Write, I, D>(w: &mut W, fields: I, delim: char) -> Result<(), String>
+    where I: Iterator<Item = D>, D: Display {
+
+    let line = fields
+        .map(|f| escape(&format!("{}", f), delim))
+        .collect::<Vec<String>>()
+        .join(&delim.to_string());
+    w.write_all(line.as_bytes())
+        .and_then(|_| w.write_all(b"\n"))
+        .map_err(|e| format!("Could not write data: {}", e))
+}
+
+fn create(fname: &str) -> Result<BufWriter<File>, String> {
+    File::create(fname)
+        .map(BufWriter::new)
+        .map_err(|e| format!("Could not create file {}: {}", fname, e))
+}
+
+/// Trait to write a matrix as delimiter separated values.
+///
+/// Fields which contain the delimiter, a quote or a line break are quoted,
+/// so that the output can be read with [`CsvLoader`](struct.CsvLoader.html).
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::io::csv::WriteCsv;
+///
+/// # fn main() {
+/// let mut v = Vec::new();
+/// mat![1.0, 2.5; 3.0, 4.0].write_delimited(&mut v, &["a", "b"], '\t').unwrap();
+/// assert_eq!(String::from_utf8(v).unwrap(), "a\tb\n1\t2.5\n3\t4\n");
+/// # }
+/// ```
+pub trait WriteCsv {
+
+    /// Writes the rows to `writer` using the delimiter `delim`. If `headers`
+    /// is not empty it is written as the first line.
+    ///
+    /// Returns an error if the number of headers does not match the number
+    /// of columns or if the data could not be written.
+    fn write_delimited<W: Write>(&self, writer: W, headers: &[&str], delim: char) -> Result<(), String>;
+
+    /// Writes the rows as comma separated values into the file `fname`. If
+    /// `headers` is not empty it is written as the first line.
+    fn write_csv(&self, fname: &str, headers: &[&str]) -> Result<(), String> {
+        self.write_delimited(try!(create(fname)), headers, ',')
+    }
+
+    /// Writes the rows as tab separated values into the file `fname`. If
+    /// `headers` is not empty it is written as the first line.
+    fn write_tsv(&self, fname: &str, headers: &[&str]) -> Result<(), String> {
+        self.write_delimited(try!(create(fname)), headers, '\t')
+    }
+}
+
+impl <T: Display + Clone> WriteCsv for Matrix<T> {
+
+    fn write_delimited<W: Write>(&self, mut writer: W, headers: &[&str], delim: char) -> Result<(), String> {
+
+        if !headers.is_empty() {
+            if headers.len() != self.cols() {
+                return Err("Number of headers does not match the number of columns.".to_string());
+            }
+            try!(write_record(&mut writer, headers.iter(), delim));
+        }
+        for row in self.row_iter() {
+            try!(write_record(&mut writer, row.iter(), delim));
+        }
+        writer.flush().map_err(|e| format!("Could not write data: {}", e))
+    }
+}
+
+/// Writes the IDs of examples and their predictions as comma separated
+/// values to `writer`. The first line contains the given headers, e.g.
+/// `("id", "label")`.
+///
+/// Returns an error if the number of IDs does not match the number of
+/// predictions or if the data could not be written.
+///
+/// # Example
+///
+/// ```
+/// use rustml::io::csv::write_predictions_to;
+///
+/// let mut v = Vec::new();
+/// write_predictions_to(&mut v, &[17, 42], &["cat", "dog"], ("id", "label")).unwrap();
+/// assert_eq!(String::from_utf8(v).unwrap(), "id,label\n17,cat\n42,dog\n");
+/// ```
+pub fn write_predictions_to<W, I, P>(mut writer: W, ids: &[I], predictions: &[P], headers: (&str, &str)) -> Result<(), String>
+    where W: Write, I: Display, P: Display {
+
+    if ids.len() != predictions.len() {
+        return Err("Number of IDs does not match the number of predictions.".to_string());
+    }
+    try!(write_record(&mut writer, [headers.0, headers.1].iter(), ','));
+    for (i, p) in ids.iter().zip(predictions.iter()) {
+        try!(write_record(&mut writer, [format!("{}", i), format!("{}", p)].iter(), ','));
+    }
+    writer.flush().map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Writes the IDs of examples and their predictions into the CSV file
+/// `fname` (see [`write_predictions_to`](fn.write_predictions_to.html)).
+pub fn write_predictions<I, P>(fname: &str, ids: &[I], predictions: &[P], headers: (&str, &str)) -> Result<(), String>
+    where I: Display, P: Display {
+
+    write_predictions_to(try!(create(fname)), ids, predictions, headers)
+}
+
+/// Infers the type of a column and converts its values.
+fn convert(values: &[Option<&str>]) -> (ColumnType, Vec<f64>) {
+
+    let present = || values.iter().filter_map(|v| *v);
+
+    if present().all(|v| v.parse::<f64>().is_ok()) {
+        let v = values.iter().map(|v| v.map(|x| x.parse::<f64>().unwrap()).unwrap_or(f64::NAN)).collect();
+        return (ColumnType::Numeric, v);
+    }
+
+    let boolean = |v: &str| match &v.to_lowercase()[..] {
+        "true" => Some(1.0),
+        "false" => Some(0.0),
+        _ => None
+    };
+    if present().all(|v| boolean(v).is_some()) {
+        let v = values.iter().map(|v| v.and_then(|x| boolean(x)).unwrap_or(f64::NAN)).collect();
+        return (ColumnType::Boolean, v);
+    }
+
+    let levels: Vec<String> = present().collect::<BTreeSet<&str>>().into_iter().map(|s| s.to_string()).collect();
+    let v = values.iter()
+        .map(|v| v.map(|x| levels.iter().position(|l| l == x).unwrap() as f64).unwrap_or(f64::NAN))
+        .collect();
+    (ColumnType::Categorical(levels), v)
+}
+
+/// A field and whether it was quoted.
+type Field = (String, bool);
+
+/// Splits the content of a CSV file into records. Each record is returned
+/// with the line number at which it starts. Empty lines are skipped.
+/// Unquoted fields are trimmed.
+fn parse_records(s: &str, delim: char, quote: char, comment: Option<char>) -> Result<Vec<(usize, Vec<Field>)>, String> {
+
+    let mut records = Vec::new();
+    let mut record: Vec<Field> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == quote {
+                if chars.peek() == Some(&quote) {
+                    field.push(quote);
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        } else if Some(c) == comment {
+            while chars.peek().map(|&x| x != '\n' && x != '\r').unwrap_or(false) {
+                chars.next();
+            }
+        } else if c == quote && field.trim().is_empty() && !quoted {
+            field.clear();
+            quoted = true;
+            in_quotes = true;
+        } else if c == delim {
+            record.push(finish(&mut field, &mut quoted));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            if !record.is_empty() || quoted || !field.trim().is_empty() {
+                record.push(finish(&mut field, &mut quoted));
+                records.push((start, record));
+                record = Vec::new();
+            }
+            field.clear();
+            line += 1;
+            start = line;
+        } else {
+            field.push(c);
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Line {}: quoted field is not terminated.", start));
+    }
+    if !record.is_empty() || quoted || !field.trim().is_empty() {
+        record.push(finish(&mut field, &mut quoted));
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+fn finish(field: &mut String, quoted: &mut bool) -> Field {
+
+    let f = if *quoted { field.clone() } else { field.trim().to_string() };
+    let r = (f, *quoted);
+    field.clear();
+    *quoted = false;
+    r
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_parse_records() {
+
+        let r = parse_records("a, b ,\"c,d\"\r\n\n\"x\"\"y\",\"line\nbreak\",\n", ',', '"', None).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].0, 1);
+        assert_eq!(r[0].1, vec![
+            ("a".to_string(), false), ("b".to_string(), false), ("c,d".to_string(), true)
+        ]);
+        assert_eq!(r[1].0, 3);
+        assert_eq!(r[1].1, vec![
+            ("x\"y".to_string(), true), ("line\nbreak".to_string(), true), ("".to_string(), false)
+        ]);
+
+        assert!(parse_records("a,\"b\n", ',', '"', None).is_err());
+        assert_eq!(parse_records("", ',', '"', None).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_load() {
+
+        let s = "1\t2.5\t\n3\tNA\tfoo\n";
+        let t = CsvLoader::new().delimiter('\t').header(false).parse(s).unwrap();
+        assert_eq!(t.names(), &["0", "1", "2"]);
+        assert_eq!(t.types()[0], ColumnType::Numeric);
+        assert_eq!(t.types()[2], ColumnType::Categorical(vec!["foo".to_string()]));
+
+        let m = t.matrix();
+        assert_eq!(m.rows(), 2);
+        assert_eq!(m.get(0, 1), Some(&2.5));
+        assert!(m.get(1, 1).unwrap().is_nan());
+        assert!(m.get(0, 2).unwrap().is_nan());
+        assert_eq!(m.get(1, 2), Some(&0.0));
+    }
+
+    #[test]
+    fn test_load_columns() {
+
+        let s = "a,b,c\n1,x,TRUE\n2,y,false\n3,x,?\n";
+
+        let t = CsvLoader::new().column_indexes(&[2, 0]).parse(s).unwrap();
+        assert_eq!(t.names(), &["c", "a"]);
+        assert_eq!(t.index("a"), Some(1));
+        assert_eq!(t.types()[0], ColumnType::Boolean);
+
+        let (m, _) = t.into_parts();
+        assert_eq!(m.col(1).unwrap(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(m.get(0, 0), Some(&1.0));
+        assert_eq!(m.get(1, 0), Some(&0.0));
+        assert!(m.get(2, 0).unwrap().is_nan());
+
+        let t = CsvLoader::new().columns(&["b"]).missing_values(&["x"]).parse(s).unwrap();
+        assert_eq!(t.types()[0], ColumnType::Categorical(vec!["y".to_string()]));
+
+        assert!(CsvLoader::new().columns(&["d"]).parse(s).is_err());
+        assert!(CsvLoader::new().column_indexes(&[3]).parse(s).is_err());
+        assert!(CsvLoader::new().header(false).columns(&["a"]).parse(s).is_err());
+    }
+
+    #[test]
+    fn test_write_delimited() {
+
+        let m = mat!["a b", "c,d"; "e\"f", "g"];
+        let mut v = Vec::new();
+        m.write_delimited(&mut v, &[], ',').unwrap();
+        assert_eq!(String::from_utf8(v).unwrap(), "a b,\"c,d\"\n\"e\"\"f\",g\n");
+
+        assert!(m.write_delimited(Vec::new(), &["x"], ',').is_err());
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() {
+
+        let path = ::std::env::temp_dir().join("rustml_test_write_csv.csv");
+        let fname = path.to_str().unwrap();
+        let m = mat![1.5, -2.0; 1e-10, 3.0];
+        m.write_csv(fname, &["x, y", "z"]).unwrap();
+
+        let t = CsvLoader::new().from_file(fname).unwrap();
+        assert_eq!(t.names(), &["x, y", "z"]);
+        assert_eq!(*t.matrix(), m);
+    }
+
+    #[test]
+    fn test_write_predictions() {
+
+        let mut v = Vec::new();
+        write_predictions_to(&mut v, &["a", "b"], &[0.5, 1.0], ("id", "p")).unwrap();
+        assert_eq!(String::from_utf8(v).unwrap(), "id,p\na,0.5\nb,1\n");
+
+        assert!(write_predictions_to(Vec::new(), &[1], &[1, 2], ("id", "p")).is_err());
+    }
+
+    #[test]
+    fn test_load_errors() {
+
+        let e = CsvLoader::new().parse("a,b\n1,2\n3\n").unwrap_err();
+        assert!(e.starts_with("Line 3"));
+        assert!(CsvLoader::new().from_file("datasets/testing/nonexisting.csv").is_err());
+
+        let t = CsvLoader::new().header(false).comment('#').from_file("datasets/testing/csv.txt").unwrap();
+        assert_eq!(*t.matrix(), mat![1.0, 2.0, 3.0, 4.0; 5.0, 6.0, 7.0, 8.0; 9.0, 10.0, 11.0, 12.0]);
+    }
+}
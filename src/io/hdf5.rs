@@ -0,0 +1,403 @@
+//! Reading and writing of matrices in HDF5 files.
+//!
+//! This module is only available if rustml is compiled with the feature
+//! `hdf5`. It requires the HDF5 C library (version 1.10 or newer). On Ubuntu
+//! it can be installed with `sudo apt-get install libhdf5-dev`.
+//!
+//! Matrices are stored as two dimensional datasets of 64-bit floats in
+//! row-major order, i.e. they can be read with other HDF5 tools like `h5py`.
+//! When reading, one dimensional datasets are returned as matrices with a
+//! single column. Datasets are written in chunks of rows so that a dataset
+//! which does not fit into memory can be read efficiently row by row (e.g.
+//! for out-of-core training) with
+//! [`row_chunks`](struct.Hdf5File.html#method.row_chunks).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::io::hdf5::*;
+//!
+//! # fn main() {
+//! let f = Hdf5File::create("/tmp/data.h5").unwrap();
+//! f.write("x", &mat![1.0, 2.0; 3.0, 4.0; 5.0, 6.0]).unwrap();
+//!
+//! // read two rows at once
+//! for chunk in f.row_chunks("x", 2).unwrap() {
+//!     let m = chunk.unwrap();
+//!     println!("{} rows", m.rows());
+//! }
+//! # }
+//! ```
+extern crate libc;
+
+use std::ffi::CString;
+use std::ptr;
+use self::libc::{c_char, c_int, c_uint, c_void};
+
+use matrix::Matrix;
+
+#[allow(non_camel_case_types)]
+type hid_t = i64;
+#[allow(non_camel_case_types)]
+type herr_t = c_int;
+#[allow(non_camel_case_types)]
+type hsize_t = u64;
+
+const H5F_ACC_RDONLY: c_uint = 0;
+const H5F_ACC_RDWR: c_uint = 1;
+const H5F_ACC_TRUNC: c_uint = 2;
+const H5P_DEFAULT: hid_t = 0;
+const H5S_ALL: hid_t = 0;
+const H5E_DEFAULT: hid_t = 0;
+const H5S_SELECT_SET: c_int = 0;
+
+/// Number of values per chunk of a dataset.
+const CHUNK_SIZE: usize = 8192;
+
+#[link(name = "hdf5")]
+extern "C" {
+    static H5T_NATIVE_DOUBLE_g: hid_t;
+    static H5T_IEEE_F64LE_g: hid_t;
+    static H5P_CLS_DATASET_CREATE_ID_g: hid_t;
+
+    fn H5open() -> herr_t;
+    fn H5Eset_auto2(estack: hid_t, func: *const c_void, data: *const c_void) -> herr_t;
+
+    fn H5Fcreate(name: *const c_char, flags: c_uint, fcpl: hid_t, fapl: hid_t) -> hid_t;
+    fn H5Fopen(name: *const c_char, flags: c_uint, fapl: hid_t) -> hid_t;
+    fn H5Fclose(id: hid_t) -> herr_t;
+
+    fn H5Lexists(loc: hid_t, name: *const c_char, lapl: hid_t) -> c_int;
+    fn H5Ldelete(loc: hid_t, name: *const c_char, lapl: hid_t) -> herr_t;
+
+    fn H5Dcreate2(loc: hid_t, name: *const c_char, dtype: hid_t, space: hid_t,
+                  lcpl: hid_t, dcpl: hid_t, dapl: hid_t) -> hid_t;
+    fn H5Dopen2(loc: hid_t, name: *const c_char, dapl: hid_t) -> hid_t;
+    fn H5Dget_space(id: hid_t) -> hid_t;
+    fn H5Dread(id: hid_t, mem_type: hid_t, mem_space: hid_t, file_space: hid_t,
+               xfer: hid_t, buf: *mut c_void) -> herr_t;
+    fn H5Dwrite(id: hid_t, mem_type: hid_t, mem_space: hid_t, file_space: hid_t,
+                xfer: hid_t, buf: *const c_void) -> herr_t;
+    fn H5Dclose(id: hid_t) -> herr_t;
+
+    fn H5Screate_simple(rank: c_int, dims: *const hsize_t, maxdims: *const hsize_t) -> hid_t;
+    fn H5Sget_simple_extent_ndims(id: hid_t) -> c_int;
+    fn H5Sget_simple_extent_dims(id: hid_t, dims: *mut hsize_t, maxdims: *mut hsize_t) -> c_int;
+    fn H5Sselect_hyperslab(id: hid_t, op: c_int, start: *const hsize_t, stride: *const hsize_t,
+                           count: *const hsize_t, block: *const hsize_t) -> herr_t;
+    fn H5Sclose(id: hid_t) -> herr_t;
+
+    fn H5Pcreate(cls: hid_t) -> hid_t;
+    fn H5Pset_chunk(id: hid_t, ndims: c_int, dims: *const hsize_t) -> herr_t;
+    fn H5Pclose(id: hid_t) -> herr_t;
+}
+
+/// Identifier of an HDF5 object which is closed when it goes out of scope.
+struct Handle {
+    id: hid_t,
+    close: unsafe extern "C" fn(hid_t) -> herr_t
+}
+
+impl Handle {
+    fn new(id: hid_t, close: unsafe extern "C" fn(hid_t) -> herr_t, msg: &str) -> Result<Handle, String> {
+        if id < 0 {
+            Err(msg.to_string())
+        } else {
+            Ok(Handle { id: id, close: close })
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { (self.close)(self.id); }
+    }
+}
+
+fn check(e: herr_t, msg: &str) -> Result<(), String> {
+    if e < 0 { Err(msg.to_string()) } else { Ok(()) }
+}
+
+fn c_string(s: &str) -> Result<CString, String> {
+    CString::new(s).map_err(|_| format!("Invalid name {}.", s))
+}
+
+fn init() -> Result<(), String> {
+    unsafe {
+        try!(check(H5open(), "Could not initialize the HDF5 library."));
+        // errors are returned to the caller instead of being printed
+        H5Eset_auto2(H5E_DEFAULT, ptr::null(), ptr::null());
+    }
+    Ok(())
+}
+
+/// An HDF5 file which is closed when the value is dropped.
+pub struct Hdf5File {
+    file: Handle
+}
+
+impl Hdf5File {
+
+    /// Opens an existing HDF5 file for reading and writing.
+    pub fn open(fname: &str) -> Result<Hdf5File, String> {
+
+        try!(init());
+        let name = try!(c_string(fname));
+        let id = unsafe { H5Fopen(name.as_ptr(), H5F_ACC_RDWR, H5P_DEFAULT) };
+        let file = try!(Handle::new(id, H5Fclose, &format!("Could not open file {}.", fname)));
+        Ok(Hdf5File { file: file })
+    }
+
+    /// Opens an existing HDF5 file for reading only.
+    pub fn open_read_only(fname: &str) -> Result<Hdf5File, String> {
+
+        try!(init());
+        let name = try!(c_string(fname));
+        let id = unsafe { H5Fopen(name.as_ptr(), H5F_ACC_RDONLY, H5P_DEFAULT) };
+        let file = try!(Handle::new(id, H5Fclose, &format!("Could not open file {}.", fname)));
+        Ok(Hdf5File { file: file })
+    }
+
+    /// Creates a new HDF5 file. An existing file is truncated.
+    pub fn create(fname: &str) -> Result<Hdf5File, String> {
+
+        try!(init());
+        let name = try!(c_string(fname));
+        let id = unsafe { H5Fcreate(name.as_ptr(), H5F_ACC_TRUNC, H5P_DEFAULT, H5P_DEFAULT) };
+        let file = try!(Handle::new(id, H5Fclose, &format!("Could not create file {}.", fname)));
+        Ok(Hdf5File { file: file })
+    }
+
+    /// Returns `true` if the file contains a dataset with the given name.
+    pub fn contains(&self, name: &str) -> bool {
+
+        match c_string(name) {
+            Ok(n) => unsafe { H5Lexists(self.file.id, n.as_ptr(), H5P_DEFAULT) > 0 },
+            Err(_) => false
+        }
+    }
+
+    /// Opens a dataset and returns it together with its dataspace and its
+    /// number of rows and columns.
+    fn dataset(&self, name: &str) -> Result<(Handle, Handle, usize, usize), String> {
+
+        let n = try!(c_string(name));
+        let dset = try!(Handle::new(
+            unsafe { H5Dopen2(self.file.id, n.as_ptr(), H5P_DEFAULT) },
+            H5Dclose, &format!("Could not open dataset {}.", name)
+        ));
+        let space = try!(Handle::new(
+            unsafe { H5Dget_space(dset.id) }, H5Sclose, "Could not get dataspace."
+        ));
+
+        let rank = unsafe { H5Sget_simple_extent_ndims(space.id) };
+        if rank != 1 && rank != 2 {
+            return Err(format!("Dataset {} has {} dimensions.", name, rank));
+        }
+        let mut dims = [0 as hsize_t, 1];
+        try!(check(unsafe { H5Sget_simple_extent_dims(space.id, dims.as_mut_ptr(), ptr::null_mut()) },
+            "Could not get the dimensions of the dataset."));
+        Ok((dset, space, dims[0] as usize, dims[1] as usize))
+    }
+
+    /// Returns the number of rows and columns of a dataset.
+    pub fn shape(&self, name: &str) -> Result<(usize, usize), String> {
+
+        let (_, _, rows, cols) = try!(self.dataset(name));
+        Ok((rows, cols))
+    }
+
+    /// Reads a complete dataset into a matrix.
+    pub fn read(&self, name: &str) -> Result<Matrix<f64>, String> {
+
+        let (dset, _, rows, cols) = try!(self.dataset(name));
+        let mut buf = vec![0.0; rows * cols];
+        if !buf.is_empty() {
+            try!(check(unsafe {
+                H5Dread(dset.id, H5T_NATIVE_DOUBLE_g, H5S_ALL, H5S_ALL, H5P_DEFAULT,
+                        buf.as_mut_ptr() as *mut c_void)
+            }, &format!("Could not read dataset {}.", name)));
+        }
+        Ok(Matrix::from_vec(buf, rows, cols))
+    }
+
+    /// Reads `n` rows of a dataset starting at row `start`.
+    ///
+    /// Returns an error if the dataset has less than `start + n` rows.
+    pub fn read_rows(&self, name: &str, start: usize, n: usize) -> Result<Matrix<f64>, String> {
+
+        let (dset, space, rows, cols) = try!(self.dataset(name));
+        if start + n > rows {
+            return Err(format!("Rows {}..{} out of range for dataset {} with {} rows.",
+                start, start + n, name, rows));
+        }
+        let mut buf = vec![0.0; n * cols];
+        if buf.is_empty() {
+            return Ok(Matrix::from_vec(buf, n, cols));
+        }
+
+        let rank = unsafe { H5Sget_simple_extent_ndims(space.id) };
+        let offset = [start as hsize_t, 0];
+        let count = [n as hsize_t, cols as hsize_t];
+        try!(check(unsafe {
+            H5Sselect_hyperslab(space.id, H5S_SELECT_SET, offset.as_ptr(), ptr::null(),
+                                count.as_ptr(), ptr::null())
+        }, "Could not select rows."));
+        let mem = try!(Handle::new(
+            unsafe { H5Screate_simple(rank, count.as_ptr(), ptr::null()) },
+            H5Sclose, "Could not create dataspace."
+        ));
+        try!(check(unsafe {
+            H5Dread(dset.id, H5T_NATIVE_DOUBLE_g, mem.id, space.id, H5P_DEFAULT,
+                    buf.as_mut_ptr() as *mut c_void)
+        }, &format!("Could not read dataset {}.", name)));
+        Ok(Matrix::from_vec(buf, n, cols))
+    }
+
+    /// Returns an iterator over the rows of a dataset which reads at most
+    /// `chunk_rows` rows at once. Only one chunk is held in memory at any
+    /// time.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn row_chunks(&self, name: &str, chunk_rows: usize) -> Result<RowChunks<'_>, String> {
+
+        assert!(chunk_rows > 0, "Number of rows per chunk must be greater than zero.");
+        let (rows, _) = try!(self.shape(name));
+        Ok(RowChunks {
+            file: self,
+            name: name.to_string(),
+            rows: rows,
+            chunk_rows: chunk_rows,
+            pos: 0
+        })
+    }
+
+    /// Writes a matrix into a dataset. An existing dataset with the same
+    /// name is replaced.
+    pub fn write(&self, name: &str, m: &Matrix<f64>) -> Result<(), String> {
+
+        let n = try!(c_string(name));
+        if self.contains(name) {
+            try!(check(unsafe { H5Ldelete(self.file.id, n.as_ptr(), H5P_DEFAULT) },
+                &format!("Could not replace dataset {}.", name)));
+        }
+
+        let dims = [m.rows() as hsize_t, m.cols() as hsize_t];
+        let space = try!(Handle::new(
+            unsafe { H5Screate_simple(2, dims.as_ptr(), ptr::null()) },
+            H5Sclose, "Could not create dataspace."
+        ));
+        let dcpl = try!(Handle::new(
+            unsafe { H5Pcreate(H5P_CLS_DATASET_CREATE_ID_g) },
+            H5Pclose, "Could not create property list."
+        ));
+        if m.rows() > 0 && m.cols() > 0 {
+            let chunk_rows = (CHUNK_SIZE / m.cols()).max(1).min(m.rows());
+            let chunk = [chunk_rows as hsize_t, m.cols() as hsize_t];
+            try!(check(unsafe { H5Pset_chunk(dcpl.id, 2, chunk.as_ptr()) },
+                "Could not set chunk size."));
+        }
+        let dset = try!(Handle::new(
+            unsafe {
+                H5Dcreate2(self.file.id, n.as_ptr(), H5T_IEEE_F64LE_g, space.id,
+                           H5P_DEFAULT, dcpl.id, H5P_DEFAULT)
+            },
+            H5Dclose, &format!("Could not create dataset {}.", name)
+        ));
+        if !m.buf().is_empty() {
+            try!(check(unsafe {
+                H5Dwrite(dset.id, H5T_NATIVE_DOUBLE_g, H5S_ALL, H5S_ALL, H5P_DEFAULT,
+                         m.buf().as_ptr() as *const c_void)
+            }, &format!("Could not write dataset {}.", name)));
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over chunks of rows of a dataset created with
+/// [`row_chunks`](struct.Hdf5File.html#method.row_chunks).
+pub struct RowChunks<'a> {
+    file: &'a Hdf5File,
+    name: String,
+    rows: usize,
+    chunk_rows: usize,
+    pos: usize
+}
+
+impl <'a> RowChunks<'a> {
+
+    /// Starts reading again at the first row.
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+    }
+}
+
+impl <'a> Iterator for RowChunks<'a> {
+    type Item = Result<Matrix<f64>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.pos >= self.rows {
+            return None;
+        }
+        let n = self.chunk_rows.min(self.rows - self.pos);
+        let r = self.file.read_rows(&self.name, self.pos, n);
+        self.pos += n;
+        Some(r)
+    }
+}
+
+/// Saves named matrices (e.g. the parameters of a model as a checkpoint)
+/// into a new HDF5 file.
+pub fn save_hdf5(fname: &str, vars: &[(&str, &Matrix<f64>)]) -> Result<(), String> {
+
+    let f = try!(Hdf5File::create(fname));
+    for &(name, m) in vars {
+        try!(f.write(name, m));
+    }
+    Ok(())
+}
+
+/// Loads the datasets with the given names from an HDF5 file.
+pub fn load_hdf5(fname: &str, names: &[&str]) -> Result<Vec<Matrix<f64>>, String> {
+
+    let f = try!(Hdf5File::open_read_only(fname));
+    names.iter().map(|name| f.read(name)).collect()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_hdf5_write_read() {
+
+        let path = ::std::env::temp_dir().join("rustml_test_hdf5.h5");
+        let fname = path.to_str().unwrap();
+        let x = Matrix::from_vec((0..20).map(|i| i as f64).collect(), 10, 2);
+        let w = mat![0.5, -1.0, 2.0];
+
+        save_hdf5(fname, &[("x", &x), ("w", &w)]).unwrap();
+        assert_eq!(load_hdf5(fname, &["w", "x"]).unwrap(), vec![w.clone(), x.clone()]);
+        assert!(load_hdf5(fname, &["y"]).is_err());
+
+        let f = Hdf5File::open(fname).unwrap();
+        assert!(f.contains("x") && !f.contains("y"));
+        assert_eq!(f.shape("x").unwrap(), (10, 2));
+        assert_eq!(f.read_rows("x", 3, 2).unwrap(), mat![6.0, 7.0; 8.0, 9.0]);
+        assert!(f.read_rows("x", 9, 2).is_err());
+
+        let chunks: Vec<Matrix<f64>> = f.row_chunks("x", 4).unwrap().map(|c| c.unwrap()).collect();
+        assert_eq!(chunks.iter().map(|c| c.rows()).collect::<Vec<usize>>(), vec![4, 4, 2]);
+        assert_eq!(chunks[2], mat![16.0, 17.0; 18.0, 19.0]);
+
+        // replace an existing dataset
+        f.write("w", &x).unwrap();
+        assert_eq!(f.read("w").unwrap(), x);
+    }
+}
@@ -0,0 +1,226 @@
+//! Reading and writing of the libsvm (svmlight) file format.
+//!
+//! Each line of a file in the libsvm format contains one example, i.e. its
+//! label followed by the non-zero features as pairs of the index and the
+//! value:
+//!
+//! ```text
+//! <label> <index>:<value> <index>:<value> ...
+//! ```
+//!
+//! By default the indexes start at 1. Comments start with `#`, `qid:`
+//! entries (used for ranking) are ignored. Because the format is sparse the
+//! examples are loaded into a [`SparseMatrix`](../../sparse/struct.SparseMatrix.html),
+//! which can be converted into a dense matrix with
+//! [`to_dense`](../../sparse/struct.SparseMatrix.html#method.to_dense) or
+//! loaded directly as a dense matrix with
+//! [`load_libsvm_dense`](fn.load_libsvm_dense.html).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::io::libsvm::*;
+//!
+//! # fn main() {
+//! let s = "1 1:0.5 3:2\n-1 2:1.5 # comment\n";
+//! let (x, y) = parse_libsvm(s, None, false).unwrap();
+//! assert_eq!(y, vec![1.0, -1.0]);
+//! assert_eq!(x.to_dense(), mat![0.5, 0.0, 2.0; 0.0, 1.5, 0.0]);
+//!
+//! let mut out = Vec::new();
+//! write_libsvm(&mut out, &x, &y, false).unwrap();
+//! assert_eq!(String::from_utf8(out).unwrap(), "1 1:0.5 3:2\n-1 2:1.5\n");
+//! # }
+//! ```
+use std::fs::File;
+use std::io::{Read, Write, BufWriter};
+
+use matrix::Matrix;
+use sparse::SparseMatrix;
+
+/// Parses examples in the libsvm format.
+///
+/// The number of features (columns) is the largest index found in the data
+/// or `n_features` if given. With `zero_based = true` the indexes start at 0
+/// instead of 1.
+///
+/// Returns the examples and their labels or an error message if a line
+/// could not be parsed, if an index occurs twice in a line or if an index
+/// is larger than `n_features`.
+pub fn parse_libsvm(s: &str, n_features: Option<usize>, zero_based: bool) -> Result<(SparseMatrix<f64>, Vec<f64>), String> {
+
+    let mut rows: Vec<Vec<(usize, f64)>> = Vec::new();
+    let mut labels = Vec::new();
+    let mut cols = 0;
+
+    for (n, line) in s.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None => line
+        };
+        let mut tokens = line.split_whitespace();
+        let label = match tokens.next() {
+            Some(t) => try!(t.parse::<f64>().map_err(|_| format!("Line {}: invalid label {}.", n + 1, t))),
+            None => continue
+        };
+
+        let mut row = Vec::new();
+        for t in tokens {
+            let mut kv = t.splitn(2, ':');
+            let (k, v) = match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) => (k, v),
+                _ => return Err(format!("Line {}: invalid feature {}.", n + 1, t))
+            };
+            if k == "qid" {
+                continue;
+            }
+            let idx = try!(k.parse::<usize>().map_err(|_| format!("Line {}: invalid index {}.", n + 1, k)));
+            if !zero_based && idx == 0 {
+                return Err(format!("Line {}: index 0 is not allowed for one-based indexes.", n + 1));
+            }
+            let idx = if zero_based { idx } else { idx - 1 };
+            let val = try!(v.parse::<f64>().map_err(|_| format!("Line {}: invalid value {}.", n + 1, v)));
+            if row.iter().any(|&(i, _)| i == idx) {
+                return Err(format!("Line {}: duplicate index {}.", n + 1, k));
+            }
+            cols = cols.max(idx + 1);
+            row.push((idx, val));
+        }
+        rows.push(row);
+        labels.push(label);
+    }
+
+    if let Some(f) = n_features {
+        if cols > f {
+            return Err(format!("Found index {} but the number of features is {}.", cols, f));
+        }
+        cols = f;
+    }
+
+    let mut m = SparseMatrix::new(cols);
+    for r in &rows {
+        m.add_row(r);
+    }
+    Ok((m, labels))
+}
+
+/// Reads examples in the libsvm format from a reader (see
+/// [`parse_libsvm`](fn.parse_libsvm.html)).
+pub fn read_libsvm<R: Read>(mut reader: R, n_features: Option<usize>, zero_based: bool) -> Result<(SparseMatrix<f64>, Vec<f64>), String> {
+
+    let mut s = String::new();
+    try!(reader.read_to_string(&mut s).map_err(|e| format!("Could not read data: {}", e)));
+    parse_libsvm(&s, n_features, zero_based)
+}
+
+/// Loads examples in the libsvm format from a file (see
+/// [`parse_libsvm`](fn.parse_libsvm.html)).
+pub fn load_libsvm(fname: &str, n_features: Option<usize>, zero_based: bool) -> Result<(SparseMatrix<f64>, Vec<f64>), String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_libsvm(f, n_features, zero_based)
+}
+
+/// Loads examples in the libsvm format from a file into a dense matrix (see
+/// [`parse_libsvm`](fn.parse_libsvm.html)).
+pub fn load_libsvm_dense(fname: &str, n_features: Option<usize>, zero_based: bool) -> Result<(Matrix<f64>, Vec<f64>), String> {
+
+    load_libsvm(fname, n_features, zero_based).map(|(m, y)| (m.to_dense(), y))
+}
+
+/// Writes examples and their labels in the libsvm format. Only the non-zero
+/// features are written. With `zero_based = true` the indexes start at 0
+/// instead of 1.
+///
+/// Returns an error if the number of rows does not match the number of
+/// labels or if the data could not be written.
+pub fn write_libsvm<W: Write>(writer: W, x: &SparseMatrix<f64>, y: &[f64], zero_based: bool) -> Result<(), String> {
+
+    if x.rows() != y.len() {
+        return Err("Number of rows does not match the number of labels.".to_string());
+    }
+
+    let offset = if zero_based { 0 } else { 1 };
+    let mut w = BufWriter::new(writer);
+    for ((idx, vals), label) in x.row_iter().zip(y.iter()) {
+        let mut line = format!("{}", label);
+        for (i, v) in idx.iter().zip(vals.iter()) {
+            line.push_str(&format!(" {}:{}", i + offset, v));
+        }
+        line.push('\n');
+        try!(w.write_all(line.as_bytes()).map_err(|e| format!("Could not write data: {}", e)));
+    }
+    w.flush().map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Writes the rows of a dense matrix and their labels in the libsvm format
+/// (see [`write_libsvm`](fn.write_libsvm.html)).
+pub fn write_libsvm_dense<W: Write>(writer: W, x: &Matrix<f64>, y: &[f64], zero_based: bool) -> Result<(), String> {
+    write_libsvm(writer, &SparseMatrix::from_dense(x), y, zero_based)
+}
+
+/// Saves examples and their labels in the libsvm format into the file
+/// `fname` (see [`write_libsvm`](fn.write_libsvm.html)).
+pub fn save_libsvm(fname: &str, x: &SparseMatrix<f64>, y: &[f64], zero_based: bool) -> Result<(), String> {
+
+    let f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    write_libsvm(f, x, y, zero_based)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_parse_libsvm() {
+
+        let s = "# header\n3 qid:1 2:1.5 1:-1\n\n0\n2.5 4:1e-3\n";
+        let (x, y) = parse_libsvm(s, None, false).unwrap();
+        assert_eq!(y, vec![3.0, 0.0, 2.5]);
+        assert_eq!(x.cols(), 4);
+        assert_eq!(x.to_dense(), mat![-1.0, 1.5, 0.0, 0.0; 0.0, 0.0, 0.0, 0.0; 0.0, 0.0, 0.0, 0.001]);
+
+        let (x, _) = parse_libsvm(s, Some(10), false).unwrap();
+        assert_eq!(x.cols(), 10);
+
+        let (x, _) = parse_libsvm("1 0:1 2:2", None, true).unwrap();
+        assert_eq!(x.to_dense(), mat![1.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_parse_libsvm_errors() {
+
+        assert!(parse_libsvm("a 1:1", None, false).is_err());
+        assert!(parse_libsvm("1 1", None, false).is_err());
+        assert!(parse_libsvm("1 x:1", None, false).is_err());
+        assert!(parse_libsvm("1 1:x", None, false).is_err());
+        assert!(parse_libsvm("1 0:1", None, false).is_err());
+        assert!(parse_libsvm("1 1:1 1:2", None, false).is_err());
+        assert!(parse_libsvm("1 5:1", Some(4), false).is_err());
+    }
+
+    #[test]
+    fn test_write_libsvm() {
+
+        let x = mat![0.0, 2.0; 0.0, 0.0; 1.5, -1.0];
+        let y = vec![1.0, 0.0, -1.0];
+
+        let mut v = Vec::new();
+        write_libsvm_dense(&mut v, &x, &y, true).unwrap();
+        assert_eq!(String::from_utf8(v).unwrap(), "1 1:2\n0\n-1 0:1.5 1:-1\n");
+
+        let path = ::std::env::temp_dir().join("rustml_test_libsvm.txt");
+        let fname = path.to_str().unwrap();
+        save_libsvm(fname, &SparseMatrix::from_dense(&x), &y, false).unwrap();
+        let (m, l) = load_libsvm_dense(fname, Some(2), false).unwrap();
+        assert_eq!(m, x);
+        assert_eq!(l, y);
+
+        assert!(write_libsvm_dense(Vec::new(), &x, &[1.0], false).is_err());
+    }
+}
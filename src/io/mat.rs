@@ -0,0 +1,384 @@
+//! Reading and writing of MATLAB/Octave Level 5 `.mat` files.
+//!
+//! A `.mat` file contains named variables. Numeric matrices of all real
+//! classes (`double`, `single` and the integer classes) can be read and are
+//! converted into matrices of `f64`. Variables of other types (e.g. strings,
+//! cell arrays, structs, sparse or complex matrices) are skipped. Both byte
+//! orders and compressed variables (the default of MATLAB since version 7)
+//! are supported.
+//!
+//! Matrices are written as `double` variables, either uncompressed or
+//! compressed. The files can be loaded in MATLAB and Octave with
+//! `load("file.mat")`.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::io::mat::*;
+//!
+//! # fn main() {
+//! let x = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+//! let y = mat![1.0; 0.0];
+//!
+//! let mut buf = Vec::new();
+//! write_mat(&mut buf, &[("x", &x), ("y", &y)], true).unwrap();
+//!
+//! let vars = read_mat(&buf[..]).unwrap();
+//! assert_eq!(vars, vec![("x".to_string(), x), ("y".to_string(), y)]);
+//! # }
+//! ```
+extern crate flate2;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use self::flate2::Compression;
+use self::flate2::read::ZlibDecoder;
+use self::flate2::write::ZlibEncoder;
+
+use matrix::Matrix;
+
+// data types of data elements
+const MI_INT8: u32 = 1;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_DOUBLE: u32 = 9;
+const MI_MATRIX: u32 = 14;
+const MI_COMPRESSED: u32 = 15;
+
+// array classes
+const MX_DOUBLE_CLASS: u32 = 6;
+const MX_UINT64_CLASS: u32 = 15;
+const COMPLEX_FLAG: u32 = 0x0800;
+
+/// Reader for the data elements of a buffer in a given byte order.
+struct Elements<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool
+}
+
+impl <'a> Elements<'a> {
+
+    fn u32_at(&self, pos: usize) -> u32 {
+        let b = [self.buf[pos], self.buf[pos + 1], self.buf[pos + 2], self.buf[pos + 3]];
+        if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+    }
+
+    /// Returns the type and the data of the next data element.
+    fn next(&mut self) -> Result<Option<(u32, &'a [u8])>, String> {
+
+        if self.pos >= self.buf.len() {
+            return Ok(None);
+        }
+        if self.pos + 8 > self.buf.len() {
+            return Err("Truncated data element.".to_string());
+        }
+        let tag = self.u32_at(self.pos);
+        // small data element format: type and size in the first four bytes
+        if tag >> 16 != 0 {
+            let (t, n) = (tag & 0xFFFF, (tag >> 16) as usize);
+            if n > 4 {
+                return Err("Invalid small data element.".to_string());
+            }
+            let data = &self.buf[self.pos + 4..self.pos + 4 + n];
+            self.pos += 8;
+            return Ok(Some((t, data)));
+        }
+        let n = self.u32_at(self.pos + 4) as usize;
+        let start = self.pos + 8;
+        if start + n > self.buf.len() {
+            return Err("Truncated data element.".to_string());
+        }
+        // elements are padded to 8 bytes except compressed elements
+        self.pos = if tag == MI_COMPRESSED { start + n } else { start + (n + 7) / 8 * 8 };
+        Ok(Some((tag, &self.buf[start..start + n])))
+    }
+
+    /// Returns the data of the next element, which must exist.
+    fn expect(&mut self) -> Result<(u32, &'a [u8]), String> {
+        match try!(self.next()) {
+            Some(e) => Ok(e),
+            None => Err("Missing data element.".to_string())
+        }
+    }
+}
+
+/// Converts numeric data of the given data type into `f64` values.
+fn numbers(t: u32, data: &[u8], big_endian: bool) -> Result<Vec<f64>, String> {
+
+    // size in bytes, float, signed
+    let (size, float, signed) = match t {
+        1 => (1, false, true),
+        2 => (1, false, false),
+        3 => (2, false, true),
+        4 => (2, false, false),
+        5 => (4, false, true),
+        6 => (4, false, false),
+        7 => (4, true, true),
+        9 => (8, true, true),
+        12 => (8, false, true),
+        13 => (8, false, false),
+        _ => return Err(format!("Unsupported data type {}.", t))
+    };
+
+    Ok(data.chunks(size).map(|b| {
+        let mut v = [0u8; 8];
+        for (i, &x) in b.iter().enumerate() {
+            v[if big_endian { size - 1 - i } else { i }] = x;
+        }
+        let bits = u64::from_le_bytes(v);
+        if float {
+            if size == 4 { f32::from_bits(bits as u32) as f64 } else { f64::from_bits(bits) }
+        } else if signed {
+            let shift = 64 - 8 * size as u32;
+            ((bits << shift) as i64 >> shift) as f64
+        } else {
+            bits as f64
+        }
+    }).collect())
+}
+
+/// Parses a matrix element. Returns `None` for unsupported variables.
+fn parse_matrix(data: &[u8], big_endian: bool) -> Result<Option<(String, Matrix<f64>)>, String> {
+
+    let mut e = Elements { buf: data, pos: 0, big_endian: big_endian };
+
+    let (_, flags) = try!(e.expect());
+    if flags.len() < 8 {
+        return Err("Invalid array flags.".to_string());
+    }
+    let f = Elements { buf: flags, pos: 0, big_endian: big_endian }.u32_at(0);
+    let class = f & 0xFF;
+
+    let (t, dims) = try!(e.expect());
+    let dims = try!(numbers(t, dims, big_endian));
+    let (_, name) = try!(e.expect());
+    let name = String::from_utf8_lossy(name).into_owned();
+
+    if class < MX_DOUBLE_CLASS || class > MX_UINT64_CLASS || f & COMPLEX_FLAG != 0 {
+        return Ok(None);
+    }
+    if dims.len() != 2 {
+        return Err(format!("Variable {} has {} dimensions.", name, dims.len()));
+    }
+    let (rows, cols) = (dims[0] as usize, dims[1] as usize);
+
+    let (t, real) = try!(e.expect());
+    let v = try!(numbers(t, real, big_endian));
+    if v.len() != rows * cols {
+        return Err(format!("Variable {} has {} elements but expected {}.", name, v.len(), rows * cols));
+    }
+    // elements are stored column by column
+    let mut m = Vec::with_capacity(rows * cols);
+    for i in 0..rows {
+        m.extend((0..cols).map(|j| v[j * rows + i]));
+    }
+    Ok(Some((name, Matrix::from_vec(m, rows, cols))))
+}
+
+/// Parses all data elements of a buffer.
+fn parse_elements(buf: &[u8], big_endian: bool, vars: &mut Vec<(String, Matrix<f64>)>) -> Result<(), String> {
+
+    let mut e = Elements { buf: buf, pos: 0, big_endian: big_endian };
+    while let Some((t, data)) = try!(e.next()) {
+        match t {
+            MI_MATRIX => {
+                if let Some(v) = try!(parse_matrix(data, big_endian)) {
+                    vars.push(v);
+                }
+            },
+            MI_COMPRESSED => {
+                let mut d = Vec::new();
+                try!(ZlibDecoder::new(data).read_to_end(&mut d)
+                    .map_err(|e| format!("Could not decompress data: {}", e)));
+                try!(parse_elements(&d, big_endian, vars));
+            },
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+/// Reads all numeric matrices of a `.mat` file given as a reader.
+///
+/// Returns the names of the variables together with the matrices in the
+/// order in which they are stored in the file.
+pub fn read_mat<R: Read>(mut reader: R) -> Result<Vec<(String, Matrix<f64>)>, String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+
+    if buf.len() < 128 {
+        return Err("Header is truncated.".to_string());
+    }
+    let big_endian = match &buf[126..128] {
+        b"IM" => false,
+        b"MI" => true,
+        _ => return Err("Not a Level 5 MAT-file.".to_string())
+    };
+
+    let mut vars = Vec::new();
+    try!(parse_elements(&buf[128..], big_endian, &mut vars));
+    Ok(vars)
+}
+
+/// Loads all numeric matrices from a `.mat` file (see
+/// [`read_mat`](fn.read_mat.html)).
+pub fn load_mat(fname: &str) -> Result<Vec<(String, Matrix<f64>)>, String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_mat(f)
+}
+
+/// Appends a data element with the given type and data (little endian).
+fn element(buf: &mut Vec<u8>, t: u32, data: &[u8]) {
+
+    buf.extend_from_slice(&t.to_le_bytes());
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.extend_from_slice(data);
+    while buf.len() % 8 != 0 {
+        buf.push(0);
+    }
+}
+
+fn matrix_element(name: &str, m: &Matrix<f64>) -> Vec<u8> {
+
+    let mut body = Vec::new();
+    element(&mut body, MI_UINT32, &[MX_DOUBLE_CLASS as u8, 0, 0, 0, 0, 0, 0, 0]);
+    let mut dims = (m.rows() as i32).to_le_bytes().to_vec();
+    dims.extend_from_slice(&(m.cols() as i32).to_le_bytes());
+    element(&mut body, MI_INT32, &dims);
+    element(&mut body, MI_INT8, name.as_bytes());
+    let mut data = Vec::with_capacity(8 * m.rows() * m.cols());
+    for col in m.col_iter() {
+        for x in col {
+            data.extend_from_slice(&x.to_bits().to_le_bytes());
+        }
+    }
+    element(&mut body, MI_DOUBLE, &data);
+
+    let mut r = Vec::new();
+    element(&mut r, MI_MATRIX, &body);
+    r
+}
+
+/// Writes named matrices as `double` variables into a `.mat` file given as
+/// a writer. With `compress = true` each variable is compressed.
+///
+/// Returns an error if a name is not a valid variable name, i.e. if it is
+/// empty, does not start with a letter or contains characters other than
+/// letters, digits and underscores.
+pub fn write_mat<W: Write>(mut writer: W, vars: &[(&str, &Matrix<f64>)], compress: bool) -> Result<(), String> {
+
+    let mut buf = Vec::new();
+    let mut text = "MATLAB 5.0 MAT-file, Platform: rustml".to_string().into_bytes();
+    text.resize(116, b' ');
+    buf.extend_from_slice(&text);
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&0x0100u16.to_le_bytes());
+    buf.extend_from_slice(b"IM");
+
+    for &(name, m) in vars {
+        let valid = name.chars().next().map(|c| c.is_ascii_alphabetic()).unwrap_or(false) &&
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Invalid variable name {}.", name));
+        }
+
+        let e = matrix_element(name, m);
+        if compress {
+            let mut z = ZlibEncoder::new(Vec::new(), Compression::Default);
+            try!(z.write_all(&e).map_err(|e| format!("Could not compress data: {}", e)));
+            let c = try!(z.finish().map_err(|e| format!("Could not compress data: {}", e)));
+            buf.extend_from_slice(&MI_COMPRESSED.to_le_bytes());
+            buf.extend_from_slice(&(c.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&c);
+        } else {
+            buf.extend_from_slice(&e);
+        }
+    }
+    writer.write_all(&buf).map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Saves named matrices into a `.mat` file (see
+/// [`write_mat`](fn.write_mat.html)).
+pub fn save_mat(fname: &str, vars: &[(&str, &Matrix<f64>)], compress: bool) -> Result<(), String> {
+
+    let f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    write_mat(f, vars, compress)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    fn be_element(buf: &mut Vec<u8>, t: u32, data: &[u8]) {
+
+        buf.extend_from_slice(&t.to_be_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(data);
+        while buf.len() % 8 != 0 {
+            buf.push(0);
+        }
+    }
+
+    #[test]
+    fn test_write_read_mat() {
+
+        let a = mat![1.0, -2.5; 3.0, 1e-300; 0.0, 7.0];
+        let b = Matrix::from_vec(vec![], 0, 0);
+
+        for &compress in &[false, true] {
+            let mut buf = Vec::new();
+            write_mat(&mut buf, &[("a", &a), ("b_2", &b)], compress).unwrap();
+            assert_eq!(&buf[126..128], b"IM");
+            let vars = read_mat(&buf[..]).unwrap();
+            assert_eq!(vars.len(), 2);
+            assert_eq!(vars[0], ("a".to_string(), a.clone()));
+            assert_eq!(vars[1].0, "b_2");
+            assert_eq!(vars[1].1.rows(), 0);
+        }
+
+        assert!(write_mat(Vec::new(), &[("1a", &a)], false).is_err());
+        assert!(write_mat(Vec::new(), &[("a b", &a)], false).is_err());
+    }
+
+    #[test]
+    fn test_read_big_endian_mat() {
+
+        // int16 matrix [1 2; -3 4] with a name in the small data element
+        // format, followed by a char array which is skipped
+        let mut body = Vec::new();
+        be_element(&mut body, MI_UINT32, &[0, 0, 0, 10, 0, 0, 0, 0]);
+        be_element(&mut body, MI_INT32, &[0, 0, 0, 2, 0, 0, 0, 2]);
+        body.extend_from_slice(&[0, 1, 0, 1, b'm', 0, 0, 0]);
+        be_element(&mut body, 3, &[0, 1, 0xFF, 0xFD, 0, 2, 0, 4]);
+
+        let mut chars = Vec::new();
+        be_element(&mut chars, MI_UINT32, &[0, 0, 0, 4, 0, 0, 0, 0]);
+        be_element(&mut chars, MI_INT32, &[0, 0, 0, 1, 0, 0, 0, 1]);
+        be_element(&mut chars, MI_INT8, b"s");
+        be_element(&mut chars, 4, &[0, b'x']);
+
+        let mut buf = vec![b' '; 126];
+        buf.extend_from_slice(b"MI");
+        be_element(&mut buf, MI_MATRIX, &body);
+        be_element(&mut buf, MI_MATRIX, &chars);
+
+        let vars = read_mat(&buf[..]).unwrap();
+        assert_eq!(vars, vec![("m".to_string(), mat![1.0, 2.0; -3.0, 4.0])]);
+    }
+
+    #[test]
+    fn test_read_mat_errors() {
+
+        assert!(read_mat(&[0u8; 10][..]).is_err());
+        assert!(read_mat(&[0u8; 128][..]).is_err());
+        assert!(load_mat("datasets/testing/nonexisting.mat").is_err());
+    }
+}
@@ -1,6 +1,21 @@
 //! Module which contains convenient functions to read from stdin and provides
 //! functions to read and write files (e.g. gzip compressed files,
 //! csv files, etc).
+//!
+//! For CSV files of real datasets with headers, quoted fields and missing
+//! values see the module [csv](csv/index.html), for sparse datasets in the
+//! libsvm format see the module [libsvm](libsvm/index.html), for NumPy
+//! arrays see the module [npy](npy/index.html) and for MATLAB/Octave
+//! `.mat` files see the module [mat](mat/index.html). Neural networks can
+//! be exported into the ONNX format with the module [onnx](onnx/index.html)
+//! and linear models into the PMML format with the module
+//! [pmml](pmml/index.html). Fitted models of this library are saved and
+//! loaded uniformly with the module [model](model/index.html). With the
+//! feature `hdf5`
+//! the module `hdf5` reads and writes matrices in HDF5 files and with the
+//! feature `arrow` the module `arrow` reads columns of Apache Arrow files.
+//! The module `parquet` reads columns of Apache Parquet files and is
+//! available with the feature `parquet`.
 extern crate flate2;
 extern crate libc;
 extern crate regex;
@@ -18,6 +33,20 @@ use std::{io as stdio};
 use vectors::copy_memory;
 use matrix::Matrix;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod csv;
+#[cfg(feature = "hdf5")]
+pub mod hdf5;
+pub mod libsvm;
+pub mod mat;
+pub mod model;
+pub mod npy;
+pub mod onnx;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod pmml;
+
 // ----------------------------------------------------------------------------
 
 /// Create comma separated values from a collection.
@@ -427,6 +456,115 @@ pub fn csv_reader<R: Read>(reader: R) -> CsvReader<R> {
     }
 }
 
+/// Iterator over chunks of rows of a CSV file with numbers which is read
+/// incrementally, i.e. only one chunk is held in memory at any time. The
+/// lines are split like with [`CsvReader`](struct.CsvReader.html).
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use std::io::Write;
+/// use rustml::*;
+/// use rustml::io::CsvChunks;
+///
+/// # fn main() {
+/// let fname = std::env::temp_dir().join("rustml_doc_csv_chunks.csv");
+/// std::fs::File::create(&fname).unwrap().write_all(b"a;b\n1;2\n3;4\n5;6\n").unwrap();
+///
+/// let mut c = CsvChunks::open(fname.to_str().unwrap(), 2).unwrap().delimiter(";").header(true);
+/// assert_eq!(c.next().unwrap().unwrap(), mat![1.0, 2.0; 3.0, 4.0]);
+/// assert_eq!(c.next().unwrap().unwrap(), mat![5.0, 6.0]);
+/// assert!(c.next().is_none());
+/// # }
+/// ```
+pub struct CsvChunks {
+    fname: String,
+    reader: CsvReader<File>,
+    header: bool,
+    chunk_rows: usize,
+    line: usize
+}
+
+impl CsvChunks {
+
+    /// Opens a CSV file which is read in chunks of at most `chunk_rows`
+    /// rows. By default the delimiter is `,` and the file has no header.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn open(fname: &str, chunk_rows: usize) -> Result<CsvChunks, String> {
+
+        assert!(chunk_rows > 0, "Number of rows per chunk must be greater than zero.");
+        let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+        Ok(CsvChunks {
+            fname: fname.to_string(),
+            reader: csv_reader(f),
+            header: false,
+            chunk_rows: chunk_rows,
+            line: 0
+        })
+    }
+
+    /// Sets the delimiter that is used for value separation.
+    pub fn delimiter(self, delim: &str) -> CsvChunks {
+        CsvChunks { reader: self.reader.delimiter(delim), .. self }
+    }
+
+    /// Sets whether the first line contains the names of the columns. The
+    /// names are skipped.
+    pub fn header(self, header: bool) -> CsvChunks {
+        CsvChunks { header: header, .. self }
+    }
+
+    /// Starts reading again at the first row.
+    pub fn rewind(&mut self) -> Result<(), String> {
+
+        let f = try!(File::open(&self.fname).map_err(|e| format!("Could not open file {}: {}", self.fname, e)));
+        let delim = self.reader.delim.clone();
+        self.reader = csv_reader(f).delimiter(&delim);
+        self.line = 0;
+        Ok(())
+    }
+}
+
+impl Iterator for CsvChunks {
+    type Item = Result<Matrix<f64>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        let mut data = Vec::new();
+        let mut rows = 0;
+        let mut cols = 0;
+        while rows < self.chunk_rows {
+            let fields = match self.reader.next() {
+                None => break,
+                Some(Err(e)) => return Some(Err(format!("Could not read file {}: {}", self.fname, e))),
+                Some(Ok(f)) => f
+            };
+            self.line += 1;
+            if self.header && self.line == 1 {
+                continue;
+            }
+            if rows > 0 && fields.len() != cols {
+                return Some(Err(format!("Row {} has {} columns but expected {}.", self.line, fields.len(), cols)));
+            }
+            cols = fields.len();
+            for f in fields {
+                match f.parse::<f64>() {
+                    Ok(v) => data.push(v),
+                    Err(_) => return Some(Err(format!("Could not parse the value {} in row {}.", f, self.line)))
+                }
+            }
+            rows += 1;
+        }
+        if rows == 0 {
+            None
+        } else {
+            Some(Ok(Matrix::from_vec(data, rows, cols)))
+        }
+    }
+}
+
 // -------------------------------------------------------------------------
 
 /// Trait to build a collection from comma separated values.
@@ -0,0 +1,464 @@
+//! Uniform persistence of fitted models.
+//!
+//! Models which implement the trait [Model](trait.Model.html) can be saved
+//! with [save_model](fn.save_model.html) and restored with
+//! [load_model](fn.load_model.html). Currently these are:
+//!
+//! * neural networks and linear models: `NeuralNetwork`, `Hypothesis`,
+//!   `SgdLinear`
+//! * clustering and decomposition: `MiniBatchKMeans`, `IncrementalPca`,
+//!   `Whitening`
+//! * preprocessing: `StandardScaler<f64>`, `MinMaxScaler<f64>`,
+//!   `RobustScaler<f64>`, `Imputer<f64>`, `KBinsDiscretizer<f64>`,
+//!   `PolynomialFeatures`
+//! * encoders: `OrdinalEncoder<String>`, `OneHotEncoder<String>`,
+//!   `LabelEncoder<String>`, `LabelBinarizer<String>`
+//! * text: `CountVectorizer`, `TfidfTransformer`
+//! * feature selection: `SelectKBest`, `Rfe`
+//! * training checkpoints of any of these models: `Checkpoint<M>`
+//!
+//! Other models, e.g. the ensembles, the mixture models or the nearest
+//! neighbour models, cannot be persisted yet.
+//!
+//! The models are stored in a versioned binary container with the
+//! following layout (all integers are little endian):
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | 8 | magic number `RUSTMLMD` |
+//! | 4 | version of the container format |
+//! | 4 + n | length and UTF-8 bytes of the type tag of the model, e.g. `rustml.nn.NeuralNetwork` |
+//! | 8 + m | length and bytes of the payload |
+//!
+//! The payload is written by the model via an [Encoder](struct.Encoder.html)
+//! and read back via a [Decoder](struct.Decoder.html). When a model is
+//! loaded the type tag is checked, i.e. a file which contains a neural
+//! network cannot be loaded accidentally as a scaler. The type tag of a file
+//! can be inspected with [model_tag](fn.model_tag.html).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::scaling::*;
+//! use rustml::io::model::*;
+//!
+//! # fn main() {
+//! let s = StandardScaler::fit(&mat![1.0, 2.0; 3.0, 6.0]);
+//!
+//! let path = std::env::temp_dir().join("rustml_doc_scaler.model");
+//! let fname = path.to_str().unwrap();
+//! save_model(fname, &s).unwrap();
+//!
+//! let t: StandardScaler<f64> = load_model(fname).unwrap();
+//! assert_eq!(t.mean(), &[2.0, 4.0]);
+//! assert_eq!(model_tag(fname).unwrap(), "rustml.scaling.StandardScaler");
+//! # }
+//! ```
+use std::fs::File;
+use std::io::{Read, Write, BufReader, BufWriter};
+
+use matrix::Matrix;
+
+/// Magic number at the beginning of each model file.
+pub const MAGIC: &'static [u8; 8] = b"RUSTMLMD";
+
+/// Current version of the container format. The version is incremented
+/// whenever the payload of a model changes (version 2 changed the payload
+/// of neural networks) and only files of the current version can be
+/// loaded.
+pub const VERSION: u32 = 2;
+
+/// Trait for models which can be saved and loaded with
+/// [save_model](fn.save_model.html) and [load_model](fn.load_model.html).
+pub trait Model: Sized {
+
+    /// Returns the unique type tag of the model.
+    fn tag() -> &'static str;
+
+    /// Writes the parameters of the model.
+    fn encode(&self, e: &mut Encoder);
+
+    /// Reads the parameters of the model which have been written by
+    /// `encode`.
+    fn decode(d: &mut Decoder) -> Result<Self, String>;
+}
+
+/// Writes the payload of a model.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    buf: Vec<u8>
+}
+
+impl Encoder {
+
+    /// Creates an empty payload.
+    pub fn new() -> Encoder {
+        Encoder { buf: vec![] }
+    }
+
+    /// Returns the bytes written so far.
+    pub fn buf(&self) -> &Vec<u8> {
+        &self.buf
+    }
+
+    /// Writes an unsigned integer.
+    pub fn u64(&mut self, v: u64) {
+        for i in 0..8 {
+            self.buf.push((v >> (8 * i)) as u8);
+        }
+    }
+
+    /// Writes a length or an index.
+    pub fn usize(&mut self, v: usize) {
+        self.u64(v as u64)
+    }
+
+    /// Writes a float.
+    pub fn f64(&mut self, v: f64) {
+        self.u64(v.to_bits())
+    }
+
+    /// Writes a boolean.
+    pub fn bool(&mut self, v: bool) {
+        self.buf.push(v as u8)
+    }
+
+    /// Writes a string.
+    pub fn str(&mut self, s: &str) {
+        self.usize(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    /// Writes a vector of floats.
+    pub fn f64s(&mut self, v: &[f64]) {
+        self.usize(v.len());
+        for &x in v {
+            self.f64(x);
+        }
+    }
+
+    /// Writes a vector of lengths or indexes.
+    pub fn usizes(&mut self, v: &[usize]) {
+        self.usize(v.len());
+        for &x in v {
+            self.usize(x);
+        }
+    }
+
+    /// Writes a matrix.
+    pub fn matrix(&mut self, m: &Matrix<f64>) {
+        self.usize(m.rows());
+        self.usize(m.cols());
+        for &x in m.buf() {
+            self.f64(x);
+        }
+    }
+}
+
+/// Reads the payload of a model.
+#[derive(Clone, Debug)]
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize
+}
+
+impl <'a> Decoder<'a> {
+
+    /// Creates a decoder which reads the payload `buf`.
+    pub fn new(buf: &'a [u8]) -> Decoder<'a> {
+        Decoder { buf: buf, pos: 0 }
+    }
+
+    /// Returns `true` if all bytes of the payload have been read.
+    pub fn done(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+
+        if n > self.buf.len() - self.pos {
+            return Err("Unexpected end of payload.".to_string());
+        }
+        let r = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(r)
+    }
+
+    /// Reads an unsigned integer.
+    pub fn u64(&mut self) -> Result<u64, String> {
+        let b = try!(self.take(8));
+        Ok(b.iter().rev().fold(0, |acc, &x| (acc << 8) | x as u64))
+    }
+
+    /// Reads a length or an index.
+    pub fn usize(&mut self) -> Result<usize, String> {
+        self.u64().map(|v| v as usize)
+    }
+
+    /// Reads a float.
+    pub fn f64(&mut self) -> Result<f64, String> {
+        self.u64().map(f64::from_bits)
+    }
+
+    /// Reads a boolean.
+    pub fn bool(&mut self) -> Result<bool, String> {
+        match try!(self.take(1))[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => Err(format!("Invalid boolean {} in payload.", v))
+        }
+    }
+
+    /// Reads a string.
+    pub fn str(&mut self) -> Result<String, String> {
+        let n = try!(self.usize());
+        let b = try!(self.take(n));
+        String::from_utf8(b.to_vec()).map_err(|_| "Invalid string in payload.".to_string())
+    }
+
+    /// Reads a vector of floats.
+    pub fn f64s(&mut self) -> Result<Vec<f64>, String> {
+        let n = try!(self.usize());
+        (0..n).map(|_| self.f64()).collect()
+    }
+
+    /// Reads a vector of lengths or indexes.
+    pub fn usizes(&mut self) -> Result<Vec<usize>, String> {
+        let n = try!(self.usize());
+        (0..n).map(|_| self.usize()).collect()
+    }
+
+    /// Reads a matrix.
+    pub fn matrix(&mut self) -> Result<Matrix<f64>, String> {
+        let rows = try!(self.usize());
+        let cols = try!(self.usize());
+        if rows.saturating_mul(cols).saturating_mul(8) > self.buf.len() - self.pos {
+            return Err("Unexpected end of payload.".to_string());
+        }
+        let v: Vec<f64> = try!((0..rows * cols).map(|_| self.f64()).collect());
+        Ok(Matrix::from_vec(v, rows, cols))
+    }
+}
+
+/// Writes a model into a container.
+pub fn write_model<W: Write, M: Model>(mut w: W, m: &M) -> Result<(), String> {
+
+    let mut e = Encoder::new();
+    m.encode(&mut e);
+
+    let mut out = Encoder::new();
+    out.buf.extend_from_slice(MAGIC);
+    out.buf.extend_from_slice(&[VERSION as u8, (VERSION >> 8) as u8, (VERSION >> 16) as u8, (VERSION >> 24) as u8]);
+    let tag = M::tag().as_bytes();
+    let n = tag.len() as u32;
+    out.buf.extend_from_slice(&[n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8]);
+    out.buf.extend_from_slice(tag);
+    out.usize(e.buf.len());
+    out.buf.extend_from_slice(&e.buf);
+
+    w.write_all(&out.buf).map_err(|e| format!("Could not write model: {}", e))
+}
+
+// Reads the container and returns the type tag and the payload.
+fn read_container<R: Read>(mut r: R) -> Result<(String, Vec<u8>), String> {
+
+    let mut buf = vec![];
+    try!(r.read_to_end(&mut buf).map_err(|e| format!("Could not read model: {}", e)));
+
+    if buf.len() < 8 || &buf[..8] != MAGIC {
+        return Err("Not a model file.".to_string());
+    }
+    let mut d = Decoder::new(&buf[8..]);
+    let h = try!(d.take(4));
+    let version = h.iter().rev().fold(0, |acc, &x| (acc << 8) | x as u32);
+    if version != VERSION {
+        return Err(format!("Unsupported version {} of the model format.", version));
+    }
+    let h = try!(d.take(4));
+    let n = h.iter().rev().fold(0, |acc, &x| (acc << 8) | x as usize);
+    let tag = try!(String::from_utf8(try!(d.take(n)).to_vec()).map_err(|_| "Invalid type tag.".to_string()));
+    let len = try!(d.usize());
+    let payload = try!(d.take(len)).to_vec();
+    Ok((tag, payload))
+}
+
+/// Reads a model from a container.
+///
+/// Returns an error if the container is invalid or if it contains a model
+/// of a different type.
+pub fn read_model<R: Read, M: Model>(r: R) -> Result<M, String> {
+
+    let (tag, payload) = try!(read_container(r));
+    if tag != M::tag() {
+        return Err(format!("Expected a model of type {} but found {}.", M::tag(), tag));
+    }
+    let mut d = Decoder::new(&payload);
+    let m = try!(M::decode(&mut d));
+    if !d.done() {
+        return Err("Trailing bytes in payload.".to_string());
+    }
+    Ok(m)
+}
+
+/// Saves a model into a file.
+pub fn save_model<M: Model>(fname: &str, m: &M) -> Result<(), String> {
+
+    let f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    write_model(BufWriter::new(f), m)
+}
+
+/// Loads a model from a file.
+///
+/// Returns an error if the file is not a model file or if it contains a
+/// model of a different type.
+pub fn load_model<M: Model>(fname: &str) -> Result<M, String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_model(BufReader::new(f))
+}
+
+/// Returns the type tag of the model in a file.
+pub fn model_tag(fname: &str) -> Result<String, String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_container(BufReader::new(f)).map(|(tag, _)| tag)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use nn::{NeuralNetwork, LeakyRelu, Tanh, Cost};
+    use opt::ParamGroup;
+    use regression::Hypothesis;
+    use scaling::*;
+    use whitening::{Whitening, WhiteningMethod};
+    use text::{CountVectorizer, TfidfTransformer};
+    use feature_selection::{SelectKBest, ScoreFunction};
+    use encoding::{OrdinalEncoder, OneHotEncoder, LabelEncoder, LabelBinarizer};
+    use polynomial::PolynomialFeatures;
+    use imputation::{Imputer, Strategy};
+    use discretization::{KBinsDiscretizer, BinStrategy, BinEncoding};
+
+    fn roundtrip<M: Model>(m: &M) -> M {
+
+        let mut buf = vec![];
+        write_model(&mut buf, m).unwrap();
+        read_model(&buf[..]).unwrap()
+    }
+
+    #[test]
+    fn test_encoder_decoder() {
+
+        let mut e = Encoder::new();
+        e.u64(7);
+        e.f64(-1.5);
+        e.bool(true);
+        e.str("abc");
+        e.usizes(&[1, 2]);
+        e.matrix(&mat![1.0, 2.0; 3.0, 4.0]);
+
+        let mut d = Decoder::new(e.buf());
+        assert_eq!(d.u64().unwrap(), 7);
+        assert_eq!(d.f64().unwrap(), -1.5);
+        assert_eq!(d.bool().unwrap(), true);
+        assert_eq!(d.str().unwrap(), "abc");
+        assert_eq!(d.usizes().unwrap(), vec![1, 2]);
+        assert!(d.matrix().unwrap().eq(&mat![1.0, 2.0; 3.0, 4.0]));
+        assert!(d.done());
+        assert!(d.u64().is_err());
+    }
+
+    #[test]
+    fn test_container_errors() {
+
+        let s = StandardScaler::fit(&mat![1.0; 2.0]);
+        let mut buf = vec![];
+        write_model(&mut buf, &s).unwrap();
+        assert_eq!(&buf[..8], b"RUSTMLMD");
+
+        let r: Result<MinMaxScaler<f64>, String> = read_model(&buf[..]);
+        assert!(r.unwrap_err().contains("rustml.scaling.StandardScaler"));
+        let r: Result<StandardScaler<f64>, String> = read_model(&buf[..buf.len() - 1]);
+        assert!(r.is_err());
+        let r: Result<StandardScaler<f64>, String> = read_model(&b"RUSTMLXX"[..]);
+        assert!(r.is_err());
+
+        let mut v = buf.clone();
+        for &version in &[1, 3] {
+            v[8] = version;
+            let r: Result<StandardScaler<f64>, String> = read_model(&v[..]);
+            assert!(r.unwrap_err().contains("version"));
+        }
+    }
+
+    #[test]
+    fn test_save_load_models() {
+
+        let x = mat![1.0, 2.0; 3.0, 5.0; 4.0, 1.0; 0.0, 7.0];
+
+        let nn = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+        assert!(roundtrip(&nn).predict(&x).eq(&nn.predict(&x)));
+        let relu = nn.set_activation(1, LeakyRelu(0.2)).set_activation(2, Tanh);
+        let r = roundtrip(&relu);
+        assert_eq!(r.activation(1).params(), vec![0.2]);
+        assert!(r.predict(&x).eq(&relu.predict(&x)));
+        let groups = [ParamGroup::new(&[0]).alpha(0.0), ParamGroup::new(&[1]).decay(0.1)];
+        assert_eq!(roundtrip(&nn.set_param_groups(&groups)).param_groups(), &groups[..]);
+        let sm = roundtrip(&relu.set_softmax(true).set_cost(Cost::CrossEntropy));
+        assert!(sm.is_softmax() && sm.cost() == Cost::CrossEntropy);
+
+        let h = Hypothesis::from_params(&[0.5, 1.0, -2.0]);
+        assert_eq!(roundtrip(&h).params(), h.params());
+
+        let s = StandardScaler::fit(&x);
+        assert!(roundtrip(&s).transform(&x).eq(&s.transform(&x)));
+        let s = MinMaxScaler::fit(&x);
+        assert!(roundtrip(&s).transform(&x).eq(&s.transform(&x)));
+        let s = RobustScaler::fit(&x);
+        assert!(roundtrip(&s).transform(&x).eq(&s.transform(&x)));
+
+        let w = Whitening::fit(&x, WhiteningMethod::Zca, 1e-5);
+        assert!(roundtrip(&w).transform(&x).eq(&w.transform(&x)));
+
+        let docs = ["a b c", "b c", "c d d"];
+        let (cv, counts) = CountVectorizer::new().ngram_range(1, 2).fit_transform(&docs);
+        assert!(roundtrip(&cv).transform(&docs).eq(&counts));
+        let t = TfidfTransformer::fit(&counts);
+        assert!(roundtrip(&t).transform(&counts).eq(&t.transform(&counts)));
+
+        let k = SelectKBest::fit(&x, &[0, 1, 0, 1], 1, ScoreFunction::FClassif);
+        assert_eq!(roundtrip(&k).selected(), k.selected());
+
+        let p = PolynomialFeatures::new(3).interaction_only();
+        assert!(roundtrip(&p).transform(&x).eq(&p.transform(&x)));
+
+        let i = Imputer::fit(&mat![1.0, f64::NAN; 3.0, 4.0], Strategy::Mean);
+        assert_eq!(roundtrip(&i).values(), i.values());
+
+        let b = KBinsDiscretizer::fit(&x, 3, BinStrategy::Uniform, BinEncoding::OneHot);
+        assert!(roundtrip(&b).transform(&x).eq(&b.transform(&x)));
+
+        let c = mat!["b".to_string(), "x".to_string(); "a".to_string(), "y".to_string()];
+        let o = OrdinalEncoder::fit(&c);
+        assert_eq!(roundtrip(&o).transform(&c), o.transform(&c));
+        let h = OneHotEncoder::fit(&c);
+        assert_eq!(roundtrip(&h).transform(&c), h.transform(&c));
+        let labels = vec!["yes".to_string(), "no".to_string()];
+        assert_eq!(roundtrip(&LabelEncoder::fit(&labels)).classes(), &["no".to_string(), "yes".to_string()]);
+        let lb = LabelBinarizer::fit(&labels);
+        assert_eq!(roundtrip(&lb).transform(&labels), lb.transform(&labels));
+
+        let path = ::std::env::temp_dir().join("rustml_test.model");
+        let fname = path.to_str().unwrap();
+        save_model(fname, &nn).unwrap();
+        assert_eq!(model_tag(fname).unwrap(), "rustml.nn.NeuralNetwork");
+        let n: NeuralNetwork = load_model(fname).unwrap();
+        assert_eq!(n.params(), nn.params());
+        assert!(load_model::<Hypothesis>(fname).is_err());
+    }
+}
@@ -0,0 +1,658 @@
+//! Reading and writing of NumPy `.npy` and `.npz` files.
+//!
+//! A `.npy` file contains a single array in the binary format of NumPy, a
+//! `.npz` file is a zip archive with one `.npy` file per array (as created
+//! by `numpy.savez`). This allows to exchange matrices losslessly with
+//! Python.
+//!
+//! Arrays with one or two dimensions can be read. A one-dimensional array of
+//! length `n` is read as a matrix with `n` rows and one column. The elements
+//! may be floats (`f4`, `f8`) or integers (`i1` to `i8`, `u1` to `u8`) in
+//! either byte order and in C or Fortran order; they are converted into the
+//! element type of the matrix. Matrices of `f32` and `f64` are written as
+//! `<f4` and `<f8` arrays in C order. Compressed `.npz` files (created by
+//! `numpy.savez_compressed`) can be read, `.npz` files are written without
+//! compression.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::io::npy::*;
+//!
+//! # fn main() {
+//! let m = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+//!
+//! let mut buf = Vec::new();
+//! write_npy(&mut buf, &m).unwrap();
+//! assert_eq!(read_npy::<f64, _>(&buf[..]).unwrap(), m);
+//!
+//! let mut buf = Vec::new();
+//! write_npz(&mut buf, &[("x", &m), ("y", &mat![1.0; 0.0])]).unwrap();
+//! let arrays = read_npz::<f64, _>(&buf[..]).unwrap();
+//! assert_eq!(arrays[0].0, "x");
+//! assert_eq!(arrays[1].1, mat![1.0; 0.0]);
+//! # }
+//! ```
+extern crate flate2;
+
+use std::fs::File;
+use std::io::{Read, Write, BufReader, Seek, SeekFrom};
+use self::flate2::read::DeflateDecoder;
+
+use matrix::Matrix;
+
+const MAGIC: &'static [u8] = b"\x93NUMPY";
+
+/// Element types of matrices which can be read from and written to `.npy`
+/// files.
+pub trait NpyType: Clone {
+
+    /// Returns the NumPy type descriptor, e.g. `<f8`.
+    fn descr() -> &'static str;
+
+    /// Appends the little endian representation of the value to `buf`.
+    fn append_le(&self, buf: &mut Vec<u8>);
+
+    /// Converts a value read from a file into the element type.
+    fn from_f64(x: f64) -> Self;
+}
+
+impl NpyType for f64 {
+
+    fn descr() -> &'static str { "<f8" }
+
+    fn append_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_bits().to_le_bytes());
+    }
+
+    fn from_f64(x: f64) -> f64 { x }
+}
+
+impl NpyType for f32 {
+
+    fn descr() -> &'static str { "<f4" }
+
+    fn append_le(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_bits().to_le_bytes());
+    }
+
+    fn from_f64(x: f64) -> f32 { x as f32 }
+}
+
+/// Parsed header of a `.npy` file.
+struct Header {
+    little_endian: bool,
+    kind: char,
+    size: usize,
+    fortran_order: bool,
+    shape: Vec<usize>
+}
+
+/// Returns the value of `key` in the header dictionary.
+fn dict_value<'a>(dict: &'a str, key: &str) -> Result<&'a str, String> {
+
+    let k = format!("'{}'", key);
+    let pos = try!(dict.find(&k).ok_or(format!("Key {} missing in header.", key)));
+    let rest = dict[pos + k.len()..].trim_start();
+    if !rest.starts_with(':') {
+        return Err("Invalid header.".to_string());
+    }
+    let rest = rest[1..].trim_start();
+    let end = if rest.starts_with('(') {
+        try!(rest.find(')').ok_or("Invalid header.".to_string())) + 1
+    } else {
+        rest.find(|c| c == ',' || c == '}').unwrap_or(rest.len())
+    };
+    Ok(rest[..end].trim())
+}
+
+fn parse_header(dict: &str) -> Result<Header, String> {
+
+    let descr = try!(dict_value(dict, "descr")).trim_matches(|c| c == '\'' || c == '"');
+    let mut chars = descr.chars();
+    let (order, kind) = (chars.next(), chars.next());
+    let size = chars.as_str().parse::<usize>().ok();
+    let (order, kind, size) = match (order, kind, size) {
+        (Some(o), Some(k), Some(s)) => (o, k, s),
+        _ => return Err(format!("Invalid type descriptor {}.", descr))
+    };
+    let supported = match kind {
+        'f' => size == 4 || size == 8,
+        'i' | 'u' => size == 1 || size == 2 || size == 4 || size == 8,
+        _ => false
+    };
+    if !supported || !(order == '<' || order == '>' || order == '|' || order == '=') {
+        return Err(format!("Unsupported type descriptor {}.", descr));
+    }
+
+    let fortran_order = match try!(dict_value(dict, "fortran_order")) {
+        "True" => true,
+        "False" => false,
+        v => return Err(format!("Invalid value for fortran_order: {}.", v))
+    };
+
+    let shape = try!(dict_value(dict, "shape"));
+    let mut dims = Vec::new();
+    for d in shape.trim_matches(|c| c == '(' || c == ')').split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+        dims.push(try!(d.parse::<usize>().map_err(|_| format!("Invalid shape {}.", shape))));
+    }
+
+    Ok(Header {
+        little_endian: order != '>',
+        kind: kind,
+        size: size,
+        fortran_order: fortran_order,
+        shape: dims
+    })
+}
+
+/// Decodes one element.
+fn decode(b: &[u8], h: &Header) -> f64 {
+
+    let mut v = [0u8; 8];
+    if h.little_endian {
+        v[..h.size].copy_from_slice(b);
+    } else {
+        for (i, x) in b.iter().rev().enumerate() {
+            v[i] = *x;
+        }
+    }
+    let bits = u64::from_le_bytes(v);
+    match (h.kind, h.size) {
+        ('f', 4) => f32::from_bits(bits as u32) as f64,
+        ('f', _) => f64::from_bits(bits),
+        ('u', _) => bits as f64,
+        // sign extension for signed integers
+        (_, s) => {
+            let shift = 64 - 8 * s as u32;
+            ((bits << shift) as i64 >> shift) as f64
+        }
+    }
+}
+
+/// Reads a matrix from a `.npy` file given as a reader.
+///
+/// Returns an error if the data is not a valid `.npy` file, if the array has
+/// more than two dimensions or if the element type is not supported.
+pub fn read_npy<T: NpyType, R: Read>(mut reader: R) -> Result<Matrix<T>, String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+    parse_npy(&buf)
+}
+
+/// Returns the position and the length of the header dictionary.
+fn header_bounds(buf: &[u8]) -> Result<(usize, usize), String> {
+
+    if buf.len() < 10 || &buf[..6] != MAGIC {
+        return Err("Invalid magic string.".to_string());
+    }
+    match buf[6] {
+        1 => Ok((10, u16::from_le_bytes([buf[8], buf[9]]) as usize)),
+        2 | 3 if buf.len() >= 12 => Ok((12, u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize)),
+        v => Err(format!("Unsupported version {}.", v))
+    }
+}
+
+/// Returns the number of rows and columns of the array.
+fn matrix_shape(h: &Header) -> Result<(usize, usize), String> {
+
+    match h.shape.len() {
+        0 => Ok((1, 1)),
+        1 => Ok((h.shape[0], 1)),
+        2 => Ok((h.shape[0], h.shape[1])),
+        n => Err(format!("Arrays with {} dimensions are not supported.", n))
+    }
+}
+
+fn parse_npy<T: NpyType>(buf: &[u8]) -> Result<Matrix<T>, String> {
+
+    let (start, len) = try!(header_bounds(buf));
+    if buf.len() < start + len {
+        return Err("Header is truncated.".to_string());
+    }
+    let dict = try!(String::from_utf8(buf[start..start + len].to_vec()).map_err(|_| "Invalid header.".to_string()));
+    let h = try!(parse_header(&dict));
+    let (rows, cols) = try!(matrix_shape(&h));
+
+    let data = &buf[start + len..];
+    let n = try!(rows.checked_mul(cols).and_then(|n| n.checked_mul(h.size)).ok_or("Invalid shape.".to_string()));
+    if data.len() != n {
+        return Err(format!("Expected {} bytes of data but found {}.", n, data.len()));
+    }
+
+    let vals: Vec<T> = data.chunks(h.size).map(|b| T::from_f64(decode(b, &h))).collect();
+    if h.fortran_order && rows > 1 && cols > 1 {
+        // elements are stored column by column
+        let mut v = Vec::with_capacity(rows * cols);
+        for i in 0..rows {
+            v.extend((0..cols).map(|j| vals[j * rows + i].clone()));
+        }
+        Ok(Matrix::from_vec(v, rows, cols))
+    } else {
+        Ok(Matrix::from_vec(vals, rows, cols))
+    }
+}
+
+/// Loads a matrix from a `.npy` file (see [`read_npy`](fn.read_npy.html)).
+pub fn load_npy<T: NpyType>(fname: &str) -> Result<Matrix<T>, String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_npy(f)
+}
+
+/// Iterator over chunks of rows of a `.npy` file which is read
+/// incrementally, i.e. only one chunk is held in memory at any time. This
+/// allows to process arrays which are larger than the available memory
+/// (e.g. with the [out-of-core training](../../stream/index.html)).
+///
+/// The array must be stored in C order (or have only one column).
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::io::npy::*;
+///
+/// # fn main() {
+/// let fname = std::env::temp_dir().join("rustml_doc_npy_chunks.npy");
+/// let fname = fname.to_str().unwrap();
+/// save_npy(fname, &mat![1.0, 2.0; 3.0, 4.0; 5.0, 6.0]).unwrap();
+///
+/// let mut c = NpyChunks::open(fname, 2).unwrap();
+/// assert_eq!(c.shape(), (3, 2));
+/// assert_eq!(c.next().unwrap().unwrap(), mat![1.0, 2.0; 3.0, 4.0]);
+/// assert_eq!(c.next().unwrap().unwrap(), mat![5.0, 6.0]);
+/// assert!(c.next().is_none());
+/// # }
+/// ```
+pub struct NpyChunks {
+    reader: BufReader<File>,
+    header: Header,
+    data_start: u64,
+    rows: usize,
+    cols: usize,
+    chunk_rows: usize,
+    pos: usize
+}
+
+impl NpyChunks {
+
+    /// Opens a `.npy` file which is read in chunks of at most `chunk_rows`
+    /// rows.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn open(fname: &str, chunk_rows: usize) -> Result<NpyChunks, String> {
+
+        assert!(chunk_rows > 0, "Number of rows per chunk must be greater than zero.");
+        let mut reader = BufReader::new(
+            try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e))));
+
+        let mut prefix = [0u8; 12];
+        try!(reader.read_exact(&mut prefix).map_err(|_| "Header is truncated.".to_string()));
+        let (start, len) = try!(header_bounds(&prefix));
+        let mut dict = vec![0u8; start + len];
+        dict[..12].copy_from_slice(&prefix);
+        try!(reader.read_exact(&mut dict[12..]).map_err(|_| "Header is truncated.".to_string()));
+        let dict = try!(String::from_utf8(dict[start..].to_vec()).map_err(|_| "Invalid header.".to_string()));
+
+        let h = try!(parse_header(&dict));
+        let (rows, cols) = try!(matrix_shape(&h));
+        if h.fortran_order && rows > 1 && cols > 1 {
+            return Err("Arrays in Fortran order cannot be read in chunks.".to_string());
+        }
+        Ok(NpyChunks {
+            reader: reader,
+            header: h,
+            data_start: (start + len) as u64,
+            rows: rows,
+            cols: cols,
+            chunk_rows: chunk_rows,
+            pos: 0
+        })
+    }
+
+    /// Returns the number of rows and columns of the array.
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// Starts reading again at the first row.
+    pub fn rewind(&mut self) -> Result<(), String> {
+
+        try!(self.reader.seek(SeekFrom::Start(self.data_start)).map_err(|e| format!("Could not seek: {}", e)));
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl Iterator for NpyChunks {
+    type Item = Result<Matrix<f64>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.pos >= self.rows {
+            return None;
+        }
+        let n = self.chunk_rows.min(self.rows - self.pos);
+        let mut buf = vec![0u8; n * self.cols * self.header.size];
+        if let Err(e) = self.reader.read_exact(&mut buf) {
+            self.pos = self.rows;
+            return Some(Err(format!("Could not read data: {}", e)));
+        }
+        self.pos += n;
+        let h = &self.header;
+        Some(Ok(Matrix::from_vec(buf.chunks(h.size).map(|b| decode(b, h)).collect(), n, self.cols)))
+    }
+}
+
+fn npy_bytes<T: NpyType>(m: &Matrix<T>) -> Vec<u8> {
+
+    let mut dict = format!("{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        T::descr(), m.rows(), m.cols());
+    // the data must be aligned to 64 bytes and the header must end with a
+    // line break
+    while (MAGIC.len() + 4 + dict.len() + 1) % 64 != 0 {
+        dict.push(' ');
+    }
+    dict.push('\n');
+
+    let mut buf = Vec::with_capacity(MAGIC.len() + 4 + dict.len() + 8 * m.rows() * m.cols());
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&[1, 0]);
+    buf.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    buf.extend_from_slice(dict.as_bytes());
+    for x in m.iter() {
+        x.append_le(&mut buf);
+    }
+    buf
+}
+
+/// Writes a matrix in the `.npy` format to a writer.
+pub fn write_npy<T: NpyType, W: Write>(mut writer: W, m: &Matrix<T>) -> Result<(), String> {
+    writer.write_all(&npy_bytes(m)).map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Saves a matrix into a `.npy` file.
+pub fn save_npy<T: NpyType>(fname: &str, m: &Matrix<T>) -> Result<(), String> {
+
+    let f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    write_npy(f, m)
+}
+
+// ----------------------------------------------------------------------------
+
+/// CRC-32 checksum (IEEE polynomial) as used by the zip format.
+fn crc32(data: &[u8]) -> u32 {
+
+    let mut crc = 0xFFFFFFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn u16_at(b: &[u8], pos: usize) -> usize {
+    u16::from_le_bytes([b[pos], b[pos + 1]]) as usize
+}
+
+fn u32_at(b: &[u8], pos: usize) -> usize {
+    u32::from_le_bytes([b[pos], b[pos + 1], b[pos + 2], b[pos + 3]]) as usize
+}
+
+/// Reads all arrays from a `.npz` file given as a reader.
+///
+/// Returns the names of the arrays (without the extension `.npy`) together
+/// with the matrices in the order in which they are stored in the archive.
+pub fn read_npz<T: NpyType, R: Read>(mut reader: R) -> Result<Vec<(String, Matrix<T>)>, String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+
+    // end of central directory record
+    let eocd = try!((0..buf.len().saturating_sub(21)).rev()
+        .find(|&i| &buf[i..i + 4] == b"PK\x05\x06")
+        .ok_or("Not a zip archive.".to_string()));
+    let entries = u16_at(&buf, eocd + 10);
+    let mut pos = u32_at(&buf, eocd + 16);
+
+    let mut r = Vec::with_capacity(entries);
+    for _ in 0..entries {
+        if pos + 46 > buf.len() || &buf[pos..pos + 4] != b"PK\x01\x02" {
+            return Err("Invalid central directory.".to_string());
+        }
+        let method = u16_at(&buf, pos + 10);
+        let crc = u32_at(&buf, pos + 16) as u32;
+        let csize = u32_at(&buf, pos + 20);
+        let name_len = u16_at(&buf, pos + 28);
+        let extra_len = u16_at(&buf, pos + 30);
+        let comment_len = u16_at(&buf, pos + 32);
+        let offset = u32_at(&buf, pos + 42);
+        let end = pos + 46 + name_len + extra_len + comment_len;
+        if end > buf.len() {
+            return Err("Invalid central directory.".to_string());
+        }
+        let name = String::from_utf8_lossy(&buf[pos + 46..pos + 46 + name_len]).into_owned();
+        pos = end;
+
+        if offset.saturating_add(30) > buf.len() || &buf[offset..offset + 4] != b"PK\x03\x04" {
+            return Err(format!("Invalid local header of {}.", name));
+        }
+        let start = offset + 30 + u16_at(&buf, offset + 26) + u16_at(&buf, offset + 28);
+        if start.saturating_add(csize) > buf.len() {
+            return Err(format!("Data of {} is truncated.", name));
+        }
+        let raw = &buf[start..start + csize];
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => {
+                let mut v = Vec::new();
+                try!(DeflateDecoder::new(raw).read_to_end(&mut v)
+                    .map_err(|e| format!("Could not decompress {}: {}", name, e)));
+                v
+            },
+            m => return Err(format!("Unsupported compression method {} of {}.", m, name))
+        };
+        if crc32(&data) != crc {
+            return Err(format!("Checksum of {} does not match.", name));
+        }
+        let m = try!(parse_npy(&data).map_err(|e| format!("{}: {}", name, e)));
+        let name = if name.ends_with(".npy") { name[..name.len() - 4].to_string() } else { name };
+        r.push((name, m));
+    }
+    Ok(r)
+}
+
+/// Loads all arrays from a `.npz` file (see [`read_npz`](fn.read_npz.html)).
+pub fn load_npz<T: NpyType>(fname: &str) -> Result<Vec<(String, Matrix<T>)>, String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_npz(f)
+}
+
+/// Writes named matrices as an uncompressed `.npz` archive to a writer. The
+/// arrays can be accessed in Python via their names, e.g.
+/// `numpy.load("data.npz")["x"]`.
+pub fn write_npz<T: NpyType, W: Write>(mut writer: W, arrays: &[(&str, &Matrix<T>)]) -> Result<(), String> {
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for &(name, m) in arrays {
+        let name = format!("{}.npy", name);
+        let data = npy_bytes(m);
+        let crc = crc32(&data);
+        let offset = out.len() as u32;
+
+        // local file header: version 2.0, no flags, stored, date 1980-01-01
+        out.extend_from_slice(b"PK\x03\x04");
+        out.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&data);
+
+        central.extend_from_slice(b"PK\x01\x02");
+        central.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // extra field, comment, disk number, attributes
+        central.extend_from_slice(&[0; 12]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let cd_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(b"PK\x05\x06");
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&cd_offset.to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+
+    writer.write_all(&out).map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Saves named matrices into an uncompressed `.npz` file (see
+/// [`write_npz`](fn.write_npz.html)).
+pub fn save_npz<T: NpyType>(fname: &str, arrays: &[(&str, &Matrix<T>)]) -> Result<(), String> {
+
+    let f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    write_npz(f, arrays)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    fn npy(dict: &str, data: &[u8]) -> Vec<u8> {
+
+        let mut v = MAGIC.to_vec();
+        v.extend_from_slice(&[1, 0]);
+        v.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+        v.extend_from_slice(dict.as_bytes());
+        v.extend_from_slice(data);
+        v
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_write_npy_header() {
+
+        let v = npy_bytes(&mat![1.0f32, 2.0]);
+        assert_eq!(&v[..8], b"\x93NUMPY\x01\x00");
+        let len = u16_at(&v, 8);
+        assert_eq!((10 + len) % 64, 0);
+        let dict = String::from_utf8(v[10..10 + len].to_vec()).unwrap();
+        assert!(dict.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (1, 2), }"));
+        assert!(dict.ends_with("\n"));
+        assert_eq!(v.len(), 10 + len + 8);
+    }
+
+    #[test]
+    fn test_read_npy() {
+
+        // big endian 16 bit integers in Fortran order
+        let b = npy("{'descr': '>i2', 'fortran_order': True, 'shape': (2, 3), }\n",
+            &[0, 1, 0, 4, 0, 2, 0, 5, 0, 3, 255, 250]);
+        assert_eq!(read_npy::<f64, _>(&b[..]).unwrap(), mat![1.0, 2.0, 3.0; 4.0, 5.0, -6.0]);
+
+        // one-dimensional array of unsigned bytes
+        let b = npy("{'descr': '|u1', 'fortran_order': False, 'shape': (3,), }\n", &[1, 2, 255]);
+        assert_eq!(read_npy::<f32, _>(&b[..]).unwrap(), mat![1.0; 2.0; 255.0]);
+
+        let m = mat![1.5f32, -2.25; 1e-30, 3.0];
+        let mut v = Vec::new();
+        write_npy(&mut v, &m).unwrap();
+        assert_eq!(read_npy::<f32, _>(&v[..]).unwrap(), m);
+        assert_eq!(read_npy::<f64, _>(&v[..]).unwrap().get(1, 0), Some(&(1e-30f32 as f64)));
+    }
+
+    #[test]
+    fn test_read_npy_errors() {
+
+        assert!(read_npy::<f64, _>(&b"NUMPY"[..]).is_err());
+        let b = npy("{'descr': '<c16', 'fortran_order': False, 'shape': (1,), }\n", &[0; 16]);
+        assert!(read_npy::<f64, _>(&b[..]).is_err());
+        let b = npy("{'descr': '<f8', 'fortran_order': False, 'shape': (1, 1, 1), }\n", &[0; 8]);
+        assert!(read_npy::<f64, _>(&b[..]).is_err());
+        let b = npy("{'descr': '<f8', 'fortran_order': False, 'shape': (2,), }\n", &[0; 8]);
+        assert!(read_npy::<f64, _>(&b[..]).is_err());
+    }
+
+    #[test]
+    fn test_npz() {
+
+        let a = mat![1.0, 2.0; 3.0, 4.0];
+        let b = mat![-1.0, 0.5, 2.0];
+
+        let path = ::std::env::temp_dir().join("rustml_test.npz");
+        let fname = path.to_str().unwrap();
+        save_npz(fname, &[("a", &a), ("b", &b)]).unwrap();
+
+        let r = load_npz::<f64>(fname).unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0], ("a".to_string(), a));
+        assert_eq!(r[1], ("b".to_string(), b));
+
+        assert!(read_npz::<f64, _>(&b"PK\x03\x04"[..]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_npz() {
+
+        let mut buf = Vec::new();
+        write_npz(&mut buf, &[("a", &mat![1.0, 2.0; 3.0, 4.0])]).unwrap();
+        let cd = (0..buf.len() - 3).rev().find(|&i| &buf[i..i + 4] == b"PK\x01\x02").unwrap();
+
+        // file name, extra field or comment beyond the end of the data
+        for &(k, v) in &[(28, 0xFF), (30, 0xFF), (32, 0xFF)] {
+            let mut b = buf.clone();
+            b[cd + k] = v;
+            assert!(read_npz::<f64, _>(&b[..]).is_err());
+        }
+        // local header offset beyond the end of the data
+        let mut b = buf.clone();
+        b[cd + 45] = 0xFF;
+        assert!(read_npz::<f64, _>(&b[..]).is_err());
+
+        // corrupted bytes must not lead to a panic
+        for i in 0..buf.len() {
+            let mut b = buf.clone();
+            b[i] ^= 0xFF;
+            let _ = read_npz::<f64, _>(&b[..]);
+        }
+        assert!(read_npy::<f64, _>(&b"\x93NUMPY\x01\x00\x40\x00{'descr': '<f8', 'fortran_order': False, 'shape': (4294967296, 4294967296), }"[..]).is_err());
+    }
+
+    #[test]
+    fn test_load_compressed_npz() {
+
+        // created with Python's zipfile module (deflate, zip64 extensions)
+        let r = load_npz::<f64>("datasets/testing/arrays.npz").unwrap();
+        assert_eq!(r[0], ("x".to_string(), mat![1.0, 2.0; 3.0, 4.0]));
+        assert_eq!(r[1], ("y".to_string(), mat![0.5; -1.0; 2.0]));
+    }
+}
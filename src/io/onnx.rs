@@ -0,0 +1,763 @@
+//! Export and import of neural networks in the ONNX format.
+//!
+//! [ONNX](https://onnx.ai/) is an open format to represent machine learning
+//! models which is supported by many runtimes (e.g. ONNX Runtime). A
+//! [NeuralNetwork](../../nn/struct.NeuralNetwork.html) is exported as a graph
+//! with one input `input` of shape `[N, input_size]` and one output `output`
+//! of shape `[N, output_size]` where `N` is the number of observations. Each
+//! layer is represented by a `MatMul` node, an `Add` node for the bias unit
+//! (except for the first layer which has no bias unit) and a node of the
+//! activation function of the layer (`Sigmoid`, `Tanh`, `Relu` or
+//! `LeakyRelu`) or a `Softmax` node if the output layer computes the
+//! softmax.
+//! The parameters are stored as 32-bit floats, which is the type that is
+//! supported by all runtimes.
+//!
+//! Conversely, simple feed-forward graphs can be imported into a
+//! `NeuralNetwork` for inference with
+//! [from_onnx](../../nn/struct.NeuralNetwork.html#method.from_onnx). The graph
+//! must be a chain of layers from the input to the output where each layer
+//! consists of a `MatMul` node optionally followed by an `Add` node, or of a
+//! `Gemm` node, followed by one of the activation nodes above (`Softmax`
+//! only in the last layer). Parameters of the types `float` and `double`
+//! are supported.
+//!
+//! **Restriction:** a `NeuralNetwork` has no bias unit in the input layer.
+//! Hence, the bias of the first layer must be zero or absent, otherwise the
+//! import fails. Models which are exported by other frameworks usually have
+//! a bias in each layer (e.g. `torch.nn.Linear` or `keras.layers.Dense`
+//! with the default arguments) and can only be imported if the first layer
+//! has been created without a bias (e.g. `bias=False` or
+//! `use_bias=False`).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rustml::nn::NeuralNetwork;
+//!
+//! let n = NeuralNetwork::new().add_layer(3).add_layer(5).add_layer(1);
+//! n.to_onnx("/tmp/model.onnx").unwrap();
+//!
+//! let m = NeuralNetwork::from_onnx("/tmp/model.onnx").unwrap();
+//! assert_eq!(m.layers(), 3);
+//! ```
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use matrix::Matrix;
+use nn::{NeuralNetwork, Activation, Sigmoid, Tanh, Relu, LeakyRelu};
+
+const IR_VERSION: u64 = 7;
+const OPSET_VERSION: u64 = 13;
+const TENSOR_FLOAT: u64 = 1;
+const TENSOR_DOUBLE: u64 = 11;
+
+// wire types of protocol buffers
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LEN: u32 = 2;
+const FIXED32: u32 = 5;
+
+/// Minimal encoder for protocol buffers messages.
+struct Message {
+    buf: Vec<u8>
+}
+
+impl Message {
+
+    fn new() -> Message {
+        Message { buf: vec![] }
+    }
+
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint((field << 3 | wire_type) as u64);
+    }
+
+    fn int(&mut self, field: u32, v: u64) -> &mut Message {
+        self.key(field, VARINT);
+        self.varint(v);
+        self
+    }
+
+    fn bytes(&mut self, field: u32, v: &[u8]) -> &mut Message {
+        self.key(field, LEN);
+        self.varint(v.len() as u64);
+        self.buf.extend_from_slice(v);
+        self
+    }
+
+    fn string(&mut self, field: u32, v: &str) -> &mut Message {
+        self.bytes(field, v.as_bytes())
+    }
+
+    fn message(&mut self, field: u32, m: &Message) -> &mut Message {
+        self.bytes(field, &m.buf)
+    }
+
+    fn float(&mut self, field: u32, v: f32) -> &mut Message {
+        self.key(field, FIXED32);
+        self.buf.extend_from_slice(&v.to_bits().to_le_bytes());
+        self
+    }
+}
+
+/// Returns a `ValueInfoProto` of a float tensor with a variable number of
+/// rows.
+fn value_info(name: &str, cols: usize) -> Message {
+
+    let mut batch = Message::new();
+    batch.string(2, "N");
+    let mut dim = Message::new();
+    dim.int(1, cols as u64);
+    let mut shape = Message::new();
+    shape.message(1, &batch).message(1, &dim);
+
+    let mut tensor = Message::new();
+    tensor.int(1, TENSOR_FLOAT).message(2, &shape);
+    let mut ty = Message::new();
+    ty.message(1, &tensor);
+
+    let mut m = Message::new();
+    m.string(1, name).message(2, &ty);
+    m
+}
+
+/// Returns a `TensorProto` with the given dimensions and values.
+fn tensor(name: &str, dims: &[usize], values: &[f64]) -> Message {
+
+    let mut m = Message::new();
+    for &d in dims {
+        m.int(1, d as u64);
+    }
+    let raw: Vec<u8> = values.iter().flat_map(|&x| (x as f32).to_bits().to_le_bytes().to_vec()).collect();
+    m.int(2, TENSOR_FLOAT).string(8, name).bytes(9, &raw);
+    m
+}
+
+fn node(op: &str, inputs: &[&str], output: &str) -> Message {
+
+    let mut m = Message::new();
+    for i in inputs {
+        m.string(1, i);
+    }
+    m.string(2, output).string(3, output).string(4, op);
+    m
+}
+
+/// Returns the node which applies the activation function to `input`.
+///
+/// Panics if the function is not supported by ONNX.
+fn activation_node(a: &dyn Activation, input: &str, output: &str) -> Message {
+
+    let op = match a.name() {
+        "sigmoid" => "Sigmoid",
+        "tanh" => "Tanh",
+        "relu" => "Relu",
+        "leaky_relu" => "LeakyRelu",
+        name => panic!("Activation function {} is not supported.", name)
+    };
+    let mut m = node(op, &[input], output);
+    if op == "LeakyRelu" {
+        // attribute of type float
+        let mut alpha = Message::new();
+        alpha.string(1, "alpha").float(2, a.params()[0] as f32).int(20, 1);
+        m.message(5, &alpha);
+    }
+    m
+}
+
+/// Returns the serialized ONNX model (a `ModelProto`) of a neural network.
+///
+/// Panics if the network has less than two layers or if a layer uses an
+/// activation function which is not supported by ONNX.
+pub fn to_onnx_bytes(n: &NeuralNetwork) -> Vec<u8> {
+
+    assert!(n.layers() >= 2, "At least two layers are required.");
+
+    let mut graph = Message::new();
+    graph.string(2, "rustml");
+
+    let params = n.params();
+    let mut x = "input".to_string();
+    for (i, p) in params.iter().enumerate() {
+        // the first column contains the weights of the bias unit except for
+        // the first layer
+        let (bias, w) = if i == 0 {
+            (None, p.clone())
+        } else {
+            (Some(p.col(0).unwrap()), p.rm_column(0))
+        };
+
+        // MatMul computes x * W^T, i.e. the transpose of W is stored
+        let wt: Vec<f64> = w.col_iter().flat_map(|c| c).collect();
+        let wname = format!("W{}", i);
+        graph.message(5, &tensor(&wname, &[w.cols(), w.rows()], &wt));
+        let mut out = format!("matmul{}", i);
+        graph.message(1, &node("MatMul", &[&x, &wname], &out));
+
+        if let Some(b) = bias {
+            let bname = format!("B{}", i);
+            graph.message(5, &tensor(&bname, &[b.len()], &b));
+            let z = format!("add{}", i);
+            graph.message(1, &node("Add", &[&out, &bname], &z));
+            out = z;
+        }
+
+        let act = n.activation(i + 1);
+        x = if i + 1 == params.len() { "output".to_string() } else { format!("{}{}", act.name(), i) };
+        if n.is_softmax() && i + 1 == params.len() {
+            graph.message(1, &node("Softmax", &[&out], &x));
+        } else {
+            graph.message(1, &activation_node(act, &out, &x));
+        }
+    }
+    graph.message(11, &value_info("input", n.input_size()));
+    graph.message(12, &value_info("output", n.output_size()));
+
+    let mut opset = Message::new();
+    opset.string(1, "").int(2, OPSET_VERSION);
+    let mut model = Message::new();
+    model.int(1, IR_VERSION)
+        .string(2, "rustml")
+        .string(3, env!("CARGO_PKG_VERSION"))
+        .message(7, &graph)
+        .message(8, &opset);
+    model.buf
+}
+
+/// Writes the ONNX model of a neural network (see
+/// [to_onnx_bytes](fn.to_onnx_bytes.html)).
+pub fn write_onnx<W: Write>(mut writer: W, n: &NeuralNetwork) -> Result<(), String> {
+
+    writer.write_all(&to_onnx_bytes(n)).map_err(|e| format!("Could not write data: {}", e))
+}
+
+/// Value of a decoded protocol buffers field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value<'a> {
+    Int(u64),
+    Bytes(&'a [u8])
+}
+
+impl <'a> Value<'a> {
+
+    fn int(&self) -> u64 {
+        match *self { Value::Int(v) => v, Value::Bytes(_) => 0 }
+    }
+
+    fn bytes(&self) -> &'a [u8] {
+        match *self { Value::Int(_) => &[], Value::Bytes(b) => b }
+    }
+
+    fn string(&self) -> String {
+        String::from_utf8_lossy(self.bytes()).into_owned()
+    }
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Result<u64, String> {
+
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let b = match buf.get(*pos) {
+            Some(&b) if shift < 64 => b,
+            _ => return Err("Invalid varint.".to_string())
+        };
+        *pos += 1;
+        v |= ((b & 0x7F) as u64) << shift;
+        shift += 7;
+        if b < 0x80 {
+            return Ok(v);
+        }
+    }
+}
+
+fn read_fixed(buf: &[u8], pos: &mut usize, n: usize) -> Result<u64, String> {
+
+    match buf.get(*pos..*pos + n) {
+        Some(b) => {
+            *pos += n;
+            Ok(b.iter().rev().fold(0, |acc, &x| acc << 8 | x as u64))
+        },
+        None => Err("Truncated message.".to_string())
+    }
+}
+
+/// Decodes the fields of a protocol buffers message.
+fn decode(buf: &[u8]) -> Result<Vec<(u32, Value<'_>)>, String> {
+
+    let mut pos = 0;
+    let mut r = vec![];
+    while pos < buf.len() {
+        let key = try!(read_varint(buf, &mut pos));
+        let field = (key >> 3) as u32;
+        let v = match (key & 7) as u32 {
+            VARINT => Value::Int(try!(read_varint(buf, &mut pos))),
+            FIXED64 => Value::Int(try!(read_fixed(buf, &mut pos, 8))),
+            FIXED32 => Value::Int(try!(read_fixed(buf, &mut pos, 4))),
+            LEN => {
+                let n = try!(read_varint(buf, &mut pos)) as usize;
+                match buf.get(pos..pos + n) {
+                    Some(b) => { pos += n; Value::Bytes(b) },
+                    None => return Err("Truncated message.".to_string())
+                }
+            },
+            t => return Err(format!("Unsupported wire type {}.", t))
+        };
+        r.push((field, v));
+    }
+    Ok(r)
+}
+
+fn first<'a>(fields: &[(u32, Value<'a>)], field: u32) -> Option<Value<'a>> {
+    fields.iter().find(|f| f.0 == field).map(|f| f.1)
+}
+
+fn all<'a>(fields: &[(u32, Value<'a>)], field: u32) -> Vec<Value<'a>> {
+    fields.iter().filter(|f| f.0 == field).map(|f| f.1).collect()
+}
+
+/// Returns the values of a repeated numeric field which is either packed
+/// or not. Each value is given as its bits.
+fn repeated(fields: &[(u32, Value)], field: u32, size: usize) -> Result<Vec<u64>, String> {
+
+    let mut r = vec![];
+    for v in all(fields, field) {
+        match v {
+            Value::Int(x) => r.push(x),
+            Value::Bytes(b) => {
+                let mut pos = 0;
+                while pos < b.len() {
+                    r.push(try!(if size == 0 { read_varint(b, &mut pos) } else { read_fixed(b, &mut pos, size) }));
+                }
+            }
+        }
+    }
+    Ok(r)
+}
+
+/// Decodes a `TensorProto` into its dimensions and values.
+fn parse_tensor(buf: &[u8]) -> Result<(String, Vec<usize>, Vec<f64>), String> {
+
+    let t = try!(decode(buf));
+    let name = first(&t, 8).map(|v| v.string()).unwrap_or(String::new());
+    let dims: Vec<usize> = try!(repeated(&t, 1, 0)).iter().map(|&d| d as usize).collect();
+    let raw = first(&t, 9).map(|v| v.bytes());
+    let size = match first(&t, 2).map(|v| v.int()) {
+        Some(TENSOR_FLOAT) => 4,
+        Some(TENSOR_DOUBLE) => 8,
+        _ => return Err(format!("Tensor {} has an unsupported data type.", name))
+    };
+    if raw.map(|r| r.len() % size != 0).unwrap_or(false) {
+        return Err(format!("Raw data of tensor {} has an invalid length.", name));
+    }
+
+    let values: Vec<f64> = match size {
+        4 => match raw {
+            Some(r) => r.chunks(4).map(|b| {
+                f32::from_bits(u32::from_le_bytes([b[0], b[1], b[2], b[3]])) as f64
+            }).collect(),
+            None => try!(repeated(&t, 4, 4)).iter().map(|&x| f32::from_bits(x as u32) as f64).collect()
+        },
+        _ => match raw {
+            Some(r) => r.chunks(8).map(|b| {
+                let mut v = [0u8; 8];
+                v.copy_from_slice(b);
+                f64::from_bits(u64::from_le_bytes(v))
+            }).collect(),
+            None => try!(repeated(&t, 10, 8)).iter().map(|&x| f64::from_bits(x)).collect()
+        }
+    };
+    if Some(values.len()) != dims.iter().fold(Some(1usize), |acc, &d| acc.and_then(|a| a.checked_mul(d))) {
+        return Err(format!("Tensor {} has an invalid number of values.", name));
+    }
+    Ok((name, dims, values))
+}
+
+/// A node of the graph.
+struct Node {
+    op: String,
+    inputs: Vec<String>,
+    output: String,
+    attributes: HashMap<String, f64>
+}
+
+fn parse_node(buf: &[u8]) -> Result<Node, String> {
+
+    let n = try!(decode(buf));
+    let mut attributes = HashMap::new();
+    for a in all(&n, 5) {
+        let a = try!(decode(a.bytes()));
+        let name = first(&a, 1).map(|v| v.string()).unwrap_or(String::new());
+        // attributes of type float (field 2) or int (field 3)
+        if let Some(f) = first(&a, 2) {
+            attributes.insert(name, f32::from_bits(f.int() as u32) as f64);
+        } else if let Some(i) = first(&a, 3) {
+            attributes.insert(name, i.int() as i64 as f64);
+        }
+    }
+    Ok(Node {
+        op: first(&n, 4).map(|v| v.string()).unwrap_or(String::new()),
+        inputs: all(&n, 1).iter().map(|v| v.string()).collect(),
+        output: first(&n, 2).map(|v| v.string()).unwrap_or(String::new()),
+        attributes: attributes
+    })
+}
+
+/// A weight matrix with one row per unit of the layer and the bias.
+type Layer = (Matrix<f64>, Option<Vec<f64>>);
+
+/// Imports a neural network from a serialized ONNX model (see the
+/// [module](index.html) description for the supported graphs).
+pub fn from_onnx_bytes(buf: &[u8]) -> Result<NeuralNetwork, String> {
+
+    let model = try!(decode(buf));
+    let graph = match first(&model, 7) {
+        Some(g) => try!(decode(g.bytes())),
+        None => return Err("Model contains no graph.".to_string())
+    };
+
+    let mut inits = HashMap::new();
+    for t in all(&graph, 5) {
+        let (name, dims, values) = try!(parse_tensor(t.bytes()));
+        inits.insert(name, (dims, values));
+    }
+    let nodes: Vec<Node> = try!(all(&graph, 1).iter().map(|n| parse_node(n.bytes())).collect());
+
+    // name of a ValueInfoProto
+    let name = |v: &Value| -> Result<String, String> {
+        let info = try!(decode(v.bytes()));
+        Ok(first(&info, 1).map(|n| n.string()).unwrap_or(String::new()))
+    };
+    let mut input = None;
+    for v in all(&graph, 11) {
+        let n = try!(name(&v));
+        if !inits.contains_key(&n) {
+            input = Some(n);
+            break;
+        }
+    }
+    let output = match first(&graph, 12) {
+        Some(v) => try!(name(&v)),
+        None => return Err("Graph has no output.".to_string())
+    };
+    let mut x = match input {
+        Some(i) => i,
+        None => return Err("Graph has no input.".to_string())
+    };
+
+    let matrix = |n: &str| -> Result<(usize, usize, Vec<f64>), String> {
+        match inits.get(n) {
+            Some(&(ref d, ref v)) if d.len() == 2 => Ok((d[0], d[1], v.clone())),
+            _ => Err(format!("Initializer {} not found or not a matrix.", n))
+        }
+    };
+    let vector = |n: &str| -> Result<Vec<f64>, String> {
+        match inits.get(n) {
+            Some(&(_, ref v)) => Ok(v.clone()),
+            None => Err(format!("Initializer {} not found.", n))
+        }
+    };
+
+    let mut layers: Vec<Layer> = vec![];
+    let mut activations = vec![];
+    let mut current: Option<Layer> = None;
+    let mut visited = vec![false; nodes.len()];
+    while x != output {
+        let node = match nodes.iter().position(|n| n.inputs.iter().any(|i| *i == x)) {
+            Some(i) if visited[i] => return Err(format!("Graph contains a cycle at {}.", x)),
+            Some(i) => { visited[i] = true; &nodes[i] },
+            None => return Err(format!("No node consumes {}.", x))
+        };
+        let other: Vec<&String> = node.inputs.iter().filter(|&i| *i != x).collect();
+
+        match (node.op.as_str(), current.is_some()) {
+            ("MatMul", false) if other.len() == 1 => {
+                // x * W where W has one column per unit
+                let (r, c, v) = try!(matrix(other[0]));
+                let w = Matrix::from_vec(v, r, c);
+                current = Some((Matrix::from_it(w.col_iter().flat_map(|c| c), r), None));
+            },
+            ("Gemm", false) if node.inputs[0] == x && node.inputs.len() >= 2 => {
+                let attr = |a: &str, d: f64| node.attributes.get(a).cloned().unwrap_or(d);
+                if attr("transA", 0.0) != 0.0 || attr("alpha", 1.0) != 1.0 || attr("beta", 1.0) != 1.0 {
+                    return Err("Unsupported attributes of Gemm.".to_string());
+                }
+                let (r, c, v) = try!(matrix(&node.inputs[1]));
+                let w = Matrix::from_vec(v, r, c);
+                let w = if attr("transB", 0.0) != 0.0 { w } else { Matrix::from_it(w.col_iter().flat_map(|c| c), r) };
+                let b = match node.inputs.get(2) {
+                    Some(b) if !b.is_empty() => Some(try!(vector(b))),
+                    _ => None
+                };
+                current = Some((w, b));
+            },
+            ("Add", true) if other.len() == 1 => {
+                let b = try!(vector(other[0]));
+                if let Some((w, None)) = current.take() {
+                    if b.len() != w.rows() {
+                        return Err(format!("Bias {} does not match the layer.", other[0]));
+                    }
+                    current = Some((w, Some(b)));
+                } else {
+                    return Err("Unexpected Add node.".to_string());
+                }
+            },
+            ("Softmax", true) if node.attributes.get("axis").map(|&a| a == -1.0 || a == 1.0).unwrap_or(true) => {
+                layers.push(current.take().unwrap());
+                activations.push((node.op.clone(), 0.0));
+            },
+            ("Sigmoid", true) | ("Tanh", true) | ("Relu", true) | ("LeakyRelu", true) => {
+                layers.push(current.take().unwrap());
+                // 0.01 is the default slope of LeakyRelu
+                activations.push((node.op.clone(), node.attributes.get("alpha").cloned().unwrap_or(0.01)));
+            },
+            (op, _) => return Err(format!("Unsupported node {} ({}).", node.output, op))
+        }
+        x = node.output.clone();
+    }
+    if current.is_some() || layers.is_empty() {
+        return Err("Graph must end with an activation node.".to_string());
+    }
+
+    let mut n = NeuralNetwork::new().add_layer(layers[0].0.cols());
+    for &(ref w, _) in &layers {
+        n = n.add_layer(w.rows());
+    }
+    for (i, (w, b)) in layers.into_iter().enumerate() {
+        let p = if i == 0 {
+            if b.map(|b| b.iter().any(|&x| x != 0.0)).unwrap_or(false) {
+                return Err("The first layer must not have a bias.".to_string());
+            }
+            w
+        } else {
+            let b = b.unwrap_or(vec![0.0; w.rows()]);
+            w.insert_column(0, &b)
+        };
+        if i > 0 && p.cols() != n.params()[i].cols() {
+            return Err("Dimensions of the layers do not match.".to_string());
+        }
+        n = n.set_params(i, p);
+    }
+    let last = activations.len() - 1;
+    for (i, (op, alpha)) in activations.into_iter().enumerate() {
+        n = match op.as_str() {
+            "Softmax" if i == last => n.set_softmax(true),
+            "Softmax" => return Err("Softmax is only supported in the last layer.".to_string()),
+            "Tanh" => n.set_activation(i + 1, Tanh),
+            "Relu" => n.set_activation(i + 1, Relu),
+            "LeakyRelu" => n.set_activation(i + 1, LeakyRelu(alpha)),
+            _ => n.set_activation(i + 1, Sigmoid)
+        };
+    }
+    Ok(n)
+}
+
+/// Imports a neural network from an ONNX model given as a reader.
+pub fn read_onnx<R: Read>(mut reader: R) -> Result<NeuralNetwork, String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+    from_onnx_bytes(&buf)
+}
+
+impl NeuralNetwork {
+
+    /// Loads a network for inference from an ONNX model in the file
+    /// `path`. See the module [onnx](../io/onnx/index.html) for the
+    /// supported graphs.
+    pub fn from_onnx(path: &str) -> Result<NeuralNetwork, String> {
+
+        let f = try!(File::open(path).map_err(|e| format!("Could not open file {}: {}", path, e)));
+        read_onnx(f)
+    }
+
+    /// Saves the network as ONNX model into the file `path` so that it can
+    /// be used by standard runtimes. See the module [onnx](../io/onnx/index.html)
+    /// for a description of the graph.
+    ///
+    /// Panics if the network has less than two layers.
+    pub fn to_onnx(&self, path: &str) -> Result<(), String> {
+
+        let f = try!(File::create(path).map_err(|e| format!("Could not create file {}: {}", path, e)));
+        write_onnx(f, self)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    fn strings(fields: &[(u32, Value)], field: u32) -> Vec<String> {
+        all(fields, field).iter().map(|v| v.string()).collect()
+    }
+
+    #[test]
+    fn test_message() {
+
+        let mut m = Message::new();
+        m.int(1, 300).string(2, "ab");
+        assert_eq!(m.buf, vec![0x08, 0xAC, 0x02, 0x12, 2, b'a', b'b']);
+    }
+
+    #[test]
+    fn test_to_onnx_bytes() {
+
+        let n = NeuralNetwork::new()
+            .add_layer(3)
+            .add_layer(2)
+            .add_layer(1)
+            .set_params(0, mat![0.1, 0.2, 0.4; 0.2, 0.1, 2.0])
+            .set_params(1, mat![0.8, 1.2, 0.6]);
+
+        let buf = to_onnx_bytes(&n);
+        let model = decode(&buf).unwrap();
+        assert_eq!(model[0], (1, Value::Int(IR_VERSION)));
+        let graph = decode(first(&model, 7).unwrap().bytes()).unwrap();
+
+        let nodes: Vec<_> = all(&graph, 1).iter().map(|v| decode(v.bytes()).unwrap()).collect();
+        let ops: Vec<String> = nodes.iter().map(|n| strings(n, 4)[0].clone()).collect();
+        assert_eq!(ops, vec!["MatMul", "Sigmoid", "MatMul", "Add", "Sigmoid"]);
+        assert_eq!(strings(&nodes[0], 1), vec!["input", "W0"]);
+        assert_eq!(strings(&nodes[4], 2), vec!["output"]);
+
+        // W0 is the transpose of the parameters of the first layer
+        let inits: Vec<_> = all(&graph, 5).iter().map(|v| parse_tensor(v.bytes()).unwrap()).collect();
+        assert_eq!(inits.len(), 3);
+        assert_eq!(inits[0].1, vec![3, 2]);
+        let w: Vec<f32> = inits[0].2.iter().map(|&x| x as f32).collect();
+        assert_eq!(w, vec![0.1, 0.2, 0.2, 0.1, 0.4, 2.0]);
+        assert_eq!(inits[1].0, "W1");
+        assert_eq!(inits[2].0, "B1");
+
+        let path = ::std::env::temp_dir().join("rustml_test_model.onnx");
+        n.to_onnx(path.to_str().unwrap()).unwrap();
+        assert_eq!(::std::fs::metadata(&path).unwrap().len() as usize, buf.len());
+    }
+
+    #[test]
+    fn test_from_onnx() {
+
+        let n = NeuralNetwork::new()
+            .add_layer(3)
+            .add_layer(2)
+            .add_layer(3)
+            .set_params(0, mat![0.1, 0.2, 0.4; 0.2, 0.1, 2.0])
+            .set_params(1, mat![0.8, 1.2, 0.6; 0.4, 0.5, 0.8; 1.4, 1.5, 2.0]);
+
+        let path = ::std::env::temp_dir().join("rustml_test_import.onnx");
+        n.to_onnx(path.to_str().unwrap()).unwrap();
+        let m = NeuralNetwork::from_onnx(path.to_str().unwrap()).unwrap();
+        assert_eq!(m.layers(), 3);
+        for (a, b) in m.params().iter().zip(n.params().iter()) {
+            assert!(a.similar(b, 1e-6));
+        }
+        let x = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0];
+        assert!(m.predict(&x).similar(&n.predict(&x), 1e-6));
+
+        // the activation functions are exported and imported
+        let n = n.set_activation(1, LeakyRelu(0.25)).set_activation(2, Tanh);
+        let m = from_onnx_bytes(&to_onnx_bytes(&n)).unwrap();
+        assert!(!m.is_softmax());
+        assert_eq!(m.activation(1).name(), "leaky_relu");
+        assert_eq!(m.activation(1).params(), vec![0.25]);
+        assert_eq!(m.activation(2).name(), "tanh");
+        let x = mat![-0.5, 1.2, -1.5; 0.3, -1.1, 1.0];
+        assert!(m.predict(&x).similar(&n.predict(&x), 1e-6));
+
+        let n = n.set_softmax(true);
+        let m = from_onnx_bytes(&to_onnx_bytes(&n)).unwrap();
+        assert!(m.is_softmax());
+        assert!(m.predict(&x).similar(&n.predict(&x), 1e-6));
+
+        assert!(from_onnx_bytes(&[0x08]).is_err());
+        assert!(NeuralNetwork::from_onnx("datasets/testing/nonexisting.onnx").is_err());
+    }
+
+    fn double_tensor(name: &str, dims: &[usize], values: &[f64]) -> Message {
+
+        let mut packed = Message::new();
+        for v in values {
+            packed.buf.extend_from_slice(&v.to_bits().to_le_bytes());
+        }
+        let mut m = Message::new();
+        for &d in dims {
+            m.int(1, d as u64);
+        }
+        m.int(2, TENSOR_DOUBLE).string(8, name).message(10, &packed);
+        m
+    }
+
+    fn gemm_model(bias0: f64) -> Vec<u8> {
+
+        let mut trans = Message::new();
+        trans.string(1, "transB").int(3, 1).int(20, 2);
+        let mut gemm = node("Gemm", &["x", "W", "B"], "h");
+        gemm.message(5, &trans);
+
+        let mut graph = Message::new();
+        graph.message(1, &gemm)
+            .message(1, &node("Sigmoid", &["h"], "y"))
+            .message(5, &double_tensor("W", &[2, 2], &[1.0, 2.0, 3.0, 4.0]))
+            .message(5, &double_tensor("B", &[2], &[bias0, 0.0]))
+            .message(11, &value_info("x", 2))
+            .message(12, &value_info("y", 2));
+        let mut model = Message::new();
+        model.int(1, IR_VERSION).message(7, &graph);
+        model.buf
+    }
+
+    #[test]
+    fn test_from_onnx_gemm() {
+
+        // Gemm with transB = 1 stores the weights like NeuralNetwork
+        let n = from_onnx_bytes(&gemm_model(0.0)).unwrap();
+        assert_eq!(n.params(), vec![mat![1.0, 2.0; 3.0, 4.0]]);
+
+        assert!(from_onnx_bytes(&gemm_model(0.5)).is_err());
+    }
+
+    #[test]
+    fn test_parse_tensor_errors() {
+
+        // raw data which is not a multiple of the size of the data type
+        let mut m = Message::new();
+        m.int(1, 1).int(2, TENSOR_FLOAT).string(8, "t").bytes(9, &[0, 0, 0]);
+        assert!(parse_tensor(&m.buf).is_err());
+        let mut m = Message::new();
+        m.int(1, 1).int(2, TENSOR_DOUBLE).string(8, "t").bytes(9, &[0; 12]);
+        assert!(parse_tensor(&m.buf).is_err());
+
+        // the product of the dimensions overflows
+        let m = double_tensor("t", &[1 << 40, 1 << 40, 0], &[]);
+        assert!(parse_tensor(&m.buf).is_err());
+        let m = double_tensor("t", &[2, 1], &[1.0, 2.0]);
+        assert_eq!(parse_tensor(&m.buf).unwrap().2, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_from_onnx_cycle() {
+
+        let mut graph = Message::new();
+        graph.message(1, &node("MatMul", &["x", "W"], "h"))
+            .message(1, &node("Sigmoid", &["h"], "x"))
+            .message(5, &double_tensor("W", &[2, 2], &[1.0, 2.0, 3.0, 4.0]))
+            .message(11, &value_info("x", 2))
+            .message(12, &value_info("y", 2));
+        let mut model = Message::new();
+        model.int(1, IR_VERSION).message(7, &graph);
+        assert!(from_onnx_bytes(&model.buf).unwrap_err().contains("cycle"));
+    }
+}
@@ -0,0 +1,750 @@
+//! Reading of numeric columns from Apache Parquet files.
+//!
+//! This module is only available if rustml is compiled with the feature
+//! `parquet`. Like the module [arrow](../arrow/index.html) it loads columns
+//! of integers, floating point numbers and booleans into a matrix of `f64`
+//! with one column per selected column of the file. Booleans are converted
+//! into `0` and `1` and null values into `NaN`. Columns of other types (e.g.
+//! strings) and nested columns are skipped unless they are explicitly
+//! requested, which is an error. All row groups of a file are concatenated.
+//!
+//! The following features of the format are supported:
+//!
+//! * the physical types `BOOLEAN`, `INT32`, `INT64`, `FLOAT` and `DOUBLE`
+//!   of required and optional top-level columns,
+//! * the compression codecs `UNCOMPRESSED`, `SNAPPY` and `GZIP`,
+//! * data pages of version 1 and 2 and dictionary pages,
+//! * the encodings `PLAIN`, `PLAIN_DICTIONARY`, `RLE_DICTIONARY`, `RLE`
+//!   (booleans), `DELTA_BINARY_PACKED` (integers) and `BYTE_STREAM_SPLIT`.
+//!
+//! Files which use other codecs (e.g. `ZSTD`) or encodings for a selected
+//! column cannot be read.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rustml::io::parquet::*;
+//!
+//! let (names, x) = load_parquet("data.parquet", &["age", "income"]).unwrap();
+//! assert_eq!(names.len(), x.cols());
+//! ```
+extern crate flate2;
+
+use std::f64;
+use std::fs::File;
+use std::io::Read;
+use self::flate2::read::GzDecoder;
+
+use matrix::Matrix;
+use sparse::SparseMatrix;
+
+const MAGIC: &'static [u8; 4] = b"PAR1";
+
+// physical types
+const TYPE_BOOLEAN: i64 = 0;
+const TYPE_INT32: i64 = 1;
+const TYPE_INT64: i64 = 2;
+const TYPE_FLOAT: i64 = 4;
+const TYPE_DOUBLE: i64 = 5;
+
+// repetition types
+const OPTIONAL: i64 = 1;
+const REPEATED: i64 = 2;
+
+// compression codecs
+const UNCOMPRESSED: i64 = 0;
+const SNAPPY: i64 = 1;
+const GZIP: i64 = 2;
+
+// page types
+const DATA_PAGE: i64 = 0;
+const DICTIONARY_PAGE: i64 = 2;
+const DATA_PAGE_V2: i64 = 3;
+
+// encodings
+const PLAIN: i64 = 0;
+const PLAIN_DICTIONARY: i64 = 2;
+const RLE: i64 = 3;
+const DELTA_BINARY_PACKED: i64 = 5;
+const RLE_DICTIONARY: i64 = 8;
+const BYTE_STREAM_SPLIT: i64 = 9;
+
+// maximum nesting of thrift structures
+const MAX_DEPTH: usize = 32;
+
+/// Value of a field of a thrift structure.
+#[derive(Clone, Debug, PartialEq)]
+enum Value<'a> {
+    Int(i64),
+    Double(f64),
+    Bytes(&'a [u8]),
+    List(Vec<Value<'a>>),
+    Struct(Vec<(i16, Value<'a>)>)
+}
+
+impl <'a> Value<'a> {
+
+    fn int(&self) -> Option<i64> {
+        match *self { Value::Int(v) => Some(v), _ => None }
+    }
+
+    fn string(&self) -> String {
+        match *self { Value::Bytes(b) => String::from_utf8_lossy(b).into_owned(), _ => String::new() }
+    }
+
+    fn list(&self) -> &[Value<'a>] {
+        match *self { Value::List(ref v) => v, _ => &[] }
+    }
+
+    fn fields(&self) -> &[(i16, Value<'a>)] {
+        match *self { Value::Struct(ref v) => v, _ => &[] }
+    }
+
+    /// Returns the field with the id `id` of a structure.
+    fn get(&self, id: i16) -> Option<&Value<'a>> {
+        self.fields().iter().find(|f| f.0 == id).map(|f| &f.1)
+    }
+
+    fn get_int(&self, id: i16) -> Option<i64> {
+        self.get(id).and_then(|v| v.int())
+    }
+}
+
+/// Decoder for the thrift compact protocol which is used for the metadata.
+struct Thrift<'a> {
+    buf: &'a [u8],
+    pos: usize
+}
+
+impl <'a> Thrift<'a> {
+
+    fn new(buf: &'a [u8]) -> Thrift<'a> {
+        Thrift { buf: buf, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        match self.buf.get(self.pos) {
+            Some(&b) => { self.pos += 1; Ok(b) },
+            None => Err("Truncated metadata.".to_string())
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        match self.buf.get(self.pos..self.pos.saturating_add(n)) {
+            Some(b) => { self.pos += n; Ok(b) },
+            None => Err("Truncated metadata.".to_string())
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        varint(self.buf, &mut self.pos)
+    }
+
+    fn zigzag(&mut self) -> Result<i64, String> {
+        let v = try!(self.varint());
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn value(&mut self, ty: u8, depth: usize) -> Result<Value<'a>, String> {
+
+        if depth > MAX_DEPTH {
+            return Err("Metadata is nested too deeply.".to_string());
+        }
+        Ok(match ty {
+            1 => Value::Int(1),
+            2 => Value::Int(0),
+            3 => Value::Int(try!(self.byte()) as i8 as i64),
+            4 | 5 | 6 => Value::Int(try!(self.zigzag())),
+            7 => {
+                let b = try!(self.take(8));
+                Value::Double(f64::from_bits(b.iter().rev().fold(0, |acc, &x| acc << 8 | x as u64)))
+            },
+            8 => {
+                let n = try!(self.varint()) as usize;
+                Value::Bytes(try!(self.take(n)))
+            },
+            9 | 10 => {
+                let h = try!(self.byte());
+                let n = if h >> 4 == 15 { try!(self.varint()) as usize } else { (h >> 4) as usize };
+                let mut v = Vec::new();
+                for _ in 0..n {
+                    // booleans in lists are stored in one byte
+                    v.push(match h & 15 {
+                        1 | 2 => Value::Int((try!(self.byte()) == 1) as i64),
+                        t => try!(self.value(t, depth + 1))
+                    });
+                }
+                Value::List(v)
+            },
+            11 => {
+                // maps are not used by the supported fields and skipped
+                let n = try!(self.varint());
+                if n > 0 {
+                    let kv = try!(self.byte());
+                    for _ in 0..n {
+                        try!(self.value(kv >> 4, depth + 1));
+                        try!(self.value(kv & 15, depth + 1));
+                    }
+                }
+                Value::List(vec![])
+            },
+            12 => try!(self.structure(depth + 1)),
+            t => return Err(format!("Invalid thrift type {}.", t))
+        })
+    }
+
+    fn structure(&mut self, depth: usize) -> Result<Value<'a>, String> {
+
+        let mut fields = Vec::new();
+        let mut id: i16 = 0;
+        loop {
+            let h = try!(self.byte());
+            if h == 0 {
+                return Ok(Value::Struct(fields));
+            }
+            id = match h >> 4 {
+                0 => try!(self.zigzag()) as i16,
+                d => id.wrapping_add(d as i16)
+            };
+            let v = try!(self.value(h & 15, depth));
+            fields.push((id, v));
+        }
+    }
+}
+
+fn varint(buf: &[u8], pos: &mut usize) -> Result<u64, String> {
+
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let b = match buf.get(*pos) {
+            Some(&b) if shift < 64 => b,
+            _ => return Err("Invalid varint.".to_string())
+        };
+        *pos += 1;
+        v |= ((b & 0x7F) as u64) << shift;
+        shift += 7;
+        if b < 0x80 {
+            return Ok(v);
+        }
+    }
+}
+
+/// Decompresses data which has been compressed with Snappy (raw format
+/// without framing).
+fn snappy(buf: &[u8]) -> Result<Vec<u8>, String> {
+
+    let mut pos = 0;
+    let len = try!(varint(buf, &mut pos)) as usize;
+    let mut out: Vec<u8> = Vec::with_capacity(len.min(1 << 24));
+    let err = || "Invalid snappy data.".to_string();
+    while pos < buf.len() {
+        let tag = buf[pos];
+        pos += 1;
+        let (n, offset) = match tag & 3 {
+            0 => {
+                let mut n = (tag >> 2) as usize;
+                if n >= 60 {
+                    let k = n - 59;
+                    let b = try!(buf.get(pos..pos + k).ok_or_else(err));
+                    n = b.iter().rev().fold(0, |acc, &x| acc << 8 | x as usize);
+                    pos += k;
+                }
+                let lit = try!(buf.get(pos..pos.saturating_add(n + 1)).ok_or_else(err));
+                out.extend_from_slice(lit);
+                pos += n + 1;
+                continue;
+            },
+            1 => {
+                let b = try!(buf.get(pos).ok_or_else(err));
+                pos += 1;
+                (4 + ((tag >> 2) & 7) as usize, ((tag as usize >> 5) << 8) | *b as usize)
+            },
+            k => {
+                let k = if k == 2 { 2 } else { 4 };
+                let b = try!(buf.get(pos..pos + k).ok_or_else(err));
+                pos += k;
+                ((tag >> 2) as usize + 1, b.iter().rev().fold(0, |acc, &x| acc << 8 | x as usize))
+            }
+        };
+        if offset == 0 || offset > out.len() {
+            return Err(err());
+        }
+        // the source and the destination may overlap
+        let start = out.len() - offset;
+        for i in 0..n {
+            let b = out[start + i];
+            out.push(b);
+        }
+    }
+    if out.len() != len {
+        return Err(err());
+    }
+    Ok(out)
+}
+
+fn decompress(codec: i64, buf: &[u8]) -> Result<Vec<u8>, String> {
+
+    match codec {
+        UNCOMPRESSED => Ok(buf.to_vec()),
+        SNAPPY => snappy(buf),
+        GZIP => {
+            let mut out = Vec::new();
+            let mut d = try!(GzDecoder::new(buf).map_err(|e| format!("Invalid gzip header: {}", e)));
+            try!(d.read_to_end(&mut out).map_err(|e| format!("Invalid gzip data: {}", e)));
+            Ok(out)
+        },
+        c => Err(format!("Unsupported compression codec {}.", c))
+    }
+}
+
+/// Reads `n` values of `width` bits which are packed starting with the least
+/// significant bit.
+fn unpack(buf: &[u8], width: usize, n: usize, out: &mut Vec<u64>) -> Result<(), String> {
+
+    if width > 64 || buf.len().saturating_mul(8) < n.saturating_mul(width) {
+        return Err("Truncated bit-packed values.".to_string());
+    }
+    for i in 0..n {
+        let mut v = 0u64;
+        for b in 0..width {
+            let bit = i * width + b;
+            v |= (((buf[bit / 8] >> (bit % 8)) & 1) as u64) << b;
+        }
+        out.push(v);
+    }
+    Ok(())
+}
+
+/// Decodes `n` values of the RLE/bit-packing hybrid encoding, which is
+/// used for definition levels, dictionary indexes and booleans.
+fn rle(buf: &[u8], width: usize, n: usize) -> Result<Vec<u64>, String> {
+
+    let mut out = Vec::with_capacity(n.min(buf.len().saturating_mul(8)));
+    let mut pos = 0;
+    while out.len() < n {
+        let h = try!(varint(buf, &mut pos));
+        if h & 1 == 0 {
+            // a run of the same value
+            let k = (width + 7) / 8;
+            let b = try!(buf.get(pos..pos + k).ok_or("Truncated run.".to_string()));
+            pos += k;
+            let v = b.iter().rev().fold(0, |acc, &x| acc << 8 | x as u64);
+            let count = ((h >> 1) as usize).min(n - out.len());
+            out.extend(::std::iter::repeat(v).take(count));
+        } else {
+            // groups of eight bit-packed values
+            let groups = (h >> 1) as usize;
+            let len = groups.saturating_mul(width);
+            let b = try!(buf.get(pos..pos.saturating_add(len)).ok_or("Truncated bit-packed run.".to_string()));
+            pos += len;
+            let count = groups.saturating_mul(8).min(n - out.len());
+            try!(unpack(b, width, count, &mut out));
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes `n` integers of the encoding `DELTA_BINARY_PACKED`.
+fn delta(buf: &[u8], n: usize) -> Result<Vec<i64>, String> {
+
+    let mut pos = 0;
+    let zigzag = |pos: &mut usize| -> Result<i64, String> {
+        let v = try!(varint(buf, pos));
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    };
+    let block = try!(varint(buf, &mut pos)) as usize;
+    let miniblocks = try!(varint(buf, &mut pos)) as usize;
+    try!(varint(buf, &mut pos));
+    let mut last = try!(zigzag(&mut pos));
+    if miniblocks == 0 || block % miniblocks != 0 || (block / miniblocks) % 8 != 0 {
+        return Err("Invalid delta encoding.".to_string());
+    }
+    let size = block / miniblocks;
+
+    let mut out = Vec::with_capacity(n.min(buf.len().saturating_mul(8)));
+    if n > 0 {
+        out.push(last);
+    }
+    while out.len() < n {
+        let min = try!(zigzag(&mut pos));
+        let widths = try!(buf.get(pos..pos + miniblocks).ok_or("Truncated delta block.".to_string())).to_vec();
+        pos += miniblocks;
+        for &w in &widths {
+            if out.len() >= n {
+                break;
+            }
+            let len = size * w as usize / 8;
+            let b = try!(buf.get(pos..pos + len).ok_or("Truncated delta block.".to_string()));
+            pos += len;
+            let mut d = Vec::with_capacity(size);
+            try!(unpack(b, w as usize, size, &mut d));
+            for x in d.into_iter().take(n - out.len()) {
+                last = last.wrapping_add(min).wrapping_add(x as i64);
+                out.push(last);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes `n` values of the physical type `ty` with the encoding `PLAIN`
+/// or `BYTE_STREAM_SPLIT`.
+fn plain(ty: i64, encoding: i64, buf: &[u8], n: usize) -> Result<Vec<f64>, String> {
+
+    if ty == TYPE_BOOLEAN {
+        let mut v = Vec::with_capacity(n);
+        try!(unpack(buf, 1, n, &mut v));
+        return Ok(v.into_iter().map(|x| x as f64).collect());
+    }
+    let size = if ty == TYPE_INT32 || ty == TYPE_FLOAT { 4 } else { 8 };
+    if buf.len() < n.saturating_mul(size) {
+        return Err("Truncated values.".to_string());
+    }
+    Ok((0..n).map(|i| {
+        let bits = (0..size).rev().fold(0u64, |acc, k| {
+            let b = if encoding == BYTE_STREAM_SPLIT { buf[k * n + i] } else { buf[i * size + k] };
+            acc << 8 | b as u64
+        });
+        match ty {
+            TYPE_INT32 => bits as u32 as i32 as f64,
+            TYPE_INT64 => bits as i64 as f64,
+            TYPE_FLOAT => f32::from_bits(bits as u32) as f64,
+            _ => f64::from_bits(bits)
+        }
+    }).collect())
+}
+
+/// A top-level column of the schema which can be converted into numbers.
+struct Column {
+    name: String,
+    // physical type or `None` if the column is not numeric
+    ty: Option<i64>,
+    optional: bool,
+    // index of the column chunk in a row group
+    leaf: usize
+}
+
+/// Returns the top-level columns of the schema which is given as a list of
+/// schema elements in depth-first order.
+fn columns(schema: &[Value]) -> Result<Vec<Column>, String> {
+
+    let children = |e: &Value| e.get_int(5).unwrap_or(0).max(0) as usize;
+    let root = try!(schema.first().ok_or("Empty schema.".to_string()));
+    let mut cols = Vec::new();
+    let mut leaf = 0;
+    let mut i = 1;
+    for _ in 0..children(root) {
+        let e = try!(schema.get(i).ok_or("Invalid schema.".to_string()));
+        // a nested column is skipped with all its descendants
+        let mut pending = children(e);
+        let mut leaves = if pending == 0 { 1 } else { 0 };
+        i += 1;
+        while pending > 0 {
+            let c = try!(schema.get(i).ok_or("Invalid schema.".to_string()));
+            pending = pending - 1 + children(c);
+            if children(c) == 0 {
+                leaves += 1;
+            }
+            i += 1;
+        }
+        let ty = e.get_int(1);
+        let rep = e.get_int(3).unwrap_or(0);
+        let numeric = children(e) == 0 && rep != REPEATED && ty.map(|t| [TYPE_BOOLEAN, TYPE_INT32, TYPE_INT64, TYPE_FLOAT, TYPE_DOUBLE].contains(&t)).unwrap_or(false);
+        cols.push(Column {
+            name: e.get(4).map(|v| v.string()).unwrap_or(String::new()),
+            ty: if numeric { ty } else { None },
+            optional: rep == OPTIONAL,
+            leaf: leaf
+        });
+        leaf += leaves;
+    }
+    Ok(cols)
+}
+
+/// Reads all values of a column chunk.
+fn read_chunk(buf: &[u8], col: &Column, meta: &Value, out: &mut Vec<f64>) -> Result<(), String> {
+
+    let ty = col.ty.unwrap();
+    let codec = meta.get_int(4).unwrap_or(UNCOMPRESSED);
+    let n = try!(meta.get_int(5).ok_or("Missing number of values.".to_string())).max(0) as usize;
+    let data = try!(meta.get_int(9).ok_or("Missing data page offset.".to_string()));
+    let mut pos = match meta.get_int(11) {
+        Some(d) if d > 0 && d < data => d,
+        _ => data
+    } as usize;
+
+    let mut dict: Vec<f64> = Vec::new();
+    let start = out.len();
+    while out.len() - start < n {
+        let mut t = Thrift::new(try!(buf.get(pos..).ok_or("Invalid page offset.".to_string())));
+        let header = try!(t.structure(0));
+        pos += t.pos;
+        let size = header.get_int(3).unwrap_or(-1);
+        if size < 0 {
+            return Err("Invalid page size.".to_string());
+        }
+        let page = try!(buf.get(pos..pos.saturating_add(size as usize)).ok_or("Truncated page.".to_string()));
+        pos += size as usize;
+
+        let (values, levels, encoding) = match header.get_int(1) {
+            Some(DICTIONARY_PAGE) => {
+                let h = try!(header.get(7).ok_or("Missing dictionary page header.".to_string()));
+                let k = h.get_int(1).unwrap_or(0).max(0) as usize;
+                dict = try!(plain(ty, PLAIN, &try!(decompress(codec, page)), k));
+                continue;
+            },
+            Some(DATA_PAGE) => {
+                let h = try!(header.get(5).ok_or("Missing data page header.".to_string()));
+                let k = h.get_int(1).unwrap_or(0).max(0) as usize;
+                let page = try!(decompress(codec, page));
+                let (levels, p) = if col.optional {
+                    let len = try!(page.get(0..4).ok_or("Truncated page.".to_string()))
+                        .iter().rev().fold(0, |acc, &x| acc << 8 | x as usize);
+                    let l = try!(page.get(4..4usize.saturating_add(len)).ok_or("Truncated page.".to_string()));
+                    (try!(rle(l, 1, k)), 4 + len)
+                } else {
+                    (vec![1; k], 0)
+                };
+                (page[p..].to_vec(), levels, h.get_int(2).unwrap_or(PLAIN))
+            },
+            Some(DATA_PAGE_V2) => {
+                let h = try!(header.get(8).ok_or("Missing data page header.".to_string()));
+                let k = h.get_int(1).unwrap_or(0).max(0) as usize;
+                let dl = h.get_int(5).unwrap_or(0).max(0) as usize;
+                let rl = h.get_int(6).unwrap_or(0).max(0) as usize;
+                let l = try!(page.get(rl..rl.saturating_add(dl)).ok_or("Truncated page.".to_string()));
+                let levels = if col.optional { try!(rle(l, 1, k)) } else { vec![1; k] };
+                let p = &page[rl + dl..];
+                let p = if h.get_int(7).unwrap_or(1) != 0 { try!(decompress(codec, p)) } else { p.to_vec() };
+                (p, levels, h.get_int(4).unwrap_or(PLAIN))
+            },
+            _ => continue
+        };
+
+        let k = levels.iter().filter(|&&l| l == 1).count();
+        let v = match encoding {
+            PLAIN | BYTE_STREAM_SPLIT => try!(plain(ty, encoding, &values, k)),
+            PLAIN_DICTIONARY | RLE_DICTIONARY => {
+                let w = *try!(values.first().ok_or("Missing bit width.".to_string())) as usize;
+                let idx = try!(rle(&values[1..], w, k));
+                try!(idx.into_iter().map(|i| dict.get(i as usize).cloned().ok_or("Invalid dictionary index.".to_string())).collect())
+            },
+            RLE if ty == TYPE_BOOLEAN => {
+                let v = try!(values.get(4..).ok_or("Truncated page.".to_string()));
+                try!(rle(v, 1, k)).into_iter().map(|x| x as f64).collect()
+            },
+            DELTA_BINARY_PACKED if ty == TYPE_INT32 || ty == TYPE_INT64 => {
+                try!(delta(&values, k)).into_iter().map(|x| if ty == TYPE_INT32 { x as i32 as f64 } else { x as f64 }).collect()
+            },
+            e => return Err(format!("Unsupported encoding {} of column {}.", e, col.name))
+        };
+        let mut v = v.into_iter();
+        out.extend(levels.iter().map(|&l| if l == 1 { v.next().unwrap() } else { f64::NAN }));
+    }
+    if out.len() - start != n {
+        return Err(format!("Invalid number of values in column {}.", col.name));
+    }
+    Ok(())
+}
+
+/// Reads numeric columns from a Parquet file given as a reader.
+///
+/// If `columns` is empty all top-level columns of a numeric or boolean
+/// type are loaded. Otherwise the columns with the given names are loaded
+/// in the given order. Returns the names of the loaded columns and the
+/// matrix with one row per record.
+pub fn read_parquet<R: Read>(mut reader: R, columns: &[&str]) -> Result<(Vec<String>, Matrix<f64>), String> {
+
+    let mut buf = Vec::new();
+    try!(reader.read_to_end(&mut buf).map_err(|e| format!("Could not read data: {}", e)));
+
+    let n = buf.len();
+    if n < 12 || &buf[..4] != MAGIC || &buf[n - 4..] != MAGIC {
+        return Err("Not a Parquet file.".to_string());
+    }
+    let len = buf[n - 8..n - 4].iter().rev().fold(0, |acc, &x| acc << 8 | x as usize);
+    if len > n - 12 {
+        return Err("Invalid length of the metadata.".to_string());
+    }
+    let meta = try!(Thrift::new(&buf[n - 8 - len..n - 8]).structure(0));
+
+    let cols = try!(self::columns(try!(meta.get(2).ok_or("Missing schema.".to_string())).list()));
+    let selected = try!(select(&cols, columns));
+
+    let mut values: Vec<Vec<f64>> = vec![Vec::new(); selected.len()];
+    for rg in meta.get(4).map(|v| v.list()).unwrap_or(&[]) {
+        let chunks = rg.get(1).map(|v| v.list()).unwrap_or(&[]);
+        for (k, &i) in selected.iter().enumerate() {
+            let c = &cols[i];
+            let chunk = try!(chunks.get(c.leaf).ok_or("Missing column chunk.".to_string()));
+            if chunk.get(1).is_some() {
+                return Err("Column chunks in other files are not supported.".to_string());
+            }
+            let m = try!(chunk.get(3).ok_or("Missing column metadata.".to_string()));
+            if m.get_int(1) != c.ty {
+                return Err(format!("Type of column {} does not match the schema.", c.name));
+            }
+            try!(read_chunk(&buf, c, m, &mut values[k]));
+        }
+    }
+
+    let rows = values.first().map(|v| v.len()).unwrap_or(0);
+    if values.iter().any(|v| v.len() != rows) {
+        return Err("Columns have different lengths.".to_string());
+    }
+    let mut m = Vec::with_capacity(rows * values.len());
+    for r in 0..rows {
+        m.extend(values.iter().map(|v| v[r]));
+    }
+    let names = selected.iter().map(|&i| cols[i].name.clone()).collect();
+    Ok((names, Matrix::from_vec(m, rows, values.len())))
+}
+
+/// Returns the indexes of the selected columns.
+fn select(cols: &[Column], columns: &[&str]) -> Result<Vec<usize>, String> {
+
+    if columns.is_empty() {
+        return Ok((0..cols.len()).filter(|&i| cols[i].ty.is_some()).collect());
+    }
+    columns.iter().map(|c| {
+        match cols.iter().position(|x| x.name == *c) {
+            Some(i) if cols[i].ty.is_some() => Ok(i),
+            Some(_) => Err(format!("Column {} is not numeric.", c)),
+            None => Err(format!("Column {} not found.", c))
+        }
+    }).collect()
+}
+
+/// Loads numeric columns from a Parquet file (see
+/// [`read_parquet`](fn.read_parquet.html)).
+pub fn load_parquet(fname: &str, columns: &[&str]) -> Result<(Vec<String>, Matrix<f64>), String> {
+
+    let f = try!(File::open(fname).map_err(|e| format!("Could not open file {}: {}", fname, e)));
+    read_parquet(f, columns)
+}
+
+/// Loads numeric columns from a Parquet file into a sparse matrix (see
+/// [`read_parquet`](fn.read_parquet.html)). Null values are stored as
+/// `NaN`.
+pub fn load_parquet_sparse(fname: &str, columns: &[&str]) -> Result<(Vec<String>, SparseMatrix<f64>), String> {
+
+    let (names, m) = try!(load_parquet(fname, columns));
+    Ok((names, SparseMatrix::from_dense(&m)))
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // The test files contain the same ten rows in row groups of four rows
+    // (see datasets/testing/README.txt):
+    // a: INT32 i * 3 % 5 or null if i % 4 == 3, b: DOUBLE i / 2,
+    // c: BOOLEAN i % 3 == 0, s: string, f: FLOAT -i or null if i is odd,
+    // l: INT64 2^40 + i % 2 and the nested column g.q.
+    fn check(fname: &str) {
+
+        let (names, m) = load_parquet(fname, &[]).unwrap();
+        assert_eq!(names, vec!["a", "b", "c", "f", "l"]);
+        assert_eq!(m.rows(), 10);
+        for i in 0..10 {
+            let r = m.row(i).unwrap();
+            if i % 4 == 3 {
+                assert!(r[0].is_nan());
+            } else {
+                assert_eq!(r[0], (i * 3 % 5) as f64);
+            }
+            assert_eq!(r[1], i as f64 / 2.0);
+            assert_eq!(r[2], if i % 3 == 0 { 1.0 } else { 0.0 });
+            if i % 2 == 1 {
+                assert!(r[3].is_nan());
+            } else {
+                assert_eq!(r[3], -(i as f64));
+            }
+            assert_eq!(r[4], (1u64 << 40) as f64 + (i % 2) as f64);
+        }
+
+        let (names, x) = load_parquet(fname, &["l", "b"]).unwrap();
+        assert_eq!(names, vec!["l", "b"]);
+        assert_eq!(x.col(1).unwrap(), m.col(1).unwrap());
+        assert!(load_parquet(fname, &["s"]).unwrap_err().contains("not numeric"));
+        assert!(load_parquet(fname, &["g"]).is_err());
+        assert!(load_parquet(fname, &["x"]).unwrap_err().contains("not found"));
+
+        let (_, s) = load_parquet_sparse(fname, &["b"]).unwrap();
+        assert_eq!(s.rows(), 10);
+    }
+
+    #[test]
+    fn test_load_parquet() {
+
+        // dictionary pages, data pages v1 and Snappy
+        check("datasets/testing/snappy.parquet");
+        // data pages v2, delta and RLE encoding and gzip
+        check("datasets/testing/gzip.parquet");
+        check("datasets/testing/plain.parquet");
+        assert!(load_parquet("datasets/testing/nonexisting.parquet", &[]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+
+        let buf = fs::read("datasets/testing/snappy.parquet").unwrap();
+        assert!(read_parquet(&buf[..buf.len() - 1], &[]).is_err());
+        assert!(read_parquet(&b"PAR1PAR1"[..], &[]).is_err());
+        // pages truncated at every position before the metadata (the last
+        // pages contain only columns which are not loaded)
+        let full = read_parquet(&buf[..], &[]).unwrap();
+        let len = buf[buf.len() - 8..buf.len() - 4].iter().rev().fold(0, |acc, &x| acc << 8 | x as usize);
+        let meta = buf.len() - 8 - len;
+        let mut v = buf[..4].to_vec();
+        v.extend_from_slice(&buf[meta..]);
+        assert!(read_parquet(&v[..], &[]).is_err());
+        for n in (4..meta).step_by(7) {
+            let mut v = buf[..n].to_vec();
+            v.extend_from_slice(&buf[meta..]);
+            match read_parquet(&v[..], &[]) {
+                Ok(r) => assert!(r.0 == full.0 && r.1.rows() == 10),
+                Err(_) => {}
+            }
+        }
+        // corrupted bytes must not lead to a panic
+        for i in (4..buf.len() - 8).step_by(3) {
+            let mut v = buf.clone();
+            v[i] ^= 0x5A;
+            let _ = read_parquet(&v[..], &[]);
+        }
+    }
+
+    #[test]
+    fn test_snappy() {
+
+        // literal "abc" followed by a copy of 6 bytes at offset 3
+        assert_eq!(snappy(&[9, 8, b'a', b'b', b'c', 0x09, 3]).unwrap(), b"abcabcabc");
+        assert!(snappy(&[9, 8, b'a', b'b', b'c', 0x09, 4]).is_err());
+        assert!(snappy(&[4, 8, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_rle() {
+
+        // run of five 3s followed by eight bit-packed values of width 2
+        let buf = [10, 3, 3, 0b11100100, 0b00011011];
+        assert_eq!(rle(&buf, 2, 13).unwrap(), vec![3, 3, 3, 3, 3, 0, 1, 2, 3, 3, 2, 1, 0]);
+        assert!(rle(&buf, 2, 14).is_err());
+    }
+
+    #[test]
+    fn test_delta() {
+
+        // block size 128 with 4 miniblocks, 3 values, first value 1,
+        // min delta 1, widths 1 (only the first miniblock is stored)
+        let mut buf = vec![128, 1, 4, 3, 2, 2, 1, 0, 0, 0];
+        buf.extend(vec![0b10; 4]);
+        assert_eq!(delta(&buf, 3).unwrap(), vec![1, 2, 4]);
+        assert!(delta(&buf[..9], 3).is_err());
+    }
+}
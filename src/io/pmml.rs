@@ -0,0 +1,176 @@
+//! Export of linear models into the PMML format.
+//!
+//! The [Predictive Model Markup Language](http://dmg.org/pmml/v4-4/GeneralStructure.html)
+//! is an XML format which is consumed by many scoring engines. This module
+//! exports linear models as `RegressionModel`:
+//!
+//! * [linear_regression_pmml](fn.linear_regression_pmml.html) exports a
+//! linear regression, e.g. a [Hypothesis](../../regression/struct.Hypothesis.html)
+//! which has been trained on a design matrix.
+//! * [logistic_regression_pmml](fn.logistic_regression_pmml.html) exports a
+//! binary logistic regression which predicts the probabilities of the
+//! classes `0` and `1`.
+//!
+//! In both cases the first parameter is the intercept, i.e. the weight of
+//! the column of ones of the design matrix, and the remaining parameters are
+//! the weights of the features.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::regression::Hypothesis;
+//! use rustml::io::pmml::*;
+//!
+//! let h = Hypothesis::from_params(&[0.5, 2.0, -1.0]);
+//! let xml = linear_regression_pmml(&h.params(), &["x1", "x2"], "y");
+//! assert!(xml.contains(r#"<RegressionTable intercept="0.5">"#));
+//! ```
+use std::fs::File;
+use std::io::Write;
+
+use regression::Hypothesis;
+
+fn escape(s: &str) -> String {
+
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn header(out: &mut String, features: &[&str], target: &str, classification: bool) {
+
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<PMML xmlns=\"http://www.dmg.org/PMML-4_4\" version=\"4.4\">\n");
+    out.push_str(&format!("  <Header><Application name=\"rustml\" version=\"{}\"/></Header>\n",
+        env!("CARGO_PKG_VERSION")));
+    out.push_str(&format!("  <DataDictionary numberOfFields=\"{}\">\n", features.len() + 1));
+    for f in features {
+        out.push_str(&format!("    <DataField name=\"{}\" optype=\"continuous\" dataType=\"double\"/>\n", escape(f)));
+    }
+    if classification {
+        out.push_str(&format!("    <DataField name=\"{}\" optype=\"categorical\" dataType=\"integer\">\n", escape(target)));
+        out.push_str("      <Value value=\"0\"/>\n      <Value value=\"1\"/>\n    </DataField>\n");
+    } else {
+        out.push_str(&format!("    <DataField name=\"{}\" optype=\"continuous\" dataType=\"double\"/>\n", escape(target)));
+    }
+    out.push_str("  </DataDictionary>\n");
+}
+
+fn mining_schema(out: &mut String, features: &[&str], target: &str) {
+
+    out.push_str("    <MiningSchema>\n");
+    for f in features {
+        out.push_str(&format!("      <MiningField name=\"{}\"/>\n", escape(f)));
+    }
+    out.push_str(&format!("      <MiningField name=\"{}\" usageType=\"target\"/>\n", escape(target)));
+    out.push_str("    </MiningSchema>\n");
+}
+
+fn predictors(out: &mut String, features: &[&str], params: &[f64]) {
+
+    for (f, c) in features.iter().zip(params.iter()) {
+        out.push_str(&format!("      <NumericPredictor name=\"{}\" coefficient=\"{:?}\"/>\n", escape(f), c));
+    }
+}
+
+/// Returns the PMML document of a linear regression with the intercept
+/// `params[0]` and the coefficients `params[1..]` of the features.
+///
+/// Panics if the number of parameters is not the number of features plus
+/// one.
+pub fn linear_regression_pmml(params: &[f64], features: &[&str], target: &str) -> String {
+
+    assert!(params.len() == features.len() + 1, "Number of parameters must be the number of features plus one.");
+
+    let mut out = String::new();
+    header(&mut out, features, target, false);
+    out.push_str("  <RegressionModel functionName=\"regression\">\n");
+    mining_schema(&mut out, features, target);
+    out.push_str(&format!("    <RegressionTable intercept=\"{:?}\">\n", params[0]));
+    predictors(&mut out, features, &params[1..]);
+    out.push_str("    </RegressionTable>\n  </RegressionModel>\n</PMML>\n");
+    out
+}
+
+/// Returns the PMML document of a binary logistic regression with the
+/// intercept `params[0]` and the coefficients `params[1..]` of the
+/// features. The probability of the class `1` is
+/// `1 / (1 + exp(-(params[0] + params[1] * x1 + ...)))`.
+///
+/// Panics if the number of parameters is not the number of features plus
+/// one.
+pub fn logistic_regression_pmml(params: &[f64], features: &[&str], target: &str) -> String {
+
+    assert!(params.len() == features.len() + 1, "Number of parameters must be the number of features plus one.");
+
+    let mut out = String::new();
+    header(&mut out, features, target, true);
+    out.push_str("  <RegressionModel functionName=\"classification\" normalizationMethod=\"logit\">\n");
+    mining_schema(&mut out, features, target);
+    out.push_str(&format!("    <RegressionTable intercept=\"{:?}\" targetCategory=\"1\">\n", params[0]));
+    predictors(&mut out, features, &params[1..]);
+    out.push_str("    </RegressionTable>\n");
+    out.push_str("    <RegressionTable intercept=\"0.0\" targetCategory=\"0\"/>\n");
+    out.push_str("  </RegressionModel>\n</PMML>\n");
+    out
+}
+
+/// Saves a PMML document into a file.
+pub fn save_pmml(fname: &str, pmml: &str) -> Result<(), String> {
+
+    let mut f = try!(File::create(fname).map_err(|e| format!("Could not create file {}: {}", fname, e)));
+    f.write_all(pmml.as_bytes()).map_err(|e| format!("Could not write file {}: {}", fname, e))
+}
+
+impl Hypothesis {
+
+    /// Returns the PMML document of the hypothesis as linear regression (see
+    /// [linear_regression_pmml](../io/pmml/fn.linear_regression_pmml.html)).
+    /// The first parameter is the intercept.
+    pub fn to_pmml(&self, features: &[&str], target: &str) -> String {
+        linear_regression_pmml(&self.params(), features, target)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regression::Hypothesis;
+
+    #[test]
+    fn test_linear_regression_pmml() {
+
+        let xml = Hypothesis::from_params(&[1.5, -2.0, 0.25]).to_pmml(&["a", "b<c"], "y");
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<DataDictionary numberOfFields=\"3\">"));
+        assert!(xml.contains("<DataField name=\"b&lt;c\" optype=\"continuous\" dataType=\"double\"/>"));
+        assert!(xml.contains("<MiningField name=\"y\" usageType=\"target\"/>"));
+        assert!(xml.contains("<RegressionTable intercept=\"1.5\">"));
+        assert!(xml.contains("<NumericPredictor name=\"a\" coefficient=\"-2.0\"/>"));
+        assert!(xml.contains("<NumericPredictor name=\"b&lt;c\" coefficient=\"0.25\"/>"));
+        assert!(xml.trim_end().ends_with("</PMML>"));
+    }
+
+    #[test]
+    fn test_logistic_regression_pmml() {
+
+        let xml = logistic_regression_pmml(&[0.1, 3.0], &["x"], "label");
+        assert!(xml.contains("functionName=\"classification\" normalizationMethod=\"logit\""));
+        assert!(xml.contains("<RegressionTable intercept=\"0.1\" targetCategory=\"1\">"));
+        assert!(xml.contains("<RegressionTable intercept=\"0.0\" targetCategory=\"0\"/>"));
+        assert!(xml.contains("<Value value=\"1\"/>"));
+
+        let path = ::std::env::temp_dir().join("rustml_test_model.pmml");
+        save_pmml(path.to_str().unwrap(), &xml).unwrap();
+        assert_eq!(::std::fs::read_to_string(&path).unwrap(), xml);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pmml_invalid_params() {
+        linear_regression_pmml(&[1.0], &["x"], "y");
+    }
+}
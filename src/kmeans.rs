@@ -0,0 +1,449 @@
+//! Mini-batch k-means clustering for very large datasets.
+//!
+//! [MiniBatchKMeans](struct.MiniBatchKMeans.html) updates the cluster
+//! centers from small random batches of the data instead of assigning all
+//! examples in each iteration (Sculley, "Web-scale k-means clustering",
+//! 2010). Each center moves towards the mean of the examples that have been
+//! assigned to it so far, i.e. the learning rate of a center decreases with
+//! the number of its examples. This needs a fraction of the time of the
+//! standard algorithm at the cost of a slightly higher inertia.
+//!
+//! The centers are initialized with k-means++ on a random sample of the
+//! data. Centers which get (almost) no examples are reassigned to random
+//! examples of the current batch. The algorithm stops after `max_iter`
+//! batches, if the centers move less than `tol` or if the smoothed inertia
+//! of the recent batches has not improved for `max_no_improvement` batches.
+//! The progress is reported after each batch (see the module
+//! [progress](../progress/index.html)).
+//!
+//! Data which does not fit into memory can be clustered by passing the
+//! chunks to [partial_fit](struct.MiniBatchKMeans.html#method.partial_fit).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::kmeans::MiniBatchKMeans;
+//!
+//! # fn main() {
+//! let x = mixture_builder()
+//!     .add(500, normal_builder([1, 2, 3, 4]).add(0.0, 0.3).add(0.0, 0.3))
+//!     .add(500, normal_builder([5, 6, 7, 8]).add(5.0, 0.3).add(5.0, 0.3))
+//!     .as_matrix()
+//!     .rm_column(0);
+//!
+//! let mut k = MiniBatchKMeans::new(2).batch_size(100).seed([1, 2, 3, 4]);
+//! k.fit(&x);
+//!
+//! let labels = k.predict(&x);
+//! assert!(labels[..500].iter().all(|&l| l == labels[0]));
+//! assert!(labels[500..].iter().all(|&l| l != labels[0]));
+//! # }
+//! ```
+extern crate rand;
+
+use self::rand::{Rng, XorShiftRng};
+
+use matrix::Matrix;
+use progress::{Progress, ProgressInfo, NoProgress, report};
+use io::model::{Model, Encoder, Decoder};
+use rng;
+
+/// Mini-batch k-means clustering.
+#[derive(Clone, Debug)]
+pub struct MiniBatchKMeans {
+    k: usize,
+    batch_size: usize,
+    max_iter: usize,
+    tol: f64,
+    max_no_improvement: usize,
+    reassignment_ratio: f64,
+    rng: XorShiftRng,
+    centers: Matrix<f64>,
+    counts: Vec<f64>,
+    n_iter: usize,
+    ewa_inertia: Option<f64>,
+    best_inertia: Option<f64>,
+    no_improvement: usize
+}
+
+impl MiniBatchKMeans {
+
+    /// Creates the algorithm for `k` clusters with the default parameters,
+    /// i.e. batches of 1024 examples, at most 100 batches, `tol` zero (no
+    /// convergence detection on the movement of the centers),
+    /// `max_no_improvement` 10 and a reassignment ratio of 0.01. The random
+    /// number generator is created with [rng](../rng/fn.rng.html).
+    ///
+    /// Panics if `k` is zero.
+    pub fn new(k: usize) -> MiniBatchKMeans {
+
+        assert!(k > 0, "Number of clusters must be greater than zero.");
+        MiniBatchKMeans {
+            k: k,
+            batch_size: 1024,
+            max_iter: 100,
+            tol: 0.0,
+            max_no_improvement: 10,
+            reassignment_ratio: 0.01,
+            rng: rng::rng(),
+            centers: Matrix::new(),
+            counts: vec![],
+            n_iter: 0,
+            ewa_inertia: None,
+            best_inertia: None,
+            no_improvement: 0
+        }
+    }
+
+    /// Sets the number of examples per batch.
+    ///
+    /// Panics if `n` is zero.
+    pub fn batch_size(&self, n: usize) -> MiniBatchKMeans {
+
+        assert!(n > 0, "Batch size must be greater than zero.");
+        MiniBatchKMeans { batch_size: n, .. self.clone() }
+    }
+
+    /// Sets the maximum number of batches of `fit`.
+    pub fn max_iter(&self, n: usize) -> MiniBatchKMeans {
+        MiniBatchKMeans { max_iter: n, .. self.clone() }
+    }
+
+    /// `fit` stops if the mean squared movement of the centers of a batch
+    /// is not larger than `tol`.
+    pub fn tol(&self, tol: f64) -> MiniBatchKMeans {
+        MiniBatchKMeans { tol: tol, .. self.clone() }
+    }
+
+    /// `fit` stops if the smoothed inertia has not improved for `n`
+    /// consecutive batches. Zero disables this criterion.
+    pub fn max_no_improvement(&self, n: usize) -> MiniBatchKMeans {
+        MiniBatchKMeans { max_no_improvement: n, .. self.clone() }
+    }
+
+    /// Centers with fewer than `ratio` times the number of examples of the
+    /// largest cluster are reassigned to random examples. Zero disables the
+    /// reassignment.
+    pub fn reassignment_ratio(&self, ratio: f64) -> MiniBatchKMeans {
+        MiniBatchKMeans { reassignment_ratio: ratio, .. self.clone() }
+    }
+
+    /// Seeds the random number generator which samples the batches and
+    /// initializes the centers.
+    pub fn seed(&self, seed: [u32; 4]) -> MiniBatchKMeans {
+        MiniBatchKMeans { rng: rng::seeded_rng(seed), .. self.clone() }
+    }
+
+    /// Returns the cluster centers in the rows.
+    pub fn centers(&self) -> &Matrix<f64> {
+        &self.centers
+    }
+
+    /// Returns the number of processed batches.
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    /// Returns the smoothed inertia of the recent batches, i.e. an
+    /// exponentially weighted average of the mean squared distance of the
+    /// examples of a batch to their centers.
+    pub fn smoothed_inertia(&self) -> Option<f64> {
+        self.ewa_inertia
+    }
+
+    /// Clusters the rows of `x` with random batches (see the module
+    /// documentation for the stopping criteria).
+    ///
+    /// Panics if `x` has fewer rows than the number of clusters.
+    pub fn fit(&mut self, x: &Matrix<f64>) {
+        self.fit_with_progress(x, &mut NoProgress)
+    }
+
+    /// Like [fit](#method.fit) but reports the progress after each batch.
+    pub fn fit_with_progress<P: Progress + ?Sized>(&mut self, x: &Matrix<f64>, progress: &mut P) {
+
+        assert!(x.rows() >= self.k, "Number of examples must not be smaller than the number of clusters.");
+        self.reset();
+
+        // k-means++ on a random sample
+        let init_size = (3 * self.batch_size).max(3 * self.k).min(x.rows());
+        let sample = self.sample(x, init_size);
+        self.init(&sample);
+
+        for i in 0..self.max_iter {
+            let batch = self.sample(x, self.batch_size.min(x.rows()));
+            let shift = self.step(&batch);
+            report(progress, &ProgressInfo {
+                algorithm: "mini-batch k-means",
+                iteration: i + 1,
+                max_iter: self.max_iter,
+                loss: self.ewa_inertia
+            });
+            if shift <= self.tol && self.tol > 0.0 {
+                break;
+            }
+            if self.max_no_improvement > 0 && self.no_improvement >= self.max_no_improvement {
+                break;
+            }
+        }
+    }
+
+    /// Updates the centers with the examples in the rows of `batch`. The
+    /// centers are initialized from the first batch, which must contain at
+    /// least `k` examples.
+    pub fn partial_fit(&mut self, batch: &Matrix<f64>) {
+
+        if self.centers.rows() == 0 {
+            assert!(batch.rows() >= self.k, "Number of examples must not be smaller than the number of clusters.");
+            self.init(batch);
+        }
+        self.step(batch);
+    }
+
+    /// Returns the index of the nearest center for each row of `x`.
+    ///
+    /// Panics if the model is not fitted or if the number of columns does
+    /// not match the dimension of the centers.
+    pub fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+
+        self.check(x);
+        x.row_iter().map(|r| self.nearest(r).0).collect()
+    }
+
+    /// Returns the sum of the squared distances of the rows of `x` to their
+    /// nearest centers.
+    pub fn inertia(&self, x: &Matrix<f64>) -> f64 {
+
+        self.check(x);
+        x.row_iter().map(|r| self.nearest(r).1).sum()
+    }
+
+    fn check(&self, x: &Matrix<f64>) {
+        assert!(self.centers.rows() > 0, "Model is not fitted.");
+        assert!(x.cols() == self.centers.cols(), "Number of columns does not match the dimension of the centers.");
+    }
+
+    fn reset(&mut self) {
+        self.centers = Matrix::new();
+        self.counts = vec![];
+        self.n_iter = 0;
+        self.ewa_inertia = None;
+        self.best_inertia = None;
+        self.no_improvement = 0;
+    }
+
+    /// Returns `n` random rows of `x` (with replacement).
+    fn sample(&mut self, x: &Matrix<f64>, n: usize) -> Matrix<f64> {
+
+        let mut v = Vec::with_capacity(n * x.cols());
+        for _ in 0..n {
+            let i = self.rng.gen_range(0, x.rows());
+            v.extend_from_slice(x.row(i).unwrap());
+        }
+        Matrix::from_vec(v, n, x.cols())
+    }
+
+    /// Initializes the centers with k-means++ on the rows of `x`.
+    fn init(&mut self, x: &Matrix<f64>) {
+
+        let first = self.rng.gen_range(0, x.rows());
+        let mut centers = x.row(first).unwrap().to_vec();
+        let mut d: Vec<f64> = x.row_iter().map(|r| squared_distance(r, x.row(first).unwrap())).collect();
+
+        for _ in 1..self.k {
+            let total: f64 = d.iter().sum();
+            let i = if total > 0.0 {
+                let mut t = self.rng.gen::<f64>() * total;
+                d.iter().position(|&v| { t -= v; t < 0.0 }).unwrap_or(x.rows() - 1)
+            } else {
+                self.rng.gen_range(0, x.rows())
+            };
+            let c = x.row(i).unwrap();
+            centers.extend_from_slice(c);
+            for (dj, r) in d.iter_mut().zip(x.row_iter()) {
+                *dj = dj.min(squared_distance(r, c));
+            }
+        }
+        self.centers = Matrix::from_vec(centers, self.k, x.cols());
+        self.counts = vec![0.0; self.k];
+    }
+
+    /// Returns the index of the nearest center and the squared distance.
+    fn nearest(&self, x: &[f64]) -> (usize, f64) {
+
+        self.centers.row_iter().map(|c| squared_distance(x, c)).enumerate()
+            .fold((0, ::std::f64::INFINITY), |best, (i, d)| if d < best.1 { (i, d) } else { best })
+    }
+
+    /// Processes one batch and returns the mean squared movement of the
+    /// centers.
+    fn step(&mut self, batch: &Matrix<f64>) -> f64 {
+
+        assert!(batch.cols() == self.centers.cols(), "Number of columns does not match the dimension of the centers.");
+        if batch.rows() == 0 {
+            return 0.0;
+        }
+        let d = batch.cols();
+        let old = self.centers.clone();
+
+        // assignment and update of the centers (each center is the mean of
+        // all examples which have been assigned to it so far)
+        let mut sums = vec![0.0; self.k * d];
+        let mut n = vec![0.0; self.k];
+        let mut inertia = 0.0;
+        for r in batch.row_iter() {
+            let (c, dist) = self.nearest(r);
+            inertia += dist;
+            n[c] += 1.0;
+            for (s, v) in sums[c * d..(c + 1) * d].iter_mut().zip(r.iter()) {
+                *s += v;
+            }
+        }
+        for c in 0..self.k {
+            if n[c] > 0.0 {
+                let total = self.counts[c] + n[c];
+                let row = self.centers.row_mut(c).unwrap();
+                for j in 0..d {
+                    row[j] = (row[j] * self.counts[c] + sums[c * d + j]) / total;
+                }
+                self.counts[c] = total;
+            }
+        }
+        self.n_iter += 1;
+
+        // reassignment of dead centers
+        if self.reassignment_ratio > 0.0 && self.n_iter % 10 == 0 {
+            self.reassign(batch);
+        }
+
+        // convergence detection on the smoothed inertia
+        let batch_inertia = inertia / batch.rows() as f64;
+        let ewa = match self.ewa_inertia {
+            None => batch_inertia,
+            Some(e) => {
+                let alpha = (2.0 * batch.rows() as f64 / (self.counts.iter().sum::<f64>() + 1.0)).min(1.0);
+                e * (1.0 - alpha) + batch_inertia * alpha
+            }
+        };
+        self.ewa_inertia = Some(ewa);
+        match self.best_inertia {
+            Some(b) if ewa >= b => self.no_improvement += 1,
+            _ => {
+                self.best_inertia = Some(ewa);
+                self.no_improvement = 0;
+            }
+        }
+
+        let shift: f64 = old.iter().zip(self.centers.iter()).map(|(a, b)| (a - b) * (a - b)).sum();
+        shift / self.k as f64
+    }
+
+    /// Moves the centers with too few examples to random examples of the
+    /// batch.
+    fn reassign(&mut self, batch: &Matrix<f64>) {
+
+        let max = self.counts.iter().fold(0.0f64, |a, &b| a.max(b));
+        let dead: Vec<usize> = (0..self.k).filter(|&c| self.counts[c] < self.reassignment_ratio * max).collect();
+        if dead.is_empty() {
+            return;
+        }
+        let min = (0..self.k).filter(|c| !dead.contains(c)).map(|c| self.counts[c])
+            .fold(::std::f64::INFINITY, f64::min);
+        for c in dead {
+            let i = self.rng.gen_range(0, batch.rows());
+            self.centers.row_mut(c).unwrap().copy_from_slice(batch.row(i).unwrap());
+            // the reassigned center must not be moved away immediately
+            self.counts[c] = min;
+        }
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+impl Model for MiniBatchKMeans {
+
+    fn tag() -> &'static str { "rustml.kmeans.MiniBatchKMeans" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.matrix(&self.centers);
+        e.f64s(&self.counts);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<MiniBatchKMeans, String> {
+
+        let centers = try!(d.matrix());
+        let counts = try!(d.f64s());
+        if centers.rows() == 0 || counts.len() != centers.rows() {
+            return Err("Invalid cluster centers.".to_string());
+        }
+        let mut k = MiniBatchKMeans::new(centers.rows());
+        k.centers = centers;
+        k.counts = counts;
+        Ok(k)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    fn blobs() -> Matrix<f64> {
+        // three well separated clusters
+        let mut v = vec![];
+        for i in 0..300 {
+            let (cx, cy) = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)][i % 3];
+            v.push(cx + ((i * 7) % 11) as f64 / 11.0);
+            v.push(cy + ((i * 5) % 13) as f64 / 13.0);
+        }
+        Matrix::from_vec(v, 300, 2)
+    }
+
+    #[test]
+    fn test_mini_batch_kmeans() {
+
+        let x = blobs();
+        let mut k = MiniBatchKMeans::new(3).batch_size(30).max_iter(200).seed([1, 2, 3, 4]);
+        let mut iterations = 0;
+        k.fit_with_progress(&x, &mut |i: &ProgressInfo| { iterations = i.iteration; assert!(i.loss.is_some()); });
+        assert_eq!(iterations, k.n_iter());
+        // stopped early because the inertia did not improve any more
+        assert!(k.n_iter() < 200);
+
+        let l = k.predict(&x);
+        for i in 3..300 {
+            assert_eq!(l[i], l[i % 3]);
+        }
+        assert!(l[0] != l[1] && l[1] != l[2] && l[0] != l[2]);
+        assert!(k.inertia(&x) / 300.0 < 0.5);
+
+        // same seed, same result
+        let mut k2 = MiniBatchKMeans::new(3).batch_size(30).max_iter(200).seed([1, 2, 3, 4]);
+        k2.fit(&x);
+        assert_eq!(k.centers(), k2.centers());
+    }
+
+    #[test]
+    fn test_partial_fit_and_reassignment() {
+
+        let x = blobs();
+        // all centers start in the first cluster
+        let first = Matrix::from_vec(x.row_iter().step_by(3).take(10).flat_map(|r| r.to_vec()).collect(), 10, 2);
+        let mut k = MiniBatchKMeans::new(3).seed([4, 3, 2, 1]);
+        k.partial_fit(&first);
+        for _ in 0..20 {
+            for c in 0..10 {
+                k.partial_fit(&Matrix::from_vec(x.buf()[c * 60..(c + 1) * 60].to_vec(), 30, 2));
+            }
+        }
+        let mut l = k.predict(&mat![0.5, 0.5; 10.5, 0.5; 0.5, 10.5]);
+        l.sort();
+        assert_eq!(l, vec![0, 1, 2]);
+    }
+}
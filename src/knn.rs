@@ -1,9 +1,16 @@
 //! Functions to compute the k-nearest neighbours.
 extern crate num;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 
+use std::cmp::Ordering;
 use self::num::traits::Float;
 use matrix::*;
 use vectors::group;
+use distance::{Metric, BLOCK_ROWS, pairwise_distances};
+use model_selection::row_batches;
+#[cfg(feature = "rayon")]
+use self::rayon::prelude::*;
 
 
 /*
@@ -71,6 +78,72 @@ pub fn classify<T, L, D>(m: &Matrix<T>, labels: &Vec<L>, example: &[T], k: usize
     r.last().unwrap().0.clone()
 }
 
+/// Searches the `k` nearest neighbours in the rows of `m` for each row of
+/// `queries` by brute force.
+///
+/// The queries are processed in blocks of
+/// [`BLOCK_ROWS`](../distance/constant.BLOCK_ROWS.html) rows whose distances
+/// to all rows of `m` are computed with
+/// [`pairwise_distances`](../distance/fn.pairwise_distances.html), i.e. with
+/// one BLAS matrix multiplication per block for the Euclidean distance.
+/// Returns the indexes of the neighbours of each query sorted by increasing
+/// distance (ties are sorted by index). If `m` has fewer than `k` rows all
+/// rows are returned.
+///
+/// Panics if the number of columns of `m` and `queries` differ.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::knn::kneighbors;
+/// use rustml::distance::Metric;
+///
+/// # fn main() {
+/// let m = mat![0.0, 0.0; 1.0, 0.0; 5.0, 5.0];
+/// let n = kneighbors(&m, &mat![0.9, 0.1; 4.0, 4.0], 2, Metric::Euclidean);
+/// assert_eq!(n, vec![vec![1, 0], vec![2, 1]]);
+/// # }
+/// ```
+pub fn kneighbors(m: &Matrix<f64>, queries: &Matrix<f64>, k: usize, metric: Metric) -> Vec<Vec<usize>> {
+
+    assert!(m.cols() == queries.cols(), "Number of columns must be equal.");
+    row_batches(queries, BLOCK_ROWS).iter()
+        .flat_map(|q| block_neighbours(m, q, k, metric))
+        .collect()
+}
+
+/// Like [`kneighbors`](fn.kneighbors.html) but the blocks of queries are
+/// processed in parallel on the global thread pool of rayon (requires the
+/// feature `rayon`). The result is the same as the result of `kneighbors`.
+#[cfg(feature = "rayon")]
+pub fn par_kneighbors(m: &Matrix<f64>, queries: &Matrix<f64>, k: usize, metric: Metric) -> Vec<Vec<usize>> {
+
+    assert!(m.cols() == queries.cols(), "Number of columns must be equal.");
+    let blocks: Vec<Vec<Vec<usize>>> = row_batches(queries, BLOCK_ROWS).par_iter()
+        .map(|q| block_neighbours(m, q, k, metric))
+        .collect();
+    blocks.into_iter().flat_map(|b| b.into_iter()).collect()
+}
+
+fn block_neighbours(m: &Matrix<f64>, queries: &Matrix<f64>, k: usize, metric: Metric) -> Vec<Vec<usize>> {
+
+    let d = pairwise_distances(queries, m, metric);
+    d.row_iter()
+        .map(|r| {
+            let cmp = |a: &(f64, usize), b: &(f64, usize)|
+                a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal).then(a.1.cmp(&b.1));
+            let mut v: Vec<(f64, usize)> = r.iter().cloned().zip(0..).collect();
+            if v.len() > k && k > 0 {
+                v.select_nth_unstable_by(k - 1, &cmp);
+            }
+            v.truncate(k);
+            v.sort_by(&cmp);
+            v.into_iter().map(|(_, i)| i).collect()
+        })
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -136,6 +209,24 @@ mod tests {
         label = scan(&m, &[1.1, 2.0], 4, |x, y| Euclid::compute(x, y).unwrap()).unwrap();
         assert_eq!(label, vec![0, 2, 1, 3]);
     }
-}
 
+    #[test]
+    fn test_kneighbors() {
+
+        let m = Matrix::from_vec((0..2 * 300).map(|i| ((i * 17) % 101) as f64).collect(), 300, 2);
+        let q = Matrix::from_vec((0..2 * 600).map(|i| ((i * 13) % 97) as f64 + 0.5).collect(), 600, 2);
+
+        let n = kneighbors(&m, &q, 3, Metric::Euclidean);
+        assert_eq!(n.len(), 600);
+        for (i, r) in q.row_iter().enumerate() {
+            let e = scan(&m, r, 3, |x, y| Metric::Euclidean.distance(x, y)).unwrap();
+            // same distances, the order of ties may differ
+            let d = |v: &Vec<usize>| v.iter().map(|&j| Metric::Euclidean.distance(m.row(j).unwrap(), r)).collect::<Vec<f64>>();
+            assert!(d(&n[i]).similar(&d(&e), 1e-9));
+        }
+        assert_eq!(kneighbors(&m, &q, 500, Metric::Manhattan)[0].len(), 300);
 
+        #[cfg(feature = "rayon")]
+        assert_eq!(par_kneighbors(&m, &q, 3, Metric::Euclidean), n);
+    }
+}
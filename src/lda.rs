@@ -0,0 +1,364 @@
+//! Latent Dirichlet allocation (LDA) topic model.
+//!
+//! [Lda](struct.Lda.html) models each document as a mixture of topics and
+//! each topic as a distribution over the words of the vocabulary. The model
+//! is fitted with collapsed Gibbs sampling on a matrix of term counts, e.g.
+//! the output of a [CountVectorizer](../text/struct.CountVectorizer.html)
+//! converted into a [SparseMatrix](../sparse/struct.SparseMatrix.html),
+//! in which each row is a document and each column a word. The counts are
+//! rounded to the nearest integer.
+//!
+//! After fitting, [topic_word](struct.Lda.html#method.topic_word) contains
+//! the word distribution of each topic and
+//! [doc_topic](struct.Lda.html#method.doc_topic) the topic distribution of
+//! each training document. The topic distributions of new documents are
+//! estimated with [transform](struct.Lda.html#method.transform) and the fit
+//! of the model is measured with the
+//! [perplexity](struct.Lda.html#method.perplexity).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::sparse::SparseMatrix;
+//! use rustml::lda::Lda;
+//!
+//! # fn main() {
+//! // the first two documents use the words 0 and 1, the last two the
+//! // words 2 and 3
+//! let counts = SparseMatrix::from_dense(&mat![
+//!     5.0, 3.0, 0.0, 0.0;
+//!     4.0, 6.0, 0.0, 0.0;
+//!     0.0, 0.0, 4.0, 5.0;
+//!     0.0, 0.0, 6.0, 3.0
+//! ]);
+//!
+//! let mut lda = Lda::new(2).iterations(200).seed([1, 2, 3, 4]);
+//! lda.fit(&counts);
+//!
+//! let t = lda.doc_topic();
+//! let topic = |d: usize| if t.get(d, 0).unwrap() > t.get(d, 1).unwrap() { 0 } else { 1 };
+//! assert_eq!(topic(0), topic(1));
+//! assert_eq!(topic(2), topic(3));
+//! assert!(topic(0) != topic(2));
+//! assert!(lda.perplexity(&counts) < 4.0);
+//! # }
+//! ```
+extern crate rand;
+
+use self::rand::{Rng, XorShiftRng};
+
+use matrix::Matrix;
+use sparse::SparseMatrix;
+use rng;
+
+/// LDA topic model fitted with collapsed Gibbs sampling.
+#[derive(Clone, Debug)]
+pub struct Lda {
+    n_topics: usize,
+    alpha: f64,
+    beta: f64,
+    iterations: usize,
+    rng: XorShiftRng,
+    topic_word: Matrix<f64>,
+    doc_topic: Matrix<f64>
+}
+
+impl Lda {
+
+    /// Creates an unfitted model with `n_topics` topics, the symmetric
+    /// Dirichlet priors `alpha = 1 / n_topics` for the topics of a document
+    /// and `beta = 0.01` for the words of a topic and 100 iterations. The
+    /// random number generator is created with [rng](../rng/fn.rng.html).
+    ///
+    /// Panics if `n_topics` is zero.
+    pub fn new(n_topics: usize) -> Lda {
+
+        assert!(n_topics > 0, "Number of topics must be greater than zero.");
+        Lda {
+            n_topics: n_topics,
+            alpha: 1.0 / n_topics as f64,
+            beta: 0.01,
+            iterations: 100,
+            rng: rng::rng(),
+            topic_word: Matrix::new(),
+            doc_topic: Matrix::new()
+        }
+    }
+
+    /// Sets the parameter of the Dirichlet prior of the topic distribution
+    /// of a document.
+    ///
+    /// Panics if `alpha` is not greater than zero.
+    pub fn alpha(&self, alpha: f64) -> Lda {
+
+        assert!(alpha > 0.0, "Alpha must be greater than zero.");
+        Lda { alpha: alpha, .. self.clone() }
+    }
+
+    /// Sets the parameter of the Dirichlet prior of the word distribution
+    /// of a topic.
+    ///
+    /// Panics if `beta` is not greater than zero.
+    pub fn beta(&self, beta: f64) -> Lda {
+
+        assert!(beta > 0.0, "Beta must be greater than zero.");
+        Lda { beta: beta, .. self.clone() }
+    }
+
+    /// Sets the number of Gibbs sweeps over all words of `fit` and
+    /// `transform`.
+    pub fn iterations(&self, n: usize) -> Lda {
+        Lda { iterations: n, .. self.clone() }
+    }
+
+    /// Seeds the random number generator of the sampler.
+    pub fn seed(&self, seed: [u32; 4]) -> Lda {
+        Lda { rng: rng::seeded_rng(seed), .. self.clone() }
+    }
+
+    /// Returns the number of topics.
+    pub fn n_topics(&self) -> usize {
+        self.n_topics
+    }
+
+    /// Returns the word distribution of each topic in the rows, i.e. the
+    /// matrix has one row per topic and one column per word and each row
+    /// sums to one.
+    pub fn topic_word(&self) -> &Matrix<f64> {
+        &self.topic_word
+    }
+
+    /// Returns the topic distribution of each training document in the
+    /// rows.
+    pub fn doc_topic(&self) -> &Matrix<f64> {
+        &self.doc_topic
+    }
+
+    /// Returns the indexes of the `n` most probable words of `topic` in
+    /// decreasing order of their probability.
+    ///
+    /// Panics if the model is not fitted or if the topic does not exist.
+    pub fn top_words(&self, topic: usize, n: usize) -> Vec<usize> {
+
+        assert!(topic < self.topic_word.rows(), "Topic does not exist.");
+        let p = self.topic_word.row(topic).unwrap();
+        let mut idx: Vec<usize> = (0..p.len()).collect();
+        idx.sort_by(|&a, &b| p[b].partial_cmp(&p[a]).unwrap().then(a.cmp(&b)));
+        idx.truncate(n);
+        idx
+    }
+
+    /// Fits the model on the term counts of the documents in the rows of
+    /// `counts`.
+    ///
+    /// Panics if `counts` has no columns.
+    pub fn fit(&mut self, counts: &SparseMatrix<f64>) {
+
+        assert!(counts.cols() > 0, "At least one word is required.");
+        let k = self.n_topics;
+        let v = counts.cols();
+        let docs = tokens(counts);
+
+        let mut nkw = vec![0.0; k * v];
+        let mut nk = vec![0.0; k];
+        let mut ndk = vec![0.0; docs.len() * k];
+        let mut z: Vec<Vec<usize>> = docs.iter().map(|d| d.iter().map(|_| self.rng.gen_range(0, k)).collect()).collect();
+        for (d, doc) in docs.iter().enumerate() {
+            for (&w, &t) in doc.iter().zip(z[d].iter()) {
+                nkw[t * v + w] += 1.0;
+                nk[t] += 1.0;
+                ndk[d * k + t] += 1.0;
+            }
+        }
+
+        let vbeta = v as f64 * self.beta;
+        let mut p = vec![0.0; k];
+        for _ in 0..self.iterations {
+            for (d, doc) in docs.iter().enumerate() {
+                for (i, &w) in doc.iter().enumerate() {
+                    let old = z[d][i];
+                    nkw[old * v + w] -= 1.0;
+                    nk[old] -= 1.0;
+                    ndk[d * k + old] -= 1.0;
+
+                    for t in 0..k {
+                        p[t] = (ndk[d * k + t] + self.alpha) * (nkw[t * v + w] + self.beta) / (nk[t] + vbeta);
+                    }
+                    let t = draw(&p, &mut self.rng);
+
+                    z[d][i] = t;
+                    nkw[t * v + w] += 1.0;
+                    nk[t] += 1.0;
+                    ndk[d * k + t] += 1.0;
+                }
+            }
+        }
+
+        let phi = (0..k * v).map(|i| (nkw[i] + self.beta) / (nk[i / v] + vbeta)).collect();
+        self.topic_word = Matrix::from_vec(phi, k, v);
+        self.doc_topic = theta(&ndk, &docs, k, self.alpha);
+    }
+
+    /// Estimates the topic distributions of the documents in the rows of
+    /// `counts` with Gibbs sampling while the word distributions of the
+    /// topics are fixed.
+    ///
+    /// Panics if the model is not fitted or if the number of columns does
+    /// not match the size of the vocabulary.
+    pub fn transform(&self, counts: &SparseMatrix<f64>) -> Matrix<f64> {
+
+        assert!(self.topic_word.rows() > 0, "Model is not fitted.");
+        assert!(counts.cols() == self.topic_word.cols(), "Number of columns does not match the size of the vocabulary.");
+
+        let k = self.n_topics;
+        let phi = self.topic_word.buf();
+        let v = counts.cols();
+        let docs = tokens(counts);
+        let mut rng = self.rng.clone();
+
+        let mut ndk = vec![0.0; docs.len() * k];
+        let mut p = vec![0.0; k];
+        for (d, doc) in docs.iter().enumerate() {
+            let mut z: Vec<usize> = doc.iter().map(|_| rng.gen_range(0, k)).collect();
+            for &t in &z {
+                ndk[d * k + t] += 1.0;
+            }
+            for _ in 0..self.iterations {
+                for (i, &w) in doc.iter().enumerate() {
+                    ndk[d * k + z[i]] -= 1.0;
+                    for t in 0..k {
+                        p[t] = (ndk[d * k + t] + self.alpha) * phi[t * v + w];
+                    }
+                    z[i] = draw(&p, &mut rng);
+                    ndk[d * k + z[i]] += 1.0;
+                }
+            }
+        }
+        theta(&ndk, &docs, k, self.alpha)
+    }
+
+    /// Computes the perplexity of the model on the documents in the rows of
+    /// `counts`, i.e. `exp(-L / N)` where `L` is the log-likelihood of all
+    /// words given the topic distributions estimated by
+    /// [transform](#method.transform) and `N` is the number of words.
+    /// Lower values are better.
+    ///
+    /// Panics if the model is not fitted, if the number of columns does not
+    /// match the size of the vocabulary or if the documents contain no
+    /// words.
+    pub fn perplexity(&self, counts: &SparseMatrix<f64>) -> f64 {
+
+        let theta = self.transform(counts);
+        let k = self.n_topics;
+        let v = counts.cols();
+        let phi = self.topic_word.buf();
+
+        let mut ll = 0.0;
+        let mut n = 0.0;
+        for (d, (idx, vals)) in counts.row_iter().enumerate() {
+            let t = theta.row(d).unwrap();
+            for (&w, &c) in idx.iter().zip(vals.iter()) {
+                let c = c.round();
+                if c > 0.0 {
+                    ll += c * (0..k).fold(0.0, |acc, j| acc + t[j] * phi[j * v + w]).ln();
+                    n += c;
+                }
+            }
+        }
+        assert!(n > 0.0, "Documents contain no words.");
+        (-ll / n).exp()
+    }
+}
+
+// expands the counts of each document into a list of word indexes
+fn tokens(counts: &SparseMatrix<f64>) -> Vec<Vec<usize>> {
+
+    counts.row_iter().map(|(idx, vals)| {
+        let mut doc = vec![];
+        for (&w, &c) in idx.iter().zip(vals.iter()) {
+            for _ in 0..c.round().max(0.0) as usize {
+                doc.push(w);
+            }
+        }
+        doc
+    }).collect()
+}
+
+// samples an index with a probability proportional to the weights in p
+fn draw(p: &[f64], rng: &mut XorShiftRng) -> usize {
+
+    let s: f64 = p.iter().sum();
+    let u = rng.gen::<f64>() * s;
+    let mut acc = 0.0;
+    for (i, &x) in p.iter().enumerate() {
+        acc += x;
+        if u < acc {
+            return i;
+        }
+    }
+    p.len() - 1
+}
+
+fn theta(ndk: &[f64], docs: &[Vec<usize>], k: usize, alpha: f64) -> Matrix<f64> {
+
+    let mut v = Vec::with_capacity(ndk.len());
+    for (d, doc) in docs.iter().enumerate() {
+        let s = doc.len() as f64 + k as f64 * alpha;
+        v.extend((0..k).map(|t| (ndk[d * k + t] + alpha) / s));
+    }
+    Matrix::from_vec(v, docs.len(), k)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lda() {
+
+        // two topics with disjoint vocabularies {0, 1, 2} and {3, 4, 5}
+        let mut rows = vec![];
+        for d in 0..20 {
+            let off = if d % 2 == 0 { 0 } else { 3 };
+            let mut r = vec![0.0; 6];
+            for j in 0..3 {
+                r[off + j] = ((d + j) % 4 + 1) as f64;
+            }
+            rows.extend(r);
+        }
+        let counts = SparseMatrix::from_dense(&Matrix::from_vec(rows, 20, 6));
+
+        let mut lda = Lda::new(2).iterations(100).seed([1, 2, 3, 4]);
+        lda.fit(&counts);
+        assert_eq!(lda.topic_word().rows(), 2);
+        assert_eq!(lda.doc_topic().rows(), 20);
+        for r in lda.topic_word().row_iter().chain(lda.doc_topic().row_iter()) {
+            assert!((r.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        }
+
+        let a = lda.top_words(0, 3);
+        let mut s = a.clone();
+        s.sort();
+        assert!(s == vec![0, 1, 2] || s == vec![3, 4, 5]);
+
+        // each document is dominated by one topic
+        let t = lda.transform(&counts);
+        for d in 0..20 {
+            let p = t.get(d, 0).unwrap();
+            assert!(*p > 0.9 || *p < 0.1);
+            assert_eq!(*p > 0.5, *lda.doc_topic().get(d, 0).unwrap() > 0.5);
+        }
+
+        // the perplexity of the two topic model is close to 3 words per
+        // topic and lower than the perplexity of a single topic
+        let p2 = lda.perplexity(&counts);
+        let mut one = Lda::new(1).iterations(20).seed([1, 2, 3, 4]);
+        one.fit(&counts);
+        assert!(p2 < 3.5);
+        assert!(one.perplexity(&counts) > 5.0);
+    }
+}
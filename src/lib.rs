@@ -34,15 +34,58 @@
 //!
 //! * [highly optimized linear algebra via BLAS integration](blas/index.html) (i.e. operations on vectors and
 //! matrices)
-//! * gradient descent with debugging capabilities (e.g. with learning curves)
-//! * [neural networks](nn/index.html)
+//! * [sparse matrices](sparse/index.html) in the CSR format
+//! * gradient descent with debugging capabilities (e.g. with learning curves) and [L-BFGS](opt/fn.lbfgs.html)
+//! * [progress reporting](progress/index.html) and logging of long-running fits
+//! * [reproducible experiments](rng/index.html) via seeds and a global seed
+//! * [fallible variants](error/index.html) of functions which panic on invalid arguments
+//! * [timing utilities and reference benchmarks](bench/index.html)
+//! * [offloading of large matrix multiplications](gpu/index.html) to the GPU via cuBLAS
+//! * [neural networks](nn/index.html) and their [quantization](quantize/index.html) to 8-bit integers
+//! * [pooling of temporary buffers](pool/index.html) to avoid allocations in training loops
+//! * [out-of-core training](stream/index.html) on datasets which are larger than the memory
+//! * [checkpoints](checkpoint/index.html) of long training runs which can be resumed after a crash
+//! * [inference of trained models](inference/index.html) on embedded devices (`no_std` with `alloc`)
 //! * DBSCAN clustering algorithm
+//! * [mini-batch k-means](kmeans/index.html) for very large datasets
 //! * linear regression
+//! * [SGD linear models](sgd/index.html) on sparse rows with feature hashing
 //! * optimization of linear regression with gradient descent
+//! * [Bayesian ridge regression](regression/struct.BayesianRidge.html) with evidence maximization and predictive variances
 //! * classification with <i>k</i>-nearest neighbours
 //! * sliding windows for arbitrary dimensions (e.g. for image processing)
 //! * [standard databases](datasets/index.html) (e.g. MNIST database of handwritten digits)
 //! * feature scaling
+//! * [probability distributions](distributions/index.html) (e.g. multivariate normal, gamma, beta, Dirichlet, Poisson) for sampling and density evaluation
+//! * [latent Dirichlet allocation](lda/index.html) topic model fitted with collapsed Gibbs sampling
+//! * [word embeddings](word2vec/index.html) with the skip-gram model and negative sampling
+//! * [n-gram language models](ngram/index.html) with Kneser-Ney smoothing
+//! * [conditional random fields](crf/index.html) for sequence labeling
+//! * [Gaussian process classification](gp/index.html) with the Laplace approximation
+//! * [expectation-maximization](em/index.html) with random restarts (e.g. Gaussian mixture models)
+//! * [mixture of experts](moe/index.html) with neural networks as experts and gating network
+//! * [autoencoders](autoencoder/index.html) with optionally tied weights
+//! * [sample weighting schedules](curriculum/index.html) for curriculum learning and reweighting
+//! * [kernel density estimation](gaussian/index.html#kernel-density-estimation) with Gaussian kernels
+//! * [whitening](whitening/index.html) (PCA and ZCA)
+//! * [incremental PCA](pca/index.html) on mini-batches of streaming data
+//! * [polynomial features](polynomial/index.html)
+//! * [discretization of continuous features](discretization/index.html)
+//! * [univariate feature selection](feature_selection/index.html)
+//! * [encoding of categorical features](encoding/index.html)
+//! * [imputation of missing values](imputation/index.html)
+//! * [bag-of-words and tf-idf features for text documents](text/index.html) and text classification with naive Bayes or logistic regression
+//! * [frames](frame/index.html), i.e. matrices with named and typed columns
+//! * [stacking, blending and voting ensembles](ensemble/index.html) of estimators
+//! * [one-vs-rest and one-vs-one](multiclass/index.html) multiclass classification with binary classifiers
+//! * [multi-output regression](multioutput/index.html) with one regressor per target
+//! * [splitting of datasets and cross-validation](model_selection/index.html) (in parallel with the feature `rayon`)
+//! * [rebalancing of imbalanced classes](sampling/index.html) (e.g. SMOTE)
+//! * [metrics](metrics/index.html) to evaluate models (e.g. confusion matrix)
+//! * [statistics](stats/index.html) for the exploratory analysis of data (e.g. correlation, hypothesis tests, dataset profiles)
+//! * [plots](plot/index.html) with gnuplot (scatter plots, histograms, heatmaps, decision boundaries)
+//! * [image features](imaging/index.html) (loading, resizing and patch extraction in pure Rust)
+//! * serving predictions of fitted models over JSON lines (feature `serve`)
 //! * video and image processing via integration of OpenCV
 //!
 //! # Performance
@@ -56,6 +99,30 @@
 //! documentation on this topic available
 //! [here](https://github.com/daniel-e/rustml/tree/master/build).
 //!
+//! If no BLAS library can be installed the crate
+//! [matrixmultiply](https://crates.io/crates/matrixmultiply) can be used for
+//! matrix multiplications instead (feature `matrixmultiply`, see the module
+//! [backend](backend/index.html)). The same module selects SIMD and
+//! multithreaded vector kernels from the features of the CPU at runtime.
+//!
+//! With the feature `pure-rust` neither BLAS nor OpenCV are required. The
+//! BLAS functions are then [implemented in Rust](blas/index.html) and the
+//! module `opencv` is not available.
+//!
+//! Targets without an operating system like `wasm32-unknown-unknown` are
+//! only supported without the default feature `std`. The crate is then
+//! `no_std` and only the module [inference](inference/index.html) is
+//! available, i.e. the prediction with the types `Network` (feed-forward
+//! neural networks with the built-in activation functions), `Linear`
+//! (linear models) and the dense matrix `Dense`. Matrices, the training of
+//! models and all other modules are not available. Models are trained on
+//! the host with the full library and converted into the types of the
+//! module `inference`, e.g. to run trained networks in the browser:
+//!
+//! ```ignore
+//! cargo build --lib --no-default-features --target wasm32-unknown-unknown
+//! ```
+//!
 //!
 //! # Machine Learning Pipelines with Rustml
 //!
@@ -118,40 +185,172 @@
 //! * [image grid](https://github.com/daniel-e/rustml/blob/master/examples/image_grid.rs): plot some of the 
 //! handwritten digits of the MNIST database into a grid
 //!
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// Without the default feature `std` only the module `inference` is
+// available which requires nothing but `core` and `alloc`.
+extern crate alloc;
+extern crate libm;
+
+#[cfg(feature = "std")]
 pub use distance::{Distance, Euclid, DistancePoint2D};
+#[cfg(feature = "std")]
 pub use matrix::{HasNan, Similar, Trim, Matrix, IntoMatrix};
+#[cfg(feature = "std")]
 pub use math::{Dimension, Normalization, Mean, MeanVec, Sum, Var, SumVec};
+#[cfg(feature = "std")]
 pub use ops::{MatrixScalarOps, Ops, VectorScalarOps, VectorVectorOps, MatrixMatrixOps};
+#[cfg(feature = "std")]
 pub use ops_inplace::{VectorVectorOpsInPlace, MatrixMatrixOpsInPlace};
+#[cfg(feature = "std")]
 pub use gaussian::{GaussianEstimator, GaussianFunctions, Gaussian};
+#[cfg(feature = "std")]
 pub use geometry::{Point2D};
+#[cfg(feature = "std")]
 pub use vectors::{Linspace, VectorIO};
+#[cfg(feature = "std")]
 pub use datasets::{mixture_builder, normal_builder};
 
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate log;
+
 // ordering is important because the macro mat! is 
 // only available for modules which follow #[macro_use]
+#[cfg(feature = "std")]
 #[macro_use]
 pub mod matrix;
 
+#[cfg(feature = "std")]
 pub mod blas;
+#[cfg(feature = "std")]
 pub mod datasets;
+#[cfg(feature = "std")]
 pub mod distance;
+#[cfg(feature = "std")]
 pub mod io;
+#[cfg(feature = "std")]
 pub mod knn;
+#[cfg(feature = "std")]
 pub mod norm;
+#[cfg(feature = "std")]
 pub mod vectors;
+#[cfg(feature = "std")]
 pub mod math;
+#[cfg(feature = "std")]
 pub mod gaussian;
+#[cfg(feature = "std")]
 pub mod ops;
+#[cfg(feature = "std")]
 pub mod consts;
+#[cfg(feature = "std")]
 pub mod ops_inplace;
+#[cfg(feature = "std")]
 pub mod scaling;
+#[cfg(all(feature = "std", not(feature = "pure-rust")))]
 pub mod opencv;
+#[cfg(feature = "std")]
 pub mod geometry;
+#[cfg(feature = "std")]
 pub mod dbscan;
+#[cfg(feature = "std")]
 pub mod sliding;
+#[cfg(feature = "std")]
 pub mod hash;
+#[cfg(feature = "std")]
 pub mod opt;
+#[cfg(feature = "std")]
 pub mod octave;
+#[cfg(feature = "std")]
 pub mod regression;
+#[cfg(feature = "std")]
 pub mod nn;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod imputation;
+#[cfg(feature = "std")]
+pub mod model_selection;
+#[cfg(feature = "std")]
+pub mod polynomial;
+#[cfg(feature = "std")]
+pub mod discretization;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "std")]
+pub mod feature_selection;
+#[cfg(feature = "std")]
+pub mod sampling;
+#[cfg(feature = "std")]
+pub mod linalg;
+#[cfg(feature = "std")]
+pub mod whitening;
+#[cfg(feature = "std")]
+pub mod groupby;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod sparse;
+#[cfg(feature = "std")]
+pub mod plot;
+#[cfg(feature = "std")]
+pub mod imaging;
+#[cfg(feature = "std")]
+pub mod frame;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod gpu;
+#[cfg(feature = "std")]
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod pool;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub mod pca;
+#[cfg(feature = "std")]
+pub mod kmeans;
+#[cfg(feature = "std")]
+pub mod sgd;
+#[cfg(feature = "std")]
+pub mod ensemble;
+#[cfg(feature = "std")]
+pub mod multiclass;
+#[cfg(feature = "std")]
+pub mod multioutput;
+#[cfg(feature = "std")]
+pub mod distributions;
+#[cfg(feature = "std")]
+pub mod lda;
+#[cfg(feature = "std")]
+pub mod word2vec;
+#[cfg(feature = "std")]
+pub mod ngram;
+#[cfg(feature = "std")]
+pub mod crf;
+#[cfg(feature = "std")]
+pub mod gp;
+#[cfg(feature = "std")]
+pub mod em;
+#[cfg(feature = "std")]
+pub mod moe;
+#[cfg(feature = "std")]
+pub mod autoencoder;
+#[cfg(feature = "std")]
+pub mod curriculum;
+#[cfg(feature = "std")]
+pub mod quantize;
+#[cfg(all(feature = "std", feature = "serve"))]
+pub mod serve;
+pub mod inference;
@@ -0,0 +1,578 @@
+//! Module with linear algebra routines that are not covered by BLAS.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::linalg::*;
+//!
+//! # fn main() {
+//! let a = mat![
+//!     2.0, 1.0;
+//!     1.0, 2.0
+//! ];
+//! let (values, vectors) = symmetric_eigen(&a);
+//! assert!(values.similar(&vec![3.0, 1.0], 1e-10));
+//!
+//! // the eigenvectors are stored in the columns
+//! let v = vectors.col(0).unwrap();
+//! assert!((v[0] - v[1]).abs() < 1e-10);
+//! # }
+//! ```
+use math::{Mean, Dimension, Normalization};
+use matrix::Matrix;
+
+/// Computes the covariance matrix of the columns of `m`, i.e. each row is an
+/// observation and each column is a variable.
+///
+/// With `Normalization::N` the sums are divided by the number of rows `n`,
+/// with `Normalization::MinusOne` by `n - 1` (or by `n` if `n` is 1).
+pub fn covariance(m: &Matrix<f64>, nrm: Normalization) -> Matrix<f64> {
+
+    let mean = m.mean(Dimension::Column);
+    let d = m.cols();
+    let n = match nrm {
+        Normalization::MinusOne if m.rows() > 1 => (m.rows() - 1) as f64,
+        _ => m.rows() as f64
+    };
+
+    let mut c = vec![0.0; d * d];
+    for row in m.row_iter() {
+        for i in 0..d {
+            let a = row[i] - mean[i];
+            for j in i..d {
+                c[i * d + j] += a * (row[j] - mean[j]);
+            }
+        }
+    }
+    for i in 0..d {
+        for j in i..d {
+            c[i * d + j] /= n;
+            c[j * d + i] = c[i * d + j];
+        }
+    }
+    Matrix::from_vec(c, d, d)
+}
+
+/// Computes the eigenvalues and eigenvectors of a symmetric matrix with the
+/// cyclic Jacobi method.
+///
+/// Returns the eigenvalues in descending order and a matrix whose columns
+/// are the corresponding eigenvectors (normalized to unit length). Only the
+/// upper triangle of `a` is used.
+///
+/// Panics if `a` is not a square matrix.
+pub fn symmetric_eigen(a: &Matrix<f64>) -> (Vec<f64>, Matrix<f64>) {
+
+    assert!(a.rows() == a.cols(), "Matrix must be a square matrix.");
+
+    let n = a.rows();
+    let mut s = a.buf().clone();
+    for i in 0..n {
+        for j in 0..i {
+            s[i * n + j] = s[j * n + i];
+        }
+    }
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+
+    let scale = s.iter().fold(0.0, |acc: f64, x| acc + x * x);
+    for _ in 0..100 {
+        let mut off = 0.0;
+        for p in 0..n {
+            for q in p + 1..n {
+                off += s[p * n + q] * s[p * n + q];
+            }
+        }
+        if off <= 1e-30 * scale {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = s[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                // rotation which sets the element (p, q) to zero
+                let theta = (s[q * n + q] - s[p * n + p]) / (2.0 * apq);
+                let sign = if theta >= 0.0 { 1.0 } else { -1.0 };
+                let t = sign / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let sn = t * c;
+
+                for k in 0..n {
+                    let (x, y) = (s[k * n + p], s[k * n + q]);
+                    s[k * n + p] = c * x - sn * y;
+                    s[k * n + q] = sn * x + c * y;
+                }
+                for k in 0..n {
+                    let (x, y) = (s[p * n + k], s[q * n + k]);
+                    s[p * n + k] = c * x - sn * y;
+                    s[q * n + k] = sn * x + c * y;
+                }
+                for k in 0..n {
+                    let (x, y) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * x - sn * y;
+                    v[k * n + q] = sn * x + c * y;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| s[j * n + j].partial_cmp(&s[i * n + i]).unwrap());
+
+    let values = order.iter().map(|&i| s[i * n + i]).collect();
+    let mut vectors = Vec::with_capacity(n * n);
+    for k in 0..n {
+        vectors.extend(order.iter().map(|&i| v[k * n + i]));
+    }
+    (values, Matrix::from_vec(vectors, n, n))
+}
+
+/// Computes the Cholesky decomposition `a = L L^T` of a symmetric positive
+/// definite matrix and returns the lower triangular matrix `L`.
+///
+/// Returns `None` if `a` is not positive definite. Only the lower triangle
+/// of `a` is used.
+///
+/// Panics if `a` is not a square matrix.
+pub fn cholesky(a: &Matrix<f64>) -> Option<Matrix<f64>> {
+
+    assert!(a.rows() == a.cols(), "Matrix must be a square matrix.");
+
+    let n = a.rows();
+    let s = a.buf();
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..i + 1 {
+            let sum = (0..j).fold(s[i * n + j], |acc, k| acc - l[i * n + k] * l[j * n + k]);
+            if i == j {
+                if sum <= 0.0 || sum.is_nan() {
+                    return None;
+                }
+                l[i * n + i] = sum.sqrt();
+            } else {
+                l[i * n + j] = sum / l[j * n + j];
+            }
+        }
+    }
+    Some(Matrix::from_vec(l, n, n))
+}
+
+/// Condition number above which the results of solving a linear system are
+/// considered to be unreliable.
+///
+/// About half of the 16 significant digits of a `f64` are lost if the
+/// condition number is `1e8`.
+pub const ILL_CONDITIONED: f64 = 1e8;
+
+/// Returns true if `a` is a square matrix with `|a_ij - a_ji| <= tol` for all
+/// elements.
+pub fn is_symmetric(a: &Matrix<f64>, tol: f64) -> bool {
+
+    if a.rows() != a.cols() {
+        return false;
+    }
+    let n = a.rows();
+    let s = a.buf();
+    (0..n).all(|i| (0..i).all(|j| (s[i * n + j] - s[j * n + i]).abs() <= tol))
+}
+
+/// Returns true if `a` is symmetric and positive definite.
+///
+/// Differences between `a_ij` and `a_ji` due to rounding errors, i.e. up to
+/// `1e-12` times the largest absolute value of `a`, are ignored. The
+/// definiteness is checked with a [Cholesky decomposition](fn.cholesky.html).
+pub fn is_positive_definite(a: &Matrix<f64>) -> bool {
+
+    let m = a.buf().iter().fold(0.0, |acc: f64, x| acc.max(x.abs()));
+    is_symmetric(a, 1e-12 * m) && cholesky(a).is_some()
+}
+
+/// Computes the condition number of `a` with respect to the 2-norm, i.e.
+/// the ratio of the largest and the smallest singular value.
+///
+/// Returns infinity if `a` is singular (or has less rows than columns). A
+/// large condition number (see [ILL_CONDITIONED](constant.ILL_CONDITIONED.html))
+/// means that small changes of the input of a linear system can cause large
+/// changes of its solution.
+///
+/// Panics if `a` is empty.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::linalg::cond;
+///
+/// # fn main() {
+/// assert!((cond(&mat![2.0, 0.0; 0.0, 0.5]) - 4.0).abs() < 1e-10);
+/// assert!(cond(&mat![1.0, 2.0; 2.0, 4.0]) > 1e15);
+/// # }
+/// ```
+pub fn cond(a: &Matrix<f64>) -> f64 {
+
+    assert!(a.rows() > 0 && a.cols() > 0, "Matrix must not be empty.");
+
+    let (n, d) = (a.rows(), a.cols());
+    if n < d {
+        return ::std::f64::INFINITY;
+    }
+    let s = a.buf();
+
+    // singular values are the absolute values of the eigenvalues of a
+    // symmetric matrix and the square roots of the eigenvalues of A^T A
+    // otherwise
+    let sv: Vec<f64> = if is_symmetric(a, 0.0) {
+        symmetric_eigen(a).0.iter().map(|x| x.abs()).collect()
+    } else {
+        let mut ata = vec![0.0; d * d];
+        for k in 0..n {
+            let r = &s[k * d..(k + 1) * d];
+            for i in 0..d {
+                for j in i..d {
+                    ata[i * d + j] += r[i] * r[j];
+                }
+            }
+        }
+        symmetric_eigen(&Matrix::from_vec(ata, d, d)).0.iter().map(|x| x.max(0.0).sqrt()).collect()
+    };
+
+    let max = sv.iter().fold(0.0, |acc: f64, &x| acc.max(x));
+    let min = sv.iter().fold(::std::f64::INFINITY, |acc: f64, &x| acc.min(x));
+    if min == 0.0 || min.is_nan() { ::std::f64::INFINITY } else { max / min }
+}
+
+/// Square band matrix which only stores the elements within the band, i.e.
+/// the element `(i, j)` is zero if `j + lower < i` or `i + upper < j`.
+///
+/// Band matrices are the result of e.g. smoothing splines or
+/// discretizations of differential equations. Solving a linear system
+/// requires `O(n * lower * (lower + upper))` operations instead of `O(n^3)`
+/// for a dense matrix.
+///
+/// # Example
+///
+/// ```
+/// use rustml::linalg::BandMatrix;
+///
+/// // tridiagonal matrix with 2 on the diagonal and -1 next to it
+/// let mut a = BandMatrix::new(4, 1, 1);
+/// for i in 0..4 {
+///     a.set(i, i, 2.0);
+///     if i > 0 {
+///         a.set(i, i - 1, -1.0);
+///         a.set(i - 1, i, -1.0);
+///     }
+/// }
+/// let x = a.solve(&[1.0, 0.0, 0.0, 1.0]).unwrap();
+/// assert!(x.iter().all(|v| (v - 1.0).abs() < 1e-12));
+/// let b = a.mul_vec(&x);
+/// assert!((b[0] - 1.0).abs() < 1e-12 && b[1].abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BandMatrix {
+    n: usize,
+    lower: usize,
+    upper: usize,
+    // row i contains the elements of the columns i - lower to i + upper
+    data: Vec<f64>
+}
+
+impl BandMatrix {
+
+    /// Creates a `n x n` band matrix with `lower` diagonals below and `upper`
+    /// diagonals above the main diagonal which are initialized with zeros.
+    pub fn new(n: usize, lower: usize, upper: usize) -> BandMatrix {
+        BandMatrix {
+            n: n,
+            lower: lower,
+            upper: upper,
+            data: vec![0.0; n * (lower + upper + 1)]
+        }
+    }
+
+    /// Creates a band matrix from the elements of `m` within the band.
+    /// Elements outside the band are ignored.
+    ///
+    /// Panics if `m` is not a square matrix.
+    pub fn from_matrix(m: &Matrix<f64>, lower: usize, upper: usize) -> BandMatrix {
+
+        assert!(m.rows() == m.cols(), "Matrix must be a square matrix.");
+
+        let mut b = BandMatrix::new(m.rows(), lower, upper);
+        for i in 0..b.n {
+            for j in b.columns(i) {
+                b.set(i, j, *m.get(i, j).unwrap());
+            }
+        }
+        b
+    }
+
+    /// Returns the number of rows and columns.
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the number of diagonals below the main diagonal.
+    pub fn lower(&self) -> usize {
+        self.lower
+    }
+
+    /// Returns the number of diagonals above the main diagonal.
+    pub fn upper(&self) -> usize {
+        self.upper
+    }
+
+    /// Returns the element at row `i` and column `j` which is zero outside
+    /// the band.
+    ///
+    /// Panics if `i` or `j` is out of bounds.
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+
+        assert!(i < self.n && j < self.n, "Index out of bounds.");
+        if j + self.lower < i || i + self.upper < j {
+            0.0
+        } else {
+            self.data[self.index(i, j)]
+        }
+    }
+
+    /// Sets the element at row `i` and column `j`.
+    ///
+    /// Panics if `i` or `j` is out of bounds or if the element is outside
+    /// the band.
+    pub fn set(&mut self, i: usize, j: usize, val: f64) {
+
+        assert!(i < self.n && j < self.n, "Index out of bounds.");
+        assert!(j + self.lower >= i && i + self.upper >= j, "Element is outside the band.");
+        let k = self.index(i, j);
+        self.data[k] = val;
+    }
+
+    /// Returns the band matrix as a dense matrix.
+    pub fn to_matrix(&self) -> Matrix<f64> {
+
+        let mut m = vec![0.0; self.n * self.n];
+        for i in 0..self.n {
+            for j in self.columns(i) {
+                m[i * self.n + j] = self.get(i, j);
+            }
+        }
+        Matrix::from_vec(m, self.n, self.n)
+    }
+
+    /// Computes the product of the matrix and the vector `x`.
+    ///
+    /// Panics if the length of `x` does not match the size of the matrix.
+    pub fn mul_vec(&self, x: &[f64]) -> Vec<f64> {
+
+        assert!(x.len() == self.n, "Dimensions do not match.");
+        (0..self.n)
+            .map(|i| self.columns(i).fold(0.0, |acc, j| acc + self.get(i, j) * x[j]))
+            .collect()
+    }
+
+    /// Solves `A x = b` via Gaussian elimination with partial pivoting
+    /// which preserves the band structure.
+    ///
+    /// Returns `None` if the matrix is singular.
+    ///
+    /// Panics if the length of `b` does not match the size of the matrix.
+    pub fn solve(&self, b: &[f64]) -> Option<Vec<f64>> {
+
+        assert!(b.len() == self.n, "Dimensions do not match.");
+
+        let (n, l) = (self.n, self.lower);
+        // pivoting increases the number of upper diagonals by `lower`
+        let u = self.upper + l;
+        let w = l + u + 1;
+        let idx = |i: usize, j: usize| i * w + j + l - i;
+
+        let mut a = vec![0.0; n * w];
+        for i in 0..n {
+            for j in self.columns(i) {
+                a[idx(i, j)] = self.get(i, j);
+            }
+        }
+        let mut x = b.to_vec();
+
+        for k in 0..n {
+            let last = (k + l).min(n - 1);
+            let p = (k + 1..last + 1).fold(k, |p, r| if a[idx(r, k)].abs() > a[idx(p, k)].abs() { r } else { p });
+            let pivot = a[idx(p, k)];
+            if pivot == 0.0 || pivot.is_nan() {
+                return None;
+            }
+            let end = (k + u + 1).min(n);
+            if p != k {
+                for j in k..end {
+                    a.swap(idx(k, j), idx(p, j));
+                }
+                x.swap(k, p);
+            }
+            for r in k + 1..last + 1 {
+                let f = a[idx(r, k)] / pivot;
+                if f == 0.0 {
+                    continue;
+                }
+                for j in k..end {
+                    a[idx(r, j)] -= f * a[idx(k, j)];
+                }
+                x[r] -= f * x[k];
+            }
+        }
+
+        for i in (0..n).rev() {
+            let end = (i + u + 1).min(n);
+            x[i] = (i + 1..end).fold(x[i], |acc, j| acc - a[idx(i, j)] * x[j]) / a[idx(i, i)];
+        }
+        Some(x)
+    }
+
+    // columns of row i which are within the band
+    fn columns(&self, i: usize) -> ::std::ops::Range<usize> {
+        i.saturating_sub(self.lower)..(i + self.upper + 1).min(self.n)
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        i * (self.lower + self.upper + 1) + j + self.lower - i
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use math::Normalization;
+
+    #[test]
+    fn test_covariance() {
+
+        let m = mat![
+            1.0, 2.0;
+            3.0, 6.0;
+            5.0, 10.0
+        ];
+        assert!(covariance(&m, Normalization::MinusOne).similar(&mat![4.0, 8.0; 8.0, 16.0], 1e-10));
+        assert!(covariance(&m, Normalization::N).similar(&mat![8.0 / 3.0, 16.0 / 3.0; 16.0 / 3.0, 32.0 / 3.0], 1e-10));
+    }
+
+    #[test]
+    fn test_symmetric_eigen() {
+
+        let a = mat![
+            4.0, 1.0, 2.0;
+            1.0, 3.0, 0.5;
+            2.0, 0.5, 5.0
+        ];
+        let (values, vectors) = symmetric_eigen(&a);
+        assert!(values[0] >= values[1] && values[1] >= values[2]);
+        assert!((values.iter().fold(0.0, |acc, x| acc + x) - 12.0f64).abs() < 1e-10);
+
+        for (i, &l) in values.iter().enumerate() {
+            let v = vectors.col(i).unwrap();
+            let norm = v.iter().fold(0.0, |acc, x| acc + x * x);
+            assert!((norm - 1.0f64).abs() < 1e-10);
+            for r in 0..3 {
+                let av = (0..3).fold(0.0, |acc, k| acc + a.get(r, k).unwrap() * v[k]);
+                assert!((av - l * v[r]).abs() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cholesky() {
+
+        let a = mat![
+            4.0, 2.0, 2.0;
+            2.0, 5.0, 3.0;
+            2.0, 3.0, 6.0
+        ];
+        let l = cholesky(&a).unwrap();
+        assert!(l.similar(&mat![2.0, 0.0, 0.0; 1.0, 2.0, 0.0; 1.0, 1.0, 2.0], 1e-10));
+
+        assert!(cholesky(&mat![1.0, 2.0; 2.0, 1.0]).is_none());
+        assert!(cholesky(&mat![1.0, 0.0; 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+
+        let a = mat![
+            4.0, 2.0, 2.0;
+            2.0, 5.0, 3.0;
+            2.0, 3.0, 6.0
+        ];
+        assert!(is_symmetric(&a, 0.0));
+        assert!(is_positive_definite(&a));
+        assert!(!is_symmetric(&mat![1.0, 2.0; 2.1, 1.0], 0.05));
+        assert!(is_symmetric(&mat![1.0, 2.0; 2.1, 1.0], 0.2));
+        assert!(!is_symmetric(&mat![1.0, 2.0], 1.0));
+        assert!(!is_positive_definite(&mat![1.0, 2.0; 2.0, 1.0]));
+        assert!(!is_positive_definite(&mat![4.0, 0.0; 3.0, 4.0]));
+        assert!(is_positive_definite(&mat![2.0, 1.0 + 1e-14; 1.0, 2.0]));
+
+        // symmetric, non-symmetric and non-square matrices
+        assert!((cond(&mat![3.0, 0.0; 0.0, -0.5]) - 6.0).abs() < 1e-10);
+        assert!((cond(&mat![1.0, 1.0; 0.0, 1.0]) - (3.0 + 5.0f64.sqrt()) / 2.0).abs() < 1e-10);
+        assert!((cond(&mat![2.0, 0.0; 0.0, 1.0; 0.0, 0.0]) - 2.0).abs() < 1e-10);
+        assert_eq!(cond(&mat![1.0, 2.0, 3.0]), ::std::f64::INFINITY);
+        assert_eq!(cond(&mat![1.0, 0.0; 0.0, 0.0]), ::std::f64::INFINITY);
+        assert!(cond(&mat![1.0, 1.0; 1.0, 1.0 + 1e-10]) > ILL_CONDITIONED);
+    }
+
+    #[test]
+    fn test_band_matrix() {
+
+        let m = mat![
+            4.0, 1.0, 0.0, 0.0, 0.0;
+            2.0, 0.0, 3.0, 0.0, 0.0;
+            1.0, 5.0, 1.0, 2.0, 0.0;
+            0.0, 1.0, 2.0, 6.0, 1.0;
+            0.0, 0.0, 3.0, 1.0, 2.0
+        ];
+        let a = BandMatrix::from_matrix(&m, 2, 1);
+        assert_eq!((a.size(), a.lower(), a.upper()), (5, 2, 1));
+        assert_eq!(a.to_matrix(), m);
+        assert_eq!(a.get(0, 4), 0.0);
+        assert_eq!(a.get(2, 0), 1.0);
+
+        // requires pivoting because of the zero at (1, 1)
+        let x = vec![1.0, -2.0, 0.5, 3.0, -1.0];
+        let b = a.mul_vec(&x);
+        assert!(a.solve(&b).unwrap().similar(&x, 1e-12));
+
+        // lower and upper bandwidth zero
+        let mut d = BandMatrix::new(3, 0, 0);
+        d.set(0, 0, 2.0);
+        d.set(1, 1, 4.0);
+        assert!(d.solve(&[1.0, 1.0, 1.0]).is_none());
+        d.set(2, 2, 1.0);
+        assert_eq!(d.solve(&[1.0, 1.0, 1.0]), Some(vec![0.5, 0.25, 1.0]));
+
+        assert!(BandMatrix::from_matrix(&mat![1.0, 2.0; 2.0, 4.0], 1, 1).solve(&[1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_band_matrix_outside() {
+        BandMatrix::new(3, 1, 0).set(0, 1, 1.0);
+    }
+
+    #[test]
+    fn test_symmetric_eigen_diagonal() {
+
+        let (values, vectors) = symmetric_eigen(&mat![1.0, 0.0; 0.0, 2.0]);
+        assert_eq!(values, vec![2.0, 1.0]);
+        assert_eq!(vectors, mat![0.0, 1.0; 1.0, 0.0]);
+    }
+}
@@ -5,10 +5,12 @@ extern crate num;
 pub mod sum;
 pub mod mean;
 pub mod var;
+pub mod quantile;
 
 pub use self::sum::{Sum, SumVec};
 pub use self::mean::{Mean, MeanVec};
 pub use self::var::Var;
+pub use self::quantile::Quantile;
 
 /// Determines the dimension over which to perform an operation.
 pub enum Dimension {
@@ -0,0 +1,126 @@
+extern crate num;
+
+use std::cmp::Ordering;
+
+// ----------------------------------------------------------------------------
+
+/// Trait to compute quantiles of values.
+pub trait Quantile<T> {
+    /// Computes the `q`-quantile of the values where `q` is in [0, 1].
+    ///
+    /// The values are sorted and the quantile is linearly interpolated
+    /// between the two closest ranks, i.e. for `n` values the quantile is
+    /// the value at the (fractional) position `q * (n - 1)` of the sorted
+    /// values. This is the same definition that is used by default in R and
+    /// NumPy. NaN values are sorted to the end. For an empty vector or slice
+    /// zero is returned.
+    ///
+    /// Panics if `q` is not in [0, 1].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::math::Quantile;
+    ///
+    /// let v = vec![7.0, 1.0, 3.0, 5.0];
+    /// assert_eq!(v.quantile(0.0), 1.0);
+    /// assert_eq!(v.quantile(0.5), 4.0);
+    /// assert_eq!(v.quantile(0.75), 5.5);
+    /// assert_eq!(v.quantile(1.0), 7.0);
+    /// ```
+    fn quantile(&self, q: T) -> T;
+
+    /// Computes the median, i.e. the 0.5-quantile, of the values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::math::Quantile;
+    ///
+    /// assert_eq!(vec![3.0, 1.0, 2.0].median(), 2.0);
+    /// assert_eq!(vec![4.0, 1.0, 2.0, 3.0].median(), 2.5);
+    /// ```
+    fn median(&self) -> T;
+
+    /// Computes the interquartile range, i.e. the difference between the
+    /// 0.75-quantile and the 0.25-quantile.
+    fn iqr(&self) -> T;
+}
+
+macro_rules! quantile_impl {
+    ($($t:ty)*) => ($(
+        impl Quantile<$t> for Vec<$t> {
+
+            fn quantile(&self, q: $t) -> $t { (&self[..]).quantile(q) }
+
+            fn median(&self) -> $t { (&self[..]).median() }
+
+            fn iqr(&self) -> $t { (&self[..]).iqr() }
+        }
+
+        impl Quantile<$t> for [$t] {
+
+            fn quantile(&self, q: $t) -> $t {
+
+                assert!(q >= 0.0 && q <= 1.0, "Quantile must be in [0, 1].");
+
+                if self.len() == 0 {
+                    return 0 as $t;
+                }
+
+                let mut v = self.to_vec();
+                v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(
+                    if a.is_nan() { Ordering::Greater } else { Ordering::Less }
+                ));
+
+                let pos = q * (v.len() - 1) as $t;
+                let i = pos.floor() as usize;
+                let frac = pos - i as $t;
+                if i + 1 < v.len() {
+                    v[i] + (v[i + 1] - v[i]) * frac
+                } else {
+                    v[i]
+                }
+            }
+
+            fn median(&self) -> $t { self.quantile(0.5) }
+
+            fn iqr(&self) -> $t { self.quantile(0.75) - self.quantile(0.25) }
+        }
+    )*)
+}
+
+quantile_impl!{ f32 f64 }
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantile() {
+
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(a.quantile(0.0), 1.0);
+        assert_eq!(a.quantile(0.25), 2.0);
+        assert_eq!(a.quantile(0.5), 3.0);
+        assert_eq!(a.quantile(0.1), 1.4);
+        assert_eq!(a.quantile(1.0), 5.0);
+        assert_eq!(a.iqr(), 2.0);
+
+        let b = [10.0f32, 2.0];
+        assert_eq!(b.median(), 6.0);
+
+        let c: Vec<f64> = vec![];
+        assert_eq!(c.median(), 0.0);
+
+        assert_eq!(vec![4.0].quantile(0.3), 4.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_quantile_panic() {
+        vec![1.0, 2.0].quantile(1.5);
+    }
+}
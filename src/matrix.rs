@@ -12,10 +12,12 @@ use std::str::FromStr;
 use std::ops::Mul;
 use std::slice::{Iter, IterMut};
 use std::cmp::min;
-use self::rand::{thread_rng, Rng, Rand};
+use self::rand::{Rng, Rand};
 use self::num::traits::{Float, Signed};
 
 use ops_inplace::{d_gemm, s_gemm};
+use rng;
+use error::{RustmlError, unwrap_or_panic};
 
 // TODO implement some ops
 // https://doc.rust-lang.org/std/ops/
@@ -380,16 +382,25 @@ impl <T: Clone> Matrix<T> {
     /// # }
     /// ```
     pub fn from_vec(vals: Vec<T>, rows: usize, cols: usize) -> Matrix<T> {
+        unwrap_or_panic(Matrix::try_from_vec(vals, rows, cols))
+    }
 
-        assert!(rows * cols == vals.len(),
-            "Number of elements in vector must be equal to the number of elements in the matrix."
-        );
+    /// Creates a matrix from the vector `vals` like
+    /// [from_vec](#method.from_vec) but returns an error if the number of
+    /// elements does not match the dimensions.
+    pub fn try_from_vec(vals: Vec<T>, rows: usize, cols: usize) -> Result<Matrix<T>, RustmlError> {
 
-        Matrix {
+        if rows * cols != vals.len() {
+            return Err(RustmlError::DimensionMismatch(
+                "Number of elements in vector must be equal to the number of elements in the matrix.".to_string()
+            ));
+        }
+
+        Ok(Matrix {
             nrows: rows,
             ncols: cols,
             data: vals
-        }
+        })
     }
 
     /// Creates a matrix from a vector of column vectors.
@@ -526,6 +537,10 @@ impl <T: Clone> Matrix<T> {
 
     /// Creates a matrix with random values.
     ///
+    /// The values are created with the generator returned by
+    /// [rng::rng](../rng/fn.rng.html), i.e. they are reproducible if a global
+    /// seed has been set.
+    ///
     /// # Example
     /// ```
     /// use rustml::Matrix;
@@ -537,10 +552,15 @@ impl <T: Clone> Matrix<T> {
     /// assert!(m.iter().all(|&x| x <= 1.0 && x >= 0.0));
     /// ```
     pub fn random<R: Rand + Clone>(rows: usize, cols: usize) -> Matrix<R> {
+        Matrix::<T>::random_with_rng(rows, cols, &mut rng::rng())
+    }
+
+    /// Creates a matrix with random values which are created with the given
+    /// random number generator.
+    pub fn random_with_rng<R: Rand + Clone, G: Rng>(rows: usize, cols: usize, rng: &mut G) -> Matrix<R> {
 
-        let mut rng = thread_rng();
         Matrix::from_vec(
-            rng.gen_iter::<R>().take(rows * cols).collect::<Vec<R>>(), 
+            rng.gen_iter::<R>().take(rows * cols).collect::<Vec<R>>(),
             rows, cols
         )
     }
@@ -550,6 +570,13 @@ impl <T: Clone> Matrix<T> {
     /// Returns the internal buffer that is used to store the matrix.
     pub fn buf(&self) -> &Vec<T> { &self.data }
 
+    /// Returns a mutable slice of the elements of the matrix in row-major
+    /// order.
+    pub fn buf_mut(&mut self) -> &mut [T] { &mut self.data }
+
+    /// Consumes the matrix and returns its elements in row-major order.
+    pub fn into_vec(self) -> Vec<T> { self.data }
+
     /// Is equivalent to calling the method `cols()` on the matrix.
     pub fn lead_dim(&self) -> usize { self.cols()  }
 
@@ -731,6 +758,31 @@ impl <T: Clone> Matrix<T> {
         }
     }
 
+    /// Returns a matrix with the rows of this matrix with the given
+    /// indexes. In contrast to [sub_matrix](#method.sub_matrix) the number
+    /// of columns is preserved even if no rows are selected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    ///
+    /// # fn main() {
+    /// let m = mat![1.0, 1.5; 2.0, 2.5; 3.0, 3.5];
+    /// assert_eq!(m.select_rows(&[2, 0]), mat![3.0, 3.5; 1.0, 1.5]);
+    /// assert_eq!(m.select_rows(&[]).cols(), 2);
+    /// # }
+    /// ```
+    pub fn select_rows(&self, rows: &[usize]) -> Matrix<T> {
+
+        let mut v = Vec::with_capacity(rows.len() * self.cols());
+        for r in self.row_iter_of(rows) {
+            v.extend_from_slice(r);
+        }
+        Matrix::from_vec(v, rows.len(), self.cols())
+    }
+
     /// Returns the position where the element at row `row` and column `col`
     /// is stored in the internal vector that is used to store the matrix.
     fn idx(&self, row: usize, col: usize) -> Option<usize> {
@@ -876,18 +928,23 @@ impl <T: Clone> Matrix<T> {
     }
 
     pub fn add_row(&mut self, row: &[T]) {
-        
+        unwrap_or_panic(self.try_add_row(row))
+    }
+
+    /// Appends a row like [add_row](#method.add_row) but returns an error if
+    /// the length of the row does not match the number of columns.
+    pub fn try_add_row(&mut self, row: &[T]) -> Result<(), RustmlError> {
+
         if self.rows() == 0 {
             self.ncols = row.len();
-        } else {
-            if self.cols() != row.len() {
-                panic!("Invalid dimension.");
-            }
+        } else if self.cols() != row.len() {
+            return Err(RustmlError::DimensionMismatch("Invalid dimension.".to_string()));
         }
         self.nrows += 1;
         for i in row {
             self.data.push(i.clone());
         }
+        Ok(())
     }
 
     /// Extends the current matrix by putting the matrix `m` below it.
@@ -1033,6 +1090,20 @@ impl <T: Clone> Matrix<T> {
         self.ncols = cols;
     }
 
+    /// Changes the dimension of the matrix to `rows` x `cols` and sets all
+    /// elements to `value`.
+    ///
+    /// The memory of the matrix is reused, i.e. no memory is allocated if
+    /// the matrix had at least `rows * cols` elements before. This is useful
+    /// for output buffers which are reused in loops.
+    pub fn resize_mut(&mut self, rows: usize, cols: usize, value: T) {
+
+        self.data.clear();
+        self.data.resize(rows * cols, value);
+        self.nrows = rows;
+        self.ncols = cols;
+    }
+
     /// Reshapes the matrix, i.e. modifies the number of rows and columns
     /// and returns the result.
     ///
@@ -1093,6 +1164,29 @@ impl <T: Clone> Matrix<T> {
         m
     }
 
+    /// Swaps the rows `i` and `j` in place.
+    ///
+    /// Panics if one of the rows does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// # use rustml::*;
+    /// # fn main() {
+    /// let mut m = mat![1, 2; 3, 4; 5, 6];
+    /// m.swap_rows(0, 2);
+    /// assert_eq!(m, mat![5, 6; 3, 4; 1, 2]);
+    /// # }
+    /// ```
+    pub fn swap_rows(&mut self, i: usize, j: usize) {
+
+        assert!(i < self.rows() && j < self.rows(), "Row does not exist.");
+
+        for k in 0..self.cols() {
+            self.data.swap(i * self.ncols + k, j * self.ncols + k);
+        }
+    }
 }
 
 // --------------- Iterators ----------------------------------------
@@ -1549,6 +1643,28 @@ mod tests {
         assert_eq!(m.row(1).unwrap().to_vec(), vec![6, 11]);
     }
 
+    #[test]
+    fn test_resize_mut() {
+
+        let mut m = mat![1, 2, 3; 4, 5, 6];
+        m.resize_mut(1, 2, 0);
+        assert!(m.eq(&mat![0, 0]));
+        m.resize_mut(3, 1, 7);
+        assert!(m.eq(&mat![7; 7; 7]));
+    }
+
+    #[test]
+    fn test_try_from_vec() {
+
+        assert!(Matrix::try_from_vec(vec![1, 2, 3], 2, 2).is_err());
+        let mut m = Matrix::try_from_vec(vec![1, 2, 3, 4], 2, 2).unwrap();
+        assert!(m.eq(&mat![1, 2; 3, 4]));
+        assert!(m.try_add_row(&[5]).is_err());
+        assert_eq!(m.rows(), 2);
+        assert!(m.try_add_row(&[5, 6]).is_ok());
+        assert_eq!(m.rows(), 3);
+    }
+
     #[test]
     fn test_add_row() {
 
@@ -1937,6 +2053,19 @@ mod tests {
         assert_eq!(k, mat![8, 9; 13, 14]);
     }
 
+    #[test]
+    fn test_swap_rows() {
+
+        let mut m = mat![
+            1, 2, 3;
+            4, 5, 6
+        ];
+        m.swap_rows(1, 0);
+        assert_eq!(m, mat![4, 5, 6; 1, 2, 3]);
+        m.swap_rows(1, 1);
+        assert_eq!(m, mat![4, 5, 6; 1, 2, 3]);
+    }
+
     /*
     #[test]
     fn test_into_iter() {
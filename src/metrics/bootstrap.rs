@@ -0,0 +1,143 @@
+//! Bootstrap confidence intervals for metrics.
+//!
+//! A score computed on a single test set is only an estimate of the true
+//! performance of a model. The bootstrap estimates the uncertainty of this
+//! score by resampling the pairs of targets and predictions with replacement
+//! and computing the metric on each resample. The percentiles of the scores
+//! of the resamples form a confidence interval.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::metrics::*;
+//!
+//! let y_true = vec![0, 1, 1, 0, 1, 0, 1, 1, 0, 0];
+//! let y_pred = vec![0, 1, 0, 0, 1, 0, 1, 1, 1, 0];
+//!
+//! let ci = bootstrap_ci(&y_true, &y_pred, |t, p| accuracy(t, p), 1000, 0.95, [1, 2, 3, 4]);
+//! assert_eq!(ci.estimate, 0.8);
+//! assert!(ci.lower < 0.8 && ci.upper > 0.8);
+//! ```
+extern crate rand;
+
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+
+use math::Quantile;
+
+/// Confidence interval of a metric.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConfidenceInterval {
+    /// The metric computed on all examples.
+    pub estimate: f64,
+    /// The lower bound of the interval.
+    pub lower: f64,
+    /// The upper bound of the interval.
+    pub upper: f64
+}
+
+/// Computes the metric on `n_resamples` bootstrap resamples of the pairs of
+/// targets and predictions and returns the scores.
+///
+/// Each resample has the same size as the original data and is drawn with
+/// replacement. The random number generator is initialized with `seed`,
+/// which must not be `[0, 0, 0, 0]`.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions or if there are no targets.
+pub fn bootstrap_scores<L, P, F>(y_true: &[L], y_pred: &[P], metric: F, n_resamples: usize,
+        seed: [u32; 4]) -> Vec<f64>
+    where L: Clone, P: Clone, F: Fn(&[L], &[P]) -> f64 {
+
+    assert!(y_true.len() == y_pred.len(), "Number of targets does not match the number of predictions.");
+    assert!(!y_true.is_empty(), "Targets must not be empty.");
+
+    let n = y_true.len();
+    let mut rng = XorShiftRng::from_seed(seed);
+    let mut t = Vec::with_capacity(n);
+    let mut p = Vec::with_capacity(n);
+    (0..n_resamples)
+        .map(|_| {
+            t.clear();
+            p.clear();
+            for _ in 0..n {
+                let i = rng.gen_range(0, n);
+                t.push(y_true[i].clone());
+                p.push(y_pred[i].clone());
+            }
+            metric(&t, &p)
+        })
+        .collect()
+}
+
+/// Computes a percentile bootstrap confidence interval for the metric.
+///
+/// The bounds are the `(1 - confidence) / 2` and `(1 + confidence) / 2`
+/// quantiles of the scores of `n_resamples` bootstrap resamples (see
+/// [`bootstrap_scores`](fn.bootstrap_scores.html)), e.g. a confidence of
+/// `0.95` gives the 2.5th and the 97.5th percentile. Usually at least 1000
+/// resamples are used.
+///
+/// Panics if `confidence` is not in `(0, 1)`, if `n_resamples` is zero, if
+/// the number of targets does not match the number of predictions or if
+/// there are no targets.
+pub fn bootstrap_ci<L, P, F>(y_true: &[L], y_pred: &[P], metric: F, n_resamples: usize,
+        confidence: f64, seed: [u32; 4]) -> ConfidenceInterval
+    where L: Clone, P: Clone, F: Fn(&[L], &[P]) -> f64 {
+
+    assert!(confidence > 0.0 && confidence < 1.0, "Confidence must be in (0, 1).");
+    assert!(n_resamples > 0, "Number of resamples must be at least 1.");
+
+    let scores = bootstrap_scores(y_true, y_pred, &metric, n_resamples, seed);
+    let alpha = (1.0 - confidence) / 2.0;
+    ConfidenceInterval {
+        estimate: metric(y_true, y_pred),
+        lower: scores.quantile(alpha),
+        upper: scores.quantile(1.0 - alpha)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics::{accuracy, mean_absolute_error};
+
+    #[test]
+    fn test_bootstrap_scores() {
+
+        let y = vec![1, 2, 3];
+        let s = bootstrap_scores(&y, &y, |t, p| accuracy(t, p), 20, [1, 2, 3, 4]);
+        assert_eq!(s, vec![1.0; 20]);
+
+        // each score is the mean of three resampled errors
+        let t = vec![0.0, 0.0, 0.0];
+        let p = vec![1.0, 2.0, 3.0];
+        let s = bootstrap_scores(&t, &p, mean_absolute_error, 50, [4, 3, 2, 1]);
+        assert_eq!(s.len(), 50);
+        assert!(s.iter().all(|&x| x >= 1.0 && x <= 3.0));
+        assert!(s.iter().any(|&x| x != 2.0));
+        assert_eq!(s, bootstrap_scores(&t, &p, mean_absolute_error, 50, [4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn test_bootstrap_ci() {
+
+        let t: Vec<f64> = (0..100).map(|_| 0.0).collect();
+        let p: Vec<f64> = (0..100).map(|i| (i % 10) as f64).collect();
+
+        let ci90 = bootstrap_ci(&t, &p, mean_absolute_error, 500, 0.9, [1, 2, 3, 4]);
+        let ci99 = bootstrap_ci(&t, &p, mean_absolute_error, 500, 0.99, [1, 2, 3, 4]);
+        assert_eq!(ci90.estimate, 4.5);
+        assert!(ci90.lower < 4.5 && ci90.upper > 4.5);
+        assert!(ci99.lower < ci90.lower && ci99.upper > ci90.upper);
+        // standard error of the mean is about 0.29
+        assert!(ci90.upper - ci90.lower > 0.5 && ci90.upper - ci90.lower < 1.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bootstrap_ci_panic() {
+        bootstrap_ci(&[1.0], &[1.0], mean_absolute_error, 10, 1.0, [1, 2, 3, 4]);
+    }
+}
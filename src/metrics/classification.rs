@@ -0,0 +1,434 @@
+//! Metrics for classification.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::metrics::*;
+//!
+//! let y_true = vec!["cat", "cat", "dog", "dog", "dog"];
+//! let y_pred = vec!["cat", "dog", "dog", "dog", "cat"];
+//!
+//! assert_eq!(accuracy(&y_true, &y_pred), 0.6);
+//!
+//! let r = classification_report(&y_true, &y_pred);
+//! assert_eq!(r.classes(), &["cat", "dog"]);
+//! assert_eq!(r.scores(1).precision, 2.0 / 3.0);
+//! assert_eq!(r.scores(1).recall, 2.0 / 3.0);
+//! assert_eq!(r.support(), &[2, 3]);
+//! println!("{}", r);
+//! ```
+use std::collections::BTreeSet;
+use std::fmt;
+
+use matrix::Matrix;
+
+/// Computes the fraction of labels that are predicted correctly.
+///
+/// Returns zero if there are no labels.
+///
+/// Panics if the number of true labels does not match the number of
+/// predicted labels.
+pub fn accuracy<L: PartialEq>(y_true: &[L], y_pred: &[L]) -> f64 {
+
+    assert!(y_true.len() == y_pred.len(), "Number of labels does not match.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    y_true.iter().zip(y_pred.iter()).filter(|&(a, b)| a == b).count() as f64 / y_true.len() as f64
+}
+
+/// Computes the confusion matrix.
+///
+/// Returns the sorted labels that occur in `y_true` or `y_pred` and a matrix
+/// in which the element in row `i` and column `j` is the number of examples
+/// with the true label `i` that have been predicted as label `j`.
+///
+/// Panics if the number of true labels does not match the number of
+/// predicted labels.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::metrics::confusion_matrix;
+///
+/// # fn main() {
+/// let (labels, m) = confusion_matrix(&[1, 1, 2, 3], &[1, 2, 2, 1]);
+/// assert_eq!(labels, vec![1, 2, 3]);
+/// assert_eq!(m, mat![
+///     1, 1, 0;
+///     0, 1, 0;
+///     1, 0, 0
+/// ]);
+/// # }
+/// ```
+pub fn confusion_matrix<L: Ord + Clone>(y_true: &[L], y_pred: &[L]) -> (Vec<L>, Matrix<usize>) {
+
+    assert!(y_true.len() == y_pred.len(), "Number of labels does not match.");
+
+    let labels: Vec<L> = y_true.iter().chain(y_pred.iter()).cloned()
+        .collect::<BTreeSet<L>>().into_iter().collect();
+    let n = labels.len();
+
+    let mut m = vec![0; n * n];
+    for (t, p) in y_true.iter().zip(y_pred.iter()) {
+        let i = labels.binary_search(t).unwrap();
+        let j = labels.binary_search(p).unwrap();
+        m[i * n + j] += 1;
+    }
+    (labels, Matrix::from_vec(m, n, n))
+}
+
+/// Precision, recall and F1 score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scores {
+    /// Fraction of the predictions of a class which are correct.
+    pub precision: f64,
+    /// Fraction of the examples of a class which are predicted correctly.
+    pub recall: f64,
+    /// Harmonic mean of precision and recall.
+    pub f1: f64
+}
+
+impl Scores {
+
+    fn from_counts(tp: usize, predicted: usize, actual: usize) -> Scores {
+
+        let precision = ratio(tp, predicted);
+        let recall = ratio(tp, actual);
+        Scores {
+            precision: precision,
+            recall: recall,
+            f1: if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 }
+        }
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 { 0.0 } else { a as f64 / b as f64 }
+}
+
+/// Per-class and averaged precision, recall and F1 scores.
+///
+/// If a class is never predicted its precision is zero, if it does not
+/// occur in the true labels its recall is zero.
+///
+/// The report implements `Display` which formats the scores as a table.
+#[derive(Clone, Debug)]
+pub struct ClassificationReport<L> {
+    classes: Vec<L>,
+    scores: Vec<Scores>,
+    support: Vec<usize>,
+    micro: Scores
+}
+
+impl <L> ClassificationReport<L> {
+
+    /// Returns the (sorted) classes.
+    pub fn classes(&self) -> &[L] {
+        &self.classes
+    }
+
+    /// Returns the scores of the class with the given index.
+    pub fn scores(&self, idx: usize) -> Scores {
+        self.scores[idx]
+    }
+
+    /// Returns the number of examples of each class in the true labels.
+    pub fn support(&self) -> &[usize] {
+        &self.support
+    }
+
+    /// Returns the unweighted mean of the scores of all classes.
+    pub fn macro_avg(&self) -> Scores {
+        self.average(&vec![1; self.scores.len()])
+    }
+
+    /// Returns the scores computed from the total number of true positives,
+    /// false positives and false negatives over all classes.
+    pub fn micro_avg(&self) -> Scores {
+        self.micro
+    }
+
+    /// Returns the mean of the scores of all classes weighted by their
+    /// support.
+    pub fn weighted_avg(&self) -> Scores {
+        self.average(&self.support)
+    }
+
+    fn average(&self, weights: &[usize]) -> Scores {
+
+        let total = weights.iter().fold(0, |acc, w| acc + w) as f64;
+        if total == 0.0 {
+            return Scores { precision: 0.0, recall: 0.0, f1: 0.0 };
+        }
+
+        let (p, r, f) = self.scores.iter().zip(weights.iter())
+            .fold((0.0, 0.0, 0.0), |(p, r, f), (s, &w)| {
+                let w = w as f64;
+                (p + s.precision * w, r + s.recall * w, f + s.f1 * w)
+            });
+        Scores {
+            precision: p / total,
+            recall: r / total,
+            f1: f / total
+        }
+    }
+}
+
+impl <L: fmt::Display> fmt::Display for ClassificationReport<L> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        let total = self.support.iter().fold(0, |acc, s| acc + s);
+        let w = self.classes.iter().map(|c| c.to_string().len()).max().unwrap_or(0).max(12);
+
+        try!(writeln!(f, "{:>w$} {:>9} {:>9} {:>9} {:>9}", "", "precision", "recall", "f1-score", "support", w = w));
+        for (i, c) in self.classes.iter().enumerate() {
+            let s = self.scores[i];
+            try!(writeln!(f, "{:>w$} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+                c.to_string(), s.precision, s.recall, s.f1, self.support[i], w = w));
+        }
+        try!(writeln!(f, ""));
+        for &(name, s) in &[("micro avg", self.micro_avg()), ("macro avg", self.macro_avg()),
+                            ("weighted avg", self.weighted_avg())] {
+            try!(writeln!(f, "{:>w$} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+                name, s.precision, s.recall, s.f1, total, w = w));
+        }
+        Ok(())
+    }
+}
+
+/// Computes the precision, recall, F1 score and support of each class that
+/// occurs in `y_true` or `y_pred` as well as their micro, macro and
+/// weighted averages.
+///
+/// Panics if the number of true labels does not match the number of
+/// predicted labels.
+pub fn classification_report<L: Ord + Clone>(y_true: &[L], y_pred: &[L]) -> ClassificationReport<L> {
+
+    let (classes, m) = confusion_matrix(y_true, y_pred);
+    let n = classes.len();
+
+    let support: Vec<usize> = (0..n).map(|i| m.row(i).unwrap().iter().fold(0, |a, b| a + b)).collect();
+    let predicted: Vec<usize> = (0..n).map(|j| (0..n).fold(0, |a, i| a + m.get(i, j).unwrap())).collect();
+    let tp: Vec<usize> = (0..n).map(|i| *m.get(i, i).unwrap()).collect();
+
+    let scores = (0..n).map(|i| Scores::from_counts(tp[i], predicted[i], support[i])).collect();
+    let sum = |v: &[usize]| v.iter().fold(0, |a, b| a + b);
+
+    ClassificationReport {
+        classes: classes,
+        scores: scores,
+        micro: Scores::from_counts(sum(&tp), sum(&predicted), sum(&support)),
+        support: support
+    }
+}
+
+/// Computes the mean binary cross-entropy (log loss) of predicted
+/// probabilities.
+///
+/// The element `p[i]` is the predicted probability that example `i` belongs
+/// to the positive class and `y_true[i]` is 1 for the positive class and 0
+/// for the negative class. The probabilities are clipped to `[eps, 1 - eps]`
+/// to avoid infinite losses.
+///
+/// Panics if the number of labels does not match the number of
+/// probabilities.
+///
+/// # Example
+///
+/// ```
+/// use rustml::metrics::binary_log_loss;
+///
+/// let l = binary_log_loss(&[1.0, 0.0], &[0.8, 0.4], 1e-15);
+/// assert!((l - (-(0.8f64.ln() + 0.6f64.ln()) / 2.0)).abs() < 1e-12);
+/// ```
+pub fn binary_log_loss(y_true: &[f64], p: &[f64], eps: f64) -> f64 {
+
+    assert!(y_true.len() == p.len(), "Number of labels does not match the number of probabilities.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    let sum = y_true.iter().zip(p.iter()).fold(0.0, |acc, (&y, &p)| {
+        let p = clip(p, eps);
+        acc - (y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+    });
+    sum / y_true.len() as f64
+}
+
+/// Computes the mean multiclass cross-entropy (log loss) of predicted
+/// probabilities.
+///
+/// Row `i` of `probs` contains the predicted probabilities of each class for
+/// example `i` and `y_true[i]` is the index of its true class (e.g. created
+/// with a [`LabelEncoder`](../encoding/struct.LabelEncoder.html)). The
+/// probabilities are clipped to `[eps, 1 - eps]` to avoid infinite losses.
+///
+/// Panics if the number of labels does not match the number of rows or if
+/// a label is not a valid column index.
+pub fn log_loss(y_true: &[usize], probs: &Matrix<f64>, eps: f64) -> f64 {
+
+    assert!(y_true.len() == probs.rows(), "Number of labels does not match the number of rows.");
+    assert!(y_true.iter().all(|&y| y < probs.cols()), "Invalid class index.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    let sum = y_true.iter().zip(probs.row_iter())
+        .fold(0.0, |acc, (&y, row)| acc - clip(row[y], eps).ln());
+    sum / y_true.len() as f64
+}
+
+fn clip(p: f64, eps: f64) -> f64 {
+    p.max(eps).min(1.0 - eps)
+}
+
+/// Computes the Brier score, i.e. the mean squared difference between the
+/// predicted probabilities of the positive class and the true labels (1 for
+/// the positive class, 0 for the negative class).
+///
+/// Panics if the number of labels does not match the number of
+/// probabilities.
+///
+/// # Example
+///
+/// ```
+/// use rustml::metrics::brier_score;
+///
+/// assert!((brier_score(&[1.0, 0.0], &[0.8, 0.4]) - 0.1).abs() < 1e-12);
+/// ```
+pub fn brier_score(y_true: &[f64], p: &[f64]) -> f64 {
+
+    assert!(y_true.len() == p.len(), "Number of labels does not match the number of probabilities.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    y_true.iter().zip(p.iter()).fold(0.0, |acc, (y, p)| acc + (p - y) * (p - y)) / y_true.len() as f64
+}
+
+/// Computes the multiclass Brier score, i.e. the mean over all examples of
+/// the squared Euclidean distance between the predicted probabilities and
+/// the one-hot encoded true class.
+///
+/// Panics if the number of labels does not match the number of rows or if
+/// a label is not a valid column index.
+pub fn multiclass_brier_score(y_true: &[usize], probs: &Matrix<f64>) -> f64 {
+
+    assert!(y_true.len() == probs.rows(), "Number of labels does not match the number of rows.");
+    assert!(y_true.iter().all(|&y| y < probs.cols()), "Invalid class index.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    let sum = y_true.iter().zip(probs.row_iter()).fold(0.0, |acc, (&y, row)| {
+        acc + row.iter().enumerate().fold(0.0, |a, (k, &p)| {
+            let t = if k == y { 1.0 } else { 0.0 };
+            a + (p - t) * (p - t)
+        })
+    });
+    sum / y_true.len() as f64
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_accuracy() {
+
+        assert_eq!(accuracy(&[1, 2, 3, 4], &[1, 2, 0, 0]), 0.5);
+        assert_eq!(accuracy::<u8>(&[], &[]), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_accuracy_panic() {
+        accuracy(&[1, 2], &[1]);
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+
+        let (l, m) = confusion_matrix(&['b', 'a', 'b', 'b'], &['b', 'b', 'c', 'b']);
+        assert_eq!(l, vec!['a', 'b', 'c']);
+        assert_eq!(m, mat![
+            0, 1, 0;
+            0, 2, 1;
+            0, 0, 0
+        ]);
+    }
+
+    #[test]
+    fn test_classification_report() {
+
+        let y_true = vec![0, 0, 0, 0, 1, 1, 2];
+        let y_pred = vec![0, 0, 1, 2, 1, 1, 1];
+        let r = classification_report(&y_true, &y_pred);
+
+        assert_eq!(r.classes(), &[0, 1, 2]);
+        assert_eq!(r.support(), &[4, 2, 1]);
+        assert_eq!(r.scores(0), Scores { precision: 1.0, recall: 0.5, f1: 2.0 / 3.0 });
+        assert_eq!(r.scores(1), Scores { precision: 0.5, recall: 1.0, f1: 2.0 / 3.0 });
+        assert_eq!(r.scores(2), Scores { precision: 0.0, recall: 0.0, f1: 0.0 });
+
+        let m = r.micro_avg();
+        assert_eq!(m.precision, 4.0 / 7.0);
+        assert_eq!(m.recall, 4.0 / 7.0);
+
+        let m = r.macro_avg();
+        assert!((m.precision - 0.5).abs() < 1e-12);
+        assert!((m.recall - 0.5).abs() < 1e-12);
+        assert!((m.f1 - 4.0 / 9.0).abs() < 1e-12);
+
+        let w = r.weighted_avg();
+        assert!((w.precision - 5.0 / 7.0).abs() < 1e-12);
+        assert!((w.recall - 4.0 / 7.0).abs() < 1e-12);
+
+        let s = format!("{}", r);
+        assert!(s.contains("precision"));
+        assert!(s.contains("weighted avg"));
+        assert_eq!(s.lines().count(), 8);
+    }
+
+    #[test]
+    fn test_log_loss() {
+
+        let probs = mat![
+            0.7, 0.2, 0.1;
+            0.1, 0.1, 0.8;
+            0.0, 1.0, 0.0
+        ];
+        let l = log_loss(&[0, 2, 1], &probs, 1e-15);
+        assert!((l + (0.7f64.ln() + 0.8f64.ln()) / 3.0).abs() < 1e-12);
+
+        // clipping avoids an infinite loss
+        let l = log_loss(&[0], &mat![0.0, 1.0], 1e-15);
+        assert!((l + 1e-15f64.ln()).abs() < 1e-9);
+
+        // binary and multiclass log loss are equal for two classes
+        let b = binary_log_loss(&[1.0, 0.0, 1.0], &[0.9, 0.3, 0.6], 1e-15);
+        let m = log_loss(&[1, 0, 1], &mat![0.1, 0.9; 0.7, 0.3; 0.4, 0.6], 1e-15);
+        assert!((b - m).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_brier_score() {
+
+        assert_eq!(brier_score(&[1.0, 0.0, 1.0, 0.0], &[1.0, 0.0, 0.5, 0.5]), 0.125);
+        assert_eq!(multiclass_brier_score(&[0, 1], &mat![1.0, 0.0; 0.5, 0.5]), 0.25);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_log_loss_panic() {
+        log_loss(&[2], &mat![0.5, 0.5], 1e-15);
+    }
+}
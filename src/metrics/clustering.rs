@@ -0,0 +1,314 @@
+//! Metrics for clustering.
+//!
+//! The silhouette score, the Davies-Bouldin index and the Calinski-Harabasz
+//! index evaluate a clustering of the examples in the rows of a matrix
+//! without knowing the true assignment (the distances are Euclidean
+//! distances). The adjusted Rand index and the normalized mutual
+//! information compare a clustering with a known assignment. The labels can
+//! be of any ordered type, e.g. the cluster indexes returned by
+//! [`dbscan`](../../dbscan/fn.dbscan.html).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::metrics::*;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     0.0, 0.0;
+//!     0.0, 1.0;
+//!     5.0, 5.0;
+//!     5.0, 6.0
+//! ];
+//! let labels = vec![0, 0, 1, 1];
+//!
+//! assert!(silhouette_score(&x, &labels) > 0.8);
+//! assert!(davies_bouldin_score(&x, &labels) < 0.2);
+//!
+//! // the names of the clusters do not matter
+//! assert_eq!(adjusted_rand_score(&labels, &[7, 7, 3, 3]), 1.0);
+//! assert_eq!(normalized_mutual_info_score(&labels, &[7, 7, 3, 3]), 1.0);
+//! # }
+//! ```
+use matrix::Matrix;
+use groupby::{GroupBy, label_indexes};
+
+fn dist(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + (x - y) * (x - y)).sqrt()
+}
+
+fn check<L>(x: &Matrix<f64>, labels: &[L]) {
+    assert!(x.rows() == labels.len(), "Number of rows does not match the number of labels.");
+}
+
+/// Computes the silhouette coefficient of each example.
+///
+/// For an example let `a` be the mean distance to the other examples of its
+/// cluster and `b` the smallest mean distance to the examples of another
+/// cluster. The silhouette coefficient is `(b - a) / max(a, b)`, i.e. a value
+/// between -1 and 1 where larger values indicate that the example fits well
+/// into its cluster. Examples which are the only member of their cluster get
+/// the coefficient 0.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if the number of clusters is not within `[2, n - 1]` where `n` is the
+/// number of examples.
+pub fn silhouette_samples<L: Ord>(x: &Matrix<f64>, labels: &[L]) -> Vec<f64> {
+
+    check(x, labels);
+    let (c, k) = label_indexes(labels);
+    let n = labels.len();
+    assert!(k >= 2 && k < n, "Number of clusters must be within [2, n - 1].");
+
+    let mut size = vec![0; k];
+    for &i in &c {
+        size[i] += 1;
+    }
+
+    (0..n).map(|i| {
+        let mut sums = vec![0.0; k];
+        let a = x.row(i).unwrap();
+        for j in 0..n {
+            if j != i {
+                sums[c[j]] += dist(a, x.row(j).unwrap());
+            }
+        }
+        if size[c[i]] == 1 {
+            return 0.0;
+        }
+        let a = sums[c[i]] / (size[c[i]] - 1) as f64;
+        let b = (0..k)
+            .filter(|&l| l != c[i])
+            .map(|l| sums[l] / size[l] as f64)
+            .fold(::std::f64::INFINITY, |acc, v| acc.min(v));
+        let m = a.max(b);
+        if m > 0.0 { (b - a) / m } else { 0.0 }
+    })
+    .collect()
+}
+
+/// Computes the mean silhouette coefficient of all examples (see
+/// [`silhouette_samples`](fn.silhouette_samples.html)).
+pub fn silhouette_score<L: Ord>(x: &Matrix<f64>, labels: &[L]) -> f64 {
+
+    let s = silhouette_samples(x, labels);
+    s.iter().fold(0.0, |acc, v| acc + v) / s.len() as f64
+}
+
+/// Computes the Davies-Bouldin index.
+///
+/// For each cluster `i` let `s_i` be the mean distance of its examples to its
+/// centroid and `d_ij` the distance between the centroids of cluster `i` and
+/// `j`. The index is the mean over all clusters of `max_j (s_i + s_j) / d_ij`.
+/// Smaller values indicate a better separation of the clusters; the
+/// minimum is zero.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if there are less than two clusters.
+pub fn davies_bouldin_score<L: Ord + Clone>(x: &Matrix<f64>, labels: &[L]) -> f64 {
+
+    check(x, labels);
+    let g = GroupBy::new(x, labels);
+    let k = g.len();
+    assert!(k >= 2, "At least two clusters are required.");
+
+    let centroids = g.means();
+    let s: Vec<f64> = g.matrices().iter().zip(centroids.iter())
+        .map(|(m, c)| m.row_iter().fold(0.0, |acc, r| acc + dist(r, c)) / m.rows() as f64)
+        .collect();
+
+    let sum = (0..k).fold(0.0, |acc, i| {
+        acc + (0..k)
+            .filter(|&j| j != i)
+            .map(|j| {
+                let d = dist(&centroids[i], &centroids[j]);
+                if d > 0.0 { (s[i] + s[j]) / d } else { 0.0 }
+            })
+            .fold(0.0, |a: f64, v| a.max(v))
+    });
+    sum / k as f64
+}
+
+/// Computes the Calinski-Harabasz index (variance ratio criterion), i.e. the
+/// ratio of the dispersion between the clusters and the dispersion within
+/// the clusters, each divided by its degrees of freedom. Larger values
+/// indicate denser and better separated clusters. If the dispersion within
+/// the clusters is zero the index is 1.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if the number of clusters is not within `[2, n - 1]` where `n` is the
+/// number of examples.
+pub fn calinski_harabasz_score<L: Ord + Clone>(x: &Matrix<f64>, labels: &[L]) -> f64 {
+
+    check(x, labels);
+    let g = GroupBy::new(x, labels);
+    let n = labels.len();
+    let k = g.len();
+    assert!(k >= 2 && k < n, "Number of clusters must be within [2, n - 1].");
+
+    let mean: Vec<f64> = (0..x.cols())
+        .map(|j| x.row_iter().fold(0.0, |acc, r| acc + r[j]) / n as f64)
+        .collect();
+    let centroids = g.means();
+
+    let between = g.counts().iter().zip(centroids.iter())
+        .fold(0.0, |acc, (&c, m)| acc + c as f64 * dist(m, &mean).powi(2));
+    let within = g.matrices().iter().zip(centroids.iter())
+        .fold(0.0, |acc, (m, c)| acc + m.row_iter().fold(0.0, |a, r| a + dist(r, c).powi(2)));
+
+    if within == 0.0 {
+        1.0
+    } else {
+        (between / (k - 1) as f64) / (within / (n - k) as f64)
+    }
+}
+
+/// Returns the contingency table of two clusterings, the sizes of the
+/// clusters of both clusterings and the number of examples.
+fn contingency<L: Ord, M: Ord>(a: &[L], b: &[M]) -> (Vec<Vec<f64>>, Vec<f64>, Vec<f64>, f64) {
+
+    assert!(a.len() == b.len(), "Number of labels does not match.");
+
+    let (ca, ka) = label_indexes(a);
+    let (cb, kb) = label_indexes(b);
+    let mut t = vec![vec![0.0; kb]; ka];
+    for (&i, &j) in ca.iter().zip(cb.iter()) {
+        t[i][j] += 1.0;
+    }
+    let ra = t.iter().map(|r| r.iter().fold(0.0, |acc, v| acc + v)).collect();
+    let rb = (0..kb).map(|j| t.iter().fold(0.0, |acc, r| acc + r[j])).collect();
+    (t, ra, rb, a.len() as f64)
+}
+
+/// Computes the adjusted Rand index between two clusterings.
+///
+/// The Rand index is the fraction of pairs of examples on which both
+/// clusterings agree (i.e. both put the pair into the same cluster or both
+/// put the pair into different clusters). The adjusted Rand index corrects
+/// this value for chance: it is 1 for identical clusterings (up to the names
+/// of the clusters) and close to 0 for random clusterings.
+///
+/// Panics if the number of labels does not match.
+pub fn adjusted_rand_score<L: Ord, M: Ord>(labels_true: &[L], labels_pred: &[M]) -> f64 {
+
+    let (t, ra, rb, n) = contingency(labels_true, labels_pred);
+    let comb2 = |x: f64| x * (x - 1.0) / 2.0;
+
+    let index = t.iter().fold(0.0, |acc, r| acc + r.iter().fold(0.0, |a, &v| a + comb2(v)));
+    let sa = ra.iter().fold(0.0, |acc, &v| acc + comb2(v));
+    let sb = rb.iter().fold(0.0, |acc, &v| acc + comb2(v));
+    let total = comb2(n);
+    if total == 0.0 {
+        return 1.0;
+    }
+
+    let expected = sa * sb / total;
+    let max = (sa + sb) / 2.0;
+    if max == expected {
+        1.0
+    } else {
+        (index - expected) / (max - expected)
+    }
+}
+
+/// Computes the normalized mutual information between two clusterings.
+///
+/// The mutual information of both clusterings is divided by the arithmetic
+/// mean of their entropies. The result is 1 for identical clusterings (up to
+/// the names of the clusters) and 0 for independent clusterings. If both
+/// clusterings consist of a single cluster the result is 1.
+///
+/// Panics if the number of labels does not match.
+pub fn normalized_mutual_info_score<L: Ord, M: Ord>(labels_true: &[L], labels_pred: &[M]) -> f64 {
+
+    let (t, ra, rb, n) = contingency(labels_true, labels_pred);
+    if n == 0.0 {
+        return 1.0;
+    }
+
+    let entropy = |v: &[f64]| v.iter()
+        .filter(|&&c| c > 0.0)
+        .fold(0.0, |acc, &c| acc - c / n * (c / n).ln());
+    let (ha, hb) = (entropy(&ra), entropy(&rb));
+    if ha == 0.0 && hb == 0.0 {
+        return 1.0;
+    }
+
+    let mut mi = 0.0;
+    for (i, r) in t.iter().enumerate() {
+        for (j, &c) in r.iter().enumerate() {
+            if c > 0.0 {
+                mi += c / n * (c * n / (ra[i] * rb[j])).ln();
+            }
+        }
+    }
+    (mi / ((ha + hb) / 2.0)).max(0.0).min(1.0)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_silhouette() {
+
+        let x = mat![0.0; 1.0; 4.0; 6.0];
+        let s = silhouette_samples(&x, &['a', 'a', 'b', 'b']);
+
+        // a = 1, b = (4 + 6) / 2 = 5 => 4 / 5
+        assert!((s[0] - 0.8).abs() < 1e-12);
+        // a = 2, b = (4 + 3) / 2 = 3.5 => 1.5 / 3.5
+        assert!((s[2] - 1.5 / 3.5).abs() < 1e-12);
+
+        // singleton cluster
+        let s = silhouette_samples(&x, &[0, 0, 0, 1]);
+        assert_eq!(s[3], 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_silhouette_panic() {
+        silhouette_score(&mat![0.0; 1.0], &[0, 0]);
+    }
+
+    #[test]
+    fn test_davies_bouldin() {
+
+        // s = [0.5, 0.5], d = 4 => (0.5 + 0.5) / 4
+        let x = mat![0.0; 1.0; 4.0; 5.0];
+        assert_eq!(davies_bouldin_score(&x, &[0, 0, 1, 1]), 0.25);
+    }
+
+    #[test]
+    fn test_calinski_harabasz() {
+
+        // between = 2 * 2^2 + 2 * 2^2 = 16, within = 4 * 0.25 = 1
+        let x = mat![0.0; 1.0; 4.0; 5.0];
+        assert_eq!(calinski_harabasz_score(&x, &[0, 0, 1, 1]), 32.0);
+        assert_eq!(calinski_harabasz_score(&mat![0.0; 0.0; 1.0], &[0, 0, 1]), 1.0);
+    }
+
+    #[test]
+    fn test_adjusted_rand_score() {
+
+        assert_eq!(adjusted_rand_score(&[0, 0, 1, 1], &[1, 1, 0, 0]), 1.0);
+        assert!((adjusted_rand_score(&[0, 0, 1, 2], &[0, 0, 1, 1]) - 4.0 / 7.0).abs() < 1e-12);
+        assert!(adjusted_rand_score(&[0, 0, 1, 1], &[0, 1, 0, 1]) < 0.0);
+    }
+
+    #[test]
+    fn test_normalized_mutual_info_score() {
+
+        assert_eq!(normalized_mutual_info_score(&[0, 0, 1, 1], &[5, 5, 2, 2]), 1.0);
+        assert_eq!(normalized_mutual_info_score(&[0, 0, 1, 1], &[0, 1, 0, 1]), 0.0);
+        assert_eq!(normalized_mutual_info_score(&[0, 0], &[1, 1]), 1.0);
+
+        let v = normalized_mutual_info_score(&[0, 0, 1, 2], &[0, 0, 1, 1]);
+        assert!(v > 0.0 && v < 1.0);
+    }
+}
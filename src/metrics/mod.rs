@@ -0,0 +1,24 @@
+//! Module with metrics to evaluate the predictions of models.
+//!
+//! All metrics expect the true labels (or values) as the first argument and
+//! the predicted labels (or values) as the second argument, so that they can
+//! be used directly as the metric of
+//! [`cross_val_score`](../model_selection/fn.cross_val_score.html).
+
+pub mod classification;
+pub mod regression;
+pub mod ranking;
+pub mod clustering;
+pub mod bootstrap;
+
+pub use self::classification::{accuracy, confusion_matrix, classification_report,
+    ClassificationReport, Scores, log_loss, binary_log_loss, brier_score,
+    multiclass_brier_score};
+pub use self::regression::{mean_absolute_error, mean_squared_error, root_mean_squared_error,
+    mean_absolute_percentage_error, r2_score, explained_variance_score, raw_values,
+    uniform_average};
+pub use self::ranking::{top_k_accuracy, reciprocal_rank, mean_reciprocal_rank, dcg_at_k,
+    ndcg_at_k, ndcg_score};
+pub use self::clustering::{silhouette_samples, silhouette_score, davies_bouldin_score,
+    calinski_harabasz_score, adjusted_rand_score, normalized_mutual_info_score};
+pub use self::bootstrap::{bootstrap_scores, bootstrap_ci, ConfidenceInterval};
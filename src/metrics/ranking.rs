@@ -0,0 +1,186 @@
+//! Metrics for rankings and scores.
+//!
+//! The functions [`reciprocal_rank`](fn.reciprocal_rank.html) and
+//! [`ndcg_at_k`](fn.ndcg_at_k.html) expect the relevances of the items in
+//! the order in which they have been ranked by a model, i.e. the first
+//! element is the relevance of the item on the first position. An item with
+//! a relevance larger than zero is relevant.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::metrics::*;
+//!
+//! // two queries: the first relevant item is on position 2 and 1
+//! let lists = vec![vec![0.0, 1.0, 0.0], vec![1.0, 0.0, 1.0]];
+//! assert_eq!(mean_reciprocal_rank(&lists), 0.75);
+//!
+//! // perfect ranking
+//! assert_eq!(ndcg_at_k(&[3.0, 2.0, 0.0], 3), 1.0);
+//! assert!(ndcg_at_k(&[0.0, 2.0, 3.0], 3) < 1.0);
+//! ```
+use std::cmp::Ordering;
+
+use matrix::Matrix;
+
+/// Computes the fraction of examples for which the true class is among the
+/// `k` classes with the highest scores.
+///
+/// Row `i` of `scores` contains the score (e.g. the probability) of each
+/// class for example `i` and `y_true[i]` is the index of its true class. If
+/// several classes have the same score as the true class the true class is
+/// ranked before them.
+///
+/// Panics if the number of labels does not match the number of rows or if
+/// a label is not a valid column index.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::metrics::top_k_accuracy;
+///
+/// # fn main() {
+/// let scores = mat![
+///     0.5, 0.3, 0.2;
+///     0.1, 0.2, 0.7;
+///     0.3, 0.4, 0.3
+/// ];
+/// assert_eq!(top_k_accuracy(&[0, 1, 2], &scores, 1), 1.0 / 3.0);
+/// assert_eq!(top_k_accuracy(&[0, 1, 2], &scores, 2), 1.0);
+/// # }
+/// ```
+pub fn top_k_accuracy(y_true: &[usize], scores: &Matrix<f64>, k: usize) -> f64 {
+
+    assert!(y_true.len() == scores.rows(), "Number of labels does not match the number of rows.");
+    assert!(y_true.iter().all(|&y| y < scores.cols()), "Invalid class index.");
+
+    if y_true.is_empty() {
+        return 0.0;
+    }
+    let hits = y_true.iter().zip(scores.row_iter())
+        .filter(|&(&y, row)| row.iter().filter(|&&s| s > row[y]).count() < k)
+        .count();
+    hits as f64 / y_true.len() as f64
+}
+
+/// Computes the reciprocal rank of a ranked list, i.e. `1 / p` where `p` is
+/// the (1-based) position of the first relevant item. Returns zero if the
+/// list contains no relevant item.
+pub fn reciprocal_rank(relevance: &[f64]) -> f64 {
+
+    match relevance.iter().position(|&r| r > 0.0) {
+        Some(p) => 1.0 / (p + 1) as f64,
+        None => 0.0
+    }
+}
+
+/// Computes the mean reciprocal rank (MRR) of several ranked lists (e.g. one
+/// list per query).
+pub fn mean_reciprocal_rank(lists: &[Vec<f64>]) -> f64 {
+
+    if lists.is_empty() {
+        return 0.0;
+    }
+    lists.iter().fold(0.0, |acc, l| acc + reciprocal_rank(l)) / lists.len() as f64
+}
+
+/// Computes the discounted cumulative gain of the first `k` items of a
+/// ranked list, i.e. the sum of `relevance[i] / log2(i + 2)`.
+pub fn dcg_at_k(relevance: &[f64], k: usize) -> f64 {
+
+    relevance.iter().take(k).enumerate()
+        .fold(0.0, |acc, (i, &r)| acc + r / ((i + 2) as f64).log2())
+}
+
+/// Computes the normalized discounted cumulative gain (NDCG@k) of a ranked
+/// list, i.e. the DCG of the first `k` items divided by the DCG of the first
+/// `k` items of the ideal ranking (the items sorted by decreasing
+/// relevance). Returns zero if the list contains no relevant item.
+pub fn ndcg_at_k(relevance: &[f64], k: usize) -> f64 {
+
+    let mut ideal = relevance.to_vec();
+    ideal.sort_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    let idcg = dcg_at_k(&ideal, k);
+    if idcg > 0.0 { dcg_at_k(relevance, k) / idcg } else { 0.0 }
+}
+
+/// Computes the mean NDCG@k of rankings given by scores.
+///
+/// Row `i` of `y_true` contains the true relevances of the items of query
+/// `i` and row `i` of `y_score` contains the scores of these items predicted
+/// by a model. The items of each row are ranked by decreasing score.
+///
+/// Panics if the dimensions of the matrices do not match.
+pub fn ndcg_score(y_true: &Matrix<f64>, y_score: &Matrix<f64>, k: usize) -> f64 {
+
+    assert!(y_true.rows() == y_score.rows() && y_true.cols() == y_score.cols(),
+        "Dimensions of matrices do not match.");
+
+    if y_true.rows() == 0 {
+        return 0.0;
+    }
+    let sum = y_true.row_iter().zip(y_score.row_iter()).fold(0.0, |acc, (t, s)| {
+        let mut idx: Vec<usize> = (0..t.len()).collect();
+        idx.sort_by(|&a, &b| s[b].partial_cmp(&s[a]).unwrap_or(Ordering::Equal));
+        let ranked: Vec<f64> = idx.iter().map(|&i| t[i]).collect();
+        acc + ndcg_at_k(&ranked, k)
+    });
+    sum / y_true.rows() as f64
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_top_k_accuracy() {
+
+        let scores = mat![
+            0.1, 0.2, 0.3, 0.4;
+            0.4, 0.3, 0.2, 0.1
+        ];
+        assert_eq!(top_k_accuracy(&[0, 0], &scores, 1), 0.5);
+        assert_eq!(top_k_accuracy(&[1, 1], &scores, 2), 0.5);
+        assert_eq!(top_k_accuracy(&[1, 1], &scores, 3), 1.0);
+
+        // ties are resolved in favour of the true class
+        assert_eq!(top_k_accuracy(&[1], &mat![0.5, 0.5], 1), 1.0);
+    }
+
+    #[test]
+    fn test_reciprocal_rank() {
+
+        assert_eq!(reciprocal_rank(&[0.0, 0.0, 1.0]), 1.0 / 3.0);
+        assert_eq!(reciprocal_rank(&[0.0, 0.0]), 0.0);
+        assert_eq!(mean_reciprocal_rank(&[]), 0.0);
+        assert_eq!(mean_reciprocal_rank(&[vec![1.0], vec![0.0, 0.0, 0.0, 1.0]]), 0.625);
+    }
+
+    #[test]
+    fn test_ndcg() {
+
+        let r = vec![3.0, 2.0, 3.0, 0.0, 1.0, 2.0];
+        assert!((dcg_at_k(&r, 6) - 6.861126688593502).abs() < 1e-12);
+        assert!((ndcg_at_k(&r, 6) - 0.9608081943360617).abs() < 1e-12);
+        assert_eq!(ndcg_at_k(&[0.0, 0.0], 2), 0.0);
+        assert_eq!(ndcg_at_k(&[0.0, 1.0], 1), 0.0);
+    }
+
+    #[test]
+    fn test_ndcg_score() {
+
+        let t = mat![3.0, 2.0, 3.0, 0.0, 1.0, 2.0];
+        let s = mat![6.0, 5.0, 4.0, 3.0, 2.0, 1.0];
+        assert!((ndcg_score(&t, &s, 6) - 0.9608081943360617).abs() < 1e-12);
+
+        let t = mat![0.0, 1.0; 1.0, 0.0];
+        let s = mat![0.2, 0.8; 0.2, 0.8];
+        assert_eq!(ndcg_score(&t, &s, 1), 0.5);
+    }
+}
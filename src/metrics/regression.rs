@@ -0,0 +1,217 @@
+//! Metrics for regression.
+//!
+//! The metrics for a single output operate on slices. For models with
+//! multiple outputs (i.e. the targets and predictions are matrices with one
+//! column per output) a metric can be computed for each column with
+//! [`raw_values`](fn.raw_values.html) or averaged over all columns with
+//! [`uniform_average`](fn.uniform_average.html).
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::metrics::*;
+//!
+//! # fn main() {
+//! let y_true = vec![3.0, -0.5, 2.0, 7.0];
+//! let y_pred = vec![2.5, 0.0, 2.0, 8.0];
+//!
+//! assert_eq!(mean_absolute_error(&y_true, &y_pred), 0.5);
+//! assert_eq!(mean_squared_error(&y_true, &y_pred), 0.375);
+//!
+//! let t = mat![0.5, 1.0; -1.0, 1.0; 7.0, -6.0];
+//! let p = mat![0.0, 2.0; -1.0, 2.0; 8.0, -5.0];
+//! let e = raw_values(&t, &p, mean_absolute_error);
+//! assert_eq!(e, vec![0.5, 1.0]);
+//! assert_eq!(uniform_average(&t, &p, mean_absolute_error), 0.75);
+//! # }
+//! ```
+use std::f64;
+
+use matrix::Matrix;
+
+fn check(y_true: &[f64], y_pred: &[f64]) {
+    assert!(y_true.len() == y_pred.len(), "Number of targets does not match the number of predictions.");
+}
+
+fn mean<I: Iterator<Item = f64>>(it: I) -> f64 {
+    let (s, n) = it.fold((0.0, 0), |(s, n), x| (s + x, n + 1));
+    if n == 0 { 0.0 } else { s / n as f64 }
+}
+
+/// Computes the mean absolute error.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn mean_absolute_error(y_true: &[f64], y_pred: &[f64]) -> f64 {
+
+    check(y_true, y_pred);
+    mean(y_true.iter().zip(y_pred.iter()).map(|(t, p)| (t - p).abs()))
+}
+
+/// Computes the mean squared error.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn mean_squared_error(y_true: &[f64], y_pred: &[f64]) -> f64 {
+
+    check(y_true, y_pred);
+    mean(y_true.iter().zip(y_pred.iter()).map(|(t, p)| (t - p) * (t - p)))
+}
+
+/// Computes the root mean squared error.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn root_mean_squared_error(y_true: &[f64], y_pred: &[f64]) -> f64 {
+    mean_squared_error(y_true, y_pred).sqrt()
+}
+
+/// Computes the mean absolute percentage error, i.e. the mean of
+/// `|y_true - y_pred| / |y_true|`. The result is a fraction, not a
+/// percentage. Targets equal to zero are replaced by the machine epsilon
+/// to avoid a division by zero, which results in very large errors.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn mean_absolute_percentage_error(y_true: &[f64], y_pred: &[f64]) -> f64 {
+
+    check(y_true, y_pred);
+    mean(y_true.iter().zip(y_pred.iter()).map(|(t, p)| (t - p).abs() / t.abs().max(f64::EPSILON)))
+}
+
+/// Computes the coefficient of determination (R²).
+///
+/// The score is `1 - SS_res / SS_tot` where `SS_res` is the sum of the
+/// squared errors and `SS_tot` is the sum of the squared differences between
+/// the targets and their mean. The best score is 1, a model which always
+/// predicts the mean of the targets gets 0 and the score can be negative.
+/// If all targets are equal the score is 1 for perfect predictions and 0
+/// otherwise.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn r2_score(y_true: &[f64], y_pred: &[f64]) -> f64 {
+
+    check(y_true, y_pred);
+
+    let m = mean(y_true.iter().cloned());
+    let ss_res = y_true.iter().zip(y_pred.iter()).fold(0.0, |acc, (t, p)| acc + (t - p) * (t - p));
+    let ss_tot = y_true.iter().fold(0.0, |acc, t| acc + (t - m) * (t - m));
+    score(ss_res, ss_tot)
+}
+
+/// Computes the explained variance score, i.e.
+/// `1 - Var(y_true - y_pred) / Var(y_true)`.
+///
+/// In contrast to the [R² score](fn.r2_score.html) a constant offset of the
+/// predictions is not penalized. If all targets are equal the score is 1
+/// for a constant error and 0 otherwise.
+///
+/// Panics if the number of targets does not match the number of
+/// predictions.
+pub fn explained_variance_score(y_true: &[f64], y_pred: &[f64]) -> f64 {
+
+    check(y_true, y_pred);
+
+    let diff: Vec<f64> = y_true.iter().zip(y_pred.iter()).map(|(t, p)| t - p).collect();
+    score(variance(&diff), variance(y_true))
+}
+
+fn variance(v: &[f64]) -> f64 {
+    let m = mean(v.iter().cloned());
+    mean(v.iter().map(|x| (x - m) * (x - m)))
+}
+
+fn score(num: f64, den: f64) -> f64 {
+    if den != 0.0 {
+        1.0 - num / den
+    } else if num == 0.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Computes a metric for each column of the targets and predictions.
+///
+/// Panics if the dimensions of the matrices do not match.
+pub fn raw_values<F>(y_true: &Matrix<f64>, y_pred: &Matrix<f64>, metric: F) -> Vec<f64>
+    where F: Fn(&[f64], &[f64]) -> f64 {
+
+    assert!(y_true.rows() == y_pred.rows() && y_true.cols() == y_pred.cols(),
+        "Dimensions of matrices do not match.");
+
+    y_true.col_iter().zip(y_pred.col_iter())
+        .map(|(t, p)| metric(&t, &p))
+        .collect()
+}
+
+/// Computes a metric for each column of the targets and predictions and
+/// returns the mean of the results.
+///
+/// Panics if the dimensions of the matrices do not match.
+pub fn uniform_average<F>(y_true: &Matrix<f64>, y_pred: &Matrix<f64>, metric: F) -> f64
+    where F: Fn(&[f64], &[f64]) -> f64 {
+
+    mean(raw_values(y_true, y_pred, metric).into_iter())
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_errors() {
+
+        let t = vec![1.0, 2.0, 4.0, -2.0];
+        let p = vec![2.0, 2.0, 1.0, -1.0];
+
+        assert_eq!(mean_absolute_error(&t, &p), 1.25);
+        assert_eq!(mean_squared_error(&t, &p), 2.75);
+        assert_eq!(root_mean_squared_error(&t, &p), 2.75f64.sqrt());
+        assert_eq!(mean_absolute_percentage_error(&t, &p), (1.0 + 0.0 + 0.75 + 0.5) / 4.0);
+        assert_eq!(mean_absolute_error(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_r2_score() {
+
+        let t = vec![1.0, 2.0, 3.0];
+        assert_eq!(r2_score(&t, &t), 1.0);
+        assert_eq!(r2_score(&t, &[2.0, 2.0, 2.0]), 0.0);
+        assert_eq!(r2_score(&t, &[3.0, 2.0, 1.0]), -3.0);
+        assert_eq!(r2_score(&[1.0, 1.0], &[1.0, 1.0]), 1.0);
+        assert_eq!(r2_score(&[1.0, 1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_explained_variance_score() {
+
+        let t = vec![1.0, 2.0, 3.0];
+        // constant offset is not penalized
+        assert_eq!(explained_variance_score(&t, &[2.0, 3.0, 4.0]), 1.0);
+        assert!(r2_score(&t, &[2.0, 3.0, 4.0]) < 1.0);
+        assert_eq!(explained_variance_score(&t, &[2.0, 2.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_multi_output() {
+
+        let t = mat![1.0, 1.0; 2.0, 2.0; 3.0, 3.0];
+        let p = mat![1.0, 2.0; 2.0, 2.0; 3.0, 2.0];
+
+        assert_eq!(raw_values(&t, &p, r2_score), vec![1.0, 0.0]);
+        assert_eq!(uniform_average(&t, &p, r2_score), 0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_multi_output_panic() {
+        raw_values(&mat![1.0, 2.0], &mat![1.0], mean_squared_error);
+    }
+}
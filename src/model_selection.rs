@@ -0,0 +1,1217 @@
+//! Module to split a dataset into training sets and test sets and to
+//! evaluate estimators with cross-validation.
+//!
+//! All functions in this module expect that the examples are stored in the
+//! rows of a matrix and that the labels are stored in a slice, i.e. the label
+//! of the example in row `i` is the `i`th element of the slice. The rows are
+//! shuffled with a random number generator that is initialized with the
+//! given seed, so that the same seed always results in the same split.
+//!
+//! # Example: training set and test set
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::model_selection::*;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     1.0, 2.0;
+//!     2.0, 3.0;
+//!     3.0, 4.0;
+//!     4.0, 5.0
+//! ];
+//! let y = vec![0, 0, 1, 1];
+//!
+//! let (x_train, x_test, y_train, y_test) = train_test_split(&x, &y, 0.25, [1, 2, 3, 4]);
+//! assert_eq!(x_train.rows(), 3);
+//! assert_eq!(x_test.rows(), 1);
+//! assert_eq!(y_train.len(), 3);
+//! assert_eq!(y_test.len(), 1);
+//! # }
+//! ```
+//!
+//! # Example: cross-validation
+//!
+//! A model that should be evaluated with cross-validation has to implement
+//! the trait [`Estimator`](trait.Estimator.html).
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::model_selection::*;
+//!
+//! // predicts the label that occurs most often in the training set
+//! #[derive(Clone)]
+//! struct Majority(usize);
+//!
+//! impl Estimator<f64, usize> for Majority {
+//!     fn fit(&mut self, _x: &Matrix<f64>, y: &[usize]) {
+//!         let ones = y.iter().filter(|&&l| l == 1).count();
+//!         self.0 = if 2 * ones > y.len() { 1 } else { 0 };
+//!     }
+//!
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+//!         vec![self.0; x.rows()]
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let x = Matrix::from_vec((0..6).map(|i| i as f64).collect(), 6, 1);
+//! let y = vec![1, 1, 1, 1, 0, 0];
+//!
+//! let accuracy = |t: &[usize], p: &[usize]|
+//!     t.iter().zip(p.iter()).filter(|&(a, b)| a == b).count() as f64 / t.len() as f64;
+//!
+//! let scores = cross_val_score(&Majority(0), &x, &y, StratifiedKFold::new(&y, 2), accuracy);
+//! assert_eq!(scores, vec![2.0 / 3.0, 2.0 / 3.0]);
+//! # }
+//! ```
+//!
+//! # Parallel evaluation
+//!
+//! If rustml is compiled with the feature `rayon` the splits of a
+//! cross-validation and the candidates of a validation curve can be
+//! evaluated in parallel with [`par_cross_val_score`](fn.par_cross_val_score.html)
+//! and [`par_validation_curve`](fn.par_validation_curve.html). The scores
+//! are always returned in the order of the splits and candidates so that
+//! the results do not depend on the number of threads.
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+use std::collections::BTreeMap;
+use std::thread;
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+#[cfg(feature = "rayon")]
+use self::rayon::prelude::*;
+
+use matrix::Matrix;
+use math::{Mean, Dimension};
+
+/// Returns the indexes `0..n` in random order.
+///
+/// The seed must not be `[0, 0, 0, 0]`.
+pub fn shuffled_indexes(n: usize, seed: [u32; 4]) -> Vec<usize> {
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let mut idx = (0..n).collect::<Vec<usize>>();
+    rng.shuffle(&mut idx);
+    idx
+}
+
+/// Trait for containers whose rows (or elements) can be swapped in place,
+/// e.g. matrices with examples and vectors with labels.
+pub trait SwapRows {
+
+    /// Returns the number of rows.
+    fn num_rows(&self) -> usize;
+
+    /// Swaps the rows `i` and `j`.
+    fn swap(&mut self, i: usize, j: usize);
+}
+
+impl <T: Clone> SwapRows for Matrix<T> {
+    fn num_rows(&self) -> usize { self.rows() }
+
+    fn swap(&mut self, i: usize, j: usize) { self.swap_rows(i, j) }
+}
+
+impl <T> SwapRows for Vec<T> {
+    fn num_rows(&self) -> usize { self.len() }
+
+    fn swap(&mut self, i: usize, j: usize) { (&mut self[..]).swap(i, j) }
+}
+
+impl <T> SwapRows for [T] {
+    fn num_rows(&self) -> usize { self.len() }
+
+    fn swap(&mut self, i: usize, j: usize) { <[T]>::swap(self, i, j) }
+}
+
+/// Shuffles the rows of `x` and `y` in place with the same permutation, so
+/// that the examples and their labels stay aligned.
+///
+/// Both `x` and `y` can be a matrix or a vector. The seed must not be
+/// `[0, 0, 0, 0]`.
+///
+/// Panics if `x` and `y` do not have the same number of rows.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::model_selection::shuffle_rows;
+///
+/// # fn main() {
+/// let mut x = mat![1.0; 2.0; 3.0; 4.0];
+/// let mut y = vec![1, 2, 3, 4];
+///
+/// shuffle_rows(&mut x, &mut y, [1, 2, 3, 4]);
+/// for (r, &l) in x.row_iter().zip(y.iter()) {
+///     assert_eq!(r[0], l as f64);
+/// }
+/// # }
+/// ```
+pub fn shuffle_rows<A: SwapRows + ?Sized, B: SwapRows + ?Sized>(x: &mut A, y: &mut B, seed: [u32; 4]) {
+
+    assert!(x.num_rows() == y.num_rows(), "Number of rows does not match.");
+
+    for (i, j) in swaps(x.num_rows(), seed) {
+        x.swap(i, j);
+        y.swap(i, j);
+    }
+}
+
+/// Shuffles the rows of all given containers in place with the same
+/// permutation. The seed must not be `[0, 0, 0, 0]`.
+///
+/// Panics if the containers do not have the same number of rows.
+pub fn shuffle_rows_all(items: &mut [&mut dyn SwapRows], seed: [u32; 4]) {
+
+    let n = items.first().map(|x| x.num_rows()).unwrap_or(0);
+    assert!(items.iter().all(|x| x.num_rows() == n), "Number of rows does not match.");
+
+    for (i, j) in swaps(n, seed) {
+        for x in items.iter_mut() {
+            x.swap(i, j);
+        }
+    }
+}
+
+/// Returns the swaps of a Fisher-Yates shuffle of `n` elements.
+fn swaps(n: usize, seed: [u32; 4]) -> Vec<(usize, usize)> {
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    (1..n).rev().map(|i| (i, rng.gen_range(0, i + 1))).collect()
+}
+
+/// Returns the number of examples of `n` examples that are put into the
+/// test set.
+fn test_size(n: usize, test_fraction: f64) -> usize {
+
+    assert!(test_fraction >= 0.0 && test_fraction <= 1.0,
+        "Fraction of the test set must be within [0, 1].");
+    (n as f64 * test_fraction).round() as usize
+}
+
+fn select<L: Clone>(y: &[L], idx: &[usize]) -> Vec<L> {
+
+    idx.iter().map(|&i| y[i].clone()).collect()
+}
+
+fn split<T: Clone, L: Clone>(x: &Matrix<T>, y: &[L], train: &[usize], test: &[usize])
+    -> (Matrix<T>, Matrix<T>, Vec<L>, Vec<L>) {
+
+    (x.select_rows(train), x.select_rows(test), select(y, train), select(y, test))
+}
+
+/// Randomly splits the examples in `x` and their labels in `y` into a
+/// training set and a test set.
+///
+/// The parameter `test_fraction` is the fraction of the examples that are
+/// put into the test set. The number of examples in the test set is rounded
+/// to the nearest integer. The rows of the returned matrices are in random
+/// order. The seed must not be `[0, 0, 0, 0]`.
+///
+/// Returns `(x_train, x_test, y_train, y_test)`.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if `test_fraction` is not within [0, 1].
+pub fn train_test_split<T: Clone, L: Clone>(x: &Matrix<T>, y: &[L], test_fraction: f64, seed: [u32; 4])
+    -> (Matrix<T>, Matrix<T>, Vec<L>, Vec<L>) {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let n = test_size(y.len(), test_fraction);
+    let idx = shuffled_indexes(y.len(), seed);
+    let (test, train) = idx.split_at(n);
+    split(x, y, train, test)
+}
+
+/// Randomly splits the examples in `x` and their labels in `y` into a
+/// training set and a test set so that the proportion of each class is
+/// (approximately) the same in both sets.
+///
+/// For each class the number of examples in the test set is the number of
+/// examples of that class multiplied by `test_fraction` rounded to the
+/// nearest integer. The rows of the returned matrices are in random order.
+/// The seed must not be `[0, 0, 0, 0]`.
+///
+/// Returns `(x_train, x_test, y_train, y_test)`.
+///
+/// Panics if the number of rows of `x` does not match the number of labels
+/// or if `test_fraction` is not within [0, 1].
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::model_selection::*;
+///
+/// # fn main() {
+/// let x = Matrix::from_vec((0..20).map(|i| i as f64).collect(), 10, 2);
+/// let y = vec![1, 1, 1, 1, 1, 1, 1, 1, 2, 2];
+///
+/// let (_, _, y_train, y_test) = stratified_train_test_split(&x, &y, 0.5, [1, 2, 3, 4]);
+/// assert_eq!(y_train.iter().filter(|&&l| l == 2).count(), 1);
+/// assert_eq!(y_test.iter().filter(|&&l| l == 2).count(), 1);
+/// # }
+/// ```
+pub fn stratified_train_test_split<T: Clone, L: Clone + Ord>(x: &Matrix<T>, y: &[L], test_fraction: f64, seed: [u32; 4])
+    -> (Matrix<T>, Matrix<T>, Vec<L>, Vec<L>) {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let mut classes: BTreeMap<&L, Vec<usize>> = BTreeMap::new();
+    for (i, l) in y.iter().enumerate() {
+        classes.entry(l).or_insert(Vec::new()).push(i);
+    }
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let mut train = Vec::new();
+    let mut test = Vec::new();
+    for (_, mut idx) in classes {
+        rng.shuffle(&mut idx);
+        let n = test_size(idx.len(), test_fraction);
+        test.extend_from_slice(&idx[..n]);
+        train.extend_from_slice(&idx[n..]);
+    }
+    rng.shuffle(&mut train);
+    rng.shuffle(&mut test);
+
+    split(x, y, &train, &test)
+}
+
+/// Trait for models that can be trained on a set of examples with known
+/// labels and that can predict the labels of new examples.
+pub trait Estimator<T, L> {
+
+    /// Trains the model on the examples in the rows of `x` with the labels
+    /// `y`. Calling `fit` again discards what has been learned before.
+    fn fit(&mut self, x: &Matrix<T>, y: &[L]);
+
+    /// Predicts the label of each row in `x`.
+    fn predict(&self, x: &Matrix<T>) -> Vec<L>;
+}
+
+/// Trait for classifiers which can predict the probability of each class.
+///
+/// The classes are the labels `0..k`.
+pub trait ProbabilisticClassifier: Estimator<f64, usize> {
+
+    /// Returns a matrix with one row for each row in `x` whose column `j`
+    /// contains the probability of the class `j`.
+    fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64>;
+}
+
+/// Predicts the labels of the rows in `x` in batches of `batch_size` rows
+/// which are distributed over `n_threads` threads.
+///
+/// The labels are returned in the order of the rows, i.e. the result is the
+/// same as the result of `estimator.predict(x)`. This is useful for serving
+/// workloads where a large input should not be predicted at once.
+///
+/// Panics if `batch_size` or `n_threads` is zero.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::model_selection::*;
+///
+/// struct Double;
+///
+/// impl Estimator<f64, f64> for Double {
+///     fn fit(&mut self, _x: &Matrix<f64>, _y: &[f64]) { }
+///     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+///         x.row_iter().map(|r| 2.0 * r[0]).collect()
+///     }
+/// }
+///
+/// # fn main() {
+/// let x = mat![1.0; 2.0; 3.0; 4.0; 5.0];
+/// assert_eq!(predict_batched(&Double, &x, 2, 3), vec![2.0, 4.0, 6.0, 8.0, 10.0]);
+/// # }
+/// ```
+pub fn predict_batched<T, L, E>(estimator: &E, x: &Matrix<T>, batch_size: usize, n_threads: usize) -> Vec<L>
+    where E: Estimator<T, L> + Sync, T: Clone + Sync, L: Send {
+
+    assert!(batch_size > 0, "Batch size must be greater than zero.");
+    assert!(n_threads > 0, "Number of threads must be greater than zero.");
+
+    let batches = row_batches(x, batch_size);
+    let n_threads = n_threads.min(batches.len());
+    if n_threads <= 1 {
+        return batches.iter().flat_map(|b| estimator.predict(b)).collect();
+    }
+
+    // thread i predicts the batches i, i + n_threads, i + 2 * n_threads, ...
+    let results: Vec<Vec<Vec<L>>> = thread::scope(|s| {
+        let handles: Vec<_> = (0..n_threads)
+            .map(|i| {
+                let batches = &batches;
+                s.spawn(move || batches.iter().skip(i).step_by(n_threads).map(|b| estimator.predict(b)).collect())
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut iters: Vec<_> = results.into_iter().map(|r| r.into_iter()).collect();
+    let mut labels = Vec::with_capacity(x.rows());
+    for i in 0..batches.len() {
+        labels.extend(iters[i % n_threads].next().unwrap());
+    }
+    labels
+}
+
+/// Splits the rows of `x` into matrices with at most `batch_size` rows.
+pub fn row_batches<T: Clone>(x: &Matrix<T>, batch_size: usize) -> Vec<Matrix<T>> {
+
+    assert!(batch_size > 0, "Batch size must be greater than zero.");
+    let cols = x.cols();
+    if cols == 0 {
+        return vec![];
+    }
+    x.buf().chunks(batch_size * cols)
+        .map(|c| Matrix::from_vec(c.to_vec(), c.len() / cols, cols))
+        .collect()
+}
+
+/// Iterator over the splits of a k-fold cross-validation.
+///
+/// The examples are divided into `k` folds of (almost) equal size. Each
+/// fold is used once as the validation set while the remaining folds are
+/// used as the training set. Each item is a pair `(train, validation)`
+/// containing the indexes of the examples in the training set and the
+/// validation set.
+///
+/// # Example
+///
+/// ```
+/// use rustml::model_selection::KFold;
+///
+/// let mut f = KFold::new(5, 2);
+/// assert_eq!(f.next(), Some((vec![3, 4], vec![0, 1, 2])));
+/// assert_eq!(f.next(), Some((vec![0, 1, 2], vec![3, 4])));
+/// assert_eq!(f.next(), None);
+/// ```
+pub struct KFold {
+    folds: Vec<Vec<usize>>,
+    pos: usize
+}
+
+impl KFold {
+
+    /// Creates the folds for `n` examples without shuffling, i.e. each fold
+    /// contains consecutive examples. If `n` is not divisible by `k` the
+    /// first folds contain one example more than the remaining folds.
+    ///
+    /// Panics if `k < 2` or `k > n`.
+    pub fn new(n: usize, k: usize) -> KFold {
+        KFold::from_indexes((0..n).collect(), k)
+    }
+
+    /// Like `new` but the examples are shuffled before they are divided
+    /// into folds. The seed must not be `[0, 0, 0, 0]`.
+    pub fn shuffled(n: usize, k: usize, seed: [u32; 4]) -> KFold {
+        KFold::from_indexes(shuffled_indexes(n, seed), k)
+    }
+
+    fn from_indexes(idx: Vec<usize>, k: usize) -> KFold {
+
+        assert!(k >= 2 && k <= idx.len(), "Number of folds must be within [2, n].");
+
+        let n = idx.len();
+        let mut folds = Vec::with_capacity(k);
+        let mut start = 0;
+        for i in 0..k {
+            let size = n / k + if i < n % k { 1 } else { 0 };
+            folds.push(idx[start..start + size].to_vec());
+            start += size;
+        }
+
+        KFold {
+            folds: folds,
+            pos: 0
+        }
+    }
+}
+
+impl Iterator for KFold {
+    type Item = (Vec<usize>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_split(&self.folds, &mut self.pos)
+    }
+}
+
+/// Iterator over the splits of a stratified k-fold cross-validation.
+///
+/// Like [`KFold`](struct.KFold.html) but the examples of each class are
+/// distributed evenly across the folds so that the proportion of each class
+/// is (approximately) the same in each fold.
+pub struct StratifiedKFold {
+    folds: Vec<Vec<usize>>,
+    pos: usize
+}
+
+impl StratifiedKFold {
+
+    /// Creates the folds for the examples with the labels `y` without
+    /// shuffling.
+    ///
+    /// Panics if `k < 2` or `k > y.len()`.
+    pub fn new<L: Ord>(y: &[L], k: usize) -> StratifiedKFold {
+        StratifiedKFold::create(y, k, None)
+    }
+
+    /// Like `new` but the examples of each class are shuffled before they
+    /// are distributed across the folds. The seed must not be `[0, 0, 0, 0]`.
+    pub fn shuffled<L: Ord>(y: &[L], k: usize, seed: [u32; 4]) -> StratifiedKFold {
+        StratifiedKFold::create(y, k, Some(XorShiftRng::from_seed(seed)))
+    }
+
+    fn create<L: Ord>(y: &[L], k: usize, mut rng: Option<XorShiftRng>) -> StratifiedKFold {
+
+        assert!(k >= 2 && k <= y.len(), "Number of folds must be within [2, n].");
+
+        let mut classes: BTreeMap<&L, Vec<usize>> = BTreeMap::new();
+        for (i, l) in y.iter().enumerate() {
+            classes.entry(l).or_insert(Vec::new()).push(i);
+        }
+
+        // the examples are distributed round robin; the next class continues
+        // with the fold after the last one used so that the sizes of the
+        // folds differ by at most one
+        let mut folds = vec![Vec::new(); k];
+        let mut f = 0;
+        for (_, mut idx) in classes {
+            if let Some(ref mut r) = rng {
+                r.shuffle(&mut idx);
+            }
+            for i in idx {
+                folds[f].push(i);
+                f = (f + 1) % k;
+            }
+        }
+        for fold in &mut folds {
+            fold.sort();
+        }
+
+        StratifiedKFold {
+            folds: folds,
+            pos: 0
+        }
+    }
+}
+
+impl Iterator for StratifiedKFold {
+    type Item = (Vec<usize>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_split(&self.folds, &mut self.pos)
+    }
+}
+
+/// Iterator over the splits of a leave-one-out cross-validation.
+///
+/// Each example is used once as the validation set while all other examples
+/// are used as the training set. This is equivalent to a k-fold
+/// cross-validation with `k = n` but does not create all folds in advance.
+///
+/// # Example
+///
+/// ```
+/// use rustml::model_selection::LeaveOneOut;
+///
+/// let mut f = LeaveOneOut::new(3);
+/// assert_eq!(f.next(), Some((vec![1, 2], vec![0])));
+/// assert_eq!(f.next(), Some((vec![0, 2], vec![1])));
+/// assert_eq!(f.next(), Some((vec![0, 1], vec![2])));
+/// assert_eq!(f.next(), None);
+/// ```
+pub struct LeaveOneOut {
+    n: usize,
+    pos: usize
+}
+
+impl LeaveOneOut {
+
+    /// Creates the splits for `n` examples.
+    ///
+    /// Panics if `n < 2`.
+    pub fn new(n: usize) -> LeaveOneOut {
+
+        assert!(n >= 2, "At least two examples are required.");
+
+        LeaveOneOut {
+            n: n,
+            pos: 0
+        }
+    }
+}
+
+impl Iterator for LeaveOneOut {
+    type Item = (Vec<usize>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.pos >= self.n {
+            return None;
+        }
+
+        let p = self.pos;
+        self.pos += 1;
+        Some(((0..self.n).filter(|&i| i != p).collect(), vec![p]))
+    }
+}
+
+/// Iterator over the splits of a cross-validation for time series.
+///
+/// The examples are expected to be ordered by time. In contrast to
+/// [`KFold`](struct.KFold.html) the training set of a split only contains
+/// examples that precede the examples in the validation set, so that no
+/// information from the future is used to predict the past. The
+/// `n` examples are divided into `k + 1` blocks of `n / (k + 1)` examples
+/// (the first block gets the remaining examples). In the `i`th split the
+/// first `i` blocks are used as the training set and the next block is used
+/// as the validation set.
+///
+/// # Example
+///
+/// ```
+/// use rustml::model_selection::TimeSeriesSplit;
+///
+/// let mut f = TimeSeriesSplit::new(7, 2);
+/// assert_eq!(f.next(), Some((vec![0, 1, 2], vec![3, 4])));
+/// assert_eq!(f.next(), Some((vec![0, 1, 2, 3, 4], vec![5, 6])));
+/// assert_eq!(f.next(), None);
+/// ```
+pub struct TimeSeriesSplit {
+    n: usize,
+    size: usize,
+    start: usize,
+    max_train_size: Option<usize>
+}
+
+impl TimeSeriesSplit {
+
+    /// Creates `k` splits for `n` examples.
+    ///
+    /// Panics if `k < 1` or `n < k + 1`.
+    pub fn new(n: usize, k: usize) -> TimeSeriesSplit {
+
+        assert!(k >= 1 && n >= k + 1, "Number of splits must be within [1, n - 1].");
+
+        let size = n / (k + 1);
+        TimeSeriesSplit {
+            n: n,
+            size: size,
+            start: n - k * size,
+            max_train_size: None
+        }
+    }
+
+    /// Limits the training set of each split to the `n` examples that
+    /// directly precede the validation set (sliding window instead of an
+    /// expanding window).
+    pub fn max_train_size(&self, n: usize) -> TimeSeriesSplit {
+        TimeSeriesSplit {
+            n: self.n,
+            size: self.size,
+            start: self.start,
+            max_train_size: Some(n)
+        }
+    }
+}
+
+impl Iterator for TimeSeriesSplit {
+    type Item = (Vec<usize>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        if self.start >= self.n {
+            return None;
+        }
+
+        let s = self.start;
+        let first = match self.max_train_size {
+            Some(m) if m < s => s - m,
+            _ => 0
+        };
+        self.start += self.size;
+        Some(((first..s).collect(), (s..s + self.size).collect()))
+    }
+}
+
+fn next_split(folds: &[Vec<usize>], pos: &mut usize) -> Option<(Vec<usize>, Vec<usize>)> {
+
+    if *pos >= folds.len() {
+        return None;
+    }
+
+    let train = folds.iter().enumerate()
+        .filter(|&(i, _)| i != *pos)
+        .flat_map(|(_, f)| f.iter().cloned())
+        .collect();
+    let validation = folds[*pos].clone();
+    *pos += 1;
+    Some((train, validation))
+}
+
+/// Evaluates an estimator with cross-validation.
+///
+/// For each split `(train, validation)` of `splits` (e.g. created with
+/// [`KFold`](struct.KFold.html),
+/// [`StratifiedKFold`](struct.StratifiedKFold.html),
+/// [`LeaveOneOut`](struct.LeaveOneOut.html) or
+/// [`TimeSeriesSplit`](struct.TimeSeriesSplit.html)) a copy of `estimator`
+/// is trained on the examples of the training set. Then, the labels of the
+/// examples in the validation set are predicted and compared with the true
+/// labels via `metric`, which is called with the true labels as the first
+/// argument and the predicted labels as the second argument.
+///
+/// Returns the score of each split.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+pub fn cross_val_score<T, L, E, S, F>(estimator: &E, x: &Matrix<T>, y: &[L], splits: S, metric: F) -> Vec<f64>
+    where T: Clone, L: Clone, E: Estimator<T, L> + Clone,
+          S: IntoIterator<Item = (Vec<usize>, Vec<usize>)>, F: Fn(&[L], &[L]) -> f64 {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    splits.into_iter()
+        .map(|(train, validation)| {
+            let (x_train, x_val, y_train, y_val) = split(x, y, &train, &validation);
+            let mut e = estimator.clone();
+            e.fit(&x_train, &y_train);
+            metric(&y_val, &e.predict(&x_val))
+        })
+        .collect()
+}
+
+/// Evaluates an estimator with cross-validation like
+/// [`cross_val_score`](fn.cross_val_score.html) but trains and evaluates the
+/// splits in parallel on the global thread pool of rayon (requires the
+/// feature `rayon`).
+///
+/// The scores are returned in the order of the splits, i.e. for a
+/// deterministic estimator the result is the same as the result of
+/// `cross_val_score`.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+#[cfg(feature = "rayon")]
+pub fn par_cross_val_score<T, L, E, S, F>(estimator: &E, x: &Matrix<T>, y: &[L], splits: S, metric: F) -> Vec<f64>
+    where T: Clone + Sync, L: Clone + Sync, E: Estimator<T, L> + Clone + Sync,
+          S: IntoIterator<Item = (Vec<usize>, Vec<usize>)>, F: Fn(&[L], &[L]) -> f64 + Sync {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let splits: Vec<(Vec<usize>, Vec<usize>)> = splits.into_iter().collect();
+    splits.par_iter()
+        .map(|&(ref train, ref validation)| {
+            let (x_train, x_val, y_train, y_val) = split(x, y, train, validation);
+            let mut e = estimator.clone();
+            e.fit(&x_train, &y_train);
+            metric(&y_val, &e.predict(&x_val))
+        })
+        .collect()
+}
+
+/// Scores of a learning curve or a validation curve.
+///
+/// Both matrices have one row per point of the curve and one column per
+/// split of the cross-validation.
+#[derive(Clone, Debug)]
+pub struct CurveScores {
+    /// Scores on the examples used for training.
+    pub train: Matrix<f64>,
+    /// Scores on the validation sets.
+    pub validation: Matrix<f64>
+}
+
+impl CurveScores {
+
+    /// Returns the mean training score of each point of the curve.
+    pub fn train_mean(&self) -> Vec<f64> {
+        self.train.mean(Dimension::Row)
+    }
+
+    /// Returns the mean validation score of each point of the curve.
+    pub fn validation_mean(&self) -> Vec<f64> {
+        self.validation.mean(Dimension::Row)
+    }
+}
+
+/// Trains the estimator on `train` and returns the scores on `train` and on
+/// `validation`.
+fn fit_scores<T, L, E, F>(mut e: E, x: &Matrix<T>, y: &[L], train: &[usize], validation: &[usize], metric: &F) -> (f64, f64)
+    where T: Clone, L: Clone, E: Estimator<T, L>, F: Fn(&[L], &[L]) -> f64 {
+
+    let (x_train, x_val, y_train, y_val) = split(x, y, train, validation);
+    e.fit(&x_train, &y_train);
+    (metric(&y_train, &e.predict(&x_train)), metric(&y_val, &e.predict(&x_val)))
+}
+
+/// Collects the scores for each point (outer vector) and each split (inner
+/// vector) into matrices.
+fn curve_scores(scores: Vec<Vec<(f64, f64)>>, n_splits: usize) -> CurveScores {
+
+    let n = scores.len();
+    let (train, validation): (Vec<f64>, Vec<f64>) = scores.into_iter().flat_map(|v| v.into_iter()).unzip();
+    CurveScores {
+        train: Matrix::from_vec(train, n, n_splits),
+        validation: Matrix::from_vec(validation, n, n_splits)
+    }
+}
+
+/// Computes a learning curve, i.e. the scores of an estimator trained on
+/// training sets of increasing size.
+///
+/// For each fraction in `train_sizes` (each in `(0, 1]`) the number of
+/// training examples is the fraction of the size of the smallest training
+/// set of `splits`, rounded up. For each split a copy of `estimator` is
+/// trained on that number of examples taken from the beginning of the
+/// training set of the split and evaluated with `metric` on these examples
+/// and on the validation set. Hence, the splits should be shuffled, e.g.
+/// with [`KFold::shuffled`](struct.KFold.html#method.shuffled).
+///
+/// A large gap between the training and validation scores which decreases
+/// with more examples indicates that the model would benefit from more
+/// data. Low training and validation scores indicate that the model is too
+/// simple.
+///
+/// Returns the number of training examples and the scores for each size.
+///
+/// Panics if the number of rows of `x` does not match the number of labels,
+/// if there are no splits or if a fraction is not in `(0, 1]`.
+///
+/// # Example
+///
+/// ```
+/// use rustml::*;
+/// use rustml::model_selection::*;
+///
+/// // predicts the mean of the training labels
+/// #[derive(Clone)]
+/// struct MeanRegressor(f64);
+///
+/// impl Estimator<f64, f64> for MeanRegressor {
+///     fn fit(&mut self, _x: &Matrix<f64>, y: &[f64]) {
+///         self.0 = y.iter().fold(0.0, |acc, v| acc + v) / y.len() as f64;
+///     }
+///
+///     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+///         vec![self.0; x.rows()]
+///     }
+/// }
+///
+/// let x = Matrix::from_vec((0..20).map(|i| i as f64).collect(), 20, 1);
+/// let y: Vec<f64> = (0..20).map(|i| (i % 4) as f64).collect();
+/// let mse = |t: &[f64], p: &[f64]|
+///     t.iter().zip(p.iter()).fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b)) / t.len() as f64;
+///
+/// let (sizes, scores) = learning_curve(&MeanRegressor(0.0), &x, &y,
+///     KFold::shuffled(20, 4, [1, 2, 3, 4]), &[0.2, 0.6, 1.0], mse);
+/// assert_eq!(sizes, vec![3, 9, 15]);
+/// assert_eq!(scores.train.rows(), 3);
+/// assert_eq!(scores.validation.cols(), 4);
+/// ```
+pub fn learning_curve<T, L, E, S, F>(estimator: &E, x: &Matrix<T>, y: &[L], splits: S,
+        train_sizes: &[f64], metric: F) -> (Vec<usize>, CurveScores)
+    where T: Clone, L: Clone, E: Estimator<T, L> + Clone,
+          S: IntoIterator<Item = (Vec<usize>, Vec<usize>)>, F: Fn(&[L], &[L]) -> f64 {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+    assert!(train_sizes.iter().all(|&f| f > 0.0 && f <= 1.0), "Train sizes must be in (0, 1].");
+
+    let splits: Vec<(Vec<usize>, Vec<usize>)> = splits.into_iter().collect();
+    assert!(!splits.is_empty(), "At least one split is required.");
+
+    let n = splits.iter().map(|&(ref t, _)| t.len()).min().unwrap();
+    let sizes: Vec<usize> = train_sizes.iter()
+        .map(|&f| ((f * n as f64).ceil() as usize).max(1).min(n))
+        .collect();
+
+    let scores = sizes.iter()
+        .map(|&size| {
+            splits.iter()
+                .map(|&(ref train, ref validation)|
+                    fit_scores(estimator.clone(), x, y, &train[..size], validation, &metric))
+                .collect()
+        })
+        .collect();
+    (sizes, curve_scores(scores, splits.len()))
+}
+
+/// Computes a validation curve, i.e. the scores of an estimator for
+/// different values of a hyperparameter.
+///
+/// For each value in `params` a copy of `estimator` is configured with
+/// `set_param`, trained on the training set of each split and evaluated
+/// with `metric` on the training set and on the validation set.
+///
+/// High training scores and low validation scores indicate that the model
+/// overfits for the corresponding parameter, low training and validation
+/// scores indicate that it underfits.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+pub fn validation_curve<T, L, E, P, S, F, B>(estimator: &E, x: &Matrix<T>, y: &[L], splits: S,
+        params: &[P], set_param: B, metric: F) -> CurveScores
+    where T: Clone, L: Clone, E: Estimator<T, L> + Clone,
+          S: IntoIterator<Item = (Vec<usize>, Vec<usize>)>, F: Fn(&[L], &[L]) -> f64,
+          B: Fn(&mut E, &P) {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let splits: Vec<(Vec<usize>, Vec<usize>)> = splits.into_iter().collect();
+
+    let scores = params.iter()
+        .map(|p| {
+            let mut e = estimator.clone();
+            set_param(&mut e, p);
+            splits.iter()
+                .map(|&(ref train, ref validation)| fit_scores(e.clone(), x, y, train, validation, &metric))
+                .collect()
+        })
+        .collect();
+    curve_scores(scores, splits.len())
+}
+
+/// Computes a validation curve like
+/// [`validation_curve`](fn.validation_curve.html) but evaluates all pairs
+/// of candidate parameters and splits in parallel on the global thread pool
+/// of rayon (requires the feature `rayon`).
+///
+/// The scores are in the same order as the scores of `validation_curve`.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+#[cfg(feature = "rayon")]
+pub fn par_validation_curve<T, L, E, P, S, F, B>(estimator: &E, x: &Matrix<T>, y: &[L], splits: S,
+        params: &[P], set_param: B, metric: F) -> CurveScores
+    where T: Clone + Sync, L: Clone + Sync, E: Estimator<T, L> + Clone + Sync, P: Sync,
+          S: IntoIterator<Item = (Vec<usize>, Vec<usize>)>, F: Fn(&[L], &[L]) -> f64 + Sync,
+          B: Fn(&mut E, &P) + Sync {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let splits: Vec<(Vec<usize>, Vec<usize>)> = splits.into_iter().collect();
+    let n = splits.len();
+
+    // task k evaluates the parameter k / n on the split k % n
+    let scores: Vec<(f64, f64)> = (0..params.len() * n).into_par_iter()
+        .map(|k| {
+            let mut e = estimator.clone();
+            set_param(&mut e, &params[k / n]);
+            let (ref train, ref validation) = splits[k % n];
+            fit_scores(e, x, y, train, validation, &metric)
+        })
+        .collect();
+    curve_scores(scores.chunks(n.max(1)).map(|c| c.to_vec()).collect(), n)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_train_test_split() {
+
+        let x = Matrix::from_vec((0..20).collect::<Vec<usize>>(), 10, 2);
+        let y = (0..10).collect::<Vec<usize>>();
+
+        let (x_train, x_test, y_train, y_test) = train_test_split(&x, &y, 0.3, [1, 2, 3, 4]);
+        assert_eq!(x_train.rows(), 7);
+        assert_eq!(x_test.rows(), 3);
+        assert_eq!(y_train.len(), 7);
+        assert_eq!(y_test.len(), 3);
+
+        // rows and labels stay together
+        for (r, &l) in x_train.row_iter().zip(y_train.iter()).chain(x_test.row_iter().zip(y_test.iter())) {
+            assert_eq!(r, &[2 * l, 2 * l + 1]);
+        }
+
+        // each example is either in the training set or the test set
+        let mut all = y_train.clone();
+        all.extend_from_slice(&y_test);
+        all.sort();
+        assert_eq!(all, y);
+
+        // same seed, same split
+        let (_, _, y_train2, _) = train_test_split(&x, &y, 0.3, [1, 2, 3, 4]);
+        assert_eq!(y_train, y_train2);
+    }
+
+    #[test]
+    fn test_shuffle_rows() {
+
+        let mut x = Matrix::from_vec((0..20).collect::<Vec<usize>>(), 10, 2);
+        let mut y = (0..10).collect::<Vec<usize>>();
+        let mut z = mat![0; 1; 2; 3; 4; 5; 6; 7; 8; 9];
+
+        shuffle_rows(&mut x, &mut y, [1, 2, 3, 4]);
+        assert!(y != (0..10).collect::<Vec<usize>>());
+        for (r, &l) in x.row_iter().zip(y.iter()) {
+            assert_eq!(r, &[2 * l, 2 * l + 1]);
+        }
+
+        let mut y2 = (0..10).collect::<Vec<usize>>();
+        shuffle_rows_all(&mut [&mut z, &mut y2], [1, 2, 3, 4]);
+        assert_eq!(y2, y);
+        assert_eq!(z.buf(), &y);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shuffle_rows_panic() {
+
+        let mut x = mat![1; 2];
+        shuffle_rows(&mut x, &mut vec![1, 2, 3], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_train_test_split_empty_test_set() {
+
+        let x = mat![1.0, 2.0; 3.0, 4.0];
+        let (x_train, x_test, _, y_test) = train_test_split(&x, &[0, 1], 0.0, [1, 2, 3, 4]);
+        assert_eq!(x_train.rows(), 2);
+        assert_eq!(x_test.rows(), 0);
+        assert_eq!(x_test.cols(), 2);
+        assert!(y_test.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_train_test_split_panic() {
+
+        let x = mat![1.0, 2.0; 3.0, 4.0];
+        train_test_split(&x, &[0, 1, 2], 0.5, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_stratified_train_test_split() {
+
+        let x = Matrix::from_vec((0..20).collect::<Vec<usize>>(), 20, 1);
+        let y = (0..20).map(|i| if i < 15 { 'a' } else { 'b' }).collect::<Vec<char>>();
+
+        let (x_train, x_test, y_train, y_test) = stratified_train_test_split(&x, &y, 0.2, [1, 2, 3, 4]);
+        assert_eq!(x_train.rows(), 16);
+        assert_eq!(x_test.rows(), 4);
+        assert_eq!(y_test.iter().filter(|&&l| l == 'a').count(), 3);
+        assert_eq!(y_test.iter().filter(|&&l| l == 'b').count(), 1);
+        assert_eq!(y_train.iter().filter(|&&l| l == 'a').count(), 12);
+        assert_eq!(y_train.iter().filter(|&&l| l == 'b').count(), 4);
+
+        for (r, &l) in x_test.row_iter().zip(y_test.iter()) {
+            assert_eq!(l, y[r[0]]);
+        }
+    }
+
+    #[test]
+    fn test_kfold() {
+
+        let v = KFold::new(7, 3).collect::<Vec<_>>();
+        assert_eq!(v, vec![
+            (vec![3, 4, 5, 6], vec![0, 1, 2]),
+            (vec![0, 1, 2, 5, 6], vec![3, 4]),
+            (vec![0, 1, 2, 3, 4], vec![5, 6])
+        ]);
+
+        let mut all = Vec::new();
+        for (train, val) in KFold::shuffled(10, 3, [1, 2, 3, 4]) {
+            assert_eq!(train.len() + val.len(), 10);
+            assert!(val.iter().all(|i| !train.contains(i)));
+            all.extend_from_slice(&val);
+        }
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_kfold_panic() {
+        KFold::new(2, 3);
+    }
+
+    #[test]
+    fn test_stratified_kfold() {
+
+        let y = vec![0, 1, 0, 0, 1, 0, 1, 0, 0];
+        let v = StratifiedKFold::new(&y, 3).collect::<Vec<_>>();
+        assert_eq!(v.len(), 3);
+        for (train, val) in v {
+            assert_eq!(val.len(), 3);
+            assert_eq!(train.len(), 6);
+            assert_eq!(val.iter().filter(|&&i| y[i] == 1).count(), 1);
+        }
+
+        for (_, val) in StratifiedKFold::shuffled(&y, 3, [1, 2, 3, 4]) {
+            assert_eq!(val.iter().filter(|&&i| y[i] == 1).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_leave_one_out() {
+
+        let v = LeaveOneOut::new(4).collect::<Vec<_>>();
+        assert_eq!(v.len(), 4);
+        for (i, (train, val)) in v.into_iter().enumerate() {
+            assert_eq!(val, vec![i]);
+            assert_eq!(train.len(), 3);
+            assert!(!train.contains(&i));
+        }
+    }
+
+    #[test]
+    fn test_time_series_split() {
+
+        let v = TimeSeriesSplit::new(6, 5).collect::<Vec<_>>();
+        assert_eq!(v, vec![
+            (vec![0], vec![1]),
+            (vec![0, 1], vec![2]),
+            (vec![0, 1, 2], vec![3]),
+            (vec![0, 1, 2, 3], vec![4]),
+            (vec![0, 1, 2, 3, 4], vec![5])
+        ]);
+
+        let v = TimeSeriesSplit::new(6, 5).max_train_size(2).collect::<Vec<_>>();
+        assert_eq!(v[0], (vec![0], vec![1]));
+        assert_eq!(v[4], (vec![3, 4], vec![5]));
+
+        // no leakage from the future
+        for (train, val) in TimeSeriesSplit::new(20, 3) {
+            assert!(train.iter().all(|&i| i < val[0]));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_time_series_split_panic() {
+        TimeSeriesSplit::new(3, 3);
+    }
+
+    #[derive(Clone)]
+    struct Mean(f64);
+
+    impl Estimator<f64, f64> for Mean {
+        fn fit(&mut self, _x: &Matrix<f64>, y: &[f64]) {
+            self.0 = y.iter().fold(0.0, |acc, x| acc + x) / y.len() as f64;
+        }
+
+        fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+            vec![self.0; x.rows()]
+        }
+    }
+
+    #[test]
+    fn test_predict_batched() {
+
+        struct Row;
+
+        impl Estimator<usize, usize> for Row {
+            fn fit(&mut self, _x: &Matrix<usize>, _y: &[usize]) { }
+            fn predict(&self, x: &Matrix<usize>) -> Vec<usize> {
+                x.row_iter().map(|r| r[0] / 2).collect()
+            }
+        }
+
+        let x = Matrix::from_vec((0..22).collect::<Vec<usize>>(), 11, 2);
+        let expected = (0..11).collect::<Vec<usize>>();
+        assert_eq!(predict_batched(&Row, &x, 3, 2), expected);
+        assert_eq!(predict_batched(&Row, &x, 1, 4), expected);
+        assert_eq!(predict_batched(&Row, &x, 100, 4), expected);
+        assert_eq!(predict_batched(&Row, &Matrix::new(), 2, 2), vec![]);
+        assert_eq!(row_batches(&x, 4).iter().map(|b| b.rows()).collect::<Vec<_>>(), vec![4, 4, 3]);
+    }
+
+    #[test]
+    fn test_cross_val_score() {
+
+        let x = mat![1.0; 2.0; 3.0; 4.0];
+        let y = vec![1.0, 3.0, 5.0, 7.0];
+        let err = |t: &[f64], p: &[f64]| t.iter().zip(p.iter()).map(|(a, b)| (a - b).abs()).sum::<f64>();
+
+        let scores = cross_val_score(&Mean(0.0), &x, &y, KFold::new(4, 2), err);
+        assert_eq!(scores, vec![8.0, 8.0]);
+    }
+
+    // predicts the mean of the training labels plus an offset
+    #[derive(Clone)]
+    struct MeanRegressor {
+        offset: f64,
+        mean: f64
+    }
+
+    impl Estimator<f64, f64> for MeanRegressor {
+        fn fit(&mut self, _x: &Matrix<f64>, y: &[f64]) {
+            self.mean = y.iter().fold(0.0, |acc, v| acc + v) / y.len() as f64;
+        }
+
+        fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+            vec![self.mean + self.offset; x.rows()]
+        }
+    }
+
+    fn mae(t: &[f64], p: &[f64]) -> f64 {
+        t.iter().zip(p.iter()).fold(0.0, |acc, (a, b)| acc + (a - b).abs()) / t.len() as f64
+    }
+
+    #[test]
+    fn test_learning_curve() {
+
+        let x = Matrix::fill(0.0, 10, 1);
+        let y = vec![1.0; 10];
+        let e = MeanRegressor { offset: 0.0, mean: 0.0 };
+
+        let (sizes, scores) = learning_curve(&e, &x, &y, KFold::new(10, 5), &[0.1, 0.5, 1.0], mae);
+        assert_eq!(sizes, vec![1, 4, 8]);
+        assert_eq!(scores.train, Matrix::fill(0.0, 3, 5));
+        assert_eq!(scores.validation_mean(), vec![0.0, 0.0, 0.0]);
+
+        // the first training example of each split is 0, all others are 1
+        let y: Vec<f64> = (0..10).map(|i| if i % 2 == 0 { 0.0 } else { 1.0 }).collect();
+        let (_, scores) = learning_curve(&e, &x, &y, KFold::new(10, 5), &[0.1], mae);
+        assert_eq!(scores.train_mean(), vec![0.0]);
+        assert_eq!(scores.validation_mean(), vec![0.5]);
+    }
+
+    #[test]
+    fn test_validation_curve() {
+
+        let x = Matrix::fill(0.0, 6, 1);
+        let y = vec![2.0; 6];
+        let e = MeanRegressor { offset: 0.0, mean: 0.0 };
+
+        let scores = validation_curve(&e, &x, &y, KFold::new(6, 3), &[-1.0, 0.0, 0.5],
+            |e, &p| e.offset = p, mae);
+        assert_eq!(scores.train.rows(), 3);
+        assert_eq!(scores.train.cols(), 3);
+        assert_eq!(scores.train_mean(), vec![1.0, 0.0, 0.5]);
+        assert_eq!(scores.validation_mean(), vec![1.0, 0.0, 0.5]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_cross_validation() {
+
+        let x = Matrix::from_vec((0..20).map(|i| i as f64).collect(), 20, 1);
+        let y: Vec<f64> = (0..20).map(|i| ((i * 7) % 5) as f64).collect();
+        let e = MeanRegressor { offset: 0.0, mean: 0.0 };
+
+        let splits: Vec<_> = KFold::shuffled(20, 5, [1, 2, 3, 4]).collect();
+        assert_eq!(par_cross_val_score(&e, &x, &y, splits.clone(), mae), cross_val_score(&e, &x, &y, splits.clone(), mae));
+
+        let params = [-1.0, 0.0, 0.5, 2.0];
+        let a = par_validation_curve(&e, &x, &y, splits.clone(), &params, |e, &p| e.offset = p, mae);
+        let b = validation_curve(&e, &x, &y, splits, &params, |e, &p| e.offset = p, mae);
+        assert_eq!(a.train, b.train);
+        assert_eq!(a.validation, b.validation);
+    }
+}
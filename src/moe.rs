@@ -0,0 +1,426 @@
+//! Mixture of experts.
+//!
+//! A [MixtureOfExperts](struct.MixtureOfExperts.html) combines `k` expert
+//! networks with a gating network, all of them instances of
+//! [NeuralNetwork](../nn/struct.NeuralNetwork.html). For an input `x` the
+//! gating network outputs one value for each expert which is normalized
+//! into the probability `g_k(x)` that expert `k` is responsible for `x`.
+//! The prediction is the mixture of the outputs of the experts weighted by
+//! these probabilities, i.e. the experts specialize on different regions of
+//! the input space.
+//!
+//! The likelihood of a target given the output of an expert is a Gaussian
+//! with a variance for each expert (regression) or a product of Bernoulli
+//! distributions, one for each output unit (classification with targets in
+//! `[0, 1]`).
+//!
+//! The mixture can be trained jointly in two ways:
+//!
+//! * with expectation-maximization via [Em](../em/struct.Em.html) on the
+//!   pair of inputs and targets. The E-step computes the posterior
+//!   responsibility of each expert for each example. The M-step performs a
+//!   few steps of gradient descent in which each expert minimizes the
+//!   squared error weighted by its responsibilities and the gating network
+//!   is fitted to the responsibilities.
+//! * with gradient descent via the trait
+//!   [GradientDescent](../nn/trait.GradientDescent.html) which performs one
+//!   of these steps after each E-step.
+//!
+//! Because the networks are only partially optimized in each M-step the
+//! log-likelihood is not guaranteed to increase in each iteration.
+//!
+//! Note that the input layer of a network has no bias unit, i.e. a column
+//! of ones should be added to the inputs.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::nn::NeuralNetwork;
+//! use rustml::em::Em;
+//! use rustml::moe::*;
+//!
+//! # fn main() {
+//! // a step function, inputs with a bias column
+//! let x = Matrix::from_it((0..100).flat_map(|i| vec![1.0, i as f64 / 100.0]), 2);
+//! let t = Matrix::from_it((0..100).map(|i| if i < 50 { 0.2 } else { 0.8 }), 1);
+//!
+//! let gate = NeuralNetwork::new().add_layer(2).add_layer(2);
+//! let experts = (0..2).map(|_| NeuralNetwork::new().add_layer(2).add_layer(1)).collect();
+//! let moe = MixtureOfExperts::new(gate, experts, ExpertOutput::Regression).learning_rate(5.0);
+//!
+//! let data = (x, t);
+//! let r = Em::new().max_iter(50).seed([1, 2, 3, 4]).fit(&moe, &data);
+//! let y = r.model.predict(&data.0);
+//! assert!((y.get(10, 0).unwrap() - 0.2).abs() < 0.1);
+//! assert!((y.get(90, 0).unwrap() - 0.8).abs() < 0.1);
+//! # }
+//! ```
+extern crate rand;
+
+use self::rand::Rng;
+
+use matrix::Matrix;
+use ops_inplace::FusedOps;
+use ops_inplace::MatrixScalarOpsInPlace;
+use nn::{NeuralNetwork, GradientDescent, MeanSquareError};
+use stream::PartialFit;
+use opt::OptParams;
+use em::EmModel;
+use progress::{Progress, ProgressInfo, NoProgress, report};
+
+/// Likelihood of a target given the output of an expert.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExpertOutput {
+    /// Gaussian with a variance for each expert which is estimated from
+    /// the residuals.
+    Regression,
+    /// Product of Bernoulli distributions with the outputs of the expert as
+    /// probabilities. The targets must be in `[0, 1]`.
+    Classification
+}
+
+/// Mixture of expert networks combined by a gating network.
+#[derive(Clone, Debug)]
+pub struct MixtureOfExperts {
+    gate: NeuralNetwork,
+    experts: Vec<NeuralNetwork>,
+    output: ExpertOutput,
+    learning_rate: f64,
+    m_steps: usize,
+    variances: Vec<f64>
+}
+
+impl MixtureOfExperts {
+
+    /// Creates a mixture of the `experts` with the gating network `gate`
+    /// which must have one output unit for each expert. The M-step of
+    /// [EM](../em/index.html) performs 10 steps of gradient descent with a
+    /// learning rate of 1. The variances of the experts are initialized to
+    /// one.
+    ///
+    /// Panics if there are no experts, if the number of output units of the
+    /// gating network does not match the number of experts or if the
+    /// networks do not have the same number of input units or the experts
+    /// do not have the same number of output units.
+    pub fn new(gate: NeuralNetwork, experts: Vec<NeuralNetwork>, output: ExpertOutput) -> MixtureOfExperts {
+
+        assert!(!experts.is_empty(), "At least one expert is required.");
+        assert!(gate.output_size() == experts.len(), "Number of outputs of the gating network does not match the number of experts.");
+        assert!(experts.iter().all(|e| e.input_size() == gate.input_size()), "Number of inputs of the networks do not match.");
+        assert!(experts.iter().all(|e| e.output_size() == experts[0].output_size()), "Number of outputs of the experts do not match.");
+        MixtureOfExperts {
+            gate: gate,
+            variances: vec![1.0; experts.len()],
+            experts: experts,
+            output: output,
+            learning_rate: 1.0,
+            m_steps: 10
+        }
+    }
+
+    /// Sets the learning rate of the gradient descent steps.
+    pub fn learning_rate(&self, eta: f64) -> MixtureOfExperts {
+        MixtureOfExperts { learning_rate: eta, .. self.clone() }
+    }
+
+    /// Sets the number of gradient descent steps of each M-step.
+    ///
+    /// Panics if `n` is zero.
+    pub fn m_steps(&self, n: usize) -> MixtureOfExperts {
+
+        assert!(n > 0, "Number of steps must be greater than zero.");
+        MixtureOfExperts { m_steps: n, .. self.clone() }
+    }
+
+    /// Returns the gating network.
+    pub fn gate(&self) -> &NeuralNetwork {
+        &self.gate
+    }
+
+    /// Returns the expert networks.
+    pub fn experts(&self) -> &[NeuralNetwork] {
+        &self.experts
+    }
+
+    /// Returns the variance of each expert (only used for regression).
+    pub fn variances(&self) -> &[f64] {
+        &self.variances
+    }
+
+    /// Returns the probability of each expert (columns) for each row of
+    /// `x`.
+    pub fn gating(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        let g = self.gate.predict(x);
+        let v = g.row_iter()
+            .flat_map(|r| {
+                let s: f64 = r.iter().sum();
+                r.iter().map(move |p| p / s)
+            })
+            .collect();
+        Matrix::from_vec(v, g.rows(), g.cols())
+    }
+
+    /// Returns the outputs of the experts for each row of `x` weighted by
+    /// the probabilities of the gating network.
+    pub fn predict(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        let g = self.gating(x);
+        let outputs: Vec<Matrix<f64>> = self.experts.iter().map(|e| e.predict(x)).collect();
+        let m = outputs[0].cols();
+        let mut v = vec![0.0; x.rows() * m];
+        for (k, o) in outputs.iter().enumerate() {
+            for (i, r) in o.row_iter().enumerate() {
+                let w = *g.get(i, k).unwrap();
+                for j in 0..m {
+                    v[i * m + j] += w * r[j];
+                }
+            }
+        }
+        Matrix::from_vec(v, x.rows(), m)
+    }
+
+    /// Returns the responsibility of each expert (columns) for each pair of
+    /// an input (row of `x`) and its target (row of `t`).
+    pub fn responsibilities(&self, x: &Matrix<f64>, t: &Matrix<f64>) -> Matrix<f64> {
+        self.posterior(x, t).0
+    }
+
+    /// Returns the log-likelihood of the targets in the rows of `t` given
+    /// the inputs in the rows of `x`.
+    pub fn log_likelihood(&self, x: &Matrix<f64>, t: &Matrix<f64>) -> f64 {
+        self.posterior(x, t).1
+    }
+
+    fn posterior(&self, x: &Matrix<f64>, t: &Matrix<f64>) -> (Matrix<f64>, f64) {
+
+        assert!(x.rows() == t.rows(), "Number of examples and targets mismatch.");
+        let k = self.experts.len();
+        let g = self.gating(x);
+        let outputs: Vec<Matrix<f64>> = self.experts.iter().map(|e| e.predict(x)).collect();
+        let mut ll = 0.0;
+        let mut v = Vec::with_capacity(x.rows() * k);
+        let mut l = vec![0.0; k];
+        for (i, target) in t.row_iter().enumerate() {
+            for c in 0..k {
+                l[c] = g.get(i, c).unwrap().ln() + self.log_density(outputs[c].row(i).unwrap(), target, c);
+            }
+            let z = log_sum_exp(&l);
+            ll += z;
+            v.extend(l.iter().map(|a| (a - z).exp()));
+        }
+        (Matrix::from_vec(v, x.rows(), k), ll)
+    }
+
+    fn log_density(&self, y: &[f64], t: &[f64], expert: usize) -> f64 {
+
+        match self.output {
+            ExpertOutput::Regression => {
+                let s = self.variances[expert];
+                let ss = y.iter().zip(t.iter()).fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b));
+                -0.5 * y.len() as f64 * (2.0 * ::std::f64::consts::PI * s).ln() - ss / (2.0 * s)
+            }
+            ExpertOutput::Classification => {
+                y.iter().zip(t.iter()).fold(0.0, |acc, (&a, &b)| {
+                    let p = a.max(1e-12).min(1.0 - 1e-12);
+                    acc + b * p.ln() + (1.0 - b) * (1.0 - p).ln()
+                })
+            }
+        }
+    }
+
+    /// Performs `p.iter` iterations with the learning rate `p.alpha`, each
+    /// consisting of an E-step followed by one step of gradient descent of
+    /// all networks, and reports the negative mean log-likelihood after each
+    /// iteration (see the module [progress](../progress/index.html)).
+    ///
+    /// Panics if the learning rate or the number of iterations is not set.
+    pub fn gd_with_progress<P: Progress>(&self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>, progress: &mut P) -> MixtureOfExperts {
+
+        let a = p.alpha.unwrap();
+        let iter = p.iter.unwrap();
+        let mut m = self.clone();
+        for k in 0..iter {
+            let (resp, ll) = m.posterior(input, targets);
+            m.step(input, targets, &resp, a);
+            m.update_variances(input, targets, &resp);
+            report(progress, &ProgressInfo { algorithm: "mixture of experts", iteration: k + 1, max_iter: iter, loss: Some(-ll / input.rows() as f64) });
+        }
+        m
+    }
+
+    // One step of gradient descent of all networks for the given
+    // responsibilities.
+    fn step(&mut self, x: &Matrix<f64>, t: &Matrix<f64>, resp: &Matrix<f64>, alpha: f64) {
+
+        self.gate.partial_fit(x, resp, alpha);
+        for (c, e) in self.experts.iter_mut().enumerate() {
+            let mut d = e.weighted_derivatives(x, t, &resp.col(c).unwrap());
+            for i in &mut d {
+                i.imul_scalar(-alpha);
+            }
+            e.update_params(&d);
+        }
+    }
+
+    fn update_variances(&mut self, x: &Matrix<f64>, t: &Matrix<f64>, resp: &Matrix<f64>) {
+
+        if self.output != ExpertOutput::Regression {
+            return;
+        }
+        for (c, e) in self.experts.iter().enumerate() {
+            let y = e.predict(x);
+            let (mut ss, mut n) = (0.0, 0.0);
+            for (i, (a, b)) in y.row_iter().zip(t.row_iter()).enumerate() {
+                let w = *resp.get(i, c).unwrap();
+                ss += w * a.iter().zip(b.iter()).fold(0.0, |acc, (p, q)| acc + (p - q) * (p - q));
+                n += w * a.len() as f64;
+            }
+            self.variances[c] = (ss / n.max(::std::f64::EPSILON)).max(1e-6);
+        }
+    }
+}
+
+impl EmModel<(Matrix<f64>, Matrix<f64>)> for MixtureOfExperts {
+
+    /// Matrix with the responsibilities of the experts (columns) for each
+    /// example (rows).
+    type Posterior = Matrix<f64>;
+
+    /// Draws new random parameters of all networks from the same
+    /// distribution as [add_layer](../nn/struct.NeuralNetwork.html#method.add_layer)
+    /// and sets the variances to the variance of the targets.
+    fn initialize(&mut self, data: &(Matrix<f64>, Matrix<f64>), rng: &mut dyn Rng) {
+
+        for n in Some(&mut self.gate).into_iter().chain(self.experts.iter_mut()) {
+            let params = n.params();
+            for (i, p) in params.iter().enumerate() {
+                let v = (0..p.rows() * p.cols()).map(|_| rng.next_f64()).collect();
+                *n = n.set_params(i, Matrix::from_vec(v, p.rows(), p.cols()));
+            }
+        }
+
+        let t = &data.1;
+        let n = (t.rows() * t.cols()) as f64;
+        let mean = t.iter().sum::<f64>() / n;
+        let var = t.iter().fold(0.0, |acc, v| acc + (v - mean) * (v - mean)) / n;
+        self.variances = vec![var.max(1e-6); self.experts.len()];
+    }
+
+    fn e_step(&self, data: &(Matrix<f64>, Matrix<f64>)) -> (Matrix<f64>, f64) {
+        self.posterior(&data.0, &data.1)
+    }
+
+    fn m_step(&mut self, data: &(Matrix<f64>, Matrix<f64>), resp: &Matrix<f64>) {
+
+        let (ref x, ref t) = *data;
+        let alpha = self.learning_rate;
+        for _ in 0..self.m_steps {
+            self.step(x, t, resp, alpha);
+        }
+        self.update_variances(x, t, resp);
+    }
+}
+
+impl GradientDescent for MixtureOfExperts {
+
+    /// Optimizes all networks via gradient descent (see
+    /// [gd_with_progress](struct.MixtureOfExperts.html#method.gd_with_progress)).
+    ///
+    /// Panics if the learning rate or the number of iterations is not set.
+    fn gd(&self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>) -> Self {
+        self.gd_with_progress(input, targets, p, &mut NoProgress)
+    }
+}
+
+impl MeanSquareError for MixtureOfExperts {
+
+    fn mse(&self, input: &Matrix<f64>, targets: &Matrix<f64>) -> f64 {
+        self.predict(input).sub_square_sum(targets) / (2.0 * input.rows() as f64)
+    }
+}
+
+fn log_sum_exp(v: &[f64]) -> f64 {
+
+    let m = v.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    if m == ::std::f64::NEG_INFINITY {
+        return m;
+    }
+    m + v.iter().fold(0.0, |acc, x| acc + (x - m).exp()).ln()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use em::Em;
+    use opt::empty_opts;
+    use rng;
+
+    // inputs with a bias column and a target which is a different linear
+    // function on each half of the interval
+    fn data() -> (Matrix<f64>, Matrix<f64>) {
+
+        let mut r = rng::seeded_rng([1, 2, 3, 4]);
+        let xs: Vec<f64> = (0..200).map(|_| r.gen::<f64>()).collect();
+        let x = Matrix::from_it(xs.iter().flat_map(|&v| vec![1.0, v]), 2);
+        let t = Matrix::from_it(xs.iter().map(|&v| if v < 0.5 { 0.1 + 0.4 * v } else { 0.9 - 0.4 * v }), 1);
+        (x, t)
+    }
+
+    #[test]
+    fn test_em() {
+
+        let data = data();
+        let gate = NeuralNetwork::new().add_layer(2).add_layer(2);
+        let experts = (0..2).map(|_| NeuralNetwork::new().add_layer(2).add_layer(1)).collect();
+        let moe = MixtureOfExperts::new(gate, experts, ExpertOutput::Regression).learning_rate(10.0);
+        let r = Em::new().max_iter(40).n_init(2).seed([5, 6, 7, 8]).fit(&moe, &data);
+        let m = r.model;
+        let (ref x, ref t) = data;
+
+        // a single expert cannot fit both halves
+        let single = NeuralNetwork::new().add_layer(2).add_layer(1).gd(x, t, empty_opts().alpha(10.0).iter(400));
+        assert!(m.mse(x, t) < 0.5 * single.mse(x, t));
+        assert!((m.log_likelihood(x, t) - r.log_likelihood).abs() < 1e-9);
+
+        // the experts are responsible for different halves
+        let g = m.gating(x);
+        assert!(g.row_iter().all(|r| (r[0] + r[1] - 1.0).abs() < 1e-12));
+        let left = (0..x.rows()).filter(|&i| x.get(i, 1).unwrap() < &0.4).map(|i| *g.get(i, 0).unwrap() > 0.5).collect::<Vec<bool>>();
+        let right = (0..x.rows()).filter(|&i| x.get(i, 1).unwrap() > &0.6).map(|i| *g.get(i, 0).unwrap() > 0.5).collect::<Vec<bool>>();
+        assert!(left.iter().all(|&b| b == left[0]));
+        assert!(right.iter().all(|&b| b != left[0]));
+        let h = m.responsibilities(x, t);
+        assert!(h.row_iter().all(|r| (r[0] + r[1] - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_gd() {
+
+        // class 1 at both ends of the interval which cannot be separated by
+        // a single logistic unit
+        let mut r = rng::seeded_rng([1, 2, 3, 4]);
+        let xs: Vec<f64> = (0..200).map(|_| r.gen::<f64>()).collect();
+        let x = Matrix::from_it(xs.iter().flat_map(|&v| vec![1.0, v]), 2);
+        let t = Matrix::from_it(xs.iter().map(|&v| if v < 0.3 || v > 0.7 { 1.0 } else { 0.0 }), 1);
+
+        let mut rng = rng::seeded_rng([3, 4, 5, 6]);
+        let gate = NeuralNetwork::new().add_layer_with_rng(2, &mut rng).add_layer_with_rng(2, &mut rng);
+        let experts = (0..2).map(|_| NeuralNetwork::new().add_layer_with_rng(2, &mut rng).add_layer_with_rng(1, &mut rng)).collect();
+        let moe = MixtureOfExperts::new(gate, experts, ExpertOutput::Classification);
+
+        let mut losses = vec![];
+        let m = moe.gd_with_progress(&x, &t, empty_opts().alpha(20.0).iter(400), &mut |i: &ProgressInfo| losses.push(i.loss.unwrap()));
+        assert_eq!(losses.len(), 400);
+        assert!(m.log_likelihood(&x, &t) > moe.log_likelihood(&x, &t));
+        assert!(losses[399] < losses[0]);
+
+        let y = m.predict(&x);
+        let correct = y.iter().zip(t.iter()).filter(|&(a, b)| (a > &0.5) == (b > &0.5)).count();
+        assert!(correct > 180, "{}", correct);
+    }
+}
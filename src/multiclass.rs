@@ -0,0 +1,339 @@
+//! Reductions of multiclass classification to binary classification.
+//!
+//! Both wrappers turn a binary classifier, i.e. a
+//! [ProbabilisticClassifier](../model_selection/trait.ProbabilisticClassifier.html)
+//! for the classes `0` and `1`, into a classifier for an arbitrary number of
+//! classes. The labels can be of any ordered type, they are mapped to the
+//! binary problems internally.
+//!
+//! * [OneVsRest](struct.OneVsRest.html) fits one classifier per class which
+//!   separates the class from all other classes. The class whose classifier
+//!   returns the highest probability is predicted.
+//! * [OneVsOne](struct.OneVsOne.html) fits one classifier per pair of
+//!   classes on the examples of these two classes. Each classifier votes
+//!   for one of its classes and the class with the most votes is predicted.
+//!   Ties are resolved with the sum of the probabilities.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::model_selection::{Estimator, ProbabilisticClassifier};
+//! use rustml::multiclass::{OneVsRest, OneVsOne};
+//!
+//! // predicts class 1 for the examples which are closer to the mean of
+//! // the examples of class 1 than to the mean of the examples of class 0
+//! #[derive(Clone)]
+//! struct NearestMean(f64, f64);
+//!
+//! impl Estimator<f64, usize> for NearestMean {
+//!     fn fit(&mut self, x: &Matrix<f64>, y: &[usize]) {
+//!         let mean = |c| {
+//!             let v: Vec<f64> = x.row_iter().zip(y).filter(|&(_, &l)| l == c).map(|(r, _)| r[0]).collect();
+//!             v.iter().sum::<f64>() / v.len() as f64
+//!         };
+//!         *self = NearestMean(mean(0), mean(1));
+//!     }
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+//!         self.predict_proba(x).row_iter().map(|r| if r[1] > 0.5 { 1 } else { 0 }).collect()
+//!     }
+//! }
+//!
+//! impl ProbabilisticClassifier for NearestMean {
+//!     fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+//!         let v = x.row_iter().flat_map(|r| {
+//!             let (a, b) = ((r[0] - self.0).abs(), (r[0] - self.1).abs());
+//!             vec![b / (a + b), a / (a + b)]
+//!         }).collect();
+//!         Matrix::from_vec(v, x.rows(), 2)
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let x = mat![0.0; 1.0; 5.0; 6.0; 10.0; 11.0];
+//! let y = vec!["a", "a", "b", "b", "c", "c"];
+//!
+//! let mut o = OneVsOne::new(NearestMean(0.0, 0.0));
+//! o.fit(&x, &y);
+//! assert_eq!(o.classes(), &["a", "b", "c"]);
+//! assert_eq!(o.predict(&mat![0.5; 5.5; 12.0]), vec!["a", "b", "c"]);
+//!
+//! let mut r = OneVsRest::new(NearestMean(0.0, 0.0));
+//! r.fit(&x, &y);
+//! assert_eq!(r.predict(&mat![-1.0; 12.0]), vec!["a", "c"]);
+//! # }
+//! ```
+use matrix::Matrix;
+use model_selection::{Estimator, ProbabilisticClassifier};
+
+/// Returns the sorted distinct labels of `y` and the index of the label of
+/// each example in the sorted labels.
+fn label_indexes<L: Ord + Clone>(y: &[L]) -> (Vec<L>, Vec<usize>) {
+
+    let mut classes = y.to_vec();
+    classes.sort();
+    classes.dedup();
+    let idx = y.iter().map(|l| classes.binary_search(l).unwrap()).collect();
+    (classes, idx)
+}
+
+fn argmax(v: &[f64]) -> usize {
+    v.iter().enumerate().fold((0, ::std::f64::MIN), |b, (j, &s)| if s > b.1 { (j, s) } else { b }).0
+}
+
+/// One-vs-rest (or one-vs-all) multiclass classifier.
+#[derive(Clone, Debug)]
+pub struct OneVsRest<E, L> {
+    prototype: E,
+    estimators: Vec<E>,
+    classes: Vec<L>
+}
+
+impl <E: ProbabilisticClassifier + Clone, L: Ord + Clone> OneVsRest<E, L> {
+
+    /// Creates an unfitted classifier. For each class a copy of `binary` is
+    /// fitted.
+    pub fn new(binary: E) -> OneVsRest<E, L> {
+        OneVsRest {
+            prototype: binary,
+            estimators: vec![],
+            classes: vec![]
+        }
+    }
+
+    /// Returns the classes in ascending order.
+    pub fn classes(&self) -> &[L] {
+        &self.classes
+    }
+
+    /// Returns the binary classifiers, i.e. the classifier at position `i`
+    /// separates the class `classes()[i]` from the other classes.
+    pub fn estimators(&self) -> &[E] {
+        &self.estimators
+    }
+
+    /// Returns a matrix with one row for each row in `x` whose column `j`
+    /// contains the probability of the class `classes()[j]` normalized so
+    /// that each row sums up to one.
+    ///
+    /// Panics if the classifier is not fitted.
+    pub fn scores(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(!self.estimators.is_empty(), "Classifier is not fitted.");
+        let k = self.estimators.len();
+        let mut v = vec![0.0; x.rows() * k];
+        for (j, e) in self.estimators.iter().enumerate() {
+            for (i, r) in e.predict_proba(x).row_iter().enumerate() {
+                v[i * k + j] = r[1];
+            }
+        }
+        for r in v.chunks_mut(k) {
+            let s: f64 = r.iter().sum();
+            if s > 0.0 {
+                for p in r.iter_mut() {
+                    *p /= s;
+                }
+            }
+        }
+        Matrix::from_vec(v, x.rows(), k)
+    }
+}
+
+impl <E: ProbabilisticClassifier + Clone, L: Ord + Clone> Estimator<f64, L> for OneVsRest<E, L> {
+
+    /// Fits one binary classifier per class.
+    ///
+    /// Panics if the number of rows of `x` and the number of labels differ
+    /// or if there are fewer than two classes.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[L]) {
+
+        assert!(x.rows() == y.len(), "Number of rows and number of labels must be equal.");
+        let (classes, idx) = label_indexes(y);
+        assert!(classes.len() >= 2, "At least two classes are required.");
+
+        self.estimators = (0..classes.len())
+            .map(|c| {
+                let yc: Vec<usize> = idx.iter().map(|&l| if l == c { 1 } else { 0 }).collect();
+                let mut e = self.prototype.clone();
+                e.fit(x, &yc);
+                e
+            })
+            .collect();
+        self.classes = classes;
+    }
+
+    /// Predicts the class with the highest probability.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<L> {
+        self.scores(x).row_iter().map(|r| self.classes[argmax(r)].clone()).collect()
+    }
+}
+
+/// One-vs-one multiclass classifier.
+#[derive(Clone, Debug)]
+pub struct OneVsOne<E, L> {
+    prototype: E,
+    estimators: Vec<(usize, usize, E)>,
+    classes: Vec<L>
+}
+
+impl <E: ProbabilisticClassifier + Clone, L: Ord + Clone> OneVsOne<E, L> {
+
+    /// Creates an unfitted classifier. For each pair of classes a copy of
+    /// `binary` is fitted.
+    pub fn new(binary: E) -> OneVsOne<E, L> {
+        OneVsOne {
+            prototype: binary,
+            estimators: vec![],
+            classes: vec![]
+        }
+    }
+
+    /// Returns the classes in ascending order.
+    pub fn classes(&self) -> &[L] {
+        &self.classes
+    }
+
+    /// Returns the number of binary classifiers, i.e. `k * (k - 1) / 2` for
+    /// `k` classes.
+    pub fn n_estimators(&self) -> usize {
+        self.estimators.len()
+    }
+
+    /// Returns a matrix with one row for each row in `x` whose column `j`
+    /// contains the number of votes for the class `classes()[j]` plus a
+    /// value in (0, 1) derived from the sum of its probabilities, which
+    /// breaks ties but never changes the order of different numbers of
+    /// votes.
+    ///
+    /// Panics if the classifier is not fitted.
+    pub fn scores(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(!self.estimators.is_empty(), "Classifier is not fitted.");
+        let k = self.classes.len();
+        let mut votes = vec![0.0; x.rows() * k];
+        let mut conf = vec![0.0; x.rows() * k];
+        for &(a, b, ref e) in &self.estimators {
+            for (i, r) in e.predict_proba(x).row_iter().enumerate() {
+                // class 1 of the binary problem is the class b
+                if r[1] > 0.5 {
+                    votes[i * k + b] += 1.0;
+                } else {
+                    votes[i * k + a] += 1.0;
+                }
+                conf[i * k + a] += r[0];
+                conf[i * k + b] += r[1];
+            }
+        }
+        // the sum of the probabilities of a class is within [0, k - 1]
+        let v = votes.iter().zip(conf.iter()).map(|(v, c)| v + c / (k as f64 + 1.0)).collect();
+        Matrix::from_vec(v, x.rows(), k)
+    }
+}
+
+impl <E: ProbabilisticClassifier + Clone, L: Ord + Clone> Estimator<f64, L> for OneVsOne<E, L> {
+
+    /// Fits one binary classifier per pair of classes.
+    ///
+    /// Panics if the number of rows of `x` and the number of labels differ
+    /// or if there are fewer than two classes.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[L]) {
+
+        assert!(x.rows() == y.len(), "Number of rows and number of labels must be equal.");
+        let (classes, idx) = label_indexes(y);
+        assert!(classes.len() >= 2, "At least two classes are required.");
+
+        let k = classes.len();
+        let mut estimators = Vec::with_capacity(k * (k - 1) / 2);
+        for a in 0..k {
+            for b in a + 1..k {
+                let rows: Vec<usize> = (0..y.len()).filter(|&i| idx[i] == a || idx[i] == b).collect();
+                let yab: Vec<usize> = rows.iter().map(|&i| if idx[i] == b { 1 } else { 0 }).collect();
+                let mut e = self.prototype.clone();
+                e.fit(&x.select_rows(&rows), &yab);
+                estimators.push((a, b, e));
+            }
+        }
+        self.estimators = estimators;
+        self.classes = classes;
+    }
+
+    /// Predicts the class with the most votes.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<L> {
+        self.scores(x).row_iter().map(|r| self.classes[argmax(r)].clone()).collect()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // logistic regression on one feature fitted with gradient descent
+    #[derive(Clone)]
+    struct Logistic(f64, f64);
+
+    impl Estimator<f64, usize> for Logistic {
+        fn fit(&mut self, x: &Matrix<f64>, y: &[usize]) {
+            *self = Logistic(0.0, 0.0);
+            for _ in 0..2000 {
+                let (mut ga, mut gb) = (0.0, 0.0);
+                for (r, &l) in x.row_iter().zip(y.iter()) {
+                    let d = 1.0 / (1.0 + (-(self.0 * r[0] + self.1)).exp()) - l as f64;
+                    ga += d * r[0];
+                    gb += d;
+                }
+                self.0 -= 0.1 * ga / y.len() as f64;
+                self.1 -= 0.1 * gb / y.len() as f64;
+            }
+        }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+            self.predict_proba(x).row_iter().map(|r| if r[1] > 0.5 { 1 } else { 0 }).collect()
+        }
+    }
+
+    impl ProbabilisticClassifier for Logistic {
+        fn predict_proba(&self, x: &Matrix<f64>) -> Matrix<f64> {
+            let v = x.row_iter().flat_map(|r| {
+                let p = 1.0 / (1.0 + (-(self.0 * r[0] + self.1)).exp());
+                vec![1.0 - p, p]
+            }).collect();
+            Matrix::from_vec(v, x.rows(), 2)
+        }
+    }
+
+    #[test]
+    fn test_one_vs_rest() {
+
+        // the middle class cannot be separated from the rest by a linear
+        // classifier but its score is still the highest in the middle
+        let x = Matrix::from_vec(vec![-3.0, -2.5, -2.0, -0.5, 0.0, 0.5, 2.0, 2.5, 3.0], 9, 1);
+        let y = vec![7, 7, 7, 8, 8, 8, 9, 9, 9];
+
+        let mut o = OneVsRest::new(Logistic(0.0, 0.0));
+        o.fit(&x, &y);
+        assert_eq!(o.classes(), &[7, 8, 9]);
+        assert_eq!(o.estimators().len(), 3);
+        assert_eq!(o.predict(&x), y);
+        let s = o.scores(&x);
+        assert!(s.row_iter().all(|r| (r.iter().sum::<f64>() - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_one_vs_one() {
+
+        let x = Matrix::from_vec(vec![0.0, 1.0, 5.0, 6.0, 10.0, 11.0, 15.0, 16.0], 8, 1);
+        let y = vec![0, 0, 1, 1, 2, 2, 3, 3];
+
+        let mut o = OneVsOne::new(Logistic(0.0, 0.0));
+        o.fit(&x, &y);
+        assert_eq!(o.n_estimators(), 6);
+        assert_eq!(o.predict(&x), y);
+
+        // each class wins all of its three binary problems
+        let s = o.scores(&x);
+        let v = *s.get(0, 0).unwrap();
+        assert!(v >= 3.0 && v < 4.0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Regression of several targets with one regressor per target.
+//!
+//! [MultiOutputRegressor](struct.MultiOutputRegressor.html) wraps a
+//! regressor which predicts a single value, i.e. an
+//! [Estimator](../model_selection/trait.Estimator.html) with `f64` labels,
+//! and fits one copy of it for each target column. The regressors can be
+//! fitted in parallel threads because they are independent of each other.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::model_selection::Estimator;
+//! use rustml::multioutput::MultiOutputRegressor;
+//!
+//! // predicts the mean of the training labels
+//! #[derive(Clone)]
+//! struct MeanLabel(f64);
+//!
+//! impl Estimator<f64, f64> for MeanLabel {
+//!     fn fit(&mut self, _x: &Matrix<f64>, y: &[f64]) {
+//!         self.0 = y.iter().sum::<f64>() / y.len() as f64;
+//!     }
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+//!         vec![self.0; x.rows()]
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let x = mat![1.0; 2.0];
+//! let y = mat![1.0, 10.0; 3.0, 20.0];
+//!
+//! let mut m = MultiOutputRegressor::new(MeanLabel(0.0)).n_threads(2);
+//! m.fit_targets(&x, &y);
+//! assert_eq!(m.predict_targets(&x), mat![2.0, 15.0; 2.0, 15.0]);
+//! # }
+//! ```
+use std::thread;
+
+use matrix::Matrix;
+use model_selection::Estimator;
+
+/// Wrapper which fits one regressor per target column.
+#[derive(Clone, Debug)]
+pub struct MultiOutputRegressor<E> {
+    prototype: E,
+    estimators: Vec<E>,
+    n_threads: usize
+}
+
+impl <E: Estimator<f64, f64> + Clone + Send + Sync> MultiOutputRegressor<E> {
+
+    /// Creates an unfitted wrapper which fits a copy of `regressor` for
+    /// each target in the current thread.
+    pub fn new(regressor: E) -> MultiOutputRegressor<E> {
+        MultiOutputRegressor {
+            prototype: regressor,
+            estimators: vec![],
+            n_threads: 1
+        }
+    }
+
+    /// Fits and evaluates the regressors in `n` threads.
+    ///
+    /// Panics if `n` is zero.
+    pub fn n_threads(&self, n: usize) -> MultiOutputRegressor<E> {
+
+        assert!(n > 0, "Number of threads must be greater than zero.");
+        MultiOutputRegressor { n_threads: n, .. self.clone() }
+    }
+
+    /// Returns the fitted regressors, one for each target.
+    pub fn estimators(&self) -> &[E] {
+        &self.estimators
+    }
+
+    /// Fits one regressor for each column of `y` on the examples in the
+    /// rows of `x`.
+    ///
+    /// Panics if the number of rows of `x` and `y` differ or if `y` has no
+    /// columns.
+    pub fn fit_targets(&mut self, x: &Matrix<f64>, y: &Matrix<f64>) {
+
+        assert!(x.rows() == y.rows(), "Number of rows of the examples and the targets must be equal.");
+        assert!(y.cols() > 0, "At least one target is required.");
+
+        let targets: Vec<Vec<f64>> = (0..y.cols()).map(|j| y.col(j).unwrap()).collect();
+        let prototype = &self.prototype;
+        self.estimators = self.map_targets(y.cols(), |j| {
+            let mut e = prototype.clone();
+            e.fit(x, &targets[j]);
+            e
+        });
+    }
+
+    /// Predicts the targets of each row of `x`, i.e. column `j` of the
+    /// result contains the predictions of the regressor of target `j`.
+    ///
+    /// Panics if the wrapper is not fitted.
+    pub fn predict_targets(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(!self.estimators.is_empty(), "Regressor is not fitted.");
+        let k = self.estimators.len();
+        let preds = self.map_targets(k, |j| self.estimators[j].predict(x));
+        let mut v = vec![0.0; x.rows() * k];
+        for (j, p) in preds.iter().enumerate() {
+            for (i, &y) in p.iter().enumerate() {
+                v[i * k + j] = y;
+            }
+        }
+        Matrix::from_vec(v, x.rows(), k)
+    }
+
+    // calls f for the targets 0..k, distributed round robin over the threads
+    fn map_targets<R: Send, F: Fn(usize) -> R + Sync>(&self, k: usize, f: F) -> Vec<R> {
+
+        let n_threads = self.n_threads.min(k);
+        if n_threads <= 1 {
+            return (0..k).map(f).collect();
+        }
+        let f = &f;
+        let results: Vec<Vec<R>> = thread::scope(|s| {
+            let handles: Vec<_> = (0..n_threads)
+                .map(|i| s.spawn(move || (i..k).step_by(n_threads).map(f).collect()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let mut iters: Vec<_> = results.into_iter().map(|r| r.into_iter()).collect();
+        (0..k).map(|j| iters[j % n_threads].next().unwrap()).collect()
+    }
+}
+
+impl <E: Estimator<f64, f64> + Clone + Send + Sync> Estimator<f64, Vec<f64>> for MultiOutputRegressor<E> {
+
+    /// Like [fit_targets](#method.fit_targets) with the targets of each
+    /// example given as a vector.
+    ///
+    /// Panics if the vectors do not have the same length.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[Vec<f64>]) {
+
+        let k = y.first().map(|v| v.len()).unwrap_or(0);
+        assert!(y.iter().all(|v| v.len() == k), "All examples must have the same number of targets.");
+        let v = y.iter().flat_map(|v| v.iter().cloned()).collect();
+        self.fit_targets(x, &Matrix::from_vec(v, y.len(), k));
+    }
+
+    /// Like [predict_targets](#method.predict_targets) with the targets of
+    /// each example returned as a vector.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<Vec<f64>> {
+        self.predict_targets(x).row_iter().map(|r| r.to_vec()).collect()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    // least squares fit of a line to the first column
+    #[derive(Clone)]
+    struct Line(f64, f64);
+
+    impl Estimator<f64, f64> for Line {
+        fn fit(&mut self, x: &Matrix<f64>, y: &[f64]) {
+            let n = y.len() as f64;
+            let mx = x.col(0).unwrap().iter().sum::<f64>() / n;
+            let my = y.iter().sum::<f64>() / n;
+            let (mut sxy, mut sxx) = (0.0, 0.0);
+            for (r, t) in x.row_iter().zip(y.iter()) {
+                sxy += (r[0] - mx) * (t - my);
+                sxx += (r[0] - mx) * (r[0] - mx);
+            }
+            self.0 = sxy / sxx;
+            self.1 = my - self.0 * mx;
+        }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+            x.row_iter().map(|r| self.0 * r[0] + self.1).collect()
+        }
+    }
+
+    #[test]
+    fn test_multi_output() {
+
+        let x = Matrix::from_vec((0..10).map(|i| i as f64).collect(), 10, 1);
+        let y = Matrix::from_vec((0..10).flat_map(|i| (0..5).map(move |j| (j as f64) * (i as f64) - j as f64)).collect(), 10, 5);
+
+        let mut a = MultiOutputRegressor::new(Line(0.0, 0.0));
+        a.fit_targets(&x, &y);
+        assert_eq!(a.estimators().len(), 5);
+        assert!(a.predict_targets(&x).similar(&y, 1e-10));
+
+        // same result in several threads
+        let mut b = MultiOutputRegressor::new(Line(0.0, 0.0)).n_threads(3);
+        b.fit_targets(&x, &y);
+        assert_eq!(b.predict_targets(&x), a.predict_targets(&x));
+
+        let rows: Vec<Vec<f64>> = y.row_iter().map(|r| r.to_vec()).collect();
+        b.fit(&x, &rows);
+        assert!(b.predict(&x).iter().zip(rows.iter()).all(|(p, t)| p[..].similar(&t[..], 1e-10)));
+    }
+}
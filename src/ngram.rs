@@ -0,0 +1,342 @@
+//! n-gram language models.
+//!
+//! An [NgramModel](struct.NgramModel.html) estimates the probability of a
+//! token given the `n - 1` preceding tokens from the counts of the n-grams
+//! of a tokenized corpus (e.g. sentences split with
+//! [tokenize](../text/fn.tokenize.html)). Each sentence is padded with
+//! `n - 1` start symbols and one end symbol so that the model also learns
+//! which tokens start and end a sentence.
+//!
+//! The counts are smoothed (see [Smoothing](enum.Smoothing.html)) so that
+//! unseen n-grams and tokens which are not in the vocabulary get a non-zero
+//! probability. The model is evaluated with the
+//! [perplexity](struct.NgramModel.html#method.perplexity) on held-out
+//! sentences and new text is generated by sampling one token after another.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::text::tokenize;
+//! use rustml::ngram::NgramModel;
+//! use rustml::rng::seeded_rng;
+//!
+//! let corpus = vec![
+//!     tokenize("the cat sat on the mat"),
+//!     tokenize("the dog sat on the log"),
+//!     tokenize("the cat saw the dog")
+//! ];
+//!
+//! let mut m = NgramModel::new(2);
+//! m.fit(&corpus);
+//! assert!(m.prob(&["the"], "cat") > m.prob(&["the"], "sat"));
+//! assert!(m.perplexity(&[tokenize("the cat sat on the log")]) < m.perplexity(&[tokenize("log the on sat")]));
+//!
+//! let s = m.generate(&["the"], 20, &mut seeded_rng([1, 2, 3, 4]));
+//! assert!(s.len() <= 20);
+//! ```
+extern crate rand;
+
+use std::collections::{BTreeMap, BTreeSet};
+use self::rand::Rng;
+
+/// Symbol with which each sentence is padded at the beginning.
+pub const START: &'static str = "<s>";
+
+/// Symbol which is appended to each sentence.
+pub const END: &'static str = "</s>";
+
+/// Smoothing methods of the [NgramModel](struct.NgramModel.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    /// Adds `k` to the count of each n-gram of the highest order (add-one
+    /// or Laplace smoothing for `k = 1`).
+    AddK(f64),
+    /// Interpolated Kneser-Ney smoothing with the given absolute discount
+    /// in `(0, 1)`. The lower orders are estimated from the number of
+    /// distinct contexts in which a token occurs and the lowest order is
+    /// interpolated with the uniform distribution.
+    KneserNey(f64)
+}
+
+/// n-gram language model.
+#[derive(Clone, Debug)]
+pub struct NgramModel {
+    n: usize,
+    smoothing: Smoothing,
+    vocabulary: Vec<String>,
+    index: BTreeMap<String, usize>,
+    // counts of the n-grams of each order m (at index m - 1), i.e. raw
+    // counts for the highest order and continuation counts for the lower
+    // orders of Kneser-Ney smoothing
+    counts: Vec<BTreeMap<Vec<usize>, f64>>,
+    // total count and number of distinct successors of each context
+    contexts: Vec<BTreeMap<Vec<usize>, (f64, f64)>>
+}
+
+impl NgramModel {
+
+    /// Creates an untrained model of order `n` (e.g. 2 for a bigram
+    /// model) with interpolated Kneser-Ney smoothing and a discount of
+    /// 0.75.
+    ///
+    /// Panics if `n` is zero.
+    pub fn new(n: usize) -> NgramModel {
+
+        assert!(n > 0, "Order must be greater than zero.");
+        NgramModel {
+            n: n,
+            smoothing: Smoothing::KneserNey(0.75),
+            vocabulary: vec![],
+            index: BTreeMap::new(),
+            counts: vec![],
+            contexts: vec![]
+        }
+    }
+
+    /// Sets the smoothing method.
+    ///
+    /// Panics if the parameter of the method is out of range.
+    pub fn smoothing(&self, s: Smoothing) -> NgramModel {
+
+        match s {
+            Smoothing::AddK(k) => assert!(k > 0.0, "k must be greater than zero."),
+            Smoothing::KneserNey(d) => assert!(d > 0.0 && d < 1.0, "Discount must be in (0, 1).")
+        }
+        NgramModel { smoothing: s, .. self.clone() }
+    }
+
+    /// Returns the order of the model.
+    pub fn order(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the sorted vocabulary of the training corpus including the
+    /// [END](constant.END.html) symbol.
+    pub fn vocabulary(&self) -> &[String] {
+        &self.vocabulary
+    }
+
+    /// Counts the n-grams of all orders of the sentences of `corpus`. Any
+    /// previous counts are discarded.
+    pub fn fit<S: AsRef<str>>(&mut self, corpus: &[Vec<S>]) {
+
+        let words: BTreeSet<&str> = corpus.iter().flat_map(|s| s.iter().map(|t| t.as_ref())).chain(Some(END)).collect();
+        self.vocabulary = words.into_iter().map(|w| w.to_string()).collect();
+        self.index = self.vocabulary.iter().enumerate().map(|(i, w)| (w.clone(), i)).collect();
+
+        let n = self.n;
+        let mut raw: Vec<BTreeMap<Vec<usize>, f64>> = vec![BTreeMap::new(); n];
+        for s in corpus {
+            let mut ids = vec![self.start(); n - 1];
+            ids.extend(s.iter().map(|t| self.id(t.as_ref())));
+            ids.push(self.id(END));
+            for i in n - 1..ids.len() {
+                for m in 1..n + 1 {
+                    *raw[m - 1].entry(ids[i + 1 - m..i + 1].to_vec()).or_insert(0.0) += 1.0;
+                }
+            }
+        }
+
+        self.counts = raw.clone();
+        if let Smoothing::KneserNey(_) = self.smoothing {
+            // continuation counts, i.e. the number of distinct tokens which
+            // precede an n-gram, except for n-grams at the start of a
+            // sentence which have no predecessor
+            for m in 1..n {
+                let start = self.start();
+                let mut c: BTreeMap<Vec<usize>, f64> = raw[m - 1].iter()
+                    .filter(|&(g, _)| g[0] == start)
+                    .map(|(g, &v)| (g.clone(), v))
+                    .collect();
+                for g in raw[m].keys().filter(|g| g[1] != start) {
+                    *c.entry(g[1..].to_vec()).or_insert(0.0) += 1.0;
+                }
+                self.counts[m - 1] = c;
+            }
+        }
+
+        self.contexts = self.counts.iter().map(|c| {
+            let mut h: BTreeMap<Vec<usize>, (f64, f64)> = BTreeMap::new();
+            for (g, &v) in c {
+                let e = h.entry(g[..g.len() - 1].to_vec()).or_insert((0.0, 0.0));
+                e.0 += v;
+                e.1 += 1.0;
+            }
+            h
+        }).collect();
+    }
+
+    /// Returns the probability of `word` after the tokens of `context`. Only
+    /// the last `n - 1` tokens of the context are used and a shorter context
+    /// is padded with start symbols, i.e. an empty context denotes the
+    /// beginning of a sentence. The probability of the end of a sentence is
+    /// `prob(context, END)`.
+    ///
+    /// Panics if the model is not trained.
+    pub fn prob<S: AsRef<str>>(&self, context: &[S], word: &str) -> f64 {
+
+        assert!(!self.vocabulary.is_empty(), "Model is not trained.");
+        let h = self.context_ids(context);
+        self.prob_ids(&h, self.id(word))
+    }
+
+    /// Returns the natural logarithm of the probability of a sentence
+    /// including its end symbol.
+    pub fn log_prob<S: AsRef<str>>(&self, sentence: &[S]) -> f64 {
+
+        assert!(!self.vocabulary.is_empty(), "Model is not trained.");
+        let mut h = vec![self.start(); self.n - 1];
+        let mut lp = 0.0;
+        for w in sentence.iter().map(|t| self.id(t.as_ref())).chain(Some(self.id(END))) {
+            lp += self.prob_ids(&h, w).ln();
+            if !h.is_empty() {
+                h.remove(0);
+                h.push(w);
+            }
+        }
+        lp
+    }
+
+    /// Returns the perplexity of the model on the sentences of `corpus`,
+    /// i.e. `exp(-L / N)` where `L` is the sum of the log probabilities of
+    /// the sentences and `N` the number of tokens including the end
+    /// symbols. Lower values are better.
+    pub fn perplexity<S: AsRef<str>>(&self, corpus: &[Vec<S>]) -> f64 {
+
+        let ll: f64 = corpus.iter().map(|s| self.log_prob(s)).sum();
+        let n: usize = corpus.iter().map(|s| s.len() + 1).sum();
+        (-ll / n as f64).exp()
+    }
+
+    /// Draws the next token after `context` from the distribution of the
+    /// model over the vocabulary.
+    pub fn sample_next<S: AsRef<str>, R: Rng>(&self, context: &[S], rng: &mut R) -> &str {
+
+        assert!(!self.vocabulary.is_empty(), "Model is not trained.");
+        let h = self.context_ids(context);
+        let p: Vec<f64> = (0..self.vocabulary.len()).map(|w| self.prob_ids(&h, w)).collect();
+        let u = rng.gen::<f64>() * p.iter().sum::<f64>();
+        let mut acc = 0.0;
+        for (w, x) in p.iter().enumerate() {
+            acc += *x;
+            if u < acc {
+                return &self.vocabulary[w];
+            }
+        }
+        &self.vocabulary[p.len() - 1]
+    }
+
+    /// Generates the continuation of `context` token by token until the end
+    /// symbol is drawn or `max_len` tokens are generated. The end symbol is
+    /// not part of the result.
+    pub fn generate<S: AsRef<str>, R: Rng>(&self, context: &[S], max_len: usize, rng: &mut R) -> Vec<String> {
+
+        let mut h: Vec<String> = context.iter().map(|t| t.as_ref().to_string()).collect();
+        let mut r = vec![];
+        while r.len() < max_len {
+            let w = self.sample_next(&h, rng).to_string();
+            if w == END {
+                break;
+            }
+            h.push(w.clone());
+            r.push(w);
+        }
+        r
+    }
+
+    // ids of the tokens of the vocabulary, the id of unknown tokens is the
+    // length of the vocabulary and the id of the start symbol is the
+    // length plus one
+    fn id(&self, w: &str) -> usize {
+        self.index.get(w).cloned().unwrap_or(self.vocabulary.len())
+    }
+
+    fn start(&self) -> usize {
+        self.vocabulary.len() + 1
+    }
+
+    fn context_ids<S: AsRef<str>>(&self, context: &[S]) -> Vec<usize> {
+
+        let k = self.n - 1;
+        let mut h = vec![self.start(); k.saturating_sub(context.len())];
+        h.extend(context[context.len().saturating_sub(k)..].iter().map(|t| self.id(t.as_ref())));
+        h
+    }
+
+    fn prob_ids(&self, h: &[usize], w: usize) -> f64 {
+
+        // predicted tokens are the vocabulary and the unknown token
+        let v = self.vocabulary.len() as f64 + 1.0;
+        match self.smoothing {
+            Smoothing::AddK(k) => {
+                let m = self.n - 1;
+                let c = self.count(m, h, w);
+                let total = self.contexts[m].get(h).map(|e| e.0).unwrap_or(0.0);
+                (c + k) / (total + k * v)
+            }
+            Smoothing::KneserNey(d) => {
+                let mut p = 1.0 / v;
+                for m in 0..self.n {
+                    let hm = &h[h.len() - m..];
+                    if let Some(&(total, distinct)) = self.contexts[m].get(hm) {
+                        let c = self.count(m, hm, w);
+                        p = (c - d).max(0.0) / total + d * distinct / total * p;
+                    }
+                }
+                p
+            }
+        }
+    }
+
+    fn count(&self, m: usize, h: &[usize], w: usize) -> f64 {
+
+        let mut g = h.to_vec();
+        g.push(w);
+        self.counts[m].get(&g).cloned().unwrap_or(0.0)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::seeded_rng;
+
+    #[test]
+    fn test_ngram() {
+
+        let corpus: Vec<Vec<&str>> = vec![
+            "a b c d", "a b d", "b c d a", "c d a b", "a c"
+        ].into_iter().map(|s| s.split(' ').collect()).collect();
+
+        for &s in &[Smoothing::KneserNey(0.75), Smoothing::AddK(1.0), Smoothing::AddK(0.1)] {
+            for n in 1..4 {
+                let mut m = NgramModel::new(n).smoothing(s);
+                m.fit(&corpus);
+                assert_eq!(m.vocabulary(), &["</s>", "a", "b", "c", "d"]);
+
+                // the distribution over the vocabulary and the unknown token
+                // sums to one for every context
+                for h in &[vec![], vec!["a"], vec!["a", "b"], vec!["x", "d"]] {
+                    let p: f64 = m.vocabulary().iter().map(|w| m.prob(h, w)).sum::<f64>() + m.prob(h, "unknown");
+                    assert!((p - 1.0).abs() < 1e-10, "{:?} {} {:?} {}", s, n, h, p);
+                }
+                assert!(m.perplexity(&corpus) < m.perplexity(&[vec!["d", "d", "x", "d"]]));
+            }
+        }
+
+        let mut m = NgramModel::new(2);
+        m.fit(&corpus);
+        assert!(m.prob(&["a"], "b") > m.prob(&["a"], "d"));
+        // 3 of 5 sentences start with "a"
+        assert!(m.prob::<&str>(&[], "a") > 0.4);
+
+        let mut rng = seeded_rng([1, 2, 3, 4]);
+        for _ in 0..20 {
+            let s = m.generate(&["a"], 10, &mut rng);
+            assert!(s.len() <= 10);
+            assert!(s.iter().all(|w| ["a", "b", "c", "d"].contains(&&w[..])));
+        }
+    }
+}
@@ -1,12 +1,174 @@
 //! Module which provides implementations of neural networks.
+//!
+//! Trained networks can be exported into the ONNX format with
+//! [to_onnx](struct.NeuralNetwork.html#method.to_onnx) (see the module
+//! [onnx](../io/onnx/index.html)).
+//!
+//! Besides regression and classification a network can be trained as the
+//! shared branch of a siamese network on pairs of inputs with a contrastive
+//! loss (see [contrastive_gd](struct.NeuralNetwork.html#method.contrastive_gd))
+//! to compute embeddings for verification tasks.
+//!
+//! All units use the sigmoid function unless another
+//! [Activation](trait.Activation.html) is set for a layer with
+//! [set_activation](struct.NeuralNetwork.html#method.set_activation).
+//! For multiclass classification the output layer can compute the softmax
+//! ([set_softmax](struct.NeuralNetwork.html#method.set_softmax)) and the
+//! network can be trained on the cross-entropy error
+//! ([set_cost](struct.NeuralNetwork.html#method.set_cost)).
 
 extern crate rand;
 
+use std::cell::RefCell;
+use std::cmp;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+
 use matrix::Matrix;
-use ops::{MatrixVectorOps, Functions, VectorVectorOps, MatrixScalarOps, MatrixMatrixOps};
-use vectors::{Append, random, from_value};
-use ops_inplace::{MatrixMatrixOpsInPlace, MatrixScalarOpsInPlace, FunctionsInPlace};
-use opt::OptParams;
+use ops::{Functions, MatrixMatrixOps};
+use vectors::{Append, random_with_rng, from_value};
+use ops_inplace::{MatrixMatrixOpsInPlace, MatrixScalarOpsInPlace, FusedOps, d_gemv, d_axpy};
+use opt::{OptParams, ParamGroup, Lookahead, PolyakAverage};
+use progress::{Progress, ProgressInfo, NoProgress, report, wants_loss};
+use io::model::{Model, Encoder, Decoder};
+use rng;
+use error::{RustmlError, unwrap_or_panic};
+use model_selection::{Estimator, row_batches};
+use quantize::QuantizedNetwork;
+use pool::{self, BufferPool};
+use stream::PartialFit;
+use checkpoint::{Checkpoint, Checkpointer, TrainState};
+use curriculum::WeightSchedule;
+use self::rand::Rng;
+
+/// Activation function of the units of a layer.
+///
+/// The function is applied to the net input `z` of a unit, i.e. the
+/// weighted sum of the activations of the previous layer.
+pub trait Activation: fmt::Debug + Send + Sync {
+
+    /// Returns the name of the function which identifies it when a network
+    /// is saved (see the module [model](../io/model/index.html)).
+    fn name(&self) -> &'static str;
+
+    /// Computes the activation of a unit with the net input `z`.
+    fn apply(&self, z: f64) -> f64;
+
+    /// Computes the derivative of the function at `z`, which is used in
+    /// backpropagation.
+    fn derivative(&self, z: f64) -> f64;
+
+    /// Returns the parameters of the function, e.g. the slope of
+    /// [LeakyRelu](struct.LeakyRelu.html).
+    fn params(&self) -> Vec<f64> {
+        vec![]
+    }
+
+    /// Returns true if the function is the logistic function of
+    /// [Sigmoid](struct.Sigmoid.html). Only then the derivative of the
+    /// cross-entropy simplifies to the difference between output and target.
+    fn is_logistic(&self) -> bool {
+        false
+    }
+}
+
+/// The logistic function 1 / (1 + exp(-z)), which is the default
+/// activation function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sigmoid;
+
+impl Activation for Sigmoid {
+    fn name(&self) -> &'static str { "sigmoid" }
+    fn apply(&self, z: f64) -> f64 { z.sigmoid() }
+    fn derivative(&self, z: f64) -> f64 { z.sigmoid_derivative() }
+    fn is_logistic(&self) -> bool { true }
+}
+
+/// The hyperbolic tangent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tanh;
+
+impl Activation for Tanh {
+    fn name(&self) -> &'static str { "tanh" }
+    fn apply(&self, z: f64) -> f64 { z.tanh() }
+    fn derivative(&self, z: f64) -> f64 { 1.0 - z.tanh() * z.tanh() }
+}
+
+/// The rectified linear unit max(0, z).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Relu;
+
+impl Activation for Relu {
+    fn name(&self) -> &'static str { "relu" }
+    fn apply(&self, z: f64) -> f64 { if z > 0.0 { z } else { 0.0 } }
+    fn derivative(&self, z: f64) -> f64 { if z > 0.0 { 1.0 } else { 0.0 } }
+}
+
+/// The leaky rectified linear unit which has the given slope for negative
+/// inputs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LeakyRelu(pub f64);
+
+impl Activation for LeakyRelu {
+    fn name(&self) -> &'static str { "leaky_relu" }
+    fn apply(&self, z: f64) -> f64 { if z > 0.0 { z } else { self.0 * z } }
+    fn derivative(&self, z: f64) -> f64 { if z > 0.0 { 1.0 } else { self.0 } }
+    fn params(&self) -> Vec<f64> { vec![self.0] }
+}
+
+/// Cost function which is minimized by the training of a
+/// [NeuralNetwork](struct.NeuralNetwork.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cost {
+    /// Half of the squared distance between output and target (the
+    /// default).
+    MeanSquare,
+    /// The cross-entropy -Σ t ln(y) if the output layer computes the softmax
+    /// and the binary cross-entropy -Σ t ln(y) + (1 - t) ln(1 - y) of each
+    /// output unit otherwise.
+    CrossEntropy
+}
+
+/// Buffers for the intermediate results of
+/// [NeuralNetwork::predict_into_with](struct.NeuralNetwork.html#method.predict_into_with)
+/// which can be reused between calls.
+#[derive(Clone, Debug, Default)]
+pub struct PredictBuffers {
+    a: Vec<f64>,
+    b: Vec<f64>
+}
+
+impl PredictBuffers {
+
+    /// Creates empty buffers. Memory is allocated on first use.
+    pub fn new() -> PredictBuffers {
+        PredictBuffers::default()
+    }
+}
+
+// numerically stable softmax
+fn softmax(v: &mut [f64]) {
+    let m = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut s = 0.0;
+    for x in v.iter_mut() {
+        *x = (*x - m).exp();
+        s += *x;
+    }
+    for x in v.iter_mut() {
+        *x /= s;
+    }
+}
+
+// keeps probabilities away from zero and one before taking the logarithm
+fn clamp(y: f64) -> f64 {
+    y.max(1e-15).min(1.0 - 1e-15)
+}
+
+thread_local! {
+    static BUFFERS: RefCell<PredictBuffers> = RefCell::new(PredictBuffers::new());
+}
 
 /// Trait to compute the mean square error of a predictor.
 pub trait MeanSquareError {
@@ -17,9 +179,23 @@ pub trait MeanSquareError {
 impl MeanSquareError for NeuralNetwork {
 
     fn mse(&self, input: &Matrix<f64>, targets: &Matrix<f64>) -> f64 {
-        let mut o = self.predict(input);
-        o.isub(targets);
-        o.iter().map(|&x| x * x).fold(0.0, |acc, val| acc + val) / (2.0 * input.rows() as f64)
+        self.predict(input).sub_square_sum(targets) / (2.0 * input.rows() as f64)
+    }
+}
+
+/// Trait to compute the cross-entropy error of a predictor.
+pub trait CrossEntropyError {
+    /// Computes the mean cross-entropy error of a predictor.
+    fn cross_entropy(&self, input: &Matrix<f64>, target: &Matrix<f64>) -> f64;
+}
+
+impl CrossEntropyError for NeuralNetwork {
+
+    /// Computes the mean over all examples of the cross-entropy (see
+    /// [Cost::CrossEntropy](enum.Cost.html)) independent of the cost which
+    /// is used for training.
+    fn cross_entropy(&self, input: &Matrix<f64>, targets: &Matrix<f64>) -> f64 {
+        self.example_costs(input, targets, Cost::CrossEntropy).iter().sum::<f64>() / input.rows() as f64
     }
 }
 
@@ -30,14 +206,91 @@ pub trait GradientDescent {
 
 impl GradientDescent for NeuralNetwork {
 
+    /// Optimizes via gradient descent (see
+    /// [gd_with_progress](struct.NeuralNetwork.html#method.gd_with_progress)).
     fn gd(&self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>) -> Self {
-        let a = p.alpha.unwrap();
-        let mut n = self.clone();
-        for _ in 0..p.iter.unwrap() {
-            let v = n.derivatives(input, targets).iter().map(|x| x.mul_scalar(-a)).collect::<Vec<_>>();
-            n.update_params(&v);
+        self.gd_with_progress(input, targets, p, &mut NoProgress)
+    }
+}
+
+impl Checkpoint<NeuralNetwork> {
+
+    /// Continues the gradient descent of
+    /// [gd_with_checkpoints](struct.NeuralNetwork.html#method.gd_with_checkpoints)
+    /// from this checkpoint until the number of iterations of `p` is
+    /// completed.
+    pub fn resume_gd<P: Progress>(self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>,
+            checkpoints: &mut Checkpointer, progress: &mut P) -> Result<NeuralNetwork, String> {
+
+        let iter = p.iter.unwrap();
+        let Checkpoint { model: mut n, mut state } = self;
+        let mut wrapper = Wrapper::new(&p, &n, &state);
+        while state.epoch < iter {
+            n.gd_step(input, targets, None, &p);
+            wrapper.step(&mut n);
+            let loss = if wants_loss(progress) { Some(n.loss(input, targets) + n.opts_penalty(&p)) } else { None };
+            state.epoch += 1;
+            state.losses.extend(loss);
+            wrapper.save(&mut state);
+            report(progress, &ProgressInfo { algorithm: "neural network", iteration: state.epoch, max_iter: iter, loss: loss });
+            try!(checkpoints.update(&n, &state));
+        }
+        wrapper.finish(&mut n);
+        Ok(n)
+    }
+}
+
+// Lookahead and averaging of the parameters of a network during gradient
+// descent (see the options lookahead and average of OptParams)
+struct Wrapper {
+    lookahead: Option<Lookahead>,
+    average: Option<PolyakAverage>
+}
+
+impl Wrapper {
+
+    // continues with the slow weights and the average of `state`
+    fn new(p: &OptParams<f64>, n: &NeuralNetwork, state: &TrainState) -> Wrapper {
+        Wrapper {
+            lookahead: p.lookahead.map(|(k, a)| {
+                let slow = if state.slow.is_empty() { n.flat_params() } else { state.slow.clone() };
+                Lookahead::resume(k, a, &slow, state.epoch)
+            }),
+            average: p.average.map(|start| PolyakAverage::resume(start, state.epoch, &state.average))
+        }
+    }
+
+    // must be called after each update of the parameters of `n`
+    fn step(&mut self, n: &mut NeuralNetwork) {
+
+        if self.lookahead.is_none() && self.average.is_none() {
+            return;
+        }
+        let mut v = n.flat_params();
+        if let Some(ref mut l) = self.lookahead {
+            if l.step(&mut v) {
+                n.set_flat_params(&v);
+            }
+        }
+        if let Some(ref mut a) = self.average {
+            a.update(&v);
+        }
+    }
+
+    fn save(&self, state: &mut TrainState) {
+        if let Some(ref l) = self.lookahead {
+            state.slow = l.slow().to_vec();
+        }
+        if let Some(v) = self.average.as_ref().and_then(|a| a.average()) {
+            state.average = v.to_vec();
+        }
+    }
+
+    // replaces the parameters by their average
+    fn finish(&self, n: &mut NeuralNetwork) {
+        if let Some(v) = self.average.as_ref().and_then(|a| a.average()) {
+            n.set_flat_params(v);
         }
-        n
     }
 }
 
@@ -95,7 +348,14 @@ impl GradientDescent for NeuralNetwork {
 #[derive(Debug, Clone)]
 pub struct NeuralNetwork {
     layers: Vec<usize>,
-    params: Vec<Matrix<f64>>
+    params: Vec<Matrix<f64>>,
+    // activation function of each layer except the input layer
+    activations: Vec<Arc<dyn Activation>>,
+    softmax: bool,
+    cost: Cost,
+    // options of the gradient descent of `Estimator::fit`
+    fit_opts: OptParams<f64>,
+    groups: Vec<ParamGroup<f64>>
 }
 
 impl NeuralNetwork {
@@ -119,7 +379,12 @@ impl NeuralNetwork {
     pub fn new() -> NeuralNetwork {
         NeuralNetwork {
             layers: vec![],
-            params: vec![]
+            params: vec![],
+            activations: vec![],
+            softmax: false,
+            cost: Cost::MeanSquare,
+            fit_opts: OptParams::new().alpha(1.0).iter(100),
+            groups: vec![]
         }
     }
 
@@ -149,7 +414,33 @@ impl NeuralNetwork {
     ///     .add_layer(10)  // ... 10 units in the hidden layer
     ///     .add_layer(4);  // and 4 units in the output layer
     /// ```
+    ///
+    /// The parameters are created with the generator returned by
+    /// [rng::rng](../rng/fn.rng.html), i.e. they are reproducible if a global
+    /// seed has been set.
     pub fn add_layer(&self, n: usize) -> NeuralNetwork {
+        self.add_layer_with_rng(n, &mut rng::rng())
+    }
+
+    /// Adds a new layer with `n` units like [add_layer](#method.add_layer)
+    /// but creates the random parameters with the given random number
+    /// generator.
+    ///
+    /// Panics if `n == 0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::nn::NeuralNetwork;
+    /// use rustml::rng::seeded_rng;
+    ///
+    /// let mut rng = seeded_rng([1, 2, 3, 4]);
+    /// let a = NeuralNetwork::new().add_layer_with_rng(2, &mut rng).add_layer_with_rng(3, &mut rng);
+    /// let mut rng = seeded_rng([1, 2, 3, 4]);
+    /// let b = NeuralNetwork::new().add_layer_with_rng(2, &mut rng).add_layer_with_rng(3, &mut rng);
+    /// assert_eq!(a.params(), b.params());
+    /// ```
+    pub fn add_layer_with_rng<R: Rng>(&self, n: usize, rng: &mut R) -> NeuralNetwork {
 
         assert!(n > 0, "The parameter n must not be zero.");
 
@@ -164,8 +455,22 @@ impl NeuralNetwork {
                 // If this is not the first layer we need to add random parameters
                 // from each unit of the previous layer to all units of the new
                 // layer.
-                Some(&m) => self.params.add(self.create_params(n, m, self.layers() == 1)),
-            }
+                Some(&m) => self.params.add(self.create_params(n, m, self.layers() == 1, rng)),
+            },
+
+            activations: match self.layers.last() {
+                None => vec![],
+                Some(_) => {
+                    let mut a = self.activations.clone();
+                    a.push(Arc::new(Sigmoid));
+                    a
+                }
+            },
+
+            softmax: self.softmax,
+            cost: self.cost,
+            fit_opts: self.fit_opts,
+            groups: self.groups.clone()
         }
     }
 
@@ -183,12 +488,12 @@ impl NeuralNetwork {
     /// 
     /// If the previous layer is the input layer, no bias unit to the previous
     /// layer is added.
-    fn create_params(&self, m: usize, n: usize, from_input_layer: bool) -> Matrix<f64> {
+    fn create_params<R: Rng>(&self, m: usize, n: usize, from_input_layer: bool, rng: &mut R) -> Matrix<f64> {
 
         // no bias unit in the input layer
         let k = if from_input_layer { n } else { n + 1 };
 
-        Matrix::from_vec(random::<f64>(m * k), m, k)
+        Matrix::from_vec(random_with_rng::<f64, R>(m * k, rng), m, k)
     }
 
     /// Sets the parameters (i.e. the weights) which connect the layer at
@@ -227,22 +532,206 @@ impl NeuralNetwork {
     /// # }
     /// ```
     pub fn set_params(&self, layer: usize, params: Matrix<f64>) -> NeuralNetwork {
+        unwrap_or_panic(self.try_set_params(layer, params))
+    }
+
+    /// Sets the parameters like [set_params](#method.set_params) but returns
+    /// an error if the layer does not exist or if the dimensions of the
+    /// parameter matrix do not match.
+    pub fn try_set_params(&self, layer: usize, params: Matrix<f64>) -> Result<NeuralNetwork, RustmlError> {
 
         let mut m = self.params.clone();
 
         match m.get_mut(layer) {
-            None     => { panic!("Layer does not exist."); }
+            None     => { return Err(RustmlError::NoSuchLayer(layer)); }
             Some(mx) => {
-                assert!(mx.rows() == params.rows() && 
-                    mx.cols() == params.cols(), "Parameter matrices do not match.");
+                if mx.rows() != params.rows() || mx.cols() != params.cols() {
+                    return Err(RustmlError::DimensionMismatch("Parameter matrices do not match.".to_string()));
+                }
                 *mx = params;
             }
         }
 
-        NeuralNetwork {
+        Ok(NeuralNetwork {
             layers: self.layers.clone(),
-            params: m
-        }
+            params: m,
+            activations: self.activations.clone(),
+            softmax: self.softmax,
+            cost: self.cost,
+            fit_opts: self.fit_opts,
+            groups: self.groups.clone()
+        })
+    }
+
+    /// Sets the activation function of the units of the layer at depth
+    /// `layer`. The input layer has depth 0 and has no activation function,
+    /// the first hidden layer has depth 1 and so on. All layers use the
+    /// [Sigmoid](struct.Sigmoid.html) by default.
+    ///
+    /// Panics if `layer` is zero or if the layer does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    /// use rustml::nn::{NeuralNetwork, Relu};
+    ///
+    /// # fn main() {
+    /// let n = NeuralNetwork::new()
+    ///     .add_layer(2)
+    ///     .add_layer(2)
+    ///     .add_layer(1)
+    ///     .set_params(0, mat![1.0, -1.0; -1.0, 1.0])
+    ///     .set_params(1, mat![0.0, 1.0, 1.0])
+    ///     .set_activation(1, Relu);
+    /// assert_eq!(n.activation(1).name(), "relu");
+    /// assert_eq!(n.activation(2).name(), "sigmoid");
+    /// // the hidden units compute |x1 - x2|
+    /// assert!(n.predict(&mat![0.2, 0.7]).similar(&n.predict(&mat![0.7, 0.2]), 1e-12));
+    /// # }
+    /// ```
+    pub fn set_activation<A: Activation + 'static>(&self, layer: usize, act: A) -> NeuralNetwork {
+
+        assert!(layer > 0, "The input layer has no activation function.");
+        assert!(layer < self.layers.len(), "Layer does not exist.");
+        let mut n = self.clone();
+        n.activations[layer - 1] = Arc::new(act);
+        n
+    }
+
+    /// Returns the activation function of the units of the layer at depth
+    /// `layer` (see [set_activation](#method.set_activation)).
+    ///
+    /// Panics if `layer` is zero or if the layer does not exist.
+    pub fn activation(&self, layer: usize) -> &dyn Activation {
+
+        assert!(layer > 0, "The input layer has no activation function.");
+        assert!(layer < self.layers.len(), "Layer does not exist.");
+        &*self.activations[layer - 1]
+    }
+
+    /// Returns the activation functions of all layers except the input
+    /// layer, i.e. the element at index `i` is the function of the layer
+    /// at depth `i + 1`.
+    pub fn activations(&self) -> &[Arc<dyn Activation>] {
+        &self.activations
+    }
+
+    /// Sets the activation functions of all layers except the input layer
+    /// (see [activations](#method.activations)).
+    ///
+    /// Panics if the number of functions is not the number of layers minus
+    /// one.
+    pub fn set_activations(&self, activations: &[Arc<dyn Activation>]) -> NeuralNetwork {
+
+        assert!(activations.len() + 1 == self.layers.len(), "Number of activation functions does not match.");
+        NeuralNetwork { activations: activations.to_vec(), .. self.clone() }
+    }
+
+    /// Lets the output layer compute the softmax of the net inputs of its
+    /// units instead of applying its activation function, i.e. the outputs
+    /// are positive and sum up to one. Together with the cost
+    /// [CrossEntropy](enum.Cost.html) this is the usual choice for
+    /// multiclass classification with one-hot encoded targets.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    /// use rustml::nn::*;
+    /// use rustml::opt::empty_opts;
+    ///
+    /// # fn main() {
+    /// let x = mat![0.0, 0.0; 0.0, 1.0; 1.0, 0.0; 1.0, 1.0];
+    /// let t = mat![1.0, 0.0, 0.0; 0.0, 1.0, 0.0; 0.0, 1.0, 0.0; 0.0, 0.0, 1.0];
+    /// let n = NeuralNetwork::new()
+    ///     .add_layer(2)
+    ///     .add_layer(4)
+    ///     .add_layer(3)
+    ///     .set_softmax(true)
+    ///     .set_cost(Cost::CrossEntropy);
+    /// let m = n.gd(&x, &t, empty_opts().alpha(2.0).iter(300));
+    /// assert!(m.cross_entropy(&x, &t) < n.cross_entropy(&x, &t));
+    /// let s: f64 = m.predict(&x).row(0).unwrap().iter().sum();
+    /// assert!((s - 1.0).abs() < 1e-12);
+    /// # }
+    /// ```
+    pub fn set_softmax(&self, softmax: bool) -> NeuralNetwork {
+        NeuralNetwork { softmax: softmax, .. self.clone() }
+    }
+
+    /// Returns true if the output layer computes the softmax.
+    pub fn is_softmax(&self) -> bool {
+        self.softmax
+    }
+
+    /// Sets the cost function which is minimized by
+    /// [derivatives](#method.derivatives) and gradient descent. The default
+    /// is [MeanSquare](enum.Cost.html).
+    pub fn set_cost(&self, cost: Cost) -> NeuralNetwork {
+        NeuralNetwork { cost: cost, .. self.clone() }
+    }
+
+    /// Returns the cost function which is minimized by the training.
+    pub fn cost(&self) -> Cost {
+        self.cost
+    }
+
+    /// Sets groups of parameter matrices with their own learning rate and
+    /// weight decay which are used by gradient descent. The parameters
+    /// which connect layer `i` with layer `i + 1` have the index `i`. If a
+    /// matrix is in several groups the first group is used. All other
+    /// matrices use the learning rate and the weight decay
+    /// ([lambda](../opt/struct.OptParams.html#method.lambda)) of the
+    /// options of the training.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    /// use rustml::nn::*;
+    /// use rustml::opt::{empty_opts, ParamGroup};
+    ///
+    /// # fn main() {
+    /// let x = mat![0.1, 0.9; 0.8, 0.2];
+    /// let t = mat![1.0; 0.0];
+    /// // freeze the first layer and use weight decay for the output layer
+    /// let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1)
+    ///     .set_param_groups(&[ParamGroup::new(&[0]).alpha(0.0), ParamGroup::new(&[1]).decay(1e-3)]);
+    /// let m = n.gd(&x, &t, empty_opts().alpha(0.5).iter(10));
+    /// assert_eq!(m.params()[0], n.params()[0]);
+    /// assert!(m.params()[1] != n.params()[1]);
+    /// # }
+    /// ```
+    pub fn set_param_groups(&self, groups: &[ParamGroup<f64>]) -> NeuralNetwork {
+        NeuralNetwork { groups: groups.to_vec(), .. self.clone() }
+    }
+
+    /// Returns the groups of parameter matrices with their own learning
+    /// rate and weight decay.
+    pub fn param_groups(&self) -> &[ParamGroup<f64>] {
+        &self.groups
+    }
+
+    // learning rate of the parameter matrix with index `layer`, i.e. the
+    // learning rate of its group or the one of `opts`
+    fn alpha_for(&self, opts: &OptParams<f64>, layer: usize) -> Option<f64> {
+        self.groups.iter()
+            .find(|g| g.layers.contains(&layer))
+            .and_then(|g| g.alpha)
+            .or(opts.alpha)
+    }
+
+    // weight decay of the parameter matrix with index `layer`, i.e. the
+    // weight decay of its group or `lambda` of `opts`
+    fn decay_for(&self, opts: &OptParams<f64>, layer: usize) -> Option<f64> {
+        self.groups.iter()
+            .find(|g| g.layers.contains(&layer))
+            .and_then(|g| g.decay)
+            .or(opts.lambda)
     }
 
     /// Returns the number of input units.
@@ -261,9 +750,13 @@ impl NeuralNetwork {
     /// assert_eq!(n.input_size(), 3);
     /// ```
     pub fn input_size(&self) -> usize {
+        unwrap_or_panic(self.try_input_size())
+    }
 
-        assert!(self.layers.len() != 0, "No input layer defined.");
-        *self.layers.first().unwrap()
+    /// Returns the number of input units or an error if no input layer
+    /// exists.
+    pub fn try_input_size(&self) -> Result<usize, RustmlError> {
+        self.layers.first().cloned().ok_or(RustmlError::MissingLayers("No input layer defined.".to_string()))
     }
 
     /// Returns the number of output units.
@@ -282,9 +775,13 @@ impl NeuralNetwork {
     /// assert_eq!(n.output_size(), 4);
     /// ```
     pub fn output_size(&self) -> usize {
+        unwrap_or_panic(self.try_output_size())
+    }
 
-        assert!(self.layers.len() != 0, "No output layer defined.");
-        *self.layers.last().unwrap()
+    /// Returns the number of output units or an error if no output layer
+    /// exists.
+    pub fn try_output_size(&self) -> Result<usize, RustmlError> {
+        self.layers.last().cloned().ok_or(RustmlError::MissingLayers("No output layer defined.".to_string()))
     }
 
     /// Returns the number of layers.
@@ -354,39 +851,169 @@ impl NeuralNetwork {
     /// # }
     /// ```
     pub fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+        unwrap_or_panic(self.try_predict(input))
+    }
+
+    /// Computes the output of the network like [predict](#method.predict)
+    /// but returns an error if the network has fewer than two layers or if
+    /// the number of columns of `input` is not the number of input units.
+    pub fn try_predict(&self, input: &Matrix<f64>) -> Result<Matrix<f64>, RustmlError> {
+
+        if self.layers.len() < 2 {
+            return Err(RustmlError::MissingLayers("At least two layers are required.".to_string()));
+        }
+        if input.cols() != self.layers[0] {
+            return Err(RustmlError::DimensionMismatch(format!(
+                "Expected {} columns but the input has {}.", self.layers[0], input.cols())));
+        }
 
         let mut o = input.clone();
 
-        for i in &self.params {
+        let n = self.params.len();
+        for (k, (i, act)) in self.params.iter().zip(self.activations.iter()).enumerate() {
             let mut x = o.mul(i, false, true);
-            x.isigmoid();
+            if self.softmax && k + 1 == n {
+                for r in 0..x.rows() {
+                    softmax(x.row_mut(r).unwrap());
+                }
+            } else {
+                for v in x.iter_mut() {
+                    *v = act.apply(*v);
+                }
+            }
             o = x.insert_column(0, &from_value(1.0, x.rows()));
         }
-        o.rm_column(0)
+        Ok(o.rm_column(0))
+    }
+
+    /// Computes the output of the network like [predict](#method.predict)
+    /// but splits the rows of `input` into `n_threads` batches of (almost)
+    /// equal size which are predicted in parallel.
+    ///
+    /// Panics if `n_threads` is zero.
+    pub fn predict_parallel(&self, input: &Matrix<f64>, n_threads: usize) -> Matrix<f64> {
+
+        assert!(n_threads > 0, "Number of threads must be greater than zero.");
+        let batch_size = cmp::max(1, (input.rows() + n_threads - 1) / n_threads);
+        if input.rows() <= batch_size {
+            return self.predict(input);
+        }
+
+        let batches = row_batches(input, batch_size);
+        let outputs: Vec<Matrix<f64>> = thread::scope(|s| {
+            let handles: Vec<_> = batches.iter().map(|b| s.spawn(move || self.predict(b))).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let cols = outputs[0].cols();
+        let data: Vec<f64> = outputs.iter().flat_map(|m| m.buf().iter().cloned()).collect();
+        Matrix::from_vec(data, input.rows(), cols)
+    }
+
+    /// Computes the output of the network like [predict](#method.predict)
+    /// and writes it into `output`.
+    ///
+    /// The dimension of `output` is changed to the number of rows of `input`
+    /// times the number of output units. Intermediate results are stored in
+    /// buffers which are kept per thread, i.e. if this function is called
+    /// repeatedly with inputs of the same size (e.g. in a game loop) no
+    /// memory is allocated after the first call. Use
+    /// [predict_into_with](#method.predict_into_with) to provide the buffers
+    /// explicitly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    /// use rustml::nn::NeuralNetwork;
+    ///
+    /// # fn main() {
+    /// let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+    /// let mut output = Matrix::new();
+    /// for i in 0..10 {
+    ///     let x = mat![i as f64, 1.0];
+    ///     n.predict_into(&x, &mut output);
+    ///     assert!(output.similar(&n.predict(&x), 1e-10));
+    /// }
+    /// # }
+    /// ```
+    pub fn predict_into(&self, input: &Matrix<f64>, output: &mut Matrix<f64>) {
+        BUFFERS.with(|b| self.predict_into_with(input, output, &mut b.borrow_mut()))
+    }
+
+    /// Computes the output of the network like
+    /// [predict_into](#method.predict_into) but stores the intermediate
+    /// results in `buffers`.
+    ///
+    /// Panics if the network has fewer than two layers or if the number of
+    /// columns of `input` is not the number of input units.
+    pub fn predict_into_with(&self, input: &Matrix<f64>, output: &mut Matrix<f64>, buffers: &mut PredictBuffers) {
+
+        assert!(self.layers.len() >= 2, "At least two layers are required.");
+        assert!(input.cols() == self.input_size(), "Dimension of input vector does not match.");
+
+        output.resize_mut(input.rows(), self.output_size(), 0.0);
+        let n = self.params.len();
+        let a = &mut buffers.a;
+        let b = &mut buffers.b;
+
+        for (r, x) in input.row_iter().enumerate() {
+            a.clear();
+            a.extend_from_slice(x);
+            for (i, (p, act)) in self.params.iter().zip(self.activations.iter()).enumerate() {
+                b.clear();
+                // bias unit for the next layer
+                if i + 1 < n {
+                    b.push(1.0);
+                }
+                let sm = self.softmax && i + 1 == n;
+                for w in p.row_iter() {
+                    let z = w.iter().zip(a.iter()).fold(0.0, |acc, (x, y)| acc + x * y);
+                    b.push(if sm { z } else { act.apply(z) });
+                }
+                if sm {
+                    softmax(b);
+                }
+                mem::swap(a, b);
+            }
+            output.row_mut(r).unwrap().copy_from_slice(a);
+        }
     }
 
-    fn feedforward(&self, x: &[f64]) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    // The vectors are borrowed from the pool and should be returned to it.
+    fn feedforward(&self, x: &[f64], pool: &mut BufferPool) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
 
         assert!(self.layers.len() >= 2, "At least two layers are required.");
         assert!(x.len() == self.input_size(), "Dimension of input vector does not match.");
 
-        let mut av = vec![x.to_vec()]; // inputs for the next layer (=sigmoid applied to outputs + bias unit)
-        let mut zv = vec![x.to_vec()]; // outputs of previous layer without sigmoid
+        let mut av = vec![pool.copy_of(x)]; // inputs for the next layer (=activation applied to outputs + bias unit)
+        let mut zv = vec![pool.copy_of(x)]; // outputs of previous layer without activation
         let n = self.layers() - 2;
 
         for (idx, theta) in self.params.iter().enumerate() {
-            let net = theta.mul_vec(&av.last().unwrap());
-            if idx < n {
-                av.push([1.0].append(&net.sigmoid()));
-            } else {
-                av.push(net.sigmoid());
+            let mut net = pool.take(theta.rows());
+            d_gemv(false, 1.0, theta, av.last().unwrap(), 0.0, &mut net);
+            // bias unit
+            let k = if idx < n { 1 } else { 0 };
+            let mut a = pool.take(net.len() + k);
+            a[0] = 1.0;
+            let act = &self.activations[idx];
+            for (y, z) in a[k..].iter_mut().zip(net.iter()) {
+                *y = act.apply(*z);
+            }
+            if self.softmax && idx == self.params.len() - 1 {
+                a.copy_from_slice(&net);
+                softmax(&mut a);
             }
+            av.push(a);
             zv.push(net);
         }
         (av, zv)
     }
 
-    fn backprop(&self, output: &[f64], target: &[f64], av_zv: &(Vec<Vec<f64>>, Vec<Vec<f64>>)) -> Vec<Vec<f64>> {
+    // The vectors are borrowed from the pool and should be returned to it.
+    fn backprop(&self, output: &[f64], target: &[f64], av_zv: &(Vec<Vec<f64>>, Vec<Vec<f64>>), cost: Cost, pool: &mut BufferPool) -> Vec<Vec<f64>> {
 
         assert!(self.layers.len() >= 2, "At least two layers are required.");
         assert!(output.len() == self.output_size(), "Dimension of output vector does not match.");
@@ -400,32 +1027,245 @@ impl NeuralNetwork {
 
         let mut pos = (1..self.layers()).collect::<Vec<usize>>();
         
-        // error of output layer
+        // error of output layer, i.e. the derivative of the cost with respect
+        // to the net inputs of the output units
         let p = pos.pop().unwrap();
-        deltas.push(output.sub(&target).mul(&zv[p].sigmoid_derivative()));
+        let act = &self.activations[p - 1];
+        let mut d = pool.take(output.len());
+        match (cost, self.softmax) {
+            (Cost::CrossEntropy, true) => {
+                for i in 0..d.len() {
+                    d[i] = output[i] - target[i];
+                }
+            },
+            (Cost::CrossEntropy, false) if act.is_logistic() => {
+                for i in 0..d.len() {
+                    d[i] = output[i] - target[i];
+                }
+            },
+            (Cost::CrossEntropy, false) => {
+                for i in 0..d.len() {
+                    let y = clamp(output[i]);
+                    d[i] = (y - target[i]) / (y * (1.0 - y)) * act.derivative(zv[p][i]);
+                }
+            },
+            (Cost::MeanSquare, true) => {
+                // product with the Jacobian of the softmax
+                let s = (0..d.len()).fold(0.0, |acc, i| acc + (output[i] - target[i]) * output[i]);
+                for i in 0..d.len() {
+                    d[i] = output[i] * (output[i] - target[i] - s);
+                }
+            },
+            (Cost::MeanSquare, false) => {
+                for i in 0..d.len() {
+                    d[i] = (output[i] - target[i]) * act.derivative(zv[p][i]);
+                }
+            }
+        }
+        deltas.push(d);
 
         // error of hidden layers
         while pos.len() > 0 {
             let p = pos.pop().unwrap();
-            let mut v = self.params[p].transp_mul_vec(&deltas.last().unwrap());
-            v.remove(0);
-            deltas.push(v.mul(&zv[p].sigmoid_derivative()));
+            let mut v = pool.take(self.params[p].cols());
+            d_gemv(true, 1.0, &self.params[p], deltas.last().unwrap(), 0.0, &mut v);
+            // the first element is the error of the bias unit
+            let mut d = pool.take(v.len() - 1);
+            for i in 0..d.len() {
+                d[i] = v[i + 1] * self.activations[p - 1].derivative(zv[p][i]);
+            }
+            pool.give(v);
+            deltas.push(d);
         }
 
         // the first entry is the delta vector for the output layer
         deltas
     }
 
-    fn update(&self, acc: &mut Vec<Matrix<f64>>, deltas: &Vec<Vec<f64>>, a: &Vec<Vec<f64>>) {
+    fn update(&self, acc: &mut Vec<Matrix<f64>>, deltas: &Vec<Vec<f64>>, a: &Vec<Vec<f64>>, w: f64) {
 
         let mut dp = deltas.len();
         for i in 0..acc.len() {
             dp -= 1;
-            acc[i].iadd(&deltas[dp].col_mul_row(&a[i]));
+            // adds the (weighted) outer product of the deltas and the activations
+            for (r, &d) in deltas[dp].iter().enumerate() {
+                d_axpy(w * d, &a[i], acc[i].row_mut(r).unwrap());
+            }
+        }
+    }
+
+    /// Optimizes the network via gradient descent like
+    /// [gd_with_progress](#method.gd_with_progress)
+    /// and saves checkpoints of the network and the state of the optimizer
+    /// (see the module [checkpoint](../checkpoint/index.html)).
+    pub fn gd_with_checkpoints<P: Progress>(&self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>,
+            checkpoints: &mut Checkpointer, progress: &mut P) -> Result<NeuralNetwork, String> {
+        Checkpoint::new(self.clone()).resume_gd(input, targets, p, checkpoints, progress)
+    }
+
+    // Performs one iteration of gradient descent with the learning rate and
+    // the weight decay of each layer.
+    fn gd_step(&mut self, input: &Matrix<f64>, targets: &Matrix<f64>, weights: Option<&[f64]>, opts: &OptParams<f64>) {
+
+        let d = pool::with_pool(|pool| self.accumulate(input, targets, weights, pool));
+        let rates: Vec<(f64, Option<f64>)> = (0..d.len()).map(|i| (self.alpha_for(opts, i).unwrap(), self.decay_for(opts, i))).collect();
+        for (i, (p, x)) in self.params.iter_mut().zip(d.iter()).enumerate() {
+            let (alpha, decay) = rates[i];
+            if let Some(decay) = decay {
+                // no decay of the weights of the bias unit
+                let k = if i == 0 { 0 } else { 1 };
+                for r in 0..p.rows() {
+                    for w in p.row_mut(r).unwrap()[k..].iter_mut() {
+                        *w *= 1.0 - alpha * decay;
+                    }
+                }
+            }
+            p.iscaled_add(-alpha, x);
         }
     }
 
+    /// Returns the L2 penalty `lambda / 2` times the sum of the squared
+    /// weights of the network without the weights of the bias units.
+    pub fn penalty(&self, lambda: f64) -> f64 {
+        (0..self.params.len()).fold(0.0, |acc, i| acc + lambda / 2.0 * self.square_weights(i))
+    }
+
+    /// Computes the [mean square error](trait.MeanSquareError.html) plus the
+    /// [penalty](#method.penalty) of the L2 regularization.
+    pub fn regularized_mse(&self, input: &Matrix<f64>, targets: &Matrix<f64>, lambda: f64) -> f64 {
+        self.mse(input, targets) + self.penalty(lambda)
+    }
+
+    /// Computes the [derivatives](#method.derivatives) of the cost plus the
+    /// [penalty](#method.penalty) of the L2 regularization, i.e. `lambda`
+    /// times the weight is added to the derivative of each weight except
+    /// the weights of the bias units.
+    pub fn regularized_derivatives(&self, examples: &Matrix<f64>, targets: &Matrix<f64>, lambda: f64) -> Vec<Matrix<f64>> {
+
+        let mut d = self.derivatives(examples, targets);
+        for (i, (x, p)) in d.iter_mut().zip(self.params.iter()).enumerate() {
+            // the weights of the bias unit are in the first column except
+            // for the input layer
+            let k = if i == 0 { 0 } else { 1 };
+            for r in 0..p.rows() {
+                d_axpy(lambda, &p.row(r).unwrap()[k..], &mut x.row_mut(r).unwrap()[k..]);
+            }
+        }
+        d
+    }
+
+    // sum of the squared weights of a layer without the weights of the bias unit
+    fn square_weights(&self, layer: usize) -> f64 {
+        let k = if layer == 0 { 0 } else { 1 };
+        self.params[layer].row_iter().fold(0.0, |acc, r| acc + r[k..].iter().fold(0.0, |a, w| a + w * w))
+    }
+
+    // penalty of the weight decay of the options
+    fn opts_penalty(&self, opts: &OptParams<f64>) -> f64 {
+        (0..self.params.len()).fold(0.0, |acc, i| acc + self.decay_for(opts, i).unwrap_or(0.0) / 2.0 * self.square_weights(i))
+    }
+
+    /// Optimizes the network via gradient descent like
+    /// [gd_with_progress](#method.gd_with_progress) but weights the cost of
+    /// example `i` with `weights[i]` (see
+    /// [weighted_derivatives](#method.weighted_derivatives)). Before each
+    /// iteration `schedule` can change the weights based on the error of each
+    /// example, e.g. for curriculum learning (see the module
+    /// [curriculum](../curriculum/index.html)). After the training `weights`
+    /// contains the weights of the last iteration.
+    ///
+    /// Panics if the number of weights does not match the number of
+    /// examples.
+    pub fn weighted_gd<S: WeightSchedule>(&self, input: &Matrix<f64>, targets: &Matrix<f64>, weights: &mut [f64],
+            p: OptParams<f64>, schedule: &mut S) -> NeuralNetwork {
+        self.weighted_gd_with_progress(input, targets, weights, p, schedule, &mut NoProgress)
+    }
+
+    /// Like [weighted_gd](#method.weighted_gd) but reports the weighted mean
+    /// cost after each iteration (see the module
+    /// [progress](../progress/index.html)).
+    pub fn weighted_gd_with_progress<S: WeightSchedule, P: Progress>(&self, input: &Matrix<f64>, targets: &Matrix<f64>,
+            weights: &mut [f64], p: OptParams<f64>, schedule: &mut S, progress: &mut P) -> NeuralNetwork {
+
+        assert!(weights.len() == input.rows(), "Number of examples and weights mismatch.");
+        let iter = p.iter.unwrap();
+        let mut n = self.clone();
+        let mut wrapper = Wrapper::new(&p, &n, &TrainState::new());
+        let mut errors = n.example_errors(input, targets);
+        for k in 0..iter {
+            schedule.update(k, &errors, weights);
+            n.gd_step(input, targets, Some(weights), &p);
+            wrapper.step(&mut n);
+            let want = wants_loss(progress);
+            // the errors after the last iteration are only required for the loss
+            if k + 1 < iter || want {
+                errors = n.example_errors(input, targets);
+            }
+            let loss = if want {
+                Some(errors.iter().zip(weights.iter()).fold(0.0, |acc, (e, w)| acc + e * w) / input.rows() as f64 + n.opts_penalty(&p))
+            } else {
+                None
+            };
+            report(progress, &ProgressInfo { algorithm: "neural network", iteration: k + 1, max_iter: iter, loss: loss });
+        }
+        wrapper.finish(&mut n);
+        n
+    }
+
+    // cost of each example
+    fn example_errors(&self, input: &Matrix<f64>, targets: &Matrix<f64>) -> Vec<f64> {
+        self.example_costs(input, targets, self.cost)
+    }
+
+    fn example_costs(&self, input: &Matrix<f64>, targets: &Matrix<f64>, cost: Cost) -> Vec<f64> {
+        self.predict(input).row_iter().zip(targets.row_iter())
+            .map(|(y, t)| match (cost, self.softmax) {
+                (Cost::MeanSquare, _) => y.iter().zip(t.iter()).fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b)) / 2.0,
+                (Cost::CrossEntropy, true) => -y.iter().zip(t.iter()).fold(0.0, |acc, (&a, b)| acc + b * clamp(a).ln()),
+                (Cost::CrossEntropy, false) => -y.iter().zip(t.iter()).fold(0.0, |acc, (&a, b)| {
+                    acc + b * clamp(a).ln() + (1.0 - b) * (1.0 - clamp(a)).ln()
+                })
+            })
+            .collect()
+    }
+
+    // value of the cost which is minimized by the training
+    fn loss(&self, input: &Matrix<f64>, targets: &Matrix<f64>) -> f64 {
+        match self.cost {
+            Cost::MeanSquare => self.mse(input, targets),
+            Cost::CrossEntropy => self.cross_entropy(input, targets)
+        }
+    }
+
+    /// Computes the derivatives of the [cost](#method.set_cost) with
+    /// respect to the parameters of the network, averaged over the rows of
+    /// `examples` and `targets`. The matrices have the same dimensions as
+    /// the [parameters](#method.params).
     pub fn derivatives(&self, examples: &Matrix<f64>, targets: &Matrix<f64>) -> Vec<Matrix<f64>> {
+        pool::with_pool(|pool| self.derivatives_with_pool(examples, targets, pool))
+    }
+
+    /// Computes the derivatives like [derivatives](#method.derivatives) but
+    /// borrows the temporary vectors of the backpropagation from `pool`
+    /// instead of the pool of the current thread.
+    pub fn derivatives_with_pool(&self, examples: &Matrix<f64>, targets: &Matrix<f64>, pool: &mut BufferPool) -> Vec<Matrix<f64>> {
+        self.accumulate(examples, targets, None, pool)
+    }
+
+    /// Computes the derivatives like [derivatives](#method.derivatives) but
+    /// weights the cost of example `i` with `weights[i]`, e.g.
+    /// with the responsibility of the network for the example in a mixture
+    /// (see the module [moe](../moe/index.html)).
+    ///
+    /// Panics if the number of weights does not match the number of
+    /// examples.
+    pub fn weighted_derivatives(&self, examples: &Matrix<f64>, targets: &Matrix<f64>, weights: &[f64]) -> Vec<Matrix<f64>> {
+
+        assert!(weights.len() == examples.rows(), "Number of examples and weights mismatch.");
+        pool::with_pool(|pool| self.accumulate(examples, targets, Some(weights), pool))
+    }
+
+    fn accumulate(&self, examples: &Matrix<f64>, targets: &Matrix<f64>, weights: Option<&[f64]>, pool: &mut BufferPool) -> Vec<Matrix<f64>> {
 
         assert!(self.layers.len() >= 2, "At least two layers are required.");
         assert!(examples.rows() == targets.rows(), "Number of examples and labels mismatch.");
@@ -437,18 +1277,152 @@ impl NeuralNetwork {
 
         // x = example
         // t = target vector
-        for (x, t) in examples.row_iter().zip(targets.row_iter()) {
-
-            let (av, zv) = self.feedforward(x);
-            let deltas = self.backprop(&av.last().unwrap().clone(), t, &(av.clone(), zv));
-            self.update(&mut acc_d, &deltas, &av);
+        for (i, (x, t)) in examples.row_iter().zip(targets.row_iter()).enumerate() {
+            let av_zv = self.feedforward(x, pool);
+            let deltas = self.backprop(av_zv.0.last().unwrap(), t, &av_zv, self.cost, pool);
+            self.update(&mut acc_d, &deltas, &av_zv.0, weights.map(|w| w[i]).unwrap_or(1.0));
+
+            // return the temporaries for the next example
+            pool.give_all(deltas);
+            pool.give_all(av_zv.0);
+            pool.give_all(av_zv.1);
         }
 
         for i in &mut acc_d {
             i.idiv_scalar(examples.rows() as f64);
         }
         acc_d
-        // TODO tests
+    }
+
+    /// Computes the contrastive loss of the network used as the shared
+    /// branch of a siamese network, i.e. the mean over all pairs of rows of
+    /// `a` and `b` of `d^2 / 2` for similar pairs and of
+    /// `max(0, margin - d)^2 / 2` for dissimilar pairs where `d` is the
+    /// euclidean distance between the outputs of the network for both rows.
+    ///
+    /// Panics if the number of rows of `a`, `b` and the number of labels in
+    /// `similar` do not match.
+    pub fn contrastive_loss(&self, a: &Matrix<f64>, b: &Matrix<f64>, similar: &[bool], margin: f64) -> f64 {
+
+        assert!(a.rows() == b.rows() && a.rows() == similar.len(), "Number of pairs and labels mismatch.");
+        let d = self.pair_distances(a, b);
+        d.iter().zip(similar.iter()).fold(0.0, |acc, (&d, &s)| {
+            let e = if s { d } else { (margin - d).max(0.0) };
+            acc + e * e / 2.0
+        }) / a.rows() as f64
+    }
+
+    /// Returns the euclidean distance between the outputs of the network
+    /// (i.e. the embeddings) for each pair of rows of `a` and `b`, e.g. to
+    /// decide whether two inputs are from the same class.
+    ///
+    /// Panics if the number of rows of `a` and `b` do not match.
+    pub fn pair_distances(&self, a: &Matrix<f64>, b: &Matrix<f64>) -> Vec<f64> {
+
+        assert!(a.rows() == b.rows(), "Number of rows mismatch.");
+        let (ea, eb) = (self.predict(a), self.predict(b));
+        ea.row_iter().zip(eb.row_iter())
+            .map(|(x, y)| x.iter().zip(y.iter()).fold(0.0, |acc, (p, q)| acc + (p - q) * (p - q)).sqrt())
+            .collect()
+    }
+
+    /// Computes the derivatives of the
+    /// [contrastive loss](#method.contrastive_loss) with respect to the
+    /// parameters. The gradients of both branches of a pair are accumulated
+    /// as the parameters are shared. A dissimilar pair with identical
+    /// outputs does not contribute.
+    ///
+    /// Panics if the number of rows of `a`, `b` and the number of labels in
+    /// `similar` do not match.
+    pub fn contrastive_derivatives(&self, a: &Matrix<f64>, b: &Matrix<f64>, similar: &[bool], margin: f64) -> Vec<Matrix<f64>> {
+
+        assert!(self.layers.len() >= 2, "At least two layers are required.");
+        assert!(a.rows() == b.rows() && a.rows() == similar.len(), "Number of pairs and labels mismatch.");
+        assert!(a.cols() == self.input_size() && b.cols() == self.input_size(), "Dimension of input vector does not match.");
+
+        let mut acc_d = self.params.iter().map(|ref m| Matrix::fill(0.0, m.rows(), m.cols())).collect();
+        pool::with_pool(|pool| {
+            for ((x, y), &s) in a.row_iter().zip(b.row_iter()).zip(similar.iter()) {
+                let fa = self.feedforward(x, pool);
+                let fb = self.feedforward(y, pool);
+                {
+                    let (oa, ob) = (fa.0.last().unwrap(), fb.0.last().unwrap());
+                    let d = oa.iter().zip(ob.iter()).fold(0.0, |acc, (p, q)| acc + (p - q) * (p - q)).sqrt();
+                    // derivative of the loss with respect to the distance divided by the distance
+                    let c = if s { 1.0 } else if d > 0.0 && d < margin { -(margin - d) / d } else { 0.0 };
+                    if c != 0.0 {
+                        // backprop computes the error of the output layer as output - target
+                        let ta: Vec<f64> = oa.iter().zip(ob.iter()).map(|(p, q)| p - c * (p - q)).collect();
+                        let tb: Vec<f64> = ob.iter().zip(oa.iter()).map(|(p, q)| p - c * (p - q)).collect();
+                        for &(f, o, t) in &[(&fa, oa, &ta), (&fb, ob, &tb)] {
+                            let deltas = self.backprop(o, t, f, Cost::MeanSquare, pool);
+                            self.update(&mut acc_d, &deltas, &f.0, 1.0);
+                            pool.give_all(deltas);
+                        }
+                    }
+                }
+                pool.give_all(fa.0);
+                pool.give_all(fa.1);
+                pool.give_all(fb.0);
+                pool.give_all(fb.1);
+            }
+        });
+
+        for i in &mut acc_d {
+            i.idiv_scalar(a.rows() as f64);
+        }
+        acc_d
+    }
+
+    /// Trains the network as the shared branch of a siamese network via
+    /// gradient descent on the [contrastive loss](#method.contrastive_loss)
+    /// of the pairs of rows of `a` and `b` with the labels `similar`. The
+    /// learning rate and the number of iterations are taken from `p`.
+    ///
+    /// The outputs of the trained network are embeddings in which similar
+    /// inputs are close to each other and dissimilar inputs are at least
+    /// `margin` apart (see [pair_distances](#method.pair_distances)).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate rustml;
+    /// use rustml::*;
+    /// use rustml::nn::NeuralNetwork;
+    /// use rustml::opt::empty_opts;
+    ///
+    /// # fn main() {
+    /// let a = mat![0.1, 0.2; 0.9, 0.8; 0.1, 0.2; 0.9, 0.8];
+    /// let b = mat![0.2, 0.1; 0.8, 0.9; 0.8, 0.9; 0.2, 0.1];
+    /// let similar = [true, true, false, false];
+    ///
+    /// let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(2)
+    ///     .contrastive_gd(&a, &b, &similar, 1.0, empty_opts().alpha(5.0).iter(500));
+    /// let d = n.pair_distances(&a, &b);
+    /// assert!(d[0] < d[2] && d[1] < d[3]);
+    /// # }
+    /// ```
+    pub fn contrastive_gd(&self, a: &Matrix<f64>, b: &Matrix<f64>, similar: &[bool], margin: f64, p: OptParams<f64>) -> NeuralNetwork {
+        self.contrastive_gd_with_progress(a, b, similar, margin, p, &mut NoProgress)
+    }
+
+    /// Like [contrastive_gd](#method.contrastive_gd) but reports the loss
+    /// after each iteration (see the module [progress](../progress/index.html)).
+    pub fn contrastive_gd_with_progress<P: Progress>(&self, a: &Matrix<f64>, b: &Matrix<f64>, similar: &[bool], margin: f64,
+            p: OptParams<f64>, progress: &mut P) -> NeuralNetwork {
+
+        let alpha = p.alpha.unwrap();
+        let iter = p.iter.unwrap();
+        let mut n = self.clone();
+        for k in 0..iter {
+            let d = n.contrastive_derivatives(a, b, similar, margin);
+            for (p, x) in n.params.iter_mut().zip(d.iter()) {
+                p.iscaled_add(-alpha, x);
+            }
+            let loss = n.contrastive_loss(a, b, similar, margin);
+            report(progress, &ProgressInfo { algorithm: "siamese network", iteration: k + 1, max_iter: iter, loss: Some(loss) });
+        }
+        n
     }
 
     /// Updates the parameters of the network.
@@ -470,6 +1444,199 @@ impl NeuralNetwork {
     pub fn params(&self) -> Vec<Matrix<f64>> {
         self.params.clone()
     }
+
+    // parameters of all layers concatenated, each matrix row by row
+    fn flat_params(&self) -> Vec<f64> {
+        self.params.iter().flat_map(|m| m.buf().clone()).collect()
+    }
+
+    fn set_flat_params(&mut self, v: &[f64]) {
+        let mut pos = 0;
+        for p in &mut self.params {
+            let n = p.rows() * p.cols();
+            *p = Matrix::from_vec(v[pos..pos + n].to_vec(), p.rows(), p.cols());
+            pos += n;
+        }
+    }
+
+    /// Quantizes the parameters of the network to 8-bit integers (see the
+    /// module [quantize](../quantize/index.html)).
+    pub fn quantize(&self) -> QuantizedNetwork {
+        QuantizedNetwork::new(self)
+    }
+
+    /// Optimizes via gradient descent with the learning rate and the number
+    /// of iterations of `p` and reports the progress after each iteration
+    /// (see the module [progress](../progress/index.html)). The parameters
+    /// which connect layer `i` with layer `i + 1` can get their own learning
+    /// rate and weight decay with a [group](#method.set_param_groups) of
+    /// index `i`, e.g. to fine-tune a pretrained network. The weight decay
+    /// of all other parameters is set with
+    /// [lambda](../opt/struct.OptParams.html#method.lambda), which is
+    /// equivalent to the gradient descent on the
+    /// [regularized derivatives](#method.regularized_derivatives). The
+    /// weights of the bias units are not decayed.
+    ///
+    /// The [cost](#method.set_cost) of the network plus the penalty of the
+    /// weight decay is minimized and reported as loss.
+    ///
+    /// The options [lookahead](../opt/struct.OptParams.html#method.lookahead)
+    /// and [average](../opt/struct.OptParams.html#method.average) are applied
+    /// to all parameters of the network. This also holds for
+    /// [weighted_gd](#method.weighted_gd) and
+    /// [gd_with_checkpoints](#method.gd_with_checkpoints)
+    /// whose checkpoints contain the slow weights and the average.
+    pub fn gd_with_progress<P: Progress>(&self, input: &Matrix<f64>, targets: &Matrix<f64>, p: OptParams<f64>, progress: &mut P) -> NeuralNetwork {
+        let iter = p.iter.unwrap();
+        let mut n = self.clone();
+        let mut wrapper = Wrapper::new(&p, &n, &TrainState::new());
+        for k in 0..iter {
+            n.gd_step(input, targets, None, &p);
+            wrapper.step(&mut n);
+            let loss = if wants_loss(progress) { Some(n.loss(input, targets) + n.opts_penalty(&p)) } else { None };
+            report(progress, &ProgressInfo { algorithm: "neural network", iteration: k + 1, max_iter: iter, loss: loss });
+        }
+        wrapper.finish(&mut n);
+        n
+    }
+
+    /// Sets the options of the gradient descent which is used by
+    /// [Estimator::fit](../model_selection/trait.Estimator.html). The
+    /// default is a learning rate of 1 and 100 iterations. The options are
+    /// not saved with the module [model](../io/model/index.html).
+    ///
+    /// Panics if the number of iterations or the learning rate is missing.
+    pub fn set_fit_opts(&self, opts: OptParams<f64>) -> NeuralNetwork {
+        assert!(opts.iter.is_some() && opts.alpha.is_some(), "Number of iterations and learning rate are required.");
+        NeuralNetwork { fit_opts: opts, .. self.clone() }
+    }
+
+    /// Returns the options of the gradient descent which is used by
+    /// [Estimator::fit](../model_selection/trait.Estimator.html).
+    pub fn fit_opts(&self) -> OptParams<f64> {
+        self.fit_opts
+    }
+}
+
+/// The label of an example is the row of the targets, i.e. one value per
+/// unit of the output layer.
+impl Estimator<f64, Vec<f64>> for NeuralNetwork {
+
+    /// Trains the network with gradient descent with the options of
+    /// [set_fit_opts](struct.NeuralNetwork.html#method.set_fit_opts)
+    /// starting from the current parameters.
+    ///
+    /// Panics if the number of examples and targets differ or if a target
+    /// does not have one value per output unit.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[Vec<f64>]) {
+
+        assert!(x.rows() == y.len(), "Number of examples and targets must be equal.");
+        let k = self.output_size();
+        assert!(y.iter().all(|t| t.len() == k), "Number of targets does not match the output layer.");
+        let t = Matrix::from_vec(y.iter().flat_map(|t| t.iter().cloned()).collect(), y.len(), k);
+        *self = self.gd(x, &t, self.fit_opts);
+    }
+
+    fn predict(&self, x: &Matrix<f64>) -> Vec<Vec<f64>> {
+        NeuralNetwork::predict(self, x).row_iter().map(|r| r.to_vec()).collect()
+    }
+}
+
+impl PartialFit for NeuralNetwork {
+
+    fn partial_fit(&mut self, x: &Matrix<f64>, y: &Matrix<f64>, alpha: f64) {
+
+        let mut d = self.derivatives(x, y);
+        for i in &mut d {
+            i.imul_scalar(-alpha);
+        }
+        self.update_params(&d);
+    }
+
+    /// The derivatives of all layers are concatenated in the order of the
+    /// layers, each matrix row by row.
+    fn gradient(&self, x: &Matrix<f64>, y: &Matrix<f64>) -> Option<Vec<f64>> {
+        Some(self.derivatives(x, y).iter().flat_map(|m| m.buf().clone()).collect())
+    }
+
+    fn apply_gradient(&mut self, g: &[f64], alpha: f64) {
+
+        assert!(g.len() == self.params.iter().map(|p| p.rows() * p.cols()).sum::<usize>(), "Dimension of gradient does not match.");
+        let mut pos = 0;
+        for p in &mut self.params {
+            let n = p.rows() * p.cols();
+            p.iscaled_add(-alpha, &Matrix::from_vec(g[pos..pos + n].to_vec(), p.rows(), p.cols()));
+            pos += n;
+        }
+    }
+}
+
+impl Model for NeuralNetwork {
+
+    fn tag() -> &'static str { "rustml.nn.NeuralNetwork" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usizes(&self.layers);
+        for p in &self.params {
+            e.matrix(p);
+        }
+        for a in &self.activations {
+            e.str(a.name());
+            e.f64s(&a.params());
+        }
+        e.bool(self.softmax);
+        e.usize(match self.cost { Cost::MeanSquare => 0, Cost::CrossEntropy => 1 });
+        e.usize(self.groups.len());
+        for g in &self.groups {
+            e.usizes(&g.layers);
+            e.f64s(&g.alpha.into_iter().collect::<Vec<f64>>());
+            e.f64s(&g.decay.into_iter().collect::<Vec<f64>>());
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<NeuralNetwork, String> {
+
+        let layers = try!(d.usizes());
+        let mut params = vec![];
+        for i in 1..layers.len() {
+            let p = try!(d.matrix());
+            // no bias unit in the input layer
+            let k = if i == 1 { layers[0] } else { layers[i - 1] + 1 };
+            if p.rows() != layers[i] || p.cols() != k {
+                return Err("Dimensions of parameters do not match the layers.".to_string());
+            }
+            params.push(p);
+        }
+        let mut activations: Vec<Arc<dyn Activation>> = vec![];
+        while activations.len() < params.len() {
+            let name = try!(d.str());
+            let p = try!(d.f64s());
+            activations.push(match (name.as_str(), p.len()) {
+                ("sigmoid", 0) => Arc::new(Sigmoid),
+                ("tanh", 0) => Arc::new(Tanh),
+                ("relu", 0) => Arc::new(Relu),
+                ("leaky_relu", 1) => Arc::new(LeakyRelu(p[0])),
+                _ => return Err(format!("Unknown activation function {}.", name))
+            });
+        }
+        let softmax = try!(d.bool());
+        let cost = match try!(d.usize()) {
+            0 => Cost::MeanSquare,
+            1 => Cost::CrossEntropy,
+            c => return Err(format!("Unknown cost function {}.", c))
+        };
+        let mut groups = vec![];
+        for _ in 0..try!(d.usize()) {
+            let layers = try!(d.usizes());
+            let alpha = try!(d.f64s());
+            let decay = try!(d.f64s());
+            groups.push(ParamGroup { layers: layers, alpha: alpha.first().cloned(), decay: decay.first().cloned() });
+        }
+        Ok(NeuralNetwork {
+            layers: layers, params: params, activations: activations, softmax: softmax, cost: cost, groups: groups,
+            .. NeuralNetwork::new()
+        })
+    }
 }
 
 
@@ -481,16 +1648,50 @@ mod tests {
     use super::*;
     use matrix::*;
     use ops::Functions;
+    use error::RustmlError;
+    use pool::BufferPool;
+    use opt::{empty_opts, ParamGroup};
+    use curriculum::FixedWeights;
+
+    // network with the given layer sizes and reproducible parameters
+    fn seeded_network(sizes: &[usize]) -> NeuralNetwork {
+
+        sizes.iter().enumerate().fold(NeuralNetwork::new(), |n, (i, &s)| {
+            let seed = if i + 1 < sizes.len() { [1, 2, 3, 4] } else { [5, 6, 7, 8] };
+            n.add_layer_with_rng(s, &mut rng::seeded_rng(seed))
+        })
+    }
+
+    // derivatives of `loss` with respect to all parameters of `n` computed
+    // with central differences
+    fn numeric_gradient<F: Fn(&NeuralNetwork) -> f64>(n: &NeuralNetwork, loss: F) -> Vec<Matrix<f64>> {
+
+        let h = 1e-6;
+        n.params().iter().enumerate().map(|(l, p)| {
+            let mut d = p.clone();
+            for r in 0..p.rows() {
+                for c in 0..p.cols() {
+                    let error = |delta: f64| {
+                        let mut w = p.clone();
+                        *w.get_mut(r, c).unwrap() += delta;
+                        loss(&n.set_params(l, w))
+                    };
+                    *d.get_mut(r, c).unwrap() = (error(h) - error(-h)) / (2.0 * h);
+                }
+            }
+            d
+        }).collect()
+    }
 
     #[test]
     fn test_nn_create_params() {
 
         // 5 = number of units in new layer (rows in matrix)
         // 3 = number of units in last layer (columns in matrix)
-        let a = NeuralNetwork::new().create_params(5, 3, true);
+        let a = NeuralNetwork::new().create_params(5, 3, true, &mut ::rng::rng());
         assert_eq!(a.rows(), 5);
         assert_eq!(a.cols(), 3);
-        let b = NeuralNetwork::new().create_params(5, 3, false);
+        let b = NeuralNetwork::new().create_params(5, 3, false, &mut ::rng::rng());
         assert_eq!(b.rows(), 5);
         assert_eq!(b.cols(), 4);
     }
@@ -627,7 +1828,8 @@ mod tests {
             .set_params(0, params1)
             .set_params(1, params2);
 
-        let (a, z) = n.feedforward(&[0.5, 1.2, 1.5]);
+        let mut pool = BufferPool::new();
+        let (a, z) = n.feedforward(&[0.5, 1.2, 1.5], &mut pool);
 
         assert_eq!(a.len(), 3);
         assert_eq!(z.len(), 3);
@@ -638,7 +1840,7 @@ mod tests {
         assert!(z[2].similar(&vec![2.2276, 1.5237, 4.3865], 0.0001));
         assert!(a[2].similar(&vec![0.90270, 0.82108, 0.98771], 0.00001));
 
-        let d = n.backprop(&a[2].clone(), &[2.7, 3.1, 1.5], &(a, z));
+        let d = n.backprop(&a[2].clone(), &[2.7, 3.1, 1.5], &(a, z), Cost::MeanSquare, &mut pool);
         assert!(d[0].similar(&vec![-0.1578584, -0.3347843, -0.0062193], 0.0000002));
         assert!(d[1].similar(&vec![-0.075561, -0.013853], 0.000002));
 
@@ -672,7 +1874,7 @@ mod tests {
         let a = vec![a1, a2];
 
         let n = NeuralNetwork::new();
-        n.update(&mut m, &d, &a);
+        n.update(&mut m, &d, &a, 1.0);
 
         assert!(m[0].similar(&mat![
             0.16, 0.08, 0.12;
@@ -687,6 +1889,322 @@ mod tests {
         ], 0.01));
     }
 
+    #[test]
+    fn test_derivatives_with_pool() {
+
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(2);
+        let x = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0];
+        let t = mat![0.1, 0.9; 0.8, 0.2];
+
+        let mut pool = BufferPool::new();
+        let d = n.derivatives_with_pool(&x, &t, &mut pool);
+        let k = pool.allocations();
+        assert!(k > 0);
+        for (a, b) in d.iter().zip(n.derivatives(&x, &t).iter()) {
+            assert!(a.similar(b, 1e-12));
+        }
+
+        // all buffers are reused
+        n.derivatives_with_pool(&x, &t, &mut pool);
+        assert_eq!(pool.allocations(), k);
+    }
+
+    #[test]
+    fn test_activations() {
+
+        for a in &[&Sigmoid as &dyn Activation, &Tanh, &Relu, &LeakyRelu(0.1)] {
+            for &z in &[-1.5, -0.3, 0.4, 2.0] {
+                let h = 1e-6;
+                let n = (a.apply(z + h) - a.apply(z - h)) / (2.0 * h);
+                assert!(num::abs(n - a.derivative(z)) < 1e-8);
+            }
+        }
+        assert_eq!(Relu.apply(-2.0), 0.0);
+        assert_eq!(LeakyRelu(0.1).apply(-2.0), -0.2);
+        assert_eq!(Tanh.apply(0.5), 0.5f64.tanh());
+    }
+
+    #[test]
+    fn test_derivatives_with_activations() {
+
+        let n = seeded_network(&[3, 4, 2])
+            .set_params(0, mat![0.5, -0.4, 0.3; -0.2, 0.6, -0.5; 0.1, 0.2, -0.3; -0.6, 0.4, 0.2])
+            .set_activation(1, LeakyRelu(0.1))
+            .set_activation(2, Tanh);
+        assert_eq!(n.activation(1).name(), "leaky_relu");
+        let x = mat![0.5, 1.2, 1.5; 0.3, -1.1, 1.0];
+        let t = mat![0.1, 0.9; -0.8, 0.2];
+
+        let mut buffers = PredictBuffers::new();
+        let mut output = Matrix::new();
+        n.predict_into_with(&x, &mut output, &mut buffers);
+        assert!(output.similar(&n.predict(&x), 1e-12));
+
+        // numerical derivatives
+        let d = n.derivatives(&x, &t);
+        for (a, e) in d.iter().zip(numeric_gradient(&n, |m| m.mse(&x, &t)).iter()) {
+            assert!(a.similar(e, 1e-8));
+        }
+    }
+
+    // sigmoid which is not recognized as such by the network
+    #[derive(Debug)]
+    struct Logistic;
+
+    impl Activation for Logistic {
+        fn name(&self) -> &'static str { "logistic" }
+        fn apply(&self, z: f64) -> f64 { z.sigmoid() }
+        fn derivative(&self, z: f64) -> f64 { z.sigmoid_derivative() }
+    }
+
+    // steeper function with the name of the logistic function
+    #[derive(Debug)]
+    struct Steep;
+
+    impl Activation for Steep {
+        fn name(&self) -> &'static str { "sigmoid" }
+        fn apply(&self, z: f64) -> f64 { (2.0 * z).sigmoid() }
+        fn derivative(&self, z: f64) -> f64 { 2.0 * (2.0 * z).sigmoid_derivative() }
+    }
+
+    #[test]
+    fn test_softmax_cross_entropy() {
+
+        let n = seeded_network(&[3, 4, 3])
+            .set_params(0, mat![0.5, -0.4, 0.3; -0.2, 0.6, -0.5; 0.1, 0.2, -0.3; -0.6, 0.4, 0.2]);
+        let x = mat![0.5, 1.2, 1.5; 0.3, -1.1, 1.0];
+        let t = mat![0.0, 1.0, 0.0; 1.0, 0.0, 0.0];
+
+        let s = n.set_softmax(true);
+        let y = s.predict(&x);
+        let mut buffers = PredictBuffers::new();
+        let mut output = Matrix::new();
+        s.predict_into_with(&x, &mut output, &mut buffers);
+        assert!(output.similar(&y, 1e-12));
+        assert!(num::abs(y.row(0).unwrap().iter().sum::<f64>() - 1.0) < 1e-12);
+        let ce = -(y.get(0, 1).unwrap().ln() + y.get(1, 0).unwrap().ln()) / 2.0;
+        assert!(num::abs(s.cross_entropy(&x, &t) - ce) < 1e-12);
+
+        // numerical derivatives of all combinations of output layer and cost
+        let nets = [
+            s.clone(),
+            s.set_cost(Cost::CrossEntropy),
+            n.set_cost(Cost::CrossEntropy),
+            n.set_activation(2, Logistic).set_cost(Cost::CrossEntropy),
+            n.set_activation(2, Steep).set_cost(Cost::CrossEntropy)
+        ];
+        for m in nets.iter() {
+            let d = m.derivatives(&x, &t);
+            for (a, e) in d.iter().zip(numeric_gradient(m, |k| k.loss(&x, &t)).iter()) {
+                assert!(a.similar(e, 1e-7));
+            }
+        }
+
+        // the loss of the training is the cross entropy
+        let m = nets[1].gd_with_progress(&x, &t, empty_opts().alpha(0.5).iter(20), &mut |i: &ProgressInfo| {
+            assert!(i.loss.unwrap() > 0.0);
+        });
+        assert!(m.cross_entropy(&x, &t) < s.cross_entropy(&x, &t));
+        assert_eq!(m.cost(), Cost::CrossEntropy);
+    }
+
+    #[test]
+    fn test_weighted_derivatives() {
+
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(2);
+        let x = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0];
+        let t = mat![0.1, 0.9; 0.8, 0.2];
+
+        for (a, b) in n.weighted_derivatives(&x, &t, &[1.0, 1.0]).iter().zip(n.derivatives(&x, &t).iter()) {
+            assert!(a.similar(b, 1e-12));
+        }
+
+        // a weight of zero removes the example, the mean is still over both
+        let first = n.derivatives(&mat![0.5, 1.2, 1.5], &mat![0.1, 0.9]);
+        for (a, b) in n.weighted_derivatives(&x, &t, &[2.0, 0.0]).iter().zip(first.iter()) {
+            assert!(a.similar(b, 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_contrastive_derivatives() {
+
+        let n = seeded_network(&[3, 4, 2]);
+        let a = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0; 0.1, 0.2, 0.3];
+        let b = mat![0.4, 1.0, 1.6; 1.3, 0.1, 0.2; 0.9, 0.2, 0.1];
+        let s = [true, false, false];
+        let margin = 2.0;
+
+        // compare with the numerical derivatives
+        let d = n.contrastive_derivatives(&a, &b, &s, margin);
+        for (x, e) in d.iter().zip(numeric_gradient(&n, |m| m.contrastive_loss(&a, &b, &s, margin)).iter()) {
+            assert!(x.similar(e, 1e-6));
+        }
+
+        // dissimilar pairs further apart than the margin do not contribute
+        let far = n.contrastive_derivatives(&a, &b, &s, 0.0);
+        let similar = n.contrastive_derivatives(&mat![0.5, 1.2, 1.5], &mat![0.4, 1.0, 1.6], &[true], 0.0);
+        for (x, y) in far.iter().zip(similar.iter()) {
+            assert!(x.iter().zip(y.iter()).all(|(p, q)| num::abs(3.0 * p - q) < 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_contrastive_gd() {
+
+        // two classes of points and pairs of all combinations of classes
+        let mut r = rng::seeded_rng([1, 2, 3, 4]);
+        let mut point = |c: usize| vec![c as f64 + 0.3 * r.gen::<f64>(), 1.0 - c as f64 + 0.3 * r.gen::<f64>()];
+        let labels: Vec<(usize, usize)> = (0..40).map(|i| (i % 2, (i / 2) % 2)).collect();
+        let a = Matrix::from_it(labels.iter().flat_map(|&(c, _)| point(c)).collect::<Vec<f64>>().into_iter(), 2);
+        let b = Matrix::from_it(labels.iter().flat_map(|&(_, c)| point(c)).collect::<Vec<f64>>().into_iter(), 2);
+        let s: Vec<bool> = labels.iter().map(|&(x, y)| x == y).collect();
+
+        let n = seeded_network(&[2, 4, 2]);
+        let mut losses = vec![];
+        let m = n.contrastive_gd_with_progress(&a, &b, &s, 1.0, empty_opts().alpha(5.0).iter(300),
+            &mut |i: &ProgressInfo| losses.push(i.loss.unwrap()));
+        assert_eq!(losses.len(), 300);
+        assert!(losses[299] < 0.5 * n.contrastive_loss(&a, &b, &s, 1.0));
+
+        let d = m.pair_distances(&a, &b);
+        let max_similar = d.iter().zip(s.iter()).filter(|&(_, &s)| s).fold(0.0, |acc: f64, (&d, _)| acc.max(d));
+        let min_dissimilar = d.iter().zip(s.iter()).filter(|&(_, &s)| !s).fold(1.0, |acc: f64, (&d, _)| acc.min(d));
+        assert!(max_similar < min_dissimilar);
+    }
+
+    #[test]
+    fn test_gd_param_groups() {
+
+        let n = seeded_network(&[2, 3, 1]);
+        let x = mat![0.1, 0.9; 0.8, 0.2; 0.5, 0.5];
+        let t = mat![1.0; 0.0; 0.5];
+
+        // the frozen first layer does not change
+        let opts = empty_opts().alpha(1.0).iter(10);
+        let m = n.set_param_groups(&[ParamGroup::new(&[0]).alpha(0.0)]).gd(&x, &t, opts);
+        assert_eq!(m.params()[0], n.params()[0]);
+        assert!(m.params()[1] != n.params()[1]);
+
+        // one step with weight decay of the output layer except its bias
+        let d = n.derivatives(&x, &t);
+        let m = n.set_param_groups(&[ParamGroup::new(&[1]).alpha(0.2).decay(0.1)]).gd(&x, &t, empty_opts().alpha(0.5).iter(1));
+        let (p0, p1) = (n.params(), m.params());
+        for c in 0..p0[1].cols() {
+            let w = *p0[1].get(0, c).unwrap();
+            let shrunk = if c == 0 { w } else { w * (1.0 - 0.2 * 0.1) };
+            assert!(num::abs(p1[1].get(0, c).unwrap() - (shrunk - 0.2 * d[1].get(0, c).unwrap())) < 1e-12);
+        }
+        for (a, b) in p1[0].iter().zip(p0[0].iter().zip(d[0].iter())) {
+            assert!(num::abs(a - (b.0 - 0.5 * b.1)) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_gd_lambda() {
+
+        let n = seeded_network(&[2, 3, 1]);
+        let x = mat![0.1, 0.9; 0.8, 0.2; 0.5, 0.5];
+        let t = mat![1.0; 0.0; 0.5];
+        let lambda = 0.3;
+
+        // numerical derivatives of the regularized error
+        let d = n.regularized_derivatives(&x, &t, lambda);
+        for (a, e) in d.iter().zip(numeric_gradient(&n, |m| m.regularized_mse(&x, &t, lambda)).iter()) {
+            assert!(a.similar(e, 1e-8));
+        }
+        // the bias is not regularized
+        let plain = n.derivatives(&x, &t);
+        assert_eq!(d[1].get(0, 0), plain[1].get(0, 0));
+
+        // one step of gradient descent on the regularized error
+        let mut losses = vec![];
+        let m = n.gd_with_progress(&x, &t, empty_opts().alpha(0.5).iter(1).lambda(lambda),
+            &mut |i: &ProgressInfo| losses.push(i.loss.unwrap()));
+        for (a, (p, g)) in m.params().iter().zip(n.params().iter().zip(d.iter())) {
+            let mut e = p.clone();
+            e.iscaled_add(-0.5, g);
+            assert!(a.similar(&e, 1e-12));
+        }
+        assert!(num::abs(losses[0] - m.regularized_mse(&x, &t, lambda)) < 1e-12);
+        assert!(m.penalty(lambda) < n.penalty(lambda));
+    }
+
+    #[test]
+    fn test_gd_lookahead_average() {
+
+        let n = seeded_network(&[2, 3, 1]);
+        let x = mat![0.1, 0.9; 0.8, 0.2; 0.5, 0.5];
+        let t = mat![1.0; 0.0; 0.5];
+        let opts = empty_opts().alpha(1.0);
+        let plain: Vec<Vec<f64>> = (1..4).map(|i| n.gd(&x, &t, opts.iter(i)).flat_params()).collect();
+        let p = n.flat_params();
+
+        // the slow weights move half way towards the fast weights after two steps
+        let m = n.gd(&x, &t, opts.iter(2).lookahead(2, 0.5)).flat_params();
+        for i in 0..p.len() {
+            assert!(num::abs(m[i] - (p[i] + 0.5 * (plain[1][i] - p[i]))) < 1e-12);
+        }
+
+        // the average of the parameters of all iterations after the first
+        let m = n.gd(&x, &t, opts.iter(3).average(1)).flat_params();
+        for i in 0..p.len() {
+            assert!(num::abs(m[i] - (plain[1][i] + plain[2][i]) / 2.0) < 1e-12);
+        }
+
+        // weighted and resumed gradient descent use the same wrappers
+        let opts = opts.iter(5).lookahead(2, 0.5).average(1);
+        let m = n.gd(&x, &t, opts).flat_params();
+        assert_eq!(n.weighted_gd(&x, &t, &mut vec![1.0; 3], opts, &mut FixedWeights).flat_params(), m);
+
+        let path = ::std::env::temp_dir().join("rustml_test_nn_lookahead.ckpt");
+        let fname = path.to_str().unwrap();
+        let mut c = Checkpointer::new(fname).every_epochs(1);
+        n.gd_with_checkpoints(&x, &t, opts.iter(3), &mut c, &mut NoProgress).unwrap();
+        let ckpt: Checkpoint<NeuralNetwork> = ::checkpoint::resume_from(fname).unwrap();
+        assert_eq!(ckpt.state.slow.len(), p.len());
+        let r = ckpt.resume_gd(&x, &t, opts, &mut c, &mut NoProgress).unwrap().flat_params();
+        for i in 0..p.len() {
+            assert!(num::abs(r[i] - m[i]) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_weighted_gd() {
+
+        let n = seeded_network(&[2, 3, 1]);
+        let x = mat![0.1, 0.9; 0.8, 0.2; 0.5, 0.5];
+        let t = mat![1.0; 0.0; 0.5];
+        let opts = empty_opts().alpha(1.0).iter(5);
+
+        // unit weights are plain gradient descent
+        let mut w = vec![1.0; 3];
+        let m = n.weighted_gd(&x, &t, &mut w, opts, &mut FixedWeights);
+        assert_eq!(m.params(), n.gd(&x, &t, opts).params());
+
+        // the schedule gets the errors of the current network and an
+        // example with weight zero does not contribute
+        let mut iters = vec![];
+        let mut losses = vec![];
+        let m = n.weighted_gd_with_progress(&x, &t, &mut w, opts,
+            &mut |i: usize, e: &[f64], w: &mut [f64]| {
+                iters.push(i);
+                assert_eq!(e.len(), 3);
+                w[2] = 0.0;
+            },
+            &mut |i: &ProgressInfo| losses.push(i.loss.unwrap()));
+        assert_eq!(iters, vec![0, 1, 2, 3, 4]);
+        assert_eq!(w, vec![1.0, 1.0, 0.0]);
+        let e = m.example_errors(&x, &t);
+        assert!(num::abs(losses[4] - (e[0] + e[1]) / 3.0) < 1e-12);
+        let x2 = mat![0.1, 0.9; 0.8, 0.2];
+        let t2 = mat![1.0; 0.0];
+        let d = n.gd(&x2, &t2, empty_opts().alpha(1.0 * 2.0 / 3.0).iter(5));
+        for (a, b) in m.params().iter().zip(d.params().iter()) {
+            assert!(a.similar(b, 1e-12));
+        }
+    }
+
     #[test]
     fn test_mse() {
 
@@ -772,6 +2290,23 @@ mod tests {
         assert!(p[1].eq(&params2));
     }
 
+    #[test]
+    fn test_estimator() {
+
+        let x = mat![0.1, 0.9; 0.8, 0.2; 0.5, 0.5];
+        let t = mat![1.0; 0.0; 0.5];
+        let y: Vec<Vec<f64>> = t.row_iter().map(|r| r.to_vec()).collect();
+        let opts = empty_opts().alpha(0.5).iter(20);
+        let n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1).set_fit_opts(opts);
+        assert_eq!(n.fit_opts().iter, Some(20));
+
+        let mut m = n.clone();
+        m.fit(&x, &y);
+        assert_eq!(m.params(), n.gd(&x, &t, opts).params());
+        let p = Estimator::predict(&m, &x);
+        assert_eq!(p, m.predict(&x).row_iter().map(|r| r.to_vec()).collect::<Vec<Vec<f64>>>());
+    }
+
     #[test]
     fn test_nn_predict() {
 
@@ -788,5 +2323,47 @@ mod tests {
         assert!(n.predict(&x).similar(&t, 0.00001));
     }
 
+    #[test]
+    fn test_nn_predict_into() {
+
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(5).add_layer(2);
+        let x = Matrix::from_vec((0..30).map(|i| i as f64 / 10.0).collect(), 10, 3);
+        let mut o = mat![1.0];
+        let mut b = PredictBuffers::new();
+        n.predict_into_with(&x, &mut o, &mut b);
+        assert!(o.similar(&n.predict(&x), 1e-12));
+        n.predict_into(&x.reshape(2, 3), &mut o);
+        assert!(o.similar(&n.predict(&x.reshape(2, 3)), 1e-12));
+    }
+
+    #[test]
+    fn test_nn_predict_parallel() {
+
+        let n = NeuralNetwork::new().add_layer(3).add_layer(4).add_layer(2);
+        let x = Matrix::from_vec((0..30).map(|i| i as f64 / 10.0).collect(), 10, 3);
+        let p = n.predict(&x);
+        assert!(n.predict_parallel(&x, 1).similar(&p, 1e-12));
+        assert!(n.predict_parallel(&x, 3).similar(&p, 1e-12));
+        assert!(n.predict_parallel(&x, 20).similar(&p, 1e-12));
+    }
+
+    #[test]
+    fn test_nn_try_variants() {
+
+        let n = NeuralNetwork::new();
+        assert!(n.try_input_size().is_err());
+        assert!(n.try_predict(&mat![1.0]).is_err());
+
+        let n = n.add_layer(2).add_layer(1);
+        assert_eq!(n.try_input_size(), Ok(2));
+        assert_eq!(n.try_output_size(), Ok(1));
+        assert_eq!(n.try_set_params(1, mat![1.0, 2.0]).err(), Some(RustmlError::NoSuchLayer(1)));
+        assert!(n.try_set_params(0, mat![1.0, 2.0, 3.0]).is_err());
+        assert!(n.try_predict(&mat![1.0, 2.0, 3.0]).is_err());
+
+        let n = n.try_set_params(0, mat![0.0, 0.0]).unwrap();
+        assert!(n.try_predict(&mat![1.0, 2.0]).unwrap().similar(&mat![0.5], 0.00001));
+    }
+
 }
 
@@ -1,3 +1,9 @@
+//! Generation of Octave scripts.
+//!
+//! For common plots (scatter plots, lines, histograms, heatmaps and decision
+//! boundaries) the structured API of the [plot](../plot/index.html) module
+//! should be preferred.
+
 extern crate num;
 
 use std::fmt;
@@ -18,6 +18,8 @@
 //! ```
 extern crate libc;
 extern crate num;
+#[cfg(feature = "matrixmultiply")]
+extern crate matrixmultiply;
 
 use self::libc::{c_int, c_float, c_double};
 use self::num::traits::Float;
@@ -25,6 +27,48 @@ use self::num::traits::Float;
 use ops::Functions;
 use blas::*;
 use matrix::Matrix;
+use backend::{backend, Backend};
+
+// Returns the row stride and the column stride of `op(m)`.
+#[cfg(feature = "matrixmultiply")]
+fn strides<T: Clone>(m: &Matrix<T>, trans: bool) -> (isize, isize) {
+    if trans { (1, m.cols() as isize) } else { (m.cols() as isize, 1) }
+}
+
+macro_rules! impl_matrixmultiply_gemm {
+    ($name:ident, $t:ty, $f:ident) => (
+
+        /// Computes `alpha * op(a) * op(b) + beta * c` with the crate
+        /// `matrixmultiply`. The dimensions must have been checked.
+        #[cfg(feature = "matrixmultiply")]
+        fn $name(alpha: $t, a: &Matrix<$t>, b: &Matrix<$t>, beta: $t, c: &mut Matrix<$t>,
+                 transa: bool, transb: bool, k: usize) {
+
+            let (m, n) = (c.rows(), c.cols());
+            let ra = if transa { a.cols() } else { a.rows() };
+            let cb = if transb { b.rows() } else { b.cols() };
+            assert!(ra == m && cb == n, "Dimensions do not match.");
+
+            let (rsa, csa) = strides(a, transa);
+            let (rsb, csb) = strides(b, transb);
+            unsafe {
+                matrixmultiply::$f(m, k, n, alpha,
+                    a.buf().as_ptr(), rsa, csa,
+                    b.buf().as_ptr(), rsb, csb,
+                    beta, c.buf_mut().as_mut_ptr(), n as isize, 1);
+            }
+        }
+
+        #[cfg(not(feature = "matrixmultiply"))]
+        fn $name(_alpha: $t, _a: &Matrix<$t>, _b: &Matrix<$t>, _beta: $t, _c: &mut Matrix<$t>,
+                 _transa: bool, _transb: bool, _k: usize) {
+            unreachable!("The backend matrixmultiply is not available.");
+        }
+    )
+}
+
+impl_matrixmultiply_gemm!(mm_dgemm, f64, dgemm);
+impl_matrixmultiply_gemm!(mm_sgemm, f32, sgemm);
 
 // ----------------------------------------------------------------------------
 
@@ -114,6 +158,11 @@ pub fn d_gemm(alpha: f64, a: &Matrix<f64>, b: &Matrix<f64>,
     let n = c.cols();
     let k = colsa;
 
+    if backend() == Backend::MatrixMultiply {
+        mm_dgemm(alpha, a, b, beta, c, transa, transb, k);
+        return;
+    }
+
     let lda = if !transa { k } else { m };
     let ldb = if !transb { n } else { k };
     let ldc = c.cols();
@@ -223,6 +272,118 @@ pub fn d_nrm2(x: &[f64]) -> f64 {
     }
 }
 
+/// Solves `op(A) * x = b` for a triangular matrix `A` and stores the solution in `x`
+/// which contains `b` on entry. (optimized via BLAS)
+///
+/// If `lower` is `true` only the lower triangle of `A` is used, otherwise only the upper
+/// triangle. If `trans` is `true` the transpose of `A` is used. If `unit` is `true` the
+/// diagonal elements of `A` are assumed to be one.
+///
+/// Panics if `A` is not a square matrix or if the dimensions of the matrix and the
+/// vector do not match.
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::ops_inplace::*;
+/// use rustml::matrix::*;
+///
+/// # fn main() {
+/// let l = mat![
+///     2.0, 0.0;
+///     1.0, 4.0
+/// ];
+/// let mut x = [4.0, 10.0];
+/// d_trsv(true, false, false, &l, &mut x);
+/// assert_eq!(x, [2.0, 2.0]);
+///
+/// // L^T x = b
+/// let mut y = [8.0, 8.0];
+/// d_trsv(true, true, false, &l, &mut y);
+/// assert_eq!(y, [3.0, 2.0]);
+/// # }
+/// ```
+pub fn d_trsv(lower: bool, trans: bool, unit: bool, a: &Matrix<f64>, x: &mut [f64]) {
+
+    if a.rows() != a.cols() || a.rows() != x.len() {
+        panic!("Invalid dimensions.");
+    }
+    if x.len() == 0 {
+        return;
+    }
+
+    unsafe {
+        cblas_dtrsv(
+            Order::RowMajor,
+            if lower { Uplo::Lower } else { Uplo::Upper },
+            if trans { Transpose::Trans } else { Transpose::NoTrans },
+            if unit { Diag::Unit } else { Diag::NonUnit },
+            a.rows() as c_int,
+            a.buf().as_ptr() as *const c_double,
+            a.cols() as c_int,
+            x.as_mut_ptr() as *mut c_double,
+            1 as c_int
+        );
+    }
+}
+
+/// Solves `op(A) * X = alpha * B` or, if `left` is `false`, `X * op(A) = alpha * B` for
+/// a triangular matrix `A` and stores the solution in `B`. (optimized via BLAS)
+///
+/// The parameters `lower`, `trans` and `unit` are the same as for
+/// [d_trsv](fn.d_trsv.html).
+///
+/// Panics if `A` is not a square matrix or if the dimensions of the matrices do not
+/// match.
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::ops_inplace::*;
+/// use rustml::matrix::*;
+///
+/// # fn main() {
+/// let u = mat![
+///     1.0, 2.0;
+///     0.0, 2.0
+/// ];
+/// let mut b = mat![
+///     5.0, 3.0;
+///     4.0, 2.0
+/// ];
+/// d_trsm(true, false, false, false, 1.0, &u, &mut b);
+/// assert_eq!(b, mat![1.0, 1.0; 2.0, 1.0]);
+/// # }
+/// ```
+pub fn d_trsm(left: bool, lower: bool, trans: bool, unit: bool, alpha: f64,
+              a: &Matrix<f64>, b: &mut Matrix<f64>) {
+
+    let k = if left { b.rows() } else { b.cols() };
+    if a.rows() != a.cols() || a.rows() != k {
+        panic!("Dimensions for d_trsm do not match: {}x{} and {}x{}",
+            a.rows(), a.cols(), b.rows(), b.cols());
+    }
+    if b.rows() == 0 || b.cols() == 0 {
+        return;
+    }
+
+    let (m, n) = (b.rows(), b.cols());
+    unsafe {
+        cblas_dtrsm(
+            Order::RowMajor,
+            if left { Side::Left } else { Side::Right },
+            if lower { Uplo::Lower } else { Uplo::Upper },
+            if trans { Transpose::Trans } else { Transpose::NoTrans },
+            if unit { Diag::Unit } else { Diag::NonUnit },
+            m as c_int,
+            n as c_int,
+            alpha as c_double,
+            a.buf().as_ptr() as *const c_double,
+            a.cols() as c_int,
+            b.buf_mut().as_mut_ptr() as *mut c_double,
+            n as c_int
+        );
+    }
+}
+
 /// Computes `alpha * x + y` and stores the result in `y`. (optimized via BLAS)
 /// 
 /// Panics if the dimensions of the vectors do not match.
@@ -306,6 +467,11 @@ pub fn s_gemm(alpha: f32, a: &Matrix<f32>, b: &Matrix<f32>,
     let n = c.cols();
     let k = colsa;
 
+    if backend() == Backend::MatrixMultiply {
+        mm_sgemm(alpha, a, b, beta, c, transa, transb, k);
+        return;
+    }
+
     let lda = if !transa { k } else { m };
     let ldb = if !transb { n } else { k };
     let ldc = c.cols();
@@ -792,6 +958,138 @@ impl_vector_vector_ops_inplace!{ f64, d_axpy, d_nrm2 }
 
 // ----------------------------------------------------------------------------
 
+/// Trait for fused operations, i.e. chains of element-wise operations which
+/// are evaluated in one pass without temporary vectors or matrices.
+///
+/// For example, the update `p = p - alpha * d` of gradient descent can be
+/// written as `p.iscaled_add(-alpha, &d)` instead of
+/// `p.sub(&d.mul_scalar(alpha))` which allocates two temporaries.
+///
+/// The reductions `sub_square_sum` and `mul_sum` of `f64` are computed with
+/// the vector kernels of the module [backend](../backend/index.html).
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::ops_inplace::FusedOps;
+///
+/// # fn main() {
+/// let mut p = mat![1.0, 2.0; 3.0, 4.0];
+/// let d = mat![1.0, 1.0; 2.0, 2.0];
+/// p.iscaled_add(-0.5, &d);
+/// assert!(p.eq(&mat![0.5, 1.5; 2.0, 3.0]));
+/// assert_eq!(p.sub_square_sum(&d), 0.25 + 0.25 + 0.0 + 1.0);
+/// # }
+/// ```
+pub trait FusedOps<T> {
+
+    /// Computes `self + alpha * rhs` and stores the result in `self`.
+    /// (optimized via BLAS)
+    ///
+    /// Panics if the dimensions do not match.
+    fn iscaled_add(&mut self, alpha: T, rhs: &Self);
+
+    /// Computes `alpha * self + beta * rhs` and stores the result in
+    /// `self`.
+    ///
+    /// Panics if the dimensions do not match.
+    fn iaxpby(&mut self, alpha: T, beta: T, rhs: &Self);
+
+    /// Computes the sum of the squared differences of the elements of
+    /// `self` and `rhs`, i.e. the squared euclidean distance.
+    ///
+    /// Panics if the dimensions do not match.
+    fn sub_square_sum(&self, rhs: &Self) -> T;
+
+    /// Computes the sum of the products of the elements of `self` and
+    /// `rhs`, i.e. the dot product.
+    ///
+    /// Panics if the dimensions do not match.
+    fn mul_sum(&self, rhs: &Self) -> T;
+}
+
+// reductions of f32 (the reductions of f64 use the kernels of the module backend)
+fn s_sub_square_sum(x: &[f32], y: &[f32]) -> f32 {
+
+    assert!(x.len() == y.len(), "Dimensions do not match.");
+    x.iter().zip(y.iter()).fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b))
+}
+
+fn s_mul_sum(x: &[f32], y: &[f32]) -> f32 {
+
+    assert!(x.len() == y.len(), "Dimensions do not match.");
+    x.iter().zip(y.iter()).fold(0.0, |acc, (a, b)| acc + a * b)
+}
+
+macro_rules! impl_fused_ops {
+    ( $x:ty, $axpy:ident, $sqdist:path, $dot:path ) => (
+
+        impl FusedOps<$x> for [$x] {
+
+            fn iscaled_add(&mut self, alpha: $x, rhs: &[$x]) {
+
+                assert!(self.len() == rhs.len(), "Dimensions do not match.");
+                $axpy(alpha, rhs, self);
+            }
+
+            fn iaxpby(&mut self, alpha: $x, beta: $x, rhs: &[$x]) {
+
+                assert!(self.len() == rhs.len(), "Dimensions do not match.");
+                for (a, b) in self.iter_mut().zip(rhs.iter()) {
+                    *a = alpha * *a + beta * *b;
+                }
+            }
+
+            fn sub_square_sum(&self, rhs: &[$x]) -> $x {
+                $sqdist(self, rhs)
+            }
+
+            fn mul_sum(&self, rhs: &[$x]) -> $x {
+                $dot(self, rhs)
+            }
+        }
+
+        impl FusedOps<$x> for Vec<$x> {
+            fn iscaled_add(&mut self, alpha: $x, rhs: &Vec<$x>) { (self[..]).iscaled_add(alpha, rhs); }
+            fn iaxpby(&mut self, alpha: $x, beta: $x, rhs: &Vec<$x>) { (self[..]).iaxpby(alpha, beta, rhs); }
+            fn sub_square_sum(&self, rhs: &Vec<$x>) -> $x { (self[..]).sub_square_sum(rhs) }
+            fn mul_sum(&self, rhs: &Vec<$x>) -> $x { (self[..]).mul_sum(rhs) }
+        }
+
+        impl FusedOps<$x> for Matrix<$x> {
+
+            fn iscaled_add(&mut self, alpha: $x, rhs: &Matrix<$x>) {
+
+                assert!(self.rows() == rhs.rows() && self.cols() == rhs.cols(), "Dimensions do not match.");
+                self.buf_mut().iscaled_add(alpha, rhs.buf());
+            }
+
+            fn iaxpby(&mut self, alpha: $x, beta: $x, rhs: &Matrix<$x>) {
+
+                assert!(self.rows() == rhs.rows() && self.cols() == rhs.cols(), "Dimensions do not match.");
+                self.buf_mut().iaxpby(alpha, beta, rhs.buf());
+            }
+
+            fn sub_square_sum(&self, rhs: &Matrix<$x>) -> $x {
+
+                assert!(self.rows() == rhs.rows() && self.cols() == rhs.cols(), "Dimensions do not match.");
+                self.buf().sub_square_sum(rhs.buf())
+            }
+
+            fn mul_sum(&self, rhs: &Matrix<$x>) -> $x {
+
+                assert!(self.rows() == rhs.rows() && self.cols() == rhs.cols(), "Dimensions do not match.");
+                self.buf().mul_sum(rhs.buf())
+            }
+        }
+    )
+}
+
+impl_fused_ops!{ f32, s_axpy, s_sub_square_sum, s_mul_sum }
+impl_fused_ops!{ f64, d_axpy, ::backend::squared_distance, ::backend::dot }
+
+// ----------------------------------------------------------------------------
+
 #[cfg(test)]
 mod tests {
     extern crate num;
@@ -938,6 +1236,53 @@ mod tests {
         assert_eq!(c.buf(), &vec![41.0, 94.0, 75.0, 88.0, 86.0, 208.0, 180.0, 166.0]);
     }
 
+    #[test]
+    fn test_fused_ops() {
+
+        let mut a = vec![1.0, 2.0, 3.0];
+        a.iscaled_add(2.0, &vec![1.0, 0.0, -1.0]);
+        assert_eq!(a, vec![3.0, 2.0, 1.0]);
+        a.iaxpby(2.0, -1.0, &vec![1.0, 1.0, 1.0]);
+        assert_eq!(a, vec![5.0, 3.0, 1.0]);
+        assert_eq!(a.sub_square_sum(&vec![4.0, 3.0, 3.0]), 5.0);
+        assert_eq!(a.mul_sum(&vec![1.0, 2.0, 3.0]), 14.0);
+
+        let mut m = mat![1.0f32, 2.0; 3.0, 4.0];
+        m.iaxpby(0.5, 1.0, &mat![1.0f32, 1.0; 1.0, 1.0]);
+        assert!(m.eq(&mat![1.5f32, 2.0; 2.5, 3.0]));
+        assert_eq!(m.mul_sum(&m), 2.25 + 4.0 + 6.25 + 9.0);
+    }
+
+    #[test]
+    fn test_gemm_backends() {
+
+        let a = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        let b = mat![1.0, 2.0; 3.0, 4.0; 5.0, 6.0];
+        let c = mat![1.0, 1.0, 1.0; 2.0, 2.0, 2.0; 0.0, 1.0, 0.0];
+        let af = a.map(|&x| x as f32);
+
+        let mut backends = vec![Backend::Blas];
+        if ::backend::is_available(Backend::MatrixMultiply) {
+            backends.push(Backend::MatrixMultiply);
+        }
+        for &bk in &backends {
+            ::backend::set_backend(bk).unwrap();
+
+            let mut r = mat![1.0, 1.0; 1.0, 1.0];
+            d_gemm(2.0, &a, &b, 1.0, &mut r, false, false);
+            assert!(r.eq(&mat![45.0, 57.0; 99.0, 129.0]));
+
+            let mut r = Matrix::fill(0.0, 2, 3);
+            d_gemm(1.0, &a, &c, 0.0, &mut r, false, true);
+            assert!(r.eq(&mat![6.0, 12.0, 2.0; 15.0, 30.0, 5.0]));
+
+            let mut r = Matrix::fill(0.0f32, 3, 3);
+            s_gemm(1.0, &af, &af, 0.0, &mut r, true, false);
+            assert!(r.eq(&mat![17.0f32, 22.0, 27.0; 22.0, 29.0, 36.0; 27.0, 36.0, 45.0]));
+        }
+        ::backend::set_backend(Backend::Blas).unwrap();
+    }
+
     #[test]
     fn test_s_gemm() {
 
@@ -1019,6 +1364,42 @@ mod tests {
         assert_eq!(y, [67.0, 76.0]);
     }
 
+    #[test]
+    fn test_d_trsv_trsm() {
+        let a = mat![
+            2.0, 1.0, 3.0;
+            4.0, 1.0, 2.0;
+            5.0, 6.0, 4.0
+        ];
+        let b = [1.0, 2.0, 3.0];
+
+        // solutions of all variants are checked with d_gemv on the triangle
+        for &lower in &[true, false] {
+            for &trans in &[true, false] {
+                for &unit in &[true, false] {
+                    let t = Matrix::from_vec((0..9).map(|i| {
+                        let (r, c) = (i / 3, i % 3);
+                        if r == c && unit { 1.0 }
+                        else if (lower && r >= c) || (!lower && r <= c) { *a.get(r, c).unwrap() }
+                        else { 0.0 }
+                    }).collect(), 3, 3);
+                    let mut x = b.clone();
+                    d_trsv(lower, trans, unit, &a, &mut x);
+                    let mut y = [0.0; 3];
+                    d_gemv(trans, 1.0, &t, &x, 0.0, &mut y);
+                    assert!(y.iter().zip(b.iter()).all(|(p, q)| (p - q).abs() < 1e-12));
+
+                    // X op(T) = 2 B
+                    let mut m = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+                    d_trsm(false, lower, trans, unit, 2.0, &a, &mut m);
+                    let mut c = Matrix::fill(0.0, 2, 3);
+                    d_gemm(1.0, &m, &t, 0.0, &mut c, false, trans);
+                    assert!(c.similar(&mat![2.0, 4.0, 6.0; 8.0, 10.0, 12.0], 1e-12));
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_s_gemv() {
         let a = mat![
@@ -34,19 +34,81 @@
 //!
 //! See [here](https://github.com/daniel-e/rustml/blob/master/examples/gradient_descent.rs) for
 //! another example.
+//!
+//! # Example: L-BFGS
+//!
+//! [lbfgs](fn.lbfgs.html) needs no learning rate and usually converges in
+//! far fewer iterations. The objective returns its value and its gradient
+//! at once.
+//!
+//! ```
+//! use rustml::opt::*;
+//!
+//! // Rosenbrock function with the minimum at (1, 1)
+//! let r = lbfgs(
+//!     &|p: &[f64]| {
+//!         let (a, b) = (1.0 - p[0], p[1] - p[0] * p[0]);
+//!         (a * a + 100.0 * b * b, vec![-2.0 * a - 400.0 * p[0] * b, 200.0 * b])
+//!     },
+//!     &[-1.0, 2.0],
+//!     empty_opts().iter(200).eps(1e-8)
+//! );
+//! assert!(r.stopped && !r.line_search_failed);
+//! assert!((r.params[0] - 1.0).abs() < 1e-4 && (r.params[1] - 1.0).abs() < 1e-4);
+//! ```
 extern crate num;
 extern crate rand;
 
+#[cfg(not(feature = "pure-rust"))]
 use self::rand::{thread_rng, Rng};
 
-use ops::*;
+use ops_inplace::FusedOps;
 use regression::*;
 use matrix::Matrix;
+#[cfg(not(feature = "pure-rust"))]
 use opencv::{Window, RgbImage};
+#[cfg(not(feature = "pure-rust"))]
 use octave::builder;
+use progress::{Progress, ProgressInfo, NoProgress, report};
+
+/// Learning rate and weight decay of a group of parameter matrices, e.g.
+/// of the layers of a [neural network](../nn/struct.NeuralNetwork.html)
+/// (see [set_param_groups](../nn/struct.NeuralNetwork.html#method.set_param_groups)).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamGroup<T: Clone> {
+    /// indexes of the parameter matrices in the group
+    pub layers: Vec<usize>,
+    /// learning rate of the group (the global learning rate if not set)
+    pub alpha: Option<T>,
+    /// weight decay (L2 penalty) of the group
+    pub decay: Option<T>,
+}
+
+impl <T: Clone> ParamGroup<T> {
+    /// Creates a group of the parameter matrices with the given indexes
+    /// which uses the global learning rate and no weight decay.
+    pub fn new(layers: &[usize]) -> ParamGroup<T> {
+        ParamGroup {
+            layers: layers.to_vec(),
+            alpha: None,
+            decay: None,
+        }
+    }
+
+    /// Sets the learning rate of the group. A learning rate of zero
+    /// freezes the parameters, e.g. of pretrained layers.
+    pub fn alpha(&self, val: T) -> ParamGroup<T> {
+        ParamGroup { alpha: Some(val), .. self.clone() }
+    }
+
+    /// Sets the weight decay of the group.
+    pub fn decay(&self, val: T) -> ParamGroup<T> {
+        ParamGroup { decay: Some(val), .. self.clone() }
+    }
+}
 
 /// Creates a container that holds the parameters for an optimization algorithm.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct OptParams<T: Clone> {
     /// learning rate
     pub alpha: Option<T>,
@@ -54,6 +116,14 @@ pub struct OptParams<T: Clone> {
     pub iter: Option<usize>,
     /// stopping criterion
     pub eps: Option<T>,
+    /// strength of the L2 regularization (weight decay)
+    pub lambda: Option<T>,
+    /// number of steps `k` and step size of the slow weights of
+    /// [Lookahead](struct.Lookahead.html)
+    pub lookahead: Option<(usize, T)>,
+    /// iteration after which the parameters are averaged (see
+    /// [PolyakAverage](struct.PolyakAverage.html))
+    pub average: Option<usize>,
 }
 
 impl <T: Clone> OptParams<T> {
@@ -71,6 +141,9 @@ impl <T: Clone> OptParams<T> {
             alpha: None,
             iter: None,
             eps: None,
+            lambda: None,
+            lookahead: None,
+            average: None,
         }
     }
 
@@ -88,6 +161,9 @@ impl <T: Clone> OptParams<T> {
             alpha: Some(val),
             iter: self.iter.clone(),
             eps: self.eps.clone(),
+            lambda: self.lambda.clone(),
+            lookahead: self.lookahead.clone(),
+            average: self.average.clone(),
         }
     }
 
@@ -105,6 +181,9 @@ impl <T: Clone> OptParams<T> {
             alpha: self.alpha.clone(),
             iter: Some(val),
             eps: self.eps.clone(),
+            lambda: self.lambda.clone(),
+            lookahead: self.lookahead.clone(),
+            average: self.average.clone(),
         }
     }
 
@@ -122,10 +201,241 @@ impl <T: Clone> OptParams<T> {
             alpha: self.alpha.clone(),
             iter: self.iter.clone(),
             eps: Some(val),
+            lambda: self.lambda.clone(),
+            lookahead: self.lookahead.clone(),
+            average: self.average.clone(),
+        }
+    }
+
+    /// Sets the strength of the L2 regularization, i.e. the penalty
+    /// `lambda / 2` times the sum of the squared weights (without the
+    /// weights of the bias units) is added to the cost. Used by the training
+    /// of [neural networks](../nn/struct.NeuralNetwork.html) where a
+    /// [group](../nn/struct.NeuralNetwork.html#method.set_param_groups) with
+    /// its own weight decay overrides `lambda`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::opt::*;
+    /// let opts = empty_opts().lambda(1e-3);
+    /// assert_eq!(opts.lambda, Some(1e-3));
+    /// ```
+    pub fn lambda(&self, val: T) -> OptParams<T> {
+        OptParams {
+            alpha: self.alpha.clone(),
+            iter: self.iter.clone(),
+            eps: self.eps.clone(),
+            lambda: Some(val),
+            lookahead: self.lookahead.clone(),
+            average: self.average.clone(),
+        }
+    }
+
+    /// Wraps the optimizer into [Lookahead](struct.Lookahead.html) which
+    /// moves a copy of the parameters (the slow weights) by the step size
+    /// `alpha` towards the parameters of the optimizer every `k` iterations.
+    /// The option is used by [opt](fn.opt.html),
+    /// [opt_hypothesis](fn.opt_hypothesis.html) and the gradient descent of
+    /// [neural networks](../nn/struct.NeuralNetwork.html). Other optimizers,
+    /// e.g. L-BFGS, ignore it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::opt::*;
+    /// let opts = empty_opts().lookahead(5, 0.5);
+    /// assert_eq!(opts.lookahead, Some((5, 0.5)));
+    /// ```
+    pub fn lookahead(&self, k: usize, alpha: T) -> OptParams<T> {
+        OptParams {
+            alpha: self.alpha.clone(),
+            iter: self.iter.clone(),
+            eps: self.eps.clone(),
+            lambda: self.lambda.clone(),
+            lookahead: Some((k, alpha)),
+            average: self.average.clone(),
+        }
+    }
+
+    /// Returns the average of the parameters of all iterations after the
+    /// first `start` iterations instead of the parameters of the last
+    /// iteration (see [PolyakAverage](struct.PolyakAverage.html)). The option
+    /// is used by the same optimizers as [lookahead](#method.lookahead).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rustml::opt::*;
+    /// let opts = empty_opts().average(100);
+    /// assert_eq!(opts.average, Some(100));
+    /// ```
+    pub fn average(&self, start: usize) -> OptParams<T> {
+        OptParams {
+            alpha: self.alpha.clone(),
+            iter: self.iter.clone(),
+            eps: self.eps.clone(),
+            lambda: self.lambda.clone(),
+            lookahead: self.lookahead.clone(),
+            average: Some(start),
         }
     }
 }
 
+/// Lookahead wrapper for an arbitrary optimizer of a parameter vector.
+///
+/// Lookahead keeps a copy of the parameters, the slow weights. The inner
+/// optimizer updates the parameters (the fast weights) as usual and after
+/// every `k` updates the slow weights are moved by the step size `alpha`
+/// towards the fast weights which are then reset to the slow weights. This
+/// reduces the variance of the updates and often improves the
+/// generalization of stochastic gradient descent.
+///
+/// # Example
+///
+/// ```
+/// use rustml::opt::Lookahead;
+///
+/// let mut fast = vec![0.0];
+/// let mut l = Lookahead::new(2, 0.5, &fast);
+/// fast[0] = 1.0;
+/// assert!(!l.step(&mut fast));
+/// fast[0] = 2.0;
+/// assert!(l.step(&mut fast));
+/// assert_eq!(fast, vec![1.0]);
+/// assert_eq!(l.slow(), &[1.0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lookahead {
+    k: usize,
+    alpha: f64,
+    slow: Vec<f64>,
+    steps: usize,
+}
+
+impl Lookahead {
+    /// Creates the wrapper with the initial parameters `init` as slow
+    /// weights.
+    ///
+    /// Panics if `k` is zero or if `alpha` is not in (0, 1].
+    pub fn new(k: usize, alpha: f64, init: &[f64]) -> Lookahead {
+        assert!(k > 0, "Number of steps must be positive.");
+        assert!(alpha > 0.0 && alpha <= 1.0, "Step size must be in (0, 1].");
+        Lookahead {
+            k: k,
+            alpha: alpha,
+            slow: init.to_vec(),
+            steps: 0,
+        }
+    }
+
+    /// Creates the wrapper with the slow weights `slow` after `steps`
+    /// updates, e.g. to continue the optimization from a checkpoint.
+    ///
+    /// Panics if `k` is zero or if `alpha` is not in (0, 1].
+    pub fn resume(k: usize, alpha: f64, slow: &[f64], steps: usize) -> Lookahead {
+        Lookahead { steps: steps, .. Lookahead::new(k, alpha, slow) }
+    }
+
+    /// Must be called after each update of the inner optimizer with the
+    /// updated parameters. Returns true if the slow weights have been updated
+    /// and `fast` has been reset to them.
+    ///
+    /// Panics if the number of parameters does not match.
+    pub fn step(&mut self, fast: &mut [f64]) -> bool {
+        assert!(fast.len() == self.slow.len(), "Number of parameters does not match.");
+        self.steps += 1;
+        if self.steps % self.k != 0 {
+            return false;
+        }
+        for (s, f) in self.slow.iter_mut().zip(fast.iter_mut()) {
+            *s += self.alpha * (*f - *s);
+            *f = *s;
+        }
+        true
+    }
+
+    /// Returns the slow weights.
+    pub fn slow(&self) -> &[f64] {
+        &self.slow
+    }
+}
+
+/// Polyak-Ruppert averaging of the parameters of an optimizer.
+///
+/// The running mean of the parameters of the iterations after a burn-in
+/// is usually closer to the minimum than the parameters of the last
+/// iteration of stochastic gradient descent which oscillate around it.
+///
+/// # Example
+///
+/// ```
+/// use rustml::opt::PolyakAverage;
+///
+/// let mut a = PolyakAverage::new(1);
+/// a.update(&[5.0, 0.0]);
+/// assert!(a.average().is_none());
+/// a.update(&[1.0, 2.0]);
+/// a.update(&[3.0, 4.0]);
+/// assert_eq!(a.average(), Some(&[2.0, 3.0][..]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PolyakAverage {
+    start: usize,
+    steps: usize,
+    mean: Vec<f64>,
+}
+
+impl PolyakAverage {
+    /// Creates an average which ignores the parameters of the first `start`
+    /// iterations.
+    pub fn new(start: usize) -> PolyakAverage {
+        PolyakAverage {
+            start: start,
+            steps: 0,
+            mean: vec![],
+        }
+    }
+
+    /// Creates an average after `steps` iterations where `mean` is the
+    /// average of the parameters of the iterations after the first `start`
+    /// iterations, e.g. to continue the optimization from a checkpoint. If
+    /// `mean` is empty the averaging starts after the first `start`
+    /// iterations or, if they are completed, with the next iteration.
+    pub fn resume(start: usize, steps: usize, mean: &[f64]) -> PolyakAverage {
+        PolyakAverage {
+            start: start,
+            steps: if mean.is_empty() { steps.min(start) } else { steps },
+            mean: mean.to_vec(),
+        }
+    }
+
+    /// Adds the parameters of the next iteration.
+    ///
+    /// Panics if the number of parameters differs from the previous ones.
+    pub fn update(&mut self, p: &[f64]) {
+        self.steps += 1;
+        if self.steps <= self.start {
+            return;
+        }
+        let n = (self.steps - self.start) as f64;
+        if n == 1.0 {
+            self.mean = p.to_vec();
+            return;
+        }
+        assert!(p.len() == self.mean.len(), "Number of parameters does not match.");
+        for (m, x) in self.mean.iter_mut().zip(p.iter()) {
+            *m += (x - *m) / n;
+        }
+    }
+
+    /// Returns the average of the parameters or `None` if no parameters
+    /// have been averaged yet.
+    pub fn average(&self) -> Option<&[f64]> {
+        if self.steps > self.start { Some(&self.mean) } else { None }
+    }
+}
+
 /// Returns an empty set of options for optimization algorithms.
 pub fn empty_opts() -> OptParams<f64> {
     OptParams::new()
@@ -138,7 +448,11 @@ pub struct OptResult<T> {
     /// The parameters after the last iteration.
     pub params: Vec<T>,
     /// True if the stopping criterion is fulfilled.
-    pub stopped: bool
+    pub stopped: bool,
+    /// True if the optimization stopped before the stopping criterion was
+    /// fulfilled because the line search could not decrease the objective
+    /// any further (only used by [lbfgs](fn.lbfgs.html)).
+    pub line_search_failed: bool
 }
 
 
@@ -209,6 +523,11 @@ impl <T: Clone + Copy> OptResult<T> {
 /// iterations is not specified in `opts` the value 1000 is used. If `epsilon` is
 /// not specified in `opts` no stopping criterion is checked.
 ///
+/// If [lookahead](struct.OptParams.html#method.lookahead) is set the
+/// updates are wrapped into [Lookahead](struct.Lookahead.html). If
+/// [average](struct.OptParams.html#method.average) is set the averaged
+/// parameters are returned instead of the parameters of the last iteration.
+///
 /// # Example
 /// 
 /// ```
@@ -238,6 +557,15 @@ impl <T: Clone + Copy> OptResult<T> {
 pub fn opt<O, D>(f: &O, fd: &D, init: &[f64], opts: OptParams<f64>) -> OptResult<f64>
     where O: Fn(&[f64]) -> f64, D: Fn(&[f64]) -> Vec<f64> {
 
+    opt_with_progress(f, fd, init, opts, &mut NoProgress)
+}
+
+/// Minimizes an objective using gradient descent and reports the progress
+/// after each iteration (see [opt](fn.opt.html) and the module
+/// [progress](../progress/index.html)).
+pub fn opt_with_progress<O, D, P>(f: &O, fd: &D, init: &[f64], opts: OptParams<f64>, progress: &mut P) -> OptResult<f64>
+    where O: Fn(&[f64]) -> f64, D: Fn(&[f64]) -> Vec<f64>, P: Progress {
+
     let alpha = opts.alpha.unwrap_or(0.1);
     let iter = opts.iter.unwrap_or(1000);
     let eps = opts.eps;
@@ -245,10 +573,21 @@ pub fn opt<O, D>(f: &O, fd: &D, init: &[f64], opts: OptParams<f64>) -> OptResult
     let mut r = vec![];
     let mut p = init.to_vec();
     let mut stopped = false;
+    let mut lookahead = opts.lookahead.map(|(k, a)| Lookahead::new(k, a, init));
+    let mut average = opts.average.map(PolyakAverage::new);
 
-    for _ in 0..iter {
-        let i = p.sub(&fd(&p).mul_scalar(alpha));
-        r.push((i.clone(), f(&i)));
+    for k in 0..iter {
+        let mut i = p.clone();
+        i.iscaled_add(-alpha, &fd(&p));
+        if let Some(ref mut l) = lookahead {
+            l.step(&mut i);
+        }
+        if let Some(ref mut a) = average {
+            a.update(&i);
+        }
+        let fval = f(&i);
+        r.push((i.clone(), fval));
+        report(progress, &ProgressInfo { algorithm: "gradient descent", iteration: k + 1, max_iter: iter, loss: Some(fval) });
         stopped = eps.is_some() && i.iter().zip(p.iter()).all(|(&x, &y)| num::abs(x - y) <= eps.unwrap());
         p = i;
         if stopped {
@@ -256,15 +595,27 @@ pub fn opt<O, D>(f: &O, fd: &D, init: &[f64], opts: OptParams<f64>) -> OptResult
         }
     }
 
+    if let Some(v) = average.as_ref().and_then(|a| a.average()) {
+        p = v.to_vec();
+    }
+
     OptResult {
         params: p.to_vec(),
         fvals: r,
-        stopped: stopped
+        stopped: stopped,
+        line_search_failed: false
     }
 }
 
 // TODO duplicated code
 pub fn opt_hypothesis(h: &Hypothesis, x: &Matrix<f64>, y: &[f64], opts: OptParams<f64>) -> OptResult<f64> {
+    opt_hypothesis_with_progress(h, x, y, opts, &mut NoProgress)
+}
+
+/// Optimizes the parameters of a hypothesis with gradient descent and
+/// reports the progress after each iteration (see the module
+/// [progress](../progress/index.html)).
+pub fn opt_hypothesis_with_progress<P: Progress>(h: &Hypothesis, x: &Matrix<f64>, y: &[f64], opts: OptParams<f64>, progress: &mut P) -> OptResult<f64> {
 
     let alpha = opts.alpha.unwrap_or(0.1);
     let iter = opts.iter.unwrap_or(1000);
@@ -275,12 +626,23 @@ pub fn opt_hypothesis(h: &Hypothesis, x: &Matrix<f64>, y: &[f64], opts: OptParam
     let mut stopped = false;
 
     let mut hx = Hypothesis::from_params(&p);
+    let mut lookahead = opts.lookahead.map(|(k, a)| Lookahead::new(k, a, &p));
+    let mut average = opts.average.map(PolyakAverage::new);
 
-    for _ in 0..iter {
+    for k in 0..iter {
         let d = hx.derivatives(x, y);
-        let i = p.sub(&d.mul_scalar(alpha));
+        let mut i = p.clone();
+        i.iscaled_add(-alpha, &d);
+        if let Some(ref mut l) = lookahead {
+            l.step(&mut i);
+        }
+        if let Some(ref mut a) = average {
+            a.update(&i);
+        }
         hx = Hypothesis::from_params(&i);
-        r.push((i.clone(), hx.error(&x, &y)));
+        let err = hx.error(&x, &y);
+        r.push((i.clone(), err));
+        report(progress, &ProgressInfo { algorithm: "gradient descent", iteration: k + 1, max_iter: iter, loss: Some(err) });
         stopped = eps.is_some() && i.iter().zip(p.iter()).all(|(&x, &y)| num::abs(x - y) <= eps.unwrap());
         p = i;
         if stopped {
@@ -288,15 +650,142 @@ pub fn opt_hypothesis(h: &Hypothesis, x: &Matrix<f64>, y: &[f64], opts: OptParam
         }
     }
 
+    if let Some(v) = average.as_ref().and_then(|a| a.average()) {
+        p = v.to_vec();
+    }
+
     OptResult {
         params: p.to_vec(),
         fvals: r,
-        stopped: stopped
+        stopped: stopped,
+        line_search_failed: false
+    }
+}
+
+/// Number of the most recent updates from which [lbfgs](fn.lbfgs.html)
+/// approximates the inverse Hessian.
+pub const LBFGS_MEMORY: usize = 10;
+
+/// Minimizes an objective with the limited-memory BFGS algorithm.
+///
+/// The function `fg` returns the value of the objective and its gradient
+/// for the given parameters. In each iteration the search direction is
+/// computed from the gradient and the last
+/// [LBFGS_MEMORY](constant.LBFGS_MEMORY.html) changes of the parameters and
+/// the gradients and the step size is chosen with a backtracking line
+/// search which guarantees a sufficient decrease of the objective.
+///
+/// If the number of iterations is not specified in `opts` the value 100 is
+/// used. The algorithm stops if the Euclidean norm of the gradient is not
+/// larger than `eps` (default 1e-5), in which case `stopped` of the result
+/// is true, or if the line search cannot decrease the objective any
+/// further, in which case `line_search_failed` is true. The learning rate
+/// `alpha` is not used.
+pub fn lbfgs<F>(fg: &F, init: &[f64], opts: OptParams<f64>) -> OptResult<f64>
+    where F: Fn(&[f64]) -> (f64, Vec<f64>) {
+
+    lbfgs_with_progress(fg, init, opts, &mut NoProgress)
+}
+
+/// Like [lbfgs](fn.lbfgs.html) and reports the progress after each
+/// iteration (see the module [progress](../progress/index.html)).
+pub fn lbfgs_with_progress<F, P>(fg: &F, init: &[f64], opts: OptParams<f64>, progress: &mut P) -> OptResult<f64>
+    where F: Fn(&[f64]) -> (f64, Vec<f64>), P: Progress {
+
+    let iter = opts.iter.unwrap_or(100);
+    let eps = opts.eps.unwrap_or(1e-5);
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).fold(0.0, |acc, (x, y)| acc + x * y);
+
+    let mut p = init.to_vec();
+    let (mut fval, mut g) = fg(&p);
+    let mut hist: Vec<(Vec<f64>, Vec<f64>, f64)> = vec![];
+    let mut r = vec![];
+    let mut stopped = dot(&g, &g).sqrt() <= eps;
+    let mut line_search_failed = false;
+
+    for k in 0..iter {
+        if stopped {
+            break;
+        }
+
+        // two-loop recursion
+        let mut d: Vec<f64> = g.iter().map(|x| -x).collect();
+        let mut a = vec![0.0; hist.len()];
+        for (i, &(ref s, ref y, rho)) in hist.iter().enumerate().rev() {
+            a[i] = rho * dot(s, &d);
+            d.iscaled_add(-a[i], y);
+        }
+        let gamma = match hist.last() {
+            Some(&(ref s, ref y, _)) => dot(s, y) / dot(y, y),
+            None => 1.0 / dot(&g, &g).sqrt().max(1.0)
+        };
+        for x in d.iter_mut() {
+            *x *= gamma;
+        }
+        for (i, &(ref s, ref y, rho)) in hist.iter().enumerate() {
+            let b = rho * dot(y, &d);
+            d.iscaled_add(a[i] - b, s);
+        }
+
+        let mut slope = dot(&g, &d);
+        if slope >= 0.0 {
+            // not a descent direction, restart with the negative gradient
+            hist.clear();
+            d = g.iter().map(|x| -x).collect();
+            slope = -dot(&g, &g);
+        }
+
+        // backtracking line search with the Armijo condition
+        let mut step = 1.0;
+        let mut next = None;
+        for _ in 0..50 {
+            let mut q = p.clone();
+            q.iscaled_add(step, &d);
+            let (fq, gq) = fg(&q);
+            if fq <= fval + 1e-4 * step * slope {
+                next = Some((q, fq, gq));
+                break;
+            }
+            step *= 0.5;
+        }
+        let (q, fq, gq) = match next {
+            Some(n) => n,
+            None => {
+                line_search_failed = true;
+                break;
+            }
+        };
+
+        let s: Vec<f64> = q.iter().zip(p.iter()).map(|(a, b)| a - b).collect();
+        let y: Vec<f64> = gq.iter().zip(g.iter()).map(|(a, b)| a - b).collect();
+        let sy = dot(&s, &y);
+        if sy > 1e-12 {
+            if hist.len() == LBFGS_MEMORY {
+                hist.remove(0);
+            }
+            hist.push((s, y, 1.0 / sy));
+        }
+
+        p = q;
+        fval = fq;
+        g = gq;
+        r.push((p.clone(), fval));
+        report(progress, &ProgressInfo { algorithm: "l-bfgs", iteration: k + 1, max_iter: iter, loss: Some(fval) });
+        stopped = dot(&g, &g).sqrt() <= eps;
+    }
+
+    OptResult {
+        params: p,
+        fvals: r,
+        stopped: stopped,
+        line_search_failed: line_search_failed
     }
 }
 
 /// Plots the learning curve from an optimization result.
 ///
+/// Not available with the feature `pure-rust`.
+#[cfg(not(feature = "pure-rust"))]
 pub fn plot_learning_curve(r: &OptResult<f64>, w: &Window) -> Result<(String, String), &'static str> {
 
     let errors = r.fvals.iter().map(|&(_, ref y)| y).cloned().collect::<Vec<f64>>();
@@ -332,3 +821,23 @@ pub fn plot_learning_curve(r: &OptResult<f64>, w: &Window) -> Result<(String, St
         _ => Err("Could not run octave.")
     }
 }
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lbfgs_line_search_failed() {
+
+        let r = lbfgs(&|p: &[f64]| (p[0] * p[0], vec![2.0 * p[0]]), &[3.0], empty_opts());
+        assert!(r.stopped && !r.line_search_failed);
+
+        // the gradient has the wrong sign so that no step decreases the
+        // objective
+        let r = lbfgs(&|p: &[f64]| (p[0] * p[0], vec![-2.0 * p[0]]), &[3.0], empty_opts());
+        assert!(!r.stopped && r.line_search_failed);
+        assert_eq!(r.params, vec![3.0]);
+    }
+}
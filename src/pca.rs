@@ -0,0 +1,264 @@
+//! Principal component analysis on mini-batches.
+//!
+//! [IncrementalPca](struct.IncrementalPca.html) updates the mean and the
+//! scatter matrix of the data with each mini-batch and computes the
+//! principal components from the eigendecomposition of the covariance
+//! matrix. Only the current batch and a `d`x`d` matrix (where `d` is the
+//! number of features) are held in memory, i.e. the data can be streamed
+//! from disk (e.g. with a [ChunkSource](../stream/trait.ChunkSource.html)).
+//! The result is the same as the result of a PCA of all examples at once.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::pca::IncrementalPca;
+//!
+//! # fn main() {
+//! let mut p = IncrementalPca::new(1);
+//! p.partial_fit(&mat![1.0, 1.0; 2.0, 2.0]);
+//! p.partial_fit(&mat![3.0, 3.0; 4.0, 4.0]);
+//!
+//! // all points are on the diagonal
+//! let c = p.components().row(0).unwrap();
+//! assert!((c[0].abs() - 0.5f64.sqrt()).abs() < 1e-10);
+//! assert!((p.explained_variance_ratio()[0] - 1.0).abs() < 1e-10);
+//!
+//! let z = p.transform(&mat![5.0, 5.0]);
+//! assert!(p.inverse_transform(&z).similar(&mat![5.0, 5.0], 1e-10));
+//! # }
+//! ```
+use math::{Mean, Dimension, Normalization};
+use matrix::Matrix;
+use ops::MatrixMatrixOps;
+use linalg::{covariance, symmetric_eigen};
+use stream::ChunkSource;
+use io::model::{Model, Encoder, Decoder};
+
+/// PCA which is fitted incrementally on mini-batches.
+#[derive(Clone, Debug)]
+pub struct IncrementalPca {
+    n_components: usize,
+    n: usize,
+    mean: Vec<f64>,
+    scatter: Matrix<f64>,
+    components: Matrix<f64>,
+    variances: Vec<f64>,
+    total_variance: f64
+}
+
+impl IncrementalPca {
+
+    /// Creates an unfitted PCA which keeps `n_components` components.
+    ///
+    /// Panics if `n_components` is zero.
+    pub fn new(n_components: usize) -> IncrementalPca {
+
+        assert!(n_components > 0, "Number of components must be greater than zero.");
+        IncrementalPca {
+            n_components: n_components,
+            n: 0,
+            mean: vec![],
+            scatter: Matrix::new(),
+            components: Matrix::new(),
+            variances: vec![],
+            total_variance: 0.0
+        }
+    }
+
+    /// Updates the components with the examples in the rows of `x`.
+    ///
+    /// Panics if the number of columns differs from the number of columns
+    /// of the previous batches or if the number of components is larger
+    /// than the number of columns.
+    pub fn partial_fit(&mut self, x: &Matrix<f64>) {
+
+        if x.rows() == 0 {
+            return;
+        }
+        let d = x.cols();
+        if self.n == 0 {
+            assert!(self.n_components <= d, "Number of components must not exceed the number of features.");
+            self.mean = vec![0.0; d];
+            self.scatter = Matrix::fill(0.0, d, d);
+        }
+        assert!(d == self.mean.len(), "Number of columns does not match the number of features.");
+
+        // combines the mean and the scatter matrix of the previous examples
+        // with those of the batch (Chan et al.)
+        let m = x.rows() as f64;
+        let n = self.n as f64;
+        let batch_mean = x.mean(Dimension::Column);
+        let batch_scatter = covariance(x, Normalization::N);
+        let delta: Vec<f64> = batch_mean.iter().zip(self.mean.iter()).map(|(b, a)| b - a).collect();
+        let f = n * m / (n + m);
+        for i in 0..d {
+            for j in 0..d {
+                let s = self.scatter.get_mut(i, j).unwrap();
+                *s += m * batch_scatter.get(i, j).unwrap() + f * delta[i] * delta[j];
+            }
+        }
+        for i in 0..d {
+            self.mean[i] += delta[i] * m / (n + m);
+        }
+        self.n += x.rows();
+        self.update_components();
+    }
+
+    /// Fits the PCA on all chunks of `source`.
+    ///
+    /// Returns an error if a chunk cannot be read.
+    pub fn fit_source<S: ChunkSource + ?Sized>(&mut self, source: &mut S) -> Result<(), String> {
+
+        try!(source.rewind());
+        while let Some(chunk) = source.next_chunk() {
+            self.partial_fit(&try!(chunk));
+        }
+        Ok(())
+    }
+
+    fn update_components(&mut self) {
+
+        let d = self.mean.len();
+        let n = self.n as f64;
+        let c = Matrix::from_it(self.scatter.iter().map(|x| x / n), d);
+        let (l, u) = symmetric_eigen(&c);
+
+        self.total_variance = l.iter().map(|x| x.max(0.0)).sum();
+        self.variances = l.iter().take(self.n_components).map(|x| x.max(0.0)).collect();
+        let mut v = Vec::with_capacity(self.n_components * d);
+        for i in 0..self.n_components {
+            v.extend(u.col(i).unwrap());
+        }
+        self.components = Matrix::from_vec(v, self.n_components, d);
+    }
+
+    /// Returns the number of examples seen so far.
+    pub fn n_samples(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the mean of the examples seen so far.
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// Returns the principal components (unit length) in the rows, sorted
+    /// by decreasing variance.
+    pub fn components(&self) -> &Matrix<f64> {
+        &self.components
+    }
+
+    /// Returns the variance of the data along each component (the
+    /// covariance matrix is normalized by the number of examples).
+    pub fn explained_variance(&self) -> &[f64] {
+        &self.variances
+    }
+
+    /// Returns the fraction of the total variance which is explained by
+    /// each component.
+    pub fn explained_variance_ratio(&self) -> Vec<f64> {
+
+        let t = if self.total_variance > 0.0 { self.total_variance } else { 1.0 };
+        self.variances.iter().map(|v| v / t).collect()
+    }
+
+    /// Projects the rows of `x` onto the principal components.
+    ///
+    /// Panics if the PCA is not fitted or if the number of columns does not
+    /// match the number of features.
+    pub fn transform(&self, x: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(self.n > 0, "PCA is not fitted.");
+        assert!(x.cols() == self.mean.len(), "Number of columns does not match the number of features.");
+
+        let d = self.mean.len();
+        let c = Matrix::from_it(x.iter().enumerate().map(|(k, v)| v - self.mean[k % d]), d);
+        c.mul(&self.components, false, true)
+    }
+
+    /// Maps projected rows back into the original space.
+    ///
+    /// Panics if the PCA is not fitted or if the number of columns is not
+    /// the number of components.
+    pub fn inverse_transform(&self, z: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(self.n > 0, "PCA is not fitted.");
+        assert!(z.cols() == self.n_components, "Number of columns does not match the number of components.");
+
+        let d = self.mean.len();
+        let r = z.mul(&self.components, false, false);
+        Matrix::from_it(r.iter().enumerate().map(|(k, v)| v + self.mean[k % d]), d)
+    }
+}
+
+impl Model for IncrementalPca {
+
+    fn tag() -> &'static str { "rustml.pca.IncrementalPca" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.n_components);
+        e.usize(self.n);
+        e.f64s(&self.mean);
+        e.matrix(&self.scatter);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<IncrementalPca, String> {
+
+        let k = try!(d.usize());
+        if k == 0 {
+            return Err("Number of components must be greater than zero.".to_string());
+        }
+        let mut p = IncrementalPca::new(k);
+        p.n = try!(d.usize());
+        p.mean = try!(d.f64s());
+        p.scatter = try!(d.matrix());
+        if p.n > 0 {
+            if p.scatter.rows() != p.mean.len() || p.scatter.cols() != p.mean.len() || p.n_components > p.mean.len() {
+                return Err("Dimensions of the scatter matrix do not match.".to_string());
+            }
+            p.update_components();
+        }
+        Ok(p)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use stream::MatrixChunks;
+    use io::model::{write_model, read_model};
+
+    #[test]
+    fn test_incremental_pca() {
+
+        let x = Matrix::from_vec((0..60).map(|i| ((i * 37 % 23) as f64 / 7.0) * if i % 3 == 0 { 2.0 } else { 1.0 }).collect(), 20, 3);
+
+        let mut p = IncrementalPca::new(2);
+        p.fit_source(&mut MatrixChunks::new(&x, 6)).unwrap();
+        assert_eq!(p.n_samples(), 20);
+        assert!(p.mean().similar(&x.mean(Dimension::Column), 1e-10));
+
+        // same result as the PCA of all examples
+        let (l, u) = symmetric_eigen(&covariance(&x, Normalization::N));
+        assert!(p.explained_variance().similar(&l[..2], 1e-8));
+        for i in 0..2 {
+            let a = p.components().row(i).unwrap();
+            let b = u.col(i).unwrap();
+            let s = if a[0] * b[0] < 0.0 { -1.0 } else { 1.0 };
+            assert!(a.iter().zip(b.iter()).all(|(x, y)| (x - s * y).abs() < 1e-8));
+        }
+        let r = p.explained_variance_ratio();
+        assert!(r[0] >= r[1] && r[0] + r[1] <= 1.0 + 1e-12);
+        assert_eq!(p.transform(&x).cols(), 2);
+
+        let mut buf = vec![];
+        write_model(&mut buf, &p).unwrap();
+        let q: IncrementalPca = read_model(&buf[..]).unwrap();
+        assert!(q.components().similar(p.components(), 1e-12));
+    }
+}
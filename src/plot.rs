@@ -0,0 +1,519 @@
+//! Module to create plots with gnuplot.
+//!
+//! A [Plot](struct.Plot.html) is created with a builder and consists of one
+//! or more series (scatter plots, lines, histograms and heatmaps) which are
+//! drawn into the same axes. The plot is rendered by
+//! [gnuplot](http://www.gnuplot.info/) (version 5 or newer) into a PNG or
+//! SVG file. The data is embedded into the gnuplot script, i.e. no temporary
+//! files are created.
+//!
+//! The functions [meshgrid](fn.meshgrid.html) and
+//! [decision_surface](fn.decision_surface.html) evaluate a classifier which
+//! implements the [Estimator](../model_selection/trait.Estimator.html) trait
+//! on a two-dimensional grid, e.g. to plot its decision boundary with
+//! [Plot::surface](struct.Plot.html#method.surface).
+//!
+//! # Example
+//!
+//! The following example plots a toy dataset together with the decision
+//! boundary of a classifier.
+//!
+//! ```no_run
+//! use rustml::plot::Plot;
+//! use rustml::datasets::make_moons;
+//!
+//! let (x, y) = make_moons(200, 0.1, [1, 2, 3, 4]);
+//! let a: Vec<usize> = (0..200).filter(|&i| y[i] == 0).collect();
+//! let b: Vec<usize> = (0..200).filter(|&i| y[i] == 1).collect();
+//! let col = |idx: &[usize], c: usize| idx.iter().map(|&i| *x.get(i, c).unwrap()).collect::<Vec<f64>>();
+//!
+//! Plot::new()
+//!     .title("two moons")
+//!     .decision_boundary((-1.5, 2.5), (-1.0, 1.5), 0.02, |p| if p[1] > 0.25 { 0.0 } else { 1.0 })
+//!     .scatter(&col(&a, 0), &col(&a, 1), "class 0")
+//!     .scatter(&col(&b, 0), &col(&b, 1), "class 1")
+//!     .save("/tmp/moons.png")
+//!     .unwrap();
+//! ```
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use matrix::Matrix;
+use model_selection::Estimator;
+
+static DEFAULT_GNUPLOT_BIN: &'static str = "gnuplot";
+
+/// Output format of a plot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Png,
+    Svg
+}
+
+impl Format {
+
+    /// Returns the format for the extension of a file name (`.png` or
+    /// `.svg`).
+    pub fn from_path(path: &str) -> Option<Format> {
+
+        let p = path.to_lowercase();
+        if p.ends_with(".png") {
+            Some(Format::Png)
+        } else if p.ends_with(".svg") {
+            Some(Format::Svg)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Series {
+    Points(Vec<f64>, Vec<f64>, String),
+    Lines(Vec<f64>, Vec<f64>, String),
+    // left edges of the bins, width of the bins, counts
+    Boxes(Vec<f64>, f64, Vec<f64>, String),
+    // values, x range, y range
+    Heatmap(Matrix<f64>, (f64, f64), (f64, f64))
+}
+
+/// Coordinates of the points of a regular two-dimensional grid.
+///
+/// The element in row `i` and column `j` of `x` (`y`) is the x-coordinate
+/// (y-coordinate) of the point in row `i` and column `j` of the grid. The x
+/// values increase along the columns and the y values along the rows.
+#[derive(Clone, Debug)]
+pub struct Meshgrid {
+    pub x: Matrix<f64>,
+    pub y: Matrix<f64>
+}
+
+impl Meshgrid {
+
+    /// Returns the number of rows of the grid, i.e. the number of distinct
+    /// y values.
+    pub fn rows(&self) -> usize {
+        self.x.rows()
+    }
+
+    /// Returns the number of columns of the grid, i.e. the number of
+    /// distinct x values.
+    pub fn cols(&self) -> usize {
+        self.x.cols()
+    }
+
+    /// Returns the smallest and the largest x value.
+    pub fn x_range(&self) -> (f64, f64) {
+        (self.x.buf()[0], self.x.buf()[self.cols() - 1])
+    }
+
+    /// Returns the smallest and the largest y value.
+    pub fn y_range(&self) -> (f64, f64) {
+        (self.y.buf()[0], self.y.buf()[self.y.buf().len() - 1])
+    }
+
+    /// Returns a matrix with two columns which contains the x and y
+    /// coordinates of all points of the grid row by row.
+    pub fn points(&self) -> Matrix<f64> {
+        Matrix::from_it(
+            self.x.iter().zip(self.y.iter()).flat_map(|(&a, &b)| vec![a, b]),
+            2
+        )
+    }
+}
+
+/// Creates a grid which covers `x_range` and `y_range` with the distance
+/// `step` between neighboured points.
+///
+/// The first point is `(x_range.0, y_range.0)`. The last x (y) value is the
+/// largest value which does not exceed `x_range.1` (`y_range.1`).
+///
+/// Panics if `step` is not positive or if a range is empty.
+///
+/// # Example
+///
+/// ```
+/// use rustml::plot::meshgrid;
+///
+/// let g = meshgrid((0.0, 1.0), (0.0, 0.5), 0.5);
+/// assert_eq!(g.x.buf(), &vec![0.0, 0.5, 1.0, 0.0, 0.5, 1.0]);
+/// assert_eq!(g.y.buf(), &vec![0.0, 0.0, 0.0, 0.5, 0.5, 0.5]);
+/// ```
+pub fn meshgrid(x_range: (f64, f64), y_range: (f64, f64), step: f64) -> Meshgrid {
+
+    assert!(step > 0.0, "Step must be greater than zero.");
+    assert!(x_range.0 <= x_range.1 && y_range.0 <= y_range.1, "Invalid range.");
+
+    // a small epsilon avoids losing the last point due to rounding errors
+    let cols = ((x_range.1 - x_range.0) / step + 1e-9).floor() as usize + 1;
+    let rows = ((y_range.1 - y_range.0) / step + 1e-9).floor() as usize + 1;
+    let xs: Vec<f64> = (0..cols).map(|j| x_range.0 + j as f64 * step).collect();
+    Meshgrid {
+        x: Matrix::from_it((0..rows).flat_map(|_| xs.clone()), cols),
+        y: Matrix::from_it((0..rows * cols).map(|k| y_range.0 + (k / cols) as f64 * step), cols)
+    }
+}
+
+/// Evaluates a classifier at each point of a grid.
+///
+/// Returns a matrix with the same dimensions as the grid that contains the
+/// prediction of `estimator` for each point of the grid. Together with the
+/// grid the result can be used to plot the decision boundary of the
+/// classifier (see [Plot::surface](struct.Plot.html#method.surface)).
+///
+/// # Example
+///
+/// ```
+/// use rustml::plot::*;
+/// use rustml::model_selection::Estimator;
+/// use rustml::matrix::Matrix;
+///
+/// struct Threshold;
+///
+/// impl Estimator<f64, usize> for Threshold {
+///     fn fit(&mut self, _x: &Matrix<f64>, _y: &[usize]) { }
+///     fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+///         x.row_iter().map(|r| if r[0] + r[1] > 1.0 { 1 } else { 0 }).collect()
+///     }
+/// }
+///
+/// let g = meshgrid((0.0, 1.0), (0.0, 1.0), 0.5);
+/// let z = decision_surface(&Threshold, &g);
+/// assert_eq!(z.buf(), &vec![0, 0, 0, 0, 0, 1, 0, 1, 1]);
+///
+/// let p = Plot::new().surface(&g, &z.map(|&c| c as f64));
+/// ```
+pub fn decision_surface<E, L>(estimator: &E, grid: &Meshgrid) -> Matrix<L>
+    where E: Estimator<f64, L>, L: Clone {
+
+    let v = estimator.predict(&grid.points());
+    Matrix::from_vec(v, grid.rows(), grid.cols())
+}
+
+/// A plot which is rendered with gnuplot.
+#[derive(Clone, Debug)]
+pub struct Plot {
+    title: Option<String>,
+    xlabel: Option<String>,
+    ylabel: Option<String>,
+    xrange: Option<(f64, f64)>,
+    yrange: Option<(f64, f64)>,
+    size: (usize, usize),
+    series: Vec<Series>,
+    gnuplot_bin: String
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Plot {
+
+    /// Creates an empty plot with a size of 640x480.
+    pub fn new() -> Plot {
+        Plot {
+            title: None,
+            xlabel: None,
+            ylabel: None,
+            xrange: None,
+            yrange: None,
+            size: (640, 480),
+            series: vec![],
+            gnuplot_bin: DEFAULT_GNUPLOT_BIN.to_string()
+        }
+    }
+
+    fn add(&self, s: Series) -> Plot {
+        let mut series = self.series.clone();
+        series.push(s);
+        Plot { series: series, .. self.clone() }
+    }
+
+    /// Sets the title of the plot.
+    pub fn title(&self, title: &str) -> Plot {
+        Plot { title: Some(title.to_string()), .. self.clone() }
+    }
+
+    /// Sets the label of the x-axis.
+    pub fn xlabel(&self, label: &str) -> Plot {
+        Plot { xlabel: Some(label.to_string()), .. self.clone() }
+    }
+
+    /// Sets the label of the y-axis.
+    pub fn ylabel(&self, label: &str) -> Plot {
+        Plot { ylabel: Some(label.to_string()), .. self.clone() }
+    }
+
+    /// Sets the range of the x-axis. By default the range is determined by
+    /// gnuplot.
+    pub fn xrange(&self, min: f64, max: f64) -> Plot {
+        Plot { xrange: Some((min, max)), .. self.clone() }
+    }
+
+    /// Sets the range of the y-axis. By default the range is determined by
+    /// gnuplot.
+    pub fn yrange(&self, min: f64, max: f64) -> Plot {
+        Plot { yrange: Some((min, max)), .. self.clone() }
+    }
+
+    /// Sets the size of the plot in pixels.
+    pub fn size(&self, width: usize, height: usize) -> Plot {
+        Plot { size: (width, height), .. self.clone() }
+    }
+
+    /// Sets the path to the gnuplot binary (default is `gnuplot`).
+    pub fn gnuplot_bin(&self, path: &str) -> Plot {
+        Plot { gnuplot_bin: path.to_string(), .. self.clone() }
+    }
+
+    /// Adds a scatter plot of the points `(x[i], y[i])`.
+    ///
+    /// Panics if `x` and `y` have different lengths.
+    pub fn scatter(&self, x: &[f64], y: &[f64], label: &str) -> Plot {
+
+        assert!(x.len() == y.len(), "Number of x and y values must be equal.");
+        self.add(Series::Points(x.to_vec(), y.to_vec(), label.to_string()))
+    }
+
+    /// Adds a scatter plot of the points in the rows of `m` where the first
+    /// column contains the x values and the second column the y values.
+    ///
+    /// Panics if `m` has less than two columns.
+    pub fn scatter_matrix(&self, m: &Matrix<f64>, label: &str) -> Plot {
+
+        assert!(m.cols() >= 2, "Matrix must have at least two columns.");
+        self.scatter(&m.col(0).unwrap(), &m.col(1).unwrap(), label)
+    }
+
+    /// Adds a line through the points `(x[i], y[i])`.
+    ///
+    /// Panics if `x` and `y` have different lengths.
+    pub fn line(&self, x: &[f64], y: &[f64], label: &str) -> Plot {
+
+        assert!(x.len() == y.len(), "Number of x and y values must be equal.");
+        self.add(Series::Lines(x.to_vec(), y.to_vec(), label.to_string()))
+    }
+
+    /// Adds a histogram of `values` with `bins` bins of equal width between
+    /// the smallest and the largest value. `NaN` values are ignored.
+    ///
+    /// Panics if `bins` is zero.
+    pub fn histogram(&self, values: &[f64], bins: usize, label: &str) -> Plot {
+
+        assert!(bins > 0, "Number of bins must be greater than zero.");
+
+        let v: Vec<f64> = values.iter().cloned().filter(|x| !x.is_nan()).collect();
+        let min = v.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (min, max) = if v.is_empty() { (0.0, 1.0) } else { (min, max) };
+        let width = if max > min { (max - min) / bins as f64 } else { 1.0 };
+
+        let mut counts = vec![0.0; bins];
+        for x in v {
+            let i = ((x - min) / width) as usize;
+            counts[i.min(bins - 1)] += 1.0;
+        }
+        let edges = (0..bins).map(|i| min + i as f64 * width).collect();
+        self.add(Series::Boxes(edges, width, counts, label.to_string()))
+    }
+
+    /// Adds a heatmap of the matrix `m`. The element in row `i` and column
+    /// `j` is drawn at the position `(x, y)` where `x` is mapped linearly from
+    /// the column index into `x_range` and `y` from the row index into
+    /// `y_range`, i.e. the first row is at the bottom.
+    pub fn heatmap(&self, m: &Matrix<f64>, x_range: (f64, f64), y_range: (f64, f64)) -> Plot {
+        self.add(Series::Heatmap(m.clone(), x_range, y_range))
+    }
+
+    /// Adds the values `z` which have been evaluated at the points of the grid
+    /// `grid` (e.g. via [decision_surface](fn.decision_surface.html)) as
+    /// heatmap.
+    ///
+    /// Panics if the dimensions of `z` and `grid` are different.
+    pub fn surface(&self, grid: &Meshgrid, z: &Matrix<f64>) -> Plot {
+
+        assert!(z.rows() == grid.rows() && z.cols() == grid.cols(), "Dimensions of grid and values must be equal.");
+        self.heatmap(z, grid.x_range(), grid.y_range())
+    }
+
+    /// Adds the decision surface of a classifier as heatmap.
+    ///
+    /// The function `f` is evaluated at each point of a grid with the
+    /// distance `step` between neighboured points which covers `x_range` and
+    /// `y_range` (see [meshgrid](fn.meshgrid.html)). Usually `f` returns the
+    /// predicted class of a point.
+    ///
+    /// Panics if `step` is not positive or if a range is empty.
+    pub fn decision_boundary<F>(&self, x_range: (f64, f64), y_range: (f64, f64), step: f64, f: F) -> Plot
+        where F: Fn(&[f64]) -> f64 {
+
+        let grid = meshgrid(x_range, y_range, step);
+        let v = grid.points().row_iter().map(|p| f(p)).collect();
+        self.surface(&grid, &Matrix::from_vec(v, grid.rows(), grid.cols()))
+    }
+
+    /// Returns the gnuplot script which renders the plot into the file
+    /// `path` with the given format.
+    pub fn to_script(&self, path: &str, format: Format) -> String {
+
+        let mut s = String::new();
+        let (w, h) = self.size;
+        s.push_str(&match format {
+            Format::Png => format!("set terminal pngcairo size {},{}\n", w, h),
+            Format::Svg => format!("set terminal svg size {},{}\n", w, h)
+        });
+        s.push_str(&format!("set output {}\n", quote(path)));
+        if let Some(ref t) = self.title {
+            s.push_str(&format!("set title {}\n", quote(t)));
+        }
+        if let Some(ref l) = self.xlabel {
+            s.push_str(&format!("set xlabel {}\n", quote(l)));
+        }
+        if let Some(ref l) = self.ylabel {
+            s.push_str(&format!("set ylabel {}\n", quote(l)));
+        }
+        if let Some((a, b)) = self.xrange {
+            s.push_str(&format!("set xrange [{:?}:{:?}]\n", a, b));
+        }
+        if let Some((a, b)) = self.yrange {
+            s.push_str(&format!("set yrange [{:?}:{:?}]\n", a, b));
+        }
+        s.push_str("set grid\n");
+
+        let mut cmds = vec![];
+        for (i, series) in self.series.iter().enumerate() {
+            s.push_str(&format!("$data{} << EOD\n", i));
+            let (style, label) = match *series {
+                Series::Points(ref x, ref y, ref l) | Series::Lines(ref x, ref y, ref l) => {
+                    for (a, b) in x.iter().zip(y.iter()) {
+                        s.push_str(&format!("{:?} {:?}\n", a, b));
+                    }
+                    let style = match *series { Series::Points(..) => "with points pt 7", _ => "with lines lw 2" };
+                    (style.to_string(), l.clone())
+                },
+                Series::Boxes(ref edges, width, ref counts, ref l) => {
+                    for (e, c) in edges.iter().zip(counts.iter()) {
+                        s.push_str(&format!("{:?} {:?}\n", e + width / 2.0, c));
+                    }
+                    (format!("using 1:2:({:?}) with boxes fill solid 0.5", width), l.clone())
+                },
+                Series::Heatmap(ref m, (x0, x1), (y0, y1)) => {
+                    let dx = if m.cols() > 1 { (x1 - x0) / (m.cols() - 1) as f64 } else { 0.0 };
+                    let dy = if m.rows() > 1 { (y1 - y0) / (m.rows() - 1) as f64 } else { 0.0 };
+                    for (r, row) in m.row_iter().enumerate() {
+                        for (c, v) in row.iter().enumerate() {
+                            s.push_str(&format!("{:?} {:?} {:?}\n", x0 + c as f64 * dx, y0 + r as f64 * dy, v));
+                        }
+                    }
+                    ("with image".to_string(), String::new())
+                }
+            };
+            s.push_str("EOD\n");
+            let title = if label.is_empty() { "notitle".to_string() } else { format!("title {}", quote(&label)) };
+            cmds.push(format!("$data{} {} {}", i, style, title));
+        }
+        if !cmds.is_empty() {
+            s.push_str(&format!("plot {}\n", cmds.join(", \\\n     ")));
+        }
+        s
+    }
+
+    /// Renders the plot with gnuplot into the file `path`. The format is
+    /// determined by the extension of the file name (`.png` or `.svg`).
+    pub fn save(&self, path: &str) -> Result<(), String> {
+
+        let format = match Format::from_path(path) {
+            Some(f) => f,
+            None => return Err(format!("Unknown format of file {}.", path))
+        };
+        let script = self.to_script(path, format);
+
+        let mut child = try!(Command::new(&self.gnuplot_bin)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not run {}: {}", self.gnuplot_bin, e)));
+        try!(child.stdin.take().unwrap().write_all(script.as_bytes())
+            .map_err(|e| format!("Could not write to gnuplot: {}", e)));
+        let out = try!(child.wait_with_output().map_err(|e| format!("Gnuplot failed: {}", e)));
+        if !out.status.success() {
+            return Err(format!("Gnuplot failed: {}", String::from_utf8_lossy(&out.stderr)));
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_plot_script() {
+
+        let s = Plot::new()
+            .title("a \"b\"")
+            .xlabel("x")
+            .xrange(0.0, 2.0)
+            .size(300, 200)
+            .scatter(&[1.0, 2.0], &[3.0, 4.0], "points")
+            .line(&[0.0, 1.0], &[0.0, 1.0], "")
+            .to_script("/tmp/p.svg", Format::Svg);
+
+        assert!(s.starts_with("set terminal svg size 300,200\nset output \"/tmp/p.svg\"\n"));
+        assert!(s.contains("set title \"a \\\"b\\\"\"\n"));
+        assert!(s.contains("set xrange [0.0:2.0]\n"));
+        assert!(s.contains("$data0 << EOD\n1.0 3.0\n2.0 4.0\nEOD\n"));
+        assert!(s.contains("plot $data0 with points pt 7 title \"points\", \\\n     $data1 with lines lw 2 notitle\n"));
+    }
+
+    #[test]
+    fn test_histogram() {
+
+        let s = Plot::new().histogram(&[0.0, 1.0, 1.5, 4.0, f64::NAN], 2, "h").to_script("a.png", Format::Png);
+        assert!(s.starts_with("set terminal pngcairo"));
+        assert!(s.contains("$data0 << EOD\n1.0 3.0\n3.0 1.0\nEOD\n"));
+        assert!(s.contains("using 1:2:(2.0) with boxes"));
+    }
+
+    #[test]
+    fn test_heatmap_decision_boundary() {
+
+        let s = Plot::new().heatmap(&mat![1.0, 2.0; 3.0, 4.0], (0.0, 1.0), (10.0, 20.0)).to_script("a.png", Format::Png);
+        assert!(s.contains("0.0 10.0 1.0\n1.0 10.0 2.0\n0.0 20.0 3.0\n1.0 20.0 4.0\n"));
+
+        let p = Plot::new().decision_boundary((0.0, 1.0), (0.0, 0.5), 0.5, |x| if x[0] > 0.5 { 1.0 } else { 0.0 });
+        let s = p.to_script("a.png", Format::Png);
+        assert!(s.contains("0.0 0.0 0.0\n0.5 0.0 0.0\n1.0 0.0 1.0\n0.0 0.5 0.0\n"));
+        assert!(s.contains("with image notitle"));
+    }
+
+    #[test]
+    fn test_meshgrid() {
+
+        let g = meshgrid((-1.0, 0.0), (0.0, 0.3), 0.1);
+        assert_eq!((g.rows(), g.cols()), (4, 11));
+        assert_eq!(g.x_range(), (-1.0, -1.0 + 10.0 * 0.1));
+        assert_eq!(g.y_range(), (0.0, 3.0 * 0.1));
+
+        let p = meshgrid((0.0, 1.0), (2.0, 3.0), 1.0).points();
+        assert!(p.eq(&mat![0.0, 2.0; 1.0, 2.0; 0.0, 3.0; 1.0, 3.0]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_surface_invalid_dimensions() {
+        Plot::new().surface(&meshgrid((0.0, 1.0), (0.0, 1.0), 0.5), &mat![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_save_errors() {
+
+        assert_eq!(Format::from_path("a.SVG"), Some(Format::Svg));
+        assert!(Plot::new().save("/tmp/plot.jpg").is_err());
+        assert!(Plot::new().gnuplot_bin("/nonexisting/gnuplot").save("/tmp/plot.png").is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! Module to expand features into polynomial and interaction features.
+//!
+//! Linear models can only learn linear relationships between the features
+//! and the target. By adding products of the features as additional
+//! features (e.g. `x0^2`, `x0 x1` and `x1^2` for two features `x0` and `x1`
+//! and degree 2) a linear model can also learn non-linear relationships.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::polynomial::PolynomialFeatures;
+//!
+//! # fn main() {
+//! let m = mat![
+//!     2.0, 3.0;
+//!     1.0, 4.0
+//! ];
+//! let p = PolynomialFeatures::new(2);
+//!
+//! assert_eq!(p.feature_names(&["a", "b"]), vec!["1", "a", "b", "a^2", "a b", "b^2"]);
+//! assert_eq!(p.transform(&m), mat![
+//!     1.0, 2.0, 3.0, 4.0, 6.0, 9.0;
+//!     1.0, 1.0, 4.0, 1.0, 4.0, 16.0
+//! ]);
+//! # }
+//! ```
+extern crate num;
+
+use self::num::traits::Float;
+
+use matrix::Matrix;
+use io::model::{Model, Encoder, Decoder};
+
+/// Generates polynomial and interaction features up to a given degree.
+///
+/// For the degree `d` the output contains all products of at most `d`
+/// input features. The columns are ordered by degree; columns with the
+/// same degree are ordered lexicographically by the indexes of the input
+/// features, i.e. for two features and degree 2 the columns are
+/// `1, x0, x1, x0^2, x0 x1, x1^2`.
+#[derive(Clone, Debug)]
+pub struct PolynomialFeatures {
+    degree: usize,
+    bias: bool,
+    interaction_only: bool
+}
+
+impl PolynomialFeatures {
+
+    /// Creates a transformer for polynomial features up to degree `degree`
+    /// including the bias column (i.e. a column in which all values are 1).
+    ///
+    /// Panics if `degree` is zero.
+    pub fn new(degree: usize) -> PolynomialFeatures {
+
+        assert!(degree > 0, "Degree must be at least 1.");
+
+        PolynomialFeatures {
+            degree: degree,
+            bias: true,
+            interaction_only: false
+        }
+    }
+
+    /// Returns a transformer which does not create the bias column.
+    pub fn without_bias(&self) -> PolynomialFeatures {
+        PolynomialFeatures {
+            bias: false,
+            .. self.clone()
+        }
+    }
+
+    /// Returns a transformer which only creates products of distinct
+    /// features, i.e. `x0 x1` but not `x0^2`.
+    pub fn interaction_only(&self) -> PolynomialFeatures {
+        PolynomialFeatures {
+            interaction_only: true,
+            .. self.clone()
+        }
+    }
+
+    /// Returns the indexes of the input features that are multiplied for
+    /// each output column. The bias column is represented by an empty
+    /// vector.
+    pub fn powers(&self, n_features: usize) -> Vec<Vec<usize>> {
+
+        let mut r: Vec<Vec<usize>> = Vec::new();
+        if self.bias {
+            r.push(vec![]);
+        }
+
+        // combinations (with replacement if interaction_only is false) of
+        // the previous degree are extended by an index which is not smaller
+        // (larger if interaction_only is true) than their last index
+        let mut prev: Vec<Vec<usize>> = vec![vec![]];
+        for _ in 0..self.degree {
+            let mut cur = Vec::new();
+            for c in &prev {
+                let start = match c.last() {
+                    None => 0,
+                    Some(&l) if self.interaction_only => l + 1,
+                    Some(&l) => l
+                };
+                for i in start..n_features {
+                    let mut v = c.clone();
+                    v.push(i);
+                    cur.push(v);
+                }
+            }
+            r.extend(cur.iter().cloned());
+            prev = cur;
+        }
+        r
+    }
+
+    /// Returns the number of output columns for `n_features` input
+    /// features.
+    pub fn output_size(&self, n_features: usize) -> usize {
+        self.powers(n_features).len()
+    }
+
+    /// Returns the names of the output columns given the names of the input
+    /// features.
+    ///
+    /// The bias column is named `1`, a product of features is denoted by
+    /// the names of the features separated by a space and powers are
+    /// denoted with `^`, e.g. `a^2 b`.
+    pub fn feature_names(&self, names: &[&str]) -> Vec<String> {
+
+        self.powers(names.len()).iter()
+            .map(|c| {
+                if c.is_empty() {
+                    return "1".to_string();
+                }
+                let mut parts: Vec<String> = Vec::new();
+                let mut i = 0;
+                while i < c.len() {
+                    let n = c[i..].iter().take_while(|&&x| x == c[i]).count();
+                    parts.push(match n {
+                        1 => names[c[i]].to_string(),
+                        _ => format!("{}^{}", names[c[i]], n)
+                    });
+                    i += n;
+                }
+                parts.join(" ")
+            })
+            .collect()
+    }
+
+    /// Expands the features of each row of `m`.
+    pub fn transform<T: Float>(&self, m: &Matrix<T>) -> Matrix<T> {
+
+        let powers = self.powers(m.cols());
+        let mut v = Vec::with_capacity(m.rows() * powers.len());
+        for row in m.row_iter() {
+            for c in &powers {
+                v.push(c.iter().fold(T::one(), |acc, &i| acc * row[i]));
+            }
+        }
+        Matrix::from_vec(v, m.rows(), powers.len())
+    }
+}
+
+impl Model for PolynomialFeatures {
+
+    fn tag() -> &'static str { "rustml.polynomial.PolynomialFeatures" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.degree);
+        e.bool(self.bias);
+        e.bool(self.interaction_only);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<PolynomialFeatures, String> {
+        Ok(PolynomialFeatures {
+            degree: try!(d.usize()),
+            bias: try!(d.bool()),
+            interaction_only: try!(d.bool())
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_polynomial_features() {
+
+        let m = mat![2.0, 3.0, 5.0];
+        let p = PolynomialFeatures::new(3).without_bias();
+
+        assert_eq!(p.output_size(3), 19);
+        assert_eq!(p.output_size(1), 3);
+
+        let r = p.transform(&m);
+        assert_eq!(r.cols(), 19);
+        assert_eq!(r.row(0).unwrap()[..9].to_vec(),
+            vec![2.0, 3.0, 5.0, 4.0, 6.0, 10.0, 9.0, 15.0, 25.0]);
+        assert_eq!(r.get(0, 18), Some(&125.0));
+    }
+
+    #[test]
+    fn test_interaction_only() {
+
+        let p = PolynomialFeatures::new(3).interaction_only();
+        assert_eq!(p.feature_names(&["a", "b", "c"]),
+            vec!["1", "a", "b", "c", "a b", "a c", "b c", "a b c"]);
+        assert_eq!(p.transform(&mat![2.0, 3.0, 5.0]),
+            mat![1.0, 2.0, 3.0, 5.0, 6.0, 10.0, 15.0, 30.0]);
+    }
+
+    #[test]
+    fn test_feature_names() {
+
+        let p = PolynomialFeatures::new(3).without_bias();
+        let n = p.feature_names(&["x0", "x1"]);
+        assert_eq!(n, vec!["x0", "x1", "x0^2", "x0 x1", "x1^2", "x0^3", "x0^2 x1", "x0 x1^2", "x1^3"]);
+    }
+
+    #[test]
+    fn test_degree_one() {
+
+        let m = mat![1.0, 2.0; 3.0, 4.0];
+        assert_eq!(PolynomialFeatures::new(1).without_bias().transform(&m), m);
+    }
+}
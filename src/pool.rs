@@ -0,0 +1,195 @@
+//! Pool of buffers for temporary vectors and matrices.
+//!
+//! Training algorithms like the backpropagation of
+//! [neural networks](../nn/index.html) need many temporary vectors of the
+//! same sizes for each example and each iteration. Instead of allocating
+//! and freeing them again and again they can be borrowed from a
+//! [BufferPool](struct.BufferPool.html) and returned to it when they are no
+//! longer needed.
+//!
+//! Each thread has its own pool which is used by the algorithms of this
+//! library and which can also be used by user code via
+//! [with_pool](fn.with_pool.html).
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::pool::BufferPool;
+//!
+//! let mut pool = BufferPool::new();
+//! for _ in 0..100 {
+//!     let mut v = pool.take(1000);
+//!     v[0] = 1.0;
+//!     pool.give(v);
+//! }
+//! // only the first buffer had to be allocated
+//! assert_eq!(pool.allocations(), 1);
+//! ```
+use std::cell::RefCell;
+
+use matrix::Matrix;
+
+/// Default maximum number of buffers which are kept by a pool.
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// A pool of buffers of `f64` values.
+#[derive(Clone, Debug)]
+pub struct BufferPool {
+    free: Vec<Vec<f64>>,
+    capacity: usize,
+    allocations: usize
+}
+
+impl BufferPool {
+
+    /// Creates an empty pool which keeps at most
+    /// [DEFAULT_CAPACITY](constant.DEFAULT_CAPACITY.html) buffers.
+    pub fn new() -> BufferPool {
+        BufferPool::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Creates an empty pool which keeps at most `n` buffers. Buffers which
+    /// are returned to a full pool are freed.
+    pub fn with_capacity(n: usize) -> BufferPool {
+        BufferPool {
+            free: vec![],
+            capacity: n,
+            allocations: 0
+        }
+    }
+
+    /// Returns a vector of `len` zeros.
+    ///
+    /// The memory of the smallest buffer in the pool with a sufficient
+    /// capacity is reused if such a buffer exists.
+    pub fn take(&mut self, len: usize) -> Vec<f64> {
+
+        // the smallest buffer which is large enough
+        let best = self.free.iter().enumerate()
+            .filter(|&(_, b)| b.capacity() >= len)
+            .min_by_key(|&(_, b)| b.capacity())
+            .map(|(i, _)| i);
+
+        let mut v = match best {
+            Some(i) => self.free.swap_remove(i),
+            None => {
+                self.allocations += 1;
+                // grow a buffer of the pool instead of keeping it unused
+                match self.free.pop() {
+                    Some(mut b) => { b.reserve(len); b },
+                    None => Vec::with_capacity(len)
+                }
+            }
+        };
+        v.clear();
+        v.resize(len, 0.0);
+        v
+    }
+
+    /// Returns a vector which contains a copy of `x`.
+    pub fn copy_of(&mut self, x: &[f64]) -> Vec<f64> {
+        let mut v = self.take(x.len());
+        v.copy_from_slice(x);
+        v
+    }
+
+    /// Returns a `rows`x`cols` matrix of zeros.
+    pub fn take_matrix(&mut self, rows: usize, cols: usize) -> Matrix<f64> {
+        Matrix::from_vec(self.take(rows * cols), rows, cols)
+    }
+
+    /// Returns the buffer `v` to the pool.
+    pub fn give(&mut self, v: Vec<f64>) {
+        if self.free.len() < self.capacity && v.capacity() > 0 {
+            self.free.push(v);
+        }
+    }
+
+    /// Returns the buffer of the matrix `m` to the pool.
+    pub fn give_matrix(&mut self, m: Matrix<f64>) {
+        self.give(m.into_vec())
+    }
+
+    /// Returns all buffers of `v` to the pool.
+    pub fn give_all(&mut self, v: Vec<Vec<f64>>) {
+        for b in v {
+            self.give(b);
+        }
+    }
+
+    /// Returns the number of buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Returns `true` if the pool contains no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    /// Returns the number of calls of [take](#method.take) which could not
+    /// reuse a buffer of the pool.
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// Frees all buffers of the pool.
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> BufferPool {
+        BufferPool::new()
+    }
+}
+
+thread_local! {
+    static POOL: RefCell<BufferPool> = RefCell::new(BufferPool::new());
+}
+
+/// Calls `f` with the pool of the current thread.
+///
+/// Panics if it is called again from within `f`.
+pub fn with_pool<F, R>(f: F) -> R where F: FnOnce(&mut BufferPool) -> R {
+    POOL.with(|p| f(&mut p.borrow_mut()))
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool() {
+
+        let mut p = BufferPool::with_capacity(2);
+        let a = p.take(10);
+        let b = p.copy_of(&[1.0, 2.0]);
+        assert_eq!(b, vec![1.0, 2.0]);
+        assert_eq!(p.allocations(), 2);
+
+        p.give(a);
+        p.give(b);
+        p.give(vec![1.0]);
+        // the pool is full
+        assert_eq!(p.len(), 2);
+
+        // reuses the buffer of a
+        let m = p.take_matrix(2, 5);
+        assert!(m.iter().all(|&x| x == 0.0));
+        assert_eq!(p.allocations(), 2);
+        p.give_matrix(m);
+
+        // no buffer is large enough
+        let c = p.take(100);
+        assert_eq!(c.len(), 100);
+        assert_eq!(p.allocations(), 3);
+
+        assert_eq!(with_pool(|p| p.take(3)), vec![0.0; 3]);
+        p.clear();
+        assert!(p.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Progress reporting of long-running fits.
+//!
+//! Iterative algorithms (e.g. gradient descent in the module
+//! [opt](../opt/index.html) or the training of
+//! [neural networks](../nn/index.html)) report their progress after each
+//! iteration to an implementation of the trait
+//! [Progress](trait.Progress.html). Any closure `FnMut(&ProgressInfo)` can be
+//! used as callback. [ProgressBar](struct.ProgressBar.html) draws a progress
+//! bar on stderr and [LogProgress](struct.LogProgress.html) writes the
+//! progress via the [log](https://crates.io/crates/log) crate.
+//!
+//! Independent of the callback each iteration is logged with the level
+//! `debug` and the target `rustml`, so that the progress of all algorithms
+//! can be inspected by installing a logger (e.g. `env_logger`).
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::opt::*;
+//! use rustml::progress::ProgressInfo;
+//!
+//! let mut losses = vec![];
+//! let r = opt_with_progress(
+//!     &|p| (p[0] - 2.0) * (p[0] - 2.0),
+//!     &|p| vec![2.0 * (p[0] - 2.0)],
+//!     &[4.0],
+//!     empty_opts().iter(5),
+//!     &mut |i: &ProgressInfo| losses.push(i.loss.unwrap())
+//! );
+//! assert_eq!(r.fvals.len(), 5);
+//! assert_eq!(losses.len(), 5);
+//! assert!(losses[4] < losses[0]);
+//! ```
+use std::io::{stderr, Stderr, Write};
+
+/// The state of an algorithm after an iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressInfo<'a> {
+    /// Name of the algorithm, e.g. `gradient descent`.
+    pub algorithm: &'a str,
+    /// Number of completed iterations.
+    pub iteration: usize,
+    /// Maximum number of iterations.
+    pub max_iter: usize,
+    /// Value of the objective after the iteration if it is computed by the
+    /// algorithm.
+    pub loss: Option<f64>
+}
+
+/// Callback which is informed about the progress of an algorithm.
+pub trait Progress {
+
+    /// Is called after each iteration.
+    fn update(&mut self, info: &ProgressInfo);
+
+    /// Returns false if the callback does not use the loss so that
+    /// algorithms can skip its computation. The default is true.
+    fn wants_loss(&self) -> bool {
+        true
+    }
+}
+
+impl <F: FnMut(&ProgressInfo)> Progress for F {
+
+    fn update(&mut self, info: &ProgressInfo) {
+        self(info)
+    }
+}
+
+/// Callback which ignores the progress.
+#[derive(Clone, Copy, Debug)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+
+    fn update(&mut self, _info: &ProgressInfo) { }
+
+    fn wants_loss(&self) -> bool {
+        false
+    }
+}
+
+/// Logs the progress and informs the callback. This function is called by
+/// the algorithms after each iteration.
+pub fn report<P: Progress + ?Sized>(p: &mut P, info: &ProgressInfo) {
+
+    match info.loss {
+        Some(l) => debug!(target: "rustml", "{}: iteration {}/{}, loss {}", info.algorithm, info.iteration, info.max_iter, l),
+        None => debug!(target: "rustml", "{}: iteration {}/{}", info.algorithm, info.iteration, info.max_iter)
+    }
+    p.update(info);
+}
+
+/// Returns true if the loss is used by the callback or if it is logged with
+/// the level `debug` (see [report](fn.report.html)). Algorithms which
+/// compute the loss only to report it can skip the computation otherwise.
+pub fn wants_loss<P: Progress + ?Sized>(p: &P) -> bool {
+    p.wants_loss() || log_enabled!(target: "rustml", ::log::Level::Debug)
+}
+
+/// Writes the progress via the `log` crate with the level `info` every
+/// `n` iterations and after the last iteration.
+#[derive(Clone, Copy, Debug)]
+pub struct LogProgress {
+    every: usize
+}
+
+impl LogProgress {
+
+    /// Creates a callback which logs every `n` iterations.
+    ///
+    /// Panics if `n` is zero.
+    pub fn new(n: usize) -> LogProgress {
+        assert!(n > 0, "Parameter n must be greater than zero.");
+        LogProgress { every: n }
+    }
+}
+
+impl Progress for LogProgress {
+
+    fn update(&mut self, info: &ProgressInfo) {
+
+        if info.iteration % self.every == 0 || info.iteration == info.max_iter {
+            match info.loss {
+                Some(l) => info!(target: "rustml", "{}: iteration {}/{}, loss {}", info.algorithm, info.iteration, info.max_iter, l),
+                None => info!(target: "rustml", "{}: iteration {}/{}", info.algorithm, info.iteration, info.max_iter)
+            }
+        }
+    }
+}
+
+/// Draws a progress bar, e.g. on stderr.
+///
+/// The bar is redrawn in the same line (via a carriage return) whenever the
+/// percentage of completed iterations changes and shows the current value
+/// of the objective.
+pub struct ProgressBar<W: Write> {
+    out: W,
+    width: usize,
+    last: Option<usize>
+}
+
+impl ProgressBar<Stderr> {
+
+    /// Creates a progress bar with a width of 40 characters which is drawn on
+    /// stderr.
+    pub fn new() -> ProgressBar<Stderr> {
+        ProgressBar::to(stderr())
+    }
+}
+
+impl <W: Write> ProgressBar<W> {
+
+    /// Creates a progress bar with a width of 40 characters which is written
+    /// to `out`.
+    pub fn to(out: W) -> ProgressBar<W> {
+        ProgressBar {
+            out: out,
+            width: 40,
+            last: None
+        }
+    }
+
+    /// Sets the width of the bar in characters.
+    pub fn width(self, n: usize) -> ProgressBar<W> {
+        ProgressBar { width: n, .. self }
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl <W: Write> Progress for ProgressBar<W> {
+
+    fn update(&mut self, info: &ProgressInfo) {
+
+        let pct = if info.max_iter == 0 { 100 } else { info.iteration * 100 / info.max_iter };
+        if self.last == Some(pct) && info.iteration != info.max_iter {
+            return;
+        }
+        self.last = Some(pct);
+
+        let done = self.width * pct / 100;
+        let bar: String = (0..self.width).map(|i| if i < done { '=' } else { ' ' }).collect();
+        let loss = info.loss.map(|l| format!(" loss {:.6}", l)).unwrap_or_default();
+        let end = if info.iteration >= info.max_iter { "\n" } else { "" };
+        // errors while drawing the progress must not abort the algorithm
+        let _ = write!(self.out, "\r{} [{}] {:3}% {}/{}{}{}",
+            info.algorithm, bar, pct, info.iteration, info.max_iter, loss, end);
+        let _ = self.out.flush();
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(i: usize, n: usize) -> ProgressInfo<'static> {
+        ProgressInfo { algorithm: "test", iteration: i, max_iter: n, loss: Some(0.5) }
+    }
+
+    #[test]
+    fn test_closure_progress() {
+
+        let mut v = vec![];
+        {
+            let mut p = |i: &ProgressInfo| v.push(i.iteration);
+            report(&mut p, &info(1, 2));
+            report(&mut p, &info(2, 2));
+        }
+        assert_eq!(v, vec![1, 2]);
+        report(&mut NoProgress, &info(1, 1));
+        report(&mut LogProgress::new(10), &info(10, 20));
+    }
+
+    #[test]
+    fn test_wants_loss() {
+
+        assert!(!NoProgress.wants_loss());
+        assert!((|_: &ProgressInfo| ()).wants_loss());
+        assert!(wants_loss(&LogProgress::new(10)));
+    }
+
+    #[test]
+    fn test_progress_bar() {
+
+        let mut p = ProgressBar::to(vec![]).width(4);
+        for i in 1..201 {
+            p.update(&info(i, 200));
+        }
+        let s = String::from_utf8(p.into_inner()).unwrap();
+        // one update for each percentage
+        assert_eq!(s.matches('\r').count(), 101);
+        assert!(s.starts_with("\rtest [    ]   0% 1/200 loss 0.500000"));
+        assert!(s.ends_with("\rtest [====] 100% 200/200 loss 0.500000\n"));
+    }
+}
@@ -0,0 +1,292 @@
+//! Post-training quantization of neural networks to 8-bit integers.
+//!
+//! [QuantizedNetwork](struct.QuantizedNetwork.html) stores the parameters of
+//! a trained [NeuralNetwork](../nn/struct.NeuralNetwork.html) as `i8` values
+//! with one scale per layer, i.e. a parameter `w` is approximated by
+//! `scale * q` where `q` is in the range `[-127, 127]` and `scale` is the
+//! largest absolute value of the parameters of the layer divided by 127. The
+//! model needs only an eighth of the memory of the original network.
+//!
+//! The inputs of each layer are quantized in the same way (with one scale
+//! per example) so that the products of the inputs and the parameters are
+//! computed with integer arithmetic. Only the accumulated sums are converted
+//! back to floating point numbers before the activation function of the
+//! layer (or the softmax in the output layer) is applied. The outputs differ
+//! slightly from the outputs of the original network.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::nn::NeuralNetwork;
+//!
+//! # fn main() {
+//! let n = NeuralNetwork::new()
+//!     .add_layer(3)
+//!     .add_layer(2)
+//!     .add_layer(3)
+//!     .set_params(0, mat![0.1, 0.2, 0.4; 0.2, 0.1, 2.0])
+//!     .set_params(1, mat![0.8, 1.2, 0.6; 0.4, 0.5, 0.8; 1.4, 1.5, 2.0]);
+//! let q = n.quantize();
+//! let x = mat![0.5, 1.2, 1.5; 0.3, 1.1, 1.0];
+//! assert!(q.predict(&x).similar(&n.predict(&x), 0.01));
+//! # }
+//! ```
+use std::f64;
+use std::fmt;
+use std::sync::Arc;
+
+use matrix::Matrix;
+use nn::{NeuralNetwork, Activation, Cost};
+
+/// A matrix of `i8` values with a common scale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedMatrix {
+    rows: usize,
+    cols: usize,
+    scale: f64,
+    data: Vec<i8>
+}
+
+impl QuantizedMatrix {
+
+    /// Quantizes the matrix `m` symmetrically, i.e. the largest absolute
+    /// value of `m` is mapped to 127.
+    pub fn new(m: &Matrix<f64>) -> QuantizedMatrix {
+
+        let (scale, data) = quantize(m.buf());
+        QuantizedMatrix {
+            rows: m.rows(),
+            cols: m.cols(),
+            scale: scale,
+            data: data
+        }
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the scale, i.e. the value of a quantized `1`.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Returns the quantized values in row-major order.
+    pub fn buf(&self) -> &[i8] {
+        &self.data
+    }
+
+    /// Converts the quantized values back to floating point numbers.
+    pub fn dequantize(&self) -> Matrix<f64> {
+        Matrix::from_vec(
+            self.data.iter().map(|&q| q as f64 * self.scale).collect(),
+            self.rows, self.cols
+        )
+    }
+}
+
+/// Quantizes the values symmetrically to `i8` and returns the scale and the
+/// quantized values. If all values are zero the scale is one.
+fn quantize(v: &[f64]) -> (f64, Vec<i8>) {
+
+    let max = v.iter().fold(0.0f64, |acc, x| acc.max(x.abs()));
+    let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+    (scale, v.iter().map(|x| (x / scale).round().max(-127.0).min(127.0) as i8).collect())
+}
+
+// numerically stable softmax
+fn softmax(v: &mut [f64]) {
+    let m = v.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut s = 0.0;
+    for x in v.iter_mut() {
+        *x = (*x - m).exp();
+        s += *x;
+    }
+    for x in v.iter_mut() {
+        *x /= s;
+    }
+}
+
+/// A neural network with quantized parameters.
+#[derive(Clone)]
+pub struct QuantizedNetwork {
+    layers: Vec<usize>,
+    params: Vec<QuantizedMatrix>,
+    activations: Vec<Arc<dyn Activation>>,
+    softmax: bool,
+    cost: Cost
+}
+
+impl fmt::Debug for QuantizedNetwork {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QuantizedNetwork")
+            .field("layers", &self.layers)
+            .field("params", &self.params)
+            .field("activations", &self.activations)
+            .field("softmax", &self.softmax)
+            .field("cost", &self.cost)
+            .finish()
+    }
+}
+
+impl PartialEq for QuantizedNetwork {
+
+    // activation functions are equal if they have the same name and the
+    // same parameters
+    fn eq(&self, other: &QuantizedNetwork) -> bool {
+        self.layers == other.layers && self.params == other.params &&
+            self.softmax == other.softmax && self.cost == other.cost &&
+            self.activations.iter().zip(other.activations.iter())
+                .all(|(a, b)| a.name() == b.name() && a.params() == b.params())
+    }
+}
+
+impl QuantizedNetwork {
+
+    /// Quantizes the parameters of the network `net`. The activation
+    /// functions, the softmax of the output layer and the cost function
+    /// are kept.
+    ///
+    /// Panics if the network has fewer than two layers.
+    pub fn new(net: &NeuralNetwork) -> QuantizedNetwork {
+
+        assert!(net.layers() >= 2, "At least two layers are required.");
+        let params = net.params();
+        let mut layers = vec![params[0].cols()];
+        layers.extend(params.iter().map(|p| p.rows()));
+        QuantizedNetwork {
+            layers: layers,
+            params: params.iter().map(QuantizedMatrix::new).collect(),
+            activations: net.activations().to_vec(),
+            softmax: net.is_softmax(),
+            cost: net.cost()
+        }
+    }
+
+    /// Returns the number of units of each layer (without bias units).
+    pub fn layers(&self) -> &[usize] {
+        &self.layers
+    }
+
+    /// Returns the quantized parameters of each layer.
+    pub fn params(&self) -> &[QuantizedMatrix] {
+        &self.params
+    }
+
+    /// Returns the number of bytes which are required for the parameters.
+    pub fn size_in_bytes(&self) -> usize {
+        self.params.iter().map(|p| p.data.len() + 8).sum()
+    }
+
+    /// Converts the quantized parameters back into a neural network with
+    /// the same activation functions, softmax and cost function as the
+    /// original network.
+    pub fn dequantize(&self) -> NeuralNetwork {
+        self.params.iter().enumerate().fold(
+            self.layers.iter().fold(NeuralNetwork::new(), |n, &l| n.add_layer(l)),
+            |n, (i, p)| n.set_params(i, p.dequantize())
+        )
+        .set_activations(&self.activations)
+        .set_softmax(self.softmax)
+        .set_cost(self.cost)
+    }
+
+    /// Computes the output of the network for the examples in the rows of
+    /// `input` with integer arithmetic.
+    ///
+    /// Panics if the number of columns of `input` is not equal to the number
+    /// of units of the input layer.
+    pub fn predict(&self, input: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(input.cols() == self.layers[0], "Dimension of input vector does not match.");
+
+        let n = self.params.len();
+        let mut out = Vec::with_capacity(input.rows() * self.layers[n]);
+        for row in input.row_iter() {
+            let mut a = row.to_vec();
+            for (i, p) in self.params.iter().enumerate() {
+                let (s, q) = quantize(&a);
+                let z: Vec<f64> = p.data.chunks(p.cols)
+                    .map(|w| {
+                        let acc = w.iter().zip(q.iter()).fold(0i32, |acc, (&x, &y)| acc + x as i32 * y as i32);
+                        acc as f64 * p.scale * s
+                    })
+                    .collect();
+                a = if self.softmax && i + 1 == n {
+                    let mut z = z;
+                    softmax(&mut z);
+                    z
+                } else {
+                    z.iter().map(|&x| self.activations[i].apply(x)).collect()
+                };
+                // bias unit for the next layer
+                if i + 1 < n {
+                    a.insert(0, 1.0);
+                }
+            }
+            out.extend(a);
+        }
+        Matrix::from_vec(out, input.rows(), self.layers[n])
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use nn::{NeuralNetwork, Tanh, LeakyRelu, Cost};
+
+    #[test]
+    fn test_quantized_matrix() {
+
+        let m = mat![0.5, -1.27; 0.0, 1.0];
+        let q = QuantizedMatrix::new(&m);
+        assert_eq!(q.buf(), &[50, -127, 0, 100]);
+        assert!((q.scale() - 0.01).abs() < 1e-12);
+        assert!(q.dequantize().similar(&m, 1e-12));
+        assert_eq!(QuantizedMatrix::new(&mat![0.0, 0.0]).buf(), &[0, 0]);
+    }
+
+    #[test]
+    fn test_quantized_network() {
+
+        let n = NeuralNetwork::new().add_layer(5).add_layer(8).add_layer(4).add_layer(2);
+        let q = QuantizedNetwork::new(&n);
+        assert_eq!(q.layers(), &[5, 8, 4, 2]);
+        assert_eq!(q.size_in_bytes(), 40 + 36 + 10 + 24);
+
+        let x = Matrix::from_vec((0..20).map(|i| i as f64 / 7.0 - 1.0).collect(), 4, 5);
+        assert!(q.predict(&x).similar(&n.predict(&x), 0.02));
+        assert!(q.dequantize().predict(&x).similar(&n.predict(&x), 0.02));
+    }
+
+    #[test]
+    fn test_quantized_network_activations() {
+
+        let n = NeuralNetwork::new().add_layer(5).add_layer(8).add_layer(4).add_layer(3)
+            .set_activation(1, Tanh)
+            .set_activation(2, LeakyRelu(0.1))
+            .set_softmax(true)
+            .set_cost(Cost::CrossEntropy);
+        let q = n.quantize();
+        let x = Matrix::from_vec((0..20).map(|i| i as f64 / 7.0 - 1.0).collect(), 4, 5);
+        assert!(q.predict(&x).similar(&n.predict(&x), 0.02));
+
+        let m = q.dequantize();
+        assert_eq!(m.activation(1).name(), "tanh");
+        assert_eq!(m.activation(2).params(), vec![0.1]);
+        assert!(m.is_softmax() && m.cost() == Cost::CrossEntropy);
+        assert!(m.predict(&x).similar(&n.predict(&x), 0.02));
+    }
+}
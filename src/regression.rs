@@ -1,13 +1,47 @@
 //! Module for linear regression.
 //!
+//! [BayesianRidge](struct.BayesianRidge.html) is a Bayesian linear
+//! regression with a Gaussian prior on the weights and Gaussian noise. It
+//! returns the posterior distribution of the weights and a predictive
+//! variance for each prediction. The precisions of the prior and of the
+//! noise are estimated from the data by maximizing the evidence (marginal
+//! likelihood), i.e. no regularization parameter needs to be tuned.
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::regression::BayesianRidge;
+//! use rustml::model_selection::Estimator;
+//!
+//! # fn main() {
+//! let x = mat![0.0; 1.0; 2.0; 3.0; 4.0];
+//! let y = [1.1, 2.9, 5.2, 6.8, 9.1];
+//!
+//! let mut r = BayesianRidge::new();
+//! r.fit(&x, &y);
+//! assert!((r.coef()[0] - 2.0).abs() < 0.1);
+//!
+//! // the predictive variance grows away from the data
+//! let (mean, var) = r.predict_dist(&mat![2.0; 20.0]);
+//! assert!((mean[0] - 5.0).abs() < 0.2);
+//! assert!(var[1] > var[0]);
+//! # }
+//! ```
 extern crate rand;
 extern crate libc;
 
-use self::rand::{thread_rng, Rng};
+use self::rand::Rng;
 use std::iter::repeat;
+use std::f64::consts::PI;
 
 use matrix::*;
 use ops::{MatrixVectorMul, MatrixVectorOps};
+use io::model::{Model, Encoder, Decoder};
+use rng;
+use stream::PartialFit;
+use linalg::{symmetric_eigen, ILL_CONDITIONED};
+use math::{Mean, Dimension};
+use model_selection::Estimator;
 
 /// Hypothesis for linear regression.
 ///
@@ -17,6 +51,7 @@ use ops::{MatrixVectorMul, MatrixVectorOps};
 /// <script type="text/x-mathjax-config">
 ///   MathJax.Hub.Config({tex2jax: {inlineMath: [['$','$'], ['\\(','\\)']]}});
 /// </script>
+#[derive(Clone, Debug)]
 pub struct Hypothesis {
     /// Parameters of the hypothesis.
     thetas: Vec<f64>
@@ -42,8 +77,15 @@ impl Hypothesis {
     /// # }
     /// ```
     pub fn random(n: usize) -> Hypothesis {
+        Hypothesis::random_with_rng(n, &mut rng::rng())
+    }
+
+    /// Creates a hypothesis with `n` parameters initialized with random
+    /// values in the interval [0,1) which are created with the given random
+    /// number generator.
+    pub fn random_with_rng<R: Rng>(n: usize, rng: &mut R) -> Hypothesis {
         Hypothesis {
-            thetas: thread_rng().gen_iter::<f64>().take(n).collect()
+            thetas: rng.gen_iter::<f64>().take(n).collect()
         }
     }
 
@@ -141,6 +183,68 @@ impl Hypothesis {
     }
 }
 
+// Solves the normal equations X'X theta = X'y with Gauss-Jordan elimination.
+// The parameters of columns which are linearly dependent on the previous
+// columns are set to zero.
+fn least_squares(x: &Matrix<f64>, y: &[f64]) -> Vec<f64> {
+
+    let p = x.cols();
+    let mut a = vec![vec![0.0; p + 1]; p];
+    for (r, &t) in x.row_iter().zip(y.iter()) {
+        for i in 0..p {
+            for j in 0..p {
+                a[i][j] += r[i] * r[j];
+            }
+            a[i][p] += r[i] * t;
+        }
+    }
+
+    let tol = 1e-12 * (0..p).fold(0.0, |acc: f64, i| acc.max(a[i][i]));
+    let mut pivots = vec![];
+    for c in 0..p {
+        let r = pivots.len();
+        let k = (r..p).fold(r, |k, i| if a[i][c].abs() > a[k][c].abs() { i } else { k });
+        if r == p || a[k][c].abs() <= tol {
+            continue;
+        }
+        a.swap(r, k);
+        for i in (0..p).filter(|&i| i != r) {
+            let f = a[i][c] / a[r][c];
+            for j in c..p + 1 {
+                a[i][j] -= f * a[r][j];
+            }
+        }
+        pivots.push(c);
+    }
+
+    let mut thetas = vec![0.0; p];
+    for (r, &c) in pivots.iter().enumerate() {
+        thetas[c] = a[r][p] / a[r][c];
+    }
+    thetas
+}
+
+/// The rows of `x` are the rows of the design matrix (see
+/// [DesignMatrix](trait.DesignMatrix.html)), i.e. the hypothesis has one
+/// parameter per column of `x`.
+impl Estimator<f64, f64> for Hypothesis {
+
+    /// Fits the parameters which minimize the
+    /// [error](struct.Hypothesis.html#method.error) by solving the normal
+    /// equations. The current parameters are discarded.
+    ///
+    /// Panics if the number of rows and targets differ.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[f64]) {
+
+        assert!(x.rows() == y.len(), "Number of examples and targets must be equal.");
+        self.thetas = least_squares(x, y);
+    }
+
+    fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+        self.eval(x)
+    }
+}
+
 /// Trait to create the design matrix of a matrix of features, i.e. a new column is
 /// inserted at the left of the matrix where all elements are equal to one.
 ///
@@ -185,6 +289,260 @@ impl DesignMatrix<f32> for Matrix<f32> {
     }
 }
 
+/// The rows of `x` are the rows of the design matrix and `y` must have one
+/// column.
+impl PartialFit for Hypothesis {
+
+    fn partial_fit(&mut self, x: &Matrix<f64>, y: &Matrix<f64>, alpha: f64) {
+
+        assert!(y.cols() == 1, "Exactly one target column is required.");
+        let d = self.derivatives(x, y.buf());
+        self.apply_gradient(&d, alpha);
+    }
+
+    fn gradient(&self, x: &Matrix<f64>, y: &Matrix<f64>) -> Option<Vec<f64>> {
+
+        assert!(y.cols() == 1, "Exactly one target column is required.");
+        Some(self.derivatives(x, y.buf()))
+    }
+
+    fn apply_gradient(&mut self, g: &[f64], alpha: f64) {
+
+        assert!(g.len() == self.thetas.len(), "Dimension of gradient does not match.");
+        for (t, v) in self.thetas.iter_mut().zip(g.iter()) {
+            *t -= alpha * v;
+        }
+    }
+}
+
+impl Model for Hypothesis {
+
+    fn tag() -> &'static str { "rustml.regression.Hypothesis" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.thetas);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Hypothesis, String> {
+        Ok(Hypothesis { thetas: try!(d.f64s()) })
+    }
+}
+
+/// Bayesian ridge regression with evidence maximization.
+///
+/// The weights have the prior `N(0, 1 / lambda I)` and the targets the
+/// noise `N(0, 1 / alpha)`. The hyperparameters `alpha` and `lambda` are
+/// updated alternately with the posterior of the weights until the weights
+/// converge (MacKay's fixed point updates).
+#[derive(Clone, Debug)]
+pub struct BayesianRidge {
+    max_iter: usize,
+    tol: f64,
+    fit_intercept: bool,
+    coef: Vec<f64>,
+    intercept: f64,
+    x_mean: Vec<f64>,
+    sigma: Matrix<f64>,
+    alpha: f64,
+    lambda: f64,
+    log_evidence: f64,
+    n_iter: usize
+}
+
+impl BayesianRidge {
+
+    /// Creates an unfitted model which fits an intercept with at most 300
+    /// iterations and a tolerance of 1e-6.
+    pub fn new() -> BayesianRidge {
+        BayesianRidge {
+            max_iter: 300,
+            tol: 1e-6,
+            fit_intercept: true,
+            coef: vec![],
+            intercept: 0.0,
+            x_mean: vec![],
+            sigma: Matrix::new(),
+            alpha: 0.0,
+            lambda: 0.0,
+            log_evidence: 0.0,
+            n_iter: 0
+        }
+    }
+
+    /// Sets the maximum number of updates of the hyperparameters.
+    pub fn max_iter(&self, n: usize) -> BayesianRidge {
+        BayesianRidge { max_iter: n, .. self.clone() }
+    }
+
+    /// The updates stop if no weight changes by more than `tol`.
+    pub fn tol(&self, tol: f64) -> BayesianRidge {
+        BayesianRidge { tol: tol, .. self.clone() }
+    }
+
+    /// Whether an intercept (which is not penalized) is fitted. If not, the
+    /// data is assumed to be centered.
+    pub fn fit_intercept(&self, b: bool) -> BayesianRidge {
+        BayesianRidge { fit_intercept: b, .. self.clone() }
+    }
+
+    /// Returns the posterior mean of the weights.
+    pub fn coef(&self) -> &[f64] {
+        &self.coef
+    }
+
+    /// Returns the intercept.
+    pub fn intercept(&self) -> f64 {
+        self.intercept
+    }
+
+    /// Returns the posterior covariance of the weights.
+    pub fn sigma(&self) -> &Matrix<f64> {
+        &self.sigma
+    }
+
+    /// Returns the estimated precision of the noise.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns the estimated precision of the weights.
+    pub fn lambda(&self) -> f64 {
+        self.lambda
+    }
+
+    /// Returns the logarithm of the evidence of the training data for the
+    /// estimated hyperparameters.
+    pub fn log_evidence(&self) -> f64 {
+        self.log_evidence
+    }
+
+    /// Returns the number of updates of the hyperparameters of the last fit.
+    pub fn n_iter(&self) -> usize {
+        self.n_iter
+    }
+
+    /// Returns the mean and the variance of the predictive distribution of
+    /// each row of `x`, i.e. the variance contains the noise and the
+    /// uncertainty of the weights.
+    ///
+    /// Panics if the model is not fitted.
+    pub fn predict_dist(&self, x: &Matrix<f64>) -> (Vec<f64>, Vec<f64>) {
+
+        assert!(!self.coef.is_empty(), "Model is not fitted.");
+        assert!(x.cols() == self.coef.len(), "Number of columns does not match the number of features.");
+
+        let d = self.coef.len();
+        let s = self.sigma.buf();
+        let mut mean = Vec::with_capacity(x.rows());
+        let mut var = Vec::with_capacity(x.rows());
+        for r in x.row_iter() {
+            let c: Vec<f64> = r.iter().zip(self.x_mean.iter()).map(|(a, b)| a - b).collect();
+            mean.push(self.intercept + r.iter().zip(self.coef.iter()).fold(0.0, |acc, (a, b)| acc + a * b));
+            let q = (0..d).fold(0.0, |acc, i| acc + c[i] * (0..d).fold(0.0, |acc, j| acc + s[i * d + j] * c[j]));
+            var.push(1.0 / self.alpha + q);
+        }
+        (mean, var)
+    }
+}
+
+impl Estimator<f64, f64> for BayesianRidge {
+
+    /// Fits the model on the examples in the rows of `x` with the targets
+    /// `y`.
+    ///
+    /// Panics if the number of rows and targets differ or if there are no
+    /// examples.
+    fn fit(&mut self, x: &Matrix<f64>, y: &[f64]) {
+
+        assert!(x.rows() == y.len(), "Number of examples and targets must be equal.");
+        assert!(x.rows() > 0, "At least one example is required.");
+
+        let (n, d) = (x.rows(), x.cols());
+        let x_mean = if self.fit_intercept { x.mean(Dimension::Column) } else { vec![0.0; d] };
+        let y_mean = if self.fit_intercept { y.iter().sum::<f64>() / n as f64 } else { 0.0 };
+        let xc: Vec<f64> = x.row_iter().flat_map(|r| r.iter().zip(x_mean.iter()).map(|(a, b)| a - b).collect::<Vec<f64>>()).collect();
+        let yc: Vec<f64> = y.iter().map(|t| t - y_mean).collect();
+
+        // eigendecomposition of X^T X and X^T y
+        let mut xtx = vec![0.0; d * d];
+        let mut xty = vec![0.0; d];
+        for k in 0..n {
+            let r = &xc[k * d..(k + 1) * d];
+            for i in 0..d {
+                xty[i] += r[i] * yc[k];
+                for j in 0..d {
+                    xtx[i * d + j] += r[i] * r[j];
+                }
+            }
+        }
+        let (eig, v) = symmetric_eigen(&Matrix::from_vec(xtx, d, d));
+        let eig: Vec<f64> = eig.iter().map(|e| e.max(0.0)).collect();
+        // the condition number of X is the square root of the one of X^T X
+        if d > 0 && !(eig[d - 1] * ILL_CONDITIONED * ILL_CONDITIONED > eig[0]) {
+            warn!(target: "rustml", "bayesian ridge: design matrix is ill-conditioned, e.g. due to collinear features");
+        }
+        let vb = v.buf();
+        // X^T y in the basis of the eigenvectors
+        let vty: Vec<f64> = (0..d).map(|j| (0..d).fold(0.0, |acc, i| acc + vb[i * d + j] * xty[i])).collect();
+
+        let var_y = yc.iter().fold(0.0, |acc, t| acc + t * t) / n as f64;
+        let mut alpha = 1.0 / (var_y + ::std::f64::EPSILON);
+        let mut lambda = 1.0;
+        let mut coef = vec![0.0; d];
+        let mut n_iter = 0;
+        let sse = |m: &[f64]| (0..n).fold(0.0, |acc, k| {
+            let e = yc[k] - (0..d).fold(0.0, |a, j| a + xc[k * d + j] * m[j]);
+            acc + e * e
+        });
+
+        for it in 0..self.max_iter.max(1) {
+            // m = alpha S_N X^T y with S_N = V diag(1 / (lambda + alpha eig)) V^T
+            let z: Vec<f64> = (0..d).map(|j| alpha * vty[j] / (lambda + alpha * eig[j])).collect();
+            let m: Vec<f64> = (0..d).map(|i| (0..d).fold(0.0, |acc, j| acc + vb[i * d + j] * z[j])).collect();
+
+            let gamma: f64 = eig.iter().map(|&e| alpha * e / (lambda + alpha * e)).sum();
+            let mm = m.iter().fold(0.0, |acc, w| acc + w * w);
+            lambda = gamma / (mm + ::std::f64::EPSILON);
+            alpha = (n as f64 - gamma).max(::std::f64::EPSILON) / (sse(&m) + ::std::f64::EPSILON);
+
+            let done = m.iter().zip(coef.iter()).all(|(a, b)| (a - b).abs() <= self.tol);
+            coef = m;
+            n_iter = it + 1;
+            if done && it > 0 {
+                break;
+            }
+        }
+
+        // posterior with the final hyperparameters
+        let z: Vec<f64> = (0..d).map(|j| alpha * vty[j] / (lambda + alpha * eig[j])).collect();
+        coef = (0..d).map(|i| (0..d).fold(0.0, |acc, j| acc + vb[i * d + j] * z[j])).collect();
+        let mut sigma = vec![0.0; d * d];
+        for i in 0..d {
+            for j in 0..d {
+                sigma[i * d + j] = (0..d).fold(0.0, |acc, k| acc + vb[i * d + k] * vb[j * d + k] / (lambda + alpha * eig[k]));
+            }
+        }
+
+        let mm = coef.iter().fold(0.0, |acc, w| acc + w * w);
+        let log_det: f64 = eig.iter().map(|&e| (lambda + alpha * e).ln()).sum();
+        self.log_evidence = 0.5 * (d as f64 * lambda.ln() + n as f64 * alpha.ln()
+            - alpha * sse(&coef) - lambda * mm - log_det - n as f64 * (2.0 * PI).ln());
+
+        self.intercept = y_mean - coef.iter().zip(x_mean.iter()).fold(0.0, |acc, (a, b)| acc + a * b);
+        self.coef = coef;
+        self.x_mean = x_mean;
+        self.sigma = Matrix::from_vec(sigma, d, d);
+        self.alpha = alpha;
+        self.lambda = lambda;
+        self.n_iter = n_iter;
+    }
+
+    /// Returns the predictive mean of each row of `x`.
+    fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+        self.predict_dist(x).0
+    }
+}
+
 // ----------------------------------------------------------------------------
 
 #[cfg(test)]
@@ -251,4 +609,67 @@ mod tests {
             vec![74.0, 49.0, 106.5]
         );
     }
+
+    #[test]
+    fn test_hypothesis_estimator() {
+
+        use model_selection::{cross_val_score, KFold};
+
+        // y = 1 + 2 x0 - 3 x1
+        let x = mat![1.0, 0.0; 2.0, 1.0; 0.5, 3.0; 4.0, 2.0; 1.5, 1.0; 3.0, 0.5].design_matrix();
+        let y: Vec<f64> = x.row_iter().map(|r| r[0] + 2.0 * r[1] - 3.0 * r[2]).collect();
+
+        let mut h = Hypothesis::from_params(&[5.0]);
+        h.fit(&x, &y);
+        assert!(h.params().similar(&vec![1.0, 2.0, -3.0], 1e-6));
+        assert!(Estimator::predict(&h, &x).similar(&y, 1e-6));
+
+        let mse = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).map(|(p, q)| (p - q) * (p - q)).sum::<f64>();
+        let s = cross_val_score(&Hypothesis::from_params(&[]), &x, &y, KFold::new(6, 3), mse);
+        assert!(s.iter().all(|&e| e < 1e-8));
+
+        // the second and the third column are equal
+        let x = mat![1.0, 1.0, 1.0; 1.0, 2.0, 2.0; 1.0, 3.0, 3.0];
+        h.fit(&x, &[2.0, 4.0, 6.0]);
+        assert!(Estimator::predict(&h, &x).similar(&vec![2.0, 4.0, 6.0], 1e-9));
+    }
+
+    #[test]
+    fn test_bayesian_ridge() {
+
+        use rng::seeded_rng;
+        use self::rand::distributions::{Normal, IndependentSample};
+
+        // y = 3 + 2 x0 - x1 + 0 x2 + noise with std 0.5
+        let mut rng = seeded_rng([1, 2, 3, 4]);
+        let noise = Normal::new(0.0, 0.5);
+        let n = 200;
+        let x = Matrix::from_vec((0..n * 3).map(|_| rng.gen::<f64>() * 10.0).collect(), n, 3);
+        let y: Vec<f64> = x.row_iter().map(|r| 3.0 + 2.0 * r[0] - r[1] + noise.ind_sample(&mut rng)).collect();
+
+        let mut b = BayesianRidge::new();
+        b.fit(&x, &y);
+        assert!(b.coef().similar(&vec![2.0, -1.0, 0.0], 0.05));
+        assert!((b.intercept() - 3.0).abs() < 0.3);
+        // noise precision 1 / 0.25
+        assert!((b.alpha() - 4.0).abs() < 1.0);
+        assert!(b.n_iter() < 300);
+
+        // posterior covariance is symmetric with small variances
+        let s = b.sigma();
+        assert!((s.get(0, 1).unwrap() - s.get(1, 0).unwrap()).abs() < 1e-12);
+        assert!(*s.get(0, 0).unwrap() > 0.0 && *s.get(0, 0).unwrap() < 1e-3);
+
+        let q = Matrix::from_vec(vec![5.0, 5.0, 5.0, 100.0, 100.0, 100.0], 2, 3);
+        let (mean, var) = b.predict_dist(&q);
+        assert!((mean[0] - 8.0).abs() < 0.3);
+        assert_eq!(b.predict(&q), mean);
+        assert!(var[0] > 1.0 / b.alpha() && var[1] > var[0]);
+
+        // the evidence is larger for the true model than for random targets
+        let mut r = BayesianRidge::new();
+        let z: Vec<f64> = (0..n).map(|_| rng.gen::<f64>() * 30.0).collect();
+        r.fit(&x, &z);
+        assert!(b.log_evidence() > r.log_evidence());
+    }
 }
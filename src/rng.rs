@@ -0,0 +1,111 @@
+//! Reproducible random number generation.
+//!
+//! Functions of this library which need random numbers either take a seed
+//! of type `[u32; 4]` (e.g. shuffling, splitting, bootstrapping and the
+//! generators of toy datasets) or an explicit random number generator
+//! (e.g. [NeuralNetwork::add_layer_with_rng](../nn/struct.NeuralNetwork.html#method.add_layer_with_rng)).
+//! Functions without such a parameter (e.g. the random initialization of the
+//! parameters of a neural network via `add_layer`, `Hypothesis::random` or
+//! `Matrix::random`) get their generator from [rng](fn.rng.html).
+//!
+//! By default `rng` returns a generator which is seeded from the operating
+//! system. After [set_global_seed](fn.set_global_seed.html) has been called
+//! the generators returned by `rng` are derived deterministically from the
+//! global seed, i.e. an experiment which sets the global seed at its
+//! beginning is reproducible bit for bit. The global seed is stored per
+//! thread so that parallel tests do not interfere with each other.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::rng::*;
+//! use rustml::nn::NeuralNetwork;
+//!
+//! set_global_seed([1, 2, 3, 4]);
+//! let a = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+//! set_global_seed([1, 2, 3, 4]);
+//! let b = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+//! assert_eq!(a.params(), b.params());
+//! clear_global_seed();
+//! ```
+extern crate rand;
+
+use std::cell::RefCell;
+
+use self::rand::{thread_rng, Rng, SeedableRng, XorShiftRng};
+
+thread_local! {
+    static GLOBAL: RefCell<Option<XorShiftRng>> = RefCell::new(None);
+}
+
+/// Returns a generator which is seeded with `seed`.
+///
+/// A seed which consists only of zeros is replaced by a fixed non-zero seed
+/// because the generator does not accept it.
+pub fn seeded_rng(seed: [u32; 4]) -> XorShiftRng {
+
+    if seed == [0; 4] {
+        XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb])
+    } else {
+        XorShiftRng::from_seed(seed)
+    }
+}
+
+/// Sets the global seed of the current thread from which all generators
+/// returned by [rng](fn.rng.html) are derived.
+pub fn set_global_seed(seed: [u32; 4]) {
+    GLOBAL.with(|g| *g.borrow_mut() = Some(seeded_rng(seed)));
+}
+
+/// Removes the global seed of the current thread, i.e. the generators
+/// returned by [rng](fn.rng.html) are seeded from the operating system
+/// again.
+pub fn clear_global_seed() {
+    GLOBAL.with(|g| *g.borrow_mut() = None);
+}
+
+/// Returns a new seed. If a global seed has been set the seed is the next
+/// value of the global sequence, otherwise it is random.
+pub fn next_seed() -> [u32; 4] {
+
+    GLOBAL.with(|g| match *g.borrow_mut() {
+        Some(ref mut r) => r.gen::<[u32; 4]>(),
+        None => thread_rng().gen::<[u32; 4]>()
+    })
+}
+
+/// Returns a new generator which is seeded via [next_seed](fn.next_seed.html).
+pub fn rng() -> XorShiftRng {
+    seeded_rng(next_seed())
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::rand::Rng;
+
+    #[test]
+    fn test_global_seed() {
+
+        set_global_seed([5, 6, 7, 8]);
+        let a: Vec<u32> = (0..3).map(|_| rng().gen()).collect();
+        set_global_seed([5, 6, 7, 8]);
+        let b: Vec<u32> = (0..3).map(|_| rng().gen()).collect();
+        assert_eq!(a, b);
+        assert!(a[0] != a[1]);
+
+        clear_global_seed();
+        let s = next_seed();
+        assert!(s != next_seed());
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+
+        assert_eq!(seeded_rng([1, 2, 3, 4]).gen::<u64>(), seeded_rng([1, 2, 3, 4]).gen::<u64>());
+        // must not panic
+        seeded_rng([0; 4]).gen::<u64>();
+    }
+}
@@ -0,0 +1,381 @@
+//! Module to rebalance datasets with imbalanced classes.
+//!
+//! Many classifiers perform poorly on classes which are rare in the training
+//! set. The functions in this module either add examples to the smaller
+//! classes (oversampling) or remove examples from the larger classes
+//! (undersampling) so that all classes have the same number of examples.
+//! All functions expect the examples in the rows of a matrix and the labels
+//! in a slice and return the rebalanced examples together with their labels.
+//! The random number generator is initialized with the given seed, which
+//! must not be `[0, 0, 0, 0]`.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::sampling::*;
+//!
+//! # fn main() {
+//! let x = mat![
+//!     1.0, 1.0;
+//!     1.5, 1.0;
+//!     1.2, 1.3;
+//!     5.0, 5.0;
+//!     6.0, 5.0
+//! ];
+//! let y = vec![0, 0, 0, 1, 1];
+//!
+//! let (xs, ys) = smote(&x, &y, 1, [1, 2, 3, 4]);
+//! assert_eq!(xs.rows(), 6);
+//! assert_eq!(ys.iter().filter(|&&l| l == 1).count(), 3);
+//!
+//! // the synthetic example is on the line between the two examples of class 1
+//! let s = xs.row(5).unwrap();
+//! assert!(s[0] >= 5.0 && s[0] <= 6.0 && s[1] == 5.0);
+//! # }
+//! ```
+//!
+//! # Mini-batches
+//!
+//! Instead of rebalancing the dataset, a
+//! [StratifiedBatchSampler](struct.StratifiedBatchSampler.html) draws the
+//! mini-batches of stochastic gradient descent (e.g. for
+//! [SgdLinear](../sgd/struct.SgdLinear.html)) so that each batch either has
+//! the class ratios of the whole dataset or contains all classes equally
+//! often.
+//!
+//! ```
+//! use rustml::sampling::StratifiedBatchSampler;
+//!
+//! let y = vec![0, 0, 0, 0, 0, 0, 1, 1];
+//!
+//! // each batch of four examples contains one example of class 1 ...
+//! let mut s = StratifiedBatchSampler::new(&y, 4, [1, 2, 3, 4]);
+//! for b in s.epoch() {
+//!     assert_eq!(b.iter().filter(|&&i| y[i] == 1).count(), 1);
+//! }
+//!
+//! // ... or two if the classes are balanced
+//! let mut s = StratifiedBatchSampler::balanced(&y, 4, [1, 2, 3, 4]);
+//! for b in s.epoch() {
+//!     assert_eq!(b.iter().filter(|&&i| y[i] == 1).count(), 2);
+//! }
+//! ```
+extern crate num;
+extern crate rand;
+
+use self::num::traits::Float;
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+
+use matrix::Matrix;
+use distance::{Distance, Euclid};
+use knn::scan;
+use groupby::{GroupBy, group_indexes};
+
+fn rows_of<T: Clone, L: Clone>(x: &Matrix<T>, y: &[L], idx: &[usize]) -> (Matrix<T>, Vec<L>) {
+
+    (x.select_rows(idx), idx.iter().map(|&i| y[i].clone()).collect())
+}
+
+/// Randomly duplicates examples of the smaller classes until each class has
+/// as many examples as the largest class.
+///
+/// The original examples are kept in their original order, the duplicated
+/// examples are appended.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+pub fn random_oversample<T: Clone, L: Ord + Clone>(x: &Matrix<T>, y: &[L], seed: [u32; 4]) -> (Matrix<T>, Vec<L>) {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let g = GroupBy::new(x, y);
+    let max = g.counts().into_iter().max().unwrap_or(0);
+
+    let mut idx: Vec<usize> = (0..y.len()).collect();
+    for &(_, ref v) in g.groups() {
+        for _ in v.len()..max {
+            idx.push(v[rng.gen_range(0, v.len())]);
+        }
+    }
+    rows_of(x, y, &idx)
+}
+
+/// Randomly removes examples of the larger classes until each class has as
+/// many examples as the smallest class.
+///
+/// The remaining examples are kept in their original order.
+///
+/// Panics if the number of rows of `x` does not match the number of labels.
+pub fn random_undersample<T: Clone, L: Ord + Clone>(x: &Matrix<T>, y: &[L], seed: [u32; 4]) -> (Matrix<T>, Vec<L>) {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let g = GroupBy::new(x, y);
+    let min = g.counts().into_iter().min().unwrap_or(0);
+
+    let mut idx = Vec::new();
+    for &(_, ref v) in g.groups() {
+        let mut v = v.clone();
+        rng.shuffle(&mut v);
+        idx.extend_from_slice(&v[..min]);
+    }
+    idx.sort();
+    rows_of(x, y, &idx)
+}
+
+/// Synthetic minority oversampling technique (SMOTE).
+///
+/// Adds synthetic examples to the smaller classes until each class has as
+/// many examples as the largest class. A synthetic example is created by
+/// choosing a random example of the class, choosing one of its `k` nearest
+/// neighbours (Euclidean distance) within the same class and interpolating
+/// at a random position between both examples. If a class contains only one
+/// example it is duplicated.
+///
+/// The original examples are kept in their original order, the synthetic
+/// examples are appended.
+///
+/// Panics if `k` is zero or if the number of rows of `x` does not match the
+/// number of labels.
+pub fn smote<T: Float, L: Ord + Clone>(x: &Matrix<T>, y: &[L], k: usize, seed: [u32; 4]) -> (Matrix<T>, Vec<L>)
+    where Euclid: Distance<T> {
+
+    assert!(x.rows() == y.len(), "Number of rows does not match the number of labels.");
+    assert!(k > 0, "Number of neighbours must be at least 1.");
+
+    let mut rng = XorShiftRng::from_seed(seed);
+    let g = GroupBy::new(x, y);
+    let max = g.counts().into_iter().max().unwrap_or(0);
+
+    let mut v = x.buf().clone();
+    let mut labels = y.to_vec();
+    for &(ref l, ref idx) in g.groups() {
+        if idx.len() == max {
+            continue;
+        }
+        let m = x.select_rows(idx);
+        for _ in idx.len()..max {
+            let i = rng.gen_range(0, idx.len());
+            let a = m.row(i).unwrap();
+            // the nearest neighbour is the example itself
+            let nn: Vec<usize> = scan(&m, a, k + 1, |p, q| Euclid::compute(p, q).unwrap())
+                .unwrap()
+                .into_iter()
+                .filter(|&j| j != i)
+                .collect();
+            let b = if nn.is_empty() { a } else { m.row(nn[rng.gen_range(0, nn.len())]).unwrap() };
+            let gap = T::from(rng.gen::<f64>()).unwrap();
+            v.extend(a.iter().zip(b.iter()).map(|(&p, &q)| p + gap * (q - p)));
+            labels.push(l.clone());
+        }
+    }
+
+    let n = labels.len();
+    (Matrix::from_vec(v, n, x.cols()), labels)
+}
+
+/// Sampler of mini-batches whose classes are stratified or balanced.
+///
+/// Each call of [epoch](#method.epoch) returns the indexes of the examples
+/// of `ceil(n / batch_size)` batches for `n` examples.
+#[derive(Clone, Debug)]
+pub struct StratifiedBatchSampler {
+    classes: Vec<Vec<usize>>,
+    n: usize,
+    batch_size: usize,
+    balanced: bool,
+    rng: XorShiftRng
+}
+
+impl StratifiedBatchSampler {
+
+    /// Creates a sampler whose batches have (approximately) the class
+    /// ratios of the labels `y`. Each epoch contains each example exactly
+    /// once.
+    ///
+    /// Panics if `y` is empty or if `batch_size` is zero.
+    pub fn new<L: Ord + Clone>(y: &[L], batch_size: usize, seed: [u32; 4]) -> StratifiedBatchSampler {
+        StratifiedBatchSampler::create(y, batch_size, false, seed)
+    }
+
+    /// Creates a sampler whose batches contain (approximately) the same
+    /// number of examples of each class. The examples of each class are
+    /// drawn without replacement until all of them have been used, then the
+    /// class is reshuffled, i.e. the examples of small classes occur more
+    /// than once per epoch and those of large classes may not occur at all.
+    ///
+    /// Panics if `y` is empty or if `batch_size` is zero.
+    pub fn balanced<L: Ord + Clone>(y: &[L], batch_size: usize, seed: [u32; 4]) -> StratifiedBatchSampler {
+        StratifiedBatchSampler::create(y, batch_size, true, seed)
+    }
+
+    fn create<L: Ord + Clone>(y: &[L], batch_size: usize, balanced: bool, seed: [u32; 4]) -> StratifiedBatchSampler {
+
+        assert!(!y.is_empty(), "Labels must not be empty.");
+        assert!(batch_size > 0, "Batch size must be greater than zero.");
+        StratifiedBatchSampler {
+            classes: group_indexes(y).into_iter().map(|(_, v)| v).collect(),
+            n: y.len(),
+            batch_size: batch_size,
+            balanced: balanced,
+            rng: XorShiftRng::from_seed(seed)
+        }
+    }
+
+    /// Returns the number of batches per epoch.
+    pub fn n_batches(&self) -> usize {
+        (self.n + self.batch_size - 1) / self.batch_size
+    }
+
+    /// Returns the batches of the next epoch.
+    pub fn epoch(&mut self) -> Vec<Vec<usize>> {
+
+        let order = if self.balanced { self.balanced_order() } else { self.stratified_order() };
+        order.chunks(self.batch_size).map(|c| c.to_vec()).collect()
+    }
+
+    // each example of a class with n examples at the (shuffled) position j
+    // gets the key (j + u) / n with a random u in [0, 1); sorting by the key
+    // interleaves the classes according to their sizes
+    fn stratified_order(&mut self) -> Vec<usize> {
+
+        let mut keys = Vec::with_capacity(self.n);
+        for c in &mut self.classes {
+            self.rng.shuffle(c);
+            let n = c.len() as f64;
+            for (j, &i) in c.iter().enumerate() {
+                keys.push(((j as f64 + self.rng.gen::<f64>()) / n, i));
+            }
+        }
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        keys.into_iter().map(|(_, i)| i).collect()
+    }
+
+    fn balanced_order(&mut self) -> Vec<usize> {
+
+        let k = self.classes.len();
+        let mut pos = vec![0; k];
+        for c in &mut self.classes {
+            self.rng.shuffle(c);
+        }
+        let mut order = Vec::with_capacity(self.n);
+        for b in 0..self.n_batches() {
+            let size = self.batch_size.min(self.n - b * self.batch_size);
+            let mut batch = Vec::with_capacity(size);
+            // the classes which get an additional example rotate
+            for s in 0..size {
+                let c = (b * self.batch_size + s) % k;
+                if pos[c] == self.classes[c].len() {
+                    self.rng.shuffle(&mut self.classes[c]);
+                    pos[c] = 0;
+                }
+                batch.push(self.classes[c][pos[c]]);
+                pos[c] += 1;
+            }
+            self.rng.shuffle(&mut batch);
+            order.extend(batch);
+        }
+        order
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    fn count<L: PartialEq>(y: &[L], l: L) -> usize {
+        y.iter().filter(|&x| *x == l).count()
+    }
+
+    #[test]
+    fn test_random_oversample() {
+
+        let x = mat![0.0; 1.0; 2.0; 3.0; 10.0];
+        let y = vec!['a', 'a', 'a', 'a', 'b'];
+
+        let (xs, ys) = random_oversample(&x, &y, [1, 2, 3, 4]);
+        assert_eq!(xs.rows(), 8);
+        assert_eq!(count(&ys, 'a'), 4);
+        assert_eq!(count(&ys, 'b'), 4);
+        assert_eq!(xs.buf()[..5].to_vec(), x.buf().clone());
+        for (r, &l) in xs.row_iter().zip(ys.iter()) {
+            assert_eq!(l == 'b', r[0] == 10.0);
+        }
+    }
+
+    #[test]
+    fn test_random_undersample() {
+
+        let x = mat![0.0; 1.0; 2.0; 3.0; 10.0; 11.0];
+        let y = vec![0, 0, 0, 0, 1, 1];
+
+        let (xs, ys) = random_undersample(&x, &y, [1, 2, 3, 4]);
+        assert_eq!(xs.rows(), 4);
+        assert_eq!(count(&ys, 0), 2);
+        assert_eq!(ys[2..].to_vec(), vec![1, 1]);
+        assert_eq!(xs.buf()[2..].to_vec(), vec![10.0, 11.0]);
+    }
+
+    #[test]
+    fn test_smote() {
+
+        let x = mat![
+            0.0, 0.0;
+            1.0, 0.0;
+            2.0, 0.0;
+            3.0, 0.0;
+            10.0, 10.0;
+            10.0, 12.0;
+            50.0, 50.0
+        ];
+        let y = vec![0, 0, 0, 0, 1, 1, 2];
+
+        let (xs, ys) = smote(&x, &y, 3, [1, 2, 3, 4]);
+        assert_eq!(xs.rows(), 12);
+        assert_eq!(count(&ys, 0), 4);
+        assert_eq!(count(&ys, 1), 4);
+        assert_eq!(count(&ys, 2), 4);
+
+        for (r, &l) in xs.row_iter().zip(ys.iter()).skip(7) {
+            match l {
+                1 => assert!(r[0] == 10.0 && r[1] >= 10.0 && r[1] <= 12.0),
+                2 => assert_eq!(r, &[50.0, 50.0]),
+                _ => panic!("unexpected label")
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_batch_sampler() {
+
+        let y: Vec<usize> = (0..100).map(|i| if i % 10 == 0 { 1 } else if i % 10 < 4 { 2 } else { 0 }).collect();
+
+        let mut s = StratifiedBatchSampler::new(&y, 20, [1, 2, 3, 4]);
+        assert_eq!(s.n_batches(), 5);
+        let e = s.epoch();
+        assert_eq!(e.len(), 5);
+        for b in &e {
+            // 2 of class 1, 6 of class 2 and 12 of class 0 (+- 1)
+            let c1 = b.iter().filter(|&&i| y[i] == 1).count();
+            let c2 = b.iter().filter(|&&i| y[i] == 2).count();
+            assert!(c1 >= 1 && c1 <= 3 && c2 >= 5 && c2 <= 7);
+        }
+        let mut all: Vec<usize> = e.into_iter().flat_map(|b| b).collect();
+        all.sort();
+        assert_eq!(all, (0..100).collect::<Vec<usize>>());
+        assert!(s.epoch() != s.epoch());
+
+        let mut s = StratifiedBatchSampler::balanced(&y, 30, [1, 2, 3, 4]);
+        let e = s.epoch();
+        assert_eq!(e.iter().map(|b| b.len()).collect::<Vec<usize>>(), vec![30, 30, 30, 10]);
+        for l in 0..3 {
+            assert_eq!(e[0].iter().filter(|&&i| y[i] == l).count(), 10);
+        }
+    }
+}
@@ -5,8 +5,9 @@ extern crate num;
 use self::num::traits::Float;
 use gaussian::{Gaussian, GaussianFunctions};
 use matrix::Matrix;
-use math::{Dimension, Normalization, Mean, Var};
+use math::{Dimension, Normalization, Mean, Var, Quantile};
 use ops_inplace::VectorVectorOpsInPlace;
+use io::model::{Model, Encoder, Decoder};
 
 /// Trait to scale a matrix.
 pub trait ScaleMatrix<T> {
@@ -151,12 +152,315 @@ scaling_vec_impl!{ f32 f64 }
 
 // ----------------------------------------------------------------------------
 
+/// Trait for scalers which learn their parameters from a training set and
+/// apply the same transformation to other matrices.
+///
+/// In contrast to [`ScaleMatrix`](trait.ScaleMatrix.html) the parameters are
+/// computed once via `fit` and can then be used to transform the training set,
+/// a validation set or new examples in exactly the same way. Each row of a
+/// matrix is interpreted as an example and each column as a feature.
+pub trait Scaler<T>: Sized {
+    /// Computes the parameters of the scaler from the examples in `m`.
+    fn fit(m: &Matrix<T>) -> Self;
+
+    /// Scales each column of `m` with the parameters learned via `fit`.
+    ///
+    /// Panics if the number of columns does not match the number of
+    /// columns of the matrix used for fitting.
+    fn transform(&self, m: &Matrix<T>) -> Matrix<T>;
+
+    /// Reverts the transformation, i.e. `inverse_transform(transform(m))`
+    /// returns `m` (except for rounding errors).
+    ///
+    /// Panics if the number of columns does not match the number of
+    /// columns of the matrix used for fitting.
+    fn inverse_transform(&self, m: &Matrix<T>) -> Matrix<T>;
+
+    /// Computes the parameters from `m` and returns the scaler together
+    /// with the transformed matrix.
+    fn fit_transform(m: &Matrix<T>) -> (Self, Matrix<T>) {
+        let s = Self::fit(m);
+        let r = s.transform(m);
+        (s, r)
+    }
+}
+
+/// Scales each feature to zero mean and unit variance.
+///
+/// For each column `j` the value `(x - mean(j)) / std(j)` is computed where
+/// the standard deviation is computed with `Normalization::N`. If the standard
+/// deviation of a column is smaller than 0.0001 then 1.0 is used in the
+/// denominator so that constant columns are only centered.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::scaling::*;
+///
+/// # fn main() {
+/// let train = mat![
+///     1.0, 10.0;
+///     3.0, 30.0
+/// ];
+/// let s = StandardScaler::fit(&train);
+/// assert_eq!(s.mean(), &[2.0, 20.0]);
+/// assert_eq!(s.std(), &[1.0, 10.0]);
+///
+/// let x = s.transform(&mat![2.0, 40.0]);
+/// assert_eq!(x, mat![0.0, 2.0]);
+/// assert_eq!(s.inverse_transform(&x), mat![2.0, 40.0]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StandardScaler<T> {
+    mean: Vec<T>,
+    std: Vec<T>
+}
+
+impl <T> StandardScaler<T> {
+    /// Returns the mean of each feature.
+    pub fn mean(&self) -> &[T] { &self.mean }
+
+    /// Returns the standard deviation of each feature that is used for scaling.
+    pub fn std(&self) -> &[T] { &self.std }
+}
+
+/// Scales each feature into the interval [0, 1].
+///
+/// For each column `j` the value `(x - min(j)) / (max(j) - min(j))` is computed.
+/// If all values of a column are equal the denominator is set to 1.0. Values
+/// of new examples which are outside of the range seen during `fit` are not
+/// clipped, i.e. they are mapped to values outside of [0, 1].
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::scaling::*;
+///
+/// # fn main() {
+/// let train = mat![
+///     1.0, -5.0;
+///     3.0,  5.0;
+///     2.0,  0.0
+/// ];
+/// let s = MinMaxScaler::fit(&train);
+/// assert_eq!(s.min(), &[1.0, -5.0]);
+/// assert_eq!(s.max(), &[3.0, 5.0]);
+/// assert_eq!(s.transform(&train), mat![0.0, 0.0; 1.0, 1.0; 0.5, 0.5]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MinMaxScaler<T> {
+    min: Vec<T>,
+    max: Vec<T>
+}
+
+impl <T> MinMaxScaler<T> {
+    /// Returns the minimum of each feature.
+    pub fn min(&self) -> &[T] { &self.min }
+
+    /// Returns the maximum of each feature.
+    pub fn max(&self) -> &[T] { &self.max }
+}
+
+/// Scales each feature by statistics that are robust to outliers.
+///
+/// For each column `j` the value `(x - median(j)) / iqr(j)` is computed where
+/// `iqr(j)` is the interquartile range, i.e. the difference between the
+/// 0.75-quantile and the 0.25-quantile of the values in column `j`. Because
+/// the median and the interquartile range are not influenced by a small
+/// number of very large or very small values this scaler should be preferred
+/// over the [`StandardScaler`](struct.StandardScaler.html) if the data
+/// contains outliers. If the interquartile range of a column is zero 1.0 is
+/// used in the denominator.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate rustml;
+/// use rustml::*;
+/// use rustml::scaling::*;
+///
+/// # fn main() {
+/// let train = mat![
+///     1.0;
+///     2.0;
+///     3.0;
+///     4.0;
+///     1000.0  // outlier
+/// ];
+/// let s = RobustScaler::fit(&train);
+/// assert_eq!(s.center(), &[3.0]);
+/// assert_eq!(s.scale(), &[2.0]);
+/// assert_eq!(s.transform(&mat![5.0]), mat![1.0]);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RobustScaler<T> {
+    center: Vec<T>,
+    scale: Vec<T>
+}
+
+impl <T> RobustScaler<T> {
+    /// Returns the median of each feature.
+    pub fn center(&self) -> &[T] { &self.center }
+
+    /// Returns the interquartile range of each feature that is used for scaling.
+    pub fn scale(&self) -> &[T] { &self.scale }
+}
+
+/// Applies `f(value, column)` to each element of `m`.
+fn map_columns<T: Clone, F>(m: &Matrix<T>, cols: usize, f: F) -> Matrix<T>
+    where F: Fn(&T, usize) -> T {
+
+    assert!(m.cols() == cols, "Number of columns does not match the number of features.");
+    Matrix::from_vec(
+        m.iter().enumerate().map(|(i, x)| f(x, i % cols)).collect(),
+        m.rows(), m.cols()
+    )
+}
+
+macro_rules! scaler_impl {
+    ($($t:ty)*) => ($(
+
+        impl Scaler<$t> for StandardScaler<$t> {
+
+            fn fit(m: &Matrix<$t>) -> StandardScaler<$t> {
+
+                StandardScaler {
+                    mean: m.mean(Dimension::Column),
+                    std: m.var(Dimension::Column, Normalization::N).iter()
+                        .map(|&x| x.sqrt())
+                        .map(|x| if x < 0.0001 { 1 as $t } else { x })
+                        .collect()
+                }
+            }
+
+            fn transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.mean.len(), |&x, j| (x - self.mean[j]) / self.std[j])
+            }
+
+            fn inverse_transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.mean.len(), |&x, j| x * self.std[j] + self.mean[j])
+            }
+        }
+
+        impl Scaler<$t> for MinMaxScaler<$t> {
+
+            fn fit(m: &Matrix<$t>) -> MinMaxScaler<$t> {
+
+                let mut min = m.row(0).map(|r| r.to_vec()).unwrap_or(vec![]);
+                let mut max = min.clone();
+                for row in m.row_iter_at(1) {
+                    for (j, &x) in row.iter().enumerate() {
+                        min[j] = min[j].min(x);
+                        max[j] = max[j].max(x);
+                    }
+                }
+                MinMaxScaler {
+                    min: min,
+                    max: max
+                }
+            }
+
+            fn transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.min.len(), |&x, j| (x - self.min[j]) / self.range(j))
+            }
+
+            fn inverse_transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.min.len(), |&x, j| x * self.range(j) + self.min[j])
+            }
+        }
+
+        impl Scaler<$t> for RobustScaler<$t> {
+
+            fn fit(m: &Matrix<$t>) -> RobustScaler<$t> {
+
+                let cols = m.col_iter().collect::<Vec<_>>();
+                RobustScaler {
+                    center: cols.iter().map(|c| c.median()).collect(),
+                    scale: cols.iter()
+                        .map(|c| c.iqr())
+                        .map(|x| if x == 0 as $t { 1 as $t } else { x })
+                        .collect()
+                }
+            }
+
+            fn transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.center.len(), |&x, j| (x - self.center[j]) / self.scale[j])
+            }
+
+            fn inverse_transform(&self, m: &Matrix<$t>) -> Matrix<$t> {
+                map_columns(m, self.center.len(), |&x, j| x * self.scale[j] + self.center[j])
+            }
+        }
+
+        impl MinMaxScaler<$t> {
+
+            fn range(&self, j: usize) -> $t {
+                let d = self.max[j] - self.min[j];
+                if d == 0 as $t { 1 as $t } else { d }
+            }
+        }
+    )*)
+}
+
+scaler_impl!{ f32 f64 }
+
+impl Model for StandardScaler<f64> {
+
+    fn tag() -> &'static str { "rustml.scaling.StandardScaler" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.mean);
+        e.f64s(&self.std);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<StandardScaler<f64>, String> {
+        Ok(StandardScaler { mean: try!(d.f64s()), std: try!(d.f64s()) })
+    }
+}
+
+impl Model for MinMaxScaler<f64> {
+
+    fn tag() -> &'static str { "rustml.scaling.MinMaxScaler" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.min);
+        e.f64s(&self.max);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<MinMaxScaler<f64>, String> {
+        Ok(MinMaxScaler { min: try!(d.f64s()), max: try!(d.f64s()) })
+    }
+}
+
+impl Model for RobustScaler<f64> {
+
+    fn tag() -> &'static str { "rustml.scaling.RobustScaler" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.center);
+        e.f64s(&self.scale);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<RobustScaler<f64>, String> {
+        Ok(RobustScaler { center: try!(d.f64s()), scale: try!(d.f64s()) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
 #[cfg(test)]
 mod tests {
     extern crate num;
 
     use super::*;
-    use matrix::Matrix;
+    use matrix::{Matrix, Similar};
     use gaussian::{Gaussian, GaussianFunctions};
 
     #[test]
@@ -213,6 +517,66 @@ mod tests {
         assert_eq!(y, vec![-0.25, -0.5, -0.75, -0.2]);
     }
 
+    #[test]
+    fn test_standard_scaler() {
+        let m = mat![
+            1.0, 2.0, 5.0;
+            3.0, 6.0, 5.0;
+            5.0, 4.0, 5.0
+        ];
+        let (s, x) = StandardScaler::fit_transform(&m);
+        assert_eq!(s.mean(), &[3.0, 4.0, 5.0]);
+        assert!(s.std().similar(&vec![1.63299, 1.63299, 1.0], 0.0001));
+        assert!(x.similar(&mat![
+            -1.22474, -1.22474, 0.0;
+             0.0,      1.22474, 0.0;
+             1.22474,  0.0,     0.0
+        ], 0.0001));
+        assert!(s.inverse_transform(&x).similar(&m, 0.0001));
+
+        let y = s.transform(&mat![7.0, 4.0, 6.0]);
+        assert!(y.similar(&mat![2.44949, 0.0, 1.0], 0.0001));
+    }
+
+    #[test]
+    fn test_minmax_scaler() {
+        let m = mat![
+            2.0f32, 1.0;
+            4.0,    1.0;
+            3.0,    1.0
+        ];
+        let (s, x) = MinMaxScaler::fit_transform(&m);
+        assert_eq!(s.min(), &[2.0, 1.0]);
+        assert_eq!(s.max(), &[4.0, 1.0]);
+        assert_eq!(x, mat![0.0, 0.0; 1.0, 0.0; 0.5, 0.0]);
+        assert_eq!(s.inverse_transform(&x), m);
+        assert_eq!(s.transform(&mat![6.0, 2.0]), mat![2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_robust_scaler() {
+        let m = mat![
+            1.0, 5.0;
+            2.0, 5.0;
+            3.0, 5.0;
+            4.0, 5.0;
+            -500.0, 5.0
+        ];
+        let (s, x) = RobustScaler::fit_transform(&m);
+        assert_eq!(s.center(), &[2.0, 5.0]);
+        assert_eq!(s.scale(), &[2.0, 1.0]);
+        assert_eq!(x.col(0).unwrap(), vec![-0.5, 0.0, 0.5, 1.0, -251.0]);
+        assert_eq!(x.col(1).unwrap(), vec![0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(s.inverse_transform(&x), m);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_scaler_dimension_mismatch() {
+        let s = StandardScaler::fit(&mat![1.0, 2.0; 3.0, 4.0]);
+        s.transform(&mat![1.0, 2.0, 3.0]);
+    }
+
 }
 
  
@@ -0,0 +1,251 @@
+//! Serving predictions of a fitted model over JSON lines.
+//!
+//! The function [serve](fn.serve.html) wraps any fitted
+//! [Estimator](../model_selection/trait.Estimator.html), reads one feature
+//! vector per line from a reader and writes one prediction per line to a
+//! writer. A feature vector is a JSON array of numbers, e.g. `[1.5, -2, 3e4]`,
+//! where `null` denotes a missing value (`NaN`). Each prediction is written
+//! as `{"prediction": ...}` where the prediction is converted into a JSON
+//! value via [ToJson](trait.ToJson.html), i.e. labels are written as
+//! strings and non-finite numbers as `null`. The output is flushed after
+//! each line, so
+//! that the function can be hooked into pipes or sockets. If a line cannot
+//! be parsed `{"error": "..."}` is written instead and the next line is
+//! processed. Empty lines are ignored.
+//!
+//! The module is only available with the feature `serve`.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::matrix::Matrix;
+//! use rustml::model_selection::Estimator;
+//! use rustml::serve::serve;
+//!
+//! struct Sum;
+//!
+//! impl Estimator<f64, f64> for Sum {
+//!     fn fit(&mut self, _x: &Matrix<f64>, _y: &[f64]) { }
+//!     fn predict(&self, x: &Matrix<f64>) -> Vec<f64> {
+//!         x.row_iter().map(|r| r.iter().sum()).collect()
+//!     }
+//! }
+//!
+//! let input = "[1, 2]\n[0.5, 0.25]\n";
+//! let mut output = vec![];
+//! let n = serve(&Sum, input.as_bytes(), &mut output).unwrap();
+//! assert_eq!(n, 2);
+//! assert_eq!(String::from_utf8(output).unwrap(), "{\"prediction\": 3}\n{\"prediction\": 0.75}\n");
+//! ```
+use std::f64;
+use std::io::{BufRead, Write};
+
+use matrix::Matrix;
+use model_selection::Estimator;
+
+/// Parses a JSON array of numbers. The value `null` is parsed as `NaN`.
+pub fn parse_features(line: &str) -> Result<Vec<f64>, String> {
+
+    let s = line.trim();
+    if !s.starts_with('[') || !s.ends_with(']') || s.len() < 2 {
+        return Err("Expected a JSON array of numbers.".to_string());
+    }
+    let inner = s[1..s.len() - 1].trim();
+    if inner.is_empty() {
+        return Err("Empty feature vector.".to_string());
+    }
+    inner.split(',')
+        .map(|v| {
+            let v = v.trim();
+            if v == "null" {
+                return Ok(f64::NAN);
+            }
+            // only accept JSON numbers, i.e. reject "inf", "NaN", etc.
+            if !v.chars().all(|c| c.is_digit(10) || "+-.eE".contains(c)) {
+                return Err(format!("Invalid number {}.", v));
+            }
+            v.parse::<f64>().map_err(|_| format!("Invalid number {}.", v))
+        })
+        .collect()
+}
+
+/// Escapes a string so that it can be written between the quotes of a JSON
+/// string. Control characters are escaped, too, so that the string does not
+/// contain line breaks.
+fn escape(s: &str) -> String {
+
+    let mut r = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            c if (c as u32) < 0x20 => r.push_str(&format!("\\u{:04x}", c as u32)),
+            c => r.push(c)
+        }
+    }
+    r
+}
+
+/// Types of predictions which can be written as JSON value.
+pub trait ToJson {
+
+    /// Returns the value as JSON.
+    fn to_json(&self) -> String;
+}
+
+macro_rules! to_json_float_impl {
+    ($($t:ty)*) => ($(
+        impl ToJson for $t {
+            // JSON has no representation of NaN and infinity
+            fn to_json(&self) -> String {
+                if self.is_finite() { self.to_string() } else { "null".to_string() }
+            }
+        }
+    )*)
+}
+
+macro_rules! to_json_display_impl {
+    ($($t:ty)*) => ($(
+        impl ToJson for $t {
+            fn to_json(&self) -> String {
+                self.to_string()
+            }
+        }
+    )*)
+}
+
+to_json_float_impl!{ f32 f64 }
+to_json_display_impl!{ u8 u16 u32 u64 usize i8 i16 i32 i64 isize bool }
+
+impl ToJson for str {
+    fn to_json(&self) -> String {
+        format!("\"{}\"", escape(self))
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> String {
+        self.as_str().to_json()
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> String {
+        self.to_string().to_json()
+    }
+}
+
+impl <'a, T: ToJson + ?Sized> ToJson for &'a T {
+    fn to_json(&self) -> String {
+        (**self).to_json()
+    }
+}
+
+/// Reads JSON lines of feature vectors from `input` and writes the
+/// predictions of `estimator` to `output` as JSON lines.
+///
+/// All feature vectors must have the same length as the first one; lines
+/// with a different length are answered with an error. Returns the number of
+/// predictions or an error if reading or writing fails.
+pub fn serve<E, L, R, W>(estimator: &E, input: R, mut output: W) -> Result<usize, String>
+    where E: Estimator<f64, L>, L: ToJson, R: BufRead, W: Write {
+
+    let mut n = 0;
+    let mut cols = None;
+    for line in input.lines() {
+        let line = try!(line.map_err(|e| format!("Could not read input: {}", e)));
+        if line.trim().is_empty() {
+            continue;
+        }
+        let r = parse_features(&line).and_then(|x| match cols {
+            Some(c) if c != x.len() => Err(format!("Expected {} features but got {}.", c, x.len())),
+            _ => Ok(x)
+        });
+        let out = match r {
+            Ok(x) => {
+                cols = Some(x.len());
+                let m = Matrix::from_vec(x, 1, cols.unwrap());
+                n += 1;
+                format!("{{\"prediction\": {}}}\n", estimator.predict(&m)[0].to_json())
+            },
+            Err(e) => format!("{{\"error\": \"{}\"}}\n", escape(&e))
+        };
+        try!(output.write_all(out.as_bytes()).and_then(|_| output.flush())
+            .map_err(|e| format!("Could not write output: {}", e)));
+    }
+    Ok(n)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use model_selection::Estimator;
+
+    struct Count;
+
+    impl Estimator<f64, usize> for Count {
+        fn fit(&mut self, _x: &Matrix<f64>, _y: &[usize]) { }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<usize> {
+            x.row_iter().map(|r| r.iter().filter(|v| v.is_nan()).count()).collect()
+        }
+    }
+
+    struct Label;
+
+    impl Estimator<f64, &'static str> for Label {
+        fn fit(&mut self, _x: &Matrix<f64>, _y: &[&'static str]) { }
+        fn predict(&self, x: &Matrix<f64>) -> Vec<&'static str> {
+            x.row_iter().map(|r| if r[0] > 0.0 { "a \"b\"" } else { "c\nd" }).collect()
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+
+        assert_eq!(1.5f64.to_json(), "1.5");
+        assert_eq!(f64::NAN.to_json(), "null");
+        assert_eq!(f32::INFINITY.to_json(), "null");
+        assert_eq!(3usize.to_json(), "3");
+        assert_eq!((-2i32).to_json(), "-2");
+        assert_eq!("a\\b".to_json(), "\"a\\\\b\"");
+        assert_eq!('x'.to_json(), "\"x\"");
+        assert_eq!(escape("a\"b\n\r\t\u{1}"), "a\\\"b\\n\\r\\t\\u0001");
+    }
+
+    #[test]
+    fn test_parse_features() {
+
+        assert_eq!(parse_features(" [1, -2.5, 3e2] ").unwrap(), vec![1.0, -2.5, 300.0]);
+        assert!(parse_features("[null, 1]").unwrap()[0].is_nan());
+        assert!(parse_features("[]").is_err());
+        assert!(parse_features("1, 2").is_err());
+        assert!(parse_features("[1, inf]").is_err());
+        assert!(parse_features("[1, \"a\"]").is_err());
+    }
+
+    #[test]
+    fn test_serve() {
+
+        let input = "[1, null]\n\n[null, null]\nfoo\n[1]\n";
+        let mut out = vec![];
+        assert_eq!(serve(&Count, input.as_bytes(), &mut out).unwrap(), 2);
+        let s = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines, vec![
+            "{\"prediction\": 1}",
+            "{\"prediction\": 2}",
+            "{\"error\": \"Expected a JSON array of numbers.\"}",
+            "{\"error\": \"Expected 2 features but got 1.\"}"
+        ]);
+
+        let mut out = vec![];
+        assert_eq!(serve(&Label, "[1]\n[-1]\n".as_bytes(), &mut out).unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"prediction\": \"a \\\"b\\\"\"}\n{\"prediction\": \"c\\nd\"}\n");
+    }
+}
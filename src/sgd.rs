@@ -0,0 +1,395 @@
+//! Linear models on sparse rows fitted with stochastic gradient descent.
+//!
+//! [SgdLinear](struct.SgdLinear.html) fits a linear model `w'x + b` with
+//! one update per example. The updates use the sparse
+//! [kernels](../sparse/index.html#kernels-for-sparse-rows), i.e. the cost of
+//! an update is proportional to the number of non-zero elements of the
+//! example and not to the number of features. The L2 regularization is
+//! applied lazily by keeping the weights as a scalar times a vector
+//! (Bottou, "Stochastic gradient descent tricks", 2012), so it does not
+//! touch all weights either.
+//!
+//! With [hash_bits](struct.SgdLinear.html#method.hash_bits) the column
+//! indexes are hashed into `2^bits` weights. This bounds the memory of the
+//! model for data with millions of (e.g. text) features.
+//!
+//! For the losses `Hinge` and `Log` the labels must be `0.0` or `1.0`.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::sparse::SparseMatrix;
+//! use rustml::sgd::{SgdLinear, Loss};
+//!
+//! let mut x = SparseMatrix::new(1000);
+//! let mut y = vec![];
+//! for i in 0..200 {
+//!     // the class is determined by the feature 7 or 900
+//!     let c = if i % 2 == 0 { 7 } else { 900 };
+//!     x.add_row(&[(c, 1.0), ((i * 13) % 1000, 0.5)]);
+//!     y.push((i % 2) as f64);
+//! }
+//!
+//! let mut m = SgdLinear::new(Loss::Log).epochs(5).hash_bits(12).seed([1, 2, 3, 4]);
+//! m.fit(&x, &y);
+//! assert_eq!(m.predict(&x), y);
+//! ```
+extern crate rand;
+
+use self::rand::{Rng, XorShiftRng};
+
+use sparse::{SparseMatrix, dot, axpy, hashed_dot, hashed_axpy};
+use progress::{Progress, ProgressInfo, NoProgress, report};
+use io::model::{Model, Encoder, Decoder};
+use rng;
+
+/// Loss functions of [SgdLinear](struct.SgdLinear.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Loss {
+    /// Squared error for regression.
+    Squared,
+    /// Hinge loss of a linear SVM.
+    Hinge,
+    /// Log loss of logistic regression.
+    Log
+}
+
+impl Loss {
+
+    /// Returns the loss of the prediction `p` for the label `y`.
+    pub fn loss(&self, p: f64, y: f64) -> f64 {
+
+        match *self {
+            Loss::Squared => 0.5 * (p - y) * (p - y),
+            Loss::Hinge => (1.0 - sign(y) * p).max(0.0),
+            Loss::Log => {
+                let z = -sign(y) * p;
+                // log(1 + exp(z)) without overflow
+                if z > 0.0 { z + (-z).exp().ln_1p() } else { z.exp().ln_1p() }
+            }
+        }
+    }
+
+    /// Returns the derivative of the loss with respect to `p`.
+    pub fn derivative(&self, p: f64, y: f64) -> f64 {
+
+        match *self {
+            Loss::Squared => p - y,
+            Loss::Hinge => if sign(y) * p < 1.0 { -sign(y) } else { 0.0 },
+            Loss::Log => {
+                let s = sign(y);
+                -s / (1.0 + (s * p).exp())
+            }
+        }
+    }
+
+    fn id(&self) -> usize {
+        match *self {
+            Loss::Squared => 0,
+            Loss::Hinge => 1,
+            Loss::Log => 2
+        }
+    }
+}
+
+fn sign(y: f64) -> f64 {
+    if y > 0.0 { 1.0 } else { -1.0 }
+}
+
+/// Linear model on sparse rows fitted with stochastic gradient descent.
+#[derive(Clone, Debug)]
+pub struct SgdLinear {
+    loss: Loss,
+    lambda: f64,
+    eta0: f64,
+    epochs: usize,
+    hash_bits: Option<usize>,
+    rng: XorShiftRng,
+    w: Vec<f64>,
+    scale: f64,
+    bias: f64,
+    t: usize
+}
+
+impl SgdLinear {
+
+    /// Creates an unfitted model with the given loss and the default
+    /// parameters, i.e. an L2 regularization of `1e-4`, an initial learning
+    /// rate of 0.1, 5 epochs and no hashing. The random number generator
+    /// which shuffles the examples is created with [rng](../rng/fn.rng.html).
+    pub fn new(loss: Loss) -> SgdLinear {
+        SgdLinear {
+            loss: loss,
+            lambda: 1e-4,
+            eta0: 0.1,
+            epochs: 5,
+            hash_bits: None,
+            rng: rng::rng(),
+            w: vec![],
+            scale: 1.0,
+            bias: 0.0,
+            t: 0
+        }
+    }
+
+    /// Sets the strength of the L2 regularization.
+    pub fn lambda(&self, lambda: f64) -> SgdLinear {
+        SgdLinear { lambda: lambda, .. self.clone() }
+    }
+
+    /// Sets the initial learning rate. The learning rate of the `t`-th
+    /// update is `eta0 / (1 + eta0 * lambda * t)`.
+    pub fn eta0(&self, eta0: f64) -> SgdLinear {
+        SgdLinear { eta0: eta0, .. self.clone() }
+    }
+
+    /// Sets the number of passes of `fit` over the data.
+    pub fn epochs(&self, n: usize) -> SgdLinear {
+        SgdLinear { epochs: n, .. self.clone() }
+    }
+
+    /// Hashes the column indexes into `2^bits` weights.
+    ///
+    /// Panics if `bits` is zero or larger than 32.
+    pub fn hash_bits(&self, bits: usize) -> SgdLinear {
+
+        assert!(bits > 0 && bits <= 32, "Number of bits must be in 1..32.");
+        SgdLinear { hash_bits: Some(bits), .. self.clone() }
+    }
+
+    /// Seeds the random number generator which shuffles the examples.
+    pub fn seed(&self, seed: [u32; 4]) -> SgdLinear {
+        SgdLinear { rng: rng::seeded_rng(seed), .. self.clone() }
+    }
+
+    /// Fits the model on the rows of `x` with the labels `y`. The examples
+    /// are shuffled in each epoch.
+    ///
+    /// Panics if the number of rows of `x` and the number of labels differ.
+    pub fn fit(&mut self, x: &SparseMatrix<f64>, y: &[f64]) {
+        self.fit_with_progress(x, y, &mut NoProgress)
+    }
+
+    /// Like [fit](#method.fit) but reports the mean loss of each epoch to
+    /// `progress`.
+    pub fn fit_with_progress<P: Progress + ?Sized>(&mut self, x: &SparseMatrix<f64>, y: &[f64], progress: &mut P) {
+
+        assert!(x.rows() == y.len(), "Number of rows and number of labels must be equal.");
+        self.reset(x.cols());
+        let mut order: Vec<usize> = (0..x.rows()).collect();
+        for e in 0..self.epochs {
+            self.rng.shuffle(&mut order);
+            let mut loss = 0.0;
+            for &i in &order {
+                let (idx, vals) = x.row(i).unwrap();
+                loss += self.step(idx, vals, y[i]);
+            }
+            report(progress, &ProgressInfo {
+                algorithm: "sgd",
+                iteration: e + 1,
+                max_iter: self.epochs,
+                loss: Some(loss / (x.rows().max(1) as f64))
+            });
+        }
+    }
+
+    /// Updates the model with one pass over the rows of `x` in their order.
+    /// The weights are created with the first call.
+    ///
+    /// Panics if the number of rows of `x` and the number of labels differ
+    /// or if the model is not hashed and `x` has more columns than the
+    /// previous batches.
+    pub fn partial_fit(&mut self, x: &SparseMatrix<f64>, y: &[f64]) {
+
+        assert!(x.rows() == y.len(), "Number of rows and number of labels must be equal.");
+        if self.w.is_empty() {
+            self.reset(x.cols());
+        }
+        assert!(self.hash_bits.is_some() || x.cols() <= self.w.len(), "Number of columns does not match the number of weights.");
+        for (i, (idx, vals)) in x.row_iter().enumerate() {
+            self.step(idx, vals, y[i]);
+        }
+    }
+
+    fn reset(&mut self, cols: usize) {
+
+        let n = match self.hash_bits {
+            Some(b) => 1 << b,
+            None => cols
+        };
+        self.w = vec![0.0; n];
+        self.scale = 1.0;
+        self.bias = 0.0;
+        self.t = 0;
+    }
+
+    // one update with the example (idx, vals); returns the loss before the update
+    fn step(&mut self, idx: &[usize], vals: &[f64], y: f64) -> f64 {
+
+        let p = self.eval(idx, vals);
+        let g = self.loss.derivative(p, y);
+        let eta = self.eta0 / (1.0 + self.eta0 * self.lambda * self.t as f64);
+
+        // weight decay of the L2 regularization
+        self.scale *= (1.0 - eta * self.lambda).max(1e-9);
+        if g != 0.0 {
+            let alpha = -eta * g / self.scale;
+            match self.hash_bits {
+                Some(_) => hashed_axpy(alpha, idx, vals, &mut self.w),
+                None => axpy(alpha, idx, vals, &mut self.w)
+            }
+            self.bias -= eta * g;
+        }
+        if self.scale < 1e-9 {
+            for v in &mut self.w {
+                *v *= self.scale;
+            }
+            self.scale = 1.0;
+        }
+        self.t += 1;
+        self.loss.loss(p, y)
+    }
+
+    fn eval(&self, idx: &[usize], vals: &[f64]) -> f64 {
+
+        let d = match self.hash_bits {
+            Some(_) => hashed_dot(idx, vals, &self.w),
+            None => dot(idx, vals, &self.w)
+        };
+        self.scale * d + self.bias
+    }
+
+    /// Returns `w'x + b` for each row of `x`.
+    ///
+    /// Panics if the model is not fitted.
+    pub fn decision_function(&self, x: &SparseMatrix<f64>) -> Vec<f64> {
+
+        assert!(!self.w.is_empty(), "Model is not fitted.");
+        x.row_iter().map(|(idx, vals)| self.eval(idx, vals)).collect()
+    }
+
+    /// Returns the predicted value (loss `Squared`) or the predicted class
+    /// `0.0` or `1.0` (losses `Hinge` and `Log`) for each row of `x`.
+    ///
+    /// Panics if the model is not fitted.
+    pub fn predict(&self, x: &SparseMatrix<f64>) -> Vec<f64> {
+
+        let p = self.decision_function(x);
+        match self.loss {
+            Loss::Squared => p,
+            _ => p.iter().map(|&v| if v > 0.0 { 1.0 } else { 0.0 }).collect()
+        }
+    }
+
+    /// Returns the weights (one per column or one per bucket if the model
+    /// is hashed).
+    pub fn weights(&self) -> Vec<f64> {
+        self.w.iter().map(|v| v * self.scale).collect()
+    }
+
+    /// Returns the bias.
+    pub fn bias(&self) -> f64 {
+        self.bias
+    }
+
+    /// Returns the number of updates so far.
+    pub fn n_updates(&self) -> usize {
+        self.t
+    }
+}
+
+impl Model for SgdLinear {
+
+    fn tag() -> &'static str { "rustml.sgd.SgdLinear" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.loss.id());
+        e.f64(self.lambda);
+        e.f64(self.eta0);
+        e.usize(self.epochs);
+        e.usize(self.hash_bits.unwrap_or(0));
+        e.f64s(&self.weights());
+        e.f64(self.bias);
+        e.usize(self.t);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<SgdLinear, String> {
+
+        let loss = match try!(d.usize()) {
+            0 => Loss::Squared,
+            1 => Loss::Hinge,
+            2 => Loss::Log,
+            x => return Err(format!("Unknown loss {}.", x))
+        };
+        let mut m = SgdLinear::new(loss);
+        m.lambda = try!(d.f64());
+        m.eta0 = try!(d.f64());
+        m.epochs = try!(d.usize());
+        m.hash_bits = match try!(d.usize()) {
+            0 => None,
+            b => Some(b)
+        };
+        m.w = try!(d.f64s());
+        m.bias = try!(d.f64());
+        m.t = try!(d.usize());
+        if let Some(b) = m.hash_bits {
+            if b > 32 || m.w.len() != 1 << b {
+                return Err("Number of weights does not match the number of hash bits.".to_string());
+            }
+        }
+        Ok(m)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use io::model::{write_model, read_model};
+
+    #[test]
+    fn test_sgd_regression() {
+
+        // y = 2 * x0 - x3 + 1
+        let mut x = SparseMatrix::new(5);
+        let mut y = vec![];
+        for i in 0..100 {
+            let a = (i % 10) as f64 / 10.0;
+            let b = (i % 7) as f64 / 7.0;
+            x.add_row(&[(0, a), (3, b)]);
+            y.push(2.0 * a - b + 1.0);
+        }
+        let mut m = SgdLinear::new(Loss::Squared).lambda(0.0).epochs(200).seed([1, 2, 3, 4]);
+        m.fit(&x, &y);
+        let w = m.weights();
+        assert!((w[0] - 2.0).abs() < 0.05 && (w[3] + 1.0).abs() < 0.05);
+        assert!((m.bias() - 1.0).abs() < 0.05);
+        assert_eq!(w[1], 0.0);
+        assert_eq!(m.n_updates(), 20000);
+
+        let mut buf = vec![];
+        write_model(&mut buf, &m).unwrap();
+        let n: SgdLinear = read_model(&buf[..]).unwrap();
+        assert_eq!(n.predict(&x), m.predict(&x));
+    }
+
+    #[test]
+    fn test_sgd_classification() {
+
+        let mut x = SparseMatrix::new(1 << 30);
+        let mut y = vec![];
+        for i in 0..100 {
+            x.add_row(&[(if i % 2 == 0 { 5 } else { 1 << 29 }, 1.0)]);
+            y.push((i % 2) as f64);
+        }
+        for &l in &[Loss::Hinge, Loss::Log] {
+            let mut m = SgdLinear::new(l).hash_bits(8);
+            m.partial_fit(&x, &y);
+            assert_eq!(m.weights().len(), 256);
+            assert_eq!(m.predict(&x), y);
+        }
+        assert!(Loss::Log.loss(-1000.0, 1.0).is_finite());
+        assert_eq!(Loss::Hinge.derivative(2.0, 1.0), 0.0);
+    }
+}
@@ -0,0 +1,376 @@
+//! Module with a sparse matrix in the compressed sparse row (CSR) format.
+//!
+//! Many datasets, e.g. bag-of-words features of text documents or datasets
+//! in the libsvm format, contain mostly zeros. A sparse matrix only stores
+//! the non-zero elements of each row together with their column indexes.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::sparse::SparseMatrix;
+//!
+//! # fn main() {
+//! let mut s = SparseMatrix::new(4);
+//! s.add_row(&[(0, 1.0), (3, 2.0)]);
+//! s.add_row(&[]);
+//! s.add_row(&[(2, 3.0)]);
+//!
+//! assert_eq!(s.rows(), 3);
+//! assert_eq!(s.nnz(), 3);
+//! assert_eq!(s.get(0, 3), 2.0);
+//! assert_eq!(s.to_dense(), mat![1.0, 0.0, 0.0, 2.0; 0.0, 0.0, 0.0, 0.0; 0.0, 0.0, 3.0, 0.0]);
+//! assert_eq!(SparseMatrix::from_dense(&s.to_dense()), s);
+//! # }
+//! ```
+//!
+//! # Kernels for sparse rows
+//!
+//! [`dot`](fn.dot.html) and [`axpy`](fn.axpy.html) combine a sparse row
+//! with a dense vector in `O(nnz)` operations, i.e. their cost does not
+//! depend on the number of columns. They are the building blocks of the
+//! [SGD linear models](../sgd/index.html) on sparse rows.
+//!
+//! For text-scale data with millions of features the dense weight vector
+//! can be shrunk with the hashing trick: [`hashed_dot`](fn.hashed_dot.html)
+//! and [`hashed_axpy`](fn.hashed_axpy.html) map each column index to one
+//! of `2^bits` buckets and a sign with [`hash_feature`](fn.hash_feature.html).
+//! Collisions of columns in the same bucket cancel out in expectation
+//! because of the random signs.
+//!
+//! ```
+//! use rustml::sparse::*;
+//!
+//! let mut w = vec![0.0; 8];
+//! axpy(2.0, &[1, 5], &[1.0, 3.0], &mut w);
+//! assert_eq!(dot(&[1, 5], &[1.0, 3.0], &w), 20.0);
+//!
+//! // 16 buckets for columns with arbitrary indexes
+//! let mut h = vec![0.0; 16];
+//! hashed_axpy(1.0, &[7, 123456789], &[1.0, 2.0], &mut h);
+//! assert_eq!(hashed_dot(&[7, 123456789], &[1.0, 2.0], &h), 5.0);
+//! ```
+extern crate num;
+
+use self::num::traits::Zero;
+
+use matrix::Matrix;
+
+/// Sparse matrix in the compressed sparse row (CSR) format.
+///
+/// The column indexes of the non-zero elements of row `i` are stored in
+/// `indices[indptr[i]..indptr[i + 1]]` in increasing order and their values
+/// at the same positions in `values`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix<T> {
+    cols: usize,
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    values: Vec<T>
+}
+
+impl <T: Clone + Zero> SparseMatrix<T> {
+
+    /// Creates a sparse matrix with no rows and `cols` columns.
+    pub fn new(cols: usize) -> SparseMatrix<T> {
+        SparseMatrix {
+            cols: cols,
+            indptr: vec![0],
+            indices: Vec::new(),
+            values: Vec::new()
+        }
+    }
+
+    /// Creates a sparse matrix from the non-zero elements of a dense matrix.
+    pub fn from_dense(m: &Matrix<T>) -> SparseMatrix<T> {
+
+        let mut s = SparseMatrix::new(m.cols());
+        for row in m.row_iter() {
+            let r: Vec<(usize, T)> = row.iter().cloned().enumerate().filter(|&(_, ref v)| !v.is_zero()).collect();
+            s.add_row(&r);
+        }
+        s
+    }
+
+    /// Appends a row with the given non-zero elements, each given as a pair
+    /// of the column index and the value. The elements may be given in any
+    /// order. Zeros are not stored.
+    ///
+    /// Panics if a column index is out of range or if a column index occurs
+    /// more than once.
+    pub fn add_row(&mut self, row: &[(usize, T)]) {
+
+        let mut r = row.to_vec();
+        r.sort_by(|a, b| a.0.cmp(&b.0));
+        for (k, &(c, ref v)) in r.iter().enumerate() {
+            assert!(c < self.cols, "Column index out of range.");
+            assert!(k == 0 || r[k - 1].0 != c, "Duplicate column index.");
+            if !v.is_zero() {
+                self.indices.push(c);
+                self.values.push(v.clone());
+            }
+        }
+        self.indptr.push(self.indices.len());
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the number of stored (non-zero) elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the column indexes and the values of the non-zero elements
+    /// of row `i`, or `None` if the row does not exist.
+    pub fn row(&self, i: usize) -> Option<(&[usize], &[T])> {
+
+        if i >= self.rows() {
+            return None;
+        }
+        let (a, b) = (self.indptr[i], self.indptr[i + 1]);
+        Some((&self.indices[a..b], &self.values[a..b]))
+    }
+
+    /// Returns an iterator over the rows. Each row is returned as with
+    /// [`row`](#method.row).
+    pub fn row_iter(&self) -> SparseRowIterator<'_, T> {
+        SparseRowIterator { m: self, pos: 0 }
+    }
+
+    /// Returns the element in row `row` and column `col`.
+    ///
+    /// Panics if the position is out of range.
+    pub fn get(&self, row: usize, col: usize) -> T {
+
+        assert!(col < self.cols, "Column index out of range.");
+        let (idx, vals) = self.row(row).expect("Row index out of range.");
+        match idx.binary_search(&col) {
+            Ok(k) => vals[k].clone(),
+            Err(_) => T::zero()
+        }
+    }
+
+    /// Returns the row pointers, i.e. the non-zero elements of row `i` are
+    /// at the positions `indptr[i]..indptr[i + 1]` of the indices and the
+    /// values.
+    pub fn indptr(&self) -> &[usize] {
+        &self.indptr
+    }
+
+    /// Returns the column indexes of all non-zero elements.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Returns the values of all non-zero elements.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns a sparse matrix with the given rows of this matrix.
+    ///
+    /// Panics if a row index is out of range.
+    pub fn select_rows(&self, rows: &[usize]) -> SparseMatrix<T> {
+
+        let mut s = SparseMatrix::new(self.cols);
+        for &i in rows {
+            let (idx, vals) = self.row(i).expect("Row index out of range.");
+            s.indices.extend_from_slice(idx);
+            s.values.extend_from_slice(vals);
+            s.indptr.push(s.indices.len());
+        }
+        s
+    }
+
+    /// Converts the sparse matrix into a dense matrix.
+    pub fn to_dense(&self) -> Matrix<T> {
+
+        let mut v = vec![T::zero(); self.rows() * self.cols];
+        for (i, (idx, vals)) in self.row_iter().enumerate() {
+            for (&c, x) in idx.iter().zip(vals.iter()) {
+                v[i * self.cols + c] = x.clone();
+            }
+        }
+        Matrix::from_vec(v, self.rows(), self.cols)
+    }
+}
+
+/// Iterator over the rows of a sparse matrix.
+pub struct SparseRowIterator<'a, T: 'a> {
+    m: &'a SparseMatrix<T>,
+    pos: usize
+}
+
+impl <'a, T: Clone + Zero> Iterator for SparseRowIterator<'a, T> {
+    type Item = (&'a [usize], &'a [T]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let r = self.m.row(self.pos);
+        if r.is_some() {
+            self.pos += 1;
+        }
+        r
+    }
+}
+
+/// Computes the dot product of the sparse row with the column indexes `idx`
+/// and the values `vals` and the dense vector `w`.
+///
+/// Panics if a column index is out of range of `w`.
+#[inline]
+pub fn dot(idx: &[usize], vals: &[f64], w: &[f64]) -> f64 {
+
+    let mut s = 0.0;
+    for (&c, &x) in idx.iter().zip(vals.iter()) {
+        s += w[c] * x;
+    }
+    s
+}
+
+/// Computes `w = alpha * x + w` where `x` is the sparse row with the
+/// column indexes `idx` and the values `vals`. Only the elements of `w`
+/// at the indexes `idx` are touched.
+///
+/// Panics if a column index is out of range of `w`.
+#[inline]
+pub fn axpy(alpha: f64, idx: &[usize], vals: &[f64], w: &mut [f64]) {
+
+    for (&c, &x) in idx.iter().zip(vals.iter()) {
+        w[c] += alpha * x;
+    }
+}
+
+/// Maps the column index `col` to a bucket of a table with `n` buckets and
+/// a sign (`1.0` or `-1.0`).
+///
+/// Panics if `n` is not a power of two.
+#[inline]
+pub fn hash_feature(col: usize, n: usize) -> (usize, f64) {
+
+    assert!(n.is_power_of_two(), "Number of buckets must be a power of two.");
+    // 64 bit finalizer of MurmurHash3
+    let mut h = col as u64;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    let sign = if h >> 63 == 0 { 1.0 } else { -1.0 };
+    ((h as usize) & (n - 1), sign)
+}
+
+/// Like [`dot`](fn.dot.html) but the column indexes are mapped to the
+/// buckets of `w` with [`hash_feature`](fn.hash_feature.html), i.e. the
+/// column indexes can be arbitrarily large.
+///
+/// Panics if the length of `w` is not a power of two.
+#[inline]
+pub fn hashed_dot(idx: &[usize], vals: &[f64], w: &[f64]) -> f64 {
+
+    let mut s = 0.0;
+    for (&c, &x) in idx.iter().zip(vals.iter()) {
+        let (b, sign) = hash_feature(c, w.len());
+        s += sign * w[b] * x;
+    }
+    s
+}
+
+/// Like [`axpy`](fn.axpy.html) but the column indexes are mapped to the
+/// buckets of `w` with [`hash_feature`](fn.hash_feature.html).
+///
+/// Panics if the length of `w` is not a power of two.
+#[inline]
+pub fn hashed_axpy(alpha: f64, idx: &[usize], vals: &[f64], w: &mut [f64]) {
+
+    let n = w.len();
+    for (&c, &x) in idx.iter().zip(vals.iter()) {
+        let (b, sign) = hash_feature(c, n);
+        w[b] += sign * alpha * x;
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_sparse_matrix() {
+
+        let mut s = SparseMatrix::new(5);
+        s.add_row(&[(4, 2.0), (1, 1.0), (2, 0.0)]);
+        s.add_row(&[]);
+        s.add_row(&[(0, 3.0)]);
+
+        assert_eq!(s.rows(), 3);
+        assert_eq!(s.cols(), 5);
+        assert_eq!(s.nnz(), 3);
+        assert_eq!(s.indptr(), &[0, 2, 2, 3]);
+        assert_eq!(s.indices(), &[1, 4, 0]);
+        assert_eq!(s.values(), &[1.0, 2.0, 3.0]);
+        assert_eq!(s.row(0), Some((&[1, 4][..], &[1.0, 2.0][..])));
+        assert_eq!(s.row(1).unwrap().0.len(), 0);
+        assert_eq!(s.row(3), None);
+        assert_eq!(s.row_iter().count(), 3);
+        assert_eq!(s.get(0, 4), 2.0);
+        assert_eq!(s.get(1, 4), 0.0);
+
+        let d = s.to_dense();
+        assert_eq!(d, mat![0.0, 1.0, 0.0, 0.0, 2.0; 0.0, 0.0, 0.0, 0.0, 0.0; 3.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(SparseMatrix::from_dense(&d), s);
+
+        let t = s.select_rows(&[2, 0]);
+        assert_eq!(t.to_dense(), mat![3.0, 0.0, 0.0, 0.0, 0.0; 0.0, 1.0, 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sparse_matrix_duplicate() {
+        SparseMatrix::new(3).add_row(&[(1, 1.0), (1, 2.0)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sparse_matrix_out_of_range() {
+        SparseMatrix::new(3).add_row(&[(3, 1.0)]);
+    }
+
+    #[test]
+    fn test_kernels() {
+
+        let mut w = vec![1.0, 2.0, 3.0, 4.0];
+        assert_eq!(dot(&[0, 3], &[2.0, 0.5], &w), 4.0);
+        axpy(-1.0, &[1, 2], &[1.0, 2.0], &mut w);
+        assert_eq!(w, vec![1.0, 1.0, 1.0, 4.0]);
+        assert_eq!(dot(&[], &[], &w), 0.0);
+
+        // all buckets are used and both signs occur
+        let h: Vec<(usize, f64)> = (0..1000).map(|c| hash_feature(c, 8)).collect();
+        assert!((0..8).all(|b| h.iter().any(|&(x, _)| x == b)));
+        assert!(h.iter().any(|&(_, s)| s < 0.0) && h.iter().any(|&(_, s)| s > 0.0));
+        assert_eq!(hash_feature(42, 8), hash_feature(42, 8));
+
+        let mut w = vec![0.0; 1024];
+        let idx = [3, 1 << 40, 77777];
+        let vals = [1.0, -2.0, 0.5];
+        hashed_axpy(1.0, &idx, &vals, &mut w);
+        assert!((hashed_dot(&idx, &vals, &w) - 5.25).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hash_feature_buckets() {
+        hash_feature(1, 10);
+    }
+}
@@ -0,0 +1,256 @@
+//! Correlation coefficients.
+//!
+//! * [Pearson](fn.pearson.html) measures the linear relationship between two
+//! variables.
+//! * [Spearman](fn.spearman.html) is the Pearson correlation of the ranks and
+//! measures monotonic relationships.
+//! * [Kendall](fn.kendall.html) is based on the number of concordant and
+//! discordant pairs and also measures monotonic relationships.
+//!
+//! All coefficients are in `[-1, 1]` and `NaN` if one of the variables is
+//! constant. [`corr_matrix`](fn.corr_matrix.html) computes the coefficients of
+//! all pairs of columns of a matrix.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::stats::*;
+//!
+//! # fn main() {
+//! let x = [1.0, 2.0, 3.0, 4.0];
+//! let y = [1.0, 4.0, 9.0, 16.0];
+//! assert!(pearson(&x, &y) < 1.0);
+//! assert_eq!(spearman(&x, &y), 1.0);
+//! assert_eq!(kendall(&x, &y), 1.0);
+//!
+//! let m = mat![1.0, 4.0; 2.0, 3.0; 3.0, 2.0];
+//! assert_eq!(corr_matrix(&m, Correlation::Pearson), mat![1.0, -1.0; -1.0, 1.0]);
+//! # }
+//! ```
+use matrix::Matrix;
+
+/// Correlation coefficients which can be computed with
+/// [`corr_matrix`](fn.corr_matrix.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Correlation {
+    /// Pearson product-moment correlation coefficient.
+    Pearson,
+    /// Spearman's rank correlation coefficient.
+    Spearman,
+    /// Kendall's tau-b rank correlation coefficient.
+    Kendall
+}
+
+impl Correlation {
+
+    /// Computes the coefficient between `a` and `b`.
+    ///
+    /// Panics if the vectors have a different length.
+    pub fn compute(&self, a: &[f64], b: &[f64]) -> f64 {
+        match *self {
+            Correlation::Pearson => pearson(a, b),
+            Correlation::Spearman => spearman(a, b),
+            Correlation::Kendall => kendall(a, b)
+        }
+    }
+}
+
+fn check(a: &[f64], b: &[f64]) {
+    assert!(a.len() == b.len(), "Vectors must have the same length.");
+}
+
+/// Computes the Pearson correlation coefficient between `a` and `b`.
+///
+/// Returns `NaN` if one of the vectors is constant or if the vectors are
+/// empty.
+///
+/// Panics if the vectors have a different length.
+pub fn pearson(a: &[f64], b: &[f64]) -> f64 {
+
+    check(a, b);
+
+    let n = a.len() as f64;
+    let ma = a.iter().fold(0.0, |acc, x| acc + x) / n;
+    let mb = b.iter().fold(0.0, |acc, x| acc + x) / n;
+    let (sab, saa, sbb) = a.iter().zip(b.iter())
+        .fold((0.0, 0.0, 0.0), |(sab, saa, sbb), (x, y)| {
+            let (dx, dy) = (x - ma, y - mb);
+            (sab + dx * dy, saa + dx * dx, sbb + dy * dy)
+        });
+    let r = sab / (saa * sbb).sqrt();
+    // avoid values slightly outside of [-1, 1] due to rounding errors
+    if r.is_nan() { r } else { r.max(-1.0).min(1.0) }
+}
+
+/// Returns the ranks of the elements of `v` starting at 1. Tied elements
+/// get the average of the ranks they would get without ties.
+///
+/// # Example
+///
+/// ```
+/// use rustml::stats::ranks;
+///
+/// assert_eq!(ranks(&[3.0, 1.0, 4.0, 1.0]), vec![3.0, 1.5, 4.0, 1.5]);
+/// ```
+pub fn ranks(v: &[f64]) -> Vec<f64> {
+
+    let mut idx: Vec<usize> = (0..v.len()).collect();
+    idx.sort_by(|&i, &j| v[i].partial_cmp(&v[j]).unwrap());
+
+    let mut r = vec![0.0; v.len()];
+    let mut i = 0;
+    while i < idx.len() {
+        let mut j = i + 1;
+        while j < idx.len() && v[idx[j]] == v[idx[i]] {
+            j += 1;
+        }
+        // elements i..j are tied and get the average of the ranks i+1..j
+        let rank = (i + j + 1) as f64 / 2.0;
+        for &k in &idx[i..j] {
+            r[k] = rank;
+        }
+        i = j;
+    }
+    r
+}
+
+/// Computes Spearman's rank correlation coefficient between `a` and `b`,
+/// i.e. the Pearson correlation coefficient of their
+/// [ranks](fn.ranks.html).
+///
+/// Returns `NaN` if one of the vectors is constant or if the vectors are
+/// empty.
+///
+/// Panics if the vectors have a different length.
+pub fn spearman(a: &[f64], b: &[f64]) -> f64 {
+
+    check(a, b);
+    pearson(&ranks(a), &ranks(b))
+}
+
+/// Computes Kendall's tau-b rank correlation coefficient between `a` and
+/// `b`, i.e. `(C - D) / sqrt((C + D + Ta) (C + D + Tb))` where `C` is the
+/// number of concordant pairs, `D` the number of discordant pairs and `Ta`
+/// (`Tb`) the number of pairs which are only tied in `a` (`b`).
+///
+/// The coefficient is computed in O(n²) time.
+///
+/// Returns `NaN` if one of the vectors is constant or if the vectors have
+/// less than two elements.
+///
+/// Panics if the vectors have a different length.
+pub fn kendall(a: &[f64], b: &[f64]) -> f64 {
+
+    check(a, b);
+
+    let (mut c, mut d, mut ta, mut tb) = (0.0f64, 0.0, 0.0, 0.0);
+    for i in 0..a.len() {
+        for j in i + 1..a.len() {
+            let s = (a[i] - a[j]) * (b[i] - b[j]);
+            if s > 0.0 {
+                c += 1.0;
+            } else if s < 0.0 {
+                d += 1.0;
+            } else if a[i] != a[j] {
+                tb += 1.0;
+            } else if b[i] != b[j] {
+                ta += 1.0;
+            }
+        }
+    }
+    (c - d) / ((c + d + ta) * (c + d + tb)).sqrt()
+}
+
+/// Computes the correlation coefficients between all pairs of columns of
+/// `m`, i.e. the element in row `i` and column `j` of the result is the
+/// coefficient between column `i` and column `j`.
+///
+/// The diagonal is always 1, even for constant columns.
+pub fn corr_matrix(m: &Matrix<f64>, method: Correlation) -> Matrix<f64> {
+
+    let cols: Vec<Vec<f64>> = m.col_iter().collect();
+    let d = cols.len();
+    let mut r = vec![1.0; d * d];
+    for i in 0..d {
+        for j in i + 1..d {
+            let c = method.compute(&cols[i], &cols[j]);
+            r[i * d + j] = c;
+            r[j * d + i] = c;
+        }
+    }
+    Matrix::from_vec(r, d, d)
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_pearson() {
+
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(pearson(&a, &[2.0, 4.0, 6.0, 8.0, 10.0]), 1.0);
+        assert_eq!(pearson(&a, &[5.0, 4.0, 3.0, 2.0, 1.0]), -1.0);
+        // sab = 1, saa = sbb = 2
+        let r = pearson(&[1.0, 2.0, 3.0], &[1.0, 3.0, 2.0]);
+        assert!((r - 0.5).abs() < 1e-12);
+        assert!(pearson(&a, &[1.0, 1.0, 1.0, 1.0, 1.0]).is_nan());
+    }
+
+    #[test]
+    fn test_ranks() {
+
+        assert_eq!(ranks(&[]), Vec::<f64>::new());
+        assert_eq!(ranks(&[2.0, 2.0, 2.0]), vec![2.0, 2.0, 2.0]);
+        assert_eq!(ranks(&[10.0, 30.0, 20.0, 20.0]), vec![1.0, 4.0, 2.5, 2.5]);
+    }
+
+    #[test]
+    fn test_spearman() {
+
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(spearman(&a, &[1.0, 8.0, 27.0, 64.0, 125.0]), 1.0);
+        assert_eq!(spearman(&a, &[0.5, 0.4, 0.3, 0.2, 0.1]), -1.0);
+        // d = [0, 0, 1, -1, 0], rho = 1 - 6 * 2 / (5 * 24)
+        assert!((spearman(&a, &[1.0, 2.0, 4.0, 3.0, 5.0]) - 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_kendall() {
+
+        let a = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(kendall(&a, &[1.0, 2.0, 3.0, 4.0]), 1.0);
+        assert_eq!(kendall(&a, &[4.0, 3.0, 2.0, 1.0]), -1.0);
+        // 5 concordant and 1 discordant pairs
+        assert!((kendall(&a, &[1.0, 3.0, 2.0, 4.0]) - 4.0 / 6.0).abs() < 1e-12);
+        // C = 5, D = 0, Ta = 0, Tb = 1
+        let t = kendall(&a, &[1.0, 2.0, 3.0, 3.0]);
+        assert!((t - 5.0 / (6.0f64 * 5.0).sqrt()).abs() < 1e-12);
+        assert!(kendall(&a, &[1.0, 1.0, 1.0, 1.0]).is_nan());
+    }
+
+    #[test]
+    fn test_corr_matrix() {
+
+        let m = mat![
+            1.0, 2.0, 5.0;
+            2.0, 4.0, 1.0;
+            3.0, 6.0, 0.0;
+            4.0, 8.0, -3.0
+        ];
+        for &c in &[Correlation::Pearson, Correlation::Spearman, Correlation::Kendall] {
+            let r = corr_matrix(&m, c);
+            assert_eq!(r.rows(), 3);
+            assert_eq!(r.get(0, 1), Some(&1.0));
+            assert_eq!(r.get(1, 0), Some(&1.0));
+            assert_eq!(r.get(2, 2), Some(&1.0));
+            assert_eq!(r.get(0, 2), r.get(2, 0));
+            assert!(*r.get(0, 2).unwrap() < -0.9);
+        }
+    }
+}
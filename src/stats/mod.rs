@@ -0,0 +1,10 @@
+//! Module with statistical functions for the exploratory analysis of data and
+//! the comparison of models.
+
+pub mod correlation;
+pub mod profile;
+pub mod special;
+pub mod tests;
+
+pub use self::correlation::{pearson, spearman, kendall, ranks, corr_matrix, Correlation};
+pub use self::profile::{profile, profile_with, DatasetProfile, ColumnProfile};
@@ -0,0 +1,243 @@
+//! Profiling of datasets.
+//!
+//! [`profile`](fn.profile.html) computes summary statistics of each column
+//! of a matrix (the number of values, the fraction of missing values, the
+//! mean, the standard deviation, the minimum, quantiles, the maximum and
+//! the number of distinct values) and finds pairs of highly correlated
+//! columns. Missing values are represented by NaN and are ignored by all
+//! statistics. The [`DatasetProfile`](struct.DatasetProfile.html)
+//! implements `Display` which formats the statistics as a table.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::stats::profile;
+//!
+//! # fn main() {
+//! let nan = std::f64::NAN;
+//! let m = mat![
+//!     1.0, 2.0, 5.0;
+//!     2.0, 4.0, nan;
+//!     3.0, 6.0, 5.0;
+//!     4.0, 8.0, 1.0
+//! ];
+//! let p = profile(&m);
+//! let c = &p.columns[2];
+//! assert_eq!(c.count, 3);
+//! assert_eq!(c.missing_fraction, 0.25);
+//! assert_eq!(c.cardinality, 2);
+//! assert_eq!((c.min, c.max), (1.0, 5.0));
+//!
+//! // the first two columns are perfectly correlated
+//! assert_eq!(p.correlated.len(), 1);
+//! assert_eq!((p.correlated[0].0, p.correlated[0].1), (0, 1));
+//! println!("{}", p);
+//! # }
+//! ```
+use std::cmp::Ordering;
+use std::fmt;
+
+use matrix::Matrix;
+use math::Quantile;
+use stats::correlation::pearson;
+
+/// Quantiles which are computed by [`profile`](fn.profile.html).
+pub const DEFAULT_QUANTILES: [f64; 3] = [0.25, 0.5, 0.75];
+
+/// Minimum absolute Pearson correlation of the pairs of columns which are
+/// reported by [`profile`](fn.profile.html).
+pub const DEFAULT_CORRELATION_THRESHOLD: f64 = 0.9;
+
+/// Statistics of one column.
+///
+/// All statistics except `count` and `missing_fraction` are NaN if the
+/// column contains no values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnProfile {
+    /// Number of values which are not missing.
+    pub count: usize,
+    /// Fraction of the rows in which the value is missing.
+    pub missing_fraction: f64,
+    /// Mean of the values.
+    pub mean: f64,
+    /// Standard deviation of the values (normalized by `count - 1`, zero
+    /// for a single value).
+    pub std: f64,
+    /// Smallest value.
+    pub min: f64,
+    /// Largest value.
+    pub max: f64,
+    /// Pairs of a probability `q` and the `q`-quantile of the values.
+    pub quantiles: Vec<(f64, f64)>,
+    /// Number of distinct values.
+    pub cardinality: usize
+}
+
+/// Statistics of all columns of a dataset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatasetProfile {
+    /// Number of rows.
+    pub rows: usize,
+    /// Statistics of each column.
+    pub columns: Vec<ColumnProfile>,
+    /// Pairs of columns `(i, j, r)` with `i < j` whose Pearson correlation
+    /// `r` (computed on the rows in which both values are present) is at
+    /// least the threshold in absolute value, sorted by decreasing absolute
+    /// correlation.
+    pub correlated: Vec<(usize, usize, f64)>
+}
+
+/// Computes the profile of the columns of `m` with the
+/// [`DEFAULT_QUANTILES`](constant.DEFAULT_QUANTILES.html) and the
+/// [`DEFAULT_CORRELATION_THRESHOLD`](constant.DEFAULT_CORRELATION_THRESHOLD.html).
+pub fn profile(m: &Matrix<f64>) -> DatasetProfile {
+    profile_with(m, &DEFAULT_QUANTILES, DEFAULT_CORRELATION_THRESHOLD)
+}
+
+/// Like [`profile`](fn.profile.html) with the given quantiles and
+/// correlation threshold.
+///
+/// Panics if a quantile is not in [0, 1].
+pub fn profile_with(m: &Matrix<f64>, quantiles: &[f64], threshold: f64) -> DatasetProfile {
+
+    let cols: Vec<Vec<f64>> = m.col_iter().collect();
+    let columns = cols.iter().map(|c| column_profile(c, quantiles)).collect();
+
+    let mut correlated = vec![];
+    for i in 0..cols.len() {
+        for j in i + 1..cols.len() {
+            let (a, b): (Vec<f64>, Vec<f64>) = cols[i].iter().zip(cols[j].iter())
+                .filter(|&(x, y)| !x.is_nan() && !y.is_nan())
+                .unzip();
+            let r = pearson(&a, &b);
+            if r.abs() >= threshold {
+                correlated.push((i, j, r));
+            }
+        }
+    }
+    correlated.sort_by(|a, b| b.2.abs().partial_cmp(&a.2.abs()).unwrap_or(Ordering::Equal));
+
+    DatasetProfile {
+        rows: m.rows(),
+        columns: columns,
+        correlated: correlated
+    }
+}
+
+fn column_profile(c: &[f64], quantiles: &[f64]) -> ColumnProfile {
+
+    let mut v: Vec<f64> = c.iter().cloned().filter(|x| !x.is_nan()).collect();
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let n = v.len();
+    let missing = if c.is_empty() { 0.0 } else { (c.len() - n) as f64 / c.len() as f64 };
+    if n == 0 {
+        let nan = ::std::f64::NAN;
+        return ColumnProfile {
+            count: 0,
+            missing_fraction: missing,
+            mean: nan,
+            std: nan,
+            min: nan,
+            max: nan,
+            quantiles: quantiles.iter().map(|&q| (q, nan)).collect(),
+            cardinality: 0
+        };
+    }
+
+    let mean = v.iter().sum::<f64>() / n as f64;
+    let ss: f64 = v.iter().map(|x| (x - mean) * (x - mean)).sum();
+    let mut distinct = v.clone();
+    distinct.dedup();
+
+    ColumnProfile {
+        count: n,
+        missing_fraction: missing,
+        mean: mean,
+        std: if n > 1 { (ss / (n - 1) as f64).sqrt() } else { 0.0 },
+        min: v[0],
+        max: v[n - 1],
+        quantiles: quantiles.iter().map(|&q| (q, v.quantile(q))).collect(),
+        cardinality: distinct.len()
+    }
+}
+
+impl fmt::Display for DatasetProfile {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        try!(writeln!(f, "{} rows, {} columns", self.rows, self.columns.len()));
+        try!(writeln!(f, ""));
+
+        let quantiles: Vec<f64> = self.columns.first().map(|c| c.quantiles.iter().map(|q| q.0).collect()).unwrap_or(vec![]);
+        try!(write!(f, "{:>6} {:>8} {:>8} {:>10} {:>10} {:>10}", "column", "count", "missing", "mean", "std", "min"));
+        for q in &quantiles {
+            try!(write!(f, " {:>10}", format!("{}%", q * 100.0)));
+        }
+        try!(writeln!(f, " {:>10} {:>8}", "max", "unique"));
+
+        for (i, c) in self.columns.iter().enumerate() {
+            try!(write!(f, "{:>6} {:>8} {:>8.4} {:>10.4} {:>10.4} {:>10.4}", i, c.count, c.missing_fraction, c.mean, c.std, c.min));
+            for &(_, v) in &c.quantiles {
+                try!(write!(f, " {:>10.4}", v));
+            }
+            try!(writeln!(f, " {:>10.4} {:>8}", c.max, c.cardinality));
+        }
+
+        if !self.correlated.is_empty() {
+            try!(writeln!(f, ""));
+            try!(writeln!(f, "highly correlated columns:"));
+            for &(i, j, r) in &self.correlated {
+                try!(writeln!(f, "{:>6} {:>6} {:>8.4}", i, j, r));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+
+        let nan = ::std::f64::NAN;
+        let m = Matrix::from_vec(vec![
+            1.0, 10.0, nan, 3.0,
+            2.0, 8.0,  nan, 3.0,
+            3.0, 6.0,  nan, 3.0,
+            4.0, 4.0,  nan, 7.0,
+            nan, 2.0,  nan, 3.0
+        ], 5, 4);
+
+        let p = profile_with(&m, &[0.5], 0.99);
+        assert_eq!(p.rows, 5);
+        let c = &p.columns[0];
+        assert_eq!(c.count, 4);
+        assert_eq!(c.missing_fraction, 0.2);
+        assert_eq!(c.mean, 2.5);
+        assert!((c.std - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(c.quantiles, vec![(0.5, 2.5)]);
+        assert_eq!(c.cardinality, 4);
+        assert_eq!(p.columns[3].cardinality, 2);
+
+        // a column without values
+        assert_eq!(p.columns[2].count, 0);
+        assert!(p.columns[2].mean.is_nan());
+
+        // columns 0 and 1 are perfectly negatively correlated on the rows
+        // in which both are present
+        assert_eq!(p.correlated.len(), 1);
+        assert_eq!((p.correlated[0].0, p.correlated[0].1), (0, 1));
+        assert!((p.correlated[0].2 + 1.0).abs() < 1e-12);
+
+        let s = p.to_string();
+        assert!(s.starts_with("5 rows, 4 columns"));
+        assert!(s.contains("highly correlated columns"));
+    }
+}
@@ -0,0 +1,283 @@
+//! Statistical hypothesis tests.
+//!
+//! Each test returns a [`TestResult`](struct.TestResult.html) with the test
+//! statistic and the p-value, i.e. the probability to observe a statistic at
+//! least as extreme as the computed one if the null hypothesis is true.
+//! All tests are two-sided.
+//!
+//! * [`ttest_ind`](fn.ttest_ind.html): do two independent samples have the
+//! same mean? This is useful to compare e.g. the cross-validation scores of
+//! two models.
+//! * [`chi2_contingency`](fn.chi2_contingency.html): are two categorical
+//! variables independent?
+//! * [`ks_2samp`](fn.ks_2samp.html): are two samples drawn from the same
+//! distribution?
+//! * [`ks_1samp`](fn.ks_1samp.html): is a sample drawn from a given
+//! distribution?
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::stats::tests::*;
+//!
+//! let a = [0.81, 0.79, 0.83, 0.80, 0.82];
+//! let b = [0.71, 0.74, 0.72, 0.70, 0.73];
+//!
+//! let r = ttest_ind(&a, &b, false);
+//! assert!(r.statistic > 0.0);
+//! assert!(r.p_value < 0.001);
+//! ```
+use std::f64;
+
+use matrix::Matrix;
+use stats::special::{beta_inc, gamma_q, kolmogorov_q};
+
+/// Result of a hypothesis test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestResult {
+    /// The value of the test statistic.
+    pub statistic: f64,
+    /// The p-value of the test.
+    pub p_value: f64
+}
+
+fn mean_var(v: &[f64]) -> (f64, f64) {
+
+    let n = v.len() as f64;
+    let m = v.iter().fold(0.0, |acc, x| acc + x) / n;
+    let var = v.iter().fold(0.0, |acc, x| acc + (x - m) * (x - m)) / (n - 1.0);
+    (m, var)
+}
+
+/// Returns the two-sided p-value of Student's t-distribution with `df`
+/// degrees of freedom.
+fn t_p_value(t: f64, df: f64) -> f64 {
+    beta_inc(df / 2.0, 0.5, df / (df + t * t))
+}
+
+/// Two-sample t-test for the null hypothesis that two independent samples
+/// have the same mean.
+///
+/// With `equal_var = true` Student's t-test with a pooled variance is
+/// computed. Otherwise Welch's t-test is computed, which does not assume
+/// that both samples have the same variance and should be preferred if this
+/// is not known.
+///
+/// The statistic is positive if the mean of `a` is larger than the mean of
+/// `b`. If both samples are constant the statistic and the p-value are
+/// `NaN` (or the statistic is infinite if the means differ).
+///
+/// Panics if one of the samples has less than two elements.
+pub fn ttest_ind(a: &[f64], b: &[f64], equal_var: bool) -> TestResult {
+
+    assert!(a.len() > 1 && b.len() > 1, "Samples must contain at least two elements.");
+
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let (ma, va) = mean_var(a);
+    let (mb, vb) = mean_var(b);
+
+    let (se, df) = if equal_var {
+        let df = na + nb - 2.0;
+        let vp = ((na - 1.0) * va + (nb - 1.0) * vb) / df;
+        ((vp * (1.0 / na + 1.0 / nb)).sqrt(), df)
+    } else {
+        let (qa, qb) = (va / na, vb / nb);
+        // Welch-Satterthwaite equation
+        let df = (qa + qb) * (qa + qb) / (qa * qa / (na - 1.0) + qb * qb / (nb - 1.0));
+        ((qa + qb).sqrt(), df)
+    };
+
+    let t = (ma - mb) / se;
+    let p = if t.is_nan() {
+        f64::NAN
+    } else if t.is_infinite() {
+        0.0
+    } else {
+        t_p_value(t, df)
+    };
+    TestResult { statistic: t, p_value: p }
+}
+
+/// Chi-squared test for the null hypothesis that the two categorical
+/// variables of a contingency table are independent.
+///
+/// The element in row `i` and column `j` of `observed` is the number of
+/// observations where the first variable has category `i` and the second
+/// variable has category `j`. The statistic has `(r - 1) (c - 1)` degrees
+/// of freedom where `r` and `c` are the number of rows and columns. No
+/// continuity correction is applied.
+///
+/// Panics if the table has less than two rows or columns or if a row or a
+/// column sums to zero.
+pub fn chi2_contingency(observed: &Matrix<f64>) -> TestResult {
+
+    let (r, c) = (observed.rows(), observed.cols());
+    assert!(r > 1 && c > 1, "Contingency table must have at least two rows and two columns.");
+
+    let rows: Vec<f64> = observed.row_iter().map(|v| v.iter().fold(0.0, |acc, x| acc + x)).collect();
+    let cols: Vec<f64> = observed.col_iter().map(|v| v.iter().fold(0.0, |acc, x| acc + x)).collect();
+    assert!(rows.iter().chain(cols.iter()).all(|&s| s > 0.0),
+        "Rows and columns of the contingency table must not sum to zero.");
+    let n = rows.iter().fold(0.0, |acc, x| acc + x);
+
+    let mut chi2 = 0.0;
+    for (i, row) in observed.row_iter().enumerate() {
+        for (j, &o) in row.iter().enumerate() {
+            let e = rows[i] * cols[j] / n;
+            chi2 += (o - e) * (o - e) / e;
+        }
+    }
+    let df = ((r - 1) * (c - 1)) as f64;
+    TestResult { statistic: chi2, p_value: gamma_q(df / 2.0, chi2 / 2.0) }
+}
+
+fn sorted(v: &[f64]) -> Vec<f64> {
+
+    let mut v = v.to_vec();
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    v
+}
+
+/// p-value of the Kolmogorov-Smirnov statistic `d` with the effective
+/// number of samples `n` (asymptotic distribution with Stephens'
+/// correction).
+fn ks_p_value(d: f64, n: f64) -> f64 {
+
+    let en = n.sqrt();
+    kolmogorov_q((en + 0.12 + 0.11 / en) * d)
+}
+
+/// Two-sample Kolmogorov-Smirnov test for the null hypothesis that two
+/// samples are drawn from the same continuous distribution.
+///
+/// The statistic is the maximum absolute difference between the empirical
+/// distribution functions of both samples. The p-value is computed from
+/// the asymptotic distribution of the statistic and is accurate for
+/// samples with at least a few dozen elements.
+///
+/// Panics if one of the samples is empty.
+pub fn ks_2samp(a: &[f64], b: &[f64]) -> TestResult {
+
+    assert!(!a.is_empty() && !b.is_empty(), "Samples must not be empty.");
+
+    let (a, b) = (sorted(a), sorted(b));
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let (mut i, mut j) = (0, 0);
+    let mut d = 0.0f64;
+    while i < a.len() && j < b.len() {
+        let x = a[i].min(b[j]);
+        while i < a.len() && a[i] == x {
+            i += 1;
+        }
+        while j < b.len() && b[j] == x {
+            j += 1;
+        }
+        d = d.max((i as f64 / na - j as f64 / nb).abs());
+    }
+    TestResult { statistic: d, p_value: ks_p_value(d, na * nb / (na + nb)) }
+}
+
+/// One-sample Kolmogorov-Smirnov test for the null hypothesis that a sample
+/// is drawn from the continuous distribution with the cumulative
+/// distribution function `cdf`.
+///
+/// The statistic is the maximum absolute difference between the empirical
+/// distribution function of the sample and `cdf`. The p-value is computed
+/// from the asymptotic distribution of the statistic.
+///
+/// Panics if the sample is empty.
+pub fn ks_1samp<F: Fn(f64) -> f64>(a: &[f64], cdf: F) -> TestResult {
+
+    assert!(!a.is_empty(), "Sample must not be empty.");
+
+    let n = a.len() as f64;
+    let d = sorted(a).iter().enumerate().fold(0.0f64, |acc, (i, &x)| {
+        let f = cdf(x);
+        acc.max((i + 1) as f64 / n - f).max(f - i as f64 / n)
+    });
+    TestResult { statistic: d, p_value: ks_p_value(d, n) }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_ttest_ind() {
+
+        // pooled variance 2.5, standard error 1, 8 degrees of freedom
+        let r = ttest_ind(&[1.0, 2.0, 3.0, 4.0, 5.0], &[6.0, 7.0, 8.0, 9.0, 10.0], true);
+        assert!((r.statistic + 5.0).abs() < 1e-12);
+        assert!(r.p_value > 0.001 && r.p_value < 0.0011);
+
+        // two degrees of freedom: p = 1 - |t| / sqrt(2 + t^2)
+        let r = ttest_ind(&[0.0, 2.0], &[-1.0, 1.0], true);
+        let t = 0.5f64.sqrt();
+        assert!((r.statistic - t).abs() < 1e-12);
+        assert!((r.p_value - (1.0 - t / (2.0 + t * t).sqrt())).abs() < 1e-9);
+
+        // equal variances and sizes: Welch's df equals the pooled df
+        let a = [1.0, 3.0, 5.0];
+        let b = [0.0, 2.0, 1.0];
+        let r1 = ttest_ind(&a, &b, true);
+        let r2 = ttest_ind(&a, &b, false);
+        assert!((r1.statistic - r2.statistic).abs() < 1e-12);
+        assert!(r1.p_value != r2.p_value);
+
+        assert!(ttest_ind(&[1.0, 1.0], &[1.0, 1.0], true).p_value.is_nan());
+        assert_eq!(ttest_ind(&[1.0, 1.0], &[2.0, 2.0], false).p_value, 0.0);
+    }
+
+    #[test]
+    fn test_chi2_contingency() {
+
+        // expected frequency 20 everywhere, 2 degrees of freedom
+        let r = chi2_contingency(&mat![10.0, 20.0, 30.0; 30.0, 20.0, 10.0]);
+        assert!((r.statistic - 20.0).abs() < 1e-12);
+        assert!((r.p_value - (-10.0f64).exp()).abs() < 1e-12);
+
+        let r = chi2_contingency(&mat![5.0, 10.0; 10.0, 20.0]);
+        assert!(r.statistic.abs() < 1e-12);
+        assert!((r.p_value - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_chi2_contingency_panic() {
+        chi2_contingency(&mat![1.0, 0.0; 2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_ks_2samp() {
+
+        let a: Vec<f64> = (0..50).map(|i| i as f64).collect();
+        let b: Vec<f64> = (0..50).map(|i| i as f64 + 0.5).collect();
+        let r = ks_2samp(&a, &b);
+        assert!((r.statistic - 0.02).abs() < 1e-12);
+        assert!(r.p_value > 0.99);
+
+        let c: Vec<f64> = (0..50).map(|i| i as f64 + 100.0).collect();
+        let r = ks_2samp(&a, &c);
+        assert_eq!(r.statistic, 1.0);
+        assert!(r.p_value < 1e-10);
+
+        // ties between the samples
+        assert_eq!(ks_2samp(&[1.0, 2.0], &[1.0, 2.0]).statistic, 0.0);
+    }
+
+    #[test]
+    fn test_ks_1samp() {
+
+        let uniform = |x: f64| x.max(0.0).min(1.0);
+        let r = ks_1samp(&[0.7, 0.1, 0.4], uniform);
+        assert!((r.statistic - 0.3).abs() < 1e-12);
+
+        let a: Vec<f64> = (0..100).map(|i| (i as f64 + 0.5) / 100.0).collect();
+        assert!(ks_1samp(&a, uniform).p_value > 0.99);
+        let b: Vec<f64> = a.iter().map(|x| x * x).collect();
+        assert!(ks_1samp(&b, uniform).p_value < 0.01);
+    }
+}
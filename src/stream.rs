@@ -0,0 +1,443 @@
+//! Out-of-core training on datasets which do not fit into memory.
+//!
+//! A [ChunkSource](trait.ChunkSource.html) delivers the examples of a
+//! dataset in chunks of rows, e.g. read incrementally from a CSV file
+//! ([CsvChunks](../io/struct.CsvChunks.html)), a NumPy file
+//! ([NpyChunks](../io/npy/struct.NpyChunks.html)) or, with the feature
+//! `hdf5`, a dataset of an HDF5 file. The
+//! [StreamTrainer](struct.StreamTrainer.html) splits each chunk into
+//! mini-batches and passes them to a model which implements
+//! [PartialFit](trait.PartialFit.html) (e.g. a linear
+//! [Hypothesis](../regression/struct.Hypothesis.html) or a
+//! [NeuralNetwork](../nn/struct.NeuralNetwork.html)). Only one chunk is held
+//! in memory at any time.
+//!
+//! The rows are processed in the order in which they are stored, i.e. the
+//! dataset should be shuffled before it is written to disk.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::regression::Hypothesis;
+//! use rustml::stream::*;
+//!
+//! # fn main() {
+//! // y = 1 + 2x; the first column is the bias, the last column the target
+//! let data = Matrix::from_vec(
+//!     (0..40).flat_map(|i| { let x = i as f64 / 40.0; vec![1.0, x, 1.0 + 2.0 * x] }).collect(),
+//!     40, 3);
+//!
+//! let mut h = Hypothesis::from_params(&[0.0, 0.0]);
+//! StreamTrainer::new()
+//!     .epochs(500)
+//!     .batch_size(10)
+//!     .alpha(0.5)
+//!     .fit(&mut h, &mut MatrixChunks::new(&data, 20))
+//!     .unwrap();
+//!
+//! let p = h.params();
+//! assert!((p[0] - 1.0).abs() < 0.01 && (p[1] - 2.0).abs() < 0.01);
+//! # }
+//! ```
+use matrix::Matrix;
+use model_selection::row_batches;
+use progress::{Progress, ProgressInfo, NoProgress, report};
+use io::CsvChunks;
+use io::npy::NpyChunks;
+#[cfg(feature = "hdf5")]
+use io::hdf5::RowChunks;
+use io::model::Model;
+use checkpoint::{Checkpoint, Checkpointer};
+
+/// Models which can be trained incrementally on mini-batches.
+pub trait PartialFit {
+
+    /// Updates the model with one step of gradient descent with the
+    /// learning rate `alpha` on the examples in the rows of `x` and the
+    /// targets in the rows of `y`.
+    fn partial_fit(&mut self, x: &Matrix<f64>, y: &Matrix<f64>, alpha: f64);
+
+    /// Returns the gradient of the mean loss on the examples in the rows of
+    /// `x` and the targets in the rows of `y` with all parameters flattened
+    /// into one vector, or `None` if the model does not support
+    /// [gradient accumulation](struct.StreamTrainer.html#method.accumulate).
+    fn gradient(&self, _x: &Matrix<f64>, _y: &Matrix<f64>) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Updates the parameters with one step of gradient descent with the
+    /// learning rate `alpha` along a gradient returned by
+    /// [gradient](#method.gradient).
+    fn apply_gradient(&mut self, _g: &[f64], _alpha: f64) {
+        panic!("Gradient accumulation is not supported.");
+    }
+}
+
+/// A dataset which is read in chunks of rows.
+pub trait ChunkSource {
+
+    /// Returns the next chunk or `None` if all rows have been read.
+    fn next_chunk(&mut self) -> Option<Result<Matrix<f64>, String>>;
+
+    /// Starts reading again at the first row.
+    fn rewind(&mut self) -> Result<(), String>;
+}
+
+impl ChunkSource for CsvChunks {
+
+    fn next_chunk(&mut self) -> Option<Result<Matrix<f64>, String>> {
+        self.next()
+    }
+
+    fn rewind(&mut self) -> Result<(), String> {
+        CsvChunks::rewind(self)
+    }
+}
+
+impl ChunkSource for NpyChunks {
+
+    fn next_chunk(&mut self) -> Option<Result<Matrix<f64>, String>> {
+        self.next()
+    }
+
+    fn rewind(&mut self) -> Result<(), String> {
+        NpyChunks::rewind(self)
+    }
+}
+
+#[cfg(feature = "hdf5")]
+impl <'a> ChunkSource for RowChunks<'a> {
+
+    fn next_chunk(&mut self) -> Option<Result<Matrix<f64>, String>> {
+        self.next()
+    }
+
+    fn rewind(&mut self) -> Result<(), String> {
+        RowChunks::rewind(self);
+        Ok(())
+    }
+}
+
+/// A matrix in memory which is delivered in chunks of rows, e.g. to test a
+/// training pipeline on a small dataset.
+pub struct MatrixChunks<'a> {
+    m: &'a Matrix<f64>,
+    chunk_rows: usize,
+    pos: usize
+}
+
+impl <'a> MatrixChunks<'a> {
+
+    /// Creates a source which delivers the rows of `m` in chunks of at most
+    /// `chunk_rows` rows.
+    ///
+    /// Panics if `chunk_rows` is zero.
+    pub fn new(m: &'a Matrix<f64>, chunk_rows: usize) -> MatrixChunks<'a> {
+
+        assert!(chunk_rows > 0, "Number of rows per chunk must be greater than zero.");
+        MatrixChunks { m: m, chunk_rows: chunk_rows, pos: 0 }
+    }
+}
+
+impl <'a> ChunkSource for MatrixChunks<'a> {
+
+    fn next_chunk(&mut self) -> Option<Result<Matrix<f64>, String>> {
+
+        if self.pos >= self.m.rows() {
+            return None;
+        }
+        let n = self.chunk_rows.min(self.m.rows() - self.pos);
+        let c = self.m.cols();
+        let data = self.m.buf()[self.pos * c..(self.pos + n) * c].to_vec();
+        self.pos += n;
+        Some(Ok(Matrix::from_vec(data, n, c)))
+    }
+
+    fn rewind(&mut self) -> Result<(), String> {
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+/// Training driver which streams mini-batches from a
+/// [ChunkSource](trait.ChunkSource.html) through a model.
+///
+/// By default the model is trained for one epoch with mini-batches of 32
+/// rows, a learning rate of 0.01, one update per mini-batch and the last
+/// column of the data as the target.
+#[derive(Clone, Debug)]
+pub struct StreamTrainer {
+    epochs: usize,
+    batch_size: usize,
+    alpha: f64,
+    accumulate: usize,
+    targets: Option<Vec<usize>>
+}
+
+impl StreamTrainer {
+
+    /// Creates a driver with the default configuration.
+    pub fn new() -> StreamTrainer {
+        StreamTrainer {
+            epochs: 1,
+            batch_size: 32,
+            alpha: 0.01,
+            accumulate: 1,
+            targets: None
+        }
+    }
+
+    /// Sets the number of passes over the dataset.
+    pub fn epochs(&self, n: usize) -> StreamTrainer {
+        StreamTrainer { epochs: n, .. self.clone() }
+    }
+
+    /// Sets the number of rows of a mini-batch. A chunk whose number of
+    /// rows is not a multiple of the batch size ends with a smaller batch.
+    ///
+    /// Panics if `n` is zero.
+    pub fn batch_size(&self, n: usize) -> StreamTrainer {
+
+        assert!(n > 0, "Batch size must be greater than zero.");
+        StreamTrainer { batch_size: n, .. self.clone() }
+    }
+
+    /// Sets the learning rate.
+    pub fn alpha(&self, alpha: f64) -> StreamTrainer {
+        StreamTrainer { alpha: alpha, .. self.clone() }
+    }
+
+    /// Accumulates the gradients of `k` mini-batches before the parameters
+    /// are updated, i.e. the update is the same as for one mini-batch of
+    /// `k` times the batch size but only one mini-batch is held in memory.
+    /// The gradients of the mini-batches are weighted by their number of
+    /// rows. The last update of an epoch may use fewer mini-batches.
+    ///
+    /// With `k > 1` the model must support
+    /// [gradient](trait.PartialFit.html#method.gradient). Otherwise,
+    /// training returns an error.
+    ///
+    /// Panics if `k` is zero.
+    pub fn accumulate(&self, k: usize) -> StreamTrainer {
+
+        assert!(k > 0, "Number of accumulated mini-batches must be greater than zero.");
+        StreamTrainer { accumulate: k, .. self.clone() }
+    }
+
+    /// Sets the (zero-based) indexes of the columns which contain the
+    /// targets. All other columns are the features.
+    pub fn targets(&self, cols: &[usize]) -> StreamTrainer {
+        StreamTrainer { targets: Some(cols.to_vec()), .. self.clone() }
+    }
+
+    /// Trains the model on all rows of the source for the configured number
+    /// of epochs. The source is rewound before each epoch.
+    ///
+    /// Returns an error if a chunk cannot be read or if a target column
+    /// does not exist.
+    pub fn fit<M, S>(&self, model: &mut M, source: &mut S) -> Result<(), String>
+        where M: PartialFit + ?Sized, S: ChunkSource + ?Sized {
+
+        self.fit_with_progress(model, source, &mut NoProgress)
+    }
+
+    /// Like [fit](#method.fit) but reports the progress after each epoch.
+    pub fn fit_with_progress<M, S, P>(&self, model: &mut M, source: &mut S, progress: &mut P) -> Result<(), String>
+        where M: PartialFit + ?Sized, S: ChunkSource + ?Sized, P: Progress + ?Sized {
+
+        for epoch in 0..self.epochs {
+            try!(self.epoch(model, source));
+            report(progress, &ProgressInfo {
+                algorithm: "out-of-core training",
+                iteration: epoch + 1,
+                max_iter: self.epochs,
+                loss: None
+            });
+        }
+        Ok(())
+    }
+
+    /// Trains the model like [fit](#method.fit) starting with the checkpoint
+    /// `start` (e.g. loaded with
+    /// [resume_from](../checkpoint/fn.resume_from.html)) and saves
+    /// checkpoints with `checkpoints`. Returns the trained model.
+    pub fn fit_with_checkpoints<M, S>(&self, start: Checkpoint<M>, source: &mut S, checkpoints: &mut Checkpointer) -> Result<M, String>
+        where M: PartialFit + Model + Clone, S: ChunkSource + ?Sized {
+
+        let Checkpoint { model: mut m, mut state } = start;
+        while state.epoch < self.epochs {
+            try!(self.epoch(&mut m, source));
+            state.epoch += 1;
+            try!(checkpoints.update(&m, &state));
+        }
+        Ok(m)
+    }
+
+    /// Trains the model on all rows of the source.
+    fn epoch<M, S>(&self, model: &mut M, source: &mut S) -> Result<(), String>
+        where M: PartialFit + ?Sized, S: ChunkSource + ?Sized {
+
+        try!(source.rewind());
+        // sum of the gradients weighted by the number of rows
+        let mut acc: Vec<f64> = vec![];
+        let (mut rows, mut batches) = (0, 0);
+        while let Some(chunk) = source.next_chunk() {
+            let chunk = try!(chunk);
+            for batch in row_batches(&chunk, self.batch_size) {
+                let (x, y) = try!(self.split(&batch));
+                if self.accumulate == 1 {
+                    model.partial_fit(&x, &y, self.alpha);
+                    continue;
+                }
+                let g = try!(model.gradient(&x, &y).ok_or("Model does not support gradient accumulation.".to_string()));
+                acc.resize(g.len(), 0.0);
+                for (a, v) in acc.iter_mut().zip(g.iter()) {
+                    *a += v * x.rows() as f64;
+                }
+                rows += x.rows();
+                batches += 1;
+                if batches == self.accumulate {
+                    self.apply(model, &mut acc, rows);
+                    rows = 0;
+                    batches = 0;
+                }
+            }
+        }
+        if batches > 0 {
+            self.apply(model, &mut acc, rows);
+        }
+        Ok(())
+    }
+
+    /// Updates the model with the mean of the accumulated gradients and
+    /// resets the accumulator.
+    fn apply<M: PartialFit + ?Sized>(&self, model: &mut M, acc: &mut Vec<f64>, rows: usize) {
+
+        for a in acc.iter_mut() {
+            *a /= rows as f64;
+        }
+        model.apply_gradient(acc, self.alpha);
+        for a in acc.iter_mut() {
+            *a = 0.0;
+        }
+    }
+
+    /// Splits the rows into features and targets.
+    fn split(&self, m: &Matrix<f64>) -> Result<(Matrix<f64>, Matrix<f64>), String> {
+
+        let c = m.cols();
+        let targets = match self.targets {
+            Some(ref t) => t.clone(),
+            None if c > 0 => vec![c - 1],
+            None => vec![]
+        };
+        if let Some(&i) = targets.iter().find(|&&i| i >= c) {
+            return Err(format!("Target column {} does not exist.", i));
+        }
+        let features: Vec<usize> = (0..c).filter(|i| !targets.contains(i)).collect();
+
+        let mut x = Vec::with_capacity(m.rows() * features.len());
+        let mut y = Vec::with_capacity(m.rows() * targets.len());
+        for r in m.row_iter() {
+            x.extend(features.iter().map(|&i| r[i]));
+            y.extend(targets.iter().map(|&i| r[i]));
+        }
+        Ok((Matrix::from_vec(x, m.rows(), features.len()), Matrix::from_vec(y, m.rows(), targets.len())))
+    }
+}
+
+impl Default for StreamTrainer {
+    fn default() -> StreamTrainer {
+        StreamTrainer::new()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use nn::{NeuralNetwork, MeanSquareError};
+    use regression::Hypothesis;
+    use io::npy::save_npy;
+
+    #[test]
+    fn test_stream_trainer() {
+
+        // target in the first column, y = 0.8 if x0 > x1 else 0.2
+        let data = Matrix::from_vec(
+            (0..200).flat_map(|i| {
+                let (a, b) = ((i * 7 % 13) as f64 / 13.0, (i * 5 % 11) as f64 / 11.0);
+                vec![if a > b { 0.8 } else { 0.2 }, a, b]
+            }).collect(),
+            200, 3);
+
+        let path = ::std::env::temp_dir().join("rustml_test_stream.npy");
+        let fname = path.to_str().unwrap();
+        save_npy(fname, &data).unwrap();
+
+        let t = StreamTrainer::new().epochs(300).batch_size(20).alpha(2.0).targets(&[0]);
+        let mut n = NeuralNetwork::new().add_layer(2).add_layer(4).add_layer(1);
+        let x = Matrix::from_vec(data.row_iter().flat_map(|r| r[1..].to_vec()).collect(), 200, 2);
+        let y = Matrix::from_vec(data.row_iter().map(|r| r[0]).collect(), 200, 1);
+        let before = n.mse(&x, &y);
+
+        let mut epochs = 0;
+        t.fit_with_progress(&mut n, &mut NpyChunks::open(fname, 50).unwrap(), &mut |_: &ProgressInfo| epochs += 1).unwrap();
+        assert_eq!(epochs, 300);
+        assert!(n.mse(&x, &y) < before / 2.0);
+
+        assert!(t.targets(&[3]).fit(&mut n, &mut MatrixChunks::new(&data, 10)).is_err());
+    }
+
+    #[test]
+    fn test_accumulate() {
+
+        let data = Matrix::from_vec(
+            (0..60).flat_map(|i| { let x = i as f64 / 60.0; vec![1.0, x, 1.0 + 2.0 * x] }).collect(),
+            60, 3);
+
+        // four accumulated batches of 5 rows are one batch of 20 rows, also
+        // across the boundaries of the chunks
+        let mut a = Hypothesis::from_params(&[0.0, 0.0]);
+        let mut b = a.clone();
+        let t = StreamTrainer::new().epochs(3).alpha(0.5);
+        t.batch_size(5).accumulate(4).fit(&mut a, &mut MatrixChunks::new(&data, 30)).unwrap();
+        t.batch_size(20).fit(&mut b, &mut MatrixChunks::new(&data, 60)).unwrap();
+        assert!(a.params().similar(&b.params(), 1e-12));
+
+        // the last update of an epoch uses the remaining batches, i.e.
+        // 40 and 20 rows
+        let mut n = NeuralNetwork::new().add_layer(2).add_layer(3).add_layer(1);
+        let mut m = n.clone();
+        let t = t.targets(&[2]);
+        t.batch_size(10).accumulate(4).fit(&mut n, &mut MatrixChunks::new(&data, 60)).unwrap();
+        t.batch_size(40).fit(&mut m, &mut MatrixChunks::new(&data, 60)).unwrap();
+        for (p, q) in n.params().iter().zip(m.params().iter()) {
+            assert!(p.similar(q, 1e-12));
+        }
+
+        struct Constant;
+        impl PartialFit for Constant {
+            fn partial_fit(&mut self, _x: &Matrix<f64>, _y: &Matrix<f64>, _alpha: f64) {}
+        }
+        assert!(t.fit(&mut Constant, &mut MatrixChunks::new(&data, 60)).is_ok());
+        assert!(t.accumulate(2).fit(&mut Constant, &mut MatrixChunks::new(&data, 60)).is_err());
+    }
+
+    #[test]
+    fn test_split() {
+
+        let m = mat![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        let (x, y) = StreamTrainer::new().split(&m).unwrap();
+        assert_eq!(x, mat![1.0, 2.0; 4.0, 5.0]);
+        assert_eq!(y, mat![3.0; 6.0]);
+
+        let (x, y) = StreamTrainer::new().targets(&[2, 0]).split(&m).unwrap();
+        assert_eq!(x, mat![2.0; 5.0]);
+        assert_eq!(y, mat![3.0, 1.0; 6.0, 4.0]);
+    }
+}
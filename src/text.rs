@@ -0,0 +1,586 @@
+//! Module to convert text documents into feature vectors.
+//!
+//! The [`CountVectorizer`](struct.CountVectorizer.html) splits each document
+//! into tokens, builds a vocabulary of the tokens (or n-grams of tokens)
+//! found in a set of training documents and converts each document into a
+//! vector which contains the number of occurrences of each entry of the
+//! vocabulary (bag-of-words model). The counts can be weighted with the
+//! [`TfidfTransformer`](struct.TfidfTransformer.html) so that tokens which
+//! occur in many documents get a lower weight.
+//!
+//! The [`TextClassifier`](struct.TextClassifier.html) combines both with a
+//! multinomial naive Bayes or a logistic regression model, i.e. it is fitted
+//! on raw documents and their labels and predicts the labels of new
+//! documents.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::text::*;
+//!
+//! # fn main() {
+//! let docs = ["The cat sat.", "The dog sat on the cat."];
+//!
+//! let v = CountVectorizer::new().fit(&docs);
+//! assert_eq!(v.vocabulary(), &["cat", "dog", "on", "sat", "the"]);
+//!
+//! let counts = v.transform(&docs);
+//! assert_eq!(counts, mat![
+//!     1.0, 0.0, 0.0, 1.0, 1.0;
+//!     1.0, 1.0, 1.0, 1.0, 2.0
+//! ]);
+//!
+//! let tfidf = TfidfTransformer::fit(&counts).transform(&counts);
+//! assert!(tfidf.get(1, 1).unwrap() > tfidf.get(1, 0).unwrap());
+//! # }
+//! ```
+//!
+//! ```
+//! use rustml::text::*;
+//!
+//! let docs = ["cheap pills now", "meeting at noon", "cheap watches", "lunch meeting"];
+//! let labels = ["spam", "ham", "spam", "ham"];
+//!
+//! let mut c = TextClassifier::new(TextModel::NaiveBayes(1.0));
+//! c.fit(&docs, &labels);
+//! assert_eq!(c.predict(&["cheap lunch pills", "noon meeting"]), vec!["spam", "ham"]);
+//! ```
+use std::collections::BTreeMap;
+
+use matrix::Matrix;
+use io::model::{Model, Encoder, Decoder};
+use opt::{lbfgs, empty_opts};
+
+/// Splits a text into lowercase tokens. A token is a maximal sequence of
+/// alphanumeric characters.
+///
+/// # Example
+///
+/// ```
+/// use rustml::text::tokenize;
+///
+/// assert_eq!(tokenize("Hello, World! 42x"), vec!["hello", "world", "42x"]);
+/// ```
+pub fn tokenize(s: &str) -> Vec<String> {
+
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Converts documents into vectors of token counts.
+///
+/// The vectorizer is configured with the builder methods and then fitted on
+/// a set of training documents which determines the vocabulary. Each entry
+/// of the vocabulary is a token or an n-gram of tokens joined by a space.
+/// The vocabulary is sorted, i.e. column `i` of a transformed matrix
+/// contains the counts of the `i`th entry of the sorted vocabulary.
+#[derive(Clone, Debug)]
+pub struct CountVectorizer {
+    ngram_min: usize,
+    ngram_max: usize,
+    min_df: usize,
+    max_df: f64,
+    vocabulary: BTreeMap<String, usize>
+}
+
+impl CountVectorizer {
+
+    /// Creates a vectorizer that uses single tokens (unigrams) and no
+    /// restrictions on the document frequency.
+    pub fn new() -> CountVectorizer {
+        CountVectorizer {
+            ngram_min: 1,
+            ngram_max: 1,
+            min_df: 1,
+            max_df: 1.0,
+            vocabulary: BTreeMap::new()
+        }
+    }
+
+    /// Uses all n-grams with `min <= n <= max`, e.g. `(1, 2)` uses unigrams
+    /// and bigrams.
+    ///
+    /// Panics if `min` is zero or `min > max`.
+    pub fn ngram_range(&self, min: usize, max: usize) -> CountVectorizer {
+
+        assert!(min > 0 && min <= max, "Invalid n-gram range.");
+
+        CountVectorizer {
+            ngram_min: min,
+            ngram_max: max,
+            .. self.clone()
+        }
+    }
+
+    /// Ignores n-grams which occur in less than `n` training documents.
+    pub fn min_df(&self, n: usize) -> CountVectorizer {
+        CountVectorizer {
+            min_df: n,
+            .. self.clone()
+        }
+    }
+
+    /// Ignores n-grams which occur in more than the given fraction of the
+    /// training documents (e.g. stop words).
+    ///
+    /// Panics if `fraction` is not within [0, 1].
+    pub fn max_df(&self, fraction: f64) -> CountVectorizer {
+
+        assert!(fraction >= 0.0 && fraction <= 1.0, "Fraction must be within [0, 1].");
+
+        CountVectorizer {
+            max_df: fraction,
+            .. self.clone()
+        }
+    }
+
+    /// Returns the n-grams of a document.
+    pub fn analyze(&self, doc: &str) -> Vec<String> {
+
+        let tokens = tokenize(doc);
+        let mut r = Vec::new();
+        for n in self.ngram_min..self.ngram_max + 1 {
+            for w in tokens.windows(n) {
+                r.push(w.join(" "));
+            }
+        }
+        r
+    }
+
+    /// Builds the vocabulary from the training documents and returns a
+    /// vectorizer with the same configuration and the new vocabulary.
+    pub fn fit<S: AsRef<str>>(&self, docs: &[S]) -> CountVectorizer {
+
+        let mut df: BTreeMap<String, usize> = BTreeMap::new();
+        for d in docs {
+            let mut grams = self.analyze(d.as_ref());
+            grams.sort();
+            grams.dedup();
+            for g in grams {
+                *df.entry(g).or_insert(0) += 1;
+            }
+        }
+
+        let max = self.max_df * docs.len() as f64;
+        let vocabulary = df.into_iter()
+            .filter(|&(_, n)| n >= self.min_df && n as f64 <= max)
+            .enumerate()
+            .map(|(i, (g, _))| (g, i))
+            .collect();
+
+        CountVectorizer {
+            vocabulary: vocabulary,
+            .. self.clone()
+        }
+    }
+
+    /// Builds the vocabulary and returns the vectorizer together with the
+    /// counts of the training documents.
+    pub fn fit_transform<S: AsRef<str>>(&self, docs: &[S]) -> (CountVectorizer, Matrix<f64>) {
+
+        let v = self.fit(docs);
+        let m = v.transform(docs);
+        (v, m)
+    }
+
+    /// Returns the sorted vocabulary.
+    pub fn vocabulary(&self) -> Vec<&str> {
+        self.vocabulary.keys().map(|k| &k[..]).collect()
+    }
+
+    /// Returns the column of the given n-gram.
+    pub fn index(&self, gram: &str) -> Option<usize> {
+        self.vocabulary.get(gram).cloned()
+    }
+
+    /// Converts each document into a row which contains the number of
+    /// occurrences of each entry of the vocabulary. N-grams which are not in
+    /// the vocabulary are ignored.
+    pub fn transform<S: AsRef<str>>(&self, docs: &[S]) -> Matrix<f64> {
+
+        let cols = self.vocabulary.len();
+        let mut v = vec![0.0; docs.len() * cols];
+        for (i, d) in docs.iter().enumerate() {
+            for g in self.analyze(d.as_ref()) {
+                if let Some(&j) = self.vocabulary.get(&g) {
+                    v[i * cols + j] += 1.0;
+                }
+            }
+        }
+        Matrix::from_vec(v, docs.len(), cols)
+    }
+}
+
+/// Weights token counts with the inverse document frequency (tf-idf).
+///
+/// The inverse document frequency of column `j` is computed as
+/// `idf(j) = ln((1 + n) / (1 + df(j))) + 1` where `n` is the number of
+/// documents and `df(j)` is the number of documents in which the count of
+/// column `j` is not zero. Each count is multiplied by the idf of its column
+/// and each row is normalized to unit length (Euclidean norm).
+#[derive(Clone, Debug)]
+pub struct TfidfTransformer {
+    idf: Vec<f64>
+}
+
+impl TfidfTransformer {
+
+    /// Computes the inverse document frequencies from a matrix of counts
+    /// (e.g. created with a [`CountVectorizer`](struct.CountVectorizer.html)).
+    pub fn fit(counts: &Matrix<f64>) -> TfidfTransformer {
+
+        let n = counts.rows() as f64;
+        TfidfTransformer {
+            idf: counts.col_iter()
+                .map(|c| c.iter().filter(|&&x| x != 0.0).count() as f64)
+                .map(|df| ((1.0 + n) / (1.0 + df)).ln() + 1.0)
+                .collect()
+        }
+    }
+
+    /// Returns the inverse document frequency of each column.
+    pub fn idf(&self) -> &[f64] {
+        &self.idf
+    }
+
+    /// Weights the counts and normalizes each row.
+    ///
+    /// Panics if the number of columns does not match the number of columns
+    /// used during `fit`.
+    pub fn transform(&self, counts: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(counts.cols() == self.idf.len(),
+            "Number of columns does not match the number of features.");
+
+        let mut v = Vec::with_capacity(counts.rows() * counts.cols());
+        for row in counts.row_iter() {
+            let w: Vec<f64> = row.iter().zip(self.idf.iter()).map(|(x, i)| x * i).collect();
+            let norm = w.iter().fold(0.0, |acc, x| acc + x * x).sqrt();
+            v.extend(w.iter().map(|x| if norm > 0.0 { x / norm } else { 0.0 }));
+        }
+        Matrix::from_vec(v, counts.rows(), counts.cols())
+    }
+}
+
+/// Models of the [`TextClassifier`](struct.TextClassifier.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextModel {
+    /// Multinomial naive Bayes with the given additive (Laplace) smoothing
+    /// of the feature counts of each class.
+    NaiveBayes(f64),
+    /// Multinomial logistic regression (softmax) with the given weight of
+    /// the L2 penalty, trained with [lbfgs](../opt/fn.lbfgs.html).
+    LogisticRegression(f64)
+}
+
+/// Classifier of text documents which converts the documents into token
+/// counts with a [`CountVectorizer`](struct.CountVectorizer.html), weights
+/// them with a [`TfidfTransformer`](struct.TfidfTransformer.html) (unless
+/// disabled) and fits a linear [model](enum.TextModel.html) on the result.
+#[derive(Clone, Debug)]
+pub struct TextClassifier {
+    vectorizer: CountVectorizer,
+    tfidf: Option<TfidfTransformer>,
+    use_tfidf: bool,
+    model: TextModel,
+    classes: Vec<String>,
+    // one row of weights per class
+    coef: Matrix<f64>,
+    intercept: Vec<f64>
+}
+
+impl TextClassifier {
+
+    /// Creates an unfitted classifier with the given model, a vectorizer
+    /// for unigrams and tf-idf weighting.
+    ///
+    /// Panics if the parameter of the model is negative.
+    pub fn new(model: TextModel) -> TextClassifier {
+
+        match model {
+            TextModel::NaiveBayes(a) => assert!(a >= 0.0, "Smoothing must not be negative."),
+            TextModel::LogisticRegression(c) => assert!(c >= 0.0, "Penalty must not be negative.")
+        }
+        TextClassifier {
+            vectorizer: CountVectorizer::new(),
+            tfidf: None,
+            use_tfidf: true,
+            model: model,
+            classes: vec![],
+            coef: Matrix::new(),
+            intercept: vec![]
+        }
+    }
+
+    /// Sets the vectorizer (e.g. to use bigrams or to remove rare tokens)
+    /// which is fitted on the training documents.
+    pub fn vectorizer(&self, v: CountVectorizer) -> TextClassifier {
+        TextClassifier { vectorizer: v, .. self.clone() }
+    }
+
+    /// Enables or disables the tf-idf weighting of the counts.
+    pub fn tfidf(&self, enabled: bool) -> TextClassifier {
+        TextClassifier { use_tfidf: enabled, .. self.clone() }
+    }
+
+    /// Returns the sorted class labels of the training documents.
+    pub fn classes(&self) -> &[String] {
+        &self.classes
+    }
+
+    /// Returns the fitted vectorizer.
+    pub fn fitted_vectorizer(&self) -> &CountVectorizer {
+        &self.vectorizer
+    }
+
+    /// Fits the vectorizer, the tf-idf weights and the model on the
+    /// documents `docs` with the labels `labels`.
+    ///
+    /// Panics if the number of documents and labels differ or if there are
+    /// no documents.
+    pub fn fit<S: AsRef<str>, T: AsRef<str>>(&mut self, docs: &[S], labels: &[T]) {
+
+        assert!(docs.len() == labels.len(), "Number of documents and labels must be equal.");
+        assert!(!docs.is_empty(), "At least one document is required.");
+
+        let mut classes: Vec<String> = labels.iter().map(|l| l.as_ref().to_string()).collect();
+        classes.sort();
+        classes.dedup();
+        let y: Vec<usize> = labels.iter().map(|l| classes.binary_search(&l.as_ref().to_string()).unwrap()).collect();
+        self.classes = classes;
+
+        let (v, counts) = self.vectorizer.fit_transform(docs);
+        self.vectorizer = v;
+        self.tfidf = if self.use_tfidf { Some(TfidfTransformer::fit(&counts)) } else { None };
+        let x = self.features(counts);
+
+        let (k, d) = (self.classes.len(), x.cols());
+        match self.model {
+            TextModel::NaiveBayes(alpha) => {
+                let mut fc = vec![0.0; k * d];
+                let mut cc = vec![0.0; k];
+                for (row, &c) in x.row_iter().zip(y.iter()) {
+                    cc[c] += 1.0;
+                    for j in 0..d {
+                        fc[c * d + j] += row[j];
+                    }
+                }
+                let mut coef = Vec::with_capacity(k * d);
+                for c in 0..k {
+                    let total: f64 = fc[c * d..(c + 1) * d].iter().sum::<f64>() + alpha * d as f64;
+                    coef.extend(fc[c * d..(c + 1) * d].iter().map(|f| ((f + alpha) / total).ln()));
+                }
+                self.coef = Matrix::from_vec(coef, k, d);
+                self.intercept = cc.iter().map(|n| (n / y.len() as f64).ln()).collect();
+            }
+            TextModel::LogisticRegression(c2) => {
+                let fg = |w: &[f64]| {
+                    let mut f = 0.5 * c2 * w[..k * d].iter().fold(0.0, |acc, x| acc + x * x);
+                    let mut g: Vec<f64> = (0..w.len()).map(|i| if i < k * d { c2 * w[i] } else { 0.0 }).collect();
+                    for (row, &c) in x.row_iter().zip(y.iter()) {
+                        let p = softmax(&linear(&w[..k * d], &w[k * d..], row));
+                        f -= p[c].ln();
+                        for j in 0..k {
+                            let e = p[j] - if j == c { 1.0 } else { 0.0 };
+                            for (i, xi) in row.iter().enumerate() {
+                                g[j * d + i] += e * xi;
+                            }
+                            g[k * d + j] += e;
+                        }
+                    }
+                    (f, g)
+                };
+                let w = lbfgs(&fg, &vec![0.0; k * d + k], empty_opts().iter(200)).params;
+                self.coef = Matrix::from_vec(w[..k * d].to_vec(), k, d);
+                self.intercept = w[k * d..].to_vec();
+            }
+        }
+    }
+
+    /// Returns the probability of each class (columns in the order of
+    /// [classes](#method.classes)) for each document (rows).
+    ///
+    /// Panics if the classifier is not fitted.
+    pub fn predict_proba<S: AsRef<str>>(&self, docs: &[S]) -> Matrix<f64> {
+
+        assert!(!self.classes.is_empty(), "Classifier is not fitted.");
+        let x = self.features(self.vectorizer.transform(docs));
+        let v = x.row_iter().flat_map(|row| softmax(&linear(self.coef.buf(), &self.intercept, row))).collect();
+        Matrix::from_vec(v, docs.len(), self.classes.len())
+    }
+
+    /// Returns the most probable class of each document.
+    ///
+    /// Panics if the classifier is not fitted.
+    pub fn predict<S: AsRef<str>>(&self, docs: &[S]) -> Vec<String> {
+
+        self.predict_proba(docs).row_iter()
+            .map(|p| (0..p.len()).fold(0, |a, b| if p[b] > p[a] { b } else { a }))
+            .map(|c| self.classes[c].clone())
+            .collect()
+    }
+
+    fn features(&self, counts: Matrix<f64>) -> Matrix<f64> {
+        match self.tfidf {
+            Some(ref t) => t.transform(&counts),
+            None => counts
+        }
+    }
+}
+
+// scores of each class for one row
+fn linear(coef: &[f64], intercept: &[f64], row: &[f64]) -> Vec<f64> {
+
+    let d = row.len();
+    intercept.iter().enumerate()
+        .map(|(c, b)| b + row.iter().zip(coef[c * d..(c + 1) * d].iter()).fold(0.0, |acc, (x, w)| acc + x * w))
+        .collect()
+}
+
+fn softmax(s: &[f64]) -> Vec<f64> {
+
+    let m = s.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max);
+    let e: Vec<f64> = s.iter().map(|x| (x - m).exp()).collect();
+    let z: f64 = e.iter().sum();
+    e.iter().map(|x| x / z).collect()
+}
+
+impl Model for CountVectorizer {
+
+    fn tag() -> &'static str { "rustml.text.CountVectorizer" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.usize(self.ngram_min);
+        e.usize(self.ngram_max);
+        e.usize(self.min_df);
+        e.f64(self.max_df);
+        e.usize(self.vocabulary.len());
+        for (k, &v) in &self.vocabulary {
+            e.str(k);
+            e.usize(v);
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<CountVectorizer, String> {
+
+        let mut c = CountVectorizer {
+            ngram_min: try!(d.usize()),
+            ngram_max: try!(d.usize()),
+            min_df: try!(d.usize()),
+            max_df: try!(d.f64()),
+            vocabulary: BTreeMap::new()
+        };
+        for _ in 0..try!(d.usize()) {
+            let k = try!(d.str());
+            c.vocabulary.insert(k, try!(d.usize()));
+        }
+        Ok(c)
+    }
+}
+
+impl Model for TfidfTransformer {
+
+    fn tag() -> &'static str { "rustml.text.TfidfTransformer" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.idf);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<TfidfTransformer, String> {
+        Ok(TfidfTransformer { idf: try!(d.f64s()) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+
+    #[test]
+    fn test_tokenize() {
+
+        assert_eq!(tokenize("  A b-c  "), vec!["a", "b", "c"]);
+        assert!(tokenize(" ,. ").is_empty());
+    }
+
+    #[test]
+    fn test_ngrams() {
+
+        let v = CountVectorizer::new().ngram_range(1, 2).fit(&["a b a"]);
+        assert_eq!(v.vocabulary(), &["a", "a b", "b", "b a"]);
+        assert_eq!(v.transform(&["a b a", "b a c"]), mat![
+            2.0, 1.0, 1.0, 1.0;
+            1.0, 0.0, 1.0, 1.0
+        ]);
+        assert_eq!(v.index("b a"), Some(3));
+        assert_eq!(v.index("c"), None);
+    }
+
+    #[test]
+    fn test_document_frequency() {
+
+        let docs = ["the a", "the b b", "the a c"];
+
+        let v = CountVectorizer::new().min_df(2).fit(&docs);
+        assert_eq!(v.vocabulary(), &["a", "the"]);
+
+        let (v, m) = CountVectorizer::new().max_df(0.8).fit_transform(&docs);
+        assert_eq!(v.vocabulary(), &["a", "b", "c"]);
+        assert_eq!(m, mat![1.0, 0.0, 0.0; 0.0, 2.0, 0.0; 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_tfidf() {
+
+        let counts = mat![
+            1.0, 1.0;
+            1.0, 0.0;
+            0.0, 0.0
+        ];
+        let t = TfidfTransformer::fit(&counts);
+        assert!(t.idf().similar(&vec![(4.0f64 / 3.0).ln() + 1.0, 2.0f64.ln() + 1.0], 1e-10));
+
+        let r = t.transform(&counts);
+        assert_eq!(r.row(1).unwrap(), &[1.0, 0.0]);
+        assert_eq!(r.row(2).unwrap(), &[0.0, 0.0]);
+        let norm = r.row(0).unwrap().iter().fold(0.0, |acc, x| acc + x * x);
+        assert!((norm - 1.0f64).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_text_classifier() {
+
+        let docs = [
+            "the striker scored a goal", "the team won the match", "a late goal won the cup",
+            "the court ruled on the law", "the judge signed the law", "a new law passed the vote",
+            "the rocket reached orbit", "the telescope saw a star", "orbit of a distant star"
+        ];
+        let labels = ["sport", "sport", "sport", "law", "law", "law", "space", "space", "space"];
+        let test = ["the goal of the team", "the judge and the court", "a star in orbit"];
+
+        for &m in &[TextModel::NaiveBayes(1.0), TextModel::LogisticRegression(0.01)] {
+            for &t in &[true, false] {
+                let mut c = TextClassifier::new(m).tfidf(t);
+                c.fit(&docs, &labels);
+                assert_eq!(c.classes(), &["law", "space", "sport"]);
+                assert_eq!(c.predict(&docs), labels);
+                assert_eq!(c.predict(&test), vec!["sport", "law", "space"]);
+
+                let p = c.predict_proba(&test);
+                for r in p.row_iter() {
+                    assert!((r.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+                }
+            }
+        }
+
+        let mut c = TextClassifier::new(TextModel::NaiveBayes(1.0)).vectorizer(CountVectorizer::new().ngram_range(1, 2));
+        c.fit(&docs, &labels);
+        assert!(c.fitted_vectorizer().index("the law").is_some());
+    }
+}
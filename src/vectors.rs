@@ -5,7 +5,7 @@ extern crate rand;
 
 use self::libc::{c_void, size_t};
 use self::num::traits::Num;
-use self::rand::{thread_rng, Rng, Rand};
+use self::rand::{Rng, Rand};
 use std::marker::Copy;
 use std::cmp::{PartialEq, min};
 use std::iter;
@@ -16,6 +16,8 @@ use std::io::{Write, Read, BufRead, BufReader, Result, Error, ErrorKind};
 use std::str::FromStr;
 use std::marker::PhantomData;
 
+use rng;
+
 
 // ------------------------------------------------------------------
 
@@ -341,10 +343,19 @@ pub fn from_value<T: Num + Clone>(val: T, n: usize) -> Vec<T> {
 ///
 /// # Implemenation details
 ///
-/// The function uses `thread_rng` to create the random elements.
+/// The function uses the generator returned by
+/// [rng::rng](../rng/fn.rng.html) to create the random elements, i.e. the
+/// elements are reproducible if a global seed has been set.
 pub fn random<T: Rand + Clone>(n: usize) -> Vec<T> {
 
-    thread_rng().gen_iter::<T>().take(n).collect::<Vec<T>>()
+    random_with_rng(n, &mut rng::rng())
+}
+
+/// Creates a vector with random elements which are created with the given
+/// random number generator.
+pub fn random_with_rng<T: Rand + Clone, R: Rng>(n: usize, rng: &mut R) -> Vec<T> {
+
+    rng.gen_iter::<T>().take(n).collect::<Vec<T>>()
 }
 
 // ------------------------------------------------------------------
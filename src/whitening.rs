@@ -0,0 +1,217 @@
+//! Module to decorrelate features (whitening).
+//!
+//! A whitening transform maps the data to data whose covariance matrix is the
+//! identity matrix, i.e. the features are uncorrelated and have unit
+//! variance. Whitening the inputs often speeds up the training of neural
+//! networks.
+//!
+//! Let `C = U diag(l) U^T` be the eigendecomposition of the covariance matrix
+//! of the training set and `m` its mean. An example `x` is transformed into
+//!
+//! * `diag(1 / sqrt(l + eps)) U^T (x - m)` with PCA whitening, i.e. the data
+//! is rotated into the coordinate system of the principal components and
+//! each component is scaled to unit variance.
+//! * `U diag(1 / sqrt(l + eps)) U^T (x - m)` with ZCA whitening, i.e. the
+//! data is rotated back after scaling so that the whitened data is as close
+//! as possible to the original data.
+//!
+//! The small constant `eps` avoids the division by zero for components
+//! with zero variance.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate rustml;
+//! use rustml::*;
+//! use rustml::linalg::covariance;
+//! use rustml::whitening::*;
+//!
+//! # fn main() {
+//! let m = mat![
+//!     1.0, 2.0;
+//!     2.0, 3.5;
+//!     3.0, 7.0;
+//!     4.0, 7.5
+//! ];
+//! let (w, x) = Whitening::fit_transform(&m, WhiteningMethod::Zca, 0.0);
+//!
+//! let c = covariance(&x, Normalization::N);
+//! assert!(c.similar(&mat![1.0, 0.0; 0.0, 1.0], 1e-8));
+//! assert!(w.inverse_transform(&x).similar(&m, 1e-8));
+//! # }
+//! ```
+use math::{Mean, Dimension, Normalization};
+use matrix::Matrix;
+use ops::MatrixMatrixOps;
+use linalg::{covariance, symmetric_eigen};
+use io::model::{Model, Encoder, Decoder};
+
+/// Determines the rotation used by the whitening transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WhiteningMethod {
+    /// The whitened data is expressed in the basis of the principal
+    /// components.
+    Pca,
+    /// The whitened data is rotated back into the original basis (zero-phase
+    /// component analysis).
+    Zca
+}
+
+/// Whitening transform which is computed from a training set.
+#[derive(Clone, Debug)]
+pub struct Whitening {
+    mean: Vec<f64>,
+    w: Matrix<f64>,
+    w_inv: Matrix<f64>
+}
+
+impl Whitening {
+
+    /// Computes the whitening transform from the examples in the rows of
+    /// `m`. The covariance matrix is normalized by the number of examples.
+    /// The constant `epsilon` is added to the eigenvalues before their
+    /// square root is computed.
+    ///
+    /// Panics if `m` has no rows.
+    pub fn fit(m: &Matrix<f64>, method: WhiteningMethod, epsilon: f64) -> Whitening {
+
+        assert!(m.rows() > 0, "Matrix must not be empty.");
+
+        let (l, u) = symmetric_eigen(&covariance(m, Normalization::N));
+        let d = l.len();
+        let s: Vec<f64> = l.iter().map(|&x| (x.max(0.0) + epsilon).sqrt()).collect();
+
+        // w = diag(1 / s) U^T, w_inv = U diag(s)
+        let mut w = Vec::with_capacity(d * d);
+        for i in 0..d {
+            w.extend(u.col(i).unwrap().iter().map(|x| if s[i] > 0.0 { x / s[i] } else { 0.0 }));
+        }
+        let w = Matrix::from_vec(w, d, d);
+        let w_inv = Matrix::from_it(u.iter().enumerate().map(|(k, x)| x * s[k % d]), d);
+
+        let (w, w_inv) = match method {
+            WhiteningMethod::Pca => (w, w_inv),
+            WhiteningMethod::Zca => (u.mul(&w, false, false), w_inv.mul(&u, false, true))
+        };
+
+        Whitening {
+            mean: m.mean(Dimension::Column),
+            w: w,
+            w_inv: w_inv
+        }
+    }
+
+    /// Computes the whitening transform and returns it together with the
+    /// whitened training set.
+    pub fn fit_transform(m: &Matrix<f64>, method: WhiteningMethod, epsilon: f64) -> (Whitening, Matrix<f64>) {
+
+        let w = Whitening::fit(m, method, epsilon);
+        let r = w.transform(m);
+        (w, r)
+    }
+
+    /// Returns the mean of the training set.
+    pub fn mean(&self) -> &[f64] {
+        &self.mean
+    }
+
+    /// Returns the whitening matrix `W`, i.e. an example `x` is transformed
+    /// into `W (x - mean)`.
+    pub fn matrix(&self) -> &Matrix<f64> {
+        &self.w
+    }
+
+    /// Whitens each row of `m`.
+    ///
+    /// Panics if the number of columns does not match the number of
+    /// features used during `fit`.
+    pub fn transform(&self, m: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(m.cols() == self.mean.len(),
+            "Number of columns does not match the number of features.");
+
+        let d = self.mean.len();
+        let c = Matrix::from_it(m.iter().enumerate().map(|(k, x)| x - self.mean[k % d]), d);
+        c.mul(&self.w, false, true)
+    }
+
+    /// Maps whitened rows back into the original space.
+    ///
+    /// Panics if the number of columns does not match the number of
+    /// features used during `fit`.
+    pub fn inverse_transform(&self, m: &Matrix<f64>) -> Matrix<f64> {
+
+        assert!(m.cols() == self.mean.len(),
+            "Number of columns does not match the number of features.");
+
+        let d = self.mean.len();
+        let r = m.mul(&self.w_inv, false, true);
+        Matrix::from_it(r.iter().enumerate().map(|(k, x)| x + self.mean[k % d]), d)
+    }
+}
+
+impl Model for Whitening {
+
+    fn tag() -> &'static str { "rustml.whitening.Whitening" }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.f64s(&self.mean);
+        e.matrix(&self.w);
+        e.matrix(&self.w_inv);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Whitening, String> {
+        Ok(Whitening { mean: try!(d.f64s()), w: try!(d.matrix()), w_inv: try!(d.matrix()) })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::*;
+    use math::Normalization;
+    use linalg::covariance;
+
+    #[test]
+    fn test_pca_whitening() {
+
+        let m = mat![
+            1.0, 2.0, 0.5;
+            2.0, 3.5, 1.0;
+            3.0, 7.0, 0.0;
+            4.0, 7.5, 2.0;
+            0.0, 1.0, 1.5
+        ];
+        let (w, x) = Whitening::fit_transform(&m, WhiteningMethod::Pca, 0.0);
+
+        let c = covariance(&x, Normalization::N);
+        assert!(c.similar(&mat![1.0, 0.0, 0.0; 0.0, 1.0, 0.0; 0.0, 0.0, 1.0], 1e-8));
+        assert!(x.mean(Dimension::Column).similar(&vec![0.0, 0.0, 0.0], 1e-10));
+        assert!(w.inverse_transform(&x).similar(&m, 1e-8));
+    }
+
+    #[test]
+    fn test_zca_is_symmetric() {
+
+        let m = mat![
+            1.0, 2.0;
+            2.0, 3.5;
+            3.0, 7.0;
+            4.0, 7.5
+        ];
+        let w = Whitening::fit(&m, WhiteningMethod::Zca, 1e-5);
+        let a = w.matrix();
+        assert!((a.get(0, 1).unwrap() - a.get(1, 0).unwrap()).abs() < 1e-10);
+        assert_eq!(w.mean(), &[2.5, 5.0]);
+    }
+
+    #[test]
+    fn test_constant_feature() {
+
+        let m = mat![1.0, 3.0; 2.0, 3.0; 3.0, 3.0];
+        let (_, x) = Whitening::fit_transform(&m, WhiteningMethod::Pca, 0.0);
+        assert!(x.iter().all(|v| v.is_finite()));
+    }
+}
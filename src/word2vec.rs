@@ -0,0 +1,304 @@
+//! Word embeddings with the skip-gram model and negative sampling
+//! (word2vec).
+//!
+//! [Word2Vec](struct.Word2Vec.html) learns a dense vector for each word of a
+//! tokenized corpus (e.g. sentences split with
+//! [tokenize](../text/fn.tokenize.html)) such that words which occur in
+//! similar contexts get similar vectors. For each word of a sentence the
+//! vector of the word is trained to predict the words within a window
+//! around it against `negative` random words drawn from the unigram
+//! distribution raised to the power of 3/4.
+//!
+//! The vectors are returned in the rows of a matrix and the most similar
+//! words of a word or of an arbitrary vector (e.g. for analogies like
+//! `king - man + woman`) are found with
+//! [kneighbors](../knn/fn.kneighbors.html) and the cosine distance.
+//!
+//! # Example
+//!
+//! ```
+//! use rustml::text::tokenize;
+//! use rustml::word2vec::Word2Vec;
+//!
+//! let mut corpus = vec![];
+//! for _ in 0..100 {
+//!     corpus.push(tokenize("the cat chased the mouse"));
+//!     corpus.push(tokenize("the dog chased the cat"));
+//! }
+//!
+//! let mut w = Word2Vec::new(10).window(2).epochs(5).seed([1, 2, 3, 4]);
+//! w.fit(&corpus);
+//! assert_eq!(w.vocabulary(), &["cat", "chased", "dog", "mouse", "the"]);
+//! assert_eq!(w.vectors().cols(), 10);
+//!
+//! let similar = w.most_similar("cat", 2).unwrap();
+//! assert_eq!(similar.len(), 2);
+//! assert!(similar.iter().all(|&(word, _)| word != "cat"));
+//! ```
+extern crate rand;
+
+use std::collections::BTreeMap;
+use self::rand::{Rng, XorShiftRng};
+
+use matrix::Matrix;
+use knn::kneighbors;
+use distance::Metric;
+use rng;
+
+/// Trainer of word embeddings with the skip-gram model and negative
+/// sampling.
+#[derive(Clone, Debug)]
+pub struct Word2Vec {
+    dim: usize,
+    window: usize,
+    negative: usize,
+    epochs: usize,
+    learning_rate: f64,
+    min_count: usize,
+    rng: XorShiftRng,
+    vocabulary: Vec<String>,
+    index: BTreeMap<String, usize>,
+    vectors: Matrix<f64>
+}
+
+impl Word2Vec {
+
+    /// Creates an untrained model which learns vectors with `dim`
+    /// dimensions with the default parameters, i.e. a window of 5 words on
+    /// each side, 5 negative samples, 5 epochs, an initial learning rate of
+    /// 0.025 which decays linearly and a minimum count of 1. The random
+    /// number generator is created with [rng](../rng/fn.rng.html).
+    ///
+    /// Panics if `dim` is zero.
+    pub fn new(dim: usize) -> Word2Vec {
+
+        assert!(dim > 0, "Number of dimensions must be greater than zero.");
+        Word2Vec {
+            dim: dim,
+            window: 5,
+            negative: 5,
+            epochs: 5,
+            learning_rate: 0.025,
+            min_count: 1,
+            rng: rng::rng(),
+            vocabulary: vec![],
+            index: BTreeMap::new(),
+            vectors: Matrix::new()
+        }
+    }
+
+    /// Sets the maximum number of words on each side of a word which are
+    /// predicted from it. For each word the window is shrunk to a random
+    /// size in `1..=n` so that nearby words get a larger weight.
+    ///
+    /// Panics if `n` is zero.
+    pub fn window(&self, n: usize) -> Word2Vec {
+
+        assert!(n > 0, "Window must be greater than zero.");
+        Word2Vec { window: n, .. self.clone() }
+    }
+
+    /// Sets the number of negative samples per word of the context.
+    pub fn negative(&self, n: usize) -> Word2Vec {
+        Word2Vec { negative: n, .. self.clone() }
+    }
+
+    /// Sets the number of passes over the corpus.
+    pub fn epochs(&self, n: usize) -> Word2Vec {
+        Word2Vec { epochs: n, .. self.clone() }
+    }
+
+    /// Sets the initial learning rate.
+    pub fn learning_rate(&self, eta: f64) -> Word2Vec {
+        Word2Vec { learning_rate: eta, .. self.clone() }
+    }
+
+    /// Words which occur fewer than `n` times in the corpus are ignored.
+    pub fn min_count(&self, n: usize) -> Word2Vec {
+        Word2Vec { min_count: n, .. self.clone() }
+    }
+
+    /// Seeds the random number generator which initializes the vectors and
+    /// draws the windows and the negative samples.
+    pub fn seed(&self, seed: [u32; 4]) -> Word2Vec {
+        Word2Vec { rng: rng::seeded_rng(seed), .. self.clone() }
+    }
+
+    /// Returns the sorted vocabulary, i.e. row `i` of
+    /// [vectors](#method.vectors) is the vector of the `i`th word.
+    pub fn vocabulary(&self) -> &[String] {
+        &self.vocabulary
+    }
+
+    /// Returns the index of `word` in the vocabulary.
+    pub fn index(&self, word: &str) -> Option<usize> {
+        self.index.get(word).cloned()
+    }
+
+    /// Returns the vectors of the words in the rows.
+    pub fn vectors(&self) -> &Matrix<f64> {
+        &self.vectors
+    }
+
+    /// Returns the vector of `word` or `None` if the word is not in the
+    /// vocabulary.
+    pub fn vector(&self, word: &str) -> Option<&[f64]> {
+        self.index(word).and_then(|i| self.vectors.row(i))
+    }
+
+    /// Trains the vectors on the sentences of `corpus`, each given as a
+    /// sequence of tokens. Words do not predict words of other sentences.
+    ///
+    /// Panics if no word occurs at least `min_count` times.
+    pub fn fit<S: AsRef<str>>(&mut self, corpus: &[Vec<S>]) {
+
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for t in corpus.iter().flat_map(|s| s.iter()) {
+            *counts.entry(t.as_ref()).or_insert(0) += 1;
+        }
+        let vocab: Vec<(&str, usize)> = counts.into_iter().filter(|&(_, c)| c >= self.min_count).collect();
+        assert!(!vocab.is_empty(), "Vocabulary is empty.");
+
+        self.vocabulary = vocab.iter().map(|&(w, _)| w.to_string()).collect();
+        self.index = self.vocabulary.iter().enumerate().map(|(i, w)| (w.clone(), i)).collect();
+        let sentences: Vec<Vec<usize>> = corpus.iter()
+            .map(|s| s.iter().filter_map(|t| self.index(t.as_ref())).collect())
+            .collect();
+
+        // cumulative noise distribution
+        let mut noise: Vec<f64> = vocab.iter().map(|&(_, c)| (c as f64).powf(0.75)).collect();
+        let total: f64 = noise.iter().sum();
+        let mut acc = 0.0;
+        for p in noise.iter_mut() {
+            acc += *p / total;
+            *p = acc;
+        }
+
+        let (v, d) = (self.vocabulary.len(), self.dim);
+        let rng = &mut self.rng;
+        let mut input: Vec<f64> = (0..v * d).map(|_| (rng.gen::<f64>() - 0.5) / d as f64).collect();
+        let mut output = vec![0.0; v * d];
+        let mut grad = vec![0.0; d];
+
+        let n_words: usize = sentences.iter().map(|s| s.len()).sum();
+        let steps = (self.epochs * n_words).max(1) as f64;
+        let mut step = 0;
+        for _ in 0..self.epochs {
+            for s in &sentences {
+                for (i, &w) in s.iter().enumerate() {
+                    let eta = self.learning_rate * (1.0 - step as f64 / steps).max(1e-4);
+                    step += 1;
+                    let b = rng.gen_range(1, self.window + 1);
+                    let lo = i.saturating_sub(b);
+                    let hi = (i + b + 1).min(s.len());
+                    for j in lo..hi {
+                        if j == i {
+                            continue;
+                        }
+                        for g in grad.iter_mut() {
+                            *g = 0.0;
+                        }
+                        let vw = &mut input[w * d..(w + 1) * d];
+                        for n in 0..self.negative + 1 {
+                            let (target, label) = if n == 0 {
+                                (s[j], 1.0)
+                            } else {
+                                let u = rng.gen::<f64>();
+                                let t = match noise.binary_search_by(|p| p.partial_cmp(&u).unwrap()) {
+                                    Ok(t) => t + 1,
+                                    Err(t) => t
+                                }.min(v - 1);
+                                if t == s[j] {
+                                    continue;
+                                }
+                                (t, 0.0)
+                            };
+                            let vo = &mut output[target * d..(target + 1) * d];
+                            let dot = vw.iter().zip(vo.iter()).fold(0.0, |acc, (a, b)| acc + a * b);
+                            let e = eta * (label - sigmoid(dot));
+                            for k in 0..d {
+                                grad[k] += e * vo[k];
+                                vo[k] += e * vw[k];
+                            }
+                        }
+                        for k in 0..d {
+                            vw[k] += grad[k];
+                        }
+                    }
+                }
+            }
+        }
+        self.vectors = Matrix::from_vec(input, v, d);
+    }
+
+    /// Returns the `k` words whose vectors are most similar to `v` with
+    /// their cosine similarity in decreasing order of the similarity.
+    ///
+    /// Panics if the model is not trained or if the length of `v` does not
+    /// match the number of dimensions.
+    pub fn nearest(&self, v: &[f64], k: usize) -> Vec<(&str, f64)> {
+
+        assert!(self.vectors.rows() > 0, "Model is not trained.");
+        assert!(v.len() == self.dim, "Length of the vector does not match the number of dimensions.");
+        let q = Matrix::from_vec(v.to_vec(), 1, v.len());
+        kneighbors(&self.vectors, &q, k, Metric::Cosine)[0].iter()
+            .map(|&i| (&self.vocabulary[i][..], 1.0 - Metric::Cosine.distance(v, self.vectors.row(i).unwrap())))
+            .collect()
+    }
+
+    /// Returns the `k` words which are most similar to `word` (excluding
+    /// the word itself) with their cosine similarity or `None` if the word
+    /// is not in the vocabulary.
+    pub fn most_similar(&self, word: &str, k: usize) -> Option<Vec<(&str, f64)>> {
+
+        self.vector(word).map(|v| {
+            self.nearest(v, k + 1).into_iter().filter(|&(w, _)| w != word).take(k).collect()
+        })
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word2vec() {
+
+        // two groups of words which never occur in the same sentence
+        let a = ["apple", "banana", "cherry", "grape"];
+        let b = ["car", "bus", "train", "plane"];
+        let mut rng = rng::seeded_rng([5, 6, 7, 8]);
+        let mut corpus = vec![];
+        for i in 0..400 {
+            let g = if i % 2 == 0 { &a } else { &b };
+            corpus.push((0..6).map(|_| g[rng.gen_range(0, 4)]).collect::<Vec<&str>>());
+        }
+        corpus.push(vec!["rare"]);
+
+        let mut w = Word2Vec::new(8).window(2).epochs(3).min_count(2).seed([1, 2, 3, 4]);
+        w.fit(&corpus);
+        assert_eq!(w.vocabulary().len(), 8);
+        assert!(w.index("rare").is_none());
+        assert!(w.most_similar("rare", 3).is_none());
+
+        for g in &[a, b] {
+            for word in g.iter() {
+                let s = w.most_similar(word, 3).unwrap();
+                assert!(s.iter().all(|&(x, _)| g.contains(&x)), "{} {:?}", word, s);
+                assert!(s[0].1 >= s[2].1);
+            }
+        }
+
+        // a word is its own nearest neighbour
+        let v = w.vector("car").unwrap().to_vec();
+        let n = w.nearest(&v, 1);
+        assert_eq!(n[0].0, "car");
+        assert!((n[0].1 - 1.0).abs() < 1e-10);
+    }
+}